@@ -0,0 +1,48 @@
+use criterion::{criterion_group, criterion_main, BenchmarkId, Criterion};
+
+use ray_tracer_challenge::{fixtures, world::World};
+
+const HSIZE: usize = 64;
+const VSIZE: usize = 48;
+
+fn scenes() -> Vec<(&'static str, World)> {
+    vec![
+        ("spheres", fixtures::spheres()),
+        ("glass", fixtures::glass()),
+        ("triangle_mesh", fixtures::triangle_mesh()),
+        ("grouped", fixtures::grouped()),
+    ]
+}
+
+/// Finding every camera ray's intersections, without shading them.
+fn intersect(c: &mut Criterion) {
+    let camera = fixtures::camera(HSIZE, VSIZE);
+    let mut group = c.benchmark_group("intersect");
+    for (name, world) in scenes() {
+        group.bench_with_input(BenchmarkId::from_parameter(name), &world, |b, world| {
+            b.iter(|| {
+                (0..VSIZE)
+                    .flat_map(|y| (0..HSIZE).map(move |x| (x, y)))
+                    .map(|(x, y)| world.intersect(camera.ray_for_pixel(x, y)).len())
+                    .sum::<usize>()
+            })
+        });
+    }
+    group.finish();
+}
+
+/// A whole render, shading included.
+fn render(c: &mut Criterion) {
+    let camera = fixtures::camera(HSIZE, VSIZE);
+    let mut group = c.benchmark_group("render");
+    group.sample_size(20);
+    for (name, world) in scenes() {
+        group.bench_with_input(BenchmarkId::from_parameter(name), &world, |b, world| {
+            b.iter(|| camera.render(world))
+        });
+    }
+    group.finish();
+}
+
+criterion_group!(benches, intersect, render);
+criterion_main!(benches);
@@ -0,0 +1,98 @@
+use std::fmt::{self, Debug};
+
+use uuid::Uuid;
+
+use crate::transformations::Transform;
+
+/// Moves one shape over time. `World::at_time` poses the shape with the
+/// transform the animation gives for that moment, so a sequence of frames
+/// can be rendered without rebuilding the scene for each one.
+pub struct Animation {
+    pub target: Uuid,
+    transform_at: Box<dyn Fn(f32) -> Transform>,
+}
+
+impl Animation {
+    pub fn new(target: Uuid, transform_at: impl Fn(f32) -> Transform + 'static) -> Self {
+        Self {
+            target,
+            transform_at: Box::new(transform_at),
+        }
+    }
+
+    pub fn transform_at(&self, t: f32) -> Transform {
+        (self.transform_at)(t)
+    }
+}
+
+impl Debug for Animation {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Animation")
+            .field("target", &self.target)
+            .finish()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::f32::consts::PI;
+
+    use crate::{
+        color,
+        lights::PointLight,
+        ray::Ray,
+        shapes::{group::Group, sphere::Sphere, Shape, ShapeBuilder},
+        tuple::{Point, Vector},
+        world::World,
+    };
+
+    use super::*;
+
+    #[test]
+    fn an_animation_gives_a_transform_for_each_moment() {
+        let a = Animation::new(Uuid::new_v4(), |t| {
+            Transform::translation(t * 2.0, 0.0, 0.0)
+        });
+
+        assert_eq!(a.transform_at(0.0), Transform::translation(0.0, 0.0, 0.0));
+        assert_eq!(a.transform_at(1.5), Transform::translation(3.0, 0.0, 0.0));
+    }
+
+    #[test]
+    fn posing_a_world_moves_its_animated_objects() {
+        let s = Sphere::default();
+        let id = s.id();
+        let w = World::new(PointLight::new(Point::new(0.0, 0.0, -10.0), color::WHITE))
+            .object(Box::new(s))
+            .animation(Animation::new(id, |t| Transform::translation(0.0, t, 0.0)));
+        let r = Ray::new(Point::new(0.0, 3.0, -5.0), Vector::new(0.0, 0.0, 1.0));
+
+        let w = w.at_time(0.0);
+        assert!(w.intersect(r).is_empty());
+
+        let w = w.at_time(3.0);
+        assert_eq!(w.intersect(r).len(), 2);
+
+        let w = w.at_time(0.0);
+        assert!(w.intersect(r).is_empty());
+    }
+
+    #[test]
+    fn posing_a_world_moves_objects_inside_groups() {
+        let s = Sphere::default();
+        let id = s.id();
+        let mut g = Group::new().with_transform(Transform::translation(0.0, 0.0, 10.0));
+        g.add_child(Box::new(s));
+        let mut w = World::new(PointLight::default())
+            .object(Box::new(g))
+            .animation(Animation::new(id, |t| Transform::rotation_y(t * PI)));
+        w.build_index();
+
+        let w = w.at_time(0.5);
+
+        assert_eq!(
+            *w.find_object(id).unwrap().transform(),
+            Transform::rotation_y(PI / 2.0)
+        );
+    }
+}
@@ -0,0 +1,315 @@
+use crate::{ray::Ray, transformations::Transform, tuple::Point, EPSILON};
+
+/// An axis-aligned box. A fresh box is empty, with its minimum above its
+/// maximum, so adding the first point makes it that point.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct BoundingBox {
+    pub min: Point,
+    pub max: Point,
+}
+
+impl BoundingBox {
+    pub fn new(min: Point, max: Point) -> Self {
+        Self { min, max }
+    }
+
+    pub fn infinite() -> Self {
+        Self::new(
+            Point::new(f32::NEG_INFINITY, f32::NEG_INFINITY, f32::NEG_INFINITY),
+            Point::new(f32::INFINITY, f32::INFINITY, f32::INFINITY),
+        )
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.min.x() > self.max.x() || self.min.y() > self.max.y() || self.min.z() > self.max.z()
+    }
+
+    pub fn is_finite(&self) -> bool {
+        [self.min, self.max]
+            .iter()
+            .all(|p| p.x().is_finite() && p.y().is_finite() && p.z().is_finite())
+    }
+
+    pub fn add_point(self, point: Point) -> Self {
+        Self::new(self.min.min(point), self.max.max(point))
+    }
+
+    pub fn merge(self, other: BoundingBox) -> Self {
+        if other.is_empty() {
+            self
+        } else {
+            self.add_point(other.min).add_point(other.max)
+        }
+    }
+
+    pub fn contains_point(&self, point: Point) -> bool {
+        (self.min.x()..=self.max.x()).contains(&point.x())
+            && (self.min.y()..=self.max.y()).contains(&point.y())
+            && (self.min.z()..=self.max.z()).contains(&point.z())
+    }
+
+    pub fn contains_box(&self, other: &BoundingBox) -> bool {
+        self.contains_point(other.min) && self.contains_point(other.max)
+    }
+
+    pub fn surface_area(&self) -> f32 {
+        let size = self.max - self.min;
+        2.0 * (size.x() * size.y() + size.y() * size.z() + size.z() * size.x())
+    }
+
+    pub fn center(&self) -> Point {
+        self.min.lerp(self.max, 0.5)
+    }
+
+    pub fn corners(&self) -> [Point; 8] {
+        let (min, max) = (self.min, self.max);
+        [
+            min,
+            Point::new(min.x(), min.y(), max.z()),
+            Point::new(min.x(), max.y(), min.z()),
+            Point::new(min.x(), max.y(), max.z()),
+            Point::new(max.x(), min.y(), min.z()),
+            Point::new(max.x(), min.y(), max.z()),
+            Point::new(max.x(), max.y(), min.z()),
+            max,
+        ]
+    }
+
+    /// The box around this one after `transform`. Infinite boxes stay
+    /// infinite in every direction, since rotating one smears it
+    /// across all three axes anyway.
+    pub fn transform(&self, transform: Transform) -> Self {
+        if self.is_empty() {
+            *self
+        } else if !self.is_finite() {
+            Self::infinite()
+        } else {
+            self.corners()
+                .iter()
+                .fold(Self::default(), |bounds, &corner| {
+                    bounds.add_point(transform * corner)
+                })
+        }
+    }
+
+    /// Whether `ray` passes through the box within its span of `t`.
+    pub fn intersects(&self, ray: Ray) -> bool {
+        self.ray_range(ray)
+            .is_some_and(|(t0, t1)| t0 <= ray.t_max && t1 >= ray.t_min)
+    }
+
+    /// The span of `t` over which `ray` is inside the box, if it ever is
+    /// from its origin on. The span may start behind the origin.
+    pub fn ray_range(&self, ray: Ray) -> Option<(f32, f32)> {
+        let (tmin, tmax) = (0..3)
+            .map(|axis| {
+                check_axis(
+                    ray.origin[axis],
+                    ray.direction[axis],
+                    self.min[axis],
+                    self.max[axis],
+                )
+            })
+            .fold(
+                (f32::NEG_INFINITY, f32::INFINITY),
+                |(tmin, tmax), (t0, t1)| (tmin.max(t0), tmax.min(t1)),
+            );
+
+        if tmin <= tmax && tmax >= 0.0 {
+            Some((tmin, tmax))
+        } else {
+            None
+        }
+    }
+}
+
+impl Default for BoundingBox {
+    fn default() -> Self {
+        Self::new(
+            Point::new(f32::INFINITY, f32::INFINITY, f32::INFINITY),
+            Point::new(f32::NEG_INFINITY, f32::NEG_INFINITY, f32::NEG_INFINITY),
+        )
+    }
+}
+
+fn check_axis(origin: f32, direction: f32, min: f32, max: f32) -> (f32, f32) {
+    if direction.abs() < EPSILON {
+        if (min..=max).contains(&origin) {
+            (f32::NEG_INFINITY, f32::INFINITY)
+        } else {
+            (f32::INFINITY, f32::NEG_INFINITY)
+        }
+    } else {
+        let t1 = (min - origin) / direction;
+        let t2 = (max - origin) / direction;
+        (t1.min(t2), t1.max(t2))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::f32::consts::{PI, SQRT_2};
+
+    use crate::tuple::Vector;
+
+    use super::*;
+
+    #[test]
+    fn creating_an_empty_bounding_box() {
+        let b = BoundingBox::default();
+
+        assert!(b.is_empty());
+        assert_eq!(b.min.x(), f32::INFINITY);
+        assert_eq!(b.max.x(), f32::NEG_INFINITY);
+    }
+
+    #[test]
+    fn adding_points_to_an_empty_bounding_box() {
+        let b = BoundingBox::default()
+            .add_point(Point::new(-5.0, 2.0, 0.0))
+            .add_point(Point::new(7.0, 0.0, -3.0));
+
+        assert_eq!(b.min, Point::new(-5.0, 0.0, -3.0));
+        assert_eq!(b.max, Point::new(7.0, 2.0, 0.0));
+    }
+
+    #[test]
+    fn adding_one_bounding_box_to_another() {
+        let b1 = BoundingBox::new(Point::new(-5.0, -2.0, 0.0), Point::new(7.0, 4.0, 4.0));
+        let b2 = BoundingBox::new(Point::new(8.0, -7.0, -2.0), Point::new(14.0, 2.0, 8.0));
+
+        let b = b1.merge(b2);
+
+        assert_eq!(b.min, Point::new(-5.0, -7.0, -2.0));
+        assert_eq!(b.max, Point::new(14.0, 4.0, 8.0));
+        assert_eq!(b1.merge(BoundingBox::default()), b1);
+    }
+
+    macro_rules! checking_to_see_if_a_box_contains_a_given_point {
+        ($($name:ident: $value:expr,)*) => {
+        $(
+            #[test]
+            fn $name() {
+                let (point, result) = $value;
+                let b = BoundingBox::new(Point::new(5.0, -2.0, 0.0), Point::new(11.0, 4.0, 7.0));
+
+                assert_eq!(b.contains_point(point), result);
+            }
+        )*
+        }
+    }
+
+    checking_to_see_if_a_box_contains_a_given_point! {
+        checking_to_see_if_a_box_contains_a_given_point_1: (Point::new(5.0, -2.0, 0.0), true),
+        checking_to_see_if_a_box_contains_a_given_point_2: (Point::new(11.0, 4.0, 7.0), true),
+        checking_to_see_if_a_box_contains_a_given_point_3: (Point::new(8.0, 1.0, 3.0), true),
+        checking_to_see_if_a_box_contains_a_given_point_4: (Point::new(3.0, 0.0, 3.0), false),
+        checking_to_see_if_a_box_contains_a_given_point_5: (Point::new(8.0, -4.0, 3.0), false),
+        checking_to_see_if_a_box_contains_a_given_point_6: (Point::new(8.0, 1.0, -1.0), false),
+        checking_to_see_if_a_box_contains_a_given_point_7: (Point::new(13.0, 1.0, 3.0), false),
+        checking_to_see_if_a_box_contains_a_given_point_8: (Point::new(8.0, 5.0, 3.0), false),
+        checking_to_see_if_a_box_contains_a_given_point_9: (Point::new(8.0, 1.0, 8.0), false),
+    }
+
+    macro_rules! checking_to_see_if_a_box_contains_a_given_box {
+        ($($name:ident: $value:expr,)*) => {
+        $(
+            #[test]
+            fn $name() {
+                let (min, max, result) = $value;
+                let b = BoundingBox::new(Point::new(5.0, -2.0, 0.0), Point::new(11.0, 4.0, 7.0));
+
+                assert_eq!(b.contains_box(&BoundingBox::new(min, max)), result);
+            }
+        )*
+        }
+    }
+
+    checking_to_see_if_a_box_contains_a_given_box! {
+        checking_to_see_if_a_box_contains_a_given_box_1: (Point::new(5.0, -2.0, 0.0), Point::new(11.0, 4.0, 7.0), true),
+        checking_to_see_if_a_box_contains_a_given_box_2: (Point::new(6.0, -1.0, 1.0), Point::new(10.0, 3.0, 6.0), true),
+        checking_to_see_if_a_box_contains_a_given_box_3: (Point::new(4.0, -3.0, -1.0), Point::new(10.0, 3.0, 6.0), false),
+        checking_to_see_if_a_box_contains_a_given_box_4: (Point::new(6.0, -1.0, 1.0), Point::new(12.0, 5.0, 8.0), false),
+    }
+
+    #[test]
+    fn transforming_a_bounding_box() {
+        let b = BoundingBox::new(Point::new(-1.0, -1.0, -1.0), Point::new(1.0, 1.0, 1.0));
+        let matrix = Transform::rotation_x(PI / 4.0) * Transform::rotation_y(PI / 4.0);
+
+        let b2 = b.transform(matrix);
+
+        assert_eq!(b2.min, Point::new(-SQRT_2, -1.70710, -1.70710));
+        assert_eq!(b2.max, Point::new(SQRT_2, 1.70710, 1.70710));
+    }
+
+    #[test]
+    fn transforming_an_infinite_bounding_box() {
+        let b = BoundingBox::new(
+            Point::new(f32::NEG_INFINITY, 0.0, f32::NEG_INFINITY),
+            Point::new(f32::INFINITY, 0.0, f32::INFINITY),
+        );
+
+        let b2 = b.transform(Transform::translation(0.0, 3.0, 0.0));
+
+        assert!(!b2.is_finite());
+        assert_eq!(b2.min.y(), f32::NEG_INFINITY);
+        assert_eq!(b2.max.y(), f32::INFINITY);
+    }
+
+    macro_rules! intersecting_a_ray_with_a_bounding_box_at_the_origin {
+        ($($name:ident: $value:expr,)*) => {
+        $(
+            #[test]
+            fn $name() {
+                let (origin, direction, result) = $value;
+                let b = BoundingBox::new(Point::new(-1.0, -1.0, -1.0), Point::new(1.0, 1.0, 1.0));
+                let r = Ray::new(origin, Vector::normalize(direction));
+
+                assert_eq!(b.intersects(r), result);
+            }
+        )*
+        }
+    }
+
+    intersecting_a_ray_with_a_bounding_box_at_the_origin! {
+        intersecting_a_ray_with_a_bounding_box_at_the_origin_1: (Point::new(5.0, 0.5, 0.0), Vector::new(-1.0, 0.0, 0.0), true),
+        intersecting_a_ray_with_a_bounding_box_at_the_origin_2: (Point::new(-5.0, 0.5, 0.0), Vector::new(1.0, 0.0, 0.0), true),
+        intersecting_a_ray_with_a_bounding_box_at_the_origin_3: (Point::new(0.5, 5.0, 0.0), Vector::new(0.0, -1.0, 0.0), true),
+        intersecting_a_ray_with_a_bounding_box_at_the_origin_4: (Point::new(0.5, -5.0, 0.0), Vector::new(0.0, 1.0, 0.0), true),
+        intersecting_a_ray_with_a_bounding_box_at_the_origin_5: (Point::new(0.5, 0.0, 5.0), Vector::new(0.0, 0.0, -1.0), true),
+        intersecting_a_ray_with_a_bounding_box_at_the_origin_6: (Point::new(0.5, 0.0, -5.0), Vector::new(0.0, 0.0, 1.0), true),
+        intersecting_a_ray_with_a_bounding_box_at_the_origin_7: (Point::new(0.0, 0.5, 0.0), Vector::new(0.0, 0.0, 1.0), true),
+        intersecting_a_ray_with_a_bounding_box_at_the_origin_8: (Point::new(-2.0, 0.0, 0.0), Vector::new(2.0, 4.0, 6.0), false),
+        intersecting_a_ray_with_a_bounding_box_at_the_origin_9: (Point::new(0.0, -2.0, 0.0), Vector::new(6.0, 2.0, 4.0), false),
+        intersecting_a_ray_with_a_bounding_box_at_the_origin_10: (Point::new(0.0, 0.0, -2.0), Vector::new(4.0, 6.0, 2.0), false),
+        intersecting_a_ray_with_a_bounding_box_at_the_origin_11: (Point::new(2.0, 0.0, 2.0), Vector::new(0.0, 0.0, -1.0), false),
+        intersecting_a_ray_with_a_bounding_box_at_the_origin_12: (Point::new(0.0, 2.0, 2.0), Vector::new(0.0, -1.0, 0.0), false),
+        intersecting_a_ray_with_a_bounding_box_at_the_origin_13: (Point::new(2.0, 2.0, 0.0), Vector::new(-1.0, 0.0, 0.0), false),
+    }
+
+    #[test]
+    fn a_box_behind_the_ray_is_missed() {
+        let b = BoundingBox::new(Point::new(-1.0, -1.0, -1.0), Point::new(1.0, 1.0, 1.0));
+        let r = Ray::new(Point::new(0.0, 0.0, 5.0), Vector::new(0.0, 0.0, 1.0));
+
+        assert!(!b.intersects(r));
+    }
+
+    #[test]
+    fn a_box_outside_the_rays_span_is_missed() {
+        let b = BoundingBox::new(Point::new(-1.0, -1.0, -1.0), Point::new(1.0, 1.0, 1.0));
+        let r = Ray::new(Point::new(0.0, 0.0, -5.0), Vector::new(0.0, 0.0, 1.0));
+
+        assert!(!b.intersects(r.t_max(3.0)));
+        assert!(!b.intersects(r.t_min(7.0)));
+        assert!(b.intersects(r.t_min(5.0).t_max(5.5)));
+    }
+
+    #[test]
+    fn an_infinite_box_is_hit_by_every_ray() {
+        let r = Ray::new(Point::new(3.0, -2.0, 5.0), Vector::new(0.0, 1.0, 0.0));
+
+        assert!(BoundingBox::infinite().intersects(r));
+    }
+}
@@ -0,0 +1,307 @@
+use std::{
+    error::Error,
+    fmt::{self, Display},
+    fs,
+    io::{self, Read, Write},
+    path::PathBuf,
+};
+
+use crate::{canvas::Canvas, color::Color};
+
+const MAGIC: &str = "rtc-bucket";
+
+/// A render split into square buckets, each saved to its own file in `dir`
+/// as soon as it's finished, so an image far too big to hold in memory can
+/// be rendered a piece at a time. Buckets already on disk are skipped,
+/// which makes an interrupted render pick up where it left off. Buckets on
+/// the right and bottom edges are cut down to fit the image.
+#[derive(Clone, Debug, PartialEq)]
+pub struct BucketRender {
+    pub dir: PathBuf,
+    pub width: usize,
+    pub height: usize,
+    pub bucket_size: usize,
+}
+
+impl BucketRender {
+    pub fn new(dir: impl Into<PathBuf>, width: usize, height: usize, bucket_size: usize) -> Self {
+        Self {
+            dir: dir.into(),
+            width,
+            height,
+            bucket_size: bucket_size.max(1),
+        }
+    }
+
+    pub fn columns(&self) -> usize {
+        self.width.div_ceil(self.bucket_size)
+    }
+
+    pub fn rows(&self) -> usize {
+        self.height.div_ceil(self.bucket_size)
+    }
+
+    /// Every bucket as `(column, row)`, in reading order.
+    pub fn buckets(&self) -> impl Iterator<Item = (usize, usize)> {
+        let columns = self.columns();
+        (0..self.rows()).flat_map(move |row| (0..columns).map(move |column| (column, row)))
+    }
+
+    /// The buckets not yet saved.
+    pub fn remaining(&self) -> Vec<(usize, usize)> {
+        self.buckets()
+            .filter(|&(column, row)| !self.path(column, row).exists())
+            .collect()
+    }
+
+    /// The pixels a bucket covers, from `x0`, `y0` up to but not including
+    /// `x1`, `y1`.
+    pub fn bounds(&self, column: usize, row: usize) -> (usize, usize, usize, usize) {
+        let (x0, y0) = (column * self.bucket_size, row * self.bucket_size);
+        (
+            x0,
+            y0,
+            (x0 + self.bucket_size).min(self.width),
+            (y0 + self.bucket_size).min(self.height),
+        )
+    }
+
+    pub fn path(&self, column: usize, row: usize) -> PathBuf {
+        self.dir.join(format!("bucket-{}-{}.bin", column, row))
+    }
+
+    /// Saves a finished bucket, unexposed, at full precision. It's written
+    /// under a temporary name and then moved into place, so a render killed
+    /// mid-write never leaves a half bucket that looks done.
+    pub fn save(&self, column: usize, row: usize, image: &Canvas) -> io::Result<()> {
+        fs::create_dir_all(&self.dir)?;
+        let path = self.path(column, row);
+        let partial = path.with_extension("tmp");
+
+        let mut bytes = format!("{}\n{} {}\n", MAGIC, image.width, image.height).into_bytes();
+        for y in 0..image.height {
+            for x in 0..image.width {
+                let pixel = image.pixel_at(x, y);
+                for channel in [pixel.red(), pixel.green(), pixel.blue()].iter() {
+                    bytes.extend_from_slice(&channel.to_le_bytes());
+                }
+            }
+        }
+        fs::File::create(&partial)?.write_all(&bytes)?;
+        fs::rename(partial, path)
+    }
+
+    pub fn load(&self, column: usize, row: usize) -> Result<Canvas, BucketError> {
+        let mut bytes = vec![];
+        fs::File::open(self.path(column, row))?.read_to_end(&mut bytes)?;
+
+        let header_end = bytes
+            .iter()
+            .enumerate()
+            .filter(|&(_, &b)| b == b'\n')
+            .nth(1)
+            .map(|(i, _)| i + 1)
+            .ok_or(BucketError::NotABucket)?;
+        let header = String::from_utf8_lossy(&bytes[..header_end]);
+        let mut tokens = header.split_whitespace();
+        if tokens.next() != Some(MAGIC) {
+            return Err(BucketError::NotABucket);
+        }
+        let mut size = || -> Option<usize> { tokens.next()?.parse().ok() };
+        let found = (
+            size().ok_or(BucketError::NotABucket)?,
+            size().ok_or(BucketError::NotABucket)?,
+        );
+        let (x0, y0, x1, y1) = self.bounds(column, row);
+        let expected = (x1 - x0, y1 - y0);
+        if found != expected {
+            return Err(BucketError::WrongSize { expected, found });
+        }
+
+        let mut channels = bytes[header_end..]
+            .chunks_exact(4)
+            .map(|b| f32::from_le_bytes([b[0], b[1], b[2], b[3]]));
+        let mut image = Canvas::new(found.0, found.1);
+        for y in 0..image.height {
+            for x in 0..image.width {
+                let mut channel = || channels.next().ok_or(BucketError::UnexpectedEnd);
+                image.write_pixel(x, y, Color::new(channel()?, channel()?, channel()?));
+            }
+        }
+        Ok(image)
+    }
+
+    /// The average luminance of the whole image, read a bucket at a time.
+    pub fn average_luminance(&self) -> Result<f32, BucketError> {
+        let mut total = 0.0;
+        for (column, row) in self.buckets() {
+            let bucket = self.load(column, row)?;
+            total += bucket.average_luminance() * (bucket.width * bucket.height) as f32;
+        }
+        Ok(total / (self.width * self.height).max(1) as f32)
+    }
+
+    /// Writes the finished image as a PPM with every pixel multiplied by
+    /// `multiplier` and encoded as sRGB, like `Canvas::export_srgb`, holding
+    /// only one row of buckets in memory at a time.
+    pub fn write_ppm(&self, multiplier: f32, mut writer: impl Write) -> Result<(), BucketError> {
+        write!(writer, "P3\n{} {}\n255\n", self.width, self.height)?;
+        for row in 0..self.rows() {
+            let buckets = (0..self.columns())
+                .map(|column| self.load(column, row))
+                .collect::<Result<Vec<_>, _>>()?;
+            for y in 0..buckets.first().map_or(0, |b| b.height) {
+                let pixels = buckets
+                    .iter()
+                    .flat_map(|b| {
+                        (0..b.width).map(move |x| (b.pixel_at(x, y) * multiplier).to_srgb())
+                    })
+                    .collect::<Vec<_>>();
+                writeln!(writer, "{}", Canvas::ppm_row(&pixels))?;
+            }
+        }
+        Ok(())
+    }
+
+    /// Deletes the saved buckets, and `dir` too if that leaves it empty,
+    /// once the image has been assembled.
+    pub fn clean_up(&self) -> io::Result<()> {
+        for (column, row) in self.buckets() {
+            match fs::remove_file(self.path(column, row)) {
+                Err(e) if e.kind() != io::ErrorKind::NotFound => return Err(e),
+                _ => (),
+            }
+        }
+        let _ = fs::remove_dir(&self.dir);
+        Ok(())
+    }
+}
+
+#[derive(Debug)]
+pub enum BucketError {
+    Io(io::Error),
+    NotABucket,
+    WrongSize {
+        expected: (usize, usize),
+        found: (usize, usize),
+    },
+    UnexpectedEnd,
+    /// The buckets are for an image of a different size from the camera's.
+    WrongImageSize {
+        expected: (usize, usize),
+        found: (usize, usize),
+    },
+}
+
+impl Display for BucketError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            BucketError::Io(e) => write!(f, "couldn't read or write a bucket: {}", e),
+            BucketError::NotABucket => write!(f, "not a saved bucket"),
+            BucketError::WrongSize { expected, found } => write!(
+                f,
+                "expected a {}x{} bucket, found {}x{}",
+                expected.0, expected.1, found.0, found.1
+            ),
+            BucketError::UnexpectedEnd => write!(f, "the saved bucket is cut short"),
+            BucketError::WrongImageSize { expected, found } => write!(
+                f,
+                "expected buckets for a {}x{} image, found a {}x{} one",
+                expected.0, expected.1, found.0, found.1
+            ),
+        }
+    }
+}
+
+impl Error for BucketError {}
+
+impl From<io::Error> for BucketError {
+    fn from(e: io::Error) -> Self {
+        BucketError::Io(e)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use uuid::Uuid;
+
+    use super::*;
+
+    fn scratch(width: usize, height: usize, bucket_size: usize) -> BucketRender {
+        let dir = std::env::temp_dir().join(format!("buckets-{}", Uuid::new_v4()));
+        BucketRender::new(dir, width, height, bucket_size)
+    }
+
+    #[test]
+    fn buckets_are_cut_down_at_the_edges() {
+        let buckets = scratch(10, 5, 4);
+
+        assert_eq!((buckets.columns(), buckets.rows()), (3, 2));
+        assert_eq!(buckets.buckets().count(), 6);
+        assert_eq!(buckets.bounds(1, 0), (4, 0, 8, 4));
+        assert_eq!(buckets.bounds(2, 1), (8, 4, 10, 5));
+    }
+
+    #[test]
+    fn a_saved_bucket_keeps_full_precision() {
+        let buckets = scratch(10, 5, 4);
+        let mut image = Canvas::new(2, 1);
+        image.write_pixel(0, 0, Color::new(1.2345678, -0.5, 100.0));
+
+        buckets.save(2, 1, &image).unwrap();
+
+        assert_eq!(buckets.load(2, 1).unwrap(), image);
+        assert_eq!(buckets.remaining().len(), 5);
+        assert!(!buckets.remaining().contains(&(2, 1)));
+        buckets.clean_up().unwrap();
+        assert_eq!(buckets.remaining().len(), 6);
+    }
+
+    #[test]
+    fn a_bucket_of_the_wrong_size() {
+        let buckets = scratch(10, 5, 4);
+        buckets.save(0, 0, &Canvas::new(2, 2)).unwrap();
+
+        let result = buckets.load(0, 0);
+
+        assert!(matches!(
+            result,
+            Err(BucketError::WrongSize {
+                expected: (4, 4),
+                found: (2, 2)
+            })
+        ));
+        buckets.clean_up().unwrap();
+    }
+
+    #[test]
+    fn assembling_the_buckets_into_a_ppm() {
+        let buckets = scratch(5, 3, 2);
+        let mut whole = Canvas::new(5, 3);
+        for y in 0..3 {
+            for x in 0..5 {
+                whole.write_pixel(x, y, Color::new(x as f32 / 10.0, y as f32 / 10.0, 0.5));
+            }
+        }
+        for (column, row) in buckets.buckets() {
+            let (x0, y0, x1, y1) = buckets.bounds(column, row);
+            let mut bucket = Canvas::new(x1 - x0, y1 - y0);
+            for y in y0..y1 {
+                for x in x0..x1 {
+                    bucket.write_pixel(x - x0, y - y0, whole.pixel_at(x, y));
+                }
+            }
+            buckets.save(column, row, &bucket).unwrap();
+        }
+        let mut ppm = vec![];
+
+        buckets.write_ppm(2.0, &mut ppm).unwrap();
+
+        whole.scale(2.0);
+        assert_eq!(String::from_utf8(ppm).unwrap(), whole.export_srgb());
+        assert!(
+            (buckets.average_luminance().unwrap() - whole.average_luminance() / 2.0).abs() < 1e-5
+        );
+        buckets.clean_up().unwrap();
+    }
+}
@@ -0,0 +1,217 @@
+use std::cmp::Ordering;
+
+use crate::{bounds::BoundingBox, ray::Ray, stats};
+
+const LEAF_SIZE: usize = 2;
+
+/// A bounding volume hierarchy over a list of boxes, answering which of
+/// them a ray might hit. Items with infinite extent can't be split
+/// usefully, so they are kept aside and returned for every ray.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Bvh {
+    bounds: Vec<BoundingBox>,
+    nodes: Vec<Node>,
+    unbounded: Vec<usize>,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+enum Node {
+    Leaf {
+        bounds: BoundingBox,
+        items: Vec<usize>,
+    },
+    Branch {
+        bounds: BoundingBox,
+        left: usize,
+        right: usize,
+    },
+}
+
+impl Node {
+    fn bounds(&self) -> &BoundingBox {
+        match self {
+            Node::Leaf { bounds, .. } | Node::Branch { bounds, .. } => bounds,
+        }
+    }
+}
+
+impl Bvh {
+    /// Builds over `bounds`, identifying each item by its index.
+    pub fn build(bounds: &[BoundingBox]) -> Self {
+        let (mut bounded, unbounded): (Vec<usize>, Vec<usize>) =
+            (0..bounds.len()).partition(|&i| bounds[i].is_finite());
+        bounded.retain(|&i| !bounds[i].is_empty());
+
+        let mut bvh = Self {
+            bounds: bounds.to_vec(),
+            nodes: vec![],
+            unbounded,
+        };
+        if !bounded.is_empty() {
+            bvh.build_node(bounded);
+        }
+        bvh
+    }
+
+    /// Indices of every item whose box the ray passes through, in no
+    /// particular order.
+    pub fn candidates(&self, ray: Ray) -> Vec<usize> {
+        let mut found = self.unbounded.clone();
+        let mut visited = 0;
+        if !self.nodes.is_empty() {
+            self.gather(0, ray, &mut found, &mut visited);
+        }
+        stats::record_index_traversal(visited, (found.len() - self.unbounded.len()) as u64);
+        found
+    }
+
+    fn build_node(&mut self, mut items: Vec<usize>) -> usize {
+        let bounds = &self.bounds;
+        let node_bounds = items
+            .iter()
+            .fold(BoundingBox::default(), |b, &i| b.merge(bounds[i]));
+        let index = self.nodes.len();
+
+        if items.len() <= LEAF_SIZE {
+            self.nodes.push(Node::Leaf {
+                bounds: node_bounds,
+                items,
+            });
+            return index;
+        }
+
+        let centers = items.iter().fold(BoundingBox::default(), |b, &i| {
+            b.add_point(bounds[i].center())
+        });
+        let axis = longest_axis(&centers);
+        items.sort_by(|&a, &b| {
+            bounds[a].center()[axis]
+                .partial_cmp(&bounds[b].center()[axis])
+                .unwrap_or(Ordering::Equal)
+        });
+        let right_items = items.split_off(items.len() / 2);
+
+        // Reserve this node's slot before its children claim theirs.
+        self.nodes.push(Node::Leaf {
+            bounds: node_bounds,
+            items: vec![],
+        });
+        let left = self.build_node(items);
+        let right = self.build_node(right_items);
+        self.nodes[index] = Node::Branch {
+            bounds: node_bounds,
+            left,
+            right,
+        };
+        index
+    }
+
+    fn gather(&self, index: usize, ray: Ray, found: &mut Vec<usize>, visited: &mut u64) {
+        *visited += 1;
+        let node = &self.nodes[index];
+        if !node.bounds().intersects(ray) {
+            return;
+        }
+
+        match node {
+            Node::Leaf { items, .. } => {
+                found.extend(items.iter().filter(|&&i| self.bounds[i].intersects(ray)))
+            }
+            Node::Branch { left, right, .. } => {
+                self.gather(*left, ray, found, visited);
+                self.gather(*right, ray, found, visited);
+            }
+        }
+    }
+}
+
+fn longest_axis(bounds: &BoundingBox) -> usize {
+    let extent = bounds.max - bounds.min;
+    if extent.x() >= extent.y() && extent.x() >= extent.z() {
+        0
+    } else if extent.y() >= extent.z() {
+        1
+    } else {
+        2
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::tuple::{Point, Vector};
+
+    use super::*;
+
+    fn unit_box_at(x: f32, y: f32, z: f32) -> BoundingBox {
+        BoundingBox::new(
+            Point::new(x - 0.5, y - 0.5, z - 0.5),
+            Point::new(x + 0.5, y + 0.5, z + 0.5),
+        )
+    }
+
+    fn row_of_boxes() -> Vec<BoundingBox> {
+        (0..10)
+            .map(|i| unit_box_at(i as f32 * 2.0, 0.0, 0.0))
+            .collect()
+    }
+
+    #[test]
+    fn an_empty_hierarchy_has_no_candidates() {
+        let bvh = Bvh::build(&[]);
+        let r = Ray::new(Point::new(0.0, 0.0, -5.0), Vector::new(0.0, 0.0, 1.0));
+
+        assert!(bvh.candidates(r).is_empty());
+    }
+
+    #[test]
+    fn a_ray_finds_only_the_box_it_passes_through() {
+        let bvh = Bvh::build(&row_of_boxes());
+        let r = Ray::new(Point::new(6.0, 0.0, -5.0), Vector::new(0.0, 0.0, 1.0));
+
+        assert_eq!(bvh.candidates(r), vec![3]);
+    }
+
+    #[test]
+    fn a_ray_along_the_row_finds_every_box() {
+        let bvh = Bvh::build(&row_of_boxes());
+        let r = Ray::new(Point::new(-5.0, 0.0, 0.0), Vector::new(1.0, 0.0, 0.0));
+
+        let mut found = bvh.candidates(r);
+        found.sort_unstable();
+
+        assert_eq!(found, (0..10).collect::<Vec<_>>());
+    }
+
+    #[test]
+    fn unbounded_items_are_always_candidates() {
+        let mut boxes = row_of_boxes();
+        boxes.push(BoundingBox::infinite());
+        let bvh = Bvh::build(&boxes);
+        let r = Ray::new(Point::new(0.0, 5.0, 0.0), Vector::new(0.0, 1.0, 0.0));
+
+        assert_eq!(bvh.candidates(r), vec![10]);
+    }
+
+    #[test]
+    fn the_hierarchy_agrees_with_testing_every_box() {
+        let boxes = (0..50)
+            .map(|i| {
+                let i = i as f32;
+                unit_box_at((i * 1.7) % 9.0, (i * 2.3) % 7.0, (i * 0.9) % 5.0)
+            })
+            .collect::<Vec<_>>();
+        let bvh = Bvh::build(&boxes);
+        let r = Ray::new(
+            Point::new(-2.0, -1.0, -3.0),
+            Vector::new(1.0, 0.7, 0.5).normalize(),
+        );
+
+        let mut found = bvh.candidates(r);
+        found.sort_unstable();
+        let expected = (0..boxes.len())
+            .filter(|&i| boxes[i].intersects(r))
+            .collect::<Vec<_>>();
+
+        assert_eq!(found, expected);
+    }
+}
@@ -1,18 +1,110 @@
+use std::io::Write;
+
+use uuid::Uuid;
+
 use crate::{
+    buckets::{BucketError, BucketRender},
     canvas::Canvas,
-    ray::Ray,
-    transformations::{self, Transform},
-    tuple::Tuple,
+    color::{self, Color},
+    gizmos::{self, Gizmo},
+    integrator::{Integrator, Preview, Whitted},
+    ray::{Ray, RayDifferential, RayKind, ShadowBias},
+    resume::{CancelToken, PartialRender, ResumeError},
+    sampler::{pixel_seed, Sampler, SamplerKind},
+    stats::{self, RenderStats},
+    trace::RayTrace,
+    transformations::{self, AxisConvention, Transform},
+    tuple::{Point, Vector},
     world::World,
 };
 
 const MAX_RECURSIVE_DEPTH: u32 = 3;
+const PREVIEW_SCALE: usize = 4;
+/// How many pixels wide gizmo lines are drawn.
+const GIZMO_WIDTH: f32 = 1.5;
+
+/// How rendered radiance is scaled before the image is clamped for output.
+#[derive(Clone, Copy, Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum Exposure {
+    /// Every pixel is multiplied by this.
+    Manual(f32),
+    /// Every pixel is scaled so the image's average luminance comes out at
+    /// this key value. 0.18, middle grey, is the usual choice.
+    Auto(f32),
+}
+
+impl Exposure {
+    /// The multiplier a physical camera would give, relative to an f/1 lens
+    /// held open for a second at ISO 100. Each halving of the shutter time,
+    /// halving of the ISO, or stop down of the aperture halves it.
+    pub fn from_camera(f_number: f32, shutter_seconds: f32, iso: f32) -> Self {
+        Exposure::Manual(shutter_seconds * (iso / 100.0) / f_number.powi(2))
+    }
 
+    fn multiplier(&self, image: &Canvas) -> f32 {
+        self.multiplier_for(|| image.average_luminance())
+    }
+
+    /// The multiplier for an image, given a way to work out its average
+    /// luminance should auto exposure need it.
+    fn multiplier_for(&self, average_luminance: impl FnOnce() -> f32) -> f32 {
+        match *self {
+            Exposure::Manual(multiplier) => multiplier,
+            Exposure::Auto(key) => {
+                let average = average_luminance();
+                if average > 0.0 {
+                    key / average
+                } else {
+                    1.0
+                }
+            }
+        }
+    }
+}
+
+impl Default for Exposure {
+    fn default() -> Self {
+        Exposure::Manual(1.0)
+    }
+}
+
+/// A render along with auxiliary buffers (AOVs) describing what each
+/// pixel's camera ray hit, for denoising and compositing. Pixels where the
+/// ray escapes have infinite depth, a black normal and albedo, and no id.
+#[derive(Clone, Debug, PartialEq)]
+pub struct RenderOutput {
+    pub color: Canvas,
+    /// Distance from the camera, repeated in all three channels.
+    pub depth: Canvas,
+    /// World space surface normal, facing the camera, with x, y and z in
+    /// the red, green and blue channels.
+    pub normal: Canvas,
+    /// Surface color before any lighting.
+    pub albedo: Canvas,
+    pub object_ids: Vec<Option<Uuid>>,
+}
+
+impl RenderOutput {
+    pub fn object_id(&self, x: usize, y: usize) -> Option<Uuid> {
+        self.object_ids[x + y * self.color.width]
+    }
+}
+
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct Camera {
     hsize: usize,
     vsize: usize,
     field_of_view: f32,
     transform: Transform,
+    axis_convention: AxisConvention,
+    exposure: Exposure,
+    shadow_bias: ShadowBias,
+    filter_patterns: bool,
+    debug_gizmos: bool,
+    gizmos: Vec<Gizmo>,
+    #[cfg_attr(feature = "serde", serde(default))]
+    sampler: SamplerKind,
 }
 
 impl Camera {
@@ -22,6 +114,13 @@ impl Camera {
             vsize,
             field_of_view,
             transform: transformations::IDENTITY,
+            axis_convention: AxisConvention::default(),
+            exposure: Exposure::default(),
+            shadow_bias: ShadowBias::default(),
+            filter_patterns: false,
+            debug_gizmos: false,
+            gizmos: vec![],
+            sampler: SamplerKind::Halton,
         }
     }
 
@@ -29,36 +128,525 @@ impl Camera {
         Self { transform, ..self }
     }
 
+    pub fn axis_convention(self, axis_convention: AxisConvention) -> Self {
+        Self {
+            axis_convention,
+            ..self
+        }
+    }
+
+    pub fn exposure(self, exposure: Exposure) -> Self {
+        Self { exposure, ..self }
+    }
+
+    /// How the rays this camera casts are offset from the surfaces they
+    /// hit, for scenes far from the book's scale.
+    pub fn shadow_bias(self, shadow_bias: ShadowBias) -> Self {
+        Self {
+            shadow_bias,
+            ..self
+        }
+    }
+
+    /// Averages stripes, rings and checkers over the patch each pixel
+    /// covers, so they fade to a blend in the distance rather than breaking
+    /// up into moiré. The rays carry differentials to track the patch,
+    /// including through reflections and refractions.
+    pub fn filter_patterns(self, filter_patterns: bool) -> Self {
+        Self {
+            filter_patterns,
+            ..self
+        }
+    }
+
+    /// Draws the world's light, portals and emissive quads over the image,
+    /// along with any gizmos added with `gizmos`, so a scene can be laid
+    /// out without guessing where they are.
+    pub fn debug_gizmos(self, debug_gizmos: bool) -> Self {
+        Self {
+            debug_gizmos,
+            ..self
+        }
+    }
+
+    /// Which sampler places each pass's sample in a pixel when rendering
+    /// in passes. Halton unless set.
+    pub fn sampler(self, sampler: SamplerKind) -> Self {
+        Self { sampler, ..self }
+    }
+
+    /// More gizmos to draw when `debug_gizmos` is on, such as another
+    /// camera's `frustum`.
+    pub fn gizmos(mut self, gizmos: impl IntoIterator<Item = Gizmo>) -> Self {
+        self.gizmos.extend(gizmos);
+        self
+    }
+
+    /// Lines out from the eye along the edges of the camera's view, `length`
+    /// long, showing what it sees when drawn by another camera.
+    pub fn frustum(&self, length: f32) -> Vec<Gizmo> {
+        let inverse = self.transform.inverse();
+        let eye = inverse * Point::new(0.0, 0.0, 0.0);
+        let (x, y) = (self.half_width(), self.half_height());
+        let corner = |x: f32, y: f32| inverse * Point::new(x, y, -1.0) - eye;
+
+        gizmos::frustum(
+            eye,
+            [corner(x, y), corner(-x, y), corner(-x, -y), corner(x, -y)],
+            length,
+        )
+    }
+
+    /// Points the camera using coordinates in its axis convention.
+    pub fn look_at(self, from: Point, to: Point, up: Vector) -> Self {
+        let transform = self.axis_convention.view_transform(from, to, up);
+        Self { transform, ..self }
+    }
+
+    pub fn hsize(&self) -> usize {
+        self.hsize
+    }
+
+    pub fn vsize(&self) -> usize {
+        self.vsize
+    }
+
     pub fn pixel_size(&self) -> f32 {
         (self.half_width() * 2.0) / self.hsize as f32
     }
 
     pub fn ray_for_pixel(&self, px: usize, py: usize) -> Ray {
-        let xoffset = (px as f32 + 0.5) * self.pixel_size();
-        let yoffset = (py as f32 + 0.5) * self.pixel_size();
+        self.ray_through_pixel(px, py, 0.5, 0.5)
+    }
+
+    /// A ray through the point `dx` across and `dy` down the pixel, each
+    /// in [0, 1), rather than through its center.
+    pub fn ray_through_pixel(&self, px: usize, py: usize, dx: f32, dy: f32) -> Ray {
+        let xoffset = (px as f32 + dx) * self.pixel_size();
+        let yoffset = (py as f32 + dy) * self.pixel_size();
 
         let world_x = self.half_width() - xoffset;
         let world_y = self.half_height() - yoffset;
 
-        let pixel = self.transform.inverse() * Tuple::point(world_x, world_y, -1.0);
-        let origin = self.transform.inverse() * Tuple::point(0.0, 0.0, 0.0);
+        let pixel = self.transform.inverse() * Point::new(world_x, world_y, -1.0);
+        let origin = self.transform.inverse() * Point::new(0.0, 0.0, 0.0);
         let direction = (pixel - origin).normalize();
 
+        let differential = if self.filter_patterns {
+            Some(self.pixel_differential(pixel - origin))
+        } else {
+            None
+        };
         Ray::new(origin, direction)
+            .bias(self.shadow_bias)
+            .differential(differential)
+    }
+
+    /// How the direction `to_pixel` from the eye turns with a step of one
+    /// pixel across and one down. Every ray leaves from the eye, so the
+    /// origin doesn't move.
+    fn pixel_differential(&self, to_pixel: Vector) -> RayDifferential {
+        let direction = to_pixel.normalize();
+        let step = |offset: Vector| {
+            let change = self.transform.inverse() * offset;
+            (change - direction * direction.dot(change)) / to_pixel.magnitude()
+        };
+
+        RayDifferential {
+            dddx: step(Vector::new(-self.pixel_size(), 0.0, 0.0)),
+            dddy: step(Vector::new(0.0, -self.pixel_size(), 0.0)),
+            ..RayDifferential::default()
+        }
+    }
+
+    /// How the pixel at `px`, `py` gets its color in a plain `render`.
+    pub fn debug_pixel(&self, px: usize, py: usize, world: &World) -> RayTrace {
+        world.debug_ray(self.ray_for_pixel(px, py), MAX_RECURSIVE_DEPTH)
     }
 
     pub fn render(&self, world: &World) -> Canvas {
+        self.render_with(world, &Whitted::new(MAX_RECURSIVE_DEPTH))
+    }
+
+    pub fn render_with(&self, world: &World, integrator: &dyn Integrator) -> Canvas {
+        self.render_progressively(world, integrator, |_, _| true)
+            .expect("a render that is never stopped finishes")
+    }
+
+    /// Renders a scanline at a time, showing `progress` the unexposed image
+    /// after each finished row. Returns `None` as soon as `progress` returns
+    /// false to stop the render.
+    pub fn render_progressively(
+        &self,
+        world: &World,
+        integrator: &dyn Integrator,
+        progress: impl FnMut(&Canvas, usize) -> bool,
+    ) -> Option<Canvas> {
+        let image = Canvas::new(self.hsize, self.vsize);
+        let rows = (0..self.vsize).collect::<Vec<_>>();
+
+        self.render_rows(world, integrator, image, &rows, progress)
+            .ok()
+    }
+
+    /// Renders lazily, one pixel at a time in reading order, so the caller
+    /// decides what to do with each as it arrives: draw a progress bar,
+    /// stream it out, or stop early by dropping the iterator. Colors come
+    /// unexposed; collect them into a canvas and pass it to `expose` to get
+    /// what `render` would.
+    pub fn render_iter<'a>(
+        &'a self,
+        world: &'a World,
+    ) -> impl Iterator<Item = (usize, usize, Color)> + 'a {
+        let integrator = Whitted::new(MAX_RECURSIVE_DEPTH);
+        let gizmos = self.gizmos_for(world);
+        self.pixels()
+            .map(move |(x, y)| (x, y, self.render_pixel(world, &integrator, &gizmos, x, y)))
+    }
+
+    /// `render_iter` with a choice of integrator.
+    pub fn render_iter_with<'a>(
+        &'a self,
+        world: &'a World,
+        integrator: &'a dyn Integrator,
+    ) -> impl Iterator<Item = (usize, usize, Color)> + 'a {
+        let gizmos = self.gizmos_for(world);
+        self.pixels()
+            .map(move |(x, y)| (x, y, self.render_pixel(world, integrator, &gizmos, x, y)))
+    }
+
+    /// Applies the camera's exposure to a finished image.
+    pub fn expose(&self, image: &mut Canvas) {
+        image.scale(self.exposure.multiplier(image));
+    }
+
+    fn pixels(&self) -> impl Iterator<Item = (usize, usize)> {
+        let (width, height) = (self.hsize, self.vsize);
+        (0..height).flat_map(move |y| (0..width).map(move |x| (x, y)))
+    }
+
+    fn render_pixel(
+        &self,
+        world: &World,
+        integrator: &dyn Integrator,
+        gizmos: &[Gizmo],
+        x: usize,
+        y: usize,
+    ) -> Color {
+        let ray = self.ray_for_pixel(x, y);
+        stats::record_ray(RayKind::Primary);
+        world.start_pixel(x, y, 0);
+        integrator.start_pixel(x, y, 0);
+        match self.gizmo_in_front(world, ray, gizmos) {
+            Some(gizmo) => gizmo,
+            None => integrator.color_at(world, ray),
+        }
+    }
+
+    /// The gizmos drawn over a render of `world`, gathered once for the
+    /// whole image. There are none unless `debug_gizmos` is on.
+    fn gizmos_for(&self, world: &World) -> Vec<Gizmo> {
+        if !self.debug_gizmos {
+            return vec![];
+        }
+        let mut gizmos = gizmos::of_world(world);
+        gizmos.extend(self.gizmos.iter().copied());
+        gizmos
+    }
+
+    /// The color of the nearest of `gizmos` along `ray`, unless the scene
+    /// hides it. The scene is only searched on pixels a gizmo covers, and
+    /// only as far as the gizmo, so the pixel isn't shaded when the gizmo
+    /// is in front.
+    fn gizmo_in_front(&self, world: &World, ray: Ray, gizmos: &[Gizmo]) -> Option<Color> {
+        let width = GIZMO_WIDTH * self.pixel_size();
+        let (t, color) = gizmos
+            .iter()
+            .filter_map(|g| Some((g.hit(ray, width)?, g.color())))
+            .min_by(|a, b| a.0.total_cmp(&b.0))?;
+
+        world
+            .intersect(ray.t_max(t))
+            .hit()
+            .is_none()
+            .then_some(color)
+    }
+
+    /// Renders only the pixels from `x0`, `y0` up to but not including
+    /// `x1`, `y1`, exactly as `render` would, into a canvas the size of the
+    /// crop. `Canvas::paste` puts it back into a full frame. The crop is
+    /// clamped to the image, and auto exposure only sees the crop.
+    pub fn render_region(
+        &self,
+        world: &World,
+        x0: usize,
+        y0: usize,
+        x1: usize,
+        y1: usize,
+    ) -> Canvas {
+        let (x1, y1) = (x1.min(self.hsize), y1.min(self.vsize));
+        let (x0, y0) = (x0.min(x1), y0.min(y1));
+        let integrator = Whitted::new(MAX_RECURSIVE_DEPTH);
+        let gizmos = self.gizmos_for(world);
+        let mut image = Canvas::new(x1 - x0, y1 - y0);
+
+        for y in y0..y1 {
+            for x in x0..x1 {
+                let color = self.render_pixel(world, &integrator, &gizmos, x, y);
+                image.write_pixel(x - x0, y - y0, color);
+            }
+        }
+
+        self.expose(&mut image);
+        image
+    }
+
+    /// Renders in passes that each add one more sample to every pixel, at
+    /// the next point the camera's sampler draws for it, shifted so the
+    /// first pass samples pixel centers, like `render`. After each pass
+    /// `progress` is shown the average so far, unexposed, and how many
+    /// passes it holds; the render carries on refining until it returns
+    /// false.
+    pub fn render_passes(
+        &self,
+        world: &World,
+        integrator: &dyn Integrator,
+        mut progress: impl FnMut(&Canvas, u32) -> bool,
+    ) -> Canvas {
+        let sampler = self.sampler.sampler(0);
+        let mut sums = vec![color::BLACK; self.hsize * self.vsize];
         let mut image = Canvas::new(self.hsize, self.vsize);
+        let mut passes = 0;
+
+        loop {
+            passes += 1;
+            for y in 0..self.vsize {
+                for x in 0..self.hsize {
+                    let (dx, dy) = self.pass_offset(sampler.as_ref(), x, y, passes - 1);
+                    let ray = self.ray_through_pixel(x, y, dx, dy);
+                    stats::record_ray(RayKind::Primary);
+                    world.start_pixel(x, y, passes - 1);
+                    integrator.start_pixel(x, y, passes - 1);
+                    let sum = &mut sums[x + y * self.hsize];
+                    *sum = *sum + integrator.color_at(world, ray);
+                    image.write_pixel(x, y, *sum * (1.0 / passes as f32));
+                }
+            }
+            if !progress(&image, passes) {
+                break;
+            }
+        }
+
+        self.expose(&mut image);
+        image
+    }
+
+    /// Where in the pixel at `x`, `y` the sample for `pass` goes. Every
+    /// pixel draws its own run of samples, moved around the wrapped pixel
+    /// so the first lands in the middle.
+    fn pass_offset(&self, sampler: &dyn Sampler, x: usize, y: usize, pass: u32) -> (f32, f32) {
+        sampler.reseed(pixel_seed(0, x, y, 0));
+        sampler.start_sample(0);
+        let (u0, v0) = sampler.next_2d();
+        sampler.start_sample(pass);
+        let (u, v) = sampler.next_2d();
+        ((u - u0 + 1.5).fract(), (v - v0 + 1.5).fract())
+    }
+
+    /// Renders until `token` is cancelled, checking after every row. A
+    /// cancelled render comes back as the work done so far, which can be
+    /// saved and later passed to `resume`.
+    pub fn render_cancellable(
+        &self,
+        world: &World,
+        integrator: &dyn Integrator,
+        token: &CancelToken,
+    ) -> Result<Canvas, PartialRender> {
+        let rows = (0..self.vsize).collect::<Vec<_>>();
+        self.render_rows(
+            world,
+            integrator,
+            Canvas::new(self.hsize, self.vsize),
+            &rows,
+            |_, _| !token.is_cancelled(),
+        )
+    }
+
+    /// Carries on with a cancelled render, rendering only its remaining
+    /// rows. A render started at a different size is handed back as an
+    /// error without rendering anything.
+    pub fn resume(
+        &self,
+        world: &World,
+        integrator: &dyn Integrator,
+        partial: PartialRender,
+        token: &CancelToken,
+    ) -> Result<Canvas, ResumeError> {
+        if (partial.image.width, partial.image.height) != (self.hsize, self.vsize) {
+            return Err(ResumeError::WrongSize {
+                camera: (self.hsize, self.vsize),
+                partial,
+            });
+        }
+
+        self.render_rows(
+            world,
+            integrator,
+            partial.image,
+            &partial.remaining_rows,
+            |_, _| !token.is_cancelled(),
+        )
+        .map_err(ResumeError::Cancelled)
+    }
+
+    fn render_rows(
+        &self,
+        world: &World,
+        integrator: &dyn Integrator,
+        mut image: Canvas,
+        rows: &[usize],
+        mut progress: impl FnMut(&Canvas, usize) -> bool,
+    ) -> Result<Canvas, PartialRender> {
+        let gizmos = self.gizmos_for(world);
+        for (i, &y) in rows.iter().enumerate() {
+            for x in 0..self.hsize {
+                image.write_pixel(x, y, self.render_pixel(world, integrator, &gizmos, x, y));
+            }
+            if !progress(&image, y) && i + 1 < rows.len() {
+                return Err(PartialRender {
+                    image,
+                    remaining_rows: rows[i + 1..].to_vec(),
+                });
+            }
+        }
+
+        self.expose(&mut image);
+        Ok(image)
+    }
+
+    /// Renders the buckets of `buckets` not already on disk, saving each as
+    /// it's finished, until they're all done or `token` is cancelled. Only a
+    /// bucket's worth of pixels is held at once, and running it again after
+    /// a cancel carries on from the buckets still missing. Buckets for an
+    /// image of a different size are an error, and nothing is rendered.
+    pub fn render_buckets(
+        &self,
+        world: &World,
+        integrator: &dyn Integrator,
+        buckets: &BucketRender,
+        token: &CancelToken,
+    ) -> Result<(), BucketError> {
+        if (buckets.width, buckets.height) != (self.hsize, self.vsize) {
+            return Err(BucketError::WrongImageSize {
+                expected: (self.hsize, self.vsize),
+                found: (buckets.width, buckets.height),
+            });
+        }
+
+        let gizmos = self.gizmos_for(world);
+        for (column, row) in buckets.remaining() {
+            let (x0, y0, x1, y1) = buckets.bounds(column, row);
+            let mut image = Canvas::new(x1 - x0, y1 - y0);
+            for y in y0..y1 {
+                for x in x0..x1 {
+                    let color = self.render_pixel(world, integrator, &gizmos, x, y);
+                    image.write_pixel(x - x0, y - y0, color);
+                }
+            }
+            buckets.save(column, row, &image)?;
+            if token.is_cancelled() {
+                break;
+            }
+        }
+        Ok(())
+    }
+
+    /// Writes the finished buckets out as a PPM, exposed as `render` would
+    /// expose the whole image.
+    pub fn write_buckets_ppm(
+        &self,
+        buckets: &BucketRender,
+        writer: impl Write,
+    ) -> Result<(), BucketError> {
+        let average = match self.exposure {
+            Exposure::Auto(_) => buckets.average_luminance()?,
+            Exposure::Manual(_) => 0.0,
+        };
+        buckets.write_ppm(self.exposure.multiplier_for(|| average), writer)
+    }
+
+    /// Renders the image and, alongside it, the depth, normal, albedo and
+    /// object id of whatever each pixel's camera ray hits first.
+    pub fn render_with_aovs(&self, world: &World, integrator: &dyn Integrator) -> RenderOutput {
+        let mut output = RenderOutput {
+            color: self.render_with(world, integrator),
+            depth: Canvas::new(self.hsize, self.vsize),
+            normal: Canvas::new(self.hsize, self.vsize),
+            albedo: Canvas::new(self.hsize, self.vsize),
+            object_ids: vec![None; self.hsize * self.vsize],
+        };
 
         for y in 0..self.vsize {
             for x in 0..self.hsize {
                 let ray = self.ray_for_pixel(x, y);
-                let color = world.color_at(ray, MAX_RECURSIVE_DEPTH);
-                image.write_pixel(x, y, color);
+                let xs = world.intersect(ray);
+                let hit = match xs.hit() {
+                    Some(hit) => hit,
+                    None => {
+                        let infinity = Color::new(f32::INFINITY, f32::INFINITY, f32::INFINITY);
+                        output.depth.write_pixel(x, y, infinity);
+                        continue;
+                    }
+                };
+
+                let comps = world.prepare_computations(hit, ray, &xs);
+                let normal = comps.normalv;
+                let albedo = world
+                    .shading_material(&comps)
+                    .color_at(hit.object, comps.point);
+                output
+                    .depth
+                    .write_pixel(x, y, Color::new(hit.t, hit.t, hit.t));
+                output
+                    .normal
+                    .write_pixel(x, y, Color::new(normal.x(), normal.y(), normal.z()));
+                output.albedo.write_pixel(x, y, albedo);
+                output.object_ids[x + y * self.hsize] = Some(hit.object.id());
             }
         }
 
-        image
+        output
+    }
+
+    /// A quick look at the composition: a quarter of the resolution, lit
+    /// without shadows, reflections or refraction.
+    pub fn render_preview(&self, world: &World) -> Canvas {
+        self.preview_camera().render_with(world, &Preview)
+    }
+
+    fn preview_camera(&self) -> Self {
+        Self {
+            hsize: (self.hsize / PREVIEW_SCALE).max(1),
+            vsize: (self.vsize / PREVIEW_SCALE).max(1),
+            field_of_view: self.field_of_view,
+            transform: self.transform,
+            axis_convention: self.axis_convention,
+            exposure: self.exposure,
+            shadow_bias: self.shadow_bias,
+            filter_patterns: self.filter_patterns,
+            debug_gizmos: self.debug_gizmos,
+            gizmos: self.gizmos.clone(),
+            sampler: self.sampler,
+        }
+    }
+
+    /// Renders while counting rays and intersection tests.
+    pub fn render_with_stats(
+        &self,
+        world: &World,
+        integrator: &dyn Integrator,
+    ) -> (Canvas, RenderStats) {
+        stats::collect(|| stats::time_phase("render", || self.render_with(world, integrator)))
     }
 
     fn aspect(&self) -> f32 {
@@ -92,7 +680,15 @@ impl Camera {
 mod tests {
     use std::f32::consts::{PI, SQRT_2};
 
-    use crate::{color::Color, float_eq};
+    use crate::{
+        color::Color,
+        float_eq,
+        integrator::PathTracer,
+        lights::PointLight,
+        ray::Roulette,
+        shapes::{plane::Plane, sphere::Sphere, ShapeBuilder},
+        EPSILON,
+    };
 
     use super::*;
 
@@ -130,8 +726,8 @@ mod tests {
 
         let r = c.ray_for_pixel(100, 50);
 
-        assert_eq!(r.origin, Tuple::point(0.0, 0.0, 0.0));
-        assert_eq!(r.direction, Tuple::vector(0.0, 0.0, -1.0));
+        assert_eq!(r.origin, Point::new(0.0, 0.0, 0.0));
+        assert_eq!(r.direction, Vector::new(0.0, 0.0, -1.0));
     }
 
     #[test]
@@ -140,8 +736,8 @@ mod tests {
 
         let r = c.ray_for_pixel(0, 0);
 
-        assert_eq!(r.origin, Tuple::point(0.0, 0.0, 0.0));
-        assert_eq!(r.direction, Tuple::vector(0.66519, 0.33259, -0.66851));
+        assert_eq!(r.origin, Point::new(0.0, 0.0, 0.0));
+        assert_eq!(r.direction, Vector::new(0.66519, 0.33259, -0.66851));
     }
 
     #[test]
@@ -151,21 +747,620 @@ mod tests {
         c.transform = Transform::rotation_y(PI / 4.0) * Transform::translation(0.0, -2.0, 5.0);
         let r = c.ray_for_pixel(100, 50);
 
-        assert_eq!(r.origin, Tuple::point(0.0, 2.0, -5.0));
-        assert_eq!(r.direction, Tuple::vector(SQRT_2 / 2.0, 0.0, -SQRT_2 / 2.0));
+        assert_eq!(r.origin, Point::new(0.0, 2.0, -5.0));
+        assert_eq!(r.direction, Vector::new(SQRT_2 / 2.0, 0.0, -SQRT_2 / 2.0));
+    }
+
+    #[test]
+    fn a_camera_casts_rays_with_its_shadow_bias() {
+        let c = Camera::new(11, 11, PI / 2.0).shadow_bias(ShadowBias::Adaptive(0.001));
+
+        assert_eq!(c.ray_for_pixel(5, 5).bias, ShadowBias::Adaptive(0.001));
+        assert_eq!(
+            Camera::new(11, 11, PI / 2.0).ray_for_pixel(5, 5).bias,
+            ShadowBias::Constant(EPSILON)
+        );
+    }
+
+    #[test]
+    fn a_camera_filtering_patterns_casts_rays_a_pixel_wide() {
+        let c = Camera::new(201, 101, PI / 2.0);
+        assert_eq!(c.ray_for_pixel(100, 50).differential, None);
+
+        let d = c.filter_patterns(true).ray_for_pixel(100, 50).differential;
+
+        assert_eq!(
+            d,
+            Some(RayDifferential {
+                dddx: Vector::new(-0.01, 0.0, 0.0),
+                dddy: Vector::new(0.0, -0.01, 0.0),
+                ..RayDifferential::default()
+            })
+        );
+    }
+
+    #[test]
+    fn looking_at_a_point_in_a_z_up_scene() {
+        let c = Camera::new(201, 101, PI / 2.0)
+            .axis_convention(AxisConvention::blender())
+            .look_at(
+                Point::new(0.0, -5.0, 2.0),
+                Point::new(0.0, 0.0, 2.0),
+                Vector::new(0.0, 0.0, 1.0),
+            );
+
+        let r = c.ray_for_pixel(100, 50);
+
+        assert_eq!(r.origin, Point::new(0.0, 2.0, -5.0));
+        assert_eq!(r.direction, Vector::new(0.0, 0.0, 1.0));
+    }
+
+    #[test]
+    fn a_z_up_camera_keeps_left_on_the_left() {
+        let native = Camera::new(201, 101, PI / 2.0).look_at(
+            Point::new(0.0, 0.0, -5.0),
+            Point::new(0.0, 0.0, 0.0),
+            Vector::new(0.0, 1.0, 0.0),
+        );
+        let z_up = Camera::new(201, 101, PI / 2.0)
+            .axis_convention(AxisConvention::blender())
+            .look_at(
+                Point::new(0.0, -5.0, 0.0),
+                Point::new(0.0, 0.0, 0.0),
+                Vector::new(0.0, 0.0, 1.0),
+            );
+
+        assert_eq!(z_up.ray_for_pixel(0, 0), native.ray_for_pixel(0, 0));
     }
 
     #[test]
     fn rendering_world_with_camera() {
         let w = World::default();
         let mut c = Camera::new(11, 11, PI / 2.0);
-        let from = Tuple::point(0.0, 0.0, -5.0);
-        let to = Tuple::point(0.0, 0.0, 0.0);
-        let up = Tuple::vector(0.0, 1.0, 0.0);
+        let from = Point::new(0.0, 0.0, -5.0);
+        let to = Point::new(0.0, 0.0, 0.0);
+        let up = Vector::new(0.0, 1.0, 0.0);
         c.transform = Transform::view_transform(from, to, up);
 
         let image = c.render(&w);
 
         assert_eq!(image.pixel_at(5, 5), Color::new(0.38066, 0.47583, 0.2855));
     }
+
+    #[test]
+    fn debugging_a_pixel_traces_the_ray_it_renders_with() {
+        let w = World::default();
+        let c = Camera::new(11, 11, PI / 2.0).transform(Transform::view_transform(
+            Point::new(0.0, 0.0, -5.0),
+            Point::new(0.0, 0.0, 0.0),
+            Vector::new(0.0, 1.0, 0.0),
+        ));
+
+        let trace = c.debug_pixel(5, 5, &w);
+
+        assert_eq!(trace.ray, c.ray_for_pixel(5, 5));
+        assert_eq!(trace.color, c.render(&w).pixel_at(5, 5));
+    }
+
+    #[test]
+    fn rendering_progressively_reports_each_finished_row() {
+        let w = World::default();
+        let c = Camera::new(5, 3, PI / 2.0)
+            .transform(Transform::translation(0.0, 0.0, -5.0))
+            .exposure(Exposure::Manual(2.0));
+        let mut rows = vec![];
+
+        let image = c
+            .render_progressively(&w, &Whitted::new(1), |partial, y| {
+                rows.push((y, partial.pixel_at(2, y)));
+                true
+            })
+            .unwrap();
+
+        assert_eq!(
+            rows.iter().map(|&(y, _)| y).collect::<Vec<_>>(),
+            vec![0, 1, 2]
+        );
+        assert_eq!(image.pixel_at(2, 1), rows[1].1 * 2.0);
+    }
+
+    #[test]
+    fn rendering_pixel_by_pixel_matches_a_full_render() {
+        let w = World::default();
+        let c = Camera::new(5, 3, PI / 2.0)
+            .transform(Transform::translation(0.0, 0.0, -5.0))
+            .exposure(Exposure::Auto(0.18));
+        let full = c.render(&w);
+
+        let mut image = Canvas::new(5, 3);
+        let mut order = vec![];
+        for (x, y, color) in c.render_iter(&w) {
+            order.push((x, y));
+            image.write_pixel(x, y, color);
+        }
+        c.expose(&mut image);
+
+        assert_eq!(order.len(), 15);
+        assert_eq!(
+            &order[..6],
+            &[(0, 0), (1, 0), (2, 0), (3, 0), (4, 0), (0, 1)]
+        );
+        assert_eq!(image, full);
+    }
+
+    #[test]
+    fn a_pixel_iterator_only_renders_what_is_taken() {
+        let w = World::default();
+        let c = Camera::new(100, 100, PI / 2.0).transform(Transform::translation(0.0, 0.0, -5.0));
+
+        let (_, stats) = stats::collect(|| {
+            c.render_iter_with(&w, &Whitted::new(1))
+                .take(3)
+                .collect::<Vec<_>>()
+        });
+
+        assert_eq!(stats.primary_rays, 3);
+    }
+
+    #[test]
+    fn rendering_a_region_matches_that_part_of_the_full_frame() {
+        let w = World::default();
+        let c = Camera::new(11, 11, PI / 2.0).transform(Transform::translation(0.0, 0.0, -5.0));
+        let full = c.render(&w);
+
+        let crop = c.render_region(&w, 3, 4, 7, 6);
+
+        assert_eq!((crop.width, crop.height), (4, 2));
+        for y in 0..2 {
+            for x in 0..4 {
+                assert_eq!(crop.pixel_at(x, y), full.pixel_at(x + 3, y + 4));
+            }
+        }
+    }
+
+    #[test]
+    fn a_region_is_clamped_to_the_image() {
+        let w = World::default();
+        let c = Camera::new(5, 5, PI / 2.0);
+
+        let crop = c.render_region(&w, 3, 4, 10, 10);
+
+        assert_eq!((crop.width, crop.height), (2, 1));
+    }
+
+    #[test]
+    fn pasting_a_region_back_into_a_full_frame() {
+        let w = World::default();
+        let c = Camera::new(5, 5, PI / 2.0).transform(Transform::translation(0.0, 0.0, -5.0));
+        let mut image = Canvas::new(5, 5);
+
+        image.paste(0, 0, &c.render_region(&w, 0, 0, 5, 3));
+        image.paste(0, 3, &c.render_region(&w, 0, 3, 5, 5));
+
+        assert_eq!(image, c.render(&w));
+    }
+
+    #[test]
+    fn the_first_pass_samples_pixel_centers() {
+        let w = World::default();
+        let c = Camera::new(5, 5, PI / 2.0).transform(Transform::translation(0.0, 0.0, -5.0));
+
+        let image = c.render_passes(&w, &Whitted::new(3), |_, _| false);
+
+        assert_eq!(image, c.render(&w));
+    }
+
+    #[test]
+    fn each_pass_adds_a_sample_to_every_pixel() {
+        let w = World::default();
+        let c = Camera::new(5, 5, PI / 2.0).transform(Transform::translation(0.0, 0.0, -5.0));
+        let integrator = Whitted::new(3);
+        let mut passes = vec![];
+
+        let image = c.render_passes(&w, &integrator, |_, pass| {
+            passes.push(pass);
+            pass < 3
+        });
+
+        let halton = SamplerKind::Halton.sampler(0);
+        let samples = (0..3)
+            .map(|pass| c.pass_offset(halton.as_ref(), 1, 3, pass))
+            .map(|(dx, dy)| integrator.color_at(&w, c.ray_through_pixel(1, 3, dx, dy)))
+            .fold(color::BLACK, |sum, c| sum + c);
+        assert_eq!(passes, vec![1, 2, 3]);
+        assert_eq!(image.pixel_at(1, 3), samples * (1.0 / 3.0));
+    }
+
+    #[test]
+    fn each_pixel_draws_its_own_samples_from_the_cameras_sampler() {
+        let c = Camera::new(5, 5, PI / 2.0).sampler(SamplerKind::Stratified(2));
+        let stratified = SamplerKind::Stratified(2).sampler(0);
+        let offsets = |x, y| {
+            (0..4)
+                .map(|pass| c.pass_offset(stratified.as_ref(), x, y, pass))
+                .collect::<Vec<_>>()
+        };
+
+        assert_eq!(offsets(1, 3)[0], (0.5, 0.5));
+        assert_ne!(offsets(1, 3), offsets(2, 3));
+        assert_eq!(offsets(1, 3), offsets(1, 3));
+    }
+
+    #[test]
+    fn stopping_a_progressive_render() {
+        let w = World::default();
+        let c = Camera::new(5, 3, PI / 2.0);
+        let mut rows = 0;
+
+        let image = c.render_progressively(&w, &Whitted::new(1), |_, _| {
+            rows += 1;
+            false
+        });
+
+        assert_eq!(image, None);
+        assert_eq!(rows, 1);
+    }
+
+    #[test]
+    fn a_render_that_is_not_cancelled_finishes() {
+        let w = World::default();
+        let c = default_world_camera();
+
+        let image = c.render_cancellable(&w, &Whitted::new(3), &CancelToken::new());
+
+        assert_eq!(image, Ok(c.render(&w)));
+    }
+
+    #[test]
+    fn a_cancelled_render_stops_after_the_current_row() {
+        let w = World::default();
+        let c = default_world_camera();
+        let token = CancelToken::new();
+        token.cancel();
+
+        let partial = c
+            .render_cancellable(&w, &Whitted::new(3), &token)
+            .unwrap_err();
+
+        assert_eq!(partial.remaining_rows, (1..11).collect::<Vec<_>>());
+        assert_eq!(partial.image.pixel_at(5, 0), c.render(&w).pixel_at(5, 0));
+        assert_eq!(partial.image.pixel_at(5, 5), Color::new(0.0, 0.0, 0.0));
+    }
+
+    #[test]
+    fn a_resumed_render_matches_an_uninterrupted_one() {
+        let w = World::default();
+        let c = default_world_camera();
+        let token = CancelToken::new();
+        token.cancel();
+        let partial = c
+            .render_cancellable(&w, &Whitted::new(3), &token)
+            .unwrap_err();
+        let mut saved = vec![];
+        partial.save(&mut saved).unwrap();
+
+        let image = c.resume(
+            &w,
+            &Whitted::new(3),
+            PartialRender::load(saved.as_slice()).unwrap(),
+            &CancelToken::new(),
+        );
+
+        assert_eq!(image, Ok(c.render(&w)));
+    }
+
+    #[test]
+    fn resuming_a_render_of_another_size_hands_it_back() {
+        let w = World::default();
+        let c = default_world_camera();
+        let partial = PartialRender {
+            image: Canvas::new(5, 5),
+            remaining_rows: (0..5).collect(),
+        };
+
+        let result = c.resume(&w, &Whitted::new(3), partial.clone(), &CancelToken::new());
+
+        assert_eq!(
+            result,
+            Err(ResumeError::WrongSize {
+                camera: (11, 11),
+                partial
+            })
+        );
+    }
+
+    #[test]
+    fn random_sampling_doesnt_depend_on_the_order_pixels_are_rendered_in() {
+        let floor = Plane::default().with_transform(Transform::translation(0.0, -1.0, 0.0));
+        let w = World::default()
+            .object(Box::new(floor))
+            .roulette(Roulette::new(0.5, 7));
+        let c = default_world_camera();
+        let in_order = c.render_with(&w, &PathTracer::new(2));
+
+        let backwards = c.resume(
+            &w,
+            &PathTracer::new(2),
+            PartialRender {
+                image: Canvas::new(11, 11),
+                remaining_rows: (0..11).rev().collect(),
+            },
+            &CancelToken::new(),
+        );
+
+        assert_eq!(backwards, Ok(in_order));
+    }
+
+    #[test]
+    fn an_interrupted_bucket_render_assembles_into_the_whole_image() {
+        let w = World::default();
+        let c = default_world_camera();
+        let dir = std::env::temp_dir().join(format!("buckets-{}", Uuid::new_v4()));
+        let buckets = BucketRender::new(dir, 11, 11, 4);
+        let token = CancelToken::new();
+        token.cancel();
+
+        c.render_buckets(&w, &Whitted::new(3), &buckets, &token)
+            .unwrap();
+        assert_eq!(buckets.remaining().len(), 8);
+        c.render_buckets(&w, &Whitted::new(3), &buckets, &CancelToken::new())
+            .unwrap();
+        let mut ppm = vec![];
+        c.write_buckets_ppm(&buckets, &mut ppm).unwrap();
+
+        assert_eq!(String::from_utf8(ppm).unwrap(), c.render(&w).export_srgb());
+        buckets.clean_up().unwrap();
+    }
+
+    #[test]
+    fn rendering_buckets_for_another_size_of_image_fails() {
+        let w = World::default();
+        let c = default_world_camera();
+        let dir = std::env::temp_dir().join(format!("buckets-{}", Uuid::new_v4()));
+        let buckets = BucketRender::new(dir, 8, 8, 4);
+
+        let result = c.render_buckets(&w, &Whitted::new(3), &buckets, &CancelToken::new());
+
+        assert!(matches!(
+            result,
+            Err(BucketError::WrongImageSize {
+                expected: (11, 11),
+                found: (8, 8)
+            })
+        ));
+        assert_eq!(buckets.remaining().len(), 4);
+    }
+
+    fn far_from_the_default_world() -> Camera {
+        Camera::new(21, 21, PI / 2.0).look_at(
+            Point::new(0.0, 0.0, -30.0),
+            Point::new(0.0, 0.0, 0.0),
+            Vector::new(0.0, 1.0, 0.0),
+        )
+    }
+
+    #[test]
+    fn debug_gizmos_mark_the_light() {
+        let w = World::default();
+
+        let plain = far_from_the_default_world().render(&w);
+        let marked = far_from_the_default_world().debug_gizmos(true).render(&w);
+
+        let marks = (0..21)
+            .flat_map(|y| (0..21).map(move |x| (x, y)))
+            .filter(|&(x, y)| marked.pixel_at(x, y) != plain.pixel_at(x, y))
+            .collect::<Vec<_>>();
+        assert!(!marks.is_empty());
+        assert!(marks.iter().all(|&(x, y)| x < 10 && y < 10));
+        assert!(marks
+            .iter()
+            .all(|&(x, y)| marked.pixel_at(x, y) == color::WHITE));
+    }
+
+    #[test]
+    fn a_gizmo_hidden_behind_the_scene_isnt_drawn() {
+        let w = World::new(PointLight::new(Point::new(0.0, 0.0, 10.0), color::WHITE)).object(
+            Box::new(Plane::default().with_transform(Transform::rotation_x(PI / 2.0))),
+        );
+
+        let plain = far_from_the_default_world().render(&w);
+        let marked = far_from_the_default_world().debug_gizmos(true).render(&w);
+
+        assert_eq!(marked, plain);
+    }
+
+    #[test]
+    fn pixels_covered_by_a_gizmo_arent_shaded() {
+        let w = World::new(PointLight::new(Point::new(0.0, 0.0, -10.0), color::WHITE))
+            .object(Box::new(Sphere::default()));
+        let c = far_from_the_default_world();
+
+        let (_, plain) = stats::collect(|| c.render(&w));
+        let (image, marked) =
+            stats::collect(|| far_from_the_default_world().debug_gizmos(true).render(&w));
+
+        assert_eq!(image.pixel_at(10, 10), color::WHITE);
+        assert!(marked.shadow_rays < plain.shadow_rays);
+    }
+
+    #[test]
+    fn drawing_another_cameras_frustum() {
+        let w = World::new(PointLight::new(Point::new(0.0, 100.0, 0.0), color::WHITE));
+        let frustum = default_world_camera().frustum(2.0);
+
+        let image = far_from_the_default_world()
+            .debug_gizmos(true)
+            .gizmos(frustum.clone())
+            .render(&w);
+
+        assert_eq!(frustum.len(), 8);
+        assert!(frustum.iter().take(4).all(|g| matches!(
+            g,
+            Gizmo::Line { from, .. } if *from == Point::new(0.0, 0.0, -5.0)
+        )));
+        assert_eq!(image.pixel_at(10, 10), frustum[0].color());
+        assert_eq!(image.pixel_at(0, 0), color::BLACK);
+    }
+
+    #[test]
+    fn rendering_aovs_alongside_the_image() {
+        let w = World::default();
+        let c = default_world_camera();
+
+        let output = c.render_with_aovs(&w, &Whitted::new(3));
+
+        assert_eq!(output.color, c.render(&w));
+        assert_eq!(output.depth.pixel_at(5, 5), Color::new(4.0, 4.0, 4.0));
+        assert_eq!(output.normal.pixel_at(5, 5), Color::new(0.0, 0.0, -1.0));
+        assert_eq!(output.albedo.pixel_at(5, 5), Color::new(0.8, 1.0, 0.6));
+        assert_eq!(output.object_id(5, 5), Some(w.objects()[0].id()));
+    }
+
+    #[test]
+    fn aovs_where_the_camera_ray_escapes() {
+        let w = World::default();
+        let c = default_world_camera();
+
+        let output = c.render_with_aovs(&w, &Whitted::new(3));
+
+        assert_eq!(output.depth.pixel_at(0, 0).red(), f32::INFINITY);
+        assert_eq!(output.normal.pixel_at(0, 0), Color::new(0.0, 0.0, 0.0));
+        assert_eq!(output.albedo.pixel_at(0, 0), Color::new(0.0, 0.0, 0.0));
+        assert_eq!(output.object_id(0, 0), None);
+    }
+
+    #[test]
+    fn rendering_with_a_path_tracer() {
+        let w = World::default();
+        let c = Camera::new(11, 11, PI / 2.0).look_at(
+            Point::new(0.0, 0.0, -5.0),
+            Point::new(0.0, 0.0, 0.0),
+            Vector::new(0.0, 1.0, 0.0),
+        );
+
+        let image = c.render_with(&w, &PathTracer::new(4));
+
+        assert_eq!(image.pixel_at(5, 5), Color::new(0.30066, 0.37583, 0.2255));
+        assert_eq!(image.pixel_at(0, 0), Color::new(0.0, 0.0, 0.0));
+    }
+
+    #[test]
+    fn rendering_with_stats() {
+        let w = World::default();
+        let c = Camera::new(11, 11, PI / 2.0).look_at(
+            Point::new(0.0, 0.0, -5.0),
+            Point::new(0.0, 0.0, 0.0),
+            Vector::new(0.0, 1.0, 0.0),
+        );
+
+        let (image, stats) = c.render_with_stats(&w, &Whitted::new(MAX_RECURSIVE_DEPTH));
+
+        assert_eq!(image, c.render(&w));
+        assert_eq!(stats.primary_rays, 121);
+        assert_eq!(
+            stats.intersection_tests["Sphere"],
+            2 * (121 + stats.shadow_rays)
+        );
+        assert!(stats.shadow_rays > 0);
+        assert_eq!(stats.reflection_rays, 0);
+        assert!(stats.phase("render").is_some());
+    }
+
+    #[test]
+    fn a_preview_is_rendered_at_a_quarter_of_the_resolution() {
+        let c = Camera::new(200, 100, PI / 2.0);
+
+        let image = c.render_preview(&World::default());
+
+        assert_eq!(image.width, 50);
+        assert_eq!(image.height, 25);
+    }
+
+    #[test]
+    fn a_preview_of_a_tiny_camera_has_at_least_one_pixel() {
+        let c = Camera::new(3, 2, PI / 2.0);
+
+        let image = c.render_preview(&World::default());
+
+        assert_eq!((image.width, image.height), (1, 1));
+    }
+
+    #[test]
+    fn a_preview_keeps_the_camera_pointed_the_same_way() {
+        let w = World::default();
+        let c = Camera::new(44, 44, PI / 2.0).look_at(
+            Point::new(0.0, 0.0, -5.0),
+            Point::new(0.0, 0.0, 0.0),
+            Vector::new(0.0, 1.0, 0.0),
+        );
+
+        let image = c.render_preview(&w);
+
+        assert_eq!(image.pixel_at(5, 5), Color::new(0.38066, 0.47583, 0.2855));
+        assert_eq!(image.pixel_at(0, 0), Color::new(0.0, 0.0, 0.0));
+    }
+
+    fn default_world_camera() -> Camera {
+        Camera::new(11, 11, PI / 2.0).look_at(
+            Point::new(0.0, 0.0, -5.0),
+            Point::new(0.0, 0.0, 0.0),
+            Vector::new(0.0, 1.0, 0.0),
+        )
+    }
+
+    #[test]
+    fn the_default_exposure_leaves_the_image_alone() {
+        let c = default_world_camera();
+
+        assert_eq!(c.exposure, Exposure::Manual(1.0));
+    }
+
+    #[test]
+    fn a_manual_exposure_scales_every_pixel() {
+        let w = World::default();
+        let c = default_world_camera().exposure(Exposure::Manual(2.0));
+
+        let image = c.render(&w);
+
+        assert_eq!(
+            image.pixel_at(5, 5),
+            Color::new(0.38066, 0.47583, 0.2855) * 2.0
+        );
+    }
+
+    #[test]
+    fn auto_exposure_brings_the_average_luminance_to_the_key() {
+        let w = World::default();
+        let c = default_world_camera().exposure(Exposure::Auto(0.18));
+
+        let image = c.render(&w);
+
+        assert!(float_eq(image.average_luminance(), 0.18));
+    }
+
+    #[test]
+    fn auto_exposure_of_a_black_image() {
+        let w = World::new(PointLight::default());
+        let c = default_world_camera().exposure(Exposure::Auto(0.18));
+
+        let image = c.render(&w);
+
+        assert!(float_eq(image.average_luminance(), 0.0));
+    }
+
+    #[test]
+    fn exposure_from_camera_settings() {
+        assert_eq!(
+            Exposure::from_camera(1.0, 1.0, 100.0),
+            Exposure::Manual(1.0)
+        );
+        assert_eq!(
+            Exposure::from_camera(2.0, 1.0, 100.0),
+            Exposure::Manual(0.25)
+        );
+        assert_eq!(
+            Exposure::from_camera(1.0, 0.5, 400.0),
+            Exposure::Manual(2.0)
+        );
+    }
 }
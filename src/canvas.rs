@@ -1,6 +1,17 @@
-use crate::{color, color::Color};
+use std::{
+    error::Error,
+    fmt::{self, Display},
+    io::{self, Read},
+};
 
-#[derive(Debug)]
+use crate::{
+    color,
+    color::Color,
+    grade::Grade,
+    text::{glyph, GLYPH_COLUMNS, GLYPH_ROWS},
+};
+
+#[derive(Clone, Debug, PartialEq)]
 pub struct Canvas {
     pub width: usize,
     pub height: usize,
@@ -29,6 +40,160 @@ impl Canvas {
         }
     }
 
+    /// Copies `other` in with its top left corner at `x`, `y`. Whatever
+    /// falls off the edge is dropped.
+    pub fn paste(&mut self, x: usize, y: usize, other: &Canvas) {
+        for j in 0..other.height {
+            for i in 0..other.width {
+                self.write_pixel(x + i, y + j, other.pixel_at(i, j));
+            }
+        }
+    }
+
+    /// A one pixel wide line from `(x0, y0)` to `(x1, y1)`, both ends
+    /// included.
+    pub fn draw_line(&mut self, x0: usize, y0: usize, x1: usize, y1: usize, color: Color) {
+        let (x0, y0, x1, y1) = (x0 as isize, y0 as isize, x1 as isize, y1 as isize);
+        let (dx, dy) = ((x1 - x0).abs(), -(y1 - y0).abs());
+        let (step_x, step_y) = ((x1 - x0).signum(), (y1 - y0).signum());
+        let (mut x, mut y, mut error) = (x0, y0, dx + dy);
+        loop {
+            self.write_pixel(x as usize, y as usize, color);
+            if x == x1 && y == y1 {
+                break;
+            }
+            let doubled = 2 * error;
+            if doubled >= dy {
+                error += dy;
+                x += step_x;
+            }
+            if doubled <= dx {
+                error += dx;
+                y += step_y;
+            }
+        }
+    }
+
+    /// The outline of a `width` by `height` box with its top left corner at
+    /// `x`, `y`.
+    pub fn draw_rect(&mut self, x: usize, y: usize, width: usize, height: usize, color: Color) {
+        if width == 0 || height == 0 {
+            return;
+        }
+
+        let (right, bottom) = (x + width - 1, y + height - 1);
+        self.draw_line(x, y, right, y, color);
+        self.draw_line(x, bottom, right, bottom, color);
+        self.draw_line(x, y, x, bottom, color);
+        self.draw_line(right, y, right, bottom, color);
+    }
+
+    pub fn fill_rect(&mut self, x: usize, y: usize, width: usize, height: usize, color: Color) {
+        for j in y..y + height {
+            for i in x..x + width {
+                self.write_pixel(i, j, color);
+            }
+        }
+    }
+
+    /// Writes `text` in the built-in block font with its top left corner at
+    /// `x`, `y`, each cell of a glyph `scale` pixels square. Newlines start
+    /// a fresh line below.
+    pub fn draw_text(&mut self, x: usize, y: usize, text: &str, scale: usize, color: Color) {
+        for (row, line) in text.lines().enumerate() {
+            let top = y + row * (GLYPH_ROWS + 2) * scale;
+            for (column, c) in line.chars().enumerate() {
+                let left = x + column * (GLYPH_COLUMNS + 1) * scale;
+                for (j, cells) in glyph(c).iter().enumerate() {
+                    for (i, cell) in cells.chars().enumerate() {
+                        if cell == '#' {
+                            self.fill_rect(left + i * scale, top + j * scale, scale, scale, color);
+                        }
+                    }
+                }
+            }
+        }
+    }
+
+    /// The width and height `draw_text` covers, for lining text up against
+    /// an edge or putting a box behind it.
+    pub fn text_size(text: &str, scale: usize) -> (usize, usize) {
+        let lines = text.lines().count();
+        let longest = text.lines().map(|l| l.chars().count()).max().unwrap_or(0);
+        if lines == 0 || longest == 0 {
+            return (0, 0);
+        }
+
+        (
+            (longest * (GLYPH_COLUMNS + 1) - 1) * scale,
+            (lines * (GLYPH_ROWS + 2) - 2) * scale,
+        )
+    }
+
+    /// Adds `other`, scaled by `weight`, to every pixel.
+    pub fn accumulate(&mut self, other: &Canvas, weight: f32) {
+        assert!(
+            self.width == other.width && self.height == other.height,
+            "only canvases of the same size can be accumulated"
+        );
+        for (pixel, &added) in self.pixels.iter_mut().zip(other.pixels.iter()) {
+            *pixel = *pixel + added * weight;
+        }
+    }
+
+    pub fn scale(&mut self, factor: f32) {
+        for pixel in self.pixels.iter_mut() {
+            *pixel = *pixel * factor;
+        }
+    }
+
+    /// Gives every pixel the look of `grade`, in place.
+    pub fn grade(&mut self, grade: &Grade) {
+        for pixel in self.pixels.iter_mut() {
+            *pixel = grade.apply(*pixel);
+        }
+    }
+
+    pub fn average_luminance(&self) -> f32 {
+        if self.pixels.is_empty() {
+            return 0.0;
+        }
+
+        self.pixels.iter().map(|p| p.luminance()).sum::<f32>() / self.pixels.len() as f32
+    }
+
+    /// Pixels packed as `0x00RRGGBB`, row by row, the way window buffers
+    /// usually want them.
+    pub fn to_rgb_u32(&self) -> Vec<u32> {
+        self.pixels
+            .iter()
+            .map(|pixel| {
+                let (r, g, b) = (
+                    color_u8(pixel.red()) as u32,
+                    color_u8(pixel.green()) as u32,
+                    color_u8(pixel.blue()) as u32,
+                );
+                (r << 16) | (g << 8) | b
+            })
+            .collect()
+    }
+
+    /// A copy with every pixel encoded as sRGB, ready to be shown.
+    pub fn to_srgb(&self) -> Self {
+        Self {
+            pixels: self.pixels.iter().map(|pixel| pixel.to_srgb()).collect(),
+            ..self.clone()
+        }
+    }
+
+    /// The PPM of the canvas with its linear colors encoded as sRGB, which
+    /// is what image viewers expect.
+    pub fn export_srgb(&self) -> String {
+        self.to_srgb().to_ppm()
+    }
+
+    /// The PPM of the canvas exactly as it is, with no encoding. That's
+    /// right for data such as heightmaps; pictures want `export_srgb`.
     pub fn to_ppm(&self) -> String {
         format!(
             "P3\n{} {}\n255\n{}\n",
@@ -36,24 +201,195 @@ impl Canvas {
             self.height,
             self.pixels
                 .chunks(self.width)
-                .map(|row| {
-                    row.iter()
-                        .map(|pixel| {
-                            (
-                                color_u8(pixel.red()),
-                                color_u8(pixel.green()),
-                                color_u8(pixel.blue()),
-                            )
-                        })
-                        .map(|(r, g, b)| format!("{} {} {}", r, g, b))
-                        .collect::<Vec<_>>()
-                        .join(" ")
-                })
-                .map(|s| split_long_ppm_line(&s))
+                .map(Self::ppm_row)
                 .collect::<Vec<_>>()
                 .join("\n")
         )
     }
+
+    /// One row of pixels as it's written in a PPM, for writing images out a
+    /// row at a time.
+    pub fn ppm_row(row: &[Color]) -> String {
+        let line = row
+            .iter()
+            .map(|pixel| {
+                (
+                    color_u8(pixel.red()),
+                    color_u8(pixel.green()),
+                    color_u8(pixel.blue()),
+                )
+            })
+            .map(|(r, g, b)| format!("{} {} {}", r, g, b))
+            .collect::<Vec<_>>()
+            .join(" ");
+        split_long_ppm_line(&line)
+    }
+
+    /// The PPM of the canvas graded by `grade` and encoded as sRGB, like
+    /// `export_srgb`. The grade sees the full range colors, before they're
+    /// clamped to 8 bits.
+    pub fn to_graded_ppm(&self, grade: &Grade) -> String {
+        Self {
+            pixels: self.pixels.iter().map(|&p| grade.apply_srgb(p)).collect(),
+            ..self.clone()
+        }
+        .to_ppm()
+    }
+
+    /// Reads a plain (P3) PPM image. Comments are skipped, pixels may span
+    /// lines, and channels are scaled by the file's maximum color value.
+    pub fn from_ppm(mut reader: impl Read) -> Result<Self, ParseError> {
+        let mut text = String::new();
+        reader.read_to_string(&mut text)?;
+        let mut tokens = text
+            .lines()
+            .map(|line| line.split('#').next().unwrap_or(""))
+            .flat_map(str::split_whitespace);
+
+        match tokens.next() {
+            Some("P3") => (),
+            other => return Err(ParseError::BadMagic(other.unwrap_or("").to_string())),
+        }
+        let width = parse_number(tokens.next())?;
+        let height = parse_number(tokens.next())?;
+        let max = parse_number(tokens.next())?;
+        if max == 0 {
+            return Err(ParseError::InvalidNumber(max.to_string()));
+        }
+
+        let pixels = checked_pixels(width, height).ok_or(ParseError::TooLarge(width, height))?;
+        let values = tokens.collect::<Vec<_>>();
+        if values.len() < pixels * 3 {
+            return Err(ParseError::UnexpectedEnd);
+        }
+        let mut values = values.into_iter();
+        let mut channel = || -> Result<f32, ParseError> {
+            let value = parse_number(values.next())?;
+            if value > max {
+                Err(ParseError::OutOfRange(value))
+            } else {
+                Ok(value as f32 / max as f32)
+            }
+        };
+
+        let mut canvas = Self::new(width, height);
+        for pixel in canvas.pixels.iter_mut() {
+            *pixel = Color::new(channel()?, channel()?, channel()?);
+        }
+        Ok(canvas)
+    }
+
+    /// Reads a PPM picture, such as a photo or painted texture, decoding
+    /// its sRGB pixels into linear colors.
+    pub fn from_srgb_ppm(reader: impl Read) -> Result<Self, ParseError> {
+        let mut canvas = Self::from_ppm(reader)?;
+        for pixel in canvas.pixels.iter_mut() {
+            *pixel = Color::from_srgb(pixel.red(), pixel.green(), pixel.blue());
+        }
+        Ok(canvas)
+    }
+}
+
+/// The most pixels a canvas read from a file may have, so a corrupt header
+/// can't ask for far more memory than any real image needs.
+pub const MAX_PIXELS: usize = 1 << 26;
+
+/// How many pixels a `width` by `height` canvas has, or `None` if that's
+/// more than `MAX_PIXELS`.
+pub fn checked_pixels(width: usize, height: usize) -> Option<usize> {
+    width
+        .checked_mul(height)
+        .filter(|&pixels| pixels <= MAX_PIXELS)
+}
+
+fn parse_number(token: Option<&str>) -> Result<usize, ParseError> {
+    let token = token.ok_or(ParseError::UnexpectedEnd)?;
+    token
+        .parse()
+        .map_err(|_| ParseError::InvalidNumber(token.to_string()))
+}
+
+/// The weighted average of whole frames rendered one after another, for
+/// refining an image by rendering it again: with new seeds to wash out
+/// noise, or at moments spread over the shutter's opening to blur motion.
+#[derive(Clone, Debug, PartialEq)]
+pub struct FrameAccumulator {
+    sum: Canvas,
+    total_weight: f32,
+    frames: u32,
+}
+
+impl FrameAccumulator {
+    pub fn new(width: usize, height: usize) -> Self {
+        Self {
+            sum: Canvas::new(width, height),
+            total_weight: 0.0,
+            frames: 0,
+        }
+    }
+
+    pub fn add(&mut self, frame: &Canvas) {
+        self.add_weighted(frame, 1.0);
+    }
+
+    /// Adds a frame counting for `weight` times as much as one added by
+    /// `add`, such as a sub-frame taken while the shutter is wider open.
+    pub fn add_weighted(&mut self, frame: &Canvas, weight: f32) {
+        self.sum.accumulate(frame, weight);
+        self.total_weight += weight;
+        self.frames += 1;
+    }
+
+    pub fn frames(&self) -> u32 {
+        self.frames
+    }
+
+    /// The average so far, black until a frame with any weight is added.
+    pub fn average(&self) -> Canvas {
+        let mut average = self.sum.clone();
+        if self.total_weight > 0.0 {
+            average.scale(1.0 / self.total_weight);
+        }
+        average
+    }
+}
+
+#[derive(Debug)]
+pub enum ParseError {
+    Io(io::Error),
+    BadMagic(String),
+    InvalidNumber(String),
+    OutOfRange(usize),
+    TooLarge(usize, usize),
+    UnexpectedEnd,
+    /// The image parsed, but has no pixels where some are needed.
+    Empty,
+}
+
+impl Display for ParseError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ParseError::Io(e) => write!(f, "couldn't read the image: {}", e),
+            ParseError::BadMagic(magic) => write!(f, "expected P3, found {:?}", magic),
+            ParseError::InvalidNumber(token) => write!(f, "{:?} is not a valid number", token),
+            ParseError::OutOfRange(value) => {
+                write!(f, "{} is above the maximum color value", value)
+            }
+            ParseError::TooLarge(width, height) => {
+                write!(f, "a {} by {} image is too large", width, height)
+            }
+            ParseError::UnexpectedEnd => write!(f, "the image ended before all its pixels"),
+            ParseError::Empty => write!(f, "the image has no pixels"),
+        }
+    }
+}
+
+impl Error for ParseError {}
+
+impl From<io::Error> for ParseError {
+    fn from(e: io::Error) -> Self {
+        ParseError::Io(e)
+    }
 }
 
 fn split_long_ppm_line(line: &str) -> String {
@@ -89,6 +425,72 @@ mod tests {
 
     use super::*;
 
+    #[test]
+    fn accumulating_one_canvas_into_another() {
+        let mut c = Canvas::new(2, 1);
+        c.write_pixel(0, 0, Color::new(0.1, 0.2, 0.3));
+        let mut other = Canvas::new(2, 1);
+        other.write_pixel(0, 0, Color::new(1.0, 1.0, 1.0));
+        other.write_pixel(1, 0, Color::new(0.0, 0.4, 0.0));
+
+        c.accumulate(&other, 0.5);
+
+        assert_eq!(c.pixel_at(0, 0), Color::new(0.6, 0.7, 0.8));
+        assert_eq!(c.pixel_at(1, 0), Color::new(0.0, 0.2, 0.0));
+    }
+
+    #[test]
+    #[should_panic]
+    fn canvases_of_different_sizes_cant_be_accumulated() {
+        Canvas::new(2, 2).accumulate(&Canvas::new(3, 2), 1.0);
+    }
+
+    fn filled(color: Color) -> Canvas {
+        let mut c = Canvas::new(2, 2);
+        c.fill_rect(0, 0, 2, 2, color);
+        c
+    }
+
+    #[test]
+    fn accumulated_frames_are_averaged() {
+        let mut frames = FrameAccumulator::new(2, 2);
+
+        frames.add(&filled(Color::new(1.0, 0.0, 0.0)));
+        frames.add(&filled(Color::new(0.0, 0.0, 1.0)));
+
+        assert_eq!(frames.frames(), 2);
+        assert_eq!(frames.average(), filled(Color::new(0.5, 0.0, 0.5)));
+    }
+
+    #[test]
+    fn weighted_frames_count_for_more() {
+        let mut frames = FrameAccumulator::new(2, 2);
+
+        frames.add_weighted(&filled(color::WHITE), 3.0);
+        frames.add(&filled(color::BLACK));
+
+        assert_eq!(frames.average(), filled(Color::new(0.75, 0.75, 0.75)));
+    }
+
+    #[test]
+    fn an_empty_accumulator_is_black() {
+        assert_eq!(FrameAccumulator::new(2, 2).average(), Canvas::new(2, 2));
+    }
+
+    #[test]
+    fn pasting_one_canvas_into_another() {
+        let mut c = Canvas::new(4, 3);
+        let mut patch = Canvas::new(2, 2);
+        patch.write_pixel(0, 0, color::WHITE);
+        patch.write_pixel(1, 1, Color::new(1.0, 0.0, 0.0));
+
+        c.paste(3, 1, &patch);
+
+        assert_eq!(c.pixel_at(3, 1), color::WHITE);
+        assert_eq!(c.pixel_at(2, 1), color::BLACK);
+        assert_eq!(c.pixel_at(3, 2), color::BLACK);
+    }
+
     #[test]
     fn creating_a_canvas() {
         let c = Canvas::new(10, 20);
@@ -110,6 +512,26 @@ mod tests {
         assert_eq!(c.pixel_at(2, 3), red);
     }
 
+    #[test]
+    fn scaling_a_canvas() {
+        let mut c = Canvas::new(2, 1);
+        c.write_pixel(0, 0, Color::new(0.1, 0.2, 0.3));
+
+        c.scale(2.0);
+
+        assert_eq!(c.pixel_at(0, 0), Color::new(0.2, 0.4, 0.6));
+        assert_eq!(c.pixel_at(1, 0), color::BLACK);
+    }
+
+    #[test]
+    fn the_average_luminance_of_a_canvas() {
+        let mut c = Canvas::new(2, 1);
+        c.write_pixel(0, 0, Color::new(1.0, 1.0, 1.0));
+
+        assert!((c.average_luminance() - 0.5).abs() < 1e-6);
+        assert!((Canvas::new(0, 0).average_luminance()).abs() < 1e-6);
+    }
+
     #[test]
     fn constructing_the_ppm_header() {
         let c = Canvas::new(5, 3);
@@ -172,4 +594,277 @@ mod tests {
 
         assert!(ppm.ends_with('\n'));
     }
+
+    #[test]
+    fn reading_a_file_with_the_wrong_magic_number() {
+        let ppm = "P32\n1 1\n255\n0 0 0\n";
+
+        assert!(matches!(
+            Canvas::from_ppm(ppm.as_bytes()),
+            Err(ParseError::BadMagic(magic)) if magic == "P32"
+        ));
+    }
+
+    #[test]
+    fn reading_a_ppm_returns_a_canvas_of_the_right_size() {
+        let ppm = "P3\n10 2\n255\n".to_string() + &"0 0 0 ".repeat(20);
+
+        let canvas = Canvas::from_ppm(ppm.as_bytes()).unwrap();
+
+        assert_eq!(canvas.width, 10);
+        assert_eq!(canvas.height, 2);
+    }
+
+    macro_rules! ppm_pixel_tests {
+        ($($name:ident: $value:expr,)*) => {
+            $(
+                #[test]
+                fn $name() {
+                    let ((x, y), expected) = $value;
+                    let ppm = "P3\n4 3\n255\n\
+                               255 127 0  0 127 255  127 255 0  255 255 255\n\
+                               0 0 0  255 0 0  0 255 0  0 0 255\n\
+                               255 255 0  0 255 255  255 0 255  127 127 127\n";
+
+                    let canvas = Canvas::from_ppm(ppm.as_bytes()).unwrap();
+
+                    assert_eq!(canvas.pixel_at(x, y), expected);
+                }
+            )*
+        }
+    }
+
+    ppm_pixel_tests! {
+        reading_pixel_data_from_a_ppm_file_0: ((0, 0), Color::new(1.0, 0.49804, 0.0)),
+        reading_pixel_data_from_a_ppm_file_1: ((1, 0), Color::new(0.0, 0.49804, 1.0)),
+        reading_pixel_data_from_a_ppm_file_2: ((2, 0), Color::new(0.49804, 1.0, 0.0)),
+        reading_pixel_data_from_a_ppm_file_3: ((3, 0), Color::new(1.0, 1.0, 1.0)),
+        reading_pixel_data_from_a_ppm_file_4: ((0, 1), Color::new(0.0, 0.0, 0.0)),
+        reading_pixel_data_from_a_ppm_file_5: ((1, 1), Color::new(1.0, 0.0, 0.0)),
+        reading_pixel_data_from_a_ppm_file_6: ((2, 1), Color::new(0.0, 1.0, 0.0)),
+        reading_pixel_data_from_a_ppm_file_7: ((3, 1), Color::new(0.0, 0.0, 1.0)),
+        reading_pixel_data_from_a_ppm_file_8: ((0, 2), Color::new(1.0, 1.0, 0.0)),
+        reading_pixel_data_from_a_ppm_file_9: ((1, 2), Color::new(0.0, 1.0, 1.0)),
+        reading_pixel_data_from_a_ppm_file_10: ((2, 2), Color::new(1.0, 0.0, 1.0)),
+        reading_pixel_data_from_a_ppm_file_11: ((3, 2), Color::new(0.49804, 0.49804, 0.49804)),
+    }
+
+    #[test]
+    fn ppm_parsing_ignores_comment_lines() {
+        let ppm = "P3\n# this is a comment\n2 1\n# this, too\n255\n# another comment\n255 255 255\n# oh, no, comments in the pixel data!\n255 0 255\n";
+
+        let canvas = Canvas::from_ppm(ppm.as_bytes()).unwrap();
+
+        assert_eq!(canvas.pixel_at(0, 0), Color::new(1.0, 1.0, 1.0));
+        assert_eq!(canvas.pixel_at(1, 0), Color::new(1.0, 0.0, 1.0));
+    }
+
+    #[test]
+    fn ppm_parsing_allows_an_rgb_triple_to_span_lines() {
+        let ppm = "P3\n1 1\n255\n51\n153\n\n204\n";
+
+        let canvas = Canvas::from_ppm(ppm.as_bytes()).unwrap();
+
+        assert_eq!(canvas.pixel_at(0, 0), Color::new(0.2, 0.6, 0.8));
+    }
+
+    #[test]
+    fn ppm_parsing_respects_the_scale_setting() {
+        let ppm = "P3\n2 2\n100\n100 100 100  50 50 50\n75 50 25  0 0 0\n";
+
+        let canvas = Canvas::from_ppm(ppm.as_bytes()).unwrap();
+
+        assert_eq!(canvas.pixel_at(0, 1), Color::new(0.75, 0.5, 0.25));
+    }
+
+    #[test]
+    fn reading_a_ppm_with_missing_pixels() {
+        let ppm = "P3\n2 1\n255\n0 0 0 255\n";
+
+        assert!(matches!(
+            Canvas::from_ppm(ppm.as_bytes()),
+            Err(ParseError::UnexpectedEnd)
+        ));
+    }
+
+    #[test]
+    fn reading_a_ppm_whose_size_overflows() {
+        let ppm = format!("P3\n{} 2\n255\n0 0 0\n", usize::MAX);
+
+        assert!(matches!(
+            Canvas::from_ppm(ppm.as_bytes()),
+            Err(ParseError::TooLarge(_, 2))
+        ));
+    }
+
+    #[test]
+    fn reading_a_ppm_far_larger_than_its_pixel_data() {
+        let huge = "P3\n10000 10000\n255\n0 0 0\n";
+        let short = "P3\n4000 4000\n255\n0 0 0\n";
+
+        assert!(matches!(
+            Canvas::from_ppm(huge.as_bytes()),
+            Err(ParseError::TooLarge(10000, 10000))
+        ));
+        assert!(matches!(
+            Canvas::from_ppm(short.as_bytes()),
+            Err(ParseError::UnexpectedEnd)
+        ));
+    }
+
+    #[test]
+    fn reading_a_ppm_with_a_value_above_the_maximum() {
+        let ppm = "P3\n1 1\n100\n0 101 0\n";
+
+        assert!(matches!(
+            Canvas::from_ppm(ppm.as_bytes()),
+            Err(ParseError::OutOfRange(101))
+        ));
+    }
+
+    #[test]
+    fn reading_a_ppm_with_a_bad_number() {
+        let ppm = "P3\n1 one\n255\n0 0 0\n";
+
+        assert!(matches!(
+            Canvas::from_ppm(ppm.as_bytes()),
+            Err(ParseError::InvalidNumber(token)) if token == "one"
+        ));
+    }
+
+    #[test]
+    fn a_rendered_canvas_round_trips_through_ppm() {
+        let mut c = Canvas::new(3, 2);
+        c.write_pixel(0, 0, Color::new(1.0, 0.0, 0.0));
+        c.write_pixel(2, 1, Color::new(0.0, 0.0, 1.0));
+
+        let read = Canvas::from_ppm(c.to_ppm().as_bytes()).unwrap();
+
+        assert_eq!(read, c);
+    }
+
+    #[test]
+    fn exporting_a_canvas_encodes_it_as_srgb() {
+        let mut c = Canvas::new(2, 1);
+        c.write_pixel(0, 0, Color::new(0.25, 1.0, 0.0));
+        c.write_pixel(1, 0, Color::new(1.5, -0.5, 0.0031308));
+
+        assert_eq!(c.export_srgb(), "P3\n2 1\n255\n137 255 0 255 0 10\n");
+        assert_eq!(c.export_srgb(), c.to_srgb().to_ppm());
+    }
+
+    #[test]
+    fn an_srgb_picture_is_read_as_linear_colors() {
+        let ppm = "P3\n2 1\n255\n255 0 128 0 0 0\n";
+
+        let canvas = Canvas::from_srgb_ppm(ppm.as_bytes()).unwrap();
+
+        assert_eq!(
+            canvas.pixel_at(0, 0),
+            Color::from_srgb(1.0, 0.0, 128.0 / 255.0)
+        );
+        assert!(canvas.pixel_at(0, 0).blue() < 0.22);
+        assert_eq!(canvas.pixel_at(1, 0), color::BLACK);
+    }
+
+    #[test]
+    fn packing_pixels_for_a_window() {
+        let mut c = Canvas::new(2, 1);
+        c.write_pixel(0, 0, Color::new(1.5, 0.0, -0.5));
+        c.write_pixel(1, 0, Color::new(0.0, 0.5, 1.0));
+
+        assert_eq!(c.to_rgb_u32(), vec![0x00ff_0000, 0x0000_80ff]);
+    }
+
+    #[test]
+    fn grading_an_export_keeps_highlights_apart() {
+        let mut c = Canvas::new(2, 1);
+        c.write_pixel(0, 0, Color::new(1.5, 1.5, 1.5));
+        c.write_pixel(1, 0, Color::new(4.0, 4.0, 4.0));
+
+        let plain = Canvas::from_ppm(c.to_ppm().as_bytes()).unwrap();
+        let graded = Canvas::from_ppm(c.to_graded_ppm(&Grade::Filmic).as_bytes()).unwrap();
+
+        assert_eq!(plain.pixel_at(0, 0), plain.pixel_at(1, 0));
+        assert!(graded.pixel_at(0, 0).red() < graded.pixel_at(1, 0).red());
+        assert_eq!(c.to_graded_ppm(&Grade::Neutral), c.export_srgb());
+    }
+
+    fn lit(c: &Canvas) -> Vec<(usize, usize)> {
+        let mut lit = vec![];
+        for y in 0..c.height {
+            for x in 0..c.width {
+                if c.pixel_at(x, y) != color::BLACK {
+                    lit.push((x, y));
+                }
+            }
+        }
+        lit
+    }
+
+    #[test]
+    fn drawing_a_line() {
+        let mut c = Canvas::new(5, 4);
+
+        c.draw_line(4, 2, 0, 0, color::WHITE);
+
+        assert_eq!(lit(&c), vec![(0, 0), (1, 0), (2, 1), (3, 1), (4, 2)]);
+    }
+
+    #[test]
+    fn a_line_off_the_edge_is_clipped() {
+        let mut c = Canvas::new(3, 3);
+
+        c.draw_line(1, 1, 1, 10, color::WHITE);
+
+        assert_eq!(lit(&c), vec![(1, 1), (1, 2)]);
+    }
+
+    #[test]
+    fn drawing_and_filling_rectangles() {
+        let mut outline = Canvas::new(5, 5);
+        let mut filled = Canvas::new(5, 5);
+
+        outline.draw_rect(1, 1, 3, 3, color::WHITE);
+        filled.fill_rect(1, 1, 3, 3, color::WHITE);
+
+        assert_eq!(lit(&outline).len(), 8);
+        assert_eq!(outline.pixel_at(2, 2), color::BLACK);
+        assert_eq!(lit(&filled).len(), 9);
+        assert_eq!(filled.pixel_at(2, 2), color::WHITE);
+    }
+
+    #[test]
+    fn drawing_text_in_the_block_font() {
+        let mut c = Canvas::new(30, 20);
+        let red = Color::new(1.0, 0.0, 0.0);
+
+        c.draw_text(1, 2, "T\nL", 1, red);
+
+        assert_eq!(c.pixel_at(1, 2), red);
+        assert_eq!(c.pixel_at(5, 2), red);
+        assert_eq!(c.pixel_at(3, 8), red);
+        assert_eq!(c.pixel_at(1, 8), color::BLACK);
+        assert_eq!(c.pixel_at(1, 17), red);
+        assert_eq!(c.pixel_at(5, 17), red);
+        assert_eq!(lit(&c).len(), 11 + 11);
+    }
+
+    #[test]
+    fn text_is_drawn_at_a_scale() {
+        let mut c = Canvas::new(20, 20);
+
+        c.draw_text(0, 0, "-", 2, color::WHITE);
+
+        assert_eq!(lit(&c).len(), 5 * 4);
+        assert_eq!(c.pixel_at(0, 6), color::WHITE);
+        assert_eq!(c.pixel_at(9, 7), color::WHITE);
+    }
+
+    #[test]
+    fn the_size_of_a_block_of_text() {
+        assert_eq!(Canvas::text_size("FRAME 12", 1), (47, 7));
+        assert_eq!(Canvas::text_size("A\nBC", 2), (22, 32));
+        assert_eq!(Canvas::text_size("", 3), (0, 0));
+    }
 }
@@ -0,0 +1,100 @@
+use crate::{
+    ray::Ray,
+    tuple::{Point, Vector},
+};
+
+/// A plane that cuts away everything on the side its normal faces, so the
+/// inside of a scene can be shown.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct ClipPlane {
+    pub point: Point,
+    pub normal: Vector,
+}
+
+impl ClipPlane {
+    pub fn new(point: Point, normal: Vector) -> Self {
+        Self {
+            point,
+            normal: normal.normalize(),
+        }
+    }
+
+    pub fn clips(&self, point: Point) -> bool {
+        (point - self.point).dot(self.normal) > 0.0
+    }
+
+    /// The span of `t` along `ray` the plane leaves alone. It's empty,
+    /// running from infinity back to minus infinity, when the plane
+    /// clips the whole ray.
+    pub fn unclipped_range(&self, ray: Ray) -> (f32, f32) {
+        let (start, rate) = (
+            (ray.origin - self.point).dot(self.normal),
+            ray.direction.dot(self.normal),
+        );
+        let crossing = -start / rate;
+        if rate > 0.0 {
+            (f32::NEG_INFINITY, crossing)
+        } else if rate < 0.0 {
+            (crossing, f32::INFINITY)
+        } else if start <= 0.0 {
+            (f32::NEG_INFINITY, f32::INFINITY)
+        } else {
+            (f32::INFINITY, f32::NEG_INFINITY)
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::float_eq;
+
+    use super::*;
+
+    #[test]
+    fn creating_a_clip_plane_normalizes_its_normal() {
+        let plane = ClipPlane::new(Point::new(0.0, 0.0, 0.0), Vector::new(0.0, 0.0, -3.0));
+
+        assert_eq!(plane.normal, Vector::new(0.0, 0.0, -1.0));
+        assert!(float_eq(plane.normal.magnitude(), 1.0));
+    }
+
+    #[test]
+    fn the_unclipped_span_of_a_ray() {
+        let plane = ClipPlane::new(Point::new(0.0, 1.0, 0.0), Vector::new(0.0, 1.0, 0.0));
+        let up = Ray::new(Point::new(0.0, -1.0, 0.0), Vector::new(0.0, 1.0, 0.0));
+        let down = Ray::new(Point::new(0.0, 3.0, 0.0), Vector::new(0.0, -2.0, 0.0));
+        let below = Ray::new(Point::new(0.0, 0.0, 0.0), Vector::new(1.0, 0.0, 0.0));
+        let above = Ray::new(Point::new(0.0, 2.0, 0.0), Vector::new(1.0, 0.0, 0.0));
+
+        assert_eq!(plane.unclipped_range(up), (f32::NEG_INFINITY, 2.0));
+        assert_eq!(plane.unclipped_range(down), (1.0, f32::INFINITY));
+        assert_eq!(
+            plane.unclipped_range(below),
+            (f32::NEG_INFINITY, f32::INFINITY)
+        );
+        assert_eq!(
+            plane.unclipped_range(above),
+            (f32::INFINITY, f32::NEG_INFINITY)
+        );
+    }
+
+    macro_rules! clip_tests {
+        ($($name:ident: $value:expr,)*) => {
+            $(
+                #[test]
+                fn $name() {
+                    let (point, expected) = $value;
+                    let plane = ClipPlane::new(Point::new(0.0, 1.0, 0.0), Vector::new(0.0, 1.0, 0.0));
+
+                    assert_eq!(plane.clips(point), expected);
+                }
+            )*
+        }
+    }
+
+    clip_tests! {
+        a_point_in_front_of_a_clip_plane_is_clipped: (Point::new(3.0, 2.0, -1.0), true),
+        a_point_behind_a_clip_plane_is_kept: (Point::new(3.0, 0.0, -1.0), false),
+        a_point_on_a_clip_plane_is_kept: (Point::new(-5.0, 1.0, 4.0), false),
+    }
+}
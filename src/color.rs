@@ -1,17 +1,26 @@
-use std::ops::{Add, Mul, Sub};
+use std::{
+    error::Error,
+    fmt::{self, Display},
+    ops::{Add, Mul, Sub},
+};
 
 use bevy::{math::Vec4, render::color};
 
 use crate::float_eq;
 
 #[derive(Clone, Copy, Debug)]
+#[cfg_attr(
+    feature = "serde",
+    derive(serde::Serialize, serde::Deserialize),
+    serde(from = "[f32; 3]", into = "[f32; 3]")
+)]
 pub struct Color(color::Color);
 
 pub const BLACK: Color = Color(color::Color::BLACK);
 pub const WHITE: Color = Color(color::Color::WHITE);
 
 impl Color {
-    pub fn new(red: f32, green: f32, blue: f32) -> Self {
+    pub const fn new(red: f32, green: f32, blue: f32) -> Self {
         Self(color::Color::rgb(red, green, blue))
     }
 
@@ -26,8 +35,104 @@ impl Color {
     pub fn blue(self) -> f32 {
         self.0.b()
     }
+
+    /// Perceived brightness, using the Rec. 709 weights.
+    pub fn luminance(self) -> f32 {
+        0.2126 * self.red() + 0.7152 * self.green() + 0.0722 * self.blue()
+    }
+
+    /// Decodes channels given in sRGB, the space of color pickers, hex codes
+    /// and most image files, into the linear space lighting is worked out
+    /// in.
+    pub fn from_srgb(red: f32, green: f32, blue: f32) -> Self {
+        Self::new(
+            srgb_to_linear(red),
+            srgb_to_linear(green),
+            srgb_to_linear(blue),
+        )
+    }
+
+    /// Encodes the color as sRGB, for showing or saving. Negative channels
+    /// become 0, and those over 1 carry on along the curve to be clamped
+    /// on output.
+    pub fn to_srgb(self) -> Self {
+        Self::new(
+            linear_to_srgb(self.red()),
+            linear_to_srgb(self.green()),
+            linear_to_srgb(self.blue()),
+        )
+    }
+
+    /// A color from 0-255 channels taken as they are, with no decoding.
+    pub fn from_u8(red: u8, green: u8, blue: u8) -> Self {
+        Self::new(
+            red as f32 / 255.0,
+            green as f32 / 255.0,
+            blue as f32 / 255.0,
+        )
+    }
+
+    /// Parses `#rrggbb` or the `#rgb` shorthand, decoding it from sRGB as
+    /// hex codes are always written. The `#` is optional.
+    pub fn from_hex(hex: &str) -> Result<Self, ParseColorError> {
+        let digits = hex.strip_prefix('#').unwrap_or(hex);
+        if let Some(c) = digits.chars().find(|c| !c.is_ascii_hexdigit()) {
+            return Err(ParseColorError::InvalidDigit(c));
+        }
+
+        let channel = |i: usize, len: usize| {
+            let value = u8::from_str_radix(&digits[i * len..(i + 1) * len], 16).unwrap();
+            if len == 1 {
+                value * 17
+            } else {
+                value
+            }
+        };
+        let encoded = match digits.len() {
+            3 => Self::from_u8(channel(0, 1), channel(1, 1), channel(2, 1)),
+            6 => Self::from_u8(channel(0, 2), channel(1, 2), channel(2, 2)),
+            len => return Err(ParseColorError::InvalidLength(len)),
+        };
+        Ok(Self::from_srgb(
+            encoded.red(),
+            encoded.green(),
+            encoded.blue(),
+        ))
+    }
+
+    /// Formats as `#rrggbb`, encoding as sRGB and clamping each channel to
+    /// [0, 1].
+    pub fn to_hex(self) -> String {
+        let channel = |c: f32| (c.clamp(0.0, 1.0) * 255.0).round() as u8;
+        let encoded = self.to_srgb();
+        format!(
+            "#{:02x}{:02x}{:02x}",
+            channel(encoded.red()),
+            channel(encoded.green()),
+            channel(encoded.blue())
+        )
+    }
 }
 
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ParseColorError {
+    InvalidLength(usize),
+    InvalidDigit(char),
+}
+
+impl Display for ParseColorError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ParseColorError::InvalidLength(len) => {
+                write!(f, "expected 3 or 6 hex digits, found {}", len)
+            }
+            ParseColorError::InvalidDigit(c) => write!(f, "{:?} is not a hex digit", c),
+        }
+    }
+}
+
+impl Error for ParseColorError {}
+
 impl Default for Color {
     fn default() -> Self {
         BLACK
@@ -82,6 +187,34 @@ impl Mul for Color {
     }
 }
 
+impl From<[f32; 3]> for Color {
+    fn from([red, green, blue]: [f32; 3]) -> Self {
+        Color::new(red, green, blue)
+    }
+}
+
+impl From<Color> for [f32; 3] {
+    fn from(color: Color) -> Self {
+        [color.red(), color.green(), color.blue()]
+    }
+}
+
+fn srgb_to_linear(c: f32) -> f32 {
+    if c <= 0.04045 {
+        c / 12.92
+    } else {
+        ((c + 0.055) / 1.055).powf(2.4)
+    }
+}
+
+fn linear_to_srgb(c: f32) -> f32 {
+    if c <= 0.0031308 {
+        c.max(0.0) * 12.92
+    } else {
+        1.055 * c.powf(1.0 / 2.4) - 0.055
+    }
+}
+
 fn hadamard_product(c1: Color, c2: Color) -> Color {
     Color(color::Color::rgb(
         c1.0.r() * c2.0.r(),
@@ -131,4 +264,95 @@ mod tests {
 
         assert_eq!(c1 * c2, Color::new(0.9, 0.2, 0.04));
     }
+
+    #[test]
+    fn the_luminance_of_a_color() {
+        assert!(float_eq(Color::new(1.0, 1.0, 1.0).luminance(), 1.0));
+        assert!(float_eq(Color::new(0.0, 1.0, 0.0).luminance(), 0.7152));
+        assert!(float_eq(BLACK.luminance(), 0.0));
+    }
+
+    #[test]
+    fn a_color_from_bytes() {
+        let c = Color::from_u8(255, 0, 51);
+
+        assert_eq!(c, Color::new(1.0, 0.0, 0.2));
+    }
+
+    macro_rules! from_hex_tests {
+        ($($name:ident: $value:expr,)*) => {
+            $(
+                #[test]
+                fn $name() {
+                    let (hex, expected) = $value;
+
+                    assert_eq!(Color::from_hex(hex), expected);
+                }
+            )*
+        }
+    }
+
+    fn from_srgb_u8(red: u8, green: u8, blue: u8) -> Color {
+        let encoded = Color::from_u8(red, green, blue);
+        Color::from_srgb(encoded.red(), encoded.green(), encoded.blue())
+    }
+
+    from_hex_tests! {
+        parsing_a_hex_color: ("#1e90ff", Ok(from_srgb_u8(30, 144, 255))),
+        parsing_a_hex_color_without_a_hash: ("1E90FF", Ok(from_srgb_u8(30, 144, 255))),
+        parsing_a_shorthand_hex_color: ("#f0a", Ok(from_srgb_u8(255, 0, 170))),
+        parsing_a_hex_color_of_the_wrong_length: ("#1e90f", Err(ParseColorError::InvalidLength(5))),
+        parsing_an_empty_hex_color: ("#", Err(ParseColorError::InvalidLength(0))),
+        parsing_a_hex_color_with_a_bad_digit: ("#1g90ff", Err(ParseColorError::InvalidDigit('g'))),
+        parsing_a_hex_color_with_a_multibyte_char: ("#1é90f", Err(ParseColorError::InvalidDigit('é'))),
+    }
+
+    #[test]
+    fn formatting_a_color_as_hex() {
+        assert_eq!(from_srgb_u8(30, 144, 255).to_hex(), "#1e90ff");
+        assert_eq!(Color::new(1.5, -0.5, 0.5).to_hex(), "#ff00bc");
+    }
+
+    #[test]
+    fn hex_colors_are_decoded_from_srgb() {
+        let grey = Color::from_hex("#808080").unwrap();
+
+        assert!((grey.red() - 0.2159).abs() < 0.0001);
+        assert_eq!(Color::from_hex("#ffffff"), Ok(WHITE));
+    }
+
+    macro_rules! srgb_tests {
+        ($($name:ident: $value:expr,)*) => {
+            $(
+                #[test]
+                fn $name() {
+                    let (encoded, linear) = $value;
+                    let decoded = Color::from_srgb(encoded, encoded, encoded).red();
+                    let reencoded = Color::new(linear, linear, linear).to_srgb().red();
+
+                    assert!((decoded - linear).abs() < 0.0001);
+                    assert!((reencoded - encoded).abs() < 0.0001);
+                }
+            )*
+        }
+    }
+
+    srgb_tests! {
+        srgb_black_is_linear_black: (0.0, 0.0),
+        srgb_white_is_linear_white: (1.0, 1.0),
+        srgb_mid_grey_is_darker_in_linear: (0.5, 0.21404),
+        srgb_is_a_straight_line_near_black: (0.02, 0.02 / 12.92),
+    }
+
+    #[test]
+    fn negative_channels_encode_as_black() {
+        assert_eq!(Color::new(-0.5, 0.0, 0.0).to_srgb(), BLACK);
+    }
+
+    #[test]
+    fn hex_colors_round_trip() {
+        let c = Color::from_hex("#c0ffee").unwrap();
+
+        assert_eq!(c.to_hex(), "#c0ffee");
+    }
 }
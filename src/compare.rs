@@ -0,0 +1,210 @@
+use crate::{
+    camera::Camera,
+    canvas::Canvas,
+    color::{self, Color},
+    integrator::Integrator,
+    world::World,
+};
+
+/// Two renders of the same view, for seeing what a change to the scene or
+/// the render settings did to the image.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Comparison {
+    pub a: Canvas,
+    pub b: Canvas,
+}
+
+impl Comparison {
+    pub fn new(a: Canvas, b: Canvas) -> Self {
+        assert!(
+            a.width == b.width && a.height == b.height,
+            "only images of the same size can be compared"
+        );
+        Self { a, b }
+    }
+
+    /// The view from `camera` rendered against each world.
+    pub fn of_worlds(camera: &Camera, a: &World, b: &World) -> Self {
+        Self::new(camera.render(a), camera.render(b))
+    }
+
+    /// `world` rendered once with each camera and integrator. The cameras
+    /// should share a size and view, differing only in their settings.
+    pub fn of_settings(
+        world: &World,
+        a: (&Camera, &dyn Integrator),
+        b: (&Camera, &dyn Integrator),
+    ) -> Self {
+        Self::new(a.0.render_with(world, a.1), b.0.render_with(world, b.1))
+    }
+
+    /// The largest difference in any channel of any pixel.
+    pub fn max_difference(&self) -> f32 {
+        self.differences().fold(0.0, f32::max)
+    }
+
+    /// How many pixels differ by more than `tolerance` in some channel.
+    pub fn differing_pixels(&self, tolerance: f32) -> usize {
+        self.differences().filter(|&d| d > tolerance).count()
+    }
+
+    /// `a` left of column `at` and `b` from there on, with a white line
+    /// down the seam and each side labelled.
+    pub fn split(&self, at: usize) -> Canvas {
+        let mut image = self.b.clone();
+        for y in 0..image.height {
+            for x in 0..at.min(image.width) {
+                image.write_pixel(x, y, self.a.pixel_at(x, y));
+            }
+        }
+        image.draw_line(at, 0, at, image.height.saturating_sub(1), color::WHITE);
+        image.draw_text(2, 2, "A", 1, color::WHITE);
+        image.draw_text(at + 3, 2, "B", 1, color::WHITE);
+        image
+    }
+
+    /// `a` and `b` next to each other, labelled.
+    pub fn side_by_side(&self) -> Canvas {
+        let width = self.a.width;
+        let mut image = Canvas::new(2 * width, self.a.height);
+        image.paste(0, 0, &self.a);
+        image.paste(width, 0, &self.b);
+        image.draw_text(2, 2, "A", 1, color::WHITE);
+        image.draw_text(width + 2, 2, "B", 1, color::WHITE);
+        image
+    }
+
+    /// Each pixel's largest channel difference as a heat map running from
+    /// black through red and yellow to white, which it reaches at a
+    /// difference of `scale`. Identical pixels stay black.
+    pub fn difference(&self, scale: f32) -> Canvas {
+        let mut image = Canvas::new(self.a.width, self.a.height);
+        for y in 0..image.height {
+            for x in 0..image.width {
+                let d = channel_difference(self.a.pixel_at(x, y), self.b.pixel_at(x, y));
+                image.write_pixel(x, y, heat((d / scale).clamp(0.0, 1.0)));
+            }
+        }
+        image
+    }
+
+    fn differences(&self) -> impl Iterator<Item = f32> + '_ {
+        (0..self.a.height).flat_map(move |y| {
+            (0..self.a.width)
+                .map(move |x| channel_difference(self.a.pixel_at(x, y), self.b.pixel_at(x, y)))
+        })
+    }
+}
+
+fn channel_difference(a: Color, b: Color) -> f32 {
+    let d = a - b;
+    d.red().abs().max(d.green().abs()).max(d.blue().abs())
+}
+
+fn heat(t: f32) -> Color {
+    Color::new(
+        (3.0 * t).min(1.0),
+        (3.0 * t - 1.0).clamp(0.0, 1.0),
+        (3.0 * t - 2.0).clamp(0.0, 1.0),
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{
+        integrator::{DebugView, Whitted},
+        tuple::{Point, Vector},
+    };
+
+    use super::*;
+
+    fn filled(width: usize, height: usize, color: Color) -> Canvas {
+        let mut c = Canvas::new(width, height);
+        c.fill_rect(0, 0, width, height, color);
+        c
+    }
+
+    fn camera() -> Camera {
+        Camera::new(11, 11, std::f32::consts::PI / 2.0).look_at(
+            Point::new(0.0, 0.0, -5.0),
+            Point::new(0.0, 0.0, 0.0),
+            Vector::new(0.0, 1.0, 0.0),
+        )
+    }
+
+    #[test]
+    fn identical_renders_show_no_difference() {
+        let world = World::default();
+
+        let comparison = Comparison::of_worlds(&camera(), &world, &world);
+
+        assert_eq!(comparison.max_difference(), 0.0);
+        assert_eq!(comparison.differing_pixels(0.0), 0);
+        assert_eq!(comparison.difference(0.1), Canvas::new(11, 11));
+    }
+
+    #[test]
+    fn comparing_two_integrators() {
+        let world = World::default();
+        let camera = camera();
+
+        let comparison = Comparison::of_settings(
+            &world,
+            (&camera, &Whitted::new(5)),
+            (&camera, &DebugView::Normals),
+        );
+
+        assert!(comparison.max_difference() > 0.0);
+        assert!(comparison.differing_pixels(0.01) > 0);
+        assert_eq!(comparison.a, camera.render(&world));
+    }
+
+    #[test]
+    fn the_difference_heat_map() {
+        let mut b = Canvas::new(3, 1);
+        b.write_pixel(1, 0, Color::new(0.0, 0.1, 0.0));
+        b.write_pixel(2, 0, Color::new(0.0, 0.0, -0.5));
+        let comparison = Comparison::new(Canvas::new(3, 1), b);
+
+        let heat_map = comparison.difference(0.3);
+
+        assert_eq!(heat_map.pixel_at(0, 0), color::BLACK);
+        assert_eq!(heat_map.pixel_at(1, 0), Color::new(1.0, 0.0, 0.0));
+        assert_eq!(heat_map.pixel_at(2, 0), color::WHITE);
+        assert_eq!(comparison.max_difference(), 0.5);
+        assert_eq!(comparison.differing_pixels(0.2), 1);
+    }
+
+    #[test]
+    fn a_split_shows_each_image_either_side_of_the_seam() {
+        let red = Color::new(1.0, 0.0, 0.0);
+        let blue = Color::new(0.0, 0.0, 1.0);
+        let comparison = Comparison::new(filled(20, 12, red), filled(20, 12, blue));
+
+        let split = comparison.split(10);
+
+        assert_eq!(split.pixel_at(9, 11), red);
+        assert_eq!(split.pixel_at(10, 11), color::WHITE);
+        assert_eq!(split.pixel_at(11, 11), blue);
+        assert_eq!(split.pixel_at(2, 5), color::WHITE);
+    }
+
+    #[test]
+    fn side_by_side_images() {
+        let red = Color::new(1.0, 0.0, 0.0);
+        let comparison = Comparison::new(filled(8, 10, red), Canvas::new(8, 10));
+
+        let image = comparison.side_by_side();
+
+        assert_eq!((image.width, image.height), (16, 10));
+        assert_eq!(image.pixel_at(7, 9), red);
+        assert_eq!(image.pixel_at(8, 9), color::BLACK);
+        assert_eq!(image.pixel_at(10, 5), color::WHITE);
+    }
+
+    #[test]
+    #[should_panic]
+    fn images_of_different_sizes_cant_be_compared() {
+        Comparison::new(Canvas::new(2, 2), Canvas::new(3, 2));
+    }
+}
@@ -0,0 +1,156 @@
+use std::{fmt::Debug, io::Read};
+
+use crate::{
+    canvas::{Canvas, ParseError},
+    color::Color,
+    patterns::uv::UvMapping,
+    transformations::Transform,
+    tuple::Vector,
+};
+
+/// What lies beyond the scene, seen by any ray that escapes without
+/// hitting anything.
+pub trait Environment: Debug {
+    fn color_in(&self, direction: Vector) -> Color;
+}
+
+/// A plain color in every direction.
+impl Environment for Color {
+    fn color_in(&self, _direction: Vector) -> Color {
+        *self
+    }
+}
+
+/// A panorama wrapped around the scene, with its width running once around
+/// the horizon and its height from straight up to straight down. The
+/// middle of the image lies along +z.
+#[derive(Clone, Debug, PartialEq)]
+pub struct ImageEnvironment {
+    pub image: Canvas,
+    /// Scales the image, so a low dynamic range picture can still light a
+    /// scene as brightly as the sky would.
+    pub intensity: f32,
+    pub transform: Transform,
+}
+
+impl ImageEnvironment {
+    /// Wraps `image` around the scene. It can't be empty, as every
+    /// direction needs a pixel to look up.
+    pub fn new(image: Canvas) -> Self {
+        assert!(
+            image.width > 0 && image.height > 0,
+            "an image environment needs at least one pixel"
+        );
+        Self {
+            image,
+            intensity: 1.0,
+            transform: Transform::default(),
+        }
+    }
+
+    /// Reads the panorama from an sRGB PPM, decoding it into linear colors.
+    pub fn from_ppm(reader: impl Read) -> Result<Self, ParseError> {
+        let image = Canvas::from_srgb_ppm(reader)?;
+        if image.width == 0 || image.height == 0 {
+            return Err(ParseError::Empty);
+        }
+        Ok(Self::new(image))
+    }
+
+    pub fn intensity(self, intensity: f32) -> Self {
+        Self { intensity, ..self }
+    }
+
+    /// Turns the panorama, e.g. to bring the sun round behind the camera.
+    pub fn transform(self, transform: Transform) -> Self {
+        Self { transform, ..self }
+    }
+}
+
+impl Environment for ImageEnvironment {
+    fn color_in(&self, direction: Vector) -> Color {
+        let local = (self.transform.inverse() * direction).normalize();
+        let (u, v) = UvMapping::Spherical.map(local.to_point());
+        let (width, height) = (self.image.width, self.image.height);
+        let x = ((u * width as f32) as usize).min(width - 1);
+        let y = (((1.0 - v) * height as f32) as usize).min(height - 1);
+
+        self.image.pixel_at(x, y) * self.intensity
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::f32::consts::PI;
+
+    use crate::color;
+
+    use super::*;
+
+    /// Four columns, one for each side of the horizon, with a white top row
+    /// and a black bottom one.
+    fn compass() -> ImageEnvironment {
+        ImageEnvironment::from_ppm(
+            "P3 4 3 255
+             255 255 255  255 255 255  255 255 255  255 255 255
+             255 0 0  0 255 0  0 0 255  255 255 0
+             0 0 0  0 0 0  0 0 0  0 0 0"
+                .as_bytes(),
+        )
+        .unwrap()
+    }
+
+    macro_rules! image_environment_tests {
+        ($($name:ident: $value:expr,)*) => {
+            $(
+                #[test]
+                fn $name() {
+                    let (direction, expected) = $value;
+
+                    assert_eq!(compass().color_in(direction), expected);
+                }
+            )*
+        }
+    }
+
+    image_environment_tests! {
+        looking_along_negative_z: (Vector::new(0.0, 0.0, -1.0), Color::new(1.0, 0.0, 0.0)),
+        looking_along_positive_x: (Vector::new(1.0, 0.0, 0.0), Color::new(0.0, 1.0, 0.0)),
+        looking_along_positive_z: (Vector::new(0.0, 0.0, 1.0), Color::new(0.0, 0.0, 1.0)),
+        looking_along_negative_x: (Vector::new(-1.0, 0.0, 0.0), Color::new(1.0, 1.0, 0.0)),
+        looking_straight_up: (Vector::new(0.0, 5.0, 0.0), color::WHITE),
+        looking_straight_down: (Vector::new(0.0, -1.0, 0.0), color::BLACK),
+    }
+
+    #[test]
+    #[should_panic(expected = "an image environment needs at least one pixel")]
+    fn an_image_environment_cant_be_empty() {
+        ImageEnvironment::new(Canvas::new(0, 4));
+    }
+
+    #[test]
+    fn reading_an_empty_image_environment_fails() {
+        let result = ImageEnvironment::from_ppm("P3 4 0 255".as_bytes());
+
+        assert!(matches!(result, Err(ParseError::Empty)));
+    }
+
+    #[test]
+    fn a_plain_color_is_the_same_everywhere() {
+        let sky = Color::new(0.2, 0.4, 0.8);
+
+        assert_eq!(sky.color_in(Vector::new(1.0, 2.0, 3.0)), sky);
+    }
+
+    #[test]
+    fn brightening_and_turning_an_image_environment() {
+        let env = compass()
+            .intensity(2.0)
+            .transform(Transform::rotation_y(PI / 2.0));
+
+        assert_eq!(
+            env.color_in(Vector::new(1.0, 0.0, 0.0)),
+            Color::new(0.0, 0.0, 2.0)
+        );
+    }
+}
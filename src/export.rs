@@ -0,0 +1,263 @@
+use crate::{
+    color::Color,
+    materials::Material,
+    shapes::Shape,
+    transformations::{Transform, IDENTITY},
+    world::World,
+};
+
+/// A world's shapes as a Wavefront OBJ file, plus the MTL library holding
+/// their materials, for carrying scenes over into other 3D tools.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct ObjExport {
+    pub obj: String,
+    pub mtl: String,
+}
+
+impl ObjExport {
+    /// Tessellates every bounded shape in `world` at `resolution`, with
+    /// group transforms and inherited materials baked in. The OBJ loads its
+    /// materials from `mtl_name`. Planes and other infinite shapes are left
+    /// out, and patterns only survive as the material's base color.
+    pub fn from_world(world: &World, resolution: usize, mtl_name: &str) -> Self {
+        let mut exporter = Exporter {
+            world,
+            resolution,
+            obj: format!("mtllib {}\n", mtl_name),
+            materials: vec![],
+            vertex_count: 0,
+            object_count: 0,
+        };
+        for object in world.objects() {
+            exporter.export(object.as_ref(), IDENTITY);
+        }
+
+        Self {
+            obj: exporter.obj,
+            mtl: exporter
+                .materials
+                .iter()
+                .enumerate()
+                .map(|(i, material)| mtl_entry(i, material))
+                .collect::<Vec<_>>()
+                .join("\n"),
+        }
+    }
+}
+
+struct Exporter<'a> {
+    world: &'a World,
+    resolution: usize,
+    obj: String,
+    materials: Vec<&'a Material>,
+    vertex_count: usize,
+    object_count: usize,
+}
+
+impl<'a> Exporter<'a> {
+    fn export(&mut self, shape: &'a dyn Shape, parent: Transform) {
+        let transform = parent * *shape.transform();
+        for child in shape.children() {
+            self.export(child.as_ref(), transform);
+        }
+
+        let mesh = match shape.tessellate(self.resolution) {
+            Some(mesh) => mesh,
+            None => return,
+        };
+
+        self.object_count += 1;
+        let material = self.material_index(self.world.material_of(shape));
+        self.obj.push_str(&format!(
+            "o {}{}\nusemtl {}\n",
+            shape.type_name().to_lowercase(),
+            self.object_count,
+            material_name(material)
+        ));
+        for &vertex in &mesh.vertices {
+            let p = transform * vertex;
+            self.obj
+                .push_str(&format!("v {} {} {}\n", p.x(), p.y(), p.z()));
+        }
+        for &[a, b, c] in &mesh.faces {
+            let offset = self.vertex_count + 1;
+            self.obj
+                .push_str(&format!("f {} {} {}\n", a + offset, b + offset, c + offset));
+        }
+        self.vertex_count += mesh.vertices.len();
+    }
+
+    fn material_index(&mut self, material: &'a Material) -> usize {
+        match self.materials.iter().position(|&m| m == material) {
+            Some(i) => i,
+            None => {
+                self.materials.push(material);
+                self.materials.len() - 1
+            }
+        }
+    }
+}
+
+fn material_name(index: usize) -> String {
+    format!("material{}", index + 1)
+}
+
+fn mtl_color(key: &str, color: Color) -> String {
+    format!(
+        "{} {} {} {}\n",
+        key,
+        color.red(),
+        color.green(),
+        color.blue()
+    )
+}
+
+fn mtl_entry(index: usize, material: &Material) -> String {
+    let specular = Color::new(material.specular, material.specular, material.specular);
+    [
+        format!("newmtl {}\n", material_name(index)),
+        mtl_color("Ka", material.color * material.ambient),
+        mtl_color("Kd", material.color * material.diffuse),
+        mtl_color("Ks", specular),
+        mtl_color("Ke", material.emissive),
+        format!("Ns {}\n", material.shininess),
+        format!("Ni {}\n", material.refractive_index),
+        format!("d {}\n", 1.0 - material.transparency),
+        "illum 2\n".to_string(),
+    ]
+    .concat()
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{
+        lights::PointLight,
+        shapes::{
+            cube::Cube, group::Group, plane::Plane, sphere::Sphere, triangle::Triangle,
+            ShapeBuilder,
+        },
+        tuple::Point,
+    };
+
+    use super::*;
+
+    fn lines_starting<'a>(text: &'a str, prefix: &str) -> Vec<&'a str> {
+        text.lines().filter(|l| l.starts_with(prefix)).collect()
+    }
+
+    #[test]
+    fn exporting_an_empty_world() {
+        let export = ObjExport::from_world(&World::new(PointLight::default()), 16, "scene.mtl");
+
+        assert_eq!(export.obj, "mtllib scene.mtl\n");
+        assert_eq!(export.mtl, "");
+    }
+
+    #[test]
+    fn exporting_a_triangle() {
+        let t = Triangle::new(
+            Point::new(0.0, 1.0, 0.0),
+            Point::new(-1.0, 0.0, 0.0),
+            Point::new(1.0, 0.0, 0.0),
+        )
+        .with_transform(Transform::translation(0.0, 0.0, 2.0));
+        let w = World::new(PointLight::default()).object(Box::new(t));
+
+        let export = ObjExport::from_world(&w, 16, "scene.mtl");
+
+        assert_eq!(
+            export.obj,
+            "mtllib scene.mtl\n\
+             o triangle1\n\
+             usemtl material1\n\
+             v 0 1 2\n\
+             v -1 0 2\n\
+             v 1 0 2\n\
+             f 1 2 3\n"
+        );
+    }
+
+    #[test]
+    fn faces_index_past_earlier_objects_vertices() {
+        let w = World::new(PointLight::default())
+            .object(Box::new(Cube::default()))
+            .object(Box::new(Cube::default()));
+
+        let export = ObjExport::from_world(&w, 16, "scene.mtl");
+        let faces = lines_starting(&export.obj, "f ");
+
+        assert_eq!(lines_starting(&export.obj, "v ").len(), 16);
+        assert_eq!(faces.len(), 24);
+        assert_eq!(faces[12], "f 10 14 16");
+    }
+
+    #[test]
+    fn infinite_shapes_are_left_out() {
+        let w = World::new(PointLight::default())
+            .object(Box::new(Plane::default()))
+            .object(Box::new(Sphere::default()));
+
+        let export = ObjExport::from_world(&w, 8, "scene.mtl");
+
+        assert_eq!(lines_starting(&export.obj, "o "), vec!["o sphere1"]);
+    }
+
+    #[test]
+    fn group_transforms_are_baked_into_vertices() {
+        let mut g = Group::new().with_transform(Transform::translation(10.0, 0.0, 0.0));
+        g.add_child(Box::new(
+            Cube::default().with_transform(Transform::scaling(2.0, 2.0, 2.0)),
+        ));
+        let w = World::new(PointLight::default()).object(Box::new(g));
+
+        let export = ObjExport::from_world(&w, 16, "scene.mtl");
+
+        assert_eq!(lines_starting(&export.obj, "v ")[0], "v 8 -2 -2");
+    }
+
+    #[test]
+    fn shared_materials_are_written_once() {
+        let red = Material::default().color(Color::new(1.0, 0.0, 0.0));
+        let mut g = Group::new().with_material(red.clone());
+        g.add_child(Box::new(Sphere::default()));
+        let w = World::new(PointLight::default())
+            .object(Box::new(g))
+            .object(Box::new(Cube::default().with_material(red)))
+            .object(Box::new(Cube::default()));
+
+        let export = ObjExport::from_world(&w, 8, "scene.mtl");
+
+        assert_eq!(
+            lines_starting(&export.obj, "usemtl"),
+            vec!["usemtl material1", "usemtl material1", "usemtl material2"]
+        );
+        assert_eq!(lines_starting(&export.mtl, "newmtl").len(), 2);
+    }
+
+    #[test]
+    fn materials_map_onto_mtl_fields() {
+        let m = Material::default()
+            .color(Color::new(1.0, 0.5, 0.0))
+            .ambient(0.2)
+            .diffuse(0.5)
+            .specular(0.25)
+            .transparency(0.75);
+        let w =
+            World::new(PointLight::default()).object(Box::new(Cube::default().with_material(m)));
+
+        let export = ObjExport::from_world(&w, 16, "scene.mtl");
+
+        assert_eq!(
+            export.mtl,
+            "newmtl material1\n\
+             Ka 0.2 0.1 0\n\
+             Kd 0.5 0.25 0\n\
+             Ks 0.25 0.25 0.25\n\
+             Ke 0 0 0\n\
+             Ns 200\n\
+             Ni 1\n\
+             d 0.25\n\
+             illum 2\n"
+        );
+    }
+}
@@ -0,0 +1,201 @@
+//! Scenes for benchmarking, each built the same way every time so timings
+//! can be compared from run to run. They all fit the view from `camera`.
+
+use std::f32::consts::PI;
+
+use crate::{
+    camera::Camera,
+    color::{self, Color},
+    lights::PointLight,
+    materials::Material,
+    patterns::checkered::Checkered,
+    prefabs,
+    shapes::{group::Group, mesh::Mesh, plane::Plane, sphere::Sphere, ShapeBuilder},
+    transformations::Transform,
+    tuple::{Point, Vector},
+    world::World,
+};
+
+/// Triangles in the `triangle_mesh` scene.
+pub const MESH_TRIANGLES: usize = 2 * MESH_COLUMNS * MESH_ROWS;
+const MESH_COLUMNS: usize = 100;
+const MESH_ROWS: usize = 50;
+
+/// The view every fixture is framed for.
+pub fn camera(hsize: usize, vsize: usize) -> Camera {
+    Camera::new(hsize, vsize, PI / 3.0).transform(Transform::view_transform(
+        Point::new(0.0, 3.0, -7.0),
+        Point::new(0.0, 0.5, 0.0),
+        Vector::new(0.0, 1.0, 0.0),
+    ))
+}
+
+fn light() -> PointLight {
+    PointLight::new(Point::new(-10.0, 10.0, -10.0), color::WHITE)
+}
+
+fn checkered_floor() -> Plane {
+    Plane::default().with_material(
+        Material::default()
+            .pattern(Box::new(Checkered::new(
+                Color::new(0.9, 0.9, 0.9),
+                Color::new(0.2, 0.2, 0.2),
+            )))
+            .specular(0.0),
+    )
+}
+
+/// A 10 by 10 grid of plain spheres and nothing else, for the cost of
+/// intersecting and shading the simplest shape.
+pub fn spheres() -> World {
+    (0..100).fold(World::new(light()), |world, i| {
+        let (column, row) = ((i % 10) as f32, (i / 10) as f32);
+        world.object(Box::new(
+            Sphere::default()
+                .with_transform(
+                    Transform::translation(column * 0.8 - 3.2, 0.3, row * 0.8 - 3.6)
+                        * Transform::scaling(0.3, 0.3, 0.3),
+                )
+                .with_material(Material::default().color(Color::new(column / 9.0, 0.5, row / 9.0))),
+        ))
+    })
+}
+
+/// Nine glass spheres, one holding an air bubble, over a checkered floor,
+/// where almost every ray spawns reflected and refracted ones.
+pub fn glass() -> World {
+    let glass = Material::default()
+        .color(Color::new(0.1, 0.1, 0.1))
+        .diffuse(0.1)
+        .specular(1.0)
+        .shininess(300.0)
+        .reflective(0.9)
+        .transparency(0.9)
+        .refractive_index(1.5);
+
+    let world = (0..9).fold(
+        World::new(light()).object(Box::new(checkered_floor())),
+        |world, i| {
+            let (x, z) = ((i % 3) as f32 * 2.0 - 2.0, (i / 3) as f32 * 2.0 - 2.0);
+            world.object(Box::new(
+                Sphere::default()
+                    .with_transform(
+                        Transform::translation(x, 0.8, z) * Transform::scaling(0.8, 0.8, 0.8),
+                    )
+                    .with_material(glass.clone()),
+            ))
+        },
+    );
+    world.object(Box::new(
+        Sphere::default()
+            .with_transform(
+                Transform::translation(0.0, 0.8, 0.0) * Transform::scaling(0.4, 0.4, 0.4),
+            )
+            .with_material(glass.refractive_index(1.0)),
+    ))
+}
+
+/// A rippled sheet of `MESH_TRIANGLES` smooth-shaded triangles, indexed by
+/// a bounding volume hierarchy, for the cost of triangles and the index.
+pub fn triangle_mesh() -> World {
+    let vertices = (0..=MESH_ROWS)
+        .flat_map(|row| {
+            (0..=MESH_COLUMNS).map(move |column| {
+                let x = column as f32 / MESH_COLUMNS as f32 * 8.0 - 4.0;
+                let z = row as f32 / MESH_ROWS as f32 * 6.0 - 3.0;
+                Point::new(x, 0.5 + 0.3 * (x * 2.0).sin() * (z * 1.5).cos(), z)
+            })
+        })
+        .collect();
+    let index = |column: usize, row: usize| row * (MESH_COLUMNS + 1) + column;
+    let faces = (0..MESH_ROWS)
+        .flat_map(|row| {
+            (0..MESH_COLUMNS).flat_map(move |column| {
+                let (a, b) = (index(column, row), index(column + 1, row));
+                let (c, d) = (index(column + 1, row + 1), index(column, row + 1));
+                vec![[a, b, c], [a, c, d]]
+            })
+        })
+        .collect();
+    let mesh = Mesh::new(vertices, faces)
+        .material(Material::default().color(Color::new(0.3, 0.6, 0.9)))
+        .smooth_normals();
+
+    let mut world = mesh
+        .triangles()
+        .into_iter()
+        .fold(World::new(light()), |world, triangle| {
+            world.object(Box::new(triangle))
+        });
+    world.build_index();
+    world
+}
+
+/// A three-level hierarchy of groups, nine clusters of four of the book's
+/// hexagons, for the cost of walking nested groups and the hierarchies over
+/// their children.
+pub fn grouped() -> World {
+    let mut scene = Group::new();
+    for i in 0..9 {
+        let mut cluster = Group::new().with_transform(Transform::translation(
+            (i % 3) as f32 * 2.6 - 2.6,
+            0.25,
+            (i / 3) as f32 * 2.6 - 2.6,
+        ));
+        for j in 0..4 {
+            cluster.add_child(Box::new(prefabs::hexagon().with_transform(
+                Transform::translation(0.0, j as f32 * 0.5, 0.0)
+                    * Transform::rotation_y(j as f32 * PI / 12.0)
+                    * Transform::scaling(0.5, 0.5, 0.5),
+            )));
+        }
+        scene.add_child(Box::new(cluster));
+    }
+
+    World::new(light())
+        .object(Box::new(checkered_floor()))
+        .object(Box::new(scene))
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::shapes::Shape;
+
+    use super::*;
+
+    fn leaves(shapes: &[Box<dyn Shape>]) -> usize {
+        shapes
+            .iter()
+            .map(|s| match s.children() {
+                [] => 1,
+                children => leaves(children),
+            })
+            .sum()
+    }
+
+    #[test]
+    fn the_fixtures_hold_what_they_say() {
+        assert_eq!(leaves(spheres().objects()), 100);
+        assert_eq!(leaves(glass().objects()), 11);
+        assert_eq!(leaves(triangle_mesh().objects()), MESH_TRIANGLES);
+        assert_eq!(MESH_TRIANGLES, 10_000);
+        assert_eq!(leaves(grouped().objects()), 1 + 9 * 4 * 12);
+    }
+
+    #[test]
+    fn every_fixture_is_in_view() {
+        let camera = camera(16, 12);
+
+        for world in [spheres(), glass(), triangle_mesh(), grouped()].iter() {
+            let ray = camera.ray_for_pixel(8, 6);
+            assert!(!world.intersect(ray).is_empty());
+        }
+    }
+
+    #[test]
+    fn fixtures_are_built_the_same_every_time() {
+        let camera = camera(8, 6);
+
+        assert_eq!(camera.render(&glass()), camera.render(&glass()));
+    }
+}
@@ -0,0 +1,316 @@
+use crate::{
+    color::Color,
+    ray::Ray,
+    shapes::quad::Quad,
+    tuple::{Point, Vector},
+    world::World,
+};
+
+const LIGHT_COLOR: Color = Color::new(1.0, 1.0, 0.6);
+const PORTAL_COLOR: Color = Color::new(0.3, 0.9, 1.0);
+const FRUSTUM_COLOR: Color = Color::new(1.0, 0.4, 0.9);
+
+/// A marker drawn over a debug render to show where something that can't
+/// otherwise be seen sits in the scene. Gizmos keep the same size on
+/// screen however far away they are, never cast shadows and never show up
+/// in reflections.
+#[derive(Clone, Copy, Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum Gizmo {
+    /// A glowing ball, as for a point light, `size` times the line width
+    /// across.
+    Point {
+        position: Point,
+        size: f32,
+        color: Color,
+    },
+    Line {
+        from: Point,
+        to: Point,
+        color: Color,
+    },
+}
+
+impl Gizmo {
+    pub fn color(&self) -> Color {
+        match *self {
+            Gizmo::Point { color, .. } | Gizmo::Line { color, .. } => color,
+        }
+    }
+
+    /// How far along `ray` the gizmo is hit, drawn `width` wide for each
+    /// unit of distance from the ray's origin.
+    pub fn hit(&self, ray: Ray, width: f32) -> Option<f32> {
+        let (t, miss) = match *self {
+            Gizmo::Point { position, size, .. } => {
+                let t =
+                    (position - ray.origin).dot(ray.direction) / ray.direction.dot(ray.direction);
+                (t, (ray.position(t) - position).magnitude() / size)
+            }
+            Gizmo::Line { from, to, .. } => {
+                let along = closest_along(ray, from, to);
+                let t = (from + (to - from) * along - ray.origin).dot(ray.direction)
+                    / ray.direction.dot(ray.direction);
+                (
+                    t,
+                    (ray.position(t) - (from + (to - from) * along)).magnitude(),
+                )
+            }
+        };
+        let reach = width * t * ray.direction.magnitude() / 2.0;
+        if t > 0.0 && miss <= reach {
+            Some(t)
+        } else {
+            None
+        }
+    }
+}
+
+/// How far from `from` to `to` the segment passes closest to `ray`, from
+/// 0 to 1.
+fn closest_along(ray: Ray, from: Point, to: Point) -> f32 {
+    let edge = to - from;
+    let offset = ray.origin - from;
+    let (a, b, c) = (
+        ray.direction.dot(ray.direction),
+        ray.direction.dot(edge),
+        edge.dot(edge),
+    );
+    let (d, e) = (ray.direction.dot(offset), edge.dot(offset));
+    let denominator = a * c - b * b;
+    if c == 0.0 || denominator.abs() < f32::EPSILON {
+        return 0.0;
+    }
+
+    ((a * e - b * d) / denominator).clamp(0.0, 1.0)
+}
+
+/// The four edges of the rectangle with a corner at `corner` and sides
+/// along `uvec` and `vvec`.
+pub fn rectangle(corner: Point, uvec: Vector, vvec: Vector, color: Color) -> Vec<Gizmo> {
+    let corners = [corner, corner + uvec, corner + uvec + vvec, corner + vvec];
+    (0..4)
+        .map(|i| Gizmo::Line {
+            from: corners[i],
+            to: corners[(i + 1) % 4],
+            color,
+        })
+        .collect()
+}
+
+/// Lines from the eye out along the four corners of a camera's view,
+/// `length` long and joined at the far end.
+pub fn frustum(eye: Point, corners: [Vector; 4], length: f32) -> Vec<Gizmo> {
+    let far = corners.map(|corner| eye + corner.normalize() * length);
+    let mut gizmos = far
+        .iter()
+        .map(|&to| Gizmo::Line {
+            from: eye,
+            to,
+            color: FRUSTUM_COLOR,
+        })
+        .collect::<Vec<_>>();
+    gizmos.extend((0..4).map(|i| Gizmo::Line {
+        from: far[i],
+        to: far[(i + 1) % 4],
+        color: FRUSTUM_COLOR,
+    }));
+    gizmos
+}
+
+/// A gizmo for the world's light, outlines of its portals and of every
+/// emissive quad, which is how area lights are made, however deep in
+/// groups it sits.
+pub fn of_world(world: &World) -> Vec<Gizmo> {
+    let light = world.light();
+    let mut gizmos = vec![Gizmo::Point {
+        position: light.position,
+        size: 5.0,
+        color: brightest_at_one(light.intensity).unwrap_or(LIGHT_COLOR),
+    }];
+
+    for portal in world.portals() {
+        gizmos.extend(rectangle(
+            portal.corner,
+            portal.uvec,
+            portal.vvec,
+            PORTAL_COLOR,
+        ));
+    }
+
+    for (object, groups) in world.emitters() {
+        let quad = match object.as_any().downcast_ref::<Quad>() {
+            Some(quad) => quad,
+            None => continue,
+        };
+        let transform = *groups * *object.transform();
+        let emissive = world.material_of(object).emissive;
+        let color = brightest_at_one(emissive).unwrap_or(LIGHT_COLOR);
+        gizmos.extend(rectangle(
+            transform * quad.origin,
+            transform * quad.uvec,
+            transform * quad.vvec,
+            color,
+        ));
+    }
+    gizmos
+}
+
+/// `color` scaled so its brightest channel is 1, keeping its hue however
+/// bright the light is.
+fn brightest_at_one(color: Color) -> Option<Color> {
+    let brightest = color.red().max(color.green()).max(color.blue());
+    if brightest > 0.0 {
+        Some(color * (1.0 / brightest))
+    } else {
+        None
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{
+        color,
+        lights::{PointLight, Portal},
+        materials::Material,
+        shapes::{group::Group, ShapeBuilder},
+        transformations::Transform,
+    };
+
+    use super::*;
+
+    fn towards_z(x: f32, y: f32) -> Ray {
+        Ray::new(Point::new(x, y, -10.0), Vector::new(0.0, 0.0, 1.0))
+    }
+
+    #[test]
+    fn a_point_gizmo_is_hit_within_its_size() {
+        let g = Gizmo::Point {
+            position: Point::new(0.0, 0.0, 0.0),
+            size: 5.0,
+            color: color::WHITE,
+        };
+
+        assert_eq!(g.hit(towards_z(0.0, 0.2), 0.01), Some(10.0));
+        assert_eq!(g.hit(towards_z(0.0, 0.3), 0.01), None);
+        assert_eq!(g.hit(towards_z(0.0, 0.3), 0.02), Some(10.0));
+    }
+
+    #[test]
+    fn a_line_gizmo_is_as_wide_on_screen_however_far_away_it_is() {
+        let near = Gizmo::Line {
+            from: Point::new(-1.0, 0.0, 0.0),
+            to: Point::new(1.0, 0.0, 0.0),
+            color: color::WHITE,
+        };
+        let far = Gizmo::Line {
+            from: Point::new(-1.0, 0.0, 90.0),
+            to: Point::new(1.0, 0.0, 90.0),
+            color: color::WHITE,
+        };
+
+        assert_eq!(near.hit(towards_z(0.5, 0.04), 0.01), Some(10.0));
+        assert_eq!(near.hit(towards_z(0.5, 0.06), 0.01), None);
+        assert_eq!(far.hit(towards_z(0.5, 0.4), 0.01), Some(100.0));
+        assert_eq!(far.hit(towards_z(2.0, 0.0), 0.01), None);
+    }
+
+    #[test]
+    fn a_gizmo_behind_the_ray_is_missed() {
+        let g = Gizmo::Point {
+            position: Point::new(0.0, 0.0, -20.0),
+            size: 5.0,
+            color: color::WHITE,
+        };
+
+        assert_eq!(g.hit(towards_z(0.0, 0.0), 0.01), None);
+    }
+
+    #[test]
+    fn a_rectangle_is_four_joined_lines() {
+        let lines = rectangle(
+            Point::new(0.0, 0.0, 0.0),
+            Vector::new(2.0, 0.0, 0.0),
+            Vector::new(0.0, 1.0, 0.0),
+            color::WHITE,
+        );
+
+        assert_eq!(lines.len(), 4);
+        assert_eq!(
+            lines[1],
+            Gizmo::Line {
+                from: Point::new(2.0, 0.0, 0.0),
+                to: Point::new(2.0, 1.0, 0.0),
+                color: color::WHITE,
+            }
+        );
+    }
+
+    #[test]
+    fn the_gizmos_of_a_world() {
+        let lamp = Quad::new(
+            Point::new(0.0, 0.0, 0.0),
+            Vector::new(1.0, 0.0, 0.0),
+            Vector::new(0.0, 0.0, 1.0),
+        )
+        .with_transform(Transform::translation(0.0, 3.0, 0.0))
+        .with_material(Material::default().emissive(Color::new(4.0, 2.0, 0.0)));
+        let w = World::new(PointLight::new(
+            Point::new(-10.0, 10.0, -10.0),
+            Color::new(0.0, 0.0, 2.0),
+        ))
+        .portal(Portal::new(
+            Point::new(0.0, 0.0, 5.0),
+            Vector::new(1.0, 0.0, 0.0),
+            Vector::new(0.0, 1.0, 0.0),
+        ))
+        .object(Box::new(lamp))
+        .object(Box::new(Quad::default()));
+
+        let gizmos = of_world(&w);
+
+        assert_eq!(gizmos.len(), 9);
+        assert_eq!(
+            gizmos[0],
+            Gizmo::Point {
+                position: Point::new(-10.0, 10.0, -10.0),
+                size: 5.0,
+                color: Color::new(0.0, 0.0, 1.0),
+            }
+        );
+        assert_eq!(gizmos[1].color(), PORTAL_COLOR);
+        assert_eq!(
+            gizmos[5],
+            Gizmo::Line {
+                from: Point::new(0.0, 3.0, 0.0),
+                to: Point::new(1.0, 3.0, 0.0),
+                color: Color::new(1.0, 0.5, 0.0),
+            }
+        );
+    }
+
+    #[test]
+    fn an_emissive_quad_in_a_group_is_outlined_where_the_group_puts_it() {
+        let lamp = Quad::new(
+            Point::new(0.0, 0.0, 0.0),
+            Vector::new(1.0, 0.0, 0.0),
+            Vector::new(0.0, 0.0, 1.0),
+        )
+        .with_material(Material::default().emissive(color::WHITE));
+        let mut group = Group::new().with_transform(Transform::translation(0.0, 3.0, 0.0));
+        group.add_child(Box::new(lamp));
+        let w = World::new(PointLight::default()).object(Box::new(group));
+
+        let gizmos = of_world(&w);
+
+        assert_eq!(gizmos.len(), 5);
+        assert_eq!(
+            gizmos[1],
+            Gizmo::Line {
+                from: Point::new(0.0, 3.0, 0.0),
+                to: Point::new(1.0, 3.0, 0.0),
+                color: color::WHITE,
+            }
+        );
+    }
+}
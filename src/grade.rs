@@ -0,0 +1,351 @@
+use std::{
+    error::Error,
+    fmt::{self, Display},
+    io::{self, Read},
+};
+
+use crate::{color, color::Color};
+
+/// A final look given to an image before it's written out. It works on the
+/// full range colors, so nothing is lost to 8-bit rounding first.
+#[derive(Clone, Debug, PartialEq)]
+pub enum Grade {
+    /// The image as rendered.
+    Neutral,
+    /// A film-like response that rolls highlights off gently instead of
+    /// clipping them, and deepens the shadows a little.
+    Filmic,
+    Lut(Lut),
+}
+
+impl Grade {
+    pub fn apply(&self, color: Color) -> Color {
+        match self {
+            Grade::Neutral => color,
+            Grade::Filmic => {
+                // Krzysztof Narkowicz's fit of the ACES filmic curve.
+                let curve = |x: f32| {
+                    let x = x.max(0.0);
+                    ((x * (2.51 * x + 0.03)) / (x * (2.43 * x + 0.59) + 0.14)).clamp(0.0, 1.0)
+                };
+                Color::new(
+                    curve(color.red()),
+                    curve(color.green()),
+                    curve(color.blue()),
+                )
+            }
+            Grade::Lut(lut) => lut.apply(color),
+        }
+    }
+
+    /// `color` graded and encoded as sRGB, ready to be written out. A LUT
+    /// maps display-encoded colors, as `.cube` files expect, so those are
+    /// encoded first; the other grades work on linear light.
+    pub fn apply_srgb(&self, color: Color) -> Color {
+        match self {
+            Grade::Lut(lut) => lut.apply(color.to_srgb()),
+            _ => self.apply(color).to_srgb(),
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum LutKind {
+    /// A curve for each channel on its own.
+    OneD,
+    /// A lattice mapping every color to another, red varying fastest.
+    ThreeD,
+}
+
+/// A color lookup table, as read from an Adobe/Resolve `.cube` file.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Lut {
+    pub kind: LutKind,
+    /// The number of samples along each axis.
+    pub size: usize,
+    pub table: Vec<Color>,
+    /// The input colors the first and last samples stand for.
+    pub domain_min: Color,
+    pub domain_max: Color,
+}
+
+impl Lut {
+    /// Reads a `.cube` file. Titles and comments are skipped.
+    pub fn parse(mut reader: impl Read) -> Result<Self, LutError> {
+        let mut text = String::new();
+        reader.read_to_string(&mut text)?;
+
+        let mut kind = None;
+        let mut domain_min = color::BLACK;
+        let mut domain_max = color::WHITE;
+        let mut table = vec![];
+        for line in text.lines() {
+            let line = line.split('#').next().unwrap_or("").trim();
+            let mut tokens = line.split_whitespace();
+            match tokens.next() {
+                None | Some("TITLE") => (),
+                Some("LUT_1D_SIZE") => kind = Some((LutKind::OneD, parse_size(tokens.next())?)),
+                Some("LUT_3D_SIZE") => kind = Some((LutKind::ThreeD, parse_size(tokens.next())?)),
+                Some("DOMAIN_MIN") => domain_min = parse_color(tokens, line)?,
+                Some("DOMAIN_MAX") => domain_max = parse_color(tokens, line)?,
+                Some(_) => table.push(parse_color(line.split_whitespace(), line)?),
+            }
+        }
+
+        let (kind, size) = kind.ok_or(LutError::MissingSize)?;
+        let expected = match kind {
+            LutKind::OneD => size,
+            LutKind::ThreeD => size.pow(3),
+        };
+        if table.len() != expected {
+            return Err(LutError::EntryCount {
+                expected,
+                found: table.len(),
+            });
+        }
+
+        Ok(Self {
+            kind,
+            size,
+            table,
+            domain_min,
+            domain_max,
+        })
+    }
+
+    /// The table's color for `color`, blended between the nearest samples.
+    /// Colors outside the domain are held at its edges.
+    pub fn apply(&self, color: Color) -> Color {
+        let position = |value: f32, min: f32, max: f32| {
+            ((value - min) / (max - min)).clamp(0.0, 1.0) * (self.size - 1) as f32
+        };
+        let [r, g, b] = [
+            position(color.red(), self.domain_min.red(), self.domain_max.red()),
+            position(
+                color.green(),
+                self.domain_min.green(),
+                self.domain_max.green(),
+            ),
+            position(color.blue(), self.domain_min.blue(), self.domain_max.blue()),
+        ];
+
+        match self.kind {
+            LutKind::OneD => {
+                let curve = |x: f32, channel: fn(Color) -> f32| {
+                    let (i, f) = self.cell(x);
+                    let (a, b) = (channel(self.table[i]), channel(self.table[i + 1]));
+                    a + (b - a) * f
+                };
+                Color::new(
+                    curve(r, Color::red),
+                    curve(g, Color::green),
+                    curve(b, Color::blue),
+                )
+            }
+            LutKind::ThreeD => {
+                let ((ri, rf), (gi, gf), (bi, bf)) = (self.cell(r), self.cell(g), self.cell(b));
+                let at = |dr: usize, dg: usize, db: usize| {
+                    self.table[(ri + dr) + (gi + dg) * self.size + (bi + db) * self.size.pow(2)]
+                };
+                let lerp = |a: Color, b: Color, t: f32| a + (b - a) * t;
+
+                lerp(
+                    lerp(
+                        lerp(at(0, 0, 0), at(1, 0, 0), rf),
+                        lerp(at(0, 1, 0), at(1, 1, 0), rf),
+                        gf,
+                    ),
+                    lerp(
+                        lerp(at(0, 0, 1), at(1, 0, 1), rf),
+                        lerp(at(0, 1, 1), at(1, 1, 1), rf),
+                        gf,
+                    ),
+                    bf,
+                )
+            }
+        }
+    }
+
+    /// The sample just below `x` and how far past it `x` is, keeping a
+    /// sample above for `x` at the very top.
+    fn cell(&self, x: f32) -> (usize, f32) {
+        let i = (x.floor() as usize).min(self.size - 2);
+        (i, x - i as f32)
+    }
+}
+
+fn parse_size(token: Option<&str>) -> Result<usize, LutError> {
+    let token = token.unwrap_or("");
+    match token.parse() {
+        Ok(size) if size >= 2 => Ok(size),
+        _ => Err(LutError::InvalidSize(token.to_string())),
+    }
+}
+
+fn parse_color<'a>(tokens: impl Iterator<Item = &'a str>, line: &str) -> Result<Color, LutError> {
+    let channels = tokens
+        .map(|token| {
+            token
+                .parse()
+                .map_err(|_| LutError::InvalidNumber(token.to_string()))
+        })
+        .collect::<Result<Vec<f32>, _>>()?;
+    match channels[..] {
+        [r, g, b] => Ok(Color::new(r, g, b)),
+        _ => Err(LutError::InvalidLine(line.to_string())),
+    }
+}
+
+#[derive(Debug)]
+pub enum LutError {
+    Io(io::Error),
+    InvalidNumber(String),
+    InvalidLine(String),
+    /// The size is missing, isn't a number, or is too small to blend
+    /// between samples.
+    InvalidSize(String),
+    MissingSize,
+    EntryCount {
+        expected: usize,
+        found: usize,
+    },
+}
+
+impl Display for LutError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            LutError::Io(e) => write!(f, "couldn't read the LUT: {}", e),
+            LutError::InvalidNumber(token) => write!(f, "{:?} is not a valid number", token),
+            LutError::InvalidLine(line) => write!(f, "{:?} is not an r g b entry", line),
+            LutError::InvalidSize(size) => write!(f, "{:?} is not a usable LUT size", size),
+            LutError::MissingSize => write!(f, "the LUT doesn't give its size"),
+            LutError::EntryCount { expected, found } => {
+                write!(f, "expected {} entries, found {}", expected, found)
+            }
+        }
+    }
+}
+
+impl Error for LutError {}
+
+impl From<io::Error> for LutError {
+    fn from(e: io::Error) -> Self {
+        LutError::Io(e)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn parse(text: &str) -> Lut {
+        Lut::parse(text.as_bytes()).unwrap()
+    }
+
+    /// A 3D LUT that swaps red and blue.
+    fn swap_red_and_blue() -> Lut {
+        let mut text = "TITLE \"swap\"\n# red varies fastest\nLUT_3D_SIZE 2\n".to_string();
+        for b in 0..2 {
+            for g in 0..2 {
+                for r in 0..2 {
+                    text += &format!("{} {} {}\n", b, g, r);
+                }
+            }
+        }
+        parse(&text)
+    }
+
+    #[test]
+    fn reading_a_1d_lut() {
+        let lut = parse(
+            "LUT_1D_SIZE 3
+DOMAIN_MIN 0 0 0
+DOMAIN_MAX 1 1 1
+0 0 0
+0.25 0.5 0.75
+1 1 1",
+        );
+
+        assert_eq!(lut.kind, LutKind::OneD);
+        assert_eq!(lut.size, 3);
+        assert_eq!(lut.table[1], Color::new(0.25, 0.5, 0.75));
+    }
+
+    #[test]
+    fn a_1d_lut_blends_each_channel_along_its_curve() {
+        let lut = parse("LUT_1D_SIZE 3\n0 0 0\n0.25 0.5 0.75\n1 1 1");
+
+        let c = lut.apply(Color::new(0.25, 0.5, 2.0));
+
+        assert_eq!(c, Color::new(0.125, 0.5, 1.0));
+    }
+
+    #[test]
+    fn a_lut_grades_display_encoded_colors() {
+        let halve = Grade::Lut(parse("LUT_1D_SIZE 2\n0 0 0\n0.5 0.5 0.5"));
+        let gray = Color::new(0.2, 0.2, 0.2);
+
+        assert_eq!(halve.apply_srgb(gray), gray.to_srgb() * 0.5);
+        assert_eq!(Grade::Neutral.apply_srgb(gray), gray.to_srgb());
+    }
+
+    #[test]
+    fn a_3d_lut_maps_colors_through_the_lattice() {
+        let lut = swap_red_and_blue();
+
+        assert_eq!(lut.kind, LutKind::ThreeD);
+        assert_eq!(
+            lut.apply(Color::new(1.0, 0.0, 0.0)),
+            Color::new(0.0, 0.0, 1.0)
+        );
+        assert_eq!(
+            lut.apply(Color::new(0.2, 0.4, 0.6)),
+            Color::new(0.6, 0.4, 0.2)
+        );
+    }
+
+    #[test]
+    fn a_lut_domain_rescales_its_input() {
+        let lut = parse("LUT_1D_SIZE 2\nDOMAIN_MIN 0 0 0\nDOMAIN_MAX 4 4 4\n0 0 0\n1 1 1");
+
+        assert_eq!(
+            lut.apply(Color::new(1.0, 2.0, 8.0)),
+            Color::new(0.25, 0.5, 1.0)
+        );
+    }
+
+    #[test]
+    fn a_lut_with_the_wrong_number_of_entries() {
+        let result = Lut::parse("LUT_3D_SIZE 2\n0 0 0\n1 1 1".as_bytes());
+
+        assert!(matches!(
+            result,
+            Err(LutError::EntryCount {
+                expected: 8,
+                found: 2
+            })
+        ));
+        assert!(matches!(
+            Lut::parse("0 0 0".as_bytes()),
+            Err(LutError::MissingSize)
+        ));
+    }
+
+    #[test]
+    fn the_neutral_grade_leaves_colors_alone() {
+        let c = Color::new(1.5, 0.2, 0.0);
+
+        assert_eq!(Grade::Neutral.apply(c), c);
+    }
+
+    #[test]
+    fn the_filmic_grade_rolls_off_highlights() {
+        let graded = |x: f32| Grade::Filmic.apply(Color::new(x, x, x)).red();
+
+        assert_eq!(graded(0.0), 0.0);
+        assert!(graded(0.02) < 0.02);
+        assert!(graded(2.0) < 1.0);
+        assert!(graded(2.0) < graded(4.0));
+        assert!((graded(100.0) - 1.0).abs() < 0.01);
+    }
+}
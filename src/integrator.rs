@@ -0,0 +1,869 @@
+use std::{
+    collections::hash_map::DefaultHasher,
+    f32::consts::PI,
+    hash::{Hash, Hasher},
+    marker::PhantomData,
+};
+
+use crate::{
+    color::{self, Color},
+    intersection::Computations,
+    ray::{DepthBudget, Ray, RayKind},
+    sampler::{pixel_seed, Sampler, SamplerKind},
+    spectrum::{Radiance, Spectra, SpectralCurve},
+    stats,
+    tuple::Vector,
+    world::World,
+    EPSILON,
+};
+
+pub trait Integrator {
+    fn color_at(&self, world: &World, ray: Ray) -> Color;
+
+    /// Called before each pass over a pixel. Integrators that draw random
+    /// numbers restart them on the pixel's own seed here.
+    fn start_pixel(&self, _x: usize, _y: usize, _pass: u32) {}
+}
+
+/// The book's recursive ray tracer: direct Phong lighting plus perfect
+/// reflection and refraction.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Whitted {
+    pub max_depth: DepthBudget,
+}
+
+impl Whitted {
+    /// Either a single depth shared by every kind of bounce, or a
+    /// `DepthBudget` giving reflection and refraction their own limits.
+    pub fn new(max_depth: impl Into<DepthBudget>) -> Self {
+        Self {
+            max_depth: max_depth.into(),
+        }
+    }
+}
+
+impl Integrator for Whitted {
+    fn color_at(&self, world: &World, ray: Ray) -> Color {
+        world.color_at(ray, self.max_depth)
+    }
+}
+
+/// Unshadowed direct lighting with no reflection or refraction, for quickly
+/// checking a composition.
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct Preview;
+
+impl Integrator for Preview {
+    fn color_at(&self, world: &World, ray: Ray) -> Color {
+        let intersections = world.intersect(ray);
+        match intersections.hit() {
+            Some(hit) => {
+                world.unshadowed_lighting(world.prepare_computations(hit, ray, &intersections))
+            }
+            None => world.background_in(ray.direction),
+        }
+    }
+}
+
+/// Views that skip shading to show the geometry itself, for tracking down
+/// modelling and transform bugs. Rays that miss everything are black.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum DebugView {
+    /// Every object in its own flat color, picked from its id.
+    ObjectId,
+    /// The world space normal facing the camera, with x, y and z mapped
+    /// from [-1, 1] to red, green and blue.
+    Normals,
+    /// Distance along the ray as a heatmap, from red up close through
+    /// green to blue at `far` and beyond.
+    Depth { far: f32 },
+    /// How much light reaches each hit: white where it's lit, black in
+    /// shadow, tinted behind colored glass.
+    Shadows,
+    /// Every value of the tag in its own flat color, with untagged shapes
+    /// dark grey. Tags on enclosing groups count.
+    Tag(&'static str),
+    /// How brightly the light and emissive shapes light each hit directly,
+    /// as a heatmap from blue in the dark through green to red at `max`
+    /// and above.
+    Falloff { max: f32 },
+}
+
+impl Integrator for DebugView {
+    fn color_at(&self, world: &World, ray: Ray) -> Color {
+        let intersections = world.intersect(ray);
+        let hit = match intersections.hit() {
+            Some(hit) => hit,
+            None => return color::BLACK,
+        };
+        let comps = world.prepare_computations(hit, ray, &intersections);
+
+        match *self {
+            DebugView::ObjectId => flat_color(comps.object.id().as_bytes()),
+            DebugView::Normals => {
+                let n = comps.normalv;
+                Color::new(
+                    (n.x() + 1.0) / 2.0,
+                    (n.y() + 1.0) / 2.0,
+                    (n.z() + 1.0) / 2.0,
+                )
+            }
+            DebugView::Depth { far } => heatmap((hit.t * ray.direction.magnitude() / far).min(1.0)),
+            DebugView::Shadows => world.shadow_at(comps.over_point),
+            DebugView::Tag(key) => match world.tag_of(comps.object, key) {
+                Some(value) => {
+                    let mut hasher = DefaultHasher::new();
+                    value.hash(&mut hasher);
+                    flat_color(&hasher.finish().to_le_bytes())
+                }
+                None => Color::new(0.2, 0.2, 0.2),
+            },
+            DebugView::Falloff { max } => {
+                let light = world.direct_lighting(comps) + world.emitted_lighting(comps);
+                heatmap(1.0 - (light.luminance() / max).min(1.0))
+            }
+        }
+    }
+}
+
+/// Red at 0 through green to blue at 1.
+fn heatmap(t: f32) -> Color {
+    Color::new(1.0 - t, 1.0 - (2.0 * t - 1.0).abs(), t)
+}
+
+/// A color picked out by the first three bytes of an id.
+fn flat_color(bytes: &[u8]) -> Color {
+    Color::new(
+        bytes[0] as f32 / 255.0,
+        bytes[1] as f32 / 255.0,
+        bytes[2] as f32 / 255.0,
+    )
+}
+
+/// Monte Carlo path tracer. Each sample follows a single path, picking
+/// diffuse, reflective or refractive continuation in proportion to the
+/// material's weights, and samples the point light and every emissive shape
+/// directly at every vertex. Ambient is ignored since indirect bounces
+/// replace it. Emission, and background seen through a portal, is only
+/// counted when a path reaches it through a mirror or glass, since a diffuse
+/// bounce already sampled it. Like the Whitted tracer, glass absorbs light
+/// crossing it, and fog and volumes dim every segment of the path.
+#[derive(Debug)]
+pub struct PathTracer {
+    pub samples: u32,
+    pub max_depth: u32,
+    pub roulette_depth: u32,
+    seed: u64,
+    sampling: SamplerKind,
+    rng: Box<dyn Sampler>,
+}
+
+impl PathTracer {
+    pub fn new(samples: u32) -> Self {
+        Self {
+            samples,
+            max_depth: 8,
+            roulette_depth: 3,
+            seed: 0x2545_f491_4f6c_dd1d,
+            sampling: SamplerKind::Random,
+            rng: SamplerKind::Random.sampler(0x2545_f491_4f6c_dd1d),
+        }
+    }
+
+    pub fn max_depth(self, max_depth: u32) -> Self {
+        Self { max_depth, ..self }
+    }
+
+    pub fn roulette_depth(self, roulette_depth: u32) -> Self {
+        Self {
+            roulette_depth,
+            ..self
+        }
+    }
+
+    pub fn seed(self, seed: u64) -> Self {
+        Self {
+            seed,
+            rng: self.sampling.sampler(seed),
+            ..self
+        }
+    }
+
+    /// Which sampler picks the light samples and bounces, seeded as before.
+    pub fn sampling(self, sampling: SamplerKind) -> Self {
+        Self {
+            sampling,
+            rng: sampling.sampler(self.seed),
+            ..self
+        }
+    }
+
+    /// Replaces the seeded generator, e.g. with a canned `Sequence`.
+    pub fn sampler(self, sampler: impl Sampler + 'static) -> Self {
+        Self {
+            rng: Box::new(sampler),
+            ..self
+        }
+    }
+
+    fn trace(&self, world: &World, ray: Ray, depth: u32, count_emission: bool) -> Color {
+        let intersections = world.intersect(ray);
+        let (color, distance) = match intersections.hit() {
+            Some(hit) => {
+                let comps = world.prepare_computations(hit, ray, &intersections);
+                let absorbed = world.transmittance(&comps, hit.t * ray.direction.magnitude());
+                (
+                    self.shade(world, comps, depth, count_emission) * absorbed,
+                    hit.t,
+                )
+            }
+            None if count_emission || !world.escapes_through_portal(ray) => {
+                (world.background_in(ray.direction), f32::INFINITY)
+            }
+            None => (color::BLACK, f32::INFINITY),
+        };
+
+        world.through_the_air(color, ray, distance)
+    }
+
+    /// The light leaving a hit back along the path: what's sampled there
+    /// directly, plus whatever comes in along one continuation of the path.
+    fn shade(&self, world: &World, comps: Computations, depth: u32, count_emission: bool) -> Color {
+        let object = comps.object;
+        let material = world.shading_material(&comps);
+
+        let emitted = if count_emission {
+            material.emissive
+        } else {
+            color::BLACK
+        };
+        let direct = emitted
+            + world.direct_lighting(comps)
+            + {
+                let (u, v) = self.rng.next_2d();
+                world.sample_emitters(comps, u, v)
+            }
+            + {
+                let (u, v) = self.rng.next_2d();
+                world.sample_portals(comps, u, v)
+            };
+        if depth >= self.max_depth {
+            return direct;
+        }
+
+        let surface_color = material.color_at(object, comps.over_point);
+        let reflective = material.reflective_at(object, comps.over_point);
+        let transparency = material.transparency_at(object, comps.over_point);
+        let diffuse = material.diffuse_at(object, comps.over_point);
+        let (reflect_weight, refract_weight) = if reflective > 0.0 && transparency > 0.0 {
+            let reflectance = comps.schlick();
+            (reflective * reflectance, transparency * (1.0 - reflectance))
+        } else {
+            (reflective, transparency)
+        };
+        let diffuse_weight = diffuse * max_component(surface_color);
+
+        let total = reflect_weight + refract_weight + diffuse_weight;
+        if total < EPSILON {
+            return direct;
+        }
+
+        let survival = if depth >= self.roulette_depth {
+            total.min(1.0)
+        } else {
+            1.0
+        };
+        if self.rng.next_f32() >= survival {
+            return direct;
+        }
+
+        let choice = self.rng.next_f32() * total;
+        let (next_ray, throughput, weight, kind) = if choice < reflect_weight {
+            (
+                Ray::new(comps.over_point, comps.reflectv),
+                color::WHITE * reflect_weight,
+                reflect_weight,
+                RayKind::Reflection,
+            )
+        } else if choice < reflect_weight + refract_weight {
+            let (next, kind) = match comps.refracted_direction() {
+                Some(direction) => (Ray::new(comps.under_point, direction), RayKind::Refraction),
+                None => (
+                    Ray::new(comps.over_point, comps.reflectv),
+                    RayKind::Reflection,
+                ),
+            };
+            (next, color::WHITE * refract_weight, refract_weight, kind)
+        } else {
+            let (u, v) = self.rng.next_2d();
+            let direction = cosine_sample_hemisphere(comps.normalv, u, v);
+            (
+                Ray::new(comps.over_point, direction),
+                surface_color * diffuse,
+                diffuse_weight,
+                RayKind::Diffuse,
+            )
+        };
+        let probability = weight / total;
+
+        stats::record_ray(kind);
+        let incoming = self.trace(
+            world,
+            next_ray
+                .kind(kind)
+                .bias(comps.bias)
+                .leaving(Some(comps.object.id())),
+            depth + 1,
+            kind != RayKind::Diffuse,
+        );
+        direct + throughput * incoming * (1.0 / (probability * survival))
+    }
+}
+
+impl Integrator for PathTracer {
+    fn color_at(&self, world: &World, ray: Ray) -> Color {
+        if self.samples == 0 {
+            return color::BLACK;
+        }
+
+        let sum = (0..self.samples).fold(color::BLACK, |sum, sample| {
+            self.rng.start_sample(sample);
+            sum + self.trace(world, ray, 0, true)
+        });
+        sum * (1.0 / self.samples as f32)
+    }
+
+    fn start_pixel(&self, x: usize, y: usize, pass: u32) {
+        self.rng.reseed(pixel_seed(self.seed, x, y, pass));
+    }
+}
+
+/// Whitted-style tracing that carries light as `R`, usually a `Spectrum`,
+/// turning it back into RGB only once a ray's light is gathered. It shades
+/// through the world's own `radiance_at`, so everything `Whitted` lights
+/// with applies here too, but surfaces and the light multiply wavelength by
+/// wavelength, which is where a material or light given as a
+/// `SpectralCurve` differs from its RGB look-alike, and a ray splits into
+/// one per wavelength at the first dispersive surface it crosses.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Spectral<R> {
+    pub max_depth: u32,
+    /// The point light's emission by wavelength, in place of its intensity.
+    pub light: Option<SpectralCurve>,
+    radiance: PhantomData<R>,
+}
+
+impl<R: Radiance> Spectral<R> {
+    pub fn new(max_depth: u32) -> Self {
+        Self {
+            max_depth,
+            light: None,
+            radiance: PhantomData,
+        }
+    }
+
+    pub fn light(self, light: SpectralCurve) -> Self {
+        Self {
+            light: Some(light),
+            ..self
+        }
+    }
+}
+
+impl<R: Radiance> Integrator for Spectral<R> {
+    fn color_at(&self, world: &World, ray: Ray) -> Color {
+        let spectra = Spectra {
+            materials: true,
+            light: self.light.as_ref(),
+        };
+        world
+            .radiance_at::<R>(ray, self.max_depth, spectra)
+            .to_rgb()
+    }
+}
+
+fn max_component(color: Color) -> f32 {
+    color.red().max(color.green()).max(color.blue())
+}
+
+fn orthonormal_basis(normal: Vector) -> (Vector, Vector) {
+    let helper = if normal.x().abs() > 0.9 {
+        Vector::new(0.0, 1.0, 0.0)
+    } else {
+        Vector::new(1.0, 0.0, 0.0)
+    };
+    let tangent = helper.cross(normal).normalize();
+    let bitangent = normal.cross(tangent);
+    (tangent, bitangent)
+}
+
+pub fn cosine_sample_hemisphere(normal: Vector, u1: f32, u2: f32) -> Vector {
+    let r = u1.sqrt();
+    let phi = 2.0 * PI * u2;
+    let (tangent, bitangent) = orthonormal_basis(normal);
+
+    (tangent * (r * phi.cos()) + bitangent * (r * phi.sin()) + normal * (1.0 - u1).sqrt())
+        .normalize()
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{
+        float_eq,
+        lights::{PointLight, Portal},
+        materials::Material,
+        sampler::{Sequence, Xorshift},
+        shapes::{cube::Cube, group::Group, plane::Plane, sphere::Sphere, ShapeBuilder},
+        spectrum::Spectrum,
+        transformations::Transform,
+        tuple::Point,
+        volume::Fog,
+    };
+
+    use super::*;
+
+    #[test]
+    fn the_whitted_integrator_is_the_recursive_ray_tracer() {
+        let w = World::default();
+        let r = Ray::new(Point::new(0.0, 0.0, -5.0), Vector::new(0.0, 0.0, 1.0));
+
+        let c = Whitted::new(3).color_at(&w, r);
+
+        assert_eq!(c, w.color_at(r, 3));
+    }
+
+    #[test]
+    fn a_single_whitted_depth_applies_to_every_kind_of_bounce() {
+        assert_eq!(Whitted::new(3), Whitted::new(DepthBudget::new(3, 3)));
+    }
+
+    #[test]
+    fn the_preview_integrator_skips_reflections() {
+        let w = World::default().object(Box::new(
+            Plane::default()
+                .with_material(Material::default().reflective(0.5))
+                .with_transform(Transform::translation(0.0, -1.0, 0.0)),
+        ));
+        let r = Ray::new(
+            Point::new(0.0, 0.0, -3.0),
+            Vector::new(0.0, -1.0, 1.0).normalize(),
+        );
+        let xs = w.intersect(r);
+        let comps = xs.hit().unwrap().prepare_computations(r, &xs);
+
+        let c = Preview.color_at(&w, r);
+
+        assert_eq!(c, w.unshadowed_lighting(comps));
+        assert_ne!(c, w.color_at(r, 5));
+    }
+
+    fn debug_view_of_the_default_world(view: DebugView, r: Ray) -> Color {
+        view.color_at(&World::default(), r)
+    }
+
+    #[test]
+    fn each_object_gets_its_own_flat_color() {
+        let w = World::default();
+        let front = Ray::new(Point::new(0.0, 0.0, -5.0), Vector::new(0.0, 0.0, 1.0));
+        let edge = Ray::new(Point::new(0.0, 0.9, -5.0), Vector::new(0.0, 0.0, 1.0));
+        let inner = Ray::new(Point::new(0.0, 0.0, 0.0), Vector::new(0.0, 0.0, 1.0));
+
+        let c = DebugView::ObjectId.color_at(&w, front);
+
+        assert_eq!(c, DebugView::ObjectId.color_at(&w, edge));
+        assert_ne!(c, DebugView::ObjectId.color_at(&w, inner));
+    }
+
+    #[test]
+    fn shapes_are_colored_by_their_tag() {
+        let mut props = Group::new().with_tag("role", "prop");
+        props.add_child(Box::new(
+            Sphere::new().with_transform(Transform::translation(-3.0, 0.0, 0.0)),
+        ));
+        let w = World::new(PointLight::new(Point::new(0.0, 10.0, 0.0), color::WHITE))
+            .object(Box::new(props))
+            .object(Box::new(Sphere::new().with_tag("role", "prop")))
+            .object(Box::new(
+                Sphere::new().with_transform(Transform::translation(3.0, 0.0, 0.0)),
+            ));
+        let view = DebugView::Tag("role");
+        let at = |x: f32| {
+            view.color_at(
+                &w,
+                Ray::new(Point::new(x, 0.0, -5.0), Vector::new(0.0, 0.0, 1.0)),
+            )
+        };
+
+        assert_eq!(at(-3.0), at(0.0));
+        assert_ne!(at(0.0), Color::new(0.2, 0.2, 0.2));
+        assert_eq!(at(3.0), Color::new(0.2, 0.2, 0.2));
+    }
+
+    macro_rules! debug_view_tests {
+        ($($name:ident: $value:expr,)*) => {
+            $(
+                #[test]
+                fn $name() {
+                    let (view, origin, direction, expected) = $value;
+
+                    let c = debug_view_of_the_default_world(view, Ray::new(origin, direction));
+
+                    assert_eq!(c, expected);
+                }
+            )*
+        }
+    }
+
+    debug_view_tests! {
+        a_normal_facing_the_camera_has_no_blue: (DebugView::Normals, Point::new(0.0, 0.0, -5.0), Vector::new(0.0, 0.0, 1.0), Color::new(0.5, 0.5, 0.0)),
+        a_normal_facing_up_is_light_green: (DebugView::Normals, Point::new(0.0, 5.0, 0.0), Vector::new(0.0, -1.0, 0.0), Color::new(0.5, 1.0, 0.5)),
+        a_near_hit_is_red_on_the_heatmap: (DebugView::Depth { far: 1000.0 }, Point::new(0.0, 0.0, -1.0), Vector::new(0.0, 0.0, 1.0), Color::new(1.0, 0.0, 0.0)),
+        a_hit_halfway_is_green_on_the_heatmap: (DebugView::Depth { far: 8.0 }, Point::new(0.0, 0.0, -5.0), Vector::new(0.0, 0.0, 1.0), Color::new(0.5, 1.0, 0.5)),
+        a_hit_past_far_is_blue_on_the_heatmap: (DebugView::Depth { far: 2.0 }, Point::new(0.0, 0.0, -5.0), Vector::new(0.0, 0.0, 1.0), Color::new(0.0, 0.0, 1.0)),
+        a_lit_hit_is_white_in_the_shadow_view: (DebugView::Shadows, Point::new(0.0, 0.0, -5.0), Vector::new(0.0, 0.0, 1.0), color::WHITE),
+        a_shadowed_hit_is_black_in_the_shadow_view: (DebugView::Shadows, Point::new(0.0, 0.0, 5.0), Vector::new(0.0, 0.0, -1.0), color::BLACK),
+        a_lit_hit_is_red_on_the_falloff_heatmap: (DebugView::Falloff { max: 0.01 }, Point::new(0.0, 0.0, -5.0), Vector::new(0.0, 0.0, 1.0), Color::new(1.0, 0.0, 0.0)),
+        a_shadowed_hit_is_blue_on_the_falloff_heatmap: (DebugView::Falloff { max: 1.0 }, Point::new(0.0, 0.0, 5.0), Vector::new(0.0, 0.0, -1.0), Color::new(0.0, 0.0, 1.0)),
+        a_miss_is_black_in_every_debug_view: (DebugView::Normals, Point::new(0.0, 0.0, -5.0), Vector::new(0.0, 1.0, 0.0), color::BLACK),
+    }
+
+    #[test]
+    fn a_path_that_misses_everything_is_black() {
+        let w = World::default();
+        let r = Ray::new(Point::new(0.0, 0.0, -5.0), Vector::new(0.0, 1.0, 0.0));
+
+        let c = PathTracer::new(4).color_at(&w, r);
+
+        assert_eq!(c, color::BLACK);
+    }
+
+    #[test]
+    fn a_convex_object_under_an_empty_sky_only_gets_direct_light() {
+        let w = World::default();
+        let r = Ray::new(Point::new(0.0, 0.0, -5.0), Vector::new(0.0, 0.0, 1.0));
+        let xs = w.intersect(r);
+        let comps = xs[0].prepare_computations(r, &xs);
+
+        let c = PathTracer::new(8).color_at(&w, r);
+
+        assert_eq!(c, w.direct_lighting(comps));
+    }
+
+    #[test]
+    fn diffuse_bounces_pick_up_light_from_nearby_surfaces() {
+        let w = World::new(PointLight::new(Point::new(0.0, 10.0, 0.0), color::WHITE))
+            .object(Box::new(
+                Plane::default().with_material(Material::default().specular(0.0)),
+            ))
+            .object(Box::new(
+                Sphere::default()
+                    .with_transform(Transform::translation(0.0, 1.0, 0.0))
+                    .with_material(Material::default().specular(0.0)),
+            ));
+        let r = Ray::new(Point::new(0.0, 0.5, -5.0), Vector::new(0.0, 0.0, 1.0));
+        let xs = w.intersect(r);
+        let hit = xs.hit().unwrap();
+        let comps = hit.prepare_computations(r, &xs);
+
+        let c = PathTracer::new(64).color_at(&w, r);
+
+        assert!(c.red() > w.direct_lighting(comps).red());
+    }
+
+    #[test]
+    fn a_path_that_hits_an_emitter_sees_its_glow() {
+        let w =
+            World::new(PointLight::new(Point::new(0.0, 10.0, 0.0), color::BLACK))
+                .object(Box::new(Sphere::default().with_material(
+                    Material::default().emissive(Color::new(2.0, 1.0, 0.0)),
+                )));
+        let r = Ray::new(Point::new(0.0, 0.0, -5.0), Vector::new(0.0, 0.0, 1.0));
+
+        let c = PathTracer::new(4).color_at(&w, r);
+
+        assert_eq!(c, Color::new(2.0, 1.0, 0.0));
+    }
+
+    #[test]
+    fn emitters_are_sampled_directly_from_diffuse_surfaces() {
+        let w = World::new(PointLight::new(Point::new(0.0, 10.0, 0.0), color::BLACK))
+            .object(Box::new(
+                Sphere::default()
+                    .with_transform(Transform::translation(0.0, 3.0, 0.0))
+                    .with_material(Material::default().emissive(color::WHITE)),
+            ))
+            .object(Box::new(
+                Plane::default().with_material(Material::default().diffuse(1.0)),
+            ));
+        let r = Ray::new(
+            Point::new(0.0, 1.0, -1.0),
+            Vector::new(0.0, -1.0, 1.0).normalize(),
+        );
+
+        let c = PathTracer::new(256).max_depth(0).color_at(&w, r);
+
+        assert!((c.red() - 1.0 / 9.0).abs() < 0.01);
+    }
+
+    #[test]
+    fn escaped_paths_see_the_background() {
+        let w = World::new(PointLight::new(Point::new(0.0, 10.0, 0.0), color::BLACK))
+            .background(color::WHITE)
+            .object(Box::new(
+                Plane::default().with_material(Material::default().diffuse(1.0)),
+            ));
+        let r = Ray::new(
+            Point::new(0.0, 1.0, -1.0),
+            Vector::new(0.0, -1.0, 1.0).normalize(),
+        );
+
+        let c = PathTracer::new(16).max_depth(1).color_at(&w, r);
+
+        assert_eq!(c, color::WHITE);
+    }
+
+    #[test]
+    fn sampling_a_portal_matches_the_sky_seen_through_it() {
+        let roof = |x| {
+            Cube::default().with_transform(
+                Transform::translation(x, 3.0, 0.0) * Transform::scaling(5.0, 0.5, 20.0),
+            )
+        };
+        let w = World::new(PointLight::new(Point::new(0.0, 1.0, 0.0), color::BLACK))
+            .background(color::WHITE)
+            .emitter_samples(1024)
+            .object(Box::new(
+                Plane::default().with_material(Material::default().diffuse(1.0).specular(0.0)),
+            ))
+            .object(Box::new(roof(-5.5)))
+            .object(Box::new(roof(5.5)))
+            .portal(Portal::new(
+                Point::new(-0.5, 3.5, -20.0),
+                Vector::new(1.0, 0.0, 0.0),
+                Vector::new(0.0, 0.0, 40.0),
+            ));
+        let r = Ray::new(
+            Point::new(0.0, 1.0, -1.0),
+            Vector::new(0.0, -1.0, 1.0).normalize(),
+        );
+        let xs = w.intersect(r);
+        let comps = xs.hit().unwrap().prepare_computations(r, &xs);
+
+        let c = PathTracer::new(1024)
+            .max_depth(0)
+            .sampling(SamplerKind::Stratified(32))
+            .color_at(&w, r);
+
+        assert!((c.red() - w.portal_lighting(comps).red()).abs() < 0.01);
+    }
+
+    #[test]
+    fn path_tracing_glass_agrees_with_whitted() {
+        let world = |fog: Option<Fog>| {
+            let glass = Sphere::default().with_material(
+                Material::default()
+                    .ambient(0.0)
+                    .diffuse(0.0)
+                    .specular(0.0)
+                    .reflective(1.0)
+                    .transparency(1.0)
+                    .refractive_index(1.5)
+                    .absorption(Color::new(0.5, 0.1, 0.0)),
+            );
+            let w = World::new(PointLight::new(Point::new(0.0, 10.0, 0.0), color::WHITE))
+                .background(Color::new(0.8, 0.6, 0.4))
+                .object(Box::new(glass));
+            match fog {
+                Some(fog) => w.fog(fog),
+                None => w,
+            }
+        };
+        let r = Ray::new(Point::new(0.0, 0.6, -5.0), Vector::new(0.0, 0.0, 1.0));
+
+        for w in [
+            world(None),
+            world(Some(Fog::new(0.1, Color::new(0.2, 0.4, 0.6)))),
+        ]
+        .iter()
+        {
+            let expected = Whitted::new(8).color_at(w, r);
+            let c = PathTracer::new(2048).color_at(w, r);
+
+            assert!((c.red() - expected.red()).abs() < 0.01);
+            assert!((c.green() - expected.green()).abs() < 0.01);
+            assert!((c.blue() - expected.blue()).abs() < 0.01);
+        }
+    }
+
+    #[test]
+    fn path_tracing_is_repeatable_for_a_seed() {
+        let w = World::default();
+        let r = Ray::new(Point::new(0.0, 0.2, -5.0), Vector::new(0.0, 0.0, 1.0));
+
+        let c1 = PathTracer::new(16).seed(7).color_at(&w, r);
+        let c2 = PathTracer::new(16).seed(7).color_at(&w, r);
+
+        assert_eq!(c1, c2);
+    }
+
+    #[test]
+    fn cosine_samples_lie_in_the_hemisphere_around_the_normal() {
+        let normal = Vector::new(1.0, 1.0, 0.0).normalize();
+        let rng = Xorshift::new(3);
+
+        for _ in 0..100 {
+            let d = cosine_sample_hemisphere(normal, rng.next_f32(), rng.next_f32());
+
+            assert!(float_eq(d.magnitude(), 1.0));
+            assert!(d.dot(normal) >= 0.0);
+        }
+    }
+
+    #[test]
+    fn spectral_rendering_under_white_light_matches_rgb() {
+        let w = World::default();
+        let r = Ray::new(Point::new(0.0, 0.0, -5.0), Vector::new(0.0, 0.0, 1.0));
+
+        let c = Spectral::<Spectrum<16>>::new(5).color_at(&w, r);
+
+        assert_eq!(c, Color::new(0.38066, 0.47583, 0.2855));
+        assert_eq!(c, Spectral::<Color>::new(5).color_at(&w, r));
+    }
+
+    #[test]
+    fn metamers_match_under_white_light_but_not_under_a_narrow_one() {
+        let orange = SpectralCurve::new(vec![(560.0, 0.0), (600.0, 1.0), (640.0, 0.0)]);
+        let lookalike = Spectrum::<16>::from_curve(&orange).to_rgb();
+        let world = |material: Material| {
+            World::new(PointLight::new(
+                Point::new(-10.0, 10.0, -10.0),
+                color::WHITE,
+            ))
+            .object(Box::new(
+                Sphere::default().with_material(material.specular(0.0)),
+            ))
+        };
+        let spectral = world(Material::default().spectrum(orange));
+        let rgb = world(Material::default().color(lookalike));
+        let r = Ray::new(Point::new(0.0, 0.0, -5.0), Vector::new(0.0, 0.0, 1.0));
+        let white = Spectral::<Spectrum<16>>::new(0);
+        let sodium = white.clone().light(SpectralCurve::new(vec![
+            (580.0, 0.0),
+            (600.0, 4.0),
+            (620.0, 0.0),
+        ]));
+
+        assert_eq!(white.color_at(&spectral, r), white.color_at(&rgb, r));
+        assert_ne!(sodium.color_at(&spectral, r), sodium.color_at(&rgb, r));
+    }
+
+    #[test]
+    fn spectral_rendering_follows_absorption_in_glass() {
+        let w = World::new(PointLight::new(Point::new(0.0, 10.0, -10.0), color::WHITE))
+            .object(Box::new(
+                Sphere::default().with_material(
+                    Material::default()
+                        .ambient(0.0)
+                        .diffuse(0.0)
+                        .specular(0.0)
+                        .transparency(1.0)
+                        .absorption(Color::new(1.0, 0.0, 0.5)),
+                ),
+            ))
+            .background(color::WHITE);
+        let r = Ray::new(Point::new(0.0, 0.0, -5.0), Vector::new(0.0, 0.0, 1.0));
+
+        let c = Spectral::<Spectrum<16>>::new(5).color_at(&w, r);
+
+        assert_eq!(c, w.color_at(r, 5));
+    }
+
+    #[test]
+    fn spectral_rendering_keeps_colored_shadows_and_fog() {
+        let w = World::new(PointLight::new(Point::new(0.0, 10.0, 0.0), color::WHITE))
+            .object(Box::new(
+                Sphere::default()
+                    .with_transform(Transform::translation(0.0, 5.0, 0.0))
+                    .with_material(
+                        Material::default()
+                            .color(Color::new(1.0, 0.0, 0.0))
+                            .transparency(1.0),
+                    ),
+            ))
+            .object(Box::new(Plane::default()))
+            .colored_shadows(true)
+            .fog(Fog::new(0.05, Color::new(0.5, 0.5, 0.5)));
+        let r = Ray::new(
+            Point::new(0.0, 1.0, -1.0),
+            Vector::new(0.0, -1.0, 1.0).normalize(),
+        );
+
+        let c = Spectral::<Spectrum<16>>::new(5).color_at(&w, r);
+
+        assert_eq!(c, w.color_at(r, 5));
+        assert!(c.red() > c.green());
+    }
+
+    #[test]
+    fn dispersive_glass_splits_a_ray_into_every_wavelength() {
+        let glass = |abbe_number: Option<f32>| {
+            World::new(PointLight::new(Point::new(0.0, 10.0, -10.0), color::WHITE))
+                .object(Box::new(Sphere::default().with_material(Material {
+                    abbe_number,
+                    ..Material::default().transparency(1.0).refractive_index(1.5)
+                })))
+                .background(color::WHITE)
+        };
+        let r = Ray::new(Point::new(0.0, 0.7, -5.0), Vector::new(0.0, 0.0, 1.0));
+
+        let (_, flint) =
+            stats::collect(|| Spectral::<Spectrum<16>>::new(1).color_at(&glass(Some(20.0)), r));
+        let (_, crown) =
+            stats::collect(|| Spectral::<Spectrum<16>>::new(1).color_at(&glass(None), r));
+        let (_, rgb) = stats::collect(|| Spectral::<Color>::new(1).color_at(&glass(Some(20.0)), r));
+
+        assert_eq!(flint.refraction_rays, 16);
+        assert_eq!(crown.refraction_rays, 1);
+        assert_eq!(rgb.refraction_rays, 3);
+    }
+
+    #[test]
+    fn a_canned_sampler_overrides_the_seed() {
+        let w = World::default();
+        let r = Ray::new(Point::new(0.0, 0.0, -5.0), Vector::new(0.0, 0.0, 1.0));
+        let canned = || Sequence::new(vec![0.2, 0.7, 0.4, 0.9]);
+
+        let c1 = PathTracer::new(4).seed(1).sampler(canned()).color_at(&w, r);
+        let c2 = PathTracer::new(4).seed(2).sampler(canned()).color_at(&w, r);
+
+        assert_eq!(c1, c2);
+    }
+
+    #[test]
+    fn a_path_tracer_draws_from_the_sampler_it_is_set_to() {
+        let w = World::new(PointLight::new(Point::new(0.0, 10.0, 0.0), color::BLACK))
+            .object(Box::new(
+                Sphere::default()
+                    .with_transform(Transform::translation(0.0, 3.0, 0.0))
+                    .with_material(Material::default().emissive(color::WHITE)),
+            ))
+            .object(Box::new(Plane::default()));
+        let r = Ray::new(
+            Point::new(0.0, 1.0, -1.0),
+            Vector::new(0.0, -1.0, 1.0).normalize(),
+        );
+        let render = |sampling| {
+            PathTracer::new(8)
+                .sampling(sampling)
+                .seed(3)
+                .color_at(&w, r)
+        };
+
+        let random = render(SamplerKind::Random);
+        let stratified = render(SamplerKind::Stratified(2));
+
+        assert_eq!(stratified, render(SamplerKind::Stratified(2)));
+        assert_ne!(stratified, random);
+        assert_eq!(random, PathTracer::new(8).seed(3).color_at(&w, r));
+    }
+}
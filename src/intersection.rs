@@ -1,39 +1,122 @@
-use std::cmp::Ordering;
+use std::{cmp::Ordering, iter::FromIterator, ops::Deref};
 
-use crate::{float_cmp, ray::Ray, shapes::Shape, tuple::Tuple, EPSILON};
+use uuid::Uuid;
+
+use crate::{
+    color::Color,
+    materials::Material,
+    ray::{Ray, RayDifferential, RayKind, ShadowBias},
+    shapes::Shape,
+    spectrum::Radiance,
+    transformations::{Transform, IDENTITY},
+    tuple::{Point, Vector},
+};
 
 #[derive(Copy, Clone, Debug)]
 pub struct Intersection<'a> {
     pub t: f32,
     pub object: &'a dyn Shape,
+    /// The shape actually hit, when `object` wraps shared geometry.
+    pub inner: Option<&'a dyn Shape>,
+    /// The transforms of the groups `inner` is nested in inside `object`,
+    /// combined, like `groups` is for `object`.
+    pub inner_groups: Transform,
+    /// Where on the surface the hit fell, for shapes that track it, such
+    /// as the barycentric coordinates of a hit on a triangle.
+    pub u: f32,
+    pub v: f32,
+    /// The transforms of the groups `object` is nested in, combined, taking
+    /// its parent's space out to the space of the ray that found it.
+    pub groups: Transform,
 }
 
 impl<'a> Intersection<'a> {
     pub fn new(t: f32, object: &'a dyn Shape) -> Self {
-        Self { t, object }
+        Self {
+            t,
+            object,
+            inner: None,
+            inner_groups: IDENTITY,
+            u: 0.0,
+            v: 0.0,
+            groups: IDENTITY,
+        }
     }
 
-    pub fn prepare_computations(&self, ray: Ray, intersections: &[Intersection]) -> Computations {
-        let point = ray.position(self.t);
-        let eyev = -ray.direction;
-        let mut normalv = self.object.normal_at(point.x(), point.y(), point.z());
-        let reflectv = ray.direction.reflect(normalv);
+    /// Claims `inner`, a hit on the geometry `object` wraps, keeping where
+    /// on the surface it fell and the groups it was found in.
+    pub fn inner(self, inner: Intersection<'a>) -> Self {
+        Self {
+            inner: Some(inner.object),
+            inner_groups: inner.groups,
+            u: inner.u,
+            v: inner.v,
+            ..self
+        }
+    }
 
-        let inside = normalv.dot(eyev) < 0.0;
-        if inside {
-            normalv = -normalv;
+    /// The hit on the wrapped geometry as that geometry found it, for its
+    /// normal and surface color.
+    pub fn inner_hit(&self) -> Option<Intersection<'a>> {
+        self.inner.map(|inner| Intersection {
+            object: inner,
+            inner: None,
+            inner_groups: IDENTITY,
+            groups: self.inner_groups,
+            ..*self
+        })
+    }
+
+    pub fn uv(self, u: f32, v: f32) -> Self {
+        Self { u, v, ..self }
+    }
+
+    /// The intersection as seen from outside a group with `transform`.
+    pub fn within(self, transform: &Transform) -> Self {
+        Self {
+            groups: *transform * self.groups,
+            ..self
         }
+    }
+
+    pub fn prepare_computations(
+        &self,
+        ray: Ray,
+        intersections: &[Intersection<'a>],
+    ) -> Computations<'a> {
+        self.prepare_computations_with(ray, intersections, |object| object.material())
+    }
 
-        let mut n1 = 0.0;
-        let mut n2 = 0.0;
-        let mut containers: Vec<&dyn Shape> = vec![];
+    /// `prepare_computations`, with `material_of` giving the material each
+    /// shape is shaded with, such as one it inherits from its groups.
+    pub fn prepare_computations_with(
+        &self,
+        ray: Ray,
+        intersections: &[Intersection<'a>],
+        material_of: impl Fn(&'a dyn Shape) -> &'a Material,
+    ) -> Computations<'a> {
+        let point = ray.position(self.t);
+        let eyev = -ray.direction;
+        let material = self
+            .object
+            .material_at(self)
+            .unwrap_or_else(|| material_of(self.object));
+        let outward = |n: Vector| if material.flip_normal { -n } else { n };
+        let normal = outward(self.object.normal_at(point, Some(self)));
+        let reflectv = ray.direction.reflect(normal);
+
+        // Two-sided surfaces turn their normal round to face the ray; the
+        // offset points go on the ray's side either way.
+        let inside = normal.dot(eyev) < 0.0;
+        let facing = if inside { -normal } else { normal };
+        let turned = inside && material.two_sided;
+        let normalv = if turned { -normal } else { normal };
+
+        let (mut n1_object, mut n2_object) = (None, None);
+        let mut containers: Vec<&'a dyn Shape> = vec![];
         for intersection in intersections {
             if intersection == self {
-                if containers.is_empty() {
-                    n1 = 1.0
-                } else {
-                    n1 = containers.last().unwrap().material().refractive_index
-                }
+                n1_object = containers.last().copied();
             }
 
             if containers.contains(&intersection.object) {
@@ -46,27 +129,49 @@ impl<'a> Intersection<'a> {
             }
 
             if intersection == self {
-                if containers.is_empty() {
-                    n2 = 1.0
-                } else {
-                    n2 = containers.last().unwrap().material().refractive_index
-                }
+                n2_object = containers.last().copied();
                 break;
             }
         }
-
+        let n1_material = n1_object.map(&material_of);
+        let n2_material = n2_object.map(&material_of);
+        let index = |material: Option<&Material>| material.map_or(1.0, |m| m.refractive_index);
+
+        let differential = ray
+            .differential
+            .map(|d| d.transfer(ray.direction, self.t, facing));
+        let normal_differential = differential.map(|d| {
+            let turn = |dp: Vector| {
+                let n = outward(self.object.normal_at(point + dp, Some(self)));
+                (if turned { -n } else { n }) - normalv
+            };
+            [turn(d.dpdx), turn(d.dpdy)]
+        });
+
+        let offset = ray.bias.offset(point, self.t * ray.direction.magnitude());
         Computations {
             t: self.t,
             object: self.object,
+            surface_color: self.object.surface_color(self),
+            material,
+            groups: self.groups,
+            footprint: differential.map_or(ray.spread * self.t, |d| d.footprint()),
+            differential,
+            normal_differential,
             point,
-            over_point: point + normalv * EPSILON,
-            under_point: point - normalv * EPSILON,
+            over_point: point + facing * offset,
+            under_point: point - facing * offset,
             eyev,
             normalv,
             reflectv,
             inside,
-            n1,
-            n2,
+            n1: index(n1_material),
+            n2: index(n2_material),
+            n1_object,
+            n1_material,
+            n2_material,
+            channel: ray.channel,
+            bias: ray.bias,
         }
     }
 }
@@ -75,6 +180,12 @@ impl Intersection<'_> {
     pub fn hit<'a>(xs: &'a [Intersection]) -> Option<&'a Intersection<'a>> {
         xs.iter().filter(|x| x.t >= 0.0).min()
     }
+
+    /// Like `hit`, but binary searches `xs`, which must already be sorted
+    /// by `t`. An unsorted slice gives an arbitrary answer.
+    pub fn hit_unchecked<'a, 'b>(xs: &'b [Intersection<'a>]) -> Option<&'b Intersection<'a>> {
+        xs.get(xs.partition_point(|x| x.t < 0.0))
+    }
 }
 
 impl PartialEq for Intersection<'_> {
@@ -85,15 +196,116 @@ impl PartialEq for Intersection<'_> {
 
 impl PartialOrd for Intersection<'_> {
     fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
-        Some(float_cmp(self.t, other.t))
+        Some(self.cmp(other))
     }
 }
 
 impl Eq for Intersection<'_> {}
 
+/// Orders by `t` exactly. An epsilon-tolerant comparison isn't transitive,
+/// so sorting by one can panic or leave hits close to zero out of order.
 impl Ord for Intersection<'_> {
     fn cmp(&self, other: &Self) -> Ordering {
-        float_cmp(self.t, other.t)
+        self.t.total_cmp(&other.t)
+    }
+}
+
+/// Intersections kept sorted by `t`, so the hit is the first one that isn't
+/// behind the ray's origin.
+#[derive(Clone, Debug, Default)]
+pub struct Intersections<'a>(Vec<Intersection<'a>>);
+
+impl<'a> Intersections<'a> {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Inserts after any intersections with the same `t`.
+    pub fn insert(&mut self, intersection: Intersection<'a>) {
+        let index = self.0.partition_point(|x| x.t <= intersection.t);
+        self.0.insert(index, intersection);
+    }
+
+    pub fn merge(self, other: Intersections<'a>) -> Self {
+        let mut merged = Vec::with_capacity(self.len() + other.len());
+        let mut left = self.0.into_iter().peekable();
+        let mut right = other.0.into_iter().peekable();
+        loop {
+            let next = match (left.peek(), right.peek()) {
+                (Some(l), Some(r)) if r.t < l.t => right.next(),
+                (Some(_), _) => left.next(),
+                (None, _) => right.next(),
+            };
+            match next {
+                Some(intersection) => merged.push(intersection),
+                None => break,
+            }
+        }
+        Self(merged)
+    }
+
+    pub fn hit(&self) -> Option<&Intersection<'a>> {
+        Intersection::hit_unchecked(&self.0)
+    }
+
+    /// The hit, passing over any intersections with the shape `id`. Useful
+    /// for a ray leaving a surface that would otherwise find itself again.
+    pub fn hit_excluding(&self, id: Uuid) -> Option<&Intersection<'a>> {
+        let first = self.0.partition_point(|x| x.t < 0.0);
+        self.0[first..].iter().find(|x| x.object.id() != id)
+    }
+
+    pub fn for_shape(&self, shape: &dyn Shape) -> Self {
+        Self(
+            self.0
+                .iter()
+                .filter(|x| x.object.shape_eq(shape))
+                .copied()
+                .collect(),
+        )
+    }
+
+    pub fn into_vec(self) -> Vec<Intersection<'a>> {
+        self.0
+    }
+}
+
+impl<'a> Deref for Intersections<'a> {
+    type Target = [Intersection<'a>];
+
+    fn deref(&self) -> &Self::Target {
+        &self.0
+    }
+}
+
+impl<'a> From<Vec<Intersection<'a>>> for Intersections<'a> {
+    fn from(mut intersections: Vec<Intersection<'a>>) -> Self {
+        intersections.sort();
+        Self(intersections)
+    }
+}
+
+impl<'a> FromIterator<Intersection<'a>> for Intersections<'a> {
+    fn from_iter<I: IntoIterator<Item = Intersection<'a>>>(iter: I) -> Self {
+        Self::from(iter.into_iter().collect::<Vec<_>>())
+    }
+}
+
+impl<'a> IntoIterator for Intersections<'a> {
+    type Item = Intersection<'a>;
+    type IntoIter = std::vec::IntoIter<Intersection<'a>>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.0.into_iter()
+    }
+}
+
+impl<'a, 'b> IntoIterator for &'b Intersections<'a> {
+    type Item = &'b Intersection<'a>;
+    type IntoIter = std::slice::Iter<'b, Intersection<'a>>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.0.iter()
     }
 }
 
@@ -101,23 +313,53 @@ impl Ord for Intersection<'_> {
 pub struct Computations<'a> {
     t: f32,
     pub object: &'a dyn Shape,
-    pub point: Tuple,
-    pub over_point: Tuple,
-    pub under_point: Tuple,
-    pub eyev: Tuple,
-    pub normalv: Tuple,
-    pub reflectv: Tuple,
+    /// A color carried by the surface at the hit, such as one blended from
+    /// a triangle's vertex colors, standing in for the material's own.
+    pub surface_color: Option<Color>,
+    /// The material the hit is shaded with, before patterns and any
+    /// surface color are applied.
+    pub material: &'a Material,
+    /// The transforms of the groups the object is nested in.
+    pub groups: Transform,
+    /// How wide the patch of surface the ray stands for is at the hit.
+    pub footprint: f32,
+    /// The ray's differential where it landed, if it was tracked.
+    pub differential: Option<RayDifferential>,
+    /// How the normal turns across the differential's offsets.
+    normal_differential: Option<[Vector; 2]>,
+    pub point: Point,
+    pub over_point: Point,
+    pub under_point: Point,
+    pub eyev: Vector,
+    pub normalv: Vector,
+    pub reflectv: Vector,
     pub n1: f32,
     pub n2: f32,
+    /// What the ray is leaving, if not empty space.
+    n1_object: Option<&'a dyn Shape>,
+    /// The materials of what the ray is leaving and entering.
+    n1_material: Option<&'a Material>,
+    n2_material: Option<&'a Material>,
+    pub channel: Option<usize>,
+    pub bias: ShadowBias,
     inside: bool,
 }
 
 impl<'a> Computations<'a> {
+    /// The share of light reflected rather than refracted, for the color
+    /// channel the ray carries, if it carries just one, or for all of them.
     pub fn schlick(&self) -> f32 {
+        self.schlick_at(self.channel.map(Color::wavelength))
+    }
+
+    /// `schlick` for light of one wavelength, in nanometres, or for white
+    /// light bending as `n1` and `n2` say.
+    pub fn schlick_at(&self, wavelength: Option<f32>) -> f32 {
+        let (n1, n2) = self.indices_at(wavelength);
         let mut cos = self.eyev.dot(self.normalv);
 
-        if self.n1 > self.n2 {
-            let n = self.n1 / self.n2;
+        if n1 > n2 {
+            let n = n1 / n2;
             let sin2_t = n.powi(2) * (1.0 - cos.powi(2));
             if sin2_t > 1.0 {
                 return 1.0;
@@ -128,14 +370,166 @@ impl<'a> Computations<'a> {
             cos = cos_t
         }
 
-        let r0 = ((self.n1 - self.n2) / (self.n1 + self.n2)).powi(2);
+        let r0 = ((n1 - n2) / (n1 + n2)).powi(2);
         r0 + (1.0 - r0) * (1.0 - cos).powf(5.0)
     }
+
+    /// The refracted direction for the color channel the ray carries, if
+    /// it carries just one, or for all of them.
+    pub fn refracted_direction(&self) -> Option<Vector> {
+        self.refracted_direction_at(self.channel.map(Color::wavelength))
+    }
+
+    /// Whether the colors bend by different amounts crossing this surface.
+    pub fn is_dispersive(&self) -> bool {
+        [self.n1_material, self.n2_material]
+            .iter()
+            .flatten()
+            .any(|m| m.abbe_number.is_some())
+    }
+
+    /// The object the ray crossed to reach the hit, if it didn't come
+    /// through empty space.
+    pub fn medium(&self) -> Option<&'a dyn Shape> {
+        self.n1_object
+    }
+
+    /// The refracted direction for light of one wavelength, in nanometres,
+    /// or for white light bending as `n1` and `n2` say.
+    pub fn refracted_direction_at(&self, wavelength: Option<f32>) -> Option<Vector> {
+        let (n_ratio, cos_i, cos_t) = self.refraction_angles(wavelength)?;
+        Some(self.normalv * (n_ratio * cos_i - cos_t) - self.eyev * n_ratio)
+    }
+
+    /// The ray reflected from the hit.
+    pub fn reflected_ray(&self) -> Ray {
+        Ray::new(self.over_point, self.reflectv)
+            .kind(RayKind::Reflection)
+            .channel(self.channel)
+            .bias(self.bias)
+            .leaving(Some(self.object.id()))
+            .differential(self.reflected_differential())
+    }
+
+    /// The ray refracted through the hit carrying `channel`, bent as light
+    /// of `wavelength` is, unless it's totally internally reflected.
+    pub fn refracted_ray(&self, channel: Option<usize>, wavelength: Option<f32>) -> Option<Ray> {
+        let direction = self.refracted_direction_at(wavelength)?;
+        Some(
+            Ray::new(self.under_point, direction)
+                .kind(RayKind::Refraction)
+                .channel(channel)
+                .bias(self.bias)
+                .leaving(Some(self.object.id()))
+                .differential(self.refracted_differential_at(wavelength)),
+        )
+    }
+
+    /// Whether light carried as `samples` wavelengths splits into one ray
+    /// per wavelength refracting here.
+    pub fn splits_refraction(&self, samples: usize) -> bool {
+        self.channel.is_none() && samples > 1 && self.is_dispersive()
+    }
+
+    /// `surface` plus the `reflected` and `refracted` light, shared out by
+    /// the Fresnel effect where `material` both reflects and refracts.
+    pub fn with_bounces<R: Radiance>(
+        &self,
+        material: &Material,
+        surface: R,
+        reflected: R,
+        refracted: R,
+    ) -> R {
+        if material.reflective_at(self.object, self.over_point) <= 0.0
+            || material.transparency_at(self.object, self.over_point) <= 0.0
+        {
+            return surface + reflected + refracted;
+        }
+        let mix = |wavelength: Option<f32>| {
+            let reflectance = self.schlick_at(wavelength);
+            surface + reflected * reflectance + refracted * (1.0 - reflectance)
+        };
+        if self.splits_refraction(R::SAMPLES) {
+            (0..R::SAMPLES).fold(R::default(), |sum, i| {
+                sum + mix(Some(R::wavelength(i))).only(i)
+            })
+        } else {
+            mix(self.channel.map(R::wavelength))
+        }
+    }
+
+    /// The differential of the reflected ray, if the incoming one had one.
+    pub fn reflected_differential(&self) -> Option<RayDifferential> {
+        let differential = self.differential?;
+        let [dndx, dndy] = self.normal_differential?;
+        let (direction, n) = (-self.eyev, self.normalv);
+
+        let reflect = |dd: Vector, dn: Vector| {
+            dd - (n * (dd.dot(n) + direction.dot(dn)) + dn * direction.dot(n)) * 2.0
+        };
+        Some(differential.leaving(
+            reflect(differential.dddx, dndx),
+            reflect(differential.dddy, dndy),
+        ))
+    }
+
+    /// The differential of the ray refracted at `wavelength`, if the
+    /// incoming ray had one and there is a refracted ray.
+    pub fn refracted_differential_at(&self, wavelength: Option<f32>) -> Option<RayDifferential> {
+        let differential = self.differential?;
+        let [dndx, dndy] = self.normal_differential?;
+        let (n_ratio, cos_i, cos_t) = self.refraction_angles(wavelength)?;
+        let n = self.normalv;
+        let mu = n_ratio * cos_i - cos_t;
+
+        let refract = |dd: Vector, dn: Vector| {
+            let dcos_i = -dd.dot(n) + self.eyev.dot(dn);
+            let dmu = (n_ratio - n_ratio.powi(2) * cos_i / cos_t) * dcos_i;
+            dd * n_ratio + dn * mu + n * dmu
+        };
+        Some(differential.leaving(
+            refract(differential.dddx, dndx),
+            refract(differential.dddy, dndy),
+        ))
+    }
+
+    /// The ratio of the refractive indices at `wavelength`, and the
+    /// cosines of the angles to the normal coming in and going out, unless
+    /// the ray is totally internally reflected.
+    fn refraction_angles(&self, wavelength: Option<f32>) -> Option<(f32, f32, f32)> {
+        let (n1, n2) = self.indices_at(wavelength);
+        self.angles_for(n1 / n2)
+    }
+
+    /// The refractive indices either side of the surface for light of
+    /// `wavelength`, or `n1` and `n2` for white light.
+    fn indices_at(&self, wavelength: Option<f32>) -> (f32, f32) {
+        match wavelength {
+            Some(wavelength) => {
+                let n = |material: Option<&Material>| {
+                    material.map_or(1.0, |m| m.refractive_index_at(wavelength))
+                };
+                (n(self.n1_material), n(self.n2_material))
+            }
+            None => (self.n1, self.n2),
+        }
+    }
+
+    fn angles_for(&self, n_ratio: f32) -> Option<(f32, f32, f32)> {
+        let cos_i = self.eyev.dot(self.normalv);
+        let sin2_t = n_ratio.powi(2) * (1.0 - cos_i.powi(2));
+
+        if sin2_t > 1.0 {
+            None
+        } else {
+            Some((n_ratio, cos_i, (1.0 - sin2_t).sqrt()))
+        }
+    }
 }
 
 #[cfg(test)]
 mod tests {
-    use std::f32::consts::SQRT_2;
+    use std::f32::consts::{PI, SQRT_2};
 
     use crate::{
         float_eq,
@@ -144,6 +538,7 @@ mod tests {
         shapes::{plane::Plane, sphere::Sphere},
         test::sqrt_n_over_n,
         transformations::Transform,
+        EPSILON,
     };
 
     use super::*;
@@ -197,6 +592,19 @@ mod tests {
         assert!(i.is_none());
     }
 
+    #[test]
+    fn intersections_nearly_at_zero_are_still_sorted() {
+        let p = Plane::default();
+        let s = Sphere::default();
+        let xs = Intersections::from(vec![
+            Intersection::new(0.00003, &p),
+            Intersection::new(-0.00005, &s),
+        ]);
+
+        assert!(float_eq(xs.hit().unwrap().t, 0.00003));
+        assert!(float_eq(xs[0].t, -0.00005));
+    }
+
     #[test]
     fn the_hit_is_always_the_lowest_nonnegative_intersection() {
         let s = Sphere::default();
@@ -211,33 +619,203 @@ mod tests {
         assert!(float_eq(i.unwrap().t, i4.t));
     }
 
+    #[test]
+    fn intersections_are_kept_sorted() {
+        let s = Sphere::default();
+        let xs = Intersections::from(vec![
+            Intersection::new(5.0, &s),
+            Intersection::new(-3.0, &s),
+            Intersection::new(2.0, &s),
+        ]);
+
+        let ts = xs.iter().map(|x| x.t).collect::<Vec<_>>();
+
+        assert_eq!(ts, vec![-3.0, 2.0, 5.0]);
+    }
+
+    #[test]
+    fn inserting_an_intersection_keeps_the_order() {
+        let s = Sphere::default();
+        let mut xs =
+            Intersections::from(vec![Intersection::new(1.0, &s), Intersection::new(4.0, &s)]);
+
+        xs.insert(Intersection::new(2.5, &s));
+        xs.insert(Intersection::new(-1.0, &s));
+        xs.insert(Intersection::new(9.0, &s));
+
+        let ts = xs.iter().map(|x| x.t).collect::<Vec<_>>();
+        assert_eq!(ts, vec![-1.0, 1.0, 2.5, 4.0, 9.0]);
+    }
+
+    #[test]
+    fn merging_two_collections_of_intersections() {
+        let s = Sphere::default();
+        let xs1: Intersections = vec![1.0, 3.0, 5.0]
+            .into_iter()
+            .map(|t| Intersection::new(t, &s))
+            .collect();
+        let xs2: Intersections = vec![2.0, 3.0, 6.0]
+            .into_iter()
+            .map(|t| Intersection::new(t, &s))
+            .collect();
+
+        let xs = xs1.merge(xs2);
+
+        let ts = xs.iter().map(|x| x.t).collect::<Vec<_>>();
+        assert_eq!(ts, vec![1.0, 2.0, 3.0, 3.0, 5.0, 6.0]);
+    }
+
+    #[test]
+    fn the_hit_of_a_collection_skips_intersections_behind_the_ray() {
+        let s = Sphere::default();
+        let xs = Intersections::from(vec![
+            Intersection::new(5.0, &s),
+            Intersection::new(7.0, &s),
+            Intersection::new(-3.0, &s),
+            Intersection::new(2.0, &s),
+        ]);
+
+        assert!(float_eq(xs.hit().unwrap().t, 2.0));
+        assert!(Intersections::new().hit().is_none());
+    }
+
+    #[test]
+    fn the_hit_of_a_sorted_slice() {
+        let s = Sphere::default();
+        let xs = vec![
+            Intersection::new(-3.0, &s),
+            Intersection::new(-0.0, &s),
+            Intersection::new(2.0, &s),
+        ];
+
+        assert!(float_eq(Intersection::hit_unchecked(&xs).unwrap().t, 0.0));
+        assert!(Intersection::hit_unchecked(&xs[..1]).is_none());
+        assert!(Intersection::hit_unchecked(&[]).is_none());
+    }
+
+    #[test]
+    fn the_hit_of_a_collection_agrees_with_a_linear_scan() {
+        let s = Sphere::default();
+        for ts in &[
+            vec![1.0, 2.0],
+            vec![-1.0, 1.0],
+            vec![-2.0, -1.0],
+            vec![-3.0, 7.0, 5.0, 2.0, -1.0, 0.0],
+        ] {
+            let xs: Intersections = ts.iter().map(|&t| Intersection::new(t, &s)).collect();
+
+            assert_eq!(xs.hit(), Intersection::hit(&xs));
+        }
+    }
+
+    #[test]
+    fn the_hit_excluding_a_shape() {
+        let s1 = Sphere::default();
+        let s2 = Sphere::default();
+        let xs = Intersections::from(vec![
+            Intersection::new(-1.0, &s2),
+            Intersection::new(0.0001, &s1),
+            Intersection::new(3.0, &s2),
+            Intersection::new(4.0, &s1),
+        ]);
+
+        assert!(float_eq(xs.hit_excluding(s1.id()).unwrap().t, 3.0));
+        assert!(float_eq(xs.hit_excluding(s2.id()).unwrap().t, 0.0001));
+        assert!(xs.for_shape(&s1).hit_excluding(s1.id()).is_none());
+    }
+
+    #[test]
+    fn filtering_intersections_by_shape() {
+        let s1 = Sphere::default();
+        let s2 = Sphere::default();
+        let xs = Intersections::from(vec![
+            Intersection::new(1.0, &s1),
+            Intersection::new(2.0, &s2),
+            Intersection::new(3.0, &s1),
+        ]);
+
+        let filtered = xs.for_shape(&s1);
+
+        assert_eq!(filtered.len(), 2);
+        assert!(filtered.iter().all(|x| x.object.shape_eq(&s1)));
+    }
+
     #[test]
     fn precomputing_the_state_of_an_intersection() {
-        let r = Ray::new(Tuple::point(0.0, 0.0, -5.0), Tuple::vector(0.0, 0.0, 1.0));
+        let r = Ray::new(Point::new(0.0, 0.0, -5.0), Vector::new(0.0, 0.0, 1.0));
         let shape = Sphere::default();
         let i = Intersection::new(4.0, &shape);
 
         let comps = i.prepare_computations(r, &[i]);
 
         assert!(float_eq(comps.t, i.t));
-        assert_eq!(comps.point, Tuple::point(0.0, 0.0, -1.0));
-        assert_eq!(comps.eyev, Tuple::vector(0.0, 0.0, -1.0));
+        assert_eq!(comps.point, Point::new(0.0, 0.0, -1.0));
+        assert_eq!(comps.eyev, Vector::new(0.0, 0.0, -1.0));
         assert!(!comps.inside);
-        assert_eq!(comps.normalv, Tuple::vector(0.0, 0.0, -1.0));
+        assert_eq!(comps.normalv, Vector::new(0.0, 0.0, -1.0));
     }
 
     #[test]
     fn the_hit_when_an_intersection_occurs_on_the_inside() {
-        let r = Ray::new(Tuple::point(0.0, 0.0, 0.0), Tuple::vector(0.0, 0.0, 1.0));
+        let r = Ray::new(Point::new(0.0, 0.0, 0.0), Vector::new(0.0, 0.0, 1.0));
         let shape = Sphere::default();
         let i = Intersection::new(1.0, &shape);
 
         let comps = i.prepare_computations(r, &[i]);
 
-        assert_eq!(comps.point, Tuple::point(0.0, 0.0, 1.0));
-        assert_eq!(comps.eyev, Tuple::vector(0.0, 0.0, -1.0));
+        assert_eq!(comps.point, Point::new(0.0, 0.0, 1.0));
+        assert_eq!(comps.eyev, Vector::new(0.0, 0.0, -1.0));
         assert!(comps.inside);
-        assert_eq!(comps.normalv, Tuple::vector(0.0, 0.0, -1.0));
+        assert_eq!(comps.normalv, Vector::new(0.0, 0.0, -1.0));
+    }
+
+    fn from_below() -> Ray {
+        Ray::new(Point::new(0.0, -1.0, 0.0), Vector::new(0.0, 1.0, 0.0))
+    }
+
+    #[test]
+    fn a_two_sided_surface_seen_from_behind_faces_the_ray() {
+        let shape = Plane::default();
+        let i = Intersection::new(1.0, &shape);
+
+        let comps = i.prepare_computations(from_below(), &[i]);
+
+        assert!(comps.inside);
+        assert_eq!(comps.normalv, Vector::new(0.0, -1.0, 0.0));
+        assert!(comps.over_point.y() < 0.0);
+    }
+
+    #[test]
+    fn a_single_sided_surface_seen_from_behind_faces_away() {
+        let shape = Plane::default().with_material(Material::default().two_sided(false));
+        let i = Intersection::new(1.0, &shape);
+
+        let comps = i.prepare_computations(from_below(), &[i]);
+
+        assert!(comps.inside);
+        assert_eq!(comps.normalv, Vector::new(0.0, 1.0, 0.0));
+        assert!(comps.over_point.y() < 0.0);
+        assert!(comps.under_point.y() > 0.0);
+    }
+
+    #[test]
+    fn a_flipped_normal_swaps_inside_and_outside() {
+        let material = Material::default().flip_normal(true);
+        let plane = Plane::default().with_material(material.clone().two_sided(false));
+        let sphere = Sphere::default().with_material(material);
+        let r = Ray::new(Point::new(0.0, 0.0, -5.0), Vector::new(0.0, 0.0, 1.0));
+        let (i1, i2) = (
+            Intersection::new(1.0, &plane),
+            Intersection::new(4.0, &sphere),
+        );
+
+        let below = i1.prepare_computations(from_below(), &[i1]);
+        let outside = i2.prepare_computations(r, &[i2]);
+
+        assert!(!below.inside);
+        assert_eq!(below.normalv, Vector::new(0.0, -1.0, 0.0));
+        assert!(outside.inside);
+        assert_eq!(outside.normalv, Vector::new(0.0, 0.0, -1.0));
     }
 
     #[test]
@@ -254,6 +832,34 @@ mod tests {
         assert!(comps.point.z() > comps.over_point.z());
     }
 
+    macro_rules! shadow_bias_tests {
+        ($($name:ident: $value:expr,)*) => {
+            $(
+                #[test]
+                fn $name() {
+                    let (bias, origin, expected) = $value;
+                    let r = Ray::new(origin, Vector::new(0.0, -1.0, 0.0)).bias(bias);
+                    let shape = Plane::default();
+                    let i = Intersection::new(origin.y(), &shape);
+
+                    let comps = i.prepare_computations(r, &[i]);
+
+                    assert!(float_eq(comps.over_point.y(), expected));
+                    assert!(float_eq(comps.under_point.y(), -expected));
+                    assert_eq!(comps.bias, bias);
+                }
+            )*
+        }
+    }
+
+    shadow_bias_tests! {
+        a_constant_bias_is_the_same_everywhere: (ShadowBias::Constant(0.01), Point::new(5000.0, 50.0, 0.0), 0.01),
+        a_scaled_bias_grows_with_the_hit_point: (ShadowBias::Scaled(EPSILON), Point::new(5000.0, 50.0, 0.0), 0.5),
+        a_scaled_bias_near_the_origin_stays_constant: (ShadowBias::Scaled(EPSILON), Point::new(0.5, 5.0, 0.0), EPSILON),
+        an_adaptive_bias_grows_with_distance: (ShadowBias::Adaptive(EPSILON), Point::new(5000.0, 50.0, 0.0), 50.0 * EPSILON),
+        an_adaptive_bias_close_up_stays_constant: (ShadowBias::Adaptive(EPSILON), Point::new(0.0, 0.5, 0.0), EPSILON),
+    }
+
     #[test]
     fn precomputing_the_reflection_vector() {
         let shape = Plane::default();
@@ -264,10 +870,7 @@ mod tests {
 
         let comps = i.prepare_computations(r, &[i]);
 
-        assert_eq!(
-            comps.reflectv,
-            Tuple::vector(0.0, SQRT_2 / 2.0, SQRT_2 / 2.0)
-        );
+        assert_eq!(comps.reflectv, Vector::new(0.0, SQRT_2 / 2.0, SQRT_2 / 2.0));
     }
 
     macro_rules! find_n1_and_n2 {
@@ -368,4 +971,98 @@ mod tests {
 
         assert!(float_eq(reflectance, 0.4887307));
     }
+
+    #[test]
+    fn the_schlick_approximation_follows_the_channel_the_ray_carries() {
+        let flint = Sphere::default().with_material(
+            Material::default()
+                .transparency(1.0)
+                .refractive_index(1.5)
+                .abbe_number(20.0),
+        );
+        let r = Ray::default()
+            .origin(0.0, 0.99, -2.0)
+            .direction(0.0, 0.0, 1.0);
+        let xs = vec![Intersection::new(1.8589, &flint)];
+        let reflectance = |channel| {
+            xs[0]
+                .prepare_computations(r.channel(channel), &xs)
+                .schlick()
+        };
+
+        assert!(reflectance(Some(0)) < reflectance(Some(1)));
+        assert!(reflectance(Some(1)) < reflectance(Some(2)));
+        assert!(float_eq(reflectance(Some(1)), reflectance(None)));
+    }
+
+    /// A ray whose direction turns by a hundredth with each pixel across
+    /// and down.
+    fn differential_ray(origin: Point, direction: Vector) -> Ray {
+        Ray::new(origin, direction).differential(Some(RayDifferential {
+            dddx: Vector::new(0.01, 0.0, 0.0),
+            dddy: Vector::new(0.0, 0.01, 0.0),
+            ..RayDifferential::default()
+        }))
+    }
+
+    #[test]
+    fn a_ray_differential_is_carried_to_the_surface_it_lands_on() {
+        let shape = Plane::default().with_transform(Transform::rotation_x(PI / 2.0));
+        let r = differential_ray(Point::new(0.0, 0.0, -2.0), Vector::new(0.0, 0.0, 1.0));
+        let i = Intersection::new(2.0, &shape);
+
+        let comps = i.prepare_computations(r, &[i]);
+        let d = comps.differential.unwrap();
+
+        assert_eq!(d.dpdx, Vector::new(0.02, 0.0, 0.0));
+        assert_eq!(d.dpdy, Vector::new(0.0, 0.02, 0.0));
+        assert!(float_eq(comps.footprint, 0.02));
+    }
+
+    #[test]
+    fn reflecting_a_ray_differential_off_a_flat_mirror() {
+        let shape = Plane::default().with_transform(Transform::rotation_x(PI / 2.0));
+        let r = differential_ray(Point::new(0.0, 0.0, -2.0), Vector::new(0.0, 0.0, 1.0));
+        let i = Intersection::new(2.0, &shape);
+
+        let comps = i.prepare_computations(r, &[i]);
+        let d = comps.reflected_differential().unwrap();
+
+        assert_eq!(d.dddx, Vector::new(0.01, 0.0, 0.0));
+        assert_eq!(d.dpdx, Vector::new(0.02, 0.0, 0.0));
+    }
+
+    #[test]
+    fn a_curved_mirror_spreads_a_ray_differential_further() {
+        let shape = Sphere::default();
+        let r = differential_ray(Point::new(0.0, 0.0, -5.0), Vector::new(0.0, 0.0, 1.0));
+        let i = Intersection::new(4.0, &shape);
+
+        let comps = i.prepare_computations(r, &[i]);
+        let d = comps.reflected_differential().unwrap();
+
+        // Off a unit sphere the reflection turns twice as fast as the
+        // normal, which turns as fast as the point moves.
+        assert!((d.dddx.x() - 0.09).abs() < 0.001);
+        assert!((d.dddy.y() - 0.09).abs() < 0.001);
+    }
+
+    #[test]
+    fn refracting_a_ray_differential_into_flat_glass() {
+        let shape = Plane::default()
+            .with_transform(Transform::rotation_x(PI / 2.0))
+            .with_material(Material::default().transparency(1.0).refractive_index(1.5));
+        let r = differential_ray(Point::new(0.0, 0.0, -2.0), Vector::new(0.0, 0.0, 1.0));
+        let i = Intersection::new(2.0, &shape);
+
+        let comps = i.prepare_computations(r, &[i]);
+        let d = comps.refracted_differential_at(None).unwrap();
+
+        assert_eq!(d.dddx, Vector::new(0.01 / 1.5, 0.0, 0.0));
+        assert_eq!(d.dpdy, Vector::new(0.0, 0.02, 0.0));
+        assert_eq!(
+            Ray::new(Point::new(0.0, 0.0, -2.0), Vector::new(0.0, 0.0, 1.0)).differential,
+            None
+        );
+    }
 }
@@ -0,0 +1,360 @@
+use std::cmp::Ordering;
+
+use crate::{bounds::BoundingBox, ray::Ray, stats, tuple::Point};
+
+const TRAVERSAL_COST: f32 = 1.0;
+const INTERSECTION_COST: f32 = 80.0;
+/// How much cheaper a split is made to look for each side it leaves empty,
+/// since rays crossing empty space cost next to nothing.
+const EMPTY_BONUS: f32 = 0.5;
+
+/// A k-d tree over a list of boxes, answering the same question as `Bvh`:
+/// which of them a ray might hit. Each node splits space in two by an
+/// axis-aligned plane, placed where the surface area heuristic expects
+/// rays to find the fewest items, so an item crossing a plane is kept on
+/// both sides. Items with infinite extent are kept aside and returned for
+/// every ray.
+#[derive(Clone, Debug, PartialEq)]
+pub struct KdTree {
+    bounds: Vec<BoundingBox>,
+    root_bounds: BoundingBox,
+    nodes: Vec<Node>,
+    unbounded: Vec<usize>,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+enum Node {
+    Leaf {
+        items: Vec<usize>,
+    },
+    Branch {
+        axis: usize,
+        split: f32,
+        below: usize,
+        above: usize,
+    },
+}
+
+impl KdTree {
+    /// Builds over `bounds`, identifying each item by its index.
+    pub fn build(bounds: &[BoundingBox]) -> Self {
+        let (mut bounded, unbounded): (Vec<usize>, Vec<usize>) =
+            (0..bounds.len()).partition(|&i| bounds[i].is_finite());
+        bounded.retain(|&i| !bounds[i].is_empty());
+
+        let root_bounds = bounded
+            .iter()
+            .fold(BoundingBox::default(), |b, &i| b.merge(bounds[i]));
+        let mut tree = Self {
+            bounds: bounds.to_vec(),
+            root_bounds,
+            nodes: vec![],
+            unbounded,
+        };
+        if !bounded.is_empty() {
+            let max_depth = 8 + (1.3 * (bounded.len() as f32).log2()).round() as usize;
+            tree.build_node(bounded, root_bounds, max_depth);
+        }
+        tree
+    }
+
+    /// Indices of every item whose box the ray passes through, in no
+    /// particular order.
+    pub fn candidates(&self, ray: Ray) -> Vec<usize> {
+        let mut found = vec![];
+        let mut visited = 0;
+        if let Some((t_min, t_max)) = self.root_bounds.ray_range(ray) {
+            let (t_min, t_max) = (t_min.max(ray.t_min), t_max.min(ray.t_max));
+            if !self.nodes.is_empty() && t_min <= t_max {
+                self.gather(0, ray, t_min, t_max, &mut found, &mut visited);
+            }
+        }
+        found.sort_unstable();
+        found.dedup();
+        stats::record_index_traversal(visited, found.len() as u64);
+
+        let mut candidates = self.unbounded.clone();
+        candidates.append(&mut found);
+        candidates
+    }
+
+    fn build_node(&mut self, items: Vec<usize>, node_bounds: BoundingBox, depth: usize) -> usize {
+        let index = self.nodes.len();
+        let split = match depth {
+            0 => None,
+            _ => self.best_split(&items, &node_bounds),
+        };
+        let (axis, split) = match split {
+            Some(split) => split,
+            None => {
+                self.nodes.push(Node::Leaf { items });
+                return index;
+            }
+        };
+
+        let bounds = &self.bounds;
+        let below_items = items
+            .iter()
+            .copied()
+            .filter(|&i| bounds[i].min[axis] < split || bounds[i].max[axis] <= split)
+            .collect();
+        let above_items = items
+            .into_iter()
+            .filter(|&i| bounds[i].max[axis] > split)
+            .collect();
+        let below_bounds =
+            BoundingBox::new(node_bounds.min, with_axis(node_bounds.max, axis, split));
+        let above_bounds =
+            BoundingBox::new(with_axis(node_bounds.min, axis, split), node_bounds.max);
+
+        // Reserve this node's slot before its children claim theirs.
+        self.nodes.push(Node::Leaf { items: vec![] });
+        let below = self.build_node(below_items, below_bounds, depth - 1);
+        let above = self.build_node(above_items, above_bounds, depth - 1);
+        self.nodes[index] = Node::Branch {
+            axis,
+            split,
+            below,
+            above,
+        };
+        index
+    }
+
+    /// The axis and plane the surface area heuristic finds cheapest to
+    /// split `items` by, or none if no split beats a leaf.
+    fn best_split(&self, items: &[usize], node_bounds: &BoundingBox) -> Option<(usize, f32)> {
+        let area = node_bounds.surface_area();
+        if items.len() <= 1 || area <= 0.0 {
+            return None;
+        }
+
+        let mut best = None;
+        let mut best_cost = INTERSECTION_COST * items.len() as f32;
+        for axis in 0..3 {
+            let (low, high) = (node_bounds.min[axis], node_bounds.max[axis]);
+            // Each item starts at its low edge and ends at its high one;
+            // at the same place, ends come first.
+            let mut edges = items
+                .iter()
+                .flat_map(|&i| {
+                    let b = &self.bounds[i];
+                    vec![(b.min[axis].max(low), true), (b.max[axis].min(high), false)]
+                })
+                .collect::<Vec<_>>();
+            edges.sort_by(|a, b| {
+                a.0.partial_cmp(&b.0)
+                    .unwrap_or(Ordering::Equal)
+                    .then(a.1.cmp(&b.1))
+            });
+
+            let (mut below, mut above) = (0, items.len());
+            for &(split, starts) in &edges {
+                if !starts {
+                    above -= 1;
+                }
+                if split > low && split < high {
+                    let below_area =
+                        BoundingBox::new(node_bounds.min, with_axis(node_bounds.max, axis, split))
+                            .surface_area();
+                    let above_area =
+                        BoundingBox::new(with_axis(node_bounds.min, axis, split), node_bounds.max)
+                            .surface_area();
+                    let bonus = if below == 0 || above == 0 {
+                        EMPTY_BONUS
+                    } else {
+                        0.0
+                    };
+                    let cost = TRAVERSAL_COST
+                        + INTERSECTION_COST
+                            * (1.0 - bonus)
+                            * (below_area / area * below as f32 + above_area / area * above as f32);
+                    if cost < best_cost {
+                        best_cost = cost;
+                        best = Some((axis, split));
+                    }
+                }
+                if starts {
+                    below += 1;
+                }
+            }
+        }
+        best
+    }
+
+    fn gather(
+        &self,
+        index: usize,
+        ray: Ray,
+        t_min: f32,
+        t_max: f32,
+        found: &mut Vec<usize>,
+        visited: &mut u64,
+    ) {
+        *visited += 1;
+        match self.nodes[index] {
+            Node::Leaf { ref items } => {
+                found.extend(items.iter().filter(|&&i| self.bounds[i].intersects(ray)))
+            }
+            Node::Branch {
+                axis,
+                split,
+                below,
+                above,
+            } => {
+                let (origin, direction) = (ray.origin[axis], ray.direction[axis]);
+                let (near, far) = if origin < split || (origin == split && direction <= 0.0) {
+                    (below, above)
+                } else {
+                    (above, below)
+                };
+                let t = (split - origin) / direction;
+                if !t.is_finite() {
+                    return self.gather(near, ray, t_min, t_max, found, visited);
+                }
+
+                // Before the split the ray is on the origin's side only when
+                // it crosses ahead of the origin.
+                let (before, after) = if t > 0.0 { (near, far) } else { (far, near) };
+                if t >= t_max {
+                    self.gather(before, ray, t_min, t_max, found, visited);
+                } else if t <= t_min {
+                    self.gather(after, ray, t_min, t_max, found, visited);
+                } else {
+                    self.gather(before, ray, t_min, t, found, visited);
+                    self.gather(after, ray, t, t_max, found, visited);
+                }
+            }
+        }
+    }
+}
+
+fn with_axis(point: Point, axis: usize, value: f32) -> Point {
+    let mut coordinates = [point.x(), point.y(), point.z()];
+    coordinates[axis] = value;
+    Point::new(coordinates[0], coordinates[1], coordinates[2])
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::tuple::Vector;
+
+    use super::*;
+
+    fn unit_box_at(x: f32, y: f32, z: f32) -> BoundingBox {
+        BoundingBox::new(
+            Point::new(x - 0.5, y - 0.5, z - 0.5),
+            Point::new(x + 0.5, y + 0.5, z + 0.5),
+        )
+    }
+
+    fn row_of_boxes() -> Vec<BoundingBox> {
+        (0..10)
+            .map(|i| unit_box_at(i as f32 * 2.0, 0.0, 0.0))
+            .collect()
+    }
+
+    #[test]
+    fn an_empty_tree_has_no_candidates() {
+        let tree = KdTree::build(&[]);
+        let r = Ray::new(Point::new(0.0, 0.0, -5.0), Vector::new(0.0, 0.0, 1.0));
+
+        assert!(tree.candidates(r).is_empty());
+    }
+
+    #[test]
+    fn a_ray_finds_only_the_box_it_passes_through() {
+        let tree = KdTree::build(&row_of_boxes());
+        let r = Ray::new(Point::new(6.0, 0.0, -5.0), Vector::new(0.0, 0.0, 1.0));
+
+        assert_eq!(tree.candidates(r), vec![3]);
+    }
+
+    #[test]
+    fn a_ray_along_the_row_finds_every_box() {
+        let tree = KdTree::build(&row_of_boxes());
+        let r = Ray::new(Point::new(-5.0, 0.0, 0.0), Vector::new(1.0, 0.0, 0.0));
+
+        assert_eq!(tree.candidates(r), (0..10).collect::<Vec<_>>());
+    }
+
+    #[test]
+    fn unbounded_items_are_always_candidates() {
+        let mut boxes = row_of_boxes();
+        boxes.push(BoundingBox::infinite());
+        let tree = KdTree::build(&boxes);
+        let r = Ray::new(Point::new(0.0, 5.0, 0.0), Vector::new(0.0, 1.0, 0.0));
+
+        assert_eq!(tree.candidates(r), vec![10]);
+    }
+
+    #[test]
+    fn the_tree_splits_off_empty_space() {
+        let tree = KdTree::build(&row_of_boxes());
+        let r = Ray::new(Point::new(0.0, 0.0, -5.0), Vector::new(0.0, 0.0, 1.0));
+
+        let (found, stats) = stats::collect(|| tree.candidates(r));
+
+        assert_eq!(found, vec![0]);
+        assert!(tree.nodes.len() > 1);
+        assert!(stats.index_nodes_visited < tree.nodes.len() as u64);
+        assert_eq!(stats.index_candidates, 1);
+    }
+
+    #[test]
+    fn overlapping_boxes_are_kept_on_both_sides_of_a_split() {
+        let mut boxes = row_of_boxes();
+        boxes.push(BoundingBox::new(
+            Point::new(-0.5, -0.5, -0.5),
+            Point::new(18.5, 0.5, 0.5),
+        ));
+        let tree = KdTree::build(&boxes);
+
+        for x in [0.0, 9.0, 18.0].iter() {
+            let r = Ray::new(Point::new(*x, 0.0, -5.0), Vector::new(0.0, 0.0, 1.0));
+            assert!(tree.candidates(r).contains(&10));
+        }
+    }
+
+    #[test]
+    fn the_tree_agrees_with_testing_every_box() {
+        let boxes = (0..50)
+            .map(|i| {
+                let i = i as f32;
+                unit_box_at((i * 1.7) % 9.0, (i * 2.3) % 7.0, (i * 0.9) % 5.0)
+            })
+            .collect::<Vec<_>>();
+        let tree = KdTree::build(&boxes);
+        let rays = [
+            Ray::new(
+                Point::new(-2.0, -1.0, -3.0),
+                Vector::new(1.0, 0.7, 0.5).normalize(),
+            ),
+            Ray::new(Point::new(4.0, 3.0, 2.0), Vector::new(-0.2, 1.0, 0.0)),
+            Ray::new(Point::new(4.0, 3.0, 20.0), Vector::new(0.0, 0.0, -1.0)),
+        ];
+
+        for &r in rays.iter() {
+            let expected = (0..boxes.len())
+                .filter(|&i| boxes[i].intersects(r))
+                .collect::<Vec<_>>();
+
+            assert_eq!(tree.candidates(r), expected);
+        }
+    }
+
+    #[test]
+    fn the_tree_searches_the_rays_own_range() {
+        let boxes = row_of_boxes();
+        let tree = KdTree::build(&boxes);
+        let r = Ray::new(Point::new(9.0, 0.0, 0.0), Vector::new(-1.0, 0.0, 0.0));
+        let rays = [r, r.t_min(-4.0), r.t_min(3.0), r.t_min(-6.0).t_max(-2.0)];
+
+        for &r in rays.iter() {
+            let expected = (0..boxes.len())
+                .filter(|&i| boxes[i].intersects(r))
+                .collect::<Vec<_>>();
+
+            assert_eq!(tree.candidates(r), expected);
+        }
+    }
+}
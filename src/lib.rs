@@ -0,0 +1,70 @@
+#![allow(dead_code)]
+
+pub mod animation;
+pub mod bounds;
+pub mod buckets;
+pub mod bvh;
+pub mod camera;
+pub mod canvas;
+pub mod clipping;
+pub mod color;
+pub mod compare;
+pub mod environment;
+pub mod export;
+pub mod fixtures;
+pub mod gizmos;
+pub mod grade;
+pub mod integrator;
+pub mod intersection;
+pub mod kdtree;
+pub mod lights;
+pub mod materials;
+pub mod obj;
+pub mod packet;
+pub mod patterns;
+pub mod perf;
+pub mod photon_map;
+pub mod post;
+pub mod prefabs;
+#[cfg(feature = "preview")]
+pub mod preview;
+pub mod ray;
+pub mod registry;
+pub mod resume;
+pub mod sampler;
+#[cfg(feature = "serde")]
+pub mod serialization;
+pub mod shapes;
+pub mod snapshot;
+pub mod spectrum;
+pub mod stats;
+pub mod text;
+pub mod tools;
+pub mod trace;
+pub mod transformations;
+pub mod tuple;
+pub mod validation;
+pub mod volume;
+pub mod watch;
+pub mod world;
+
+#[cfg(test)]
+mod test;
+
+use std::cmp::Ordering;
+
+pub const EPSILON: f32 = 0.0001;
+
+pub fn float_eq(x: f32, y: f32) -> bool {
+    (y - x).abs() < EPSILON
+}
+
+pub fn float_cmp(x: f32, y: f32) -> Ordering {
+    if float_eq(x, y) {
+        Ordering::Equal
+    } else if x < y {
+        Ordering::Less
+    } else {
+        Ordering::Greater
+    }
+}
@@ -1,22 +1,77 @@
-use crate::{color::Color, tuple::Tuple};
+use uuid::Uuid;
 
+use crate::{
+    color::Color,
+    materials::Material,
+    ray::Ray,
+    transformations::Transform,
+    tuple::{Point, Vector},
+    EPSILON,
+};
+
+/// A set of up to 32 light groups, one bit each. A light only illuminates,
+/// and is only shadowed by, surfaces whose material shares at least one of
+/// its groups; both belong
+/// to every group unless told otherwise. `Registry::light_group` hands out
+/// groups by name.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct LightGroups(u32);
+
+impl LightGroups {
+    pub const ALL: LightGroups = LightGroups(u32::MAX);
+    pub const NONE: LightGroups = LightGroups(0);
+
+    /// The group numbered `index`, or `None` past the last one.
+    pub fn group(index: usize) -> Option<Self> {
+        1u32.checked_shl(index as u32).map(LightGroups)
+    }
+
+    pub fn union(self, other: LightGroups) -> Self {
+        LightGroups(self.0 | other.0)
+    }
+
+    pub fn shares_any(self, other: LightGroups) -> bool {
+        self.0 & other.0 != 0
+    }
+}
+
+impl Default for LightGroups {
+    fn default() -> Self {
+        LightGroups::ALL
+    }
+}
+
+/// A light at a single point. Like a shape, it can be moved by a transform
+/// and parented to a group, so it follows the geometry around; `position`
+/// is then in the light's own object space.
 #[derive(Copy, Clone, Debug, Default, PartialEq)]
+#[cfg_attr(
+    feature = "serde",
+    derive(serde::Serialize, serde::Deserialize),
+    serde(default)
+)]
 pub struct PointLight {
-    pub position: Tuple,
+    pub position: Point,
     pub intensity: Color,
+    pub transform: Transform,
+    #[cfg_attr(feature = "serde", serde(skip))]
+    pub parent: Option<Uuid>,
+    pub groups: LightGroups,
 }
 
 impl PointLight {
-    pub fn new(position: Tuple, intensity: Color) -> Self {
+    pub fn new(position: Point, intensity: Color) -> Self {
         Self {
             position,
             intensity,
+            ..Self::default()
         }
     }
 
     pub fn position(self, x: f32, y: f32, z: f32) -> Self {
         Self {
-            position: Tuple::point(x, y, z),
+            position: Point::new(x, y, z),
             ..self
         }
     }
@@ -27,20 +82,172 @@ impl PointLight {
             ..self
         }
     }
+
+    pub fn transform(self, transform: Transform) -> Self {
+        Self { transform, ..self }
+    }
+
+    /// Attaches the light to the group with id `parent`. The world applies
+    /// the group's transforms when it lights the scene.
+    pub fn parent(self, parent: Uuid) -> Self {
+        Self {
+            parent: Some(parent),
+            ..self
+        }
+    }
+
+    pub fn groups(self, groups: LightGroups) -> Self {
+        Self { groups, ..self }
+    }
+
+    /// Whether the light falls on surfaces made of `material`.
+    pub fn illuminates(&self, material: &Material) -> bool {
+        self.groups.shares_any(material.light_groups)
+    }
+
+    /// The position after the light's own transform, but not its parents'.
+    pub fn transformed_position(&self) -> Point {
+        self.transform * self.position
+    }
+}
+
+/// A window or doorway the world's background shines in through. Sampling
+/// the opening directly finds the sky far more often than waiting for
+/// diffuse bounces to escape through it.
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct Portal {
+    pub corner: Point,
+    pub uvec: Vector,
+    pub vvec: Vector,
+}
+
+impl Portal {
+    pub fn new(corner: Point, uvec: Vector, vvec: Vector) -> Self {
+        Self { corner, uvec, vvec }
+    }
+
+    pub fn point_on(&self, u: f32, v: f32) -> Point {
+        self.corner + self.uvec * u + self.vvec * v
+    }
+
+    pub fn normal(&self) -> Vector {
+        self.uvec.cross(self.vvec).normalize()
+    }
+
+    pub fn area(&self) -> f32 {
+        self.uvec.cross(self.vvec).magnitude()
+    }
+
+    /// Whether the ray passes through the opening, from either side.
+    pub fn intersects(&self, ray: Ray) -> bool {
+        let n = self.uvec.cross(self.vvec);
+        let denominator = ray.direction.dot(n);
+        if denominator.abs() < EPSILON {
+            return false;
+        }
+
+        let t = (self.corner - ray.origin).dot(n) / denominator;
+        if t <= 0.0 {
+            return false;
+        }
+
+        let p = ray.position(t) - self.corner;
+        let area = n.dot(n);
+        let u = p.cross(self.vvec).dot(n) / area;
+        let v = self.uvec.cross(p).dot(n) / area;
+        (0.0..=1.0).contains(&u) && (0.0..=1.0).contains(&v)
+    }
 }
 
 #[cfg(test)]
 mod tests {
+    use crate::float_eq;
+
     use super::*;
 
     #[test]
     fn a_point_light_has_a_position_and_intensity() {
         let intensity = Color::new(1.0, 1.0, 1.0);
-        let position = Tuple::point(0.0, 0.0, 0.0);
+        let position = Point::new(0.0, 0.0, 0.0);
 
         let light = PointLight::new(position, intensity);
 
         assert_eq!(light.position, position);
         assert_eq!(light.intensity, intensity);
     }
+
+    #[test]
+    fn a_transformed_point_light() {
+        let light = PointLight::new(Point::new(0.0, 1.0, 0.0), Color::new(1.0, 1.0, 1.0))
+            .transform(Transform::translation(5.0, 0.0, 0.0) * Transform::scaling(2.0, 2.0, 2.0));
+
+        assert_eq!(light.transformed_position(), Point::new(5.0, 2.0, 0.0));
+        assert_eq!(light.position, Point::new(0.0, 1.0, 0.0));
+    }
+
+    #[test]
+    fn lights_and_materials_are_in_every_light_group_by_default() {
+        let light = PointLight::default();
+
+        assert_eq!(light.groups, LightGroups::ALL);
+        assert!(light.illuminates(&Material::default()));
+    }
+
+    #[test]
+    fn a_light_illuminates_materials_sharing_one_of_its_groups() {
+        let key = LightGroups::group(0).unwrap();
+        let rim = LightGroups::group(1).unwrap();
+        let fill = LightGroups::group(2).unwrap();
+        let light = PointLight::default().groups(key.union(rim));
+
+        assert!(light.illuminates(&Material::default().light_groups(rim)));
+        assert!(light.illuminates(&Material::default().light_groups(rim.union(fill))));
+        assert!(!light.illuminates(&Material::default().light_groups(fill)));
+        assert!(!light.illuminates(&Material::default().light_groups(LightGroups::NONE)));
+    }
+
+    #[test]
+    fn there_are_32_light_groups() {
+        assert!(LightGroups::group(31).is_some());
+        assert!(LightGroups::group(32).is_none());
+    }
+
+    fn window() -> Portal {
+        Portal::new(
+            Point::new(-1.0, 2.0, 0.0),
+            Vector::new(2.0, 0.0, 0.0),
+            Vector::new(0.0, 0.0, 3.0),
+        )
+    }
+
+    #[test]
+    fn a_portal_is_a_parallelogram() {
+        let p = window();
+
+        assert_eq!(p.point_on(0.5, 0.5), Point::new(0.0, 2.0, 1.5));
+        assert_eq!(p.normal(), Vector::new(0.0, -1.0, 0.0));
+        assert!(float_eq(p.area(), 6.0));
+    }
+
+    #[test]
+    fn a_ray_through_the_opening_intersects_the_portal() {
+        let p = window();
+        let up = Ray::new(Point::new(0.0, 0.0, 1.0), Vector::new(0.0, 1.0, 0.0));
+        let down = Ray::new(Point::new(0.5, 5.0, 2.0), Vector::new(0.0, -1.0, 0.0));
+
+        assert!(p.intersects(up));
+        assert!(p.intersects(down));
+    }
+
+    #[test]
+    fn a_ray_beside_the_opening_misses_the_portal() {
+        let p = window();
+        let beside = Ray::new(Point::new(1.5, 0.0, 1.0), Vector::new(0.0, 1.0, 0.0));
+        let away = Ray::new(Point::new(0.0, 0.0, 1.0), Vector::new(0.0, -1.0, 0.0));
+        let parallel = Ray::new(Point::new(0.0, 0.0, 1.0), Vector::new(1.0, 0.0, 0.0));
+
+        assert!(!p.intersects(beside));
+        assert!(!p.intersects(away));
+        assert!(!p.intersects(parallel));
+    }
 }
@@ -1,63 +1,30 @@
 #![allow(dead_code)]
 
-mod camera;
-mod canvas;
-mod color;
-mod intersection;
-mod lights;
-mod materials;
-mod patterns;
-mod ray;
-mod shapes;
-mod transformations;
-mod tuple;
-mod world;
-
-#[cfg(test)]
-mod test;
-
-use std::cmp::Ordering;
-use std::{f32::consts::PI, fs};
-
-use camera::Camera;
-use color::Color;
-use lights::PointLight;
-use materials::Material;
-use shapes::cone::Cone;
-use shapes::cylinder::Cylinder;
-use shapes::plane::Plane;
-use shapes::sphere::Sphere;
-use shapes::ShapeBuilder;
-use transformations::Transform;
-use tuple::*;
-use world::World;
-
-pub const EPSILON: f32 = 0.0001;
-
-pub fn float_eq(x: f32, y: f32) -> bool {
-    (y - x).abs() < EPSILON
-}
-
-pub fn float_cmp(x: f32, y: f32) -> Ordering {
-    if float_eq(x, y) {
-        Ordering::Equal
-    } else if x < y {
-        Ordering::Less
-    } else {
-        Ordering::Greater
-    }
-}
+use std::{env, f32::consts::PI, fs};
+
+use ray_tracer_challenge::{
+    camera::Camera,
+    color::{self, Color},
+    lights::PointLight,
+    materials::Material,
+    shapes::{cone::Cone, cylinder::Cylinder, plane::Plane, sphere::Sphere, ShapeBuilder},
+    transformations::Transform,
+    tuple::*,
+    world::World,
+};
+#[cfg(feature = "preview")]
+use ray_tracer_challenge::{integrator, preview};
 
 #[derive(Clone, Copy)]
 struct Projectile {
-    position: Tuple,
-    velocity: Tuple,
+    position: Point,
+    velocity: Vector,
 }
 
 #[derive(Clone, Copy)]
 struct Environment {
-    gravity: Tuple,
-    wind: Tuple,
+    gravity: Vector,
+    wind: Vector,
 }
 
 fn tick(env: Environment, proj: Projectile) -> Projectile {
@@ -66,7 +33,65 @@ fn tick(env: Environment, proj: Projectile) -> Projectile {
     Projectile { position, velocity }
 }
 
+/// A scene file for watch mode: a camera, a light and the objects they see,
+/// in JSON as the `serde` feature saves them.
+#[cfg(feature = "serde")]
+#[derive(serde::Deserialize)]
+struct SceneFile {
+    camera: Camera,
+    light: PointLight,
+    objects: Vec<Box<dyn ray_tracer_challenge::shapes::Shape>>,
+}
+
+/// `watch scene.json`: renders the scene to `canvas.ppm`, then again every
+/// time the file is saved, until stopped.
+#[cfg(feature = "serde")]
+fn watch(path: &str) {
+    use std::time::Duration;
+
+    use ray_tracer_challenge::{integrator::Whitted, watch};
+
+    let load = |path: &std::path::Path| {
+        let scene: SceneFile = match serde_json::from_str(&fs::read_to_string(path).ok()?) {
+            Ok(scene) => scene,
+            Err(e) => {
+                eprintln!("can't read {}: {}", path.display(), e);
+                return None;
+            }
+        };
+        let world = scene
+            .objects
+            .into_iter()
+            .fold(World::new(scene.light), World::object);
+        Some((world, scene.camera))
+    };
+
+    watch::render_on_change(
+        watch::FileWatcher::new(path, Duration::from_millis(200)),
+        Duration::from_millis(50),
+        &Whitted::new(5),
+        load,
+        |canvas| {
+            fs::write("canvas.ppm", canvas.export_srgb()).unwrap();
+            println!("rendered {}", path);
+            true
+        },
+    );
+}
+
+#[cfg(not(feature = "serde"))]
+fn watch(_path: &str) {
+    eprintln!("watch mode reads scenes with serde; build with --features serde");
+}
+
 fn main() {
+    if let [_, command, path] = env::args().collect::<Vec<_>>().as_slice() {
+        if command == "watch" {
+            watch(path);
+            return;
+        }
+    }
+
     let floor_material = Material::default()
         .color(Color::new(1.0, 0.9, 0.9))
         .specular(0.0)
@@ -110,7 +135,7 @@ fn main() {
         .with_caps(-1.0, 3.0);
 
     let world = World::new(PointLight::new(
-        Tuple::point(-10.0, 10.0, -10.0),
+        Point::new(-10.0, 10.0, -10.0),
         color::WHITE,
     ))
     .object(Box::new(floor))
@@ -119,12 +144,22 @@ fn main() {
     .object(Box::new(right));
 
     let camera = Camera::new(1000, 500, PI / 3.0).transform(Transform::view_transform(
-        Tuple::point(0.0, 1.5, -5.0),
-        Tuple::point(0.0, 1.0, 0.0),
-        Tuple::vector(0.0, 1.0, 0.0),
+        Point::new(0.0, 1.5, -5.0),
+        Point::new(0.0, 1.0, 0.0),
+        Vector::new(0.0, 1.0, 0.0),
     ));
 
+    #[cfg(not(feature = "preview"))]
     let canvas = camera.render(&world);
-
-    fs::write("canvas.ppm", canvas.to_ppm()).unwrap();
+    #[cfg(feature = "preview")]
+    let canvas = match preview::render_in_window(&camera, &world, &integrator::Whitted::new(3)) {
+        Ok(Some(canvas)) => canvas,
+        Ok(None) => return,
+        Err(e) => {
+            eprintln!("can't open a preview window, rendering without one: {}", e);
+            camera.render(&world)
+        }
+    };
+
+    fs::write("canvas.ppm", canvas.export_srgb()).unwrap();
 }
@@ -1,14 +1,49 @@
+use std::{borrow::Cow, f32::consts::PI};
+
 use crate::{
     color::{self, Color},
-    lights::PointLight,
-    patterns::BoxPattern,
+    lights::{LightGroups, PointLight},
+    patterns::{BoxPattern, HitPattern},
     shapes::Shape,
-    tuple::Tuple,
+    spectrum::{Radiance, SpectralCurve},
+    transformations::{Transform, IDENTITY},
+    tuple::{Point, Vector},
 };
 
+/// The model a material's direct lighting follows.
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum Shading {
+    /// The book's Phong model, using `diffuse`, `specular` and `shininess`.
+    #[default]
+    Phong,
+    /// Physically based shading with a GGX highlight. `color` is the base
+    /// color: metals tint their highlight with it and have no diffuse
+    /// part, while rough surfaces spread their highlight wide.
+    MetallicRoughness { metallic: f32, roughness: f32 },
+}
+
+/// A scalar material parameter that a pattern can drive in place of its
+/// constant value.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum Channel {
+    Ambient,
+    Diffuse,
+    Specular,
+    Reflective,
+    Transparency,
+}
+
 #[derive(Clone, Debug, PartialEq)]
+#[cfg_attr(
+    feature = "serde",
+    derive(serde::Serialize, serde::Deserialize),
+    serde(default)
+)]
 pub struct Material {
     pub color: Color,
+    pub shading: Shading,
     pub ambient: f32,
     pub diffuse: f32,
     pub reflective: f32,
@@ -16,7 +51,36 @@ pub struct Material {
     pub shininess: f32,
     pub transparency: f32,
     pub refractive_index: f32,
+    /// How strongly a transparent material splits light into colors. Lower
+    /// is stronger: around 60 for crown glass, 20 for dense flint glass.
+    /// `None` refracts every color alike.
+    pub abbe_number: Option<f32>,
+    /// Reverses the shapes' normals, for meshes wound inside out or planes
+    /// meant to face down, swapping which side counts as the inside.
+    pub flip_normal: bool,
+    /// Whether the surface looks the same from both sides. Seen from
+    /// behind, a single-sided surface keeps its normal facing away and is
+    /// shaded as its unlit back, which suits opaque shells and shows up
+    /// faces wound the wrong way.
+    pub two_sided: bool,
+    /// How quickly each channel dies away inside a transparent object, per
+    /// unit of distance. Light crossing `d` keeps `exp(-absorption * d)`
+    /// of itself, so thick glass is more deeply tinted than thin.
+    pub absorption: Color,
+    /// Reflectance by wavelength, standing in for `color` in spectral
+    /// renders and ignored by the rest.
+    pub spectrum: Option<SpectralCurve>,
+    /// How much light soaks through the surface, for wax, skin or jade.
+    /// Diffuse light wraps this far round past the shadow line, and light
+    /// shining in from behind shows through thin parts of the object.
+    pub translucency: f32,
+    /// How far light travels inside a translucent object before most of it
+    /// has been scattered away.
+    pub scatter_distance: f32,
     pub pattern: Option<BoxPattern>,
+    pub emissive: Color,
+    pub light_groups: LightGroups,
+    pub channel_patterns: Vec<(Channel, BoxPattern)>,
 }
 
 impl Material {
@@ -24,6 +88,10 @@ impl Material {
         Self { color, ..self }
     }
 
+    pub fn shading(self, shading: Shading) -> Self {
+        Self { shading, ..self }
+    }
+
     pub fn ambient(self, ambient: f32) -> Self {
         Self { ambient, ..self }
     }
@@ -58,6 +126,76 @@ impl Material {
         }
     }
 
+    pub fn abbe_number(self, abbe_number: f32) -> Self {
+        Self {
+            abbe_number: Some(abbe_number),
+            ..self
+        }
+    }
+
+    pub fn flip_normal(self, flip_normal: bool) -> Self {
+        Self {
+            flip_normal,
+            ..self
+        }
+    }
+
+    pub fn two_sided(self, two_sided: bool) -> Self {
+        Self { two_sided, ..self }
+    }
+
+    pub fn absorption(self, absorption: Color) -> Self {
+        Self { absorption, ..self }
+    }
+
+    pub fn spectrum(self, spectrum: SpectralCurve) -> Self {
+        Self {
+            spectrum: Some(spectrum),
+            ..self
+        }
+    }
+
+    pub fn translucency(self, translucency: f32) -> Self {
+        Self {
+            translucency,
+            ..self
+        }
+    }
+
+    pub fn scatter_distance(self, scatter_distance: f32) -> Self {
+        Self {
+            scatter_distance,
+            ..self
+        }
+    }
+
+    /// Refractive indices for the red, green and blue channels. With an
+    /// Abbe number they follow Cauchy's equation, fitted so green gets
+    /// `refractive_index` at the yellow d line while red and blue fall at
+    /// the C and F lines either side of it.
+    pub fn refractive_indices(&self) -> [f32; 3] {
+        [
+            self.refractive_index_at(656.3),
+            self.refractive_index,
+            self.refractive_index_at(486.1),
+        ]
+    }
+
+    /// The refractive index at `wavelength` nanometres, from the same fit.
+    pub fn refractive_index_at(&self, wavelength: f32) -> f32 {
+        let n = self.refractive_index;
+        match self.abbe_number {
+            None => n,
+            Some(abbe_number) => {
+                let (c, d, f) = (0.6563_f32, 0.5876_f32, 0.4861_f32);
+                let b = (n - 1.0) / (abbe_number * (1.0 / (f * f) - 1.0 / (c * c)));
+                let a = n - b / (d * d);
+                let micrometres = wavelength / 1000.0;
+                a + b / (micrometres * micrometres)
+            }
+        }
+    }
+
     pub fn pattern(self, pattern: BoxPattern) -> Self {
         Self {
             pattern: Some(pattern),
@@ -65,46 +203,214 @@ impl Material {
         }
     }
 
+    pub fn emissive(self, emissive: Color) -> Self {
+        Self { emissive, ..self }
+    }
+
+    /// Limits which lights fall on the surface to those in `light_groups`.
+    pub fn light_groups(self, light_groups: LightGroups) -> Self {
+        Self {
+            light_groups,
+            ..self
+        }
+    }
+
+    pub fn is_emissive(&self) -> bool {
+        self.emissive.red() > 0.0 || self.emissive.green() > 0.0 || self.emissive.blue() > 0.0
+    }
+
+    /// Drives `channel` with `pattern`, taking the brightness of the
+    /// pattern's color (the mean of its channels) in place of the constant.
+    pub fn channel_pattern(self, channel: Channel, pattern: BoxPattern) -> Self {
+        let mut channel_patterns = self.channel_patterns;
+        channel_patterns.retain(|(c, _)| *c != channel);
+        channel_patterns.push((channel, pattern));
+
+        Self {
+            channel_patterns,
+            ..self
+        }
+    }
+
+    /// The material as used at a hit on a shape nested in groups with the
+    /// combined transform `groups`, with its patterns following the groups
+    /// around and averaged over the hit's `footprint`.
+    pub fn at_hit(&self, groups: &Transform, footprint: f32) -> Cow<'_, Material> {
+        let plain = *groups == IDENTITY && footprint == 0.0;
+        if plain || (self.pattern.is_none() && self.channel_patterns.is_empty()) {
+            return Cow::Borrowed(self);
+        }
+
+        let nest = |pattern: &BoxPattern| -> BoxPattern {
+            Box::new(HitPattern {
+                pattern: pattern.clone(),
+                groups: *groups,
+                footprint,
+            })
+        };
+        Cow::Owned(Material {
+            pattern: self.pattern.as_ref().map(nest),
+            channel_patterns: self
+                .channel_patterns
+                .iter()
+                .map(|(channel, pattern)| (*channel, nest(pattern)))
+                .collect(),
+            ..self.clone()
+        })
+    }
+
+    /// The value of `channel` at `point` on `object`.
+    pub fn value_at(&self, channel: Channel, object: &dyn Shape, point: Point) -> f32 {
+        match self.channel_patterns.iter().find(|(c, _)| *c == channel) {
+            Some((_, pattern)) => {
+                let color = pattern.pattern_at_shape(object, point);
+                (color.red() + color.green() + color.blue()) / 3.0
+            }
+            None => match channel {
+                Channel::Ambient => self.ambient,
+                Channel::Diffuse => self.diffuse,
+                Channel::Specular => self.specular,
+                Channel::Reflective => self.reflective,
+                Channel::Transparency => self.transparency,
+            },
+        }
+    }
+
+    pub fn diffuse_at(&self, object: &dyn Shape, point: Point) -> f32 {
+        self.value_at(Channel::Diffuse, object, point)
+    }
+
+    pub fn reflective_at(&self, object: &dyn Shape, point: Point) -> f32 {
+        self.value_at(Channel::Reflective, object, point)
+    }
+
+    pub fn transparency_at(&self, object: &dyn Shape, point: Point) -> f32 {
+        self.value_at(Channel::Transparency, object, point)
+    }
+
+    pub fn color_at(&self, object: &dyn Shape, point: Point) -> Color {
+        if let Some(pattern) = &self.pattern {
+            pattern.pattern_at_shape(object, point)
+        } else {
+            self.color
+        }
+    }
+
+    /// Phong lighting with `shadow` the share of each channel of the light
+    /// that reaches `point`: white when nothing is in the way, black when
+    /// the point is in full shadow.
     pub fn lighting(
         &self,
         object: &dyn Shape,
         light: PointLight,
-        point: Tuple,
-        eyev: Tuple,
-        normalv: Tuple,
-        in_shadow: bool,
+        point: Point,
+        eyev: Vector,
+        normalv: Vector,
+        shadow: Color,
     ) -> Color {
-        let color = if let Some(pattern) = &self.pattern {
-            pattern.pattern_at_shape(object, point)
-        } else {
-            self.color
-        };
-        let effective_color = color * light.intensity;
-        let lightv = (light.position - point).normalize();
+        self.lighting_breakdown(object, light, point, eyev, normalv, shadow)
+            .total()
+    }
+
+    pub fn lighting_breakdown(
+        &self,
+        object: &dyn Shape,
+        light: PointLight,
+        point: Point,
+        eyev: Vector,
+        normalv: Vector,
+        shadow: Color,
+    ) -> LightingBreakdown {
+        self.lighting_breakdown_as(
+            object,
+            light,
+            point,
+            eyev,
+            normalv,
+            shadow,
+            self.color_at(object, point),
+            light.intensity,
+        )
+    }
 
-        let ambient = effective_color * self.ambient;
-        if in_shadow {
-            return ambient;
+    /// `lighting_breakdown` with the light carried as `R`, given the
+    /// surface's `color` and the light's `intensity` as `R` too.
+    #[allow(clippy::too_many_arguments)]
+    pub fn lighting_breakdown_as<R: Radiance>(
+        &self,
+        object: &dyn Shape,
+        light: PointLight,
+        point: Point,
+        eyev: Vector,
+        normalv: Vector,
+        shadow: Color,
+        color: R,
+        intensity: R,
+    ) -> LightingBreakdown<R> {
+        let effective_color = color * intensity;
+        let lightv = (light.transformed_position() - point).normalize();
+
+        let ambient = effective_color * self.value_at(Channel::Ambient, object, point);
+        if shadow == color::BLACK {
+            return LightingBreakdown {
+                ambient,
+                ..LightingBreakdown::default()
+            };
+        }
+        let intensity = intensity * R::from_rgb(shadow);
+
+        if let Shading::MetallicRoughness {
+            metallic,
+            roughness,
+        } = self.shading
+        {
+            let (diffuse, specular) =
+                ggx_lighting(color, metallic, roughness, lightv, eyev, normalv);
+            return LightingBreakdown {
+                ambient,
+                diffuse: diffuse * intensity,
+                specular: specular * intensity,
+            };
         }
 
         let light_dot_normal = lightv.dot(normalv);
-        let (diffuse, specular) = if light_behind_surface(light_dot_normal) {
-            (color::BLACK, color::BLACK)
+        let wrapped = (light_dot_normal + self.translucency) / (1.0 + self.translucency);
+        let diffuse = if wrapped > 0.0 {
+            color * intensity * self.diffuse_at(object, point) * wrapped
+        } else {
+            R::default()
+        };
+        let specular = if light_behind_surface(light_dot_normal) {
+            R::default()
         } else {
             let reflectv = (-lightv).reflect(normalv);
             let reflect_dot_eye = reflectv.dot(eyev);
-            (
-                effective_color * self.diffuse * light_dot_normal,
-                if reflect_dot_eye <= 0.0 {
-                    color::BLACK
-                } else {
-                    let factor = reflect_dot_eye.powf(self.shininess);
-                    light.intensity * self.specular * factor
-                },
-            )
+            if reflect_dot_eye <= 0.0 {
+                R::default()
+            } else {
+                let factor = reflect_dot_eye.powf(self.shininess);
+                intensity * self.value_at(Channel::Specular, object, point) * factor
+            }
         };
 
-        ambient + diffuse + specular
+        LightingBreakdown {
+            ambient,
+            diffuse,
+            specular,
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct LightingBreakdown<R = Color> {
+    pub ambient: R,
+    pub diffuse: R,
+    pub specular: R,
+}
+
+impl<R: Radiance> LightingBreakdown<R> {
+    pub fn total(&self) -> R {
+        self.ambient + self.diffuse + self.specular
     }
 }
 
@@ -112,6 +418,7 @@ impl Default for Material {
     fn default() -> Self {
         Self {
             color: Color::new(1.0, 1.0, 1.0),
+            shading: Shading::Phong,
             ambient: 0.1,
             diffuse: 0.9,
             reflective: 0.0,
@@ -120,6 +427,16 @@ impl Default for Material {
             pattern: None,
             transparency: 0.0,
             refractive_index: 1.0,
+            abbe_number: None,
+            flip_normal: false,
+            two_sided: true,
+            absorption: color::BLACK,
+            spectrum: None,
+            translucency: 0.0,
+            scatter_distance: 1.0,
+            emissive: color::BLACK,
+            light_groups: LightGroups::ALL,
+            channel_patterns: vec![],
         }
     }
 }
@@ -128,6 +445,43 @@ fn light_behind_surface(light_dot_normal: f32) -> bool {
     light_dot_normal < 0.0
 }
 
+/// Cook-Torrance lighting with the GGX distribution, Schlick's Fresnel and
+/// Smith's shadowing terms, as the diffuse and specular light reflected
+/// from a light of unit intensity.
+fn ggx_lighting<R: Radiance>(
+    base: R,
+    metallic: f32,
+    roughness: f32,
+    lightv: Vector,
+    eyev: Vector,
+    normalv: Vector,
+) -> (R, R) {
+    let n_dot_l = normalv.dot(lightv);
+    let n_dot_v = normalv.dot(eyev);
+    if n_dot_l <= 0.0 || n_dot_v <= 0.0 {
+        return (R::default(), R::default());
+    }
+    let halfway = (lightv + eyev).normalize();
+    let n_dot_h = normalv.dot(halfway).max(0.0);
+    let v_dot_h = eyev.dot(halfway).max(0.0);
+
+    let alpha2 = (roughness * roughness).max(1e-4).powi(2);
+    let distribution = alpha2 / (PI * (n_dot_h * n_dot_h * (alpha2 - 1.0) + 1.0).powi(2));
+
+    let k = (roughness + 1.0).powi(2) / 8.0;
+    let shadowing = |cos: f32| cos / (cos * (1.0 - k) + k);
+    let geometry = shadowing(n_dot_l) * shadowing(n_dot_v);
+
+    let white = R::from_rgb(color::WHITE);
+    let dielectric = R::from_rgb(Color::new(0.04, 0.04, 0.04));
+    let f0 = dielectric * (1.0 - metallic) + base * metallic;
+    let fresnel = f0 + (white - f0) * (1.0 - v_dot_h).powi(5);
+
+    let diffuse = (white - fresnel) * base * ((1.0 - metallic) * n_dot_l);
+    let specular = fresnel * (PI * distribution * geometry / (4.0 * n_dot_v));
+    (diffuse, specular)
+}
+
 #[cfg(test)]
 mod tests {
     use crate::{float_eq, patterns::striped::Striped, shapes::sphere::Sphere, test::*};
@@ -145,15 +499,24 @@ mod tests {
         assert!(float_eq(m.shininess, 200.0));
     }
 
+    #[test]
+    fn materials_do_not_glow_by_default() {
+        let m = Material::default();
+
+        assert_eq!(m.emissive, color::BLACK);
+        assert!(!m.is_emissive());
+        assert!(m.emissive(Color::new(0.0, 0.5, 0.0)).is_emissive());
+    }
+
     #[test]
     fn lighting_with_eye_between_light_and_surface() {
         let (m, position) = shared_setup();
-        let eyev = Tuple::vector(0.0, 0.0, -1.0);
-        let normalv = Tuple::vector(0.0, 0.0, -1.0);
-        let light = PointLight::new(Tuple::point(0.0, 0.0, -10.0), Color::new(1.0, 1.0, 1.0));
+        let eyev = Vector::new(0.0, 0.0, -1.0);
+        let normalv = Vector::new(0.0, 0.0, -1.0);
+        let light = PointLight::new(Point::new(0.0, 0.0, -10.0), Color::new(1.0, 1.0, 1.0));
         let object = Sphere::default();
 
-        let result = m.lighting(&object, light, position, eyev, normalv, false);
+        let result = m.lighting(&object, light, position, eyev, normalv, color::WHITE);
 
         assert_eq!(result, Color::new(1.9, 1.9, 1.9));
     }
@@ -161,12 +524,12 @@ mod tests {
     #[test]
     fn lighting_with_eye_between_light_and_surface_offset_45_deg() {
         let (m, position) = shared_setup();
-        let eyev = Tuple::vector(0.0, sqrt_n_over_n(2), -sqrt_n_over_n(2));
-        let normalv = Tuple::vector(0.0, 0.0, -1.0);
-        let light = PointLight::new(Tuple::point(0.0, 0.0, -10.0), Color::new(1.0, 1.0, 1.0));
+        let eyev = Vector::new(0.0, sqrt_n_over_n(2), -sqrt_n_over_n(2));
+        let normalv = Vector::new(0.0, 0.0, -1.0);
+        let light = PointLight::new(Point::new(0.0, 0.0, -10.0), Color::new(1.0, 1.0, 1.0));
         let object = Sphere::default();
 
-        let result = m.lighting(&object, light, position, eyev, normalv, false);
+        let result = m.lighting(&object, light, position, eyev, normalv, color::WHITE);
 
         assert_eq!(result, Color::new(1.0, 1.0, 1.0));
     }
@@ -174,12 +537,12 @@ mod tests {
     #[test]
     fn lighting_with_eye_opposite_surface_light_offset_45_deg() {
         let (m, position) = shared_setup();
-        let eyev = Tuple::vector(0.0, 0.0, -1.0);
-        let normalv = Tuple::vector(0.0, 0.0, -1.0);
-        let light = PointLight::new(Tuple::point(0.0, 10.0, -10.0), Color::new(1.0, 1.0, 1.0));
+        let eyev = Vector::new(0.0, 0.0, -1.0);
+        let normalv = Vector::new(0.0, 0.0, -1.0);
+        let light = PointLight::new(Point::new(0.0, 10.0, -10.0), Color::new(1.0, 1.0, 1.0));
         let object = Sphere::default();
 
-        let result = m.lighting(&object, light, position, eyev, normalv, false);
+        let result = m.lighting(&object, light, position, eyev, normalv, color::WHITE);
 
         assert_eq!(result, Color::new(0.7364, 0.7364, 0.7364));
     }
@@ -187,12 +550,12 @@ mod tests {
     #[test]
     fn lighting_with_eye_in_path_of_reflection() {
         let (m, position) = shared_setup();
-        let eyev = Tuple::vector(0.0, -sqrt_n_over_n(2), -sqrt_n_over_n(2));
-        let normalv = Tuple::vector(0.0, 0.0, -1.0);
-        let light = PointLight::new(Tuple::point(0.0, 10.0, -10.0), Color::new(1.0, 1.0, 1.0));
+        let eyev = Vector::new(0.0, -sqrt_n_over_n(2), -sqrt_n_over_n(2));
+        let normalv = Vector::new(0.0, 0.0, -1.0);
+        let light = PointLight::new(Point::new(0.0, 10.0, -10.0), Color::new(1.0, 1.0, 1.0));
         let object = Sphere::default();
 
-        let result = m.lighting(&object, light, position, eyev, normalv, false);
+        let result = m.lighting(&object, light, position, eyev, normalv, color::WHITE);
 
         assert_eq!(result, Color::new(1.6364, 1.6364, 1.6364));
     }
@@ -200,32 +563,181 @@ mod tests {
     #[test]
     fn lighting_with_light_behind_surface() {
         let (m, position) = shared_setup();
-        let eyev = Tuple::vector(0.0, 0.0, -1.0);
-        let normalv = Tuple::vector(0.0, 0.0, -1.0);
-        let light = PointLight::new(Tuple::point(0.0, 0.0, 10.0), Color::new(1.0, 1.0, 1.0));
+        let eyev = Vector::new(0.0, 0.0, -1.0);
+        let normalv = Vector::new(0.0, 0.0, -1.0);
+        let light = PointLight::new(Point::new(0.0, 0.0, 10.0), Color::new(1.0, 1.0, 1.0));
         let object = Sphere::default();
 
-        let result = m.lighting(&object, light, position, eyev, normalv, false);
+        let result = m.lighting(&object, light, position, eyev, normalv, color::WHITE);
 
         assert_eq!(result, Color::new(0.1, 0.1, 0.1));
     }
 
+    #[test]
+    fn translucent_lighting_wraps_past_the_shadow_line() {
+        let (m, position) = shared_setup();
+        let eyev = Vector::new(0.0, 0.0, -1.0);
+        let normalv = Vector::new(0.0, 0.0, -1.0);
+        let light = PointLight::new(Point::new(10.0, 0.0, 1.0), Color::new(1.0, 1.0, 1.0));
+        let object = Sphere::default();
+
+        let opaque = m.lighting_breakdown(&object, light, position, eyev, normalv, color::WHITE);
+        let wax = m.clone().translucency(0.5).lighting_breakdown(
+            &object,
+            light,
+            position,
+            eyev,
+            normalv,
+            color::WHITE,
+        );
+
+        assert_eq!(opaque.diffuse, color::BLACK);
+        assert!(wax.diffuse.red() > 0.0);
+        assert_eq!(wax.specular, color::BLACK);
+    }
+
     #[test]
     fn lighting_with_the_surface_in_shadow() {
         let (m, position) = shared_setup();
-        let eyev = Tuple::vector(0.0, 0.0, -1.0);
-        let normalv = Tuple::vector(0.0, 0.0, -1.0);
+        let eyev = Vector::new(0.0, 0.0, -1.0);
+        let normalv = Vector::new(0.0, 0.0, -1.0);
         let light = PointLight::default()
             .position(0.0, 0.0, -10.0)
             .intensity(1.0, 1.0, 1.0);
-        let in_shadow = true;
+        let shadow = color::BLACK;
         let object = Sphere::default();
 
-        let result = m.lighting(&object, light, position, eyev, normalv, in_shadow);
+        let result = m.lighting(&object, light, position, eyev, normalv, shadow);
 
         assert_eq!(result, Color::new(0.1, 0.1, 0.1));
     }
 
+    #[test]
+    fn without_an_abbe_number_every_channel_refracts_alike() {
+        let m = Material::default().refractive_index(1.5);
+
+        assert_eq!(m.refractive_indices(), [1.5, 1.5, 1.5]);
+    }
+
+    #[test]
+    fn an_abbe_number_spreads_the_refractive_indices() {
+        let m = Material::default().refractive_index(1.5).abbe_number(20.0);
+
+        let [red, green, blue] = m.refractive_indices();
+
+        assert!(red < green && green < blue);
+        assert!(float_eq(green, 1.5));
+        assert!(float_eq(blue - red, 0.5 / 20.0));
+    }
+
+    #[test]
+    fn shorter_wavelengths_refract_more() {
+        let m = Material::default().refractive_index(1.5).abbe_number(20.0);
+
+        assert!(float_eq(m.refractive_index_at(587.6), 1.5));
+        assert!(m.refractive_index_at(400.0) > m.refractive_index_at(700.0));
+        assert_eq!(
+            Material::default()
+                .refractive_index(1.5)
+                .refractive_index_at(400.0),
+            1.5
+        );
+    }
+
+    #[test]
+    fn lighting_through_a_colored_shadow() {
+        let (m, position) = shared_setup();
+        let eyev = Vector::new(0.0, 0.0, -1.0);
+        let normalv = Vector::new(0.0, 0.0, -1.0);
+        let light = PointLight::default()
+            .position(0.0, 0.0, -10.0)
+            .intensity(1.0, 1.0, 1.0);
+        let shadow = Color::new(0.5, 0.0, 0.0);
+        let object = Sphere::default();
+
+        let result = m.lighting(&object, light, position, eyev, normalv, shadow);
+
+        assert_eq!(result, Color::new(0.1 + 0.5 * 1.8, 0.1, 0.1));
+    }
+
+    #[test]
+    fn channels_take_the_constant_values_without_a_pattern() {
+        let m = Material::default().reflective(0.3).transparency(0.6);
+        let s = Sphere::default();
+        let p = Point::new(0.5, 0.0, 0.0);
+
+        assert!(float_eq(m.value_at(Channel::Ambient, &s, p), 0.1));
+        assert!(float_eq(m.diffuse_at(&s, p), 0.9));
+        assert!(float_eq(m.value_at(Channel::Specular, &s, p), 0.9));
+        assert!(float_eq(m.reflective_at(&s, p), 0.3));
+        assert!(float_eq(m.transparency_at(&s, p), 0.6));
+    }
+
+    #[test]
+    fn a_pattern_drives_a_channel_by_its_brightness() {
+        let stripes = Striped::new(color::WHITE, Color::new(0.3, 0.0, 0.0));
+        let m = Material::default().channel_pattern(Channel::Reflective, Box::new(stripes));
+        let s = Sphere::default();
+
+        assert!(float_eq(
+            m.reflective_at(&s, Point::new(0.5, 0.0, 0.0)),
+            1.0
+        ));
+        assert!(float_eq(
+            m.reflective_at(&s, Point::new(1.5, 0.0, 0.0)),
+            0.1
+        ));
+        assert!(float_eq(
+            m.transparency_at(&s, Point::new(0.5, 0.0, 0.0)),
+            0.0
+        ));
+    }
+
+    #[test]
+    fn driving_a_channel_again_replaces_its_pattern() {
+        let m = Material::default()
+            .channel_pattern(
+                Channel::Diffuse,
+                Box::new(Striped::new(color::WHITE, color::BLACK)),
+            )
+            .channel_pattern(
+                Channel::Diffuse,
+                Box::new(Striped::new(color::BLACK, color::WHITE)),
+            );
+
+        assert_eq!(m.channel_patterns.len(), 1);
+        assert!(float_eq(
+            m.diffuse_at(&Sphere::default(), Point::new(0.5, 0.0, 0.0)),
+            0.0
+        ));
+    }
+
+    #[test]
+    fn lighting_with_patterned_channels() {
+        let stripes = || Box::new(Striped::new(color::WHITE, color::BLACK));
+        let m = Material::default()
+            .channel_pattern(Channel::Ambient, stripes())
+            .channel_pattern(Channel::Diffuse, stripes())
+            .channel_pattern(Channel::Specular, stripes());
+        let eyev = Vector::new(0.0, 0.0, -1.0);
+        let normalv = Vector::new(0.0, 0.0, -1.0);
+        let light = PointLight::new(Point::new(0.0, 0.0, -10.0), color::WHITE);
+        let object = Sphere::default();
+        let lit = |x| {
+            m.lighting(
+                &object,
+                light,
+                Point::new(x, 0.0, 0.0),
+                eyev,
+                normalv,
+                color::WHITE,
+            )
+        };
+
+        assert!(lit(0.5).red() > 2.0);
+        assert_eq!(lit(1.5), color::BLACK);
+    }
+
     #[test]
     fn lighting_with_pattern_applied() {
         let (mut m, _) = shared_setup();
@@ -233,8 +745,8 @@ mod tests {
         m.ambient = 1.0;
         m.diffuse = 0.0;
         m.specular = 0.0;
-        let eyev = Tuple::vector(0.0, 0.0, -1.0);
-        let normalv = Tuple::vector(0.0, 0.0, -1.0);
+        let eyev = Vector::new(0.0, 0.0, -1.0);
+        let normalv = Vector::new(0.0, 0.0, -1.0);
         let light = PointLight::default()
             .position(0.0, 0.0, -10.0)
             .intensity(1.0, 1.0, 1.0);
@@ -243,24 +755,103 @@ mod tests {
         let c1 = m.lighting(
             &object,
             light,
-            Tuple::point(0.9, 0.0, 0.0),
+            Point::new(0.9, 0.0, 0.0),
             eyev,
             normalv,
-            false,
+            color::WHITE,
         );
         let c2 = m.lighting(
             &object,
             light,
-            Tuple::point(1.1, 0.0, 0.0),
+            Point::new(1.1, 0.0, 0.0),
             eyev,
             normalv,
-            false,
+            color::WHITE,
         );
 
         assert_eq!(c1, color::WHITE);
         assert_eq!(c2, color::BLACK);
     }
 
+    #[test]
+    fn the_breakdown_with_eye_between_light_and_surface() {
+        let (m, position) = shared_setup();
+        let eyev = Vector::new(0.0, 0.0, -1.0);
+        let normalv = Vector::new(0.0, 0.0, -1.0);
+        let light = PointLight::new(Point::new(0.0, 0.0, -10.0), Color::new(1.0, 1.0, 1.0));
+        let object = Sphere::default();
+
+        let result = m.lighting_breakdown(&object, light, position, eyev, normalv, color::WHITE);
+
+        assert_eq!(result.ambient, Color::new(0.1, 0.1, 0.1));
+        assert_eq!(result.diffuse, Color::new(0.9, 0.9, 0.9));
+        assert_eq!(result.specular, Color::new(0.9, 0.9, 0.9));
+        assert_eq!(result.total(), Color::new(1.9, 1.9, 1.9));
+    }
+
+    #[test]
+    fn the_breakdown_with_eye_offset_45_deg_has_no_specular() {
+        let (m, position) = shared_setup();
+        let eyev = Vector::new(0.0, sqrt_n_over_n(2), -sqrt_n_over_n(2));
+        let normalv = Vector::new(0.0, 0.0, -1.0);
+        let light = PointLight::new(Point::new(0.0, 0.0, -10.0), Color::new(1.0, 1.0, 1.0));
+        let object = Sphere::default();
+
+        let result = m.lighting_breakdown(&object, light, position, eyev, normalv, color::WHITE);
+
+        assert_eq!(result.diffuse, Color::new(0.9, 0.9, 0.9));
+        assert_eq!(result.specular, color::BLACK);
+    }
+
+    #[test]
+    fn the_breakdown_with_eye_in_path_of_reflection() {
+        let (m, position) = shared_setup();
+        let eyev = Vector::new(0.0, -sqrt_n_over_n(2), -sqrt_n_over_n(2));
+        let normalv = Vector::new(0.0, 0.0, -1.0);
+        let light = PointLight::new(Point::new(0.0, 10.0, -10.0), Color::new(1.0, 1.0, 1.0));
+        let object = Sphere::default();
+
+        let result = m.lighting_breakdown(&object, light, position, eyev, normalv, color::WHITE);
+
+        assert_eq!(result.diffuse, Color::new(0.6364, 0.6364, 0.6364));
+        assert_eq!(result.specular, Color::new(0.9, 0.9, 0.9));
+    }
+
+    #[test]
+    fn the_breakdown_with_light_behind_surface_is_only_ambient() {
+        let (m, position) = shared_setup();
+        let eyev = Vector::new(0.0, 0.0, -1.0);
+        let normalv = Vector::new(0.0, 0.0, -1.0);
+        let light = PointLight::new(Point::new(0.0, 0.0, 10.0), Color::new(1.0, 1.0, 1.0));
+        let object = Sphere::default();
+
+        let result = m.lighting_breakdown(&object, light, position, eyev, normalv, color::WHITE);
+
+        assert_eq!(result.ambient, Color::new(0.1, 0.1, 0.1));
+        assert_eq!(result.diffuse, color::BLACK);
+        assert_eq!(result.specular, color::BLACK);
+    }
+
+    #[test]
+    fn the_breakdown_in_shadow_is_only_ambient() {
+        let (m, position) = shared_setup();
+        let eyev = Vector::new(0.0, 0.0, -1.0);
+        let normalv = Vector::new(0.0, 0.0, -1.0);
+        let light = PointLight::new(Point::new(0.0, 0.0, -10.0), Color::new(1.0, 1.0, 1.0));
+        let object = Sphere::default();
+
+        let result = m.lighting_breakdown(&object, light, position, eyev, normalv, color::BLACK);
+
+        assert_eq!(
+            result,
+            LightingBreakdown {
+                ambient: Color::new(0.1, 0.1, 0.1),
+                diffuse: color::BLACK,
+                specular: color::BLACK,
+            }
+        );
+    }
+
     #[test]
     fn reflectivity_for_the_default_material() {
         let (m, _) = shared_setup();
@@ -268,8 +859,110 @@ mod tests {
         assert!(float_eq(m.reflective, 0.0));
     }
 
-    fn shared_setup() -> (Material, Tuple) {
-        (Material::default(), Tuple::point(0.0, 0.0, 0.0))
+    fn shared_setup() -> (Material, Point) {
+        (Material::default(), Point::new(0.0, 0.0, 0.0))
+    }
+
+    fn ggx_highlight(roughness: f32, light_x: f32) -> Color {
+        let m = Material::default().shading(Shading::MetallicRoughness {
+            metallic: 1.0,
+            roughness,
+        });
+        let eyev = Vector::new(0.0, 0.0, -1.0);
+        let normalv = Vector::new(0.0, 0.0, -1.0);
+        let light = PointLight::new(Point::new(light_x, 0.0, -10.0), color::WHITE);
+
+        m.lighting_breakdown(
+            &Sphere::default(),
+            light,
+            Point::new(0.0, 0.0, 0.0),
+            eyev,
+            normalv,
+            color::WHITE,
+        )
+        .specular
+    }
+
+    #[test]
+    fn materials_use_phong_shading_by_default() {
+        assert_eq!(Material::default().shading, Shading::Phong);
+    }
+
+    #[test]
+    fn a_metal_has_no_diffuse_light_and_a_tinted_highlight() {
+        let gold = Material::default()
+            .color(Color::new(1.0, 0.8, 0.3))
+            .shading(Shading::MetallicRoughness {
+                metallic: 1.0,
+                roughness: 0.3,
+            });
+        let eyev = Vector::new(0.0, 0.0, -1.0);
+        let normalv = Vector::new(0.0, 0.0, -1.0);
+        let light = PointLight::new(Point::new(0.0, 0.0, -10.0), color::WHITE);
+
+        let result = gold.lighting_breakdown(
+            &Sphere::default(),
+            light,
+            Point::new(0.0, 0.0, 0.0),
+            eyev,
+            normalv,
+            color::WHITE,
+        );
+
+        assert_eq!(result.diffuse, color::BLACK);
+        assert!(result.specular.red() > result.specular.green());
+        assert!(result.specular.green() > result.specular.blue());
+    }
+
+    #[test]
+    fn a_rough_dielectric_is_mostly_diffuse() {
+        let plastic = Material::default()
+            .color(Color::new(0.2, 0.4, 0.8))
+            .shading(Shading::MetallicRoughness {
+                metallic: 0.0,
+                roughness: 1.0,
+            });
+        let eyev = Vector::new(0.0, 0.0, -1.0);
+        let normalv = Vector::new(0.0, 0.0, -1.0);
+        let light = PointLight::new(Point::new(0.0, 0.0, -10.0), color::WHITE);
+
+        let result = plastic.lighting_breakdown(
+            &Sphere::default(),
+            light,
+            Point::new(0.0, 0.0, 0.0),
+            eyev,
+            normalv,
+            color::WHITE,
+        );
+
+        assert_eq!(result.diffuse, Color::new(0.2, 0.4, 0.8) * 0.96);
+        assert!(result.specular.red() < 0.1);
+    }
+
+    #[test]
+    fn roughness_spreads_the_highlight() {
+        assert!(ggx_highlight(0.1, 0.0).red() > ggx_highlight(0.8, 0.0).red());
+        assert!(ggx_highlight(0.1, 10.0).red() < ggx_highlight(0.8, 10.0).red());
+    }
+
+    #[test]
+    fn a_pbr_surface_lit_from_behind_is_only_ambient() {
+        let m = Material::default().shading(Shading::MetallicRoughness {
+            metallic: 0.0,
+            roughness: 0.5,
+        });
+        let light = PointLight::new(Point::new(0.0, 0.0, 10.0), color::WHITE);
+
+        let result = m.lighting(
+            &Sphere::default(),
+            light,
+            Point::new(0.0, 0.0, 0.0),
+            Vector::new(0.0, 0.0, -1.0),
+            Vector::new(0.0, 0.0, -1.0),
+            color::WHITE,
+        );
+
+        assert_eq!(result, Color::new(0.1, 0.1, 0.1));
     }
 
     #[test]
@@ -0,0 +1,917 @@
+use std::{
+    collections::HashMap,
+    error::Error,
+    fmt::{self, Display},
+    fs::{self, File},
+    io::{self, BufRead, BufReader, Read},
+    path::Path,
+};
+
+use crate::{
+    color::{self, Color},
+    shapes::{group::Group, mesh::Mesh, triangle::Triangle},
+    transformations::AxisConvention,
+    tuple::{Point, Vector},
+    EPSILON,
+};
+
+const PROGRESS_LINES: usize = 100_000;
+
+/// The geometry of a Wavefront OBJ file: its vertices, and its faces split
+/// into triangles and sorted by the group they were given in.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct ObjFile {
+    pub vertices: Vec<Point>,
+    /// A color for each vertex, or none at all. They come from the
+    /// `v x y z r g b` extension many tools write, or a sidecar file.
+    pub colors: Vec<Color>,
+    /// The `vn` lines, in order.
+    pub normals: Vec<Vector>,
+    /// Faces given before any `g` line.
+    pub default_group: Vec<[usize; 3]>,
+    pub groups: Vec<(String, Vec<[usize; 3]>)>,
+    /// The normal at each corner of each face, in the order the faces were
+    /// read, which is the default group's and then each named group's.
+    /// A face with a corner that wasn't given a normal has none, as do any
+    /// past the end.
+    pub face_normals: Vec<Option<[usize; 3]>>,
+    /// How many lines were skipped, whether unsupported or unrecognized.
+    pub ignored: usize,
+}
+
+impl ObjFile {
+    /// Reads vertices, faces and groups, skipping everything else. Faces
+    /// with more than three vertices are fanned out from the first, and
+    /// the texture part of `v/vt/vn` references is skipped. Normals belong
+    /// to face corners, so a vertex can have a different one on either
+    /// side of a crease, and references to normals that weren't given are
+    /// ignored. Vertices missing a color in a file that colors some are
+    /// left white.
+    pub fn parse(reader: impl Read) -> Result<Self, ObjError> {
+        Self::parse_with_progress(BufReader::new(reader), |_, _| ())
+    }
+
+    /// Like `parse`, but a line at a time, so a huge file is never held in
+    /// memory whole. Every so often `progress` is shown what's been read so
+    /// far and how many bytes that took, and once more at the end.
+    pub fn parse_with_progress(
+        reader: impl BufRead,
+        progress: impl FnMut(&ObjFile, u64),
+    ) -> Result<Self, ObjError> {
+        Self::default().read(reader, progress)
+    }
+
+    /// Parses the file at `path`, having first skimmed it to count its
+    /// vertices and faces so every buffer can be sized up front. `progress`
+    /// is as for `parse_with_progress`, along with the file's size.
+    pub fn open(
+        path: impl AsRef<Path>,
+        mut progress: impl FnMut(&ObjFile, u64, u64),
+    ) -> Result<Self, ObjError> {
+        let path = path.as_ref();
+        let (vertices, faces) = count_records(BufReader::new(File::open(path)?))?;
+        let size = fs::metadata(path)?.len();
+
+        let mut obj = Self::default();
+        obj.vertices.reserve_exact(vertices);
+        obj.default_group.reserve(faces);
+        obj.read(BufReader::new(File::open(path)?), |obj, bytes| {
+            progress(obj, bytes, size)
+        })
+    }
+
+    fn read(
+        mut self,
+        mut reader: impl BufRead,
+        mut progress: impl FnMut(&ObjFile, u64),
+    ) -> Result<Self, ObjError> {
+        let mut colors = Vec::with_capacity(self.vertices.capacity());
+        let mut numbers = vec![];
+        let mut corners = vec![];
+        let mut line = String::new();
+        let (mut bytes, mut lines, mut face_count) = (0, 0, 0);
+        loop {
+            if lines > 0 && lines % PROGRESS_LINES == 0 {
+                progress(&self, bytes);
+            }
+            line.clear();
+            let read = reader.read_line(&mut line)?;
+            if read == 0 {
+                break;
+            }
+            bytes += read as u64;
+            lines += 1;
+
+            let mut tokens = line.split_whitespace();
+            match tokens.next() {
+                Some("v") => {
+                    parse_numbers(tokens, &mut numbers)?;
+                    match numbers[..] {
+                        [x, y, z] | [x, y, z, _] => {
+                            self.vertices.push(Point::new(x, y, z));
+                            colors.push(None);
+                        }
+                        [x, y, z, r, g, b] => {
+                            self.vertices.push(Point::new(x, y, z));
+                            colors.push(Some(Color::new(r, g, b)));
+                        }
+                        _ => self.ignored += 1,
+                    }
+                }
+                Some("vn") => {
+                    parse_numbers(tokens, &mut numbers)?;
+                    match numbers[..] {
+                        [x, y, z] => self.normals.push(Vector::new(x, y, z)),
+                        _ => self.ignored += 1,
+                    }
+                }
+                Some("f") => {
+                    corners.clear();
+                    for token in tokens {
+                        corners.push((
+                            self.vertex_index(token)?,
+                            normal_reference(token, self.normals.len()),
+                        ));
+                    }
+                    if corners.len() < 3 {
+                        self.ignored += 1;
+                        continue;
+                    }
+                    let faces = match self.groups.last_mut() {
+                        Some((_, faces)) => faces,
+                        None => &mut self.default_group,
+                    };
+                    for pair in corners[1..].windows(2) {
+                        faces.push([corners[0].0, pair[0].0, pair[1].0]);
+                        if let (Some(a), Some(b), Some(c)) = (corners[0].1, pair[0].1, pair[1].1) {
+                            self.face_normals.resize(face_count, None);
+                            self.face_normals.push(Some([a, b, c]));
+                        }
+                        face_count += 1;
+                    }
+                }
+                Some("g") => {
+                    let name = tokens.collect::<Vec<_>>().join(" ");
+                    self.groups.push((name, vec![]));
+                }
+                Some(_) => self.ignored += 1,
+                None => (),
+            }
+        }
+
+        if colors.iter().any(Option::is_some) {
+            self.colors = colors
+                .into_iter()
+                .map(|c| c.unwrap_or(color::WHITE))
+                .collect();
+        }
+        progress(&self, bytes);
+        Ok(self)
+    }
+
+    /// Brings the vertices and normals into the renderer's space from
+    /// `convention`, the one used by whatever wrote the file. Faces keep
+    /// their winding: the mirroring a right-handed file needs is what
+    /// makes its counterclockwise faces face the right way here.
+    pub fn axis_convention(mut self, convention: AxisConvention) -> Self {
+        let to_native = convention.to_native();
+        for vertex in &mut self.vertices {
+            *vertex = to_native * *vertex;
+        }
+        for normal in &mut self.normals {
+            *normal = to_native * *normal;
+        }
+        self
+    }
+
+    /// Takes the vertex colors from a sidecar file instead, with one
+    /// `r g b` line per vertex in order. Blank lines and `#` comments are
+    /// skipped.
+    pub fn read_vertex_colors(&mut self, mut reader: impl Read) -> Result<(), ObjError> {
+        let mut text = String::new();
+        reader.read_to_string(&mut text)?;
+
+        let mut colors = vec![];
+        for line in text.lines() {
+            let line = line.split('#').next().unwrap_or("");
+            if line.trim().is_empty() {
+                continue;
+            }
+            let channels = line
+                .split_whitespace()
+                .map(parse_number)
+                .collect::<Result<Vec<_>, _>>()?;
+            match channels[..] {
+                [r, g, b] => colors.push(Color::new(r, g, b)),
+                _ => return Err(ObjError::InvalidColor(line.trim().to_string())),
+            }
+        }
+
+        if colors.len() != self.vertices.len() {
+            return Err(ObjError::ColorCount {
+                vertices: self.vertices.len(),
+                colors: colors.len(),
+            });
+        }
+        self.colors = colors;
+        Ok(())
+    }
+
+    /// Every face in the file as one mesh, whatever group it was in. A
+    /// mesh has one normal per vertex, so it only gets the file's normals
+    /// if every face has them, and a vertex given different normals by
+    /// different faces is split into one for each.
+    pub fn to_mesh(&self) -> Mesh {
+        let mut faces = self.faces().copied().collect::<Vec<_>>();
+        let face_normals = self
+            .face_normals
+            .iter()
+            .copied()
+            .collect::<Option<Vec<_>>>();
+        let face_normals = match face_normals {
+            Some(face_normals) if !faces.is_empty() && face_normals.len() == faces.len() => {
+                face_normals
+            }
+            _ => return Mesh::new(self.vertices.clone(), faces).colors(self.colors.clone()),
+        };
+
+        let mut vertices = self.vertices.clone();
+        let mut colors = self.colors.clone();
+        let mut normals = vec![None; vertices.len()];
+        let mut seams = HashMap::new();
+        for (face, corner_normals) in faces.iter_mut().zip(face_normals) {
+            for (index, normal) in face.iter_mut().zip(corner_normals) {
+                let given = normals[*index];
+                match given {
+                    None => normals[*index] = Some(normal),
+                    Some(given) if given == normal => (),
+                    Some(_) => {
+                        *index = *seams.entry((*index, normal)).or_insert_with(|| {
+                            vertices.push(vertices[*index]);
+                            if !colors.is_empty() {
+                                colors.push(colors[*index]);
+                            }
+                            normals.push(Some(normal));
+                            vertices.len() - 1
+                        });
+                    }
+                }
+            }
+        }
+
+        let normals = normals
+            .into_iter()
+            .map(|n| n.map_or_else(|| Vector::new(0.0, 0.0, 0.0), |n| self.normals[n]))
+            .collect();
+        Mesh::new(vertices, faces).colors(colors).normals(normals)
+    }
+
+    /// Every face as one mesh, tidied up as `cleanup` says, and given
+    /// smooth normals if the file had none.
+    pub fn to_clean_mesh(&self, cleanup: Cleanup) -> Mesh {
+        let mut mesh = self.to_mesh();
+        if cleanup.weld > 0.0 {
+            mesh = mesh.weld(cleanup.weld);
+        }
+        match cleanup.winding {
+            Winding::Keep => (),
+            Winding::Flip => mesh.flip_winding(),
+            Winding::Fix => {
+                mesh.fix_winding();
+            }
+        }
+
+        if mesh.normals.is_empty() {
+            mesh.area_weighted_normals()
+        } else {
+            mesh
+        }
+    }
+
+    /// The default group's triangles, with a child group for each named one.
+    pub fn to_group(&self) -> Group {
+        let mut normals = self.face_normals.iter().copied();
+        let mut group = Group::new();
+        for &face in &self.default_group {
+            group.add_child(Box::new(self.triangle(face, normals.next().flatten())));
+        }
+        for (_, faces) in &self.groups {
+            let mut child = Group::new();
+            for &face in faces {
+                child.add_child(Box::new(self.triangle(face, normals.next().flatten())));
+            }
+            group.add_child(Box::new(child));
+        }
+        group
+    }
+
+    /// Every face, the default group's first.
+    fn faces(&self) -> impl Iterator<Item = &[usize; 3]> {
+        self.default_group
+            .iter()
+            .chain(self.groups.iter().flat_map(|(_, faces)| faces))
+    }
+
+    fn triangle(&self, [a, b, c]: [usize; 3], normals: Option<[usize; 3]>) -> Triangle {
+        let mut triangle = Triangle::new(self.vertices[a], self.vertices[b], self.vertices[c]);
+        if !self.colors.is_empty() {
+            triangle =
+                triangle.with_vertex_colors([self.colors[a], self.colors[b], self.colors[c]]);
+        }
+        if let Some(normals) = normals {
+            triangle = triangle.with_vertex_normals(normals.map(|n| self.normals[n]));
+        }
+        triangle
+    }
+
+    /// The zero based vertex a face refers to. Indices count from one, or
+    /// back from the latest vertex when negative.
+    fn vertex_index(&self, token: &str) -> Result<usize, ObjError> {
+        let reference = token.split('/').next().unwrap_or("");
+        let index: isize = reference
+            .parse()
+            .map_err(|_| ObjError::InvalidNumber(reference.to_string()))?;
+        let count = self.vertices.len() as isize;
+        let resolved = if index < 0 { count + index } else { index - 1 };
+
+        if (0..count).contains(&resolved) {
+            Ok(resolved as usize)
+        } else {
+            Err(ObjError::BadIndex(index))
+        }
+    }
+}
+
+/// The zero based normal a `v/vt/vn` or `v//vn` reference points to, if
+/// it has one that was given. Indices count as for vertices.
+fn normal_reference(token: &str, count: usize) -> Option<usize> {
+    let index: isize = token.split('/').nth(2)?.parse().ok()?;
+    let count = count as isize;
+    let resolved = if index < 0 { count + index } else { index - 1 };
+
+    if (0..count).contains(&resolved) {
+        Some(resolved as usize)
+    } else {
+        None
+    }
+}
+
+/// Fills `numbers` with the numbers in `tokens`, reusing its space.
+fn parse_numbers<'a>(
+    tokens: impl Iterator<Item = &'a str>,
+    numbers: &mut Vec<f32>,
+) -> Result<(), ObjError> {
+    numbers.clear();
+    for token in tokens {
+        numbers.push(parse_number(token)?);
+    }
+    Ok(())
+}
+
+/// How many vertices and triangles a file holds, counting polygons by the
+/// triangles they'll be fanned into.
+fn count_records(mut reader: impl BufRead) -> Result<(usize, usize), ObjError> {
+    let (mut vertices, mut faces) = (0, 0);
+    let mut line = vec![];
+    while reader.read_until(b'\n', &mut line)? > 0 {
+        let mut tokens = line
+            .split(u8::is_ascii_whitespace)
+            .filter(|t| !t.is_empty());
+        match tokens.next() {
+            Some(b"v") => vertices += 1,
+            Some(b"f") => faces += tokens.count().saturating_sub(2),
+            _ => (),
+        }
+        line.clear();
+    }
+    Ok((vertices, faces))
+}
+
+fn parse_number(token: &str) -> Result<f32, ObjError> {
+    token
+        .parse()
+        .map_err(|_| ObjError::InvalidNumber(token.to_string()))
+}
+
+/// Tidying for downloaded models, many of which import faceted or inside
+/// out otherwise.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Cleanup {
+    /// Vertices closer than this are merged. Zero leaves them apart.
+    pub weld: f32,
+    pub winding: Winding,
+}
+
+impl Cleanup {
+    pub fn weld(self, weld: f32) -> Self {
+        Self { weld, ..self }
+    }
+
+    pub fn winding(self, winding: Winding) -> Self {
+        Self { winding, ..self }
+    }
+}
+
+impl Default for Cleanup {
+    fn default() -> Self {
+        Self {
+            weld: EPSILON,
+            winding: Winding::Keep,
+        }
+    }
+}
+
+/// What to do about which way a mesh's faces point. Normals from the file
+/// are kept as they are either way.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Winding {
+    Keep,
+    Flip,
+    /// Turn a closed mesh the right way out if its faces mostly point in.
+    Fix,
+}
+
+#[derive(Debug)]
+pub enum ObjError {
+    Io(io::Error),
+    InvalidNumber(String),
+    /// A face refers to a vertex that hasn't been given.
+    BadIndex(isize),
+    InvalidColor(String),
+    ColorCount {
+        vertices: usize,
+        colors: usize,
+    },
+}
+
+impl Display for ObjError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ObjError::Io(e) => write!(f, "couldn't read the file: {}", e),
+            ObjError::InvalidNumber(token) => write!(f, "{:?} is not a valid number", token),
+            ObjError::BadIndex(index) => write!(f, "there is no vertex {}", index),
+            ObjError::InvalidColor(line) => write!(f, "{:?} is not an r g b color", line),
+            ObjError::ColorCount { vertices, colors } => write!(
+                f,
+                "expected a color for each of {} vertices, found {}",
+                vertices, colors
+            ),
+        }
+    }
+}
+
+impl Error for ObjError {}
+
+impl From<io::Error> for ObjError {
+    fn from(e: io::Error) -> Self {
+        ObjError::Io(e)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{intersection::Intersection, ray::Ray, shapes::Shape};
+
+    use super::*;
+
+    fn parse(text: &str) -> ObjFile {
+        ObjFile::parse(text.as_bytes()).unwrap()
+    }
+
+    #[test]
+    fn ignoring_unrecognized_lines() {
+        let gibberish = "There was a young lady named Bright
+who traveled much faster than light.
+She set out one day
+in a relative way,
+and came back the previous night.";
+
+        let obj = parse(gibberish);
+
+        assert_eq!(obj.ignored, 5);
+        assert!(obj.vertices.is_empty());
+    }
+
+    #[test]
+    fn vertex_records() {
+        let obj = parse(
+            "v -1 1 0
+v -1.0000 0.5000 0.0000
+v 1 0 0
+v 1 1 0",
+        );
+
+        assert_eq!(
+            obj.vertices,
+            vec![
+                Point::new(-1.0, 1.0, 0.0),
+                Point::new(-1.0, 0.5, 0.0),
+                Point::new(1.0, 0.0, 0.0),
+                Point::new(1.0, 1.0, 0.0),
+            ]
+        );
+        assert!(obj.colors.is_empty());
+    }
+
+    #[test]
+    fn parsing_triangle_faces() {
+        let obj = parse(
+            "v -1 1 0
+v -1 0 0
+v 1 0 0
+v 1 1 0
+
+f 1 2 3
+f 1 3 4",
+        );
+
+        assert_eq!(obj.default_group, vec![[0, 1, 2], [0, 2, 3]]);
+        assert_eq!(obj.ignored, 0);
+    }
+
+    #[test]
+    fn triangulating_polygons() {
+        let obj = parse(
+            "v -1 1 0
+v -1 0 0
+v 1 0 0
+v 1 1 0
+v 0 2 0
+
+f 1 2 3 4 5",
+        );
+
+        assert_eq!(obj.default_group, vec![[0, 1, 2], [0, 2, 3], [0, 3, 4]]);
+    }
+
+    #[test]
+    fn faces_with_texture_and_normal_references() {
+        let obj = parse(
+            "v 0 1 0
+v -1 0 0
+v 1 0 0
+f 1/1/1 2//2 -1/3",
+        );
+
+        assert_eq!(obj.default_group, vec![[0, 1, 2]]);
+    }
+
+    #[test]
+    fn triangles_in_groups() {
+        let obj = parse(
+            "v -1 1 0
+v -1 0 0
+v 1 0 0
+v 1 1 0
+g FirstGroup
+f 1 2 3
+g SecondGroup
+f 1 3 4",
+        );
+
+        assert!(obj.default_group.is_empty());
+        assert_eq!(
+            obj.groups,
+            vec![
+                ("FirstGroup".to_string(), vec![[0, 1, 2]]),
+                ("SecondGroup".to_string(), vec![[0, 2, 3]]),
+            ]
+        );
+    }
+
+    #[test]
+    fn converting_an_obj_file_to_a_group() {
+        let obj = parse(
+            "v -1 1 0
+v -1 0 0
+v 1 0 0
+v 1 1 0
+f 1 2 3
+g Second
+f 1 3 4",
+        );
+
+        let g = obj.to_group();
+
+        assert_eq!(g.children().len(), 2);
+        assert_eq!(g.children()[0].type_name(), "Triangle");
+        assert_eq!(g.children()[1].children().len(), 1);
+        assert_eq!(obj.to_mesh().faces, vec![[0, 1, 2], [0, 2, 3]]);
+    }
+
+    #[test]
+    fn a_face_referring_to_a_missing_vertex() {
+        let result = ObjFile::parse("v 0 0 0\nf 1 2 3".as_bytes());
+
+        assert!(matches!(result, Err(ObjError::BadIndex(2))));
+    }
+
+    #[test]
+    fn reading_vertex_colors() {
+        let obj = parse(
+            "v 0 1 0 1 0 0
+v -1 0 0
+v 1 0 0 0 0 1
+f 1 2 3",
+        );
+
+        assert_eq!(
+            obj.colors,
+            vec![
+                Color::new(1.0, 0.0, 0.0),
+                color::WHITE,
+                Color::new(0.0, 0.0, 1.0),
+            ]
+        );
+    }
+
+    #[test]
+    fn reading_vertex_colors_from_a_sidecar_file() {
+        let mut obj = parse("v 0 1 0\nv -1 0 0\nv 1 0 0\nf 1 2 3");
+
+        obj.read_vertex_colors("# r g b\n1 0 0\n\n0 1 0\n0 0 1\n".as_bytes())
+            .unwrap();
+
+        assert_eq!(obj.colors[1], Color::new(0.0, 1.0, 0.0));
+        assert!(matches!(
+            obj.read_vertex_colors("1 0 0".as_bytes()),
+            Err(ObjError::ColorCount {
+                vertices: 3,
+                colors: 1
+            })
+        ));
+    }
+
+    #[test]
+    fn vertex_colors_are_blended_across_a_loaded_triangle() {
+        let obj = parse(
+            "v 0 1 0 1 0 0
+v -1 0 0 0 1 0
+v 1 0 0 0 0 1
+f 1 2 3",
+        );
+        let g = obj.to_group();
+        let triangle = g.children()[0].as_ref();
+        let r = Ray::new(Point::new(0.0, 0.5, -2.0), Vector::new(0.0, 0.0, 1.0));
+
+        let xs = triangle.intersect(r);
+        let hit = Intersection::hit(&xs).unwrap();
+
+        assert_eq!(
+            triangle.surface_color(hit),
+            Some(Color::new(0.5, 0.25, 0.25))
+        );
+    }
+
+    #[test]
+    fn vertex_normal_records() {
+        let obj = parse(
+            "v 0 1 0
+v -1 0 0
+v 1 0 0
+vn -1 0 0
+vn 1 2 3
+f 1//2 2//1 -1//-1",
+        );
+
+        assert_eq!(
+            obj.normals,
+            vec![Vector::new(-1.0, 0.0, 0.0), Vector::new(1.0, 2.0, 3.0)]
+        );
+        assert_eq!(obj.face_normals, vec![Some([1, 0, 1])]);
+        assert_eq!(
+            obj.to_mesh().normals,
+            vec![
+                Vector::new(1.0, 2.0, 3.0),
+                Vector::new(-1.0, 0.0, 0.0),
+                Vector::new(1.0, 2.0, 3.0),
+            ]
+        );
+        assert_eq!(
+            obj.to_group().children()[0]
+                .as_any()
+                .downcast_ref::<Triangle>()
+                .unwrap()
+                .normals,
+            Some([
+                Vector::new(1.0, 2.0, 3.0),
+                Vector::new(-1.0, 0.0, 0.0),
+                Vector::new(1.0, 2.0, 3.0),
+            ])
+        );
+    }
+
+    #[test]
+    fn converting_a_z_up_file() {
+        let obj = parse(
+            "v 0 0 0
+v 1 0 0
+v 0 0 1
+vn 0 -1 0
+f 1//1 2//1 3//1",
+        )
+        .axis_convention(AxisConvention::blender());
+
+        assert_eq!(
+            obj.vertices,
+            vec![
+                Point::new(0.0, 0.0, 0.0),
+                Point::new(1.0, 0.0, 0.0),
+                Point::new(0.0, 1.0, 0.0),
+            ]
+        );
+        assert_eq!(obj.normals, vec![Vector::new(0.0, 0.0, -1.0)]);
+        assert_eq!(
+            obj.to_group().children()[0]
+                .as_any()
+                .downcast_ref::<Triangle>()
+                .unwrap()
+                .normal,
+            Vector::new(0.0, 0.0, -1.0)
+        );
+    }
+
+    #[test]
+    fn a_face_missing_a_normal_is_left_flat() {
+        let obj = parse(
+            "v 0 1 0
+v -1 0 0
+v 1 0 0
+vn 0 0 -1
+f 1//1 2//1 3",
+        );
+
+        assert!(obj.face_normals.is_empty());
+        assert!(obj.to_mesh().normals.is_empty());
+        assert_eq!(
+            obj.to_group().children()[0]
+                .as_any()
+                .downcast_ref::<Triangle>()
+                .unwrap()
+                .normals,
+            None
+        );
+    }
+
+    #[test]
+    fn a_vertex_shared_by_faces_with_different_normals() {
+        let obj = parse(
+            "v 0 0 0
+v 1 0 0
+v 0 1 0
+v 0 0 1
+vn 0 0 -1
+vn -1 0 0
+f 1//1 3//1 2//1
+f 1//2 4//2 3//2",
+        );
+        let triangle = |i: usize| {
+            obj.to_group().children()[i]
+                .as_any()
+                .downcast_ref::<Triangle>()
+                .unwrap()
+                .normals
+        };
+
+        assert_eq!(triangle(0), Some([Vector::new(0.0, 0.0, -1.0); 3]));
+        assert_eq!(triangle(1), Some([Vector::new(-1.0, 0.0, 0.0); 3]));
+
+        let mesh = obj.to_mesh();
+
+        assert_eq!(mesh.vertices.len(), 6);
+        assert_eq!(mesh.faces, vec![[0, 2, 1], [4, 3, 5]]);
+        assert_eq!(mesh.vertices[4], Point::new(0.0, 0.0, 0.0));
+        assert_eq!(mesh.vertices[5], Point::new(0.0, 1.0, 0.0));
+        assert_eq!(mesh.normals[4], Vector::new(-1.0, 0.0, 0.0));
+        assert_eq!(mesh.normals[0], Vector::new(0.0, 0.0, -1.0));
+    }
+
+    #[test]
+    fn cleaning_up_a_hard_edged_cube_keeps_its_creases() {
+        let obj = parse(
+            "v -1 -1 -1
+v 1 -1 -1
+v 1 1 -1
+v -1 1 -1
+v -1 -1 1
+v 1 -1 1
+v 1 1 1
+v -1 1 1
+vn 0 0 -1
+vn 0 0 1
+vn -1 0 0
+vn 1 0 0
+vn 0 -1 0
+vn 0 1 0
+f 1//1 4//1 3//1 2//1
+f 5//2 6//2 7//2 8//2
+f 1//3 5//3 8//3 4//3
+f 2//4 3//4 7//4 6//4
+f 1//5 2//5 6//5 5//5
+f 4//6 8//6 7//6 3//6",
+        );
+
+        let mesh = obj.to_clean_mesh(Cleanup::default());
+
+        assert_eq!(mesh.vertices.len(), 24);
+        assert_eq!(mesh.faces.len(), 12);
+        for face in &mesh.faces {
+            let [a, b, c] = face.map(|i| mesh.normals[i]);
+            assert!(a == b && b == c);
+        }
+    }
+
+    #[test]
+    fn cleaning_up_a_faceted_inside_out_model() {
+        let obj = parse(
+            "v 0 0 0
+v 1 0 0
+v 0 1 0
+v 0 0 1
+v 0 0 1.00001
+f 1 3 2
+f 1 2 5
+f 1 4 3
+f 2 3 4",
+        );
+
+        let mesh = obj.to_clean_mesh(Cleanup::default().winding(Winding::Fix));
+
+        assert_eq!(mesh.vertices.len(), 4);
+        assert!(mesh.is_closed());
+        assert!(mesh.signed_volume() > 0.0);
+        assert_eq!(mesh.normals[0], Vector::new(-1.0, -1.0, -1.0).normalize());
+        assert_eq!(
+            obj.to_clean_mesh(Cleanup::default().weld(0.0))
+                .vertices
+                .len(),
+            5
+        );
+    }
+
+    #[test]
+    fn cleaning_up_keeps_the_files_normals() {
+        let obj = parse(
+            "v 0 1 0
+v -1 0 0
+v 1 0 0
+vn 0 0 -1
+f 1//1 2//1 3//1",
+        );
+
+        let mesh = obj.to_clean_mesh(Cleanup::default().winding(Winding::Flip));
+
+        assert_eq!(mesh.faces, vec![[0, 2, 1]]);
+        assert_eq!(mesh.normals, vec![Vector::new(0.0, 0.0, -1.0); 3]);
+    }
+
+    #[test]
+    fn parsing_reports_progress() {
+        let mut text = String::new();
+        for i in 0..PROGRESS_LINES + 10 {
+            text.push_str(&format!("v {} 0 0\n", i));
+        }
+        let mut reports = vec![];
+
+        let obj = ObjFile::parse_with_progress(text.as_bytes(), |obj, bytes| {
+            reports.push((obj.vertices.len(), bytes))
+        })
+        .unwrap();
+
+        assert_eq!(obj.vertices.len(), PROGRESS_LINES + 10);
+        assert_eq!(
+            reports,
+            vec![
+                (
+                    PROGRESS_LINES,
+                    text.lines()
+                        .take(PROGRESS_LINES)
+                        .map(|l| l.len() as u64 + 1)
+                        .sum()
+                ),
+                (PROGRESS_LINES + 10, text.len() as u64),
+            ]
+        );
+    }
+
+    #[test]
+    fn counting_the_records_in_a_file() {
+        let text = "v 0 0 0\nv 1 0 0\nvn 0 1 0\n  v 0 1 0\nf 1 2 3 4 5\nf 1 2 3\n# f 1 2 3";
+
+        assert_eq!(count_records(text.as_bytes()).unwrap(), (3, 4));
+    }
+
+    #[test]
+    fn opening_a_file_sizes_its_buffers_up_front() {
+        let path = std::env::temp_dir().join(format!("{}.obj", uuid::Uuid::new_v4()));
+        fs::write(&path, "v 0 1 0\nv -1 0 0\nv 1 0 0\nv 1 1 0\nf 1 2 3 4\n").unwrap();
+        let mut last = None;
+
+        let obj = ObjFile::open(&path, |_, bytes, size| last = Some((bytes, size))).unwrap();
+
+        fs::remove_file(&path).unwrap();
+        assert_eq!(obj.vertices.capacity(), 4);
+        assert_eq!(obj.default_group, vec![[0, 1, 2], [0, 2, 3]]);
+        assert_eq!(last, Some((43, 43)));
+    }
+}
@@ -0,0 +1,240 @@
+use bevy::math::Vec4;
+
+use crate::{
+    ray::Ray,
+    transformations::Transform,
+    tuple::{Point, Vector},
+    EPSILON,
+};
+
+/// Four rays stored a component at a time: `origin[0]` holds the x of
+/// every origin, one ray to a lane, so a single `Vec4` operation steps all
+/// four rays together.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct RayPacket {
+    pub rays: [Ray; 4],
+    origin: [Vec4; 3],
+    direction: [Vec4; 3],
+}
+
+impl RayPacket {
+    pub fn new(rays: [Ray; 4]) -> Self {
+        let lanes = |component: fn(&Ray) -> f32| {
+            Vec4::new(
+                component(&rays[0]),
+                component(&rays[1]),
+                component(&rays[2]),
+                component(&rays[3]),
+            )
+        };
+
+        Self {
+            rays,
+            origin: [
+                lanes(|r| r.origin.x()),
+                lanes(|r| r.origin.y()),
+                lanes(|r| r.origin.z()),
+            ],
+            direction: [
+                lanes(|r| r.direction.x()),
+                lanes(|r| r.direction.y()),
+                lanes(|r| r.direction.z()),
+            ],
+        }
+    }
+
+    /// Every ray in the packet moved by `transform`, like `Ray::transform`.
+    pub fn transform(&self, transform: Transform) -> Self {
+        let m = transform.mat();
+        let (x, y, z, w) = (m.x_axis, m.y_axis, m.z_axis, m.w_axis);
+        let [ox, oy, oz] = self.origin;
+        let [dx, dy, dz] = self.direction;
+        let origin = [
+            ox * x.x + oy * y.x + oz * z.x + Vec4::splat(w.x),
+            ox * x.y + oy * y.y + oz * z.y + Vec4::splat(w.y),
+            ox * x.z + oy * y.z + oz * z.z + Vec4::splat(w.z),
+        ];
+        let direction = [
+            dx * x.x + dy * y.x + dz * z.x,
+            dx * x.y + dy * y.y + dz * z.y,
+            dx * x.z + dy * y.z + dz * z.z,
+        ];
+
+        let mut rays = self.rays;
+        for (lane, ray) in rays.iter_mut().enumerate() {
+            ray.origin = Point::new(origin[0][lane], origin[1][lane], origin[2][lane]);
+            ray.direction = Vector::new(direction[0][lane], direction[1][lane], direction[2][lane]);
+        }
+
+        Self {
+            rays,
+            origin,
+            direction,
+        }
+    }
+
+    /// Where each ray enters and leaves the unit sphere at the origin.
+    pub fn sphere_hits(&self) -> [Option<(f32, f32)>; 4] {
+        let [ox, oy, oz] = self.origin;
+        let [dx, dy, dz] = self.direction;
+        let a = dx * dx + dy * dy + dz * dz;
+        let b = (dx * ox + dy * oy + dz * oz) * 2.0;
+        let c = ox * ox + oy * oy + oz * oz - Vec4::ONE;
+        let discriminant = b * b - a * c * 4.0;
+        let missed = discriminant.cmplt(Vec4::ZERO).bitmask();
+
+        let mut hits = [None; 4];
+        for (lane, hit) in hits.iter_mut().enumerate() {
+            if missed & (1 << lane) == 0 {
+                let root = discriminant[lane].sqrt();
+                let two_a = 2.0 * a[lane];
+                *hit = Some(((-b[lane] - root) / two_a, (-b[lane] + root) / two_a));
+            }
+        }
+        hits
+    }
+
+    /// Where each ray crosses the xz plane, if it isn't running parallel.
+    pub fn plane_hits(&self) -> [Option<f32>; 4] {
+        let [_, oy, _] = self.origin;
+        let [_, dy, _] = self.direction;
+        let t = -oy / dy;
+        let parallel = dy.abs().cmplt(Vec4::splat(EPSILON)).bitmask();
+
+        let mut hits = [None; 4];
+        for (lane, hit) in hits.iter_mut().enumerate() {
+            if parallel & (1 << lane) == 0 {
+                *hit = Some(t[lane]);
+            }
+        }
+        hits
+    }
+
+    /// Where each ray enters and leaves the cube from -1 to 1 on each axis.
+    pub fn cube_hits(&self) -> [Option<(f32, f32)>; 4] {
+        let mut tmin = Vec4::splat(f32::NEG_INFINITY);
+        let mut tmax = Vec4::splat(f32::INFINITY);
+        for axis in 0..3 {
+            let (near, far) = slab(self.origin[axis], self.direction[axis]);
+            tmin = tmin.max(near);
+            tmax = tmax.min(far);
+        }
+        let missed = tmin.cmpgt(tmax).bitmask();
+
+        let mut hits = [None; 4];
+        for (lane, hit) in hits.iter_mut().enumerate() {
+            if missed & (1 << lane) == 0 {
+                *hit = Some((tmin[lane], tmax[lane]));
+            }
+        }
+        hits
+    }
+}
+
+/// The packet version of the cube's `check_axis`.
+fn slab(origin: Vec4, direction: Vec4) -> (Vec4, Vec4) {
+    let tmin_numerator = -Vec4::ONE - origin;
+    let tmax_numerator = Vec4::ONE - origin;
+    let steep = direction.abs().cmpge(Vec4::splat(EPSILON));
+    let tmin = Vec4::select(steep, tmin_numerator / direction, tmin_numerator * f32::MAX);
+    let tmax = Vec4::select(steep, tmax_numerator / direction, tmax_numerator * f32::MAX);
+
+    (tmin.min(tmax), tmin.max(tmax))
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{
+        float_eq,
+        intersection::Intersection,
+        shapes::{cube::Cube, plane::Plane, sphere::Sphere, Shape, ShapeBuilder},
+    };
+
+    use super::*;
+
+    fn packet() -> RayPacket {
+        RayPacket::new([
+            Ray::new(Point::new(0.0, 0.0, -5.0), Vector::new(0.0, 0.0, 1.0)),
+            Ray::new(Point::new(0.0, 0.5, -5.0), Vector::new(0.0, 0.0, 1.0)),
+            Ray::new(Point::new(0.0, 2.0, -5.0), Vector::new(0.0, 0.0, 1.0)),
+            Ray::new(Point::new(0.5, 3.0, 0.0), Vector::new(0.0, -1.0, 0.0)),
+        ])
+    }
+
+    fn ts(xs: &[Intersection]) -> Vec<f32> {
+        xs.iter().map(|x| x.t).collect()
+    }
+
+    fn assert_matches_single_rays(shape: &dyn Shape, packet: &RayPacket) {
+        let together = shape.intersect_packet(packet);
+
+        for (lane, ray) in packet.rays.iter().enumerate() {
+            let alone = ts(&shape.intersect(*ray));
+            let packed = ts(&together[lane]);
+
+            assert_eq!(alone.len(), packed.len());
+            for (a, p) in alone.iter().zip(packed.iter()) {
+                assert!(float_eq(*a, *p));
+            }
+        }
+    }
+
+    #[test]
+    fn a_packet_keeps_its_rays() {
+        let p = packet();
+
+        assert_eq!(p.rays[3].origin, Point::new(0.5, 3.0, 0.0));
+        assert_eq!(p.rays[3].direction, Vector::new(0.0, -1.0, 0.0));
+    }
+
+    #[test]
+    fn transforming_a_packet_transforms_each_ray() {
+        let t = Transform::translation(3.0, 4.0, 5.0) * Transform::scaling(2.0, 3.0, 4.0);
+
+        let moved = packet().transform(t);
+
+        for lane in 0..4 {
+            assert_eq!(moved.rays[lane], packet().rays[lane].transform(t));
+            assert_eq!(RayPacket::new(moved.rays), moved);
+        }
+    }
+
+    #[test]
+    fn a_packet_of_rays_intersecting_the_unit_sphere() {
+        let hits = packet().sphere_hits();
+
+        assert_eq!(hits[0], Some((4.0, 6.0)));
+        assert!(float_eq(hits[1].unwrap().1, 5.0 + 0.75_f32.sqrt()));
+        assert_eq!(hits[2], None);
+        assert!(float_eq(hits[3].unwrap().0, 3.0 - 0.75_f32.sqrt()));
+    }
+
+    #[test]
+    fn a_packet_of_rays_intersecting_the_xz_plane() {
+        let hits = packet().plane_hits();
+
+        assert_eq!(hits[..3], [None, None, None]);
+        assert_eq!(hits[3], Some(3.0));
+    }
+
+    #[test]
+    fn a_packet_of_rays_intersecting_the_unit_cube() {
+        let hits = packet().cube_hits();
+
+        assert_eq!(hits[0], Some((4.0, 6.0)));
+        assert_eq!(hits[1], Some((4.0, 6.0)));
+        assert_eq!(hits[2], None);
+        assert_eq!(hits[3], Some((2.0, 4.0)));
+    }
+
+    #[test]
+    fn packets_agree_with_single_rays_on_transformed_shapes() {
+        let p = packet();
+        let t = Transform::translation(0.0, 1.0, 0.0) * Transform::scaling(1.0, 2.0, 1.0);
+
+        assert_matches_single_rays(&Sphere::default().with_transform(t), &p);
+        assert_matches_single_rays(&Plane::default().with_transform(t), &p);
+        assert_matches_single_rays(&Cube::default().with_transform(t), &p);
+        assert_matches_single_rays(&Sphere::glass(), &p);
+    }
+}
@@ -3,12 +3,18 @@ use std::any::Any;
 use crate::{
     color::{self, Color},
     transformations::Transform,
-    tuple::Tuple,
+    tuple::Point,
+    EPSILON,
 };
 
-use super::{BoxPattern, Pattern, PatternBuilder};
+use super::{alternation_average, blend_alternation, BoxPattern, Pattern, PatternBuilder};
 
 #[derive(Clone, Copy, Debug, PartialEq)]
+#[cfg_attr(
+    feature = "serde",
+    derive(serde::Serialize, serde::Deserialize),
+    serde(default)
+)]
 pub struct Checkered {
     pub a: Color,
     pub b: Color,
@@ -58,13 +64,36 @@ impl Pattern for Checkered {
         &self.transform
     }
 
-    fn pattern_at(&self, point: Tuple) -> Color {
-        if (point.x().floor() + point.y().floor() + point.z().floor()) as u32 % 2 == 0 {
+    fn pattern_at(&self, point: Point) -> Color {
+        // Points a hair below a cell boundary count as on it, so a plane at
+        // y = 0 doesn't speckle as rounding error flips the sign of y.
+        let cell = |v: f32| (v + EPSILON).floor() as i64;
+        if (cell(point.x()) + cell(point.y()) + cell(point.z())).rem_euclid(2) == 0 {
             self.a
         } else {
             self.b
         }
     }
+
+    /// Checkers alternate along each axis independently, so averaging them
+    /// over a box is averaging each axis's alternation and multiplying. An
+    /// axis the point sits on a cell boundary of is left unaveraged, since
+    /// that's a surface lying along the boundary, like a floor at y = 0,
+    /// whose footprint doesn't reach across it.
+    fn filtered_pattern_at(&self, point: Point, width: f32) -> Color {
+        if width < EPSILON {
+            return self.pattern_at(point);
+        }
+        let average = |v: f32| {
+            if (v - v.round()).abs() < EPSILON {
+                1.0 - 2.0 * ((v + EPSILON).floor() as i64).rem_euclid(2) as f32
+            } else {
+                alternation_average(v + EPSILON, width)
+            }
+        };
+        let alternation = average(point.x()) * average(point.y()) * average(point.z());
+        blend_alternation(self.a, self.b, alternation)
+    }
 }
 
 #[cfg(test)]
@@ -75,53 +104,93 @@ mod tests {
     fn checkers_should_repeat_in_x() {
         let pattern = Checkered::new(color::WHITE, color::BLACK);
 
+        assert_eq!(pattern.pattern_at(Point::new(0.0, 0.0, 0.0)), color::WHITE);
+        assert_eq!(pattern.pattern_at(Point::new(0.99, 0.0, 0.0)), color::WHITE);
+        assert_eq!(pattern.pattern_at(Point::new(1.01, 0.0, 0.0)), color::BLACK);
+    }
+
+    #[test]
+    fn checkers_should_repeat_in_y() {
+        let pattern = Checkered::new(color::WHITE, color::BLACK);
+
+        assert_eq!(pattern.pattern_at(Point::new(0.0, 0.0, 0.0)), color::WHITE);
+        assert_eq!(pattern.pattern_at(Point::new(0.0, 0.99, 0.0)), color::WHITE);
+        assert_eq!(pattern.pattern_at(Point::new(0.0, 1.01, 0.0)), color::BLACK);
+    }
+
+    #[test]
+    fn checkers_should_repeat_in_z() {
+        let pattern = Checkered::new(color::WHITE, color::BLACK);
+
+        assert_eq!(pattern.pattern_at(Point::new(0.0, 0.0, 0.0)), color::WHITE);
+        assert_eq!(pattern.pattern_at(Point::new(0.0, 0.0, 0.99)), color::WHITE);
+        assert_eq!(pattern.pattern_at(Point::new(0.0, 0.0, 1.01)), color::BLACK);
+    }
+
+    #[test]
+    fn checkers_ignore_rounding_error_at_a_cell_boundary() {
+        let pattern = Checkered::new(color::WHITE, color::BLACK);
+
         assert_eq!(
-            pattern.pattern_at(Tuple::point(0.0, 0.0, 0.0)),
+            pattern.pattern_at(Point::new(0.5, -0.00001, 0.5)),
             color::WHITE
         );
         assert_eq!(
-            pattern.pattern_at(Tuple::point(0.99, 0.0, 0.0)),
+            pattern.pattern_at(Point::new(0.5, 0.00001, 0.5)),
             color::WHITE
         );
-        assert_eq!(
-            pattern.pattern_at(Tuple::point(1.01, 0.0, 0.0)),
-            color::BLACK
-        );
     }
 
     #[test]
-    fn checkers_should_repeat_in_y() {
+    fn checkers_alternate_at_negative_coordinates() {
+        let pattern = Checkered::new(color::WHITE, color::BLACK);
+
+        assert_eq!(pattern.pattern_at(Point::new(-0.5, 0.0, 0.5)), color::BLACK);
+        assert_eq!(pattern.pattern_at(Point::new(-1.5, 0.0, 0.5)), color::WHITE);
+    }
+
+    #[test]
+    fn checkers_averaged_over_many_cells_are_an_even_blend() {
+        let pattern = Checkered::new(color::WHITE, color::BLACK);
+
+        let c = pattern.filtered_pattern_at(Point::new(10.3, 0.0, -4.6), 6.0);
+
+        assert_eq!(c, Color::new(0.5, 0.5, 0.5));
+    }
+
+    #[test]
+    fn checkers_on_a_floor_are_averaged_only_across_the_floor() {
         let pattern = Checkered::new(color::WHITE, color::BLACK);
 
         assert_eq!(
-            pattern.pattern_at(Tuple::point(0.0, 0.0, 0.0)),
+            pattern.filtered_pattern_at(Point::new(0.5, 0.0, 0.5), 0.05),
             color::WHITE
         );
         assert_eq!(
-            pattern.pattern_at(Tuple::point(0.0, 0.99, 0.0)),
-            color::WHITE
+            pattern.filtered_pattern_at(Point::new(1.5, 0.0, 0.5), 0.01),
+            color::BLACK
         );
         assert_eq!(
-            pattern.pattern_at(Tuple::point(0.0, 1.01, 0.0)),
+            pattern.filtered_pattern_at(Point::new(0.5, -0.00001, -0.5), 0.05),
             color::BLACK
         );
+        assert_eq!(
+            pattern.filtered_pattern_at(Point::new(1.02, 0.0, 0.5), 0.2),
+            Color::new(0.3995, 0.3995, 0.3995)
+        );
     }
 
     #[test]
-    fn checkers_should_repeat_in_z() {
+    fn checkers_averaged_within_a_cell_keep_its_color() {
         let pattern = Checkered::new(color::WHITE, color::BLACK);
 
         assert_eq!(
-            pattern.pattern_at(Tuple::point(0.0, 0.0, 0.0)),
-            color::WHITE
-        );
-        assert_eq!(
-            pattern.pattern_at(Tuple::point(0.0, 0.0, 0.99)),
-            color::WHITE
+            pattern.filtered_pattern_at(Point::new(-0.5, 0.5, 0.5), 0.2),
+            color::BLACK
         );
         assert_eq!(
-            pattern.pattern_at(Tuple::point(0.0, 0.0, 1.01)),
-            color::BLACK
+            pattern.filtered_pattern_at(Point::new(1.0 - EPSILON, 0.5, 0.5), 0.2),
+            Color::new(0.5, 0.5, 0.5)
         );
     }
 }
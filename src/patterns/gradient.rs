@@ -3,12 +3,17 @@ use std::any::Any;
 use crate::{
     color::{self, Color},
     transformations::Transform,
-    tuple::Tuple,
+    tuple::Point,
 };
 
 use super::{BoxPattern, Pattern, PatternBuilder};
 
 #[derive(Clone, Copy, Debug, PartialEq)]
+#[cfg_attr(
+    feature = "serde",
+    derive(serde::Serialize, serde::Deserialize),
+    serde(default)
+)]
 pub struct Gradient {
     pub a: Color,
     pub b: Color,
@@ -58,7 +63,7 @@ impl Pattern for Gradient {
         &self.transform
     }
 
-    fn pattern_at(&self, point: Tuple) -> Color {
+    fn pattern_at(&self, point: Point) -> Color {
         let distance = self.b - self.a;
         let fraction = point.x() - point.x().floor();
 
@@ -74,20 +79,17 @@ mod tests {
     fn a_gradient_linearly_interpolates_between_colors() {
         let pattern = Gradient::new(color::WHITE, color::BLACK);
 
+        assert_eq!(pattern.pattern_at(Point::new(0.0, 0.0, 0.0)), color::WHITE);
         assert_eq!(
-            pattern.pattern_at(Tuple::point(0.0, 0.0, 0.0)),
-            color::WHITE
-        );
-        assert_eq!(
-            pattern.pattern_at(Tuple::point(0.25, 0.0, 0.0)),
+            pattern.pattern_at(Point::new(0.25, 0.0, 0.0)),
             Color::new(0.75, 0.75, 0.75)
         );
         assert_eq!(
-            pattern.pattern_at(Tuple::point(0.5, 0.0, 0.0)),
+            pattern.pattern_at(Point::new(0.5, 0.0, 0.0)),
             Color::new(0.5, 0.5, 0.5)
         );
         assert_eq!(
-            pattern.pattern_at(Tuple::point(0.75, 0.0, 0.0)),
+            pattern.pattern_at(Point::new(0.75, 0.0, 0.0)),
             Color::new(0.25, 0.25, 0.25)
         );
     }
@@ -0,0 +1,213 @@
+use std::any::Any;
+
+use crate::{
+    color::{self, Color},
+    transformations::Transform,
+    tuple::Point,
+    EPSILON,
+};
+
+use super::{BoxPattern, Pattern, PatternBuilder};
+
+/// Lines of `a` over `b`, like graph paper, running along x and z every
+/// `spacing` units, for ground planes in technical renders. With a fade
+/// distance the lines melt into `b` towards that far from the pattern's
+/// origin, so move the origin under the camera to keep the horizon clean.
+#[derive(Clone, Copy, Debug, PartialEq)]
+#[cfg_attr(
+    feature = "serde",
+    derive(serde::Serialize, serde::Deserialize),
+    serde(default)
+)]
+pub struct Grid {
+    pub a: Color,
+    pub b: Color,
+    pub spacing: f32,
+    pub line_width: f32,
+    pub fade: Option<f32>,
+    pub transform: Transform,
+}
+
+impl Grid {
+    pub fn new(a: Color, b: Color) -> Self {
+        Self {
+            a,
+            b,
+            ..Self::default()
+        }
+    }
+
+    pub fn spacing(self, spacing: f32) -> Self {
+        Self { spacing, ..self }
+    }
+
+    pub fn line_width(self, line_width: f32) -> Self {
+        Self { line_width, ..self }
+    }
+
+    pub fn fade(self, distance: f32) -> Self {
+        Self {
+            fade: Some(distance),
+            ..self
+        }
+    }
+
+    /// How strongly lines show at `point`, from 1 at the origin down to 0
+    /// at the fade distance.
+    fn strength(&self, point: Point) -> f32 {
+        match self.fade {
+            Some(fade) => 1.0 - (point.x().hypot(point.z()) / fade).min(1.0),
+            None => 1.0,
+        }
+    }
+
+    /// The share of `[v - width / 2, v + width / 2]` covered by lines.
+    fn coverage(&self, v: f32, width: f32) -> f32 {
+        let half = self.line_width / 2.0;
+        if width < EPSILON {
+            let offset = (v / self.spacing - (v / self.spacing).round()).abs() * self.spacing;
+            return if offset <= half { 1.0 } else { 0.0 };
+        }
+        // How much line there is from minus infinity up to `v`, counting
+        // from some line's near edge.
+        let covered = |v: f32| {
+            let from_edge = v + half;
+            (from_edge / self.spacing).floor() * self.line_width
+                + from_edge.rem_euclid(self.spacing).min(self.line_width)
+        };
+        (covered(v + width / 2.0) - covered(v - width / 2.0)) / width
+    }
+
+    fn blend(&self, point: Point, width: f32) -> Color {
+        let (x, z) = (
+            self.coverage(point.x(), width),
+            self.coverage(point.z(), width),
+        );
+        let line = x + z - x * z;
+        self.b + (self.a - self.b) * (line * self.strength(point))
+    }
+}
+
+impl PatternBuilder for Grid {
+    fn with_transform(self, transform: Transform) -> Self {
+        Self { transform, ..self }
+    }
+}
+
+impl Default for Grid {
+    fn default() -> Self {
+        Self {
+            a: color::BLACK,
+            b: color::WHITE,
+            spacing: 1.0,
+            line_width: 0.02,
+            fade: None,
+            transform: Transform::default(),
+        }
+    }
+}
+
+impl Pattern for Grid {
+    fn box_clone(&self) -> BoxPattern {
+        Box::new(*self)
+    }
+
+    fn box_eq(&self, other: &dyn Any) -> bool {
+        other.downcast_ref::<Self>().is_some_and(|a| self == a)
+    }
+
+    fn as_any(&self) -> &dyn Any {
+        self
+    }
+
+    fn transform(&self) -> &Transform {
+        &self.transform
+    }
+
+    fn pattern_at(&self, point: Point) -> Color {
+        self.blend(point, 0.0)
+    }
+
+    /// Lines averaged over the footprint thin out into a tint of `a` far
+    /// away, rather than breaking up into dashes.
+    fn filtered_pattern_at(&self, point: Point, width: f32) -> Color {
+        self.blend(point, width)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::shapes::sphere::Sphere;
+
+    use super::*;
+
+    #[test]
+    fn grid_lines_run_along_x_and_z() {
+        let pattern = Grid::new(color::BLACK, color::WHITE);
+
+        assert_eq!(pattern.pattern_at(Point::new(0.0, 0.0, 0.5)), color::BLACK);
+        assert_eq!(
+            pattern.pattern_at(Point::new(2.005, 0.0, 0.5)),
+            color::BLACK
+        );
+        assert_eq!(pattern.pattern_at(Point::new(0.5, 0.0, -3.0)), color::BLACK);
+        assert_eq!(pattern.pattern_at(Point::new(0.5, 0.0, 0.5)), color::WHITE);
+        assert_eq!(pattern.pattern_at(Point::new(0.5, 7.0, 0.5)), color::WHITE);
+    }
+
+    #[test]
+    fn grid_spacing_and_line_width_are_configurable() {
+        let pattern = Grid::new(color::BLACK, color::WHITE)
+            .spacing(2.0)
+            .line_width(0.5);
+
+        assert_eq!(pattern.pattern_at(Point::new(1.0, 0.0, 1.0)), color::WHITE);
+        assert_eq!(pattern.pattern_at(Point::new(2.2, 0.0, 1.0)), color::BLACK);
+        assert_eq!(pattern.pattern_at(Point::new(2.3, 0.0, 1.0)), color::WHITE);
+        assert_eq!(pattern.pattern_at(Point::new(1.0, 0.0, -3.8)), color::BLACK);
+    }
+
+    #[test]
+    fn grid_lines_fade_with_distance() {
+        let pattern = Grid::new(color::BLACK, color::WHITE).fade(10.0);
+
+        assert_eq!(pattern.pattern_at(Point::new(0.0, 0.0, 0.0)), color::BLACK);
+        assert_eq!(
+            pattern.pattern_at(Point::new(0.0, 0.0, 5.0)),
+            Color::new(0.5, 0.5, 0.5)
+        );
+        assert_eq!(pattern.pattern_at(Point::new(0.0, 0.0, 12.0)), color::WHITE);
+    }
+
+    #[test]
+    fn a_grid_averaged_over_many_cells_is_tinted_by_its_lines() {
+        let pattern = Grid::new(color::BLACK, color::WHITE)
+            .spacing(1.0)
+            .line_width(0.1);
+
+        let c = pattern.filtered_pattern_at(Point::new(3.3, 0.0, -7.6), 10.0);
+
+        // A tenth of each axis is line, so 0.19 of the area is.
+        assert_eq!(c, Color::new(0.81, 0.81, 0.81));
+        assert_eq!(
+            pattern.filtered_pattern_at(Point::new(0.5, 0.0, 0.5), 0.2),
+            color::WHITE
+        );
+    }
+
+    #[test]
+    fn a_grid_with_a_pattern_transformation() {
+        let object = Sphere::default();
+        let pattern = Grid::new(color::BLACK, color::WHITE)
+            .with_transform(Transform::translation(0.5, 0.0, 0.5));
+
+        assert_eq!(
+            pattern.pattern_at_shape(&object, Point::new(0.5, 0.0, 0.0)),
+            color::BLACK
+        );
+        assert_eq!(
+            pattern.pattern_at_shape(&object, Point::new(0.0, 0.0, 0.0)),
+            color::WHITE
+        );
+    }
+}
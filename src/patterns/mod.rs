@@ -1,32 +1,131 @@
 pub mod checkered;
 pub mod gradient;
+pub mod grid;
+pub mod noise;
 pub mod ring;
 pub mod striped;
+pub mod uv;
 
 use std::{any::Any, fmt::Debug};
 
-use crate::{color::Color, shapes::Shape, transformations::Transform, tuple::Tuple};
+use crate::{
+    color::Color,
+    shapes::Shape,
+    transformations::{Transform, IDENTITY},
+    tuple::Point,
+};
 
 pub trait PatternBuilder {
     fn with_transform(self, transform: Transform) -> Self;
 }
 
-pub trait Pattern: Any + Debug {
+pub trait Pattern: Any + Debug + Send + Sync {
     fn box_clone(&self) -> BoxPattern;
     fn box_eq(&self, other: &dyn Any) -> bool;
     fn as_any(&self) -> &dyn Any;
     fn transform(&self) -> &Transform;
-    fn pattern_at(&self, point: Tuple) -> Color;
-    fn pattern_at_shape(&self, object: &dyn Shape, world_point: Tuple) -> Color {
-        let object_point = object.transform().inverse() * world_point;
-        let pattern_point = self.transform().inverse() * object_point;
+    fn pattern_at(&self, point: Point) -> Color;
 
-        self.pattern_at(pattern_point)
+    /// The pattern averaged over a box `width` across centered on `point`.
+    /// Patterns that can't be averaged take the one sample.
+    fn filtered_pattern_at(&self, point: Point, _width: f32) -> Color {
+        self.pattern_at(point)
+    }
+
+    fn pattern_at_shape(&self, object: &dyn Shape, world_point: Point) -> Color {
+        self.filtered_pattern_at_shape(object, world_point, 0.0)
+    }
+
+    /// `pattern_at_shape` averaged over a footprint `width` across in world
+    /// space, such as the patch of surface a pixel covers.
+    fn filtered_pattern_at_shape(
+        &self,
+        object: &dyn Shape,
+        world_point: Point,
+        width: f32,
+    ) -> Color {
+        let object_width = width * object.transform().inverse().scale_factor();
+        self.pattern_at_object(object.world_to_object(world_point, &IDENTITY), object_width)
+    }
+
+    /// The pattern at a point already in the object's space, averaged over
+    /// `width` there.
+    fn pattern_at_object(&self, object_point: Point, width: f32) -> Color {
+        let inverse = self.transform().inverse();
+
+        self.filtered_pattern_at(inverse * object_point, width * inverse.scale_factor())
     }
 }
 
 pub type BoxPattern = Box<dyn Pattern>;
 
+/// A pattern as it's seen at a hit: found on a shape nested in `groups`,
+/// the groups' transforms combined, so it moves with them, and averaged
+/// over the hit's `footprint`.
+#[derive(Clone, Debug)]
+pub struct HitPattern {
+    pub pattern: BoxPattern,
+    pub groups: Transform,
+    pub footprint: f32,
+}
+
+impl Pattern for HitPattern {
+    fn box_clone(&self) -> BoxPattern {
+        Box::new(self.clone())
+    }
+
+    fn box_eq(&self, other: &dyn Any) -> bool {
+        other.downcast_ref::<Self>().map_or(false, |a| {
+            self.pattern.box_eq(a.pattern.as_any())
+                && self.groups == a.groups
+                && self.footprint == a.footprint
+        })
+    }
+
+    fn as_any(&self) -> &dyn Any {
+        self
+    }
+
+    fn transform(&self) -> &Transform {
+        self.pattern.transform()
+    }
+
+    fn pattern_at(&self, point: Point) -> Color {
+        self.pattern.pattern_at(point)
+    }
+
+    fn pattern_at_shape(&self, object: &dyn Shape, world_point: Point) -> Color {
+        self.filtered_pattern_at_shape(object, world_point, self.footprint)
+    }
+
+    fn filtered_pattern_at_shape(
+        &self,
+        object: &dyn Shape,
+        world_point: Point,
+        width: f32,
+    ) -> Color {
+        let to_object = (self.groups * *object.transform()).inverse();
+        self.pattern.pattern_at_object(
+            object.world_to_object(world_point, &self.groups),
+            width * to_object.scale_factor(),
+        )
+    }
+}
+
+/// The average over `[x - width / 2, x + width / 2]` of a wave that's 1 on
+/// even cells and -1 on odd ones, for box filtering patterns that alternate
+/// between two colors.
+fn alternation_average(x: f32, width: f32) -> f32 {
+    // The wave's running integral, a triangle wave.
+    let integral = |x: f32| 1.0 - (x.rem_euclid(2.0) - 1.0).abs();
+    (integral(x + width / 2.0) - integral(x - width / 2.0)) / width
+}
+
+/// `a` where `alternation` is 1, `b` where it's -1, and a blend between.
+fn blend_alternation(a: Color, b: Color, alternation: f32) -> Color {
+    b + (a - b) * (0.5 + 0.5 * alternation)
+}
+
 impl Clone for BoxPattern {
     fn clone(&self) -> Self {
         self.box_clone()
@@ -63,17 +162,14 @@ impl Pattern for TestPattern {
         &self.transform
     }
 
-    fn pattern_at(&self, point: Tuple) -> Color {
+    fn pattern_at(&self, point: Point) -> Color {
         Color::new(point.x(), point.y(), point.z())
     }
 }
 
 #[cfg(test)]
 mod tests {
-    use crate::{
-        shapes::{sphere::Sphere, ShapeBuilder},
-        transformations::IDENTITY,
-    };
+    use crate::shapes::{sphere::Sphere, ShapeBuilder};
 
     use super::*;
 
@@ -89,7 +185,7 @@ mod tests {
         let shape = Sphere::default().with_transform(Transform::scaling(2.0, 2.0, 2.0));
         let pattern = TestPattern::default();
 
-        let c = pattern.pattern_at_shape(&shape, Tuple::point(2.0, 3.0, 4.0));
+        let c = pattern.pattern_at_shape(&shape, Point::new(2.0, 3.0, 4.0));
 
         assert_eq!(c, Color::new(1.0, 1.5, 2.0));
     }
@@ -101,7 +197,7 @@ mod tests {
             transform: Transform::scaling(2.0, 2.0, 2.0),
         };
 
-        let c = pattern.pattern_at_shape(&shape, Tuple::point(2.0, 3.0, 4.0));
+        let c = pattern.pattern_at_shape(&shape, Point::new(2.0, 3.0, 4.0));
 
         assert_eq!(c, Color::new(1.0, 1.5, 2.0));
     }
@@ -113,7 +209,7 @@ mod tests {
             transform: Transform::translation(0.5, 1.0, 1.5),
         };
 
-        let c = pattern.pattern_at_shape(&shape, Tuple::point(2.5, 3.0, 3.5));
+        let c = pattern.pattern_at_shape(&shape, Point::new(2.5, 3.0, 3.5));
 
         assert_eq!(c, Color::new(0.75, 0.5, 0.25));
     }
@@ -0,0 +1,323 @@
+use std::any::Any;
+
+use crate::{
+    color::{self, Color},
+    transformations::Transform,
+    tuple::Point,
+};
+
+use super::{BoxPattern, Pattern, PatternBuilder};
+
+/// How the octaves of noise are combined before being looked up in the
+/// ramp.
+#[derive(Clone, Copy, Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum NoiseStyle {
+    /// Soft, rolling noise, as for clouds.
+    Smooth,
+    /// The magnitudes of the octaves summed, with creases where each
+    /// crosses zero, as for granite or fire.
+    Turbulence,
+    /// Bands across x, warped by turbulence, as for marble veins.
+    Marble,
+}
+
+/// Solid Perlin noise summed over `octaves`, each twice the frequency of
+/// the last and `persistence` times as strong, then colored by a ramp of
+/// `(t, color)` stops for the noise running from 0 to 1.
+#[derive(Clone, Debug, PartialEq)]
+#[cfg_attr(
+    feature = "serde",
+    derive(serde::Serialize, serde::Deserialize),
+    serde(default)
+)]
+pub struct Noise {
+    pub style: NoiseStyle,
+    pub octaves: usize,
+    pub persistence: f32,
+    pub ramp: Vec<(f32, Color)>,
+    pub transform: Transform,
+}
+
+impl Noise {
+    pub fn new(style: NoiseStyle) -> Self {
+        Self {
+            style,
+            ..Self::default()
+        }
+    }
+
+    /// White wisps on a blue sky.
+    pub fn clouds() -> Self {
+        Self::new(NoiseStyle::Smooth)
+            .ramp(vec![(0.3, Color::new(0.3, 0.5, 0.9)), (0.7, color::WHITE)])
+    }
+
+    /// Dark veins through white stone.
+    pub fn marble() -> Self {
+        Self::new(NoiseStyle::Marble).octaves(5).ramp(vec![
+            (0.0, Color::new(0.2, 0.2, 0.25)),
+            (0.3, Color::new(0.75, 0.75, 0.78)),
+            (1.0, Color::new(0.95, 0.95, 0.95)),
+        ])
+    }
+
+    /// Fine speckles of pink and grey.
+    pub fn granite() -> Self {
+        Self::new(NoiseStyle::Turbulence)
+            .octaves(6)
+            .persistence(0.7)
+            .ramp(vec![
+                (0.0, Color::new(0.15, 0.15, 0.15)),
+                (0.4, Color::new(0.6, 0.55, 0.55)),
+                (0.7, Color::new(0.85, 0.65, 0.6)),
+                (1.0, color::WHITE),
+            ])
+    }
+
+    pub fn octaves(self, octaves: usize) -> Self {
+        Self { octaves, ..self }
+    }
+
+    pub fn persistence(self, persistence: f32) -> Self {
+        Self {
+            persistence,
+            ..self
+        }
+    }
+
+    /// The stops are kept in order of `t`, whatever order they come in.
+    pub fn ramp(self, mut ramp: Vec<(f32, Color)>) -> Self {
+        ramp.sort_by(|a, b| a.0.partial_cmp(&b.0).unwrap());
+        Self { ramp, ..self }
+    }
+
+    /// The noise at `point`, from 0 to 1.
+    pub fn value_at(&self, point: Point) -> f32 {
+        let value = match self.style {
+            NoiseStyle::Smooth => 0.5 + 0.5 * self.octaves_at(point, |n| n),
+            NoiseStyle::Turbulence => self.octaves_at(point, f32::abs),
+            NoiseStyle::Marble => {
+                let warp = self.octaves_at(point, f32::abs);
+                0.5 + 0.5 * (point.x() + 5.0 * warp).sin()
+            }
+        };
+        value.clamp(0.0, 1.0)
+    }
+
+    /// The ramp's color at `t`, blended between the stops either side and
+    /// held at the end stops beyond them.
+    pub fn ramp_at(&self, t: f32) -> Color {
+        let next = self.ramp.iter().position(|&(stop, _)| stop > t);
+        match next {
+            None => self.ramp.last().map_or(color::BLACK, |&(_, c)| c),
+            Some(0) => self.ramp[0].1,
+            Some(i) => {
+                let (t0, c0) = self.ramp[i - 1];
+                let (t1, c1) = self.ramp[i];
+                c0 + (c1 - c0) * ((t - t0) / (t1 - t0))
+            }
+        }
+    }
+
+    /// The octaves, each passed through `shape`, summed and scaled back to
+    /// the range of a single one.
+    fn octaves_at(&self, point: Point, shape: impl Fn(f32) -> f32) -> f32 {
+        let mut total = 0.0;
+        let mut amplitude = 1.0;
+        let mut frequency = 1.0;
+        let mut max = 0.0;
+        for _ in 0..self.octaves.max(1) {
+            total += amplitude * shape(perlin(point, frequency));
+            max += amplitude;
+            amplitude *= self.persistence;
+            frequency *= 2.0;
+        }
+        total / max
+    }
+}
+
+impl PatternBuilder for Noise {
+    fn with_transform(self, transform: Transform) -> Self {
+        Self { transform, ..self }
+    }
+}
+
+impl Default for Noise {
+    fn default() -> Self {
+        Self {
+            style: NoiseStyle::Smooth,
+            octaves: 4,
+            persistence: 0.5,
+            ramp: vec![(0.0, color::BLACK), (1.0, color::WHITE)],
+            transform: Transform::default(),
+        }
+    }
+}
+
+impl Pattern for Noise {
+    fn box_clone(&self) -> BoxPattern {
+        Box::new(self.clone())
+    }
+
+    fn box_eq(&self, other: &dyn Any) -> bool {
+        other.downcast_ref::<Self>().map_or(false, |a| self == a)
+    }
+
+    fn as_any(&self) -> &dyn Any {
+        self
+    }
+
+    fn transform(&self) -> &Transform {
+        &self.transform
+    }
+
+    fn pattern_at(&self, point: Point) -> Color {
+        self.ramp_at(self.value_at(point))
+    }
+}
+
+/// Ken Perlin's improved noise at `point` scaled by `frequency`: zero at
+/// every lattice point and smooth in between, roughly from -1 to 1.
+fn perlin(point: Point, frequency: f32) -> f32 {
+    let (x, y, z) = (
+        point.x() * frequency,
+        point.y() * frequency,
+        point.z() * frequency,
+    );
+    let (xi, yi, zi) = (x.floor() as i32, y.floor() as i32, z.floor() as i32);
+    let (xf, yf, zf) = (x - x.floor(), y - y.floor(), z - z.floor());
+    let (u, v, w) = (fade(xf), fade(yf), fade(zf));
+
+    let corner = |dx: i32, dy: i32, dz: i32| {
+        gradient(
+            hash(xi + dx, yi + dy, zi + dz),
+            xf - dx as f32,
+            yf - dy as f32,
+            zf - dz as f32,
+        )
+    };
+    let lerp = |t: f32, a: f32, b: f32| a + t * (b - a);
+
+    lerp(
+        w,
+        lerp(
+            v,
+            lerp(u, corner(0, 0, 0), corner(1, 0, 0)),
+            lerp(u, corner(0, 1, 0), corner(1, 1, 0)),
+        ),
+        lerp(
+            v,
+            lerp(u, corner(0, 0, 1), corner(1, 0, 1)),
+            lerp(u, corner(0, 1, 1), corner(1, 1, 1)),
+        ),
+    )
+}
+
+fn fade(t: f32) -> f32 {
+    t * t * t * (t * (t * 6.0 - 15.0) + 10.0)
+}
+
+/// Scrambles a lattice point into bits for picking its gradient, standing
+/// in for the usual permutation table.
+fn hash(x: i32, y: i32, z: i32) -> u32 {
+    let mut h = (x as u32).wrapping_mul(0x8da6_b343)
+        ^ (y as u32).wrapping_mul(0xd816_3841)
+        ^ (z as u32).wrapping_mul(0xcb1a_b31f);
+    h ^= h >> 13;
+    h = h.wrapping_mul(0x5bd1_e995);
+    h ^ (h >> 15)
+}
+
+/// The dot product of the offset with one of the twelve edge directions
+/// of a cube, picked by `hash`.
+fn gradient(hash: u32, x: f32, y: f32, z: f32) -> f32 {
+    let h = hash & 15;
+    let u = if h < 8 { x } else { y };
+    let v = match h {
+        0..=3 => y,
+        12 | 14 => x,
+        _ => z,
+    };
+    (if h & 1 == 0 { u } else { -u }) + (if h & 2 == 0 { v } else { -v })
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::float_eq;
+
+    use super::*;
+
+    fn samples() -> impl Iterator<Item = Point> {
+        (0..500).map(|i| {
+            let i = i as f32;
+            Point::new(i * 0.37, i * 0.11 - 20.0, i * 0.73)
+        })
+    }
+
+    #[test]
+    fn noise_is_zero_on_the_lattice() {
+        for (x, y, z) in [(0.0, 0.0, 0.0), (1.0, -2.0, 3.0), (-5.0, 7.0, 11.0)] {
+            assert_eq!(perlin(Point::new(x, y, z), 1.0), 0.0);
+        }
+    }
+
+    #[test]
+    fn noise_varies_smoothly() {
+        for p in samples() {
+            let near = Point::new(p.x() + 0.001, p.y(), p.z());
+            assert!((perlin(p, 1.0) - perlin(near, 1.0)).abs() < 0.01);
+        }
+    }
+
+    #[test]
+    fn noise_values_stay_between_zero_and_one() {
+        let styles = [
+            NoiseStyle::Smooth,
+            NoiseStyle::Turbulence,
+            NoiseStyle::Marble,
+        ];
+        for style in styles.iter() {
+            let noise = Noise::new(*style);
+            let values = samples().map(|p| noise.value_at(p)).collect::<Vec<_>>();
+
+            assert!(values.iter().all(|v| (0.0..=1.0).contains(v)));
+            assert!(values.iter().any(|v| (v - values[0]).abs() > 0.1));
+        }
+    }
+
+    #[test]
+    fn more_octaves_add_finer_detail() {
+        let p = Point::new(0.3, 0.6, 0.2);
+        let q = Point::new(0.3, 0.6, 0.25);
+        let coarse = Noise::default().octaves(1);
+        let fine = Noise::default().octaves(6).persistence(0.8);
+
+        let coarse_change = (coarse.value_at(p) - coarse.value_at(q)).abs();
+        let fine_change = (fine.value_at(p) - fine.value_at(q)).abs();
+
+        assert_ne!(coarse.value_at(p), fine.value_at(p));
+        assert!(fine_change > coarse_change);
+    }
+
+    #[test]
+    fn the_ramp_blends_between_stops() {
+        let noise =
+            Noise::default().ramp(vec![(1.0, color::WHITE), (0.5, Color::new(1.0, 0.0, 0.0))]);
+
+        assert_eq!(noise.ramp_at(0.2), Color::new(1.0, 0.0, 0.0));
+        assert_eq!(noise.ramp_at(0.75), Color::new(1.0, 0.5, 0.5));
+        assert_eq!(noise.ramp_at(1.0), color::WHITE);
+        assert_eq!(noise.ramp_at(1.5), color::WHITE);
+    }
+
+    #[test]
+    fn the_pattern_is_the_ramp_at_the_noise() {
+        let marble = Noise::marble();
+        let grey = Noise::default();
+        let p = Point::new(1.3, -0.4, 2.2);
+
+        assert_eq!(marble.pattern_at(p), marble.ramp_at(marble.value_at(p)));
+        assert!(float_eq(grey.pattern_at(p).red(), grey.value_at(p)));
+    }
+}
@@ -3,12 +3,18 @@ use std::any::Any;
 use crate::{
     color::{self, Color},
     transformations::Transform,
-    tuple::Tuple,
+    tuple::Point,
+    EPSILON,
 };
 
-use super::{BoxPattern, Pattern, PatternBuilder};
+use super::{alternation_average, blend_alternation, BoxPattern, Pattern, PatternBuilder};
 
 #[derive(Clone, Copy, Debug, PartialEq)]
+#[cfg_attr(
+    feature = "serde",
+    derive(serde::Serialize, serde::Deserialize),
+    serde(default)
+)]
 pub struct Ring {
     pub a: Color,
     pub b: Color,
@@ -58,7 +64,7 @@ impl Pattern for Ring {
         &self.transform
     }
 
-    fn pattern_at(&self, point: Tuple) -> Color {
+    fn pattern_at(&self, point: Point) -> Color {
         if (point.x() * point.x() + point.z() * point.z())
             .sqrt()
             .floor() as u32
@@ -70,6 +76,14 @@ impl Pattern for Ring {
             self.b
         }
     }
+
+    fn filtered_pattern_at(&self, point: Point, width: f32) -> Color {
+        if width < EPSILON {
+            return self.pattern_at(point);
+        }
+        let radius = (point.x() * point.x() + point.z() * point.z()).sqrt();
+        blend_alternation(self.a, self.b, alternation_average(radius, width))
+    }
 }
 
 #[cfg(test)]
@@ -80,22 +94,22 @@ mod tests {
     fn a_ring_should_extend_in_both_x_and_z() {
         let pattern = Ring::new(color::WHITE, color::BLACK);
 
-        assert_eq!(
-            pattern.pattern_at(Tuple::point(0.0, 0.0, 0.0)),
-            color::WHITE
-        );
-        assert_eq!(
-            pattern.pattern_at(Tuple::point(1.0, 0.0, 0.0)),
-            color::BLACK
-        );
-        assert_eq!(
-            pattern.pattern_at(Tuple::point(0.0, 0.0, 1.0)),
-            color::BLACK
-        );
+        assert_eq!(pattern.pattern_at(Point::new(0.0, 0.0, 0.0)), color::WHITE);
+        assert_eq!(pattern.pattern_at(Point::new(1.0, 0.0, 0.0)), color::BLACK);
+        assert_eq!(pattern.pattern_at(Point::new(0.0, 0.0, 1.0)), color::BLACK);
         // 0.708 = slightly more than sqrt(2) / 2
         assert_eq!(
-            pattern.pattern_at(Tuple::point(0.708, 0.0, 0.708)),
+            pattern.pattern_at(Point::new(0.708, 0.0, 0.708)),
             color::BLACK
         );
     }
+
+    #[test]
+    fn rings_averaged_across_an_edge() {
+        let pattern = Ring::new(color::WHITE, color::BLACK);
+
+        let c = pattern.filtered_pattern_at(Point::new(0.0, 0.0, 1.0), 0.5);
+
+        assert_eq!(c, Color::new(0.5, 0.5, 0.5));
+    }
 }
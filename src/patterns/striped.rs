@@ -3,12 +3,18 @@ use std::any::Any;
 use crate::{
     color::{self, Color},
     transformations::Transform,
-    tuple::Tuple,
+    tuple::Point,
+    EPSILON,
 };
 
-use super::{BoxPattern, Pattern, PatternBuilder};
+use super::{alternation_average, blend_alternation, BoxPattern, Pattern, PatternBuilder};
 
 #[derive(Clone, Copy, Debug, PartialEq)]
+#[cfg_attr(
+    feature = "serde",
+    derive(serde::Serialize, serde::Deserialize),
+    serde(default)
+)]
 pub struct Striped {
     pub a: Color,
     pub b: Color,
@@ -58,13 +64,20 @@ impl Pattern for Striped {
         &self.transform
     }
 
-    fn pattern_at(&self, point: Tuple) -> Color {
+    fn pattern_at(&self, point: Point) -> Color {
         if ((point.x() % 2.0) + 2.0) % 2.0 < 1.0 {
             self.a
         } else {
             self.b
         }
     }
+
+    fn filtered_pattern_at(&self, point: Point, width: f32) -> Color {
+        if width < EPSILON {
+            return self.pattern_at(point);
+        }
+        blend_alternation(self.a, self.b, alternation_average(point.x(), width))
+    }
 }
 
 #[cfg(test)]
@@ -89,66 +102,30 @@ mod tests {
     fn a_stripe_pattern_is_constant_in_y() {
         let pattern = Striped::new(color::WHITE, color::BLACK);
 
-        assert_eq!(
-            pattern.pattern_at(Tuple::point(0.0, 0.0, 0.0)),
-            color::WHITE
-        );
-        assert_eq!(
-            pattern.pattern_at(Tuple::point(0.0, 1.0, 0.0)),
-            color::WHITE
-        );
-        assert_eq!(
-            pattern.pattern_at(Tuple::point(0.0, 2.0, 0.0)),
-            color::WHITE
-        );
+        assert_eq!(pattern.pattern_at(Point::new(0.0, 0.0, 0.0)), color::WHITE);
+        assert_eq!(pattern.pattern_at(Point::new(0.0, 1.0, 0.0)), color::WHITE);
+        assert_eq!(pattern.pattern_at(Point::new(0.0, 2.0, 0.0)), color::WHITE);
     }
 
     #[test]
     fn a_stripe_pattern_is_constant_in_z() {
         let pattern = Striped::new(color::WHITE, color::BLACK);
 
-        assert_eq!(
-            pattern.pattern_at(Tuple::point(0.0, 0.0, 0.0)),
-            color::WHITE
-        );
-        assert_eq!(
-            pattern.pattern_at(Tuple::point(0.0, 0.0, 1.0)),
-            color::WHITE
-        );
-        assert_eq!(
-            pattern.pattern_at(Tuple::point(0.0, 0.0, 2.0)),
-            color::WHITE
-        );
+        assert_eq!(pattern.pattern_at(Point::new(0.0, 0.0, 0.0)), color::WHITE);
+        assert_eq!(pattern.pattern_at(Point::new(0.0, 0.0, 1.0)), color::WHITE);
+        assert_eq!(pattern.pattern_at(Point::new(0.0, 0.0, 2.0)), color::WHITE);
     }
 
     #[test]
     fn a_stripe_pattern_alternates_in_x() {
         let pattern = Striped::new(color::WHITE, color::BLACK);
 
-        assert_eq!(
-            pattern.pattern_at(Tuple::point(0.0, 0.0, 0.0)),
-            color::WHITE
-        );
-        assert_eq!(
-            pattern.pattern_at(Tuple::point(0.9, 0.0, 0.0)),
-            color::WHITE
-        );
-        assert_eq!(
-            pattern.pattern_at(Tuple::point(1.0, 0.0, 0.0)),
-            color::BLACK
-        );
-        assert_eq!(
-            pattern.pattern_at(Tuple::point(-0.1, 0.0, 0.0)),
-            color::BLACK
-        );
-        assert_eq!(
-            pattern.pattern_at(Tuple::point(-1.0, 0.0, 0.0)),
-            color::BLACK
-        );
-        assert_eq!(
-            pattern.pattern_at(Tuple::point(-1.1, 0.0, 0.0)),
-            color::WHITE
-        );
+        assert_eq!(pattern.pattern_at(Point::new(0.0, 0.0, 0.0)), color::WHITE);
+        assert_eq!(pattern.pattern_at(Point::new(0.9, 0.0, 0.0)), color::WHITE);
+        assert_eq!(pattern.pattern_at(Point::new(1.0, 0.0, 0.0)), color::BLACK);
+        assert_eq!(pattern.pattern_at(Point::new(-0.1, 0.0, 0.0)), color::BLACK);
+        assert_eq!(pattern.pattern_at(Point::new(-1.0, 0.0, 0.0)), color::BLACK);
+        assert_eq!(pattern.pattern_at(Point::new(-1.1, 0.0, 0.0)), color::WHITE);
     }
 
     #[test]
@@ -156,7 +133,7 @@ mod tests {
         let object = Sphere::default().with_transform(Transform::scaling(2.0, 2.0, 2.0));
         let pattern = Striped::new(color::WHITE, color::BLACK);
 
-        let c = pattern.pattern_at_shape(&object, Tuple::point(1.5, 0.0, 0.0));
+        let c = pattern.pattern_at_shape(&object, Point::new(1.5, 0.0, 0.0));
 
         assert_eq!(c, color::WHITE);
     }
@@ -167,7 +144,7 @@ mod tests {
         let pattern = Striped::new(color::WHITE, color::BLACK)
             .with_transform(Transform::scaling(2.0, 2.0, 2.0));
 
-        let c = pattern.pattern_at_shape(&object, Tuple::point(1.5, 0.0, 0.0));
+        let c = pattern.pattern_at_shape(&object, Point::new(1.5, 0.0, 0.0));
 
         assert_eq!(c, color::WHITE);
     }
@@ -178,8 +155,40 @@ mod tests {
         let pattern = Striped::new(color::WHITE, color::BLACK)
             .with_transform(Transform::translation(0.5, 0.0, 0.0));
 
-        let c = pattern.pattern_at_shape(&object, Tuple::point(2.5, 0.0, 0.0));
+        let c = pattern.pattern_at_shape(&object, Point::new(2.5, 0.0, 0.0));
 
         assert_eq!(c, color::WHITE);
     }
+
+    #[test]
+    fn stripes_averaged_over_a_pair_blend_evenly() {
+        let pattern = Striped::new(color::WHITE, color::BLACK);
+        let grey = Color::new(0.5, 0.5, 0.5);
+
+        assert_eq!(
+            pattern.filtered_pattern_at(Point::new(0.3, 0.0, 0.0), 2.0),
+            grey
+        );
+        assert_eq!(
+            pattern.filtered_pattern_at(Point::new(7.9, 0.0, 0.0), 4.0),
+            grey
+        );
+    }
+
+    #[test]
+    fn stripes_averaged_across_an_edge() {
+        let pattern = Striped::new(color::WHITE, color::BLACK);
+
+        let c = pattern.filtered_pattern_at(Point::new(0.9, 0.0, 0.0), 0.4);
+
+        assert_eq!(c, Color::new(0.75, 0.75, 0.75));
+        assert_eq!(
+            pattern.filtered_pattern_at(Point::new(0.5, 0.0, 0.0), 0.4),
+            color::WHITE
+        );
+        assert_eq!(
+            pattern.filtered_pattern_at(Point::new(1.5, 0.0, 0.0), 0.0),
+            color::BLACK
+        );
+    }
 }
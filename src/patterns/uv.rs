@@ -0,0 +1,784 @@
+use std::{
+    any::Any,
+    f32::consts::{FRAC_1_PI, PI},
+};
+
+use crate::{
+    canvas::Canvas,
+    color::{self, Color},
+    transformations::Transform,
+    tuple::{Point, Vector},
+};
+
+use super::{BoxPattern, Pattern, PatternBuilder};
+
+/// Checkers laid out over the unit square of texture space, `width` across
+/// and `height` high.
+#[derive(Clone, Copy, Debug, PartialEq)]
+#[cfg_attr(
+    feature = "serde",
+    derive(serde::Serialize, serde::Deserialize),
+    serde(default)
+)]
+pub struct UvChecker {
+    pub width: f32,
+    pub height: f32,
+    pub a: Color,
+    pub b: Color,
+}
+
+impl UvChecker {
+    pub fn new(width: f32, height: f32, a: Color, b: Color) -> Self {
+        Self {
+            width,
+            height,
+            a,
+            b,
+        }
+    }
+
+    pub fn uv_pattern_at(&self, u: f32, v: f32) -> Color {
+        let u2 = (u * self.width).floor() as i64;
+        let v2 = (v * self.height).floor() as i64;
+
+        if (u2 + v2).rem_euclid(2) == 0 {
+            self.a
+        } else {
+            self.b
+        }
+    }
+}
+
+impl Default for UvChecker {
+    fn default() -> Self {
+        Self::new(2.0, 2.0, color::WHITE, color::BLACK)
+    }
+}
+
+/// A test card for checking how texture space is laid out: `main`
+/// everywhere but the corners, each of which gets its own color.
+#[derive(Clone, Copy, Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct UvAlignCheck {
+    pub main: Color,
+    pub upper_left: Color,
+    pub upper_right: Color,
+    pub bottom_left: Color,
+    pub bottom_right: Color,
+}
+
+impl UvAlignCheck {
+    pub fn new(
+        main: Color,
+        upper_left: Color,
+        upper_right: Color,
+        bottom_left: Color,
+        bottom_right: Color,
+    ) -> Self {
+        Self {
+            main,
+            upper_left,
+            upper_right,
+            bottom_left,
+            bottom_right,
+        }
+    }
+
+    pub fn uv_pattern_at(&self, u: f32, v: f32) -> Color {
+        match (u, v) {
+            (u, v) if v > 0.8 && u < 0.2 => self.upper_left,
+            (u, v) if v > 0.8 && u > 0.8 => self.upper_right,
+            (u, v) if v < 0.2 && u < 0.2 => self.bottom_left,
+            (u, v) if v < 0.2 && u > 0.8 => self.bottom_right,
+            _ => self.main,
+        }
+    }
+}
+
+/// A label stuck on part of texture space: `label` fitted to the rectangle
+/// from `min` to `max`, with `background` everywhere else, as for the band
+/// of paper around a bottle.
+#[derive(Clone, Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct UvLabel {
+    pub label: Box<UvPattern>,
+    pub background: Color,
+    pub min: (f32, f32),
+    pub max: (f32, f32),
+}
+
+impl UvLabel {
+    pub fn new(
+        label: impl Into<UvPattern>,
+        background: Color,
+        min: (f32, f32),
+        max: (f32, f32),
+    ) -> Self {
+        Self {
+            label: Box::new(label.into()),
+            background,
+            min,
+            max,
+        }
+    }
+
+    pub fn uv_pattern_at(&self, u: f32, v: f32) -> Color {
+        let (u0, v0) = self.min;
+        let (u1, v1) = self.max;
+        if (u0..u1).contains(&u) && (v0..v1).contains(&v) {
+            self.label
+                .uv_pattern_at((u - u0) / (u1 - u0), (v - v0) / (v1 - v0))
+        } else {
+            self.background
+        }
+    }
+}
+
+/// An image stretched over the unit square of texture space, with `v`
+/// running up from the bottom row.
+#[derive(Clone, Debug, PartialEq)]
+pub struct UvImage {
+    pub image: Canvas,
+}
+
+impl UvImage {
+    pub fn new(image: Canvas) -> Self {
+        Self { image }
+    }
+
+    /// The nearest pixel to `u`, `v`.
+    pub fn uv_pattern_at(&self, u: f32, v: f32) -> Color {
+        let (width, height) = (self.image.width, self.image.height);
+        let x = (u.clamp(0.0, 1.0) * (width - 1) as f32).round() as usize;
+        let y = ((1.0 - v.clamp(0.0, 1.0)) * (height - 1) as f32).round() as usize;
+
+        self.image.pixel_at(x, y)
+    }
+}
+
+/// Anything that colors the unit square of texture space.
+#[derive(Clone, Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum UvPattern {
+    Checker(UvChecker),
+    AlignCheck(UvAlignCheck),
+    Label(UvLabel),
+    /// Images aren't saved with a scene.
+    #[cfg_attr(feature = "serde", serde(skip))]
+    Image(UvImage),
+}
+
+impl UvPattern {
+    pub fn uv_pattern_at(&self, u: f32, v: f32) -> Color {
+        match self {
+            UvPattern::Checker(checker) => checker.uv_pattern_at(u, v),
+            UvPattern::AlignCheck(align_check) => align_check.uv_pattern_at(u, v),
+            UvPattern::Label(label) => label.uv_pattern_at(u, v),
+            UvPattern::Image(image) => image.uv_pattern_at(u, v),
+        }
+    }
+}
+
+impl From<UvChecker> for UvPattern {
+    fn from(checker: UvChecker) -> Self {
+        UvPattern::Checker(checker)
+    }
+}
+
+impl From<UvAlignCheck> for UvPattern {
+    fn from(align_check: UvAlignCheck) -> Self {
+        UvPattern::AlignCheck(align_check)
+    }
+}
+
+impl From<UvLabel> for UvPattern {
+    fn from(label: UvLabel) -> Self {
+        UvPattern::Label(label)
+    }
+}
+
+impl From<UvImage> for UvPattern {
+    fn from(image: UvImage) -> Self {
+        UvPattern::Image(image)
+    }
+}
+
+/// How a point in pattern space is flattened into `u` and `v` in [0, 1).
+#[derive(Clone, Copy, Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum UvMapping {
+    /// Tiles the xz plane, ignoring y entirely.
+    Planar,
+    /// Wraps around the unit sphere like lines of longitude and latitude.
+    Spherical,
+    /// Measures along the sides of a parallelogram, from (0, 0) at `origin`
+    /// to (1, 1) at the opposite corner.
+    Parallelogram {
+        origin: Point,
+        uvec: Vector,
+        vvec: Vector,
+    },
+    /// Spreads each face of the cube from -1 to 1 over the whole square, so
+    /// every face shows the full pattern.
+    Cube,
+    /// Wraps once around the sides of the unit cylinder, with `u` running
+    /// round from -z and `v` repeating every unit of height.
+    Cylindrical,
+    /// Wraps around a cone with its tip at the origin, as `Cylindrical`
+    /// does, but with `v` measured along the sloping side from the tip, so
+    /// a label on a cup keeps its proportions.
+    Conical,
+}
+
+impl UvMapping {
+    pub fn map(&self, point: Point) -> (f32, f32) {
+        match self {
+            UvMapping::Planar => (point.x().rem_euclid(1.0), point.z().rem_euclid(1.0)),
+            UvMapping::Spherical => {
+                let theta = point.x().atan2(point.z());
+                let radius = point.to_vector().magnitude();
+                let phi = (point.y() / radius).acos();
+                let raw_u = theta / (2.0 * PI);
+
+                (1.0 - (raw_u + 0.5), 1.0 - phi * FRAC_1_PI)
+            }
+            UvMapping::Parallelogram { origin, uvec, vvec } => {
+                let offset = point - *origin;
+                let n = uvec.cross(*vvec);
+                let w = n / n.dot(n);
+
+                (w.dot(offset.cross(*vvec)), w.dot(uvec.cross(offset)))
+            }
+            UvMapping::Cube => {
+                let (_, u, v) = cube_uv(point);
+                (u, v)
+            }
+            UvMapping::Cylindrical => (around(point), point.y().rem_euclid(1.0)),
+            UvMapping::Conical => (around(point), point.to_vector().magnitude().rem_euclid(1.0)),
+        }
+    }
+}
+
+/// How far round the y axis `point` is, from 0 at -z through +x and +z.
+fn around(point: Point) -> f32 {
+    let theta = point.x().atan2(point.z());
+    let raw_u = theta / (2.0 * PI);
+    (1.0 - (raw_u + 0.5)).rem_euclid(1.0)
+}
+
+/// The faces of the cube from -1 to 1, named as seen from outside with +y
+/// up and the front toward +z.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum CubeFace {
+    Left,
+    Right,
+    Front,
+    Back,
+    Up,
+    Down,
+}
+
+impl CubeFace {
+    /// The face `point` is on, or nearest to, going by its largest
+    /// coordinate.
+    pub fn of(point: Point) -> Self {
+        let (x, y, z) = (point.x(), point.y(), point.z());
+        let coord = x.abs().max(y.abs()).max(z.abs());
+
+        match coord {
+            _ if coord == x => CubeFace::Right,
+            _ if coord == -x => CubeFace::Left,
+            _ if coord == y => CubeFace::Up,
+            _ if coord == -y => CubeFace::Down,
+            _ if coord == z => CubeFace::Front,
+            _ => CubeFace::Back,
+        }
+    }
+
+    fn index(self) -> usize {
+        self as usize
+    }
+}
+
+/// The face `point` lies on and where on it, with `u` running left to
+/// right and `v` bottom to top as the face is seen from outside. The up
+/// and down faces are seen with the front face below them.
+pub fn cube_uv(point: Point) -> (CubeFace, f32, f32) {
+    let (x, y, z) = (point.x(), point.y(), point.z());
+    let unit = |a: f32| (a + 1.0).rem_euclid(2.0) / 2.0;
+    let face = CubeFace::of(point);
+
+    let (u, v) = match face {
+        CubeFace::Front => (unit(x), unit(y)),
+        CubeFace::Back => (unit(-x), unit(y)),
+        CubeFace::Left => (unit(z), unit(y)),
+        CubeFace::Right => (unit(-z), unit(y)),
+        CubeFace::Up => (unit(x), unit(-z)),
+        CubeFace::Down => (unit(x), unit(z)),
+    };
+    (face, u, v)
+}
+
+/// A texture space pattern wrapped onto shapes by a `UvMapping`.
+#[derive(Clone, Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct TextureMap {
+    pub uv_pattern: UvPattern,
+    pub mapping: UvMapping,
+    pub transform: Transform,
+}
+
+impl TextureMap {
+    pub fn new(uv_pattern: impl Into<UvPattern>, mapping: UvMapping) -> Self {
+        Self {
+            uv_pattern: uv_pattern.into(),
+            mapping,
+            transform: Transform::default(),
+        }
+    }
+}
+
+impl PatternBuilder for TextureMap {
+    fn with_transform(self, transform: Transform) -> Self {
+        Self { transform, ..self }
+    }
+}
+
+impl Pattern for TextureMap {
+    fn box_clone(&self) -> BoxPattern {
+        Box::new(self.clone())
+    }
+
+    fn box_eq(&self, other: &dyn Any) -> bool {
+        other.downcast_ref::<Self>().map_or(false, |a| self == a)
+    }
+
+    fn as_any(&self) -> &dyn Any {
+        self
+    }
+
+    fn transform(&self) -> &Transform {
+        &self.transform
+    }
+
+    fn pattern_at(&self, point: Point) -> Color {
+        let (u, v) = self.mapping.map(point);
+        self.uv_pattern.uv_pattern_at(u, v)
+    }
+}
+
+/// A different texture space pattern on each face of the cube from -1 to 1,
+/// as for a skybox.
+#[derive(Clone, Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct CubeMap {
+    /// In the order of `CubeFace`: left, right, front, back, up, down.
+    pub faces: [UvPattern; 6],
+    pub transform: Transform,
+}
+
+impl CubeMap {
+    pub fn new(
+        left: impl Into<UvPattern>,
+        right: impl Into<UvPattern>,
+        front: impl Into<UvPattern>,
+        back: impl Into<UvPattern>,
+        up: impl Into<UvPattern>,
+        down: impl Into<UvPattern>,
+    ) -> Self {
+        Self {
+            faces: [
+                left.into(),
+                right.into(),
+                front.into(),
+                back.into(),
+                up.into(),
+                down.into(),
+            ],
+            transform: Transform::default(),
+        }
+    }
+
+    pub fn face(&self, face: CubeFace) -> &UvPattern {
+        &self.faces[face.index()]
+    }
+}
+
+impl PatternBuilder for CubeMap {
+    fn with_transform(self, transform: Transform) -> Self {
+        Self { transform, ..self }
+    }
+}
+
+impl Pattern for CubeMap {
+    fn box_clone(&self) -> BoxPattern {
+        Box::new(self.clone())
+    }
+
+    fn box_eq(&self, other: &dyn Any) -> bool {
+        other.downcast_ref::<Self>().map_or(false, |a| self == a)
+    }
+
+    fn as_any(&self) -> &dyn Any {
+        self
+    }
+
+    fn transform(&self) -> &Transform {
+        &self.transform
+    }
+
+    fn pattern_at(&self, point: Point) -> Color {
+        let (face, u, v) = cube_uv(point);
+        self.face(face).uv_pattern_at(u, v)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::f32::consts::{FRAC_1_SQRT_2, SQRT_2};
+
+    use crate::{
+        float_eq,
+        shapes::{cube::Cube, plane::Plane, ShapeBuilder},
+    };
+
+    use super::*;
+
+    macro_rules! uv_checker_tests {
+        ($($name:ident: $value:expr,)*) => {
+            $(
+                #[test]
+                fn $name() {
+                    let ((u, v), expected) = $value;
+                    let checkers = UvChecker::new(2.0, 2.0, color::BLACK, color::WHITE);
+
+                    assert_eq!(checkers.uv_pattern_at(u, v), expected);
+                }
+            )*
+        }
+    }
+
+    uv_checker_tests! {
+        checker_pattern_in_2d_0: ((0.0, 0.0), color::BLACK),
+        checker_pattern_in_2d_1: ((0.5, 0.0), color::WHITE),
+        checker_pattern_in_2d_2: ((0.0, 0.5), color::WHITE),
+        checker_pattern_in_2d_3: ((0.5, 0.5), color::BLACK),
+        checker_pattern_in_2d_4: ((1.0, 1.0), color::BLACK),
+    }
+
+    macro_rules! mapping_tests {
+        ($($name:ident: $value:expr,)*) => {
+            $(
+                #[test]
+                fn $name() {
+                    let (mapping, point, (u, v)) = $value;
+
+                    let (actual_u, actual_v) = mapping.map(point);
+
+                    assert!(float_eq(actual_u, u));
+                    assert!(float_eq(actual_v, v));
+                }
+            )*
+        }
+    }
+
+    mapping_tests! {
+        spherical_mapping_on_a_3d_point_0: (UvMapping::Spherical, Point::new(0.0, 0.0, -1.0), (0.0, 0.5)),
+        spherical_mapping_on_a_3d_point_1: (UvMapping::Spherical, Point::new(1.0, 0.0, 0.0), (0.25, 0.5)),
+        spherical_mapping_on_a_3d_point_2: (UvMapping::Spherical, Point::new(0.0, 0.0, 1.0), (0.5, 0.5)),
+        spherical_mapping_on_a_3d_point_3: (UvMapping::Spherical, Point::new(-1.0, 0.0, 0.0), (0.75, 0.5)),
+        spherical_mapping_on_a_3d_point_4: (UvMapping::Spherical, Point::new(0.0, 1.0, 0.0), (0.5, 1.0)),
+        spherical_mapping_on_a_3d_point_5: (UvMapping::Spherical, Point::new(0.0, -1.0, 0.0), (0.5, 0.0)),
+        spherical_mapping_on_a_3d_point_6: (UvMapping::Spherical, Point::new(SQRT_2 / 2.0, SQRT_2 / 2.0, 0.0), (0.25, 0.75)),
+        planar_mapping_on_a_3d_point_0: (UvMapping::Planar, Point::new(0.25, 0.5, 0.75), (0.25, 0.75)),
+        planar_mapping_on_a_3d_point_1: (UvMapping::Planar, Point::new(0.25, 0.5, -0.25), (0.25, 0.75)),
+        planar_mapping_on_a_3d_point_2: (UvMapping::Planar, Point::new(0.25, 0.5, -1.75), (0.25, 0.25)),
+        planar_mapping_on_a_3d_point_3: (UvMapping::Planar, Point::new(1.25, 0.0, 0.5), (0.25, 0.5)),
+        planar_mapping_on_a_3d_point_4: (UvMapping::Planar, Point::new(0.25, 0.0, -1.75), (0.25, 0.25)),
+        planar_mapping_on_a_3d_point_5: (UvMapping::Planar, Point::new(1.0, 0.0, -1.0), (0.0, 0.0)),
+        planar_mapping_on_a_3d_point_6: (UvMapping::Planar, Point::new(0.0, 0.0, 0.0), (0.0, 0.0)),
+        cylindrical_mapping_on_a_3d_point_0: (UvMapping::Cylindrical, Point::new(0.0, 0.0, -1.0), (0.0, 0.0)),
+        cylindrical_mapping_on_a_3d_point_1: (UvMapping::Cylindrical, Point::new(0.0, 0.5, -1.0), (0.0, 0.5)),
+        cylindrical_mapping_on_a_3d_point_2: (UvMapping::Cylindrical, Point::new(0.0, 1.0, -1.0), (0.0, 0.0)),
+        cylindrical_mapping_on_a_3d_point_3: (UvMapping::Cylindrical, Point::new(FRAC_1_SQRT_2, 0.5, -FRAC_1_SQRT_2), (0.125, 0.5)),
+        cylindrical_mapping_on_a_3d_point_4: (UvMapping::Cylindrical, Point::new(1.0, 0.5, 0.0), (0.25, 0.5)),
+        cylindrical_mapping_on_a_3d_point_5: (UvMapping::Cylindrical, Point::new(FRAC_1_SQRT_2, 0.5, FRAC_1_SQRT_2), (0.375, 0.5)),
+        cylindrical_mapping_on_a_3d_point_6: (UvMapping::Cylindrical, Point::new(0.0, -0.25, 1.0), (0.5, 0.75)),
+        cylindrical_mapping_on_a_3d_point_7: (UvMapping::Cylindrical, Point::new(-FRAC_1_SQRT_2, 0.5, FRAC_1_SQRT_2), (0.625, 0.5)),
+        cylindrical_mapping_on_a_3d_point_8: (UvMapping::Cylindrical, Point::new(-1.0, 1.25, 0.0), (0.75, 0.25)),
+        cylindrical_mapping_on_a_3d_point_9: (UvMapping::Cylindrical, Point::new(-FRAC_1_SQRT_2, 0.5, -FRAC_1_SQRT_2), (0.875, 0.5)),
+        conical_mapping_at_the_tip: (UvMapping::Conical, Point::new(0.0, 0.0, 0.0), (0.5, 0.0)),
+        conical_mapping_measures_along_the_side: (UvMapping::Conical, Point::new(0.0, 0.5, -0.5), (0.0, SQRT_2 / 2.0)),
+        conical_mapping_wraps_around_like_a_cylinder: (UvMapping::Conical, Point::new(0.25, 0.25, 0.0), (0.25, SQRT_2 / 4.0)),
+        conical_mapping_below_the_tip: (UvMapping::Conical, Point::new(-0.5, -0.5, 0.0), (0.75, SQRT_2 / 2.0)),
+    }
+
+    macro_rules! spherical_texture_tests {
+        ($($name:ident: $value:expr,)*) => {
+            $(
+                #[test]
+                fn $name() {
+                    let (point, expected) = $value;
+                    let checker = UvChecker::new(16.0, 8.0, color::BLACK, color::WHITE);
+                    let pattern = TextureMap::new(checker, UvMapping::Spherical);
+
+                    assert_eq!(pattern.pattern_at(point), expected);
+                }
+            )*
+        }
+    }
+
+    spherical_texture_tests! {
+        using_a_texture_map_with_a_spherical_map_0: (Point::new(0.4315, 0.4670, 0.7719), color::WHITE),
+        using_a_texture_map_with_a_spherical_map_1: (Point::new(-0.9654, 0.2552, -0.0534), color::BLACK),
+        using_a_texture_map_with_a_spherical_map_2: (Point::new(0.1039, 0.7090, 0.6975), color::WHITE),
+        using_a_texture_map_with_a_spherical_map_3: (Point::new(-0.4986, -0.7856, -0.3663), color::BLACK),
+        using_a_texture_map_with_a_spherical_map_4: (Point::new(-0.0317, -0.9395, 0.3411), color::BLACK),
+        using_a_texture_map_with_a_spherical_map_5: (Point::new(0.4809, -0.7721, 0.4154), color::BLACK),
+        using_a_texture_map_with_a_spherical_map_6: (Point::new(0.0285, -0.9612, -0.2745), color::BLACK),
+        using_a_texture_map_with_a_spherical_map_7: (Point::new(-0.5734, -0.2162, -0.7903), color::WHITE),
+        using_a_texture_map_with_a_spherical_map_8: (Point::new(0.7688, -0.1470, 0.6223), color::BLACK),
+        using_a_texture_map_with_a_spherical_map_9: (Point::new(-0.7652, 0.2175, 0.6060), color::BLACK),
+    }
+
+    #[test]
+    fn layout_of_the_align_check_pattern() {
+        let red = Color::new(1.0, 0.0, 0.0);
+        let yellow = Color::new(1.0, 1.0, 0.0);
+        let green = Color::new(0.0, 1.0, 0.0);
+        let cyan = Color::new(0.0, 1.0, 1.0);
+        let pattern = UvAlignCheck::new(color::WHITE, red, yellow, green, cyan);
+
+        assert_eq!(pattern.uv_pattern_at(0.5, 0.5), color::WHITE);
+        assert_eq!(pattern.uv_pattern_at(0.1, 0.9), red);
+        assert_eq!(pattern.uv_pattern_at(0.9, 0.9), yellow);
+        assert_eq!(pattern.uv_pattern_at(0.1, 0.1), green);
+        assert_eq!(pattern.uv_pattern_at(0.9, 0.1), cyan);
+    }
+
+    #[test]
+    fn an_image_pattern_picks_the_nearest_pixel() {
+        let mut image = Canvas::new(3, 2);
+        image.write_pixel(0, 0, Color::new(1.0, 0.0, 0.0));
+        image.write_pixel(2, 1, Color::new(0.0, 0.0, 1.0));
+        let pattern = UvImage::new(image);
+
+        assert_eq!(pattern.uv_pattern_at(0.0, 1.0), Color::new(1.0, 0.0, 0.0));
+        assert_eq!(pattern.uv_pattern_at(0.9, 0.2), Color::new(0.0, 0.0, 1.0));
+        assert_eq!(pattern.uv_pattern_at(0.5, 0.5), color::BLACK);
+    }
+
+    macro_rules! cube_face_tests {
+        ($($name:ident: $value:expr,)*) => {
+            $(
+                #[test]
+                fn $name() {
+                    let (point, face) = $value;
+
+                    assert_eq!(CubeFace::of(point), face);
+                }
+            )*
+        }
+    }
+
+    cube_face_tests! {
+        identifying_the_face_of_a_cube_from_a_point_0: (Point::new(-1.0, 0.5, -0.25), CubeFace::Left),
+        identifying_the_face_of_a_cube_from_a_point_1: (Point::new(1.1, -0.75, 0.8), CubeFace::Right),
+        identifying_the_face_of_a_cube_from_a_point_2: (Point::new(0.1, 0.6, 0.9), CubeFace::Front),
+        identifying_the_face_of_a_cube_from_a_point_3: (Point::new(-0.7, 0.0, -2.0), CubeFace::Back),
+        identifying_the_face_of_a_cube_from_a_point_4: (Point::new(0.5, 1.0, 0.9), CubeFace::Up),
+        identifying_the_face_of_a_cube_from_a_point_5: (Point::new(-0.2, -1.3, 1.1), CubeFace::Down),
+    }
+
+    macro_rules! cube_uv_tests {
+        ($($name:ident: $value:expr,)*) => {
+            $(
+                #[test]
+                fn $name() {
+                    let (point, (u, v)) = $value;
+
+                    let (_, actual_u, actual_v) = cube_uv(point);
+
+                    assert!(float_eq(actual_u, u));
+                    assert!(float_eq(actual_v, v));
+                }
+            )*
+        }
+    }
+
+    cube_uv_tests! {
+        uv_mapping_the_front_face_of_a_cube_0: (Point::new(-0.5, 0.5, 1.0), (0.25, 0.75)),
+        uv_mapping_the_front_face_of_a_cube_1: (Point::new(0.5, -0.5, 1.0), (0.75, 0.25)),
+        uv_mapping_the_back_face_of_a_cube_0: (Point::new(0.5, 0.5, -1.0), (0.25, 0.75)),
+        uv_mapping_the_back_face_of_a_cube_1: (Point::new(-0.5, -0.5, -1.0), (0.75, 0.25)),
+        uv_mapping_the_left_face_of_a_cube_0: (Point::new(-1.0, 0.5, -0.5), (0.25, 0.75)),
+        uv_mapping_the_left_face_of_a_cube_1: (Point::new(-1.0, -0.5, 0.5), (0.75, 0.25)),
+        uv_mapping_the_right_face_of_a_cube_0: (Point::new(1.0, 0.5, 0.5), (0.25, 0.75)),
+        uv_mapping_the_right_face_of_a_cube_1: (Point::new(1.0, -0.5, -0.5), (0.75, 0.25)),
+        uv_mapping_the_upper_face_of_a_cube_0: (Point::new(-0.5, 1.0, -0.5), (0.25, 0.75)),
+        uv_mapping_the_upper_face_of_a_cube_1: (Point::new(0.5, 1.0, 0.5), (0.75, 0.25)),
+        uv_mapping_the_lower_face_of_a_cube_0: (Point::new(-0.5, -1.0, 0.5), (0.25, 0.75)),
+        uv_mapping_the_lower_face_of_a_cube_1: (Point::new(0.5, -1.0, -0.5), (0.75, 0.25)),
+    }
+
+    fn align_check(colors: [Color; 5]) -> UvAlignCheck {
+        UvAlignCheck::new(colors[0], colors[1], colors[2], colors[3], colors[4])
+    }
+
+    const RED: Color = Color::new(1.0, 0.0, 0.0);
+    const YELLOW: Color = Color::new(1.0, 1.0, 0.0);
+    const BROWN: Color = Color::new(1.0, 0.5, 0.0);
+    const GREEN: Color = Color::new(0.0, 1.0, 0.0);
+    const CYAN: Color = Color::new(0.0, 1.0, 1.0);
+    const BLUE: Color = Color::new(0.0, 0.0, 1.0);
+    const PURPLE: Color = Color::new(1.0, 0.0, 1.0);
+    const WHITE: Color = Color::new(1.0, 1.0, 1.0);
+
+    fn align_check_cube() -> CubeMap {
+        CubeMap::new(
+            align_check([YELLOW, CYAN, RED, BLUE, BROWN]),
+            align_check([RED, YELLOW, PURPLE, GREEN, WHITE]),
+            align_check([CYAN, RED, YELLOW, BROWN, GREEN]),
+            align_check([GREEN, PURPLE, CYAN, WHITE, BLUE]),
+            align_check([BROWN, CYAN, PURPLE, RED, YELLOW]),
+            align_check([PURPLE, BROWN, GREEN, BLUE, WHITE]),
+        )
+    }
+
+    macro_rules! cube_map_tests {
+        ($($name:ident: $value:expr,)*) => {
+            $(
+                #[test]
+                fn $name() {
+                    let (point, expected) = $value;
+
+                    assert_eq!(align_check_cube().pattern_at(point), expected);
+                }
+            )*
+        }
+    }
+
+    cube_map_tests! {
+        finding_the_colors_on_a_mapped_cube_left_0: (Point::new(-1.0, 0.0, 0.0), YELLOW),
+        finding_the_colors_on_a_mapped_cube_left_1: (Point::new(-1.0, 0.9, -0.9), CYAN),
+        finding_the_colors_on_a_mapped_cube_left_2: (Point::new(-1.0, 0.9, 0.9), RED),
+        finding_the_colors_on_a_mapped_cube_left_3: (Point::new(-1.0, -0.9, -0.9), BLUE),
+        finding_the_colors_on_a_mapped_cube_left_4: (Point::new(-1.0, -0.9, 0.9), BROWN),
+        finding_the_colors_on_a_mapped_cube_front_0: (Point::new(0.0, 0.0, 1.0), CYAN),
+        finding_the_colors_on_a_mapped_cube_front_1: (Point::new(-0.9, 0.9, 1.0), RED),
+        finding_the_colors_on_a_mapped_cube_front_2: (Point::new(0.9, 0.9, 1.0), YELLOW),
+        finding_the_colors_on_a_mapped_cube_front_3: (Point::new(-0.9, -0.9, 1.0), BROWN),
+        finding_the_colors_on_a_mapped_cube_front_4: (Point::new(0.9, -0.9, 1.0), GREEN),
+        finding_the_colors_on_a_mapped_cube_right_0: (Point::new(1.0, 0.0, 0.0), RED),
+        finding_the_colors_on_a_mapped_cube_right_1: (Point::new(1.0, 0.9, 0.9), YELLOW),
+        finding_the_colors_on_a_mapped_cube_right_2: (Point::new(1.0, 0.9, -0.9), PURPLE),
+        finding_the_colors_on_a_mapped_cube_right_3: (Point::new(1.0, -0.9, 0.9), GREEN),
+        finding_the_colors_on_a_mapped_cube_right_4: (Point::new(1.0, -0.9, -0.9), WHITE),
+        finding_the_colors_on_a_mapped_cube_back_0: (Point::new(0.0, 0.0, -1.0), GREEN),
+        finding_the_colors_on_a_mapped_cube_back_1: (Point::new(0.9, 0.9, -1.0), PURPLE),
+        finding_the_colors_on_a_mapped_cube_back_2: (Point::new(-0.9, 0.9, -1.0), CYAN),
+        finding_the_colors_on_a_mapped_cube_back_3: (Point::new(0.9, -0.9, -1.0), WHITE),
+        finding_the_colors_on_a_mapped_cube_back_4: (Point::new(-0.9, -0.9, -1.0), BLUE),
+        finding_the_colors_on_a_mapped_cube_up_0: (Point::new(0.0, 1.0, 0.0), BROWN),
+        finding_the_colors_on_a_mapped_cube_up_1: (Point::new(-0.9, 1.0, -0.9), CYAN),
+        finding_the_colors_on_a_mapped_cube_up_2: (Point::new(0.9, 1.0, -0.9), PURPLE),
+        finding_the_colors_on_a_mapped_cube_up_3: (Point::new(-0.9, 1.0, 0.9), RED),
+        finding_the_colors_on_a_mapped_cube_up_4: (Point::new(0.9, 1.0, 0.9), YELLOW),
+        finding_the_colors_on_a_mapped_cube_down_0: (Point::new(0.0, -1.0, 0.0), PURPLE),
+        finding_the_colors_on_a_mapped_cube_down_1: (Point::new(-0.9, -1.0, 0.9), BROWN),
+        finding_the_colors_on_a_mapped_cube_down_2: (Point::new(0.9, -1.0, 0.9), GREEN),
+        finding_the_colors_on_a_mapped_cube_down_3: (Point::new(-0.9, -1.0, -0.9), BLUE),
+        finding_the_colors_on_a_mapped_cube_down_4: (Point::new(0.9, -1.0, -0.9), WHITE),
+    }
+
+    #[test]
+    fn a_cube_map_follows_a_transformed_cube() {
+        let cube = Cube::default().with_transform(
+            Transform::translation(0.0, 5.0, 0.0) * Transform::scaling(2.0, 2.0, 2.0),
+        );
+        let pattern = align_check_cube();
+
+        assert_eq!(
+            pattern.pattern_at_shape(&cube, Point::new(-1.8, 6.8, 2.0)),
+            RED
+        );
+        assert_eq!(
+            pattern.pattern_at_shape(&cube, Point::new(0.0, 7.0, 0.0)),
+            BROWN
+        );
+    }
+
+    #[test]
+    fn an_align_check_wrapped_around_a_cylinder() {
+        let pattern = TextureMap::new(
+            align_check([WHITE, RED, YELLOW, GREEN, CYAN]),
+            UvMapping::Cylindrical,
+        );
+
+        assert_eq!(pattern.pattern_at(Point::new(0.0, 0.5, -1.0)), WHITE);
+        assert_eq!(pattern.pattern_at(Point::new(-0.1, 0.9, -1.0)), YELLOW);
+        assert_eq!(pattern.pattern_at(Point::new(0.1, 0.9, -1.0)), RED);
+        assert_eq!(pattern.pattern_at(Point::new(-0.1, 0.1, -1.0)), CYAN);
+        assert_eq!(pattern.pattern_at(Point::new(0.1, 0.1, -1.0)), GREEN);
+    }
+
+    #[test]
+    fn a_label_covers_only_its_part_of_texture_space() {
+        let label = UvLabel::new(
+            align_check([WHITE, RED, YELLOW, GREEN, CYAN]),
+            BLUE,
+            (0.25, 0.5),
+            (0.75, 1.0),
+        );
+
+        assert_eq!(label.uv_pattern_at(0.1, 0.75), BLUE);
+        assert_eq!(label.uv_pattern_at(0.5, 0.25), BLUE);
+        assert_eq!(label.uv_pattern_at(0.5, 0.75), WHITE);
+        assert_eq!(label.uv_pattern_at(0.3, 0.95), RED);
+        assert_eq!(label.uv_pattern_at(0.7, 0.55), CYAN);
+    }
+
+    #[test]
+    fn a_label_around_a_cup() {
+        let pattern = TextureMap::new(
+            UvLabel::new(
+                UvChecker::new(2.0, 2.0, RED, YELLOW),
+                WHITE,
+                (0.0, 0.2),
+                (1.0, 0.8),
+            ),
+            UvMapping::Conical,
+        );
+
+        assert_eq!(pattern.pattern_at(Point::new(0.0, 0.1, -0.1)), WHITE);
+        assert_eq!(pattern.pattern_at(Point::new(0.2, 0.2, 0.0)), RED);
+        assert_eq!(pattern.pattern_at(Point::new(-0.3, 0.3, 0.0)), YELLOW);
+        assert_eq!(pattern.pattern_at(Point::new(0.0, 0.6, -0.6)), WHITE);
+    }
+
+    #[test]
+    fn one_pattern_on_every_face_of_a_cube() {
+        let pattern = TextureMap::new(align_check([WHITE, RED, RED, RED, RED]), UvMapping::Cube);
+
+        assert_eq!(pattern.pattern_at(Point::new(-1.0, 0.9, 0.9)), RED);
+        assert_eq!(pattern.pattern_at(Point::new(0.9, 1.0, -0.9)), RED);
+        assert_eq!(pattern.pattern_at(Point::new(0.0, 0.0, -1.0)), WHITE);
+    }
+
+    #[test]
+    fn planar_checkers_do_not_speckle_either_side_of_a_plane() {
+        let plane = Plane::default();
+        let pattern = TextureMap::new(UvChecker::default(), UvMapping::Planar);
+
+        for &y in &[-0.00001, 0.0, 0.00001] {
+            assert_eq!(
+                pattern.pattern_at_shape(&plane, Point::new(0.25, y, 0.25)),
+                color::WHITE
+            );
+            assert_eq!(
+                pattern.pattern_at_shape(&plane, Point::new(0.75, y, 0.25)),
+                color::BLACK
+            );
+        }
+    }
+}
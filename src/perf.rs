@@ -0,0 +1,209 @@
+//! Throughput gate for the renderer, measured in camera rays per second
+//! over the fastest of a few renders of a fixed scene. Run it with
+//! `cargo test --release --features serde -- --ignored perf`. Each run is
+//! added to a history of this machine's results, which stays out of the
+//! source tree since the numbers mean nothing on another machine.
+//!
+//! * `PERF_HISTORY` - history file, defaults to `target/perf/history.json`
+//! * `PERF_MAX_REGRESSION` - allowed slowdown in percent, defaults to 10
+//! * `PERF_UPDATE_BASELINE` - set to make this run the new baseline
+
+use crate::{
+    camera::Camera,
+    color::{self, Color},
+    lights::PointLight,
+    materials::Material,
+    shapes::{cone::Cone, cylinder::Cylinder, plane::Plane, sphere::Sphere, ShapeBuilder},
+    transformations::Transform,
+    tuple::{Point, Vector},
+    world::World,
+};
+
+const HSIZE: usize = 400;
+const VSIZE: usize = 200;
+const RENDERS: u32 = 3;
+
+/// Percentage by which `current` is slower than `baseline`.
+fn regression(baseline: f64, current: f64) -> f64 {
+    (baseline - current) / baseline * 100.0
+}
+
+/// The demo scene: a reflective floor, a glass sphere, and capped
+/// cylinder and cone, lit by a single point light.
+pub fn benchmark_scene() -> (World, Camera) {
+    let floor = Plane::default().with_material(
+        Material::default()
+            .color(Color::new(1.0, 0.9, 0.9))
+            .specular(0.0)
+            .reflective(0.8),
+    );
+
+    let middle = Sphere::default()
+        .with_transform(Transform::translation(-0.5, 1.0, 0.5))
+        .with_material(
+            Material::default()
+                .color(Color::new(0.1, 1.0, 0.5))
+                .diffuse(0.2)
+                .specular(0.3)
+                .reflective(0.9)
+                .transparency(0.9),
+        );
+
+    let right = Cone::default()
+        .with_transform(Transform::translation(1.5, 0.0, -0.5) * Transform::scaling(0.5, 0.5, 0.5))
+        .with_material(
+            Material::default()
+                .color(Color::new(0.5, 1.0, 0.1))
+                .diffuse(0.7)
+                .specular(0.3)
+                .reflective(0.2),
+        )
+        .with_caps(0.0, 1.5);
+
+    let left = Cylinder::default()
+        .with_transform(
+            Transform::translation(-1.5, 0.33, -0.75) * Transform::scaling(0.33, 0.33, 0.33),
+        )
+        .with_material(
+            Material::default()
+                .color(Color::new(1.0, 0.8, 0.1))
+                .diffuse(0.7)
+                .specular(0.3),
+        )
+        .with_caps(-1.0, 3.0);
+
+    let world = World::new(PointLight::new(
+        Point::new(-10.0, 10.0, -10.0),
+        color::WHITE,
+    ))
+    .object(Box::new(floor))
+    .object(Box::new(left))
+    .object(Box::new(middle))
+    .object(Box::new(right));
+
+    let camera =
+        Camera::new(HSIZE, VSIZE, std::f32::consts::PI / 3.0).transform(Transform::view_transform(
+            Point::new(0.0, 1.5, -5.0),
+            Point::new(0.0, 1.0, 0.0),
+            Vector::new(0.0, 1.0, 0.0),
+        ));
+
+    (world, camera)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[cfg(feature = "serde")]
+    mod history {
+        use std::{
+            env, fs,
+            path::Path,
+            time::{Instant, SystemTime, UNIX_EPOCH},
+        };
+
+        use serde::{Deserialize, Serialize};
+
+        use super::super::*;
+
+        #[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+        struct History {
+            baseline: Option<f64>,
+            runs: Vec<Run>,
+        }
+
+        #[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
+        struct Run {
+            unix_time: u64,
+            rays_per_second: f64,
+        }
+
+        #[test]
+        #[ignore]
+        fn perf_rays_per_second_does_not_regress() {
+            let history_path =
+                env::var("PERF_HISTORY").unwrap_or_else(|_| "target/perf/history.json".into());
+            let max_regression = env::var("PERF_MAX_REGRESSION")
+                .ok()
+                .and_then(|v| v.parse().ok())
+                .unwrap_or(10.0);
+
+            let (world, camera) = benchmark_scene();
+            let fastest = (0..RENDERS)
+                .map(|_| {
+                    let start = Instant::now();
+                    camera.render(&world);
+                    start.elapsed().as_secs_f64()
+                })
+                .fold(f64::MAX, f64::min);
+            let rays_per_second = (HSIZE * VSIZE) as f64 / fastest;
+
+            let mut history: History = fs::read_to_string(&history_path)
+                .map(|json| serde_json::from_str(&json).unwrap())
+                .unwrap_or_default();
+            let baseline = match history.baseline {
+                Some(baseline) if env::var("PERF_UPDATE_BASELINE").is_err() => baseline,
+                _ => rays_per_second,
+            };
+            history.baseline = Some(baseline);
+            history.runs.push(Run {
+                unix_time: SystemTime::now()
+                    .duration_since(UNIX_EPOCH)
+                    .map_or(0, |d| d.as_secs()),
+                rays_per_second,
+            });
+            if let Some(dir) = Path::new(&history_path).parent() {
+                fs::create_dir_all(dir).unwrap();
+            }
+            fs::write(
+                &history_path,
+                serde_json::to_string_pretty(&history).unwrap(),
+            )
+            .unwrap();
+
+            let slowdown = regression(baseline, rays_per_second);
+            assert!(
+                slowdown <= max_regression,
+                "{:.0} rays/s is {:.1}% slower than the baseline of {:.0} rays/s",
+                rays_per_second,
+                slowdown,
+                baseline
+            );
+        }
+
+        #[test]
+        fn history_round_trips_through_json() {
+            let history = History {
+                baseline: Some(1234.5),
+                runs: vec![
+                    Run {
+                        unix_time: 1_600_000_000,
+                        rays_per_second: 1234.5,
+                    },
+                    Run {
+                        unix_time: 1_600_000_100,
+                        rays_per_second: 1100.0,
+                    },
+                ],
+            };
+            let json = serde_json::to_string_pretty(&history).unwrap();
+
+            assert_eq!(serde_json::from_str::<History>(&json).unwrap(), history);
+        }
+
+        #[test]
+        fn an_empty_history_has_no_baseline() {
+            let history: History =
+                serde_json::from_str(r#"{ "baseline": null, "runs": [] }"#).unwrap();
+
+            assert_eq!(history, History::default());
+        }
+    }
+
+    #[test]
+    fn regression_is_the_percentage_slowdown() {
+        assert!((regression(1000.0, 900.0) - 10.0).abs() < 1e-9);
+        assert!(regression(1000.0, 1100.0) < 0.0);
+    }
+}
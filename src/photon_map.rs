@@ -0,0 +1,210 @@
+use std::{cmp::Ordering, f32::consts::PI};
+
+use crate::{
+    color::{self, Color},
+    tuple::{Point, Vector},
+};
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Photon {
+    pub position: Point,
+    pub power: Color,
+}
+
+impl Photon {
+    pub fn new(position: Point, power: Color) -> Self {
+        Self { position, power }
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct PhotonSettings {
+    pub photons: usize,
+    pub radius: f32,
+    pub max_bounces: u32,
+}
+
+impl Default for PhotonSettings {
+    fn default() -> Self {
+        Self {
+            photons: 100_000,
+            radius: 0.1,
+            max_bounces: 5,
+        }
+    }
+}
+
+/// Photons stored as an implicit kd-tree: the median of every slice is the
+/// splitting node, and the split axis cycles through x, y and z with depth.
+#[derive(Clone, Debug, PartialEq)]
+pub struct PhotonMap {
+    photons: Vec<Photon>,
+    radius: f32,
+}
+
+impl PhotonMap {
+    pub fn new(mut photons: Vec<Photon>, radius: f32) -> Self {
+        build(&mut photons, 0);
+        Self { photons, radius }
+    }
+
+    pub fn len(&self) -> usize {
+        self.photons.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.photons.is_empty()
+    }
+
+    pub fn photons_within(&self, point: Point, radius: f32) -> Vec<&Photon> {
+        let mut found = vec![];
+        gather(&self.photons, 0, point, radius, &mut found);
+        found
+    }
+
+    pub fn irradiance_at(&self, point: Point) -> Color {
+        let area = PI * self.radius.powi(2);
+        self.photons_within(point, self.radius)
+            .iter()
+            .fold(color::BLACK, |sum, photon| sum + photon.power)
+            * (1.0 / area)
+    }
+}
+
+/// Evenly spreads `count` directions over the unit sphere (a Fibonacci
+/// lattice), so photon emission needs no random numbers.
+pub fn emission_direction(i: usize, count: usize) -> Vector {
+    let golden_angle = PI * (3.0 - 5_f32.sqrt());
+    let y = 1.0 - 2.0 * (i as f32 + 0.5) / count as f32;
+    let radius = (1.0 - y * y).sqrt();
+    let phi = golden_angle * i as f32;
+
+    Vector::new(phi.cos() * radius, y, phi.sin() * radius)
+}
+
+fn component(point: Point, axis: usize) -> f32 {
+    match axis {
+        0 => point.x(),
+        1 => point.y(),
+        _ => point.z(),
+    }
+}
+
+fn build(photons: &mut [Photon], depth: usize) {
+    if photons.len() <= 1 {
+        return;
+    }
+
+    let axis = depth % 3;
+    photons.sort_by(|a, b| {
+        component(a.position, axis)
+            .partial_cmp(&component(b.position, axis))
+            .unwrap_or(Ordering::Equal)
+    });
+
+    let mid = photons.len() / 2;
+    let (left, right) = photons.split_at_mut(mid);
+    build(left, depth + 1);
+    build(&mut right[1..], depth + 1);
+}
+
+fn gather<'a>(
+    photons: &'a [Photon],
+    depth: usize,
+    point: Point,
+    radius: f32,
+    found: &mut Vec<&'a Photon>,
+) {
+    if photons.is_empty() {
+        return;
+    }
+
+    let mid = photons.len() / 2;
+    let photon = &photons[mid];
+    if (photon.position - point).magnitude() <= radius {
+        found.push(photon);
+    }
+
+    let axis = depth % 3;
+    let delta = component(point, axis) - component(photon.position, axis);
+    if delta - radius <= 0.0 {
+        gather(&photons[..mid], depth + 1, point, radius, found);
+    }
+    if delta + radius >= 0.0 {
+        gather(&photons[mid + 1..], depth + 1, point, radius, found);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::float_eq;
+
+    use super::*;
+
+    fn grid_of_photons() -> Vec<Photon> {
+        let mut photons = vec![];
+        for x in -5..=5 {
+            for z in -5..=5 {
+                photons.push(Photon::new(
+                    Point::new(x as f32, 0.0, z as f32),
+                    Color::new(1.0, 1.0, 1.0),
+                ));
+            }
+        }
+        photons
+    }
+
+    #[test]
+    fn an_empty_photon_map_has_no_irradiance() {
+        let map = PhotonMap::new(vec![], 0.5);
+
+        assert!(map.is_empty());
+        assert_eq!(map.irradiance_at(Point::new(0.0, 0.0, 0.0)), color::BLACK);
+    }
+
+    #[test]
+    fn finding_photons_within_a_radius() {
+        let map = PhotonMap::new(grid_of_photons(), 0.5);
+
+        let found = map.photons_within(Point::new(0.0, 0.0, 0.0), 1.0);
+
+        assert_eq!(found.len(), 5);
+    }
+
+    #[test]
+    fn the_kd_tree_agrees_with_a_linear_search() {
+        let photons = grid_of_photons();
+        let map = PhotonMap::new(photons.clone(), 0.5);
+        let point = Point::new(1.3, 0.2, -2.6);
+
+        let expected = photons
+            .iter()
+            .filter(|p| (p.position - point).magnitude() <= 2.5)
+            .count();
+
+        assert_eq!(map.photons_within(point, 2.5).len(), expected);
+    }
+
+    #[test]
+    fn irradiance_is_photon_power_over_the_gather_area() {
+        let photons = vec![
+            Photon::new(Point::new(0.0, 0.0, 0.0), Color::new(1.0, 0.5, 0.0)),
+            Photon::new(Point::new(0.1, 0.0, 0.0), Color::new(1.0, 0.5, 0.0)),
+            Photon::new(Point::new(3.0, 0.0, 0.0), Color::new(1.0, 0.5, 0.0)),
+        ];
+        let map = PhotonMap::new(photons, 1.0);
+
+        let e = map.irradiance_at(Point::new(0.0, 0.0, 0.0));
+
+        assert_eq!(e, Color::new(2.0 / PI, 1.0 / PI, 0.0));
+    }
+
+    #[test]
+    fn emission_directions_are_unit_vectors() {
+        for i in 0..100 {
+            let d = emission_direction(i, 100);
+
+            assert!(float_eq(d.magnitude(), 1.0));
+        }
+    }
+}
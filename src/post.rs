@@ -0,0 +1,487 @@
+use std::f32::consts::PI;
+
+use crate::{
+    camera::RenderOutput,
+    canvas::Canvas,
+    color::{self, Color},
+};
+
+/// An edge-preserving blur for noisy renders. It's a cross bilateral
+/// filter: each pixel becomes a weighted average of its neighbours, with
+/// neighbours counting for less the further away they are, the more their
+/// color differs, and the more their normal or depth says they lie on a
+/// different surface. Noise within a surface is smoothed out while edges
+/// between surfaces stay sharp.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Denoiser {
+    /// How different two colors can be and still be averaged. Zero turns
+    /// the filter off.
+    pub strength: f32,
+    pub radius: usize,
+    pub normal_sigma: f32,
+    /// Tolerance for depth differences, relative to the depth itself.
+    pub depth_sigma: f32,
+}
+
+impl Denoiser {
+    pub fn new(strength: f32) -> Self {
+        Self {
+            strength,
+            ..Self::default()
+        }
+    }
+
+    pub fn radius(self, radius: usize) -> Self {
+        Self { radius, ..self }
+    }
+
+    pub fn normal_sigma(self, normal_sigma: f32) -> Self {
+        Self {
+            normal_sigma,
+            ..self
+        }
+    }
+
+    pub fn depth_sigma(self, depth_sigma: f32) -> Self {
+        Self {
+            depth_sigma,
+            ..self
+        }
+    }
+
+    pub fn apply(&self, output: &RenderOutput) -> Canvas {
+        let image = &output.color;
+        if self.strength <= 0.0 {
+            return image.clone();
+        }
+
+        let mut result = Canvas::new(image.width, image.height);
+        for y in 0..image.height {
+            for x in 0..image.width {
+                result.write_pixel(x, y, self.filter_pixel(output, x, y));
+            }
+        }
+        result
+    }
+
+    fn filter_pixel(&self, output: &RenderOutput, x: usize, y: usize) -> Color {
+        let image = &output.color;
+        let center = image.pixel_at(x, y);
+        let normal = output.normal.pixel_at(x, y);
+        let depth = output.depth.pixel_at(x, y).red();
+        let spatial_sigma = (self.radius as f32 / 2.0).max(0.5);
+
+        let mut total = Color::new(0.0, 0.0, 0.0);
+        let mut total_weight = 0.0;
+        for ny in y.saturating_sub(self.radius)..(y + self.radius + 1).min(image.height) {
+            for nx in x.saturating_sub(self.radius)..(x + self.radius + 1).min(image.width) {
+                let color = image.pixel_at(nx, ny);
+                let distance_squared =
+                    (nx as f32 - x as f32).powi(2) + (ny as f32 - y as f32).powi(2);
+                let weight = gaussian(distance_squared, spatial_sigma)
+                    * gaussian(difference_squared(center, color), self.strength)
+                    * gaussian(
+                        difference_squared(normal, output.normal.pixel_at(nx, ny)),
+                        self.normal_sigma,
+                    )
+                    * gaussian(
+                        relative_difference(depth, output.depth.pixel_at(nx, ny).red()).powi(2),
+                        self.depth_sigma,
+                    );
+
+                total = total + color * weight;
+                total_weight += weight;
+            }
+        }
+
+        total * (1.0 / total_weight)
+    }
+}
+
+impl Default for Denoiser {
+    fn default() -> Self {
+        Self {
+            strength: 0.1,
+            radius: 2,
+            normal_sigma: 0.1,
+            depth_sigma: 0.05,
+        }
+    }
+}
+
+/// A glow around the brightest parts of an image, as a camera lens
+/// scatters strong light onto the pixels around it. Whatever is brighter
+/// than `threshold` is blurred and added back over the image, optionally
+/// along with star-shaped streaks, so highlights and lamps bleed past their
+/// own pixels instead of stopping flat at their edges. Meant for renders
+/// whose colors haven't been clamped, where highlights go above one.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Bloom {
+    /// How much of the glow is added back. Zero turns bloom off.
+    pub intensity: f32,
+    /// The luminance light must pass to glow. Only the excess glows, so
+    /// the glow fades in smoothly as pixels brighten.
+    pub threshold: f32,
+    /// The spread of the glow's blur, in pixels.
+    pub radius: f32,
+    /// How many streaks radiate from each bright pixel, evenly spaced and
+    /// starting from horizontal: 4 gives a cross, 6 a snowflake, 0 none.
+    pub streaks: usize,
+    /// How far streaks reach, in pixels.
+    pub streak_length: usize,
+}
+
+impl Bloom {
+    pub fn new(intensity: f32) -> Self {
+        Self {
+            intensity,
+            ..Self::default()
+        }
+    }
+
+    pub fn threshold(self, threshold: f32) -> Self {
+        Self { threshold, ..self }
+    }
+
+    pub fn radius(self, radius: f32) -> Self {
+        Self { radius, ..self }
+    }
+
+    pub fn streaks(self, streaks: usize, streak_length: usize) -> Self {
+        Self {
+            streaks,
+            streak_length,
+            ..self
+        }
+    }
+
+    pub fn apply(&self, image: &Canvas) -> Canvas {
+        let mut result = image.clone();
+        if self.intensity <= 0.0 {
+            return result;
+        }
+
+        let bright = self.bright_part(image);
+        result.accumulate(&blur(&bright, self.radius), self.intensity);
+        if self.streaks > 0 && self.streak_length > 0 {
+            result.accumulate(&self.streak(&bright), self.intensity);
+        }
+        result
+    }
+
+    /// The light above the threshold, keeping each pixel's hue.
+    fn bright_part(&self, image: &Canvas) -> Canvas {
+        let mut bright = Canvas::new(image.width, image.height);
+        for y in 0..image.height {
+            for x in 0..image.width {
+                let color = image.pixel_at(x, y);
+                let luminance = color.luminance();
+                if luminance > self.threshold {
+                    bright.write_pixel(x, y, color * ((luminance - self.threshold) / luminance));
+                }
+            }
+        }
+        bright
+    }
+
+    /// `bright` smeared outwards along each streak, dimming towards the
+    /// end. Together the streaks carry as much light as the glow does.
+    fn streak(&self, bright: &Canvas) -> Canvas {
+        let weights = (1..=self.streak_length)
+            .map(|step| 1.0 - (step - 1) as f32 / self.streak_length as f32)
+            .collect::<Vec<_>>();
+        let scale = 1.0 / (weights.iter().sum::<f32>() * self.streaks as f32);
+
+        let mut streaks = Canvas::new(bright.width, bright.height);
+        for y in 0..bright.height {
+            for x in 0..bright.width {
+                let source = bright.pixel_at(x, y);
+                if source == color::BLACK {
+                    continue;
+                }
+                for i in 0..self.streaks {
+                    let angle = i as f32 * 2.0 * PI / self.streaks as f32;
+                    let (dx, dy) = (angle.cos(), angle.sin());
+                    for (step, weight) in weights.iter().enumerate() {
+                        let distance = (step + 1) as f32;
+                        let sx = (x as f32 + dx * distance).round();
+                        let sy = (y as f32 + dy * distance).round();
+                        if sx < 0.0 || sy < 0.0 {
+                            break;
+                        }
+                        let (sx, sy) = (sx as usize, sy as usize);
+                        if sx >= bright.width || sy >= bright.height {
+                            break;
+                        }
+                        let lit = streaks.pixel_at(sx, sy);
+                        streaks.write_pixel(sx, sy, lit + source * (weight * scale));
+                    }
+                }
+            }
+        }
+        streaks
+    }
+}
+
+impl Default for Bloom {
+    fn default() -> Self {
+        Self {
+            intensity: 0.5,
+            threshold: 1.0,
+            radius: 4.0,
+            streaks: 0,
+            streak_length: 16,
+        }
+    }
+}
+
+/// A Gaussian blur with a spread of `sigma` pixels, done across and then
+/// down. Pixels past the edges repeat the edge ones.
+fn blur(image: &Canvas, sigma: f32) -> Canvas {
+    let reach = (sigma * 3.0).ceil() as isize;
+    let weights = (-reach..=reach)
+        .map(|offset| gaussian((offset * offset) as f32, sigma))
+        .collect::<Vec<_>>();
+    let total = weights.iter().sum::<f32>();
+
+    let pass = |image: &Canvas, across: bool| {
+        let mut result = Canvas::new(image.width, image.height);
+        for y in 0..image.height {
+            for x in 0..image.width {
+                let mut sum = color::BLACK;
+                for (offset, &weight) in (-reach..=reach).zip(weights.iter()) {
+                    let pixel = if across {
+                        let nx = (x as isize + offset).clamp(0, image.width as isize - 1);
+                        image.pixel_at(nx as usize, y)
+                    } else {
+                        let ny = (y as isize + offset).clamp(0, image.height as isize - 1);
+                        image.pixel_at(x, ny as usize)
+                    };
+                    sum = sum + pixel * weight;
+                }
+                result.write_pixel(x, y, sum * (1.0 / total));
+            }
+        }
+        result
+    };
+    pass(&pass(image, true), false)
+}
+
+/// A bell curve falling away from one at no distance. A zero `sigma` is
+/// taken as a tiny one, so only identical values carry any weight.
+fn gaussian(distance_squared: f32, sigma: f32) -> f32 {
+    let sigma = sigma.max(f32::EPSILON);
+    (-distance_squared / (2.0 * sigma * sigma)).exp()
+}
+
+/// Mean squared difference over the three channels.
+fn difference_squared(a: Color, b: Color) -> f32 {
+    let d = a - b;
+    (d.red() * d.red() + d.green() * d.green() + d.blue() * d.blue()) / 3.0
+}
+
+/// 0 for equal depths, up to 1 for wildly different ones or where only one
+/// of the two pixels hit anything.
+fn relative_difference(a: f32, b: f32) -> f32 {
+    if a == b {
+        0.0
+    } else if a.is_infinite() || b.is_infinite() {
+        1.0
+    } else {
+        (a - b).abs() / a.abs().max(b.abs())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{color, float_eq};
+
+    use super::*;
+
+    /// A flat grey surface facing the camera, speckled with noise.
+    fn noisy_wall(width: usize, height: usize) -> RenderOutput {
+        let mut output = RenderOutput {
+            color: Canvas::new(width, height),
+            depth: Canvas::new(width, height),
+            normal: Canvas::new(width, height),
+            albedo: Canvas::new(width, height),
+            object_ids: vec![None; width * height],
+        };
+        for y in 0..height {
+            for x in 0..width {
+                let grey = if (x + y) % 2 == 0 { 0.45 } else { 0.55 };
+                output.color.write_pixel(x, y, Color::new(grey, grey, grey));
+                output.depth.write_pixel(x, y, Color::new(5.0, 5.0, 5.0));
+                output.normal.write_pixel(x, y, Color::new(0.0, 0.0, -1.0));
+            }
+        }
+        output
+    }
+
+    fn spread(canvas: &Canvas) -> f32 {
+        let reds = (0..canvas.height)
+            .flat_map(|y| (0..canvas.width).map(move |x| (x, y)))
+            .map(|(x, y)| canvas.pixel_at(x, y).red())
+            .collect::<Vec<_>>();
+        let max = reds.iter().cloned().fold(f32::NEG_INFINITY, f32::max);
+        let min = reds.iter().cloned().fold(f32::INFINITY, f32::min);
+        max - min
+    }
+
+    #[test]
+    fn zero_strength_leaves_the_image_alone() {
+        let output = noisy_wall(6, 6);
+
+        assert_eq!(Denoiser::new(0.0).apply(&output), output.color);
+    }
+
+    #[test]
+    fn zero_sigmas_only_blend_matching_pixels() {
+        let output = noisy_wall(6, 6);
+
+        let denoised = Denoiser::default()
+            .normal_sigma(0.0)
+            .depth_sigma(0.0)
+            .apply(&output);
+
+        assert!(spread(&denoised) < spread(&output.color));
+        assert!(!denoised.pixel_at(3, 3).red().is_nan());
+    }
+
+    #[test]
+    fn noise_on_a_flat_surface_is_smoothed_out() {
+        let output = noisy_wall(8, 8);
+
+        let denoised = Denoiser::default().apply(&output);
+
+        assert!(spread(&denoised) < spread(&output.color) / 2.0);
+        assert!((denoised.pixel_at(4, 4).red() - 0.5).abs() < 0.02);
+    }
+
+    #[test]
+    fn edges_between_surfaces_are_kept() {
+        let mut output = noisy_wall(8, 8);
+        for y in 0..8 {
+            for x in 4..8 {
+                output.color.write_pixel(x, y, color::WHITE);
+                output.normal.write_pixel(x, y, Color::new(1.0, 0.0, 0.0));
+            }
+        }
+        let denoiser = Denoiser::default().radius(3);
+
+        let denoised = denoiser.apply(&output);
+
+        assert!(denoised.pixel_at(3, 4).red() < 0.6);
+        assert_eq!(denoised.pixel_at(4, 4), color::WHITE);
+    }
+
+    #[test]
+    fn the_background_is_not_blended_into_surfaces() {
+        let mut output = noisy_wall(8, 8);
+        for y in 0..8 {
+            output.color.write_pixel(0, y, color::WHITE);
+            output.depth.write_pixel(
+                0,
+                y,
+                Color::new(f32::INFINITY, f32::INFINITY, f32::INFINITY),
+            );
+        }
+        let denoiser = Denoiser::new(10.0);
+
+        let denoised = denoiser.apply(&output);
+
+        assert_eq!(denoised.pixel_at(0, 3), color::WHITE);
+        assert!(denoised.pixel_at(1, 3).red() < 0.6);
+    }
+
+    /// A dark 21 by 21 image with one pixel of the given brightness in the
+    /// middle.
+    fn lamp(brightness: f32) -> Canvas {
+        let mut image = Canvas::new(21, 21);
+        for y in 0..21 {
+            for x in 0..21 {
+                image.write_pixel(x, y, Color::new(0.1, 0.1, 0.1));
+            }
+        }
+        image.write_pixel(10, 10, Color::new(brightness, brightness, brightness));
+        image
+    }
+
+    #[test]
+    fn zero_intensity_bloom_leaves_the_image_alone() {
+        let image = lamp(20.0);
+
+        assert_eq!(Bloom::new(0.0).apply(&image), image);
+    }
+
+    #[test]
+    fn nothing_below_the_threshold_glows() {
+        let image = lamp(0.9);
+
+        assert_eq!(Bloom::new(1.0).apply(&image), image);
+    }
+
+    #[test]
+    fn only_light_above_the_threshold_glows() {
+        let bloom = Bloom::default().threshold(1.0);
+        let mut image = Canvas::new(1, 1);
+        image.write_pixel(0, 0, Color::new(3.0, 3.0, 3.0));
+
+        assert_eq!(
+            bloom.bright_part(&image).pixel_at(0, 0),
+            Color::new(2.0, 2.0, 2.0)
+        );
+    }
+
+    #[test]
+    fn a_bright_pixel_glows_onto_its_neighbours() {
+        let image = lamp(20.0);
+
+        let bloomed = Bloom::new(1.0).radius(2.0).apply(&image);
+
+        let near = bloomed.pixel_at(11, 10).red();
+        let far = bloomed.pixel_at(14, 10).red();
+        assert!(near > far && far > 0.1);
+        assert!(float_eq(bloomed.pixel_at(0, 0).red(), 0.1));
+        assert!(bloomed.pixel_at(10, 10).red() > 20.0);
+        assert!(float_eq(near, bloomed.pixel_at(10, 9).red()));
+        assert!(float_eq(near, bloomed.pixel_at(9, 10).red()));
+    }
+
+    #[test]
+    fn the_glow_carries_the_light_above_the_threshold() {
+        let image = lamp(20.0);
+
+        let bloomed = Bloom::new(1.0).radius(2.0).apply(&image);
+
+        let added = (0..21)
+            .flat_map(|y| (0..21).map(move |x| (x, y)))
+            .map(|(x, y)| bloomed.pixel_at(x, y).red() - image.pixel_at(x, y).red())
+            .sum::<f32>();
+        assert!((added - 19.0).abs() < 0.01);
+    }
+
+    #[test]
+    fn streaks_reach_past_the_glow_in_their_directions() {
+        let image = lamp(20.0);
+        let bloom = Bloom::new(1.0).radius(0.5).streaks(4, 8);
+
+        let bloomed = bloom.apply(&image);
+
+        assert!(bloomed.pixel_at(16, 10).red() > 0.2);
+        assert!(bloomed.pixel_at(10, 4).red() > 0.2);
+        assert!(float_eq(bloomed.pixel_at(16, 16).red(), 0.1));
+        assert!(float_eq(bloomed.pixel_at(20, 10).red(), 0.1));
+    }
+
+    #[test]
+    fn relative_depth_differences() {
+        assert!(float_eq(relative_difference(4.0, 4.0), 0.0));
+        assert!(float_eq(relative_difference(4.0, 5.0), 0.2));
+        assert!(float_eq(relative_difference(4.0, f32::INFINITY), 1.0));
+        assert!(float_eq(
+            relative_difference(f32::INFINITY, f32::INFINITY),
+            0.0
+        ));
+    }
+}
@@ -0,0 +1,195 @@
+use std::f32::consts::PI;
+
+use crate::{
+    color::Color,
+    materials::Material,
+    patterns::grid::Grid,
+    shapes::{
+        cube::Cube, cylinder::Cylinder, group::Group, plane::Plane, sphere::Sphere, ShapeBuilder,
+    },
+    transformations::Transform,
+};
+
+/// The book's hexagon: six cylinder edges of radius 0.25 joined by sphere
+/// corners, lying flat on the xz plane with its corners one unit out.
+pub fn hexagon() -> Group {
+    let mut hex = Group::new();
+    for n in 0..6 {
+        hex.add_child(Box::new(
+            hexagon_side().with_transform(Transform::rotation_y(n as f32 * PI / 3.0)),
+        ));
+    }
+    hex
+}
+
+fn hexagon_side() -> Group {
+    let corner = Sphere::default().with_transform(
+        Transform::translation(0.0, 0.0, -1.0) * Transform::scaling(0.25, 0.25, 0.25),
+    );
+    let edge = Cylinder::default().truncated(0.0, 1.0).with_transform(
+        Transform::translation(0.0, 0.0, -1.0)
+            * Transform::rotation_y(-PI / 6.0)
+            * Transform::rotation_z(-PI / 2.0)
+            * Transform::scaling(0.25, 1.0, 0.25),
+    );
+
+    let mut side = Group::new();
+    side.add_child(Box::new(corner));
+    side.add_child(Box::new(edge));
+    side
+}
+
+/// A square table standing on the xz plane, `width` across and `height`
+/// to the top of its slab, with a leg at each corner.
+pub fn table(width: f32, height: f32) -> Group {
+    let half = width / 2.0;
+    let thickness = height * 0.05;
+    let leg_radius = width * 0.04;
+
+    let mut table = Group::new();
+    table.add_child(Box::new(Cube::default().with_transform(
+        Transform::translation(0.0, height - thickness / 2.0, 0.0)
+            * Transform::scaling(half, thickness / 2.0, half),
+    )));
+    for &(x, z) in &[(1.0, 1.0), (1.0, -1.0), (-1.0, -1.0), (-1.0, 1.0)] {
+        let inset = half - leg_radius;
+        table.add_child(Box::new(
+            Cylinder::default()
+                .with_caps(0.0, height - thickness)
+                .with_transform(
+                    Transform::translation(x * inset, 0.0, z * inset)
+                        * Transform::scaling(leg_radius, 1.0, leg_radius),
+                ),
+        ));
+    }
+    table
+}
+
+/// A box of a room, `size` each way, with its floor on the xz plane. The
+/// floor, ceiling and four walls are separate slabs, in that order, so each
+/// can be given its own material.
+pub fn room(size: f32) -> Group {
+    let half = size / 2.0;
+    let thickness = size * 0.01;
+    let slab = |x: f32, y: f32, z: f32, sx: f32, sy: f32, sz: f32| {
+        Box::new(
+            Cube::default()
+                .with_transform(Transform::translation(x, y, z) * Transform::scaling(sx, sy, sz)),
+        )
+    };
+    let depth = half + thickness;
+
+    let mut room = Group::new();
+    room.add_child(slab(0.0, -thickness, 0.0, depth, thickness, depth));
+    room.add_child(slab(0.0, size + thickness, 0.0, depth, thickness, depth));
+    room.add_child(slab(depth, half, 0.0, thickness, half, depth));
+    room.add_child(slab(-depth, half, 0.0, thickness, half, depth));
+    room.add_child(slab(0.0, half, depth, depth, half, thickness));
+    room.add_child(slab(0.0, half, -depth, depth, half, thickness));
+    room
+}
+
+/// The xz plane ruled every unit in dark grey over light grey, fading out
+/// `fade` from the origin, as a matte floor for diagnostic renders.
+pub fn grid_floor(fade: f32) -> Plane {
+    let grid = Grid::new(Color::new(0.2, 0.2, 0.2), Color::new(0.8, 0.8, 0.8)).fade(fade);
+    Plane::default().with_material(Material::default().pattern(Box::new(grid)).specular(0.0))
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{
+        float_eq,
+        intersection::Intersection,
+        ray::Ray,
+        shapes::Shape,
+        tuple::{Point, Vector},
+    };
+
+    use super::*;
+
+    fn first_hit(shape: &dyn Shape, ray: Ray) -> Option<f32> {
+        Intersection::hit(&shape.intersect(ray)).map(|i| i.t)
+    }
+
+    #[test]
+    fn a_hexagon_has_six_sides_of_a_corner_and_an_edge() {
+        let hex = hexagon();
+
+        assert_eq!(hex.children().len(), 6);
+        assert!(hex.children().iter().all(|side| side.children().len() == 2));
+    }
+
+    #[test]
+    fn a_ray_through_a_hexagon_corner() {
+        let r = Ray::new(Point::new(0.0, 0.0, -5.0), Vector::new(0.0, 0.0, 1.0));
+
+        assert!(float_eq(first_hit(&hexagon(), r).unwrap(), 3.75));
+    }
+
+    #[test]
+    fn a_ray_through_the_middle_of_a_hexagon_edge() {
+        let middle = Point::new(0.433_012_7, 0.0, -0.75);
+        let r = Ray::new(
+            Point::new(middle.x(), 5.0, middle.z()),
+            Vector::new(0.0, -1.0, 0.0),
+        );
+
+        assert!(float_eq(first_hit(&hexagon(), r).unwrap(), 4.75));
+    }
+
+    #[test]
+    fn the_hexagon_is_hollow() {
+        let r = Ray::new(Point::new(0.0, 5.0, 0.0), Vector::new(0.0, -1.0, 0.0));
+
+        assert_eq!(first_hit(&hexagon(), r), None);
+    }
+
+    #[test]
+    fn a_table_top_is_at_its_height() {
+        let r = Ray::new(Point::new(0.0, 5.0, 0.0), Vector::new(0.0, -1.0, 0.0));
+
+        assert!(float_eq(first_hit(&table(2.0, 1.0), r).unwrap(), 4.0));
+    }
+
+    #[test]
+    fn a_table_stands_on_its_legs() {
+        let r = Ray::new(Point::new(-5.0, 0.5, 0.92), Vector::new(1.0, 0.0, 0.0));
+
+        assert!(float_eq(first_hit(&table(2.0, 1.0), r).unwrap(), 4.0));
+    }
+
+    macro_rules! room_tests {
+        ($($name:ident: $value:expr,)*) => {
+            $(
+                #[test]
+                fn $name() {
+                    let (direction, expected) = $value;
+                    let r = Ray::new(Point::new(0.0, 2.0, 0.0), direction);
+
+                    assert!(float_eq(first_hit(&room(8.0), r).unwrap(), expected));
+                }
+            )*
+        }
+    }
+
+    room_tests! {
+        the_room_floor_is_on_the_xz_plane: (Vector::new(0.0, -1.0, 0.0), 2.0),
+        the_room_ceiling_is_size_high: (Vector::new(0.0, 1.0, 0.0), 6.0),
+        the_room_walls_are_half_the_size_away_0: (Vector::new(1.0, 0.0, 0.0), 4.0),
+        the_room_walls_are_half_the_size_away_1: (Vector::new(-1.0, 0.0, 0.0), 4.0),
+        the_room_walls_are_half_the_size_away_2: (Vector::new(0.0, 0.0, 1.0), 4.0),
+        the_room_walls_are_half_the_size_away_3: (Vector::new(0.0, 0.0, -1.0), 4.0),
+    }
+
+    #[test]
+    fn a_grid_floor_is_ruled_every_unit() {
+        let floor = grid_floor(20.0);
+
+        let color = |x: f32, z: f32| floor.material().color_at(&floor, Point::new(x, 0.0, z));
+
+        assert_eq!(color(0.5, 0.5), Color::new(0.8, 0.8, 0.8));
+        assert_eq!(color(0.0, 0.0), Color::new(0.2, 0.2, 0.2));
+        assert_eq!(color(0.5, 30.0), Color::new(0.8, 0.8, 0.8));
+    }
+}
@@ -0,0 +1,56 @@
+use minifb::{Error, Key, Window, WindowOptions};
+
+use crate::{camera::Camera, canvas::Canvas, integrator::Integrator, world::World};
+
+/// Renders into a window, redrawing after every scanline, then keeps the
+/// finished image up until the window is closed. Closing the window or
+/// pressing Escape part way through abandons the render and returns `None`.
+/// Fails without rendering anything if no window can be opened, e.g. on a
+/// machine without a display.
+pub fn render_in_window(
+    camera: &Camera,
+    world: &World,
+    integrator: &dyn Integrator,
+) -> Result<Option<Canvas>, Error> {
+    let mut window = open_window(camera)?;
+
+    let image = camera.render_progressively(world, integrator, |partial, _| {
+        show(&mut window, partial) && !window.is_key_down(Key::Escape)
+    });
+
+    if let Some(image) = &image {
+        while show(&mut window, image) && !window.is_key_down(Key::Escape) {}
+    }
+    Ok(image)
+}
+
+/// Renders pass after pass into a window, each one refining the last,
+/// until the window is closed or Escape is pressed, then returns the image
+/// as it stands. Fails, like `render_in_window`, if no window can be opened.
+pub fn render_passes_in_window(
+    camera: &Camera,
+    world: &World,
+    integrator: &dyn Integrator,
+) -> Result<Canvas, Error> {
+    let mut window = open_window(camera)?;
+
+    Ok(camera.render_passes(world, integrator, |average, _| {
+        show(&mut window, average) && !window.is_key_down(Key::Escape)
+    }))
+}
+
+fn open_window(camera: &Camera) -> Result<Window, Error> {
+    Window::new(
+        "Ray Tracer Challenge",
+        camera.hsize(),
+        camera.vsize(),
+        WindowOptions::default(),
+    )
+}
+
+fn show(window: &mut Window, image: &Canvas) -> bool {
+    window
+        .update_with_buffer(&image.to_srgb().to_rgb_u32(), image.width, image.height)
+        .is_ok()
+        && window.is_open()
+}
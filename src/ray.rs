@@ -1,58 +1,352 @@
-use crate::{transformations::Transform, tuple::Tuple};
+use uuid::Uuid;
 
-#[derive(Copy, Clone, Debug, Default, PartialEq)]
+use crate::{
+    sampler::{pixel_seed, Sampler, Xorshift},
+    transformations::Transform,
+    tuple::{Point, Vector},
+    EPSILON,
+};
+
+/// What a ray is being cast for.
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq, Hash)]
+pub enum RayKind {
+    #[default]
+    Primary,
+    Shadow,
+    Reflection,
+    Refraction,
+    Diffuse,
+}
+
+/// How many more bounces of each kind a ray may spawn. Other kinds of ray
+/// aren't limited by it.
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct DepthBudget {
+    pub reflection: u32,
+    pub refraction: u32,
+    /// How much whatever the ray brings back will count toward the pixel:
+    /// the product of the reflectivity and transparency of every surface
+    /// it has bounced off or passed through on the way.
+    pub contribution: f32,
+}
+
+impl DepthBudget {
+    pub fn new(reflection: u32, refraction: u32) -> Self {
+        Self {
+            reflection,
+            refraction,
+            contribution: 1.0,
+        }
+    }
+
+    /// The budget for a ray whose color will be scaled by `factor`.
+    pub fn attenuate(self, factor: f32) -> Self {
+        Self {
+            contribution: self.contribution * factor,
+            ..self
+        }
+    }
+
+    pub fn allows(&self, kind: RayKind) -> bool {
+        match kind {
+            RayKind::Reflection => self.reflection > 0,
+            RayKind::Refraction => self.refraction > 0,
+            _ => true,
+        }
+    }
+
+    /// The budget left for whatever a ray of `kind` goes on to spawn.
+    pub fn spend(self, kind: RayKind) -> Self {
+        match kind {
+            RayKind::Reflection => Self {
+                reflection: self.reflection.saturating_sub(1),
+                ..self
+            },
+            RayKind::Refraction => Self {
+                refraction: self.refraction.saturating_sub(1),
+                ..self
+            },
+            _ => self,
+        }
+    }
+}
+
+/// Russian roulette for reflected and refracted rays. A ray that would
+/// contribute less than `threshold` is only followed some of the time, with
+/// its color scaled up to make up for the times it isn't, so the image
+/// comes out the same on average while far fewer faint rays are cast.
+#[derive(Debug)]
+pub struct Roulette {
+    pub threshold: f32,
+    seed: u64,
+    rng: Xorshift,
+}
+
+impl Roulette {
+    pub fn new(threshold: f32, seed: u64) -> Self {
+        Self {
+            threshold,
+            seed,
+            rng: Xorshift::new(seed),
+        }
+    }
+
+    pub fn start_pixel(&self, x: usize, y: usize, pass: u32) {
+        self.rng.reseed(pixel_seed(self.seed, x, y, pass));
+    }
+
+    /// What to scale the color of a ray contributing `contribution` by, or
+    /// `None` when it loses and isn't followed.
+    pub fn survive(&self, contribution: f32) -> Option<f32> {
+        if contribution >= self.threshold {
+            return Some(1.0);
+        }
+
+        let probability = contribution / self.threshold;
+        if self.rng.next_f32() < probability {
+            Some(1.0 / probability)
+        } else {
+            None
+        }
+    }
+}
+
+/// The same budget for every kind, like the book's single `remaining`.
+impl From<u32> for DepthBudget {
+    fn from(depth: u32) -> Self {
+        Self::new(depth, depth)
+    }
+}
+
+/// How far the points a ray leaves a surface from are nudged off it. Too
+/// little and the surface shadows itself in speckles (acne); too much and
+/// shadows come away from the objects casting them (peter-panning). The
+/// book's fixed `EPSILON` is only right for scenes around its own scale.
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub enum ShadowBias {
+    /// The same distance everywhere.
+    Constant(f32),
+    /// This much per unit of the hit point's largest coordinate, following
+    /// the precision of the floats describing it in big scenes.
+    Scaled(f32),
+    /// This much per unit of distance the ray travelled to the hit, so
+    /// faraway surfaces seen at a glance get more room.
+    Adaptive(f32),
+}
+
+impl ShadowBias {
+    /// The offset for a hit at `point`, `distance` along the ray. Scaled
+    /// and adaptive biases never go below their constant value.
+    pub fn offset(&self, point: Point, distance: f32) -> f32 {
+        match *self {
+            ShadowBias::Constant(bias) => bias,
+            ShadowBias::Scaled(bias) => {
+                let magnitude = point.x().abs().max(point.y().abs()).max(point.z().abs());
+                bias * magnitude.max(1.0)
+            }
+            ShadowBias::Adaptive(bias) => bias * distance.max(1.0),
+        }
+    }
+}
+
+impl Default for ShadowBias {
+    fn default() -> Self {
+        ShadowBias::Constant(EPSILON)
+    }
+}
+
+#[derive(Copy, Clone, Debug, PartialEq)]
 
 pub struct Ray {
-    pub origin: Tuple,
-    pub direction: Tuple,
+    pub origin: Point,
+    pub direction: Vector,
+    pub kind: RayKind,
+    /// The one color channel (0 red, 1 green, 2 blue) a ray carries once
+    /// dispersion has split it; `None` carries them all.
+    pub channel: Option<usize>,
+    /// How far surfaces this ray hits offset the rays leaving them. Rays
+    /// spawned from a hit carry it on.
+    pub bias: ShadowBias,
+    /// How much wider the patch of scene the ray stands for gets with each
+    /// unit of `t`, such as the width of a camera pixel. Patterns average
+    /// themselves over that patch where the ray lands; 0 samples a point.
+    pub spread: f32,
+    /// How the ray moves from pixel to pixel, when that's being tracked.
+    /// Where it's known, it takes the place of `spread`.
+    pub differential: Option<RayDifferential>,
+    /// The shape the ray set off from, if any. Hits on it closer than the
+    /// ray's bias are the ray finding its own starting point again, and
+    /// are skipped.
+    pub leaving: Option<Uuid>,
+    /// The span of `t` the ray looks for hits in, both ends included, such
+    /// as up to the light for a shadow ray. Unbounded either way at first.
+    pub t_min: f32,
+    pub t_max: f32,
+}
+
+impl Default for Ray {
+    fn default() -> Self {
+        Self::new(Point::default(), Vector::default())
+    }
 }
 
 impl Ray {
-    pub fn new(origin: Tuple, direction: Tuple) -> Self {
-        Self { origin, direction }
+    pub fn new(origin: Point, direction: Vector) -> Self {
+        Self {
+            origin,
+            direction,
+            kind: RayKind::default(),
+            channel: None,
+            bias: ShadowBias::default(),
+            spread: 0.0,
+            differential: None,
+            leaving: None,
+            t_min: f32::NEG_INFINITY,
+            t_max: f32::INFINITY,
+        }
+    }
+
+    pub fn kind(self, kind: RayKind) -> Self {
+        Self { kind, ..self }
+    }
+
+    pub fn channel(self, channel: Option<usize>) -> Self {
+        Self { channel, ..self }
+    }
+
+    pub fn bias(self, bias: ShadowBias) -> Self {
+        Self { bias, ..self }
+    }
+
+    pub fn spread(self, spread: f32) -> Self {
+        Self { spread, ..self }
+    }
+
+    pub fn differential(self, differential: Option<RayDifferential>) -> Self {
+        Self {
+            differential,
+            ..self
+        }
+    }
+
+    pub fn leaving(self, leaving: Option<Uuid>) -> Self {
+        Self { leaving, ..self }
+    }
+
+    pub fn t_min(self, t_min: f32) -> Self {
+        Self { t_min, ..self }
+    }
+
+    pub fn t_max(self, t_max: f32) -> Self {
+        Self { t_max, ..self }
+    }
+
+    /// Whether `t` lies within the ray's span.
+    pub fn in_range(&self, t: f32) -> bool {
+        self.t_min <= t && t <= self.t_max
+    }
+
+    /// Whether a hit `t` along the ray on `object` is just the surface the
+    /// ray left: ahead of it, but no further than its bias.
+    pub fn is_self_hit(&self, object: Uuid, t: f32) -> bool {
+        self.leaving == Some(object)
+            && t >= 0.0
+            && t * self.direction.magnitude() < self.bias.offset(self.origin, 0.0)
     }
 
     pub fn origin(self, x: f32, y: f32, z: f32) -> Self {
         Self {
-            origin: Tuple::point(x, y, z),
+            origin: Point::new(x, y, z),
             ..self
         }
     }
 
     pub fn direction(self, x: f32, y: f32, z: f32) -> Self {
         Self {
-            direction: Tuple::vector(x, y, z),
+            direction: Vector::new(x, y, z),
             ..self
         }
     }
 
-    pub fn position(&self, t: f32) -> Tuple {
+    pub fn position(&self, t: f32) -> Point {
         self.origin + self.direction * t
     }
 
     pub fn transform(self, transform: Transform) -> Self {
-        let origin_vec = transform.mat() * self.origin.vec();
-        let direction_vec = transform.mat() * self.direction.vec();
         Self {
-            origin: Tuple::new(origin_vec.x, origin_vec.y, origin_vec.z, origin_vec.w),
-            direction: Tuple::new(
-                direction_vec.x,
-                direction_vec.y,
-                direction_vec.z,
-                direction_vec.w,
-            ),
+            origin: transform * self.origin,
+            direction: transform * self.direction,
+            differential: self.differential.map(|d| d.transform(transform)),
+            ..self
         }
     }
 }
 
+/// How a ray's origin and direction change with a step of one pixel
+/// across, `x`, and one down, `y`. Followed to where the ray lands, they
+/// give the patch of surface the ray stands for.
+#[derive(Copy, Clone, Debug, Default, PartialEq)]
+pub struct RayDifferential {
+    pub dpdx: Vector,
+    pub dpdy: Vector,
+    pub dddx: Vector,
+    pub dddy: Vector,
+}
+
+impl RayDifferential {
+    pub fn transform(self, transform: Transform) -> Self {
+        Self {
+            dpdx: transform * self.dpdx,
+            dpdy: transform * self.dpdy,
+            dddx: transform * self.dddx,
+            dddy: transform * self.dddy,
+        }
+    }
+
+    /// The differential carried along a ray in `direction` to where it
+    /// lands at `t`, with the offsets slid along the ray to lie in the
+    /// surface there, which faces `normal`.
+    pub fn transfer(self, direction: Vector, t: f32, normal: Vector) -> Self {
+        let facing = direction.dot(normal);
+        let onto_surface = |dp: Vector, dd: Vector| {
+            let dp = dp + dd * t;
+            if facing.abs() < EPSILON {
+                dp
+            } else {
+                dp - direction * (dp.dot(normal) / facing)
+            }
+        };
+
+        Self {
+            dpdx: onto_surface(self.dpdx, self.dddx),
+            dpdy: onto_surface(self.dpdy, self.dddy),
+            ..self
+        }
+    }
+
+    /// A ray leaving from where this one landed, with its direction
+    /// changing by `dddx` and `dddy`.
+    pub fn leaving(self, dddx: Vector, dddy: Vector) -> Self {
+        Self { dddx, dddy, ..self }
+    }
+
+    /// The width of the patch the ray covers, taking the longer side.
+    pub fn footprint(&self) -> f32 {
+        self.dpdx.magnitude().max(self.dpdy.magnitude())
+    }
+}
+
 #[cfg(test)]
 mod tests {
+    use crate::float_eq;
+
     use super::*;
 
     #[test]
     fn creating_and_querying_a_ray() {
-        let origin = Tuple::point(1.0, 2.0, 3.0);
-        let direction = Tuple::vector(4.0, 5.0, 6.0);
+        let origin = Point::new(1.0, 2.0, 3.0);
+        let direction = Vector::new(4.0, 5.0, 6.0);
 
         let r = Ray::new(origin, direction);
 
@@ -62,39 +356,145 @@ mod tests {
 
     #[test]
     fn computing_a_point_from_a_distance() {
-        let r = Ray::new(Tuple::point(2.0, 3.0, 4.0), Tuple::vector(1.0, 0.0, 0.0));
+        let r = Ray::new(Point::new(2.0, 3.0, 4.0), Vector::new(1.0, 0.0, 0.0));
 
-        assert_eq!(r.position(0.0), Tuple::point(2.0, 3.0, 4.0));
-        assert_eq!(r.position(1.0), Tuple::point(3.0, 3.0, 4.0));
-        assert_eq!(r.position(-1.0), Tuple::point(1.0, 3.0, 4.0));
-        assert_eq!(r.position(2.5), Tuple::point(4.5, 3.0, 4.0));
+        assert_eq!(r.position(0.0), Point::new(2.0, 3.0, 4.0));
+        assert_eq!(r.position(1.0), Point::new(3.0, 3.0, 4.0));
+        assert_eq!(r.position(-1.0), Point::new(1.0, 3.0, 4.0));
+        assert_eq!(r.position(2.5), Point::new(4.5, 3.0, 4.0));
     }
 
     #[test]
     fn translating_a_ray() {
-        let r = Ray::new(Tuple::point(1.0, 2.0, 3.0), Tuple::vector(0.0, 1.0, 0.0));
+        let r = Ray::new(Point::new(1.0, 2.0, 3.0), Vector::new(0.0, 1.0, 0.0));
         let m = Transform::translation(3.0, 4.0, 5.0);
 
         let r2 = r.transform(m);
 
-        assert_eq!(r2.origin, Tuple::point(4.0, 6.0, 8.0));
-        assert_eq!(r2.direction, Tuple::vector(0.0, 1.0, 0.0));
+        assert_eq!(r2.origin, Point::new(4.0, 6.0, 8.0));
+        assert_eq!(r2.direction, Vector::new(0.0, 1.0, 0.0));
     }
 
     #[test]
     fn scaling_a_ray() {
-        let r = Ray::new(Tuple::point(1.0, 2.0, 3.0), Tuple::vector(0.0, 1.0, 0.0));
+        let r = Ray::new(Point::new(1.0, 2.0, 3.0), Vector::new(0.0, 1.0, 0.0));
         let m = Transform::scaling(2.0, 3.0, 4.0);
 
         let r2 = r.transform(m);
 
-        assert_eq!(r2.origin, Tuple::point(2.0, 6.0, 12.0));
-        assert_eq!(r2.direction, Tuple::vector(0.0, 3.0, 0.0));
+        assert_eq!(r2.origin, Point::new(2.0, 6.0, 12.0));
+        assert_eq!(r2.direction, Vector::new(0.0, 3.0, 0.0));
+    }
+
+    #[test]
+    fn a_depth_budget_from_a_single_depth() {
+        let budget = DepthBudget::from(3);
+
+        assert_eq!(budget, DepthBudget::new(3, 3));
+    }
+
+    #[test]
+    fn spending_a_depth_budget() {
+        let budget = DepthBudget::new(1, 2);
+
+        let reflected = budget.spend(RayKind::Reflection);
+        let refracted = budget.spend(RayKind::Refraction);
+
+        assert_eq!(reflected, DepthBudget::new(0, 2));
+        assert!(!reflected.allows(RayKind::Reflection));
+        assert!(reflected.allows(RayKind::Refraction));
+        assert_eq!(refracted, DepthBudget::new(1, 1));
+        assert_eq!(budget.spend(RayKind::Shadow), budget);
+        assert_eq!(reflected.spend(RayKind::Reflection), reflected);
+    }
+
+    #[test]
+    fn attenuating_a_depth_budget() {
+        let budget = DepthBudget::from(3).attenuate(0.5).attenuate(0.1);
+
+        assert!(float_eq(budget.contribution, 0.05));
+        assert_eq!(
+            budget.spend(RayKind::Reflection).contribution,
+            budget.contribution
+        );
+    }
+
+    #[test]
+    fn strong_rays_always_survive_the_roulette() {
+        let roulette = Roulette::new(0.1, 1);
+
+        for _ in 0..100 {
+            assert_eq!(roulette.survive(0.1), Some(1.0));
+        }
+    }
+
+    #[test]
+    fn faint_rays_survive_in_proportion_and_are_compensated() {
+        let roulette = Roulette::new(0.1, 1);
+
+        let survivors = (0..10_000)
+            .filter_map(|_| roulette.survive(0.025))
+            .collect::<Vec<_>>();
+
+        assert!((2300..2700).contains(&survivors.len()));
+        assert!(survivors.iter().all(|&w| float_eq(w, 4.0)));
+    }
+
+    #[test]
+    fn an_exhausted_budget_still_allows_unlimited_kinds() {
+        let budget = DepthBudget::from(0);
+
+        assert!(!budget.allows(RayKind::Reflection));
+        assert!(!budget.allows(RayKind::Refraction));
+        assert!(budget.allows(RayKind::Shadow));
+        assert!(budget.allows(RayKind::Primary));
+    }
+
+    #[test]
+    fn a_new_ray_is_a_primary_ray() {
+        let r = Ray::new(Point::new(1.0, 2.0, 3.0), Vector::new(0.0, 1.0, 0.0));
+
+        assert_eq!(r.kind, RayKind::Primary);
+    }
+
+    #[test]
+    fn transforming_a_ray_keeps_its_kind() {
+        let r =
+            Ray::new(Point::new(1.0, 2.0, 3.0), Vector::new(0.0, 1.0, 0.0)).kind(RayKind::Shadow);
+
+        let r2 = r.transform(Transform::scaling(2.0, 3.0, 4.0));
+
+        assert_eq!(r2.kind, RayKind::Shadow);
+    }
+
+    #[test]
+    fn a_new_ray_looks_for_hits_everywhere_along_it() {
+        let r = Ray::new(Point::new(1.0, 2.0, 3.0), Vector::new(0.0, 1.0, 0.0));
+
+        assert!(r.in_range(f32::NEG_INFINITY) && r.in_range(1e30));
+        assert!(r.t_min(0.0).t_max(2.0).in_range(2.0));
+        assert!(!r.t_min(0.0).t_max(2.0).in_range(-0.5));
+        assert!(!r.t_min(0.0).t_max(2.0).in_range(2.5));
+    }
+
+    #[test]
+    fn only_hits_on_the_shape_left_within_the_bias_are_self_hits() {
+        let (shape, other) = (Uuid::new_v4(), Uuid::new_v4());
+        let r = Ray::new(Point::new(0.0, 0.0, 0.0), Vector::new(0.0, 0.0, 2.0))
+            .bias(ShadowBias::Constant(0.01))
+            .leaving(Some(shape));
+
+        assert!(r.is_self_hit(shape, 0.0));
+        assert!(r.is_self_hit(shape, 0.004));
+        assert!(!r.is_self_hit(shape, 0.006));
+        assert!(!r.is_self_hit(shape, -0.001));
+        assert!(!r.is_self_hit(other, 0.001));
+        assert!(!r.leaving(None).is_self_hit(shape, 0.001));
     }
 
     // #[test]
     // fn intersecting_a_scaled_sphere_with_a_ray() {
-    //     let r = Ray::new(Tuple::point(0.0, 0.0, -5.0), Tuple::vector(0.0, 0.0, 1.0));
+    //     let r = Ray::new(Point::new(0.0, 0.0, -5.0), Vector::new(0.0, 0.0, 1.0));
     //     let mut s = Sphere::default();
 
     //     s = s.transform(Transform::scaling(2.0, 2.0, 2.0));
@@ -107,7 +507,7 @@ mod tests {
 
     // #[test]
     // fn intersecting_a_translated_sphere_with_a_ray() {
-    //     let r = Ray::new(Tuple::point(0.0, 0.0, -5.0), Tuple::vector(0.0, 0.0, 1.0));
+    //     let r = Ray::new(Point::new(0.0, 0.0, -5.0), Vector::new(0.0, 0.0, 1.0));
     //     let mut s = Sphere::default();
 
     //     s = s.transform(Transform::translation(5.0, 0.0, 0.0));
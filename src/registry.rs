@@ -0,0 +1,340 @@
+use std::collections::BTreeMap;
+
+use crate::{
+    color::Color,
+    lights::LightGroups,
+    materials::{Material, Shading},
+    transformations::{Transform, IDENTITY},
+};
+
+/// Named materials and transform stacks, defined once and shared between
+/// many objects. Like the book's scene files, a definition can extend an
+/// earlier one: a material by changing some of its fields, a transform
+/// stack by appending more steps.
+///
+/// With the `serde` feature a registry saves in any serde format, such as
+/// RON or JSON, so definitions tuned once can be reused in other scenes.
+/// Names are kept in order so the file diffs cleanly, and any section left
+/// out of a file loads empty.
+#[derive(Clone, Debug, Default, PartialEq)]
+#[cfg_attr(
+    feature = "serde",
+    derive(serde::Serialize, serde::Deserialize),
+    serde(default)
+)]
+pub struct Registry {
+    materials: BTreeMap<String, Material>,
+    transforms: BTreeMap<String, Vec<Transform>>,
+    light_groups: Vec<String>,
+}
+
+impl Registry {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// A starting set of common materials, to use as they are or to extend.
+    pub fn presets() -> Self {
+        let mut registry = Self::new();
+        registry.define_material(
+            "brushed_steel",
+            Material::default()
+                .color(Color::new(0.56, 0.57, 0.58))
+                .shading(Shading::MetallicRoughness {
+                    metallic: 1.0,
+                    roughness: 0.35,
+                }),
+        );
+        registry.define_material(
+            "chrome",
+            Material::default()
+                .color(Color::new(0.1, 0.1, 0.1))
+                .diffuse(0.1)
+                .specular(1.0)
+                .shininess(300.0)
+                .reflective(0.9),
+        );
+        registry.define_material(
+            "glass",
+            Material::default()
+                .color(Color::new(0.1, 0.1, 0.1))
+                .diffuse(0.1)
+                .specular(1.0)
+                .shininess(300.0)
+                .reflective(0.9)
+                .transparency(0.9)
+                .refractive_index(1.5),
+        );
+        registry.define_material("matte_white", Material::default().specular(0.0));
+        registry.define_material(
+            "red_plastic",
+            Material::default()
+                .color(Color::new(0.8, 0.1, 0.1))
+                .specular(0.5)
+                .shininess(50.0)
+                .reflective(0.05),
+        );
+        registry
+    }
+
+    /// Adds every definition in `other`, which wins where both define a
+    /// name, so a project's registry can override a shared one.
+    pub fn merge(&mut self, other: Registry) {
+        self.materials.extend(other.materials);
+        self.transforms.extend(other.transforms);
+        for group in other.light_groups {
+            if !self.light_groups.contains(&group) {
+                self.light_groups.push(group);
+            }
+        }
+    }
+
+    pub fn define_material(&mut self, name: &str, material: Material) {
+        self.materials.insert(name.to_string(), material);
+    }
+
+    /// The names of the defined materials, in alphabetical order.
+    pub fn material_names(&self) -> impl Iterator<Item = &str> {
+        self.materials.keys().map(String::as_str)
+    }
+
+    /// Defines `name` as a copy of `base` with `changes` applied, returning
+    /// the new material, or `None` if `base` isn't defined.
+    pub fn extend_material(
+        &mut self,
+        name: &str,
+        base: &str,
+        changes: impl FnOnce(Material) -> Material,
+    ) -> Option<&Material> {
+        let material = changes(self.material(base)?);
+        self.define_material(name, material);
+        self.materials.get(name)
+    }
+
+    pub fn material(&self, name: &str) -> Option<Material> {
+        self.materials.get(name).cloned()
+    }
+
+    /// Defines `name` as `steps` applied in order, first to last.
+    pub fn define_transform(&mut self, name: &str, steps: Vec<Transform>) {
+        self.transforms.insert(name.to_string(), steps);
+    }
+
+    /// Defines `name` as the steps of `base` followed by `steps`, returning
+    /// the combined transform, or `None` if `base` isn't defined.
+    pub fn extend_transform(
+        &mut self,
+        name: &str,
+        base: &str,
+        steps: Vec<Transform>,
+    ) -> Option<Transform> {
+        let mut combined = self.transforms.get(base)?.clone();
+        combined.extend(steps);
+        self.define_transform(name, combined);
+        self.transform(name)
+    }
+
+    /// The light group called `name`, set aside the first time it's asked
+    /// for. `None` once every group has been taken.
+    pub fn light_group(&mut self, name: &str) -> Option<LightGroups> {
+        let index = match self.light_groups.iter().position(|g| g == name) {
+            Some(index) => index,
+            None => {
+                let index = self.light_groups.len();
+                LightGroups::group(index)?;
+                self.light_groups.push(name.to_string());
+                index
+            }
+        };
+        LightGroups::group(index)
+    }
+
+    pub fn transform(&self, name: &str) -> Option<Transform> {
+        let steps = self.transforms.get(name)?;
+        Some(
+            steps
+                .iter()
+                .fold(IDENTITY, |combined, &step| step * combined),
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{
+        color::Color,
+        float_eq,
+        shapes::{sphere::Sphere, Shape, ShapeBuilder},
+        tuple::Point,
+    };
+
+    use super::*;
+
+    fn white_material() -> Material {
+        Material::default()
+            .color(Color::new(1.0, 1.0, 1.0))
+            .diffuse(0.7)
+            .ambient(0.1)
+            .specular(0.0)
+            .reflective(0.1)
+    }
+
+    #[test]
+    fn looking_up_a_defined_material() {
+        let mut registry = Registry::new();
+        registry.define_material("white-material", white_material());
+
+        assert_eq!(registry.material("white-material"), Some(white_material()));
+        assert_eq!(registry.material("black-material"), None);
+    }
+
+    #[test]
+    fn extending_a_material_keeps_the_fields_it_does_not_change() {
+        let mut registry = Registry::new();
+        registry.define_material("white-material", white_material());
+
+        registry
+            .extend_material("blue-material", "white-material", |m| {
+                m.color(Color::new(0.537, 0.831, 0.914))
+            })
+            .unwrap();
+        let blue = registry.material("blue-material").unwrap();
+
+        assert_eq!(blue.color, Color::new(0.537, 0.831, 0.914));
+        assert!(float_eq(blue.diffuse, 0.7));
+        assert!(float_eq(blue.reflective, 0.1));
+        assert_eq!(registry.material("white-material"), Some(white_material()));
+    }
+
+    #[test]
+    fn material_names_are_listed_in_order() {
+        let mut registry = Registry::new();
+        registry.define_material("wood", Material::default());
+        registry.define_material("brass", Material::default());
+        registry.define_material("marble", Material::default());
+
+        assert_eq!(
+            registry.material_names().collect::<Vec<_>>(),
+            vec!["brass", "marble", "wood"]
+        );
+    }
+
+    #[test]
+    fn merging_registries_prefers_the_newer_definitions() {
+        let mut shared = Registry::presets();
+        shared.define_transform("raised", vec![Transform::translation(0.0, 1.0, 0.0)]);
+        let mut project = Registry::new();
+        project.define_material("glass", Material::default().refractive_index(1.7));
+        project.define_material("jade", Material::default().translucency(0.5));
+        project.define_transform("raised", vec![Transform::translation(0.0, 2.0, 0.0)]);
+
+        shared.merge(project);
+
+        assert_eq!(
+            shared.material_names().count(),
+            Registry::presets().material_names().count() + 1
+        );
+        assert!(float_eq(
+            shared.material("glass").unwrap().refractive_index,
+            1.7
+        ));
+        assert!(shared.material("brushed_steel").is_some());
+        assert_eq!(
+            shared.transform("raised"),
+            Some(Transform::translation(0.0, 2.0, 0.0))
+        );
+    }
+
+    #[test]
+    fn extending_an_undefined_material() {
+        let mut registry = Registry::new();
+
+        assert!(registry.extend_material("blue", "white", |m| m).is_none());
+        assert_eq!(registry.material("blue"), None);
+    }
+
+    #[test]
+    fn a_transform_stack_applies_its_steps_in_order() {
+        let mut registry = Registry::new();
+        registry.define_transform(
+            "standard-transform",
+            vec![
+                Transform::translation(1.0, -1.0, 1.0),
+                Transform::scaling(0.5, 0.5, 0.5),
+            ],
+        );
+
+        let t = registry.transform("standard-transform").unwrap();
+
+        assert_eq!(t * Point::new(1.0, 1.0, 1.0), Point::new(1.0, 0.0, 1.0));
+    }
+
+    #[test]
+    fn extending_a_transform_stack_appends_steps() {
+        let mut registry = Registry::new();
+        registry.define_transform(
+            "standard-transform",
+            vec![
+                Transform::translation(1.0, -1.0, 1.0),
+                Transform::scaling(0.5, 0.5, 0.5),
+            ],
+        );
+
+        let large = registry
+            .extend_transform(
+                "large-object",
+                "standard-transform",
+                vec![Transform::scaling(3.5, 3.5, 3.5)],
+            )
+            .unwrap();
+
+        assert_eq!(large * Point::new(1.0, 1.0, 1.0), Point::new(3.5, 0.0, 3.5));
+        assert_eq!(registry.transform("large-object"), Some(large));
+        assert!(registry
+            .extend_transform("huge", "missing", vec![])
+            .is_none());
+    }
+
+    #[test]
+    fn light_groups_are_handed_out_by_name() {
+        let mut registry = Registry::new();
+
+        let key = registry.light_group("key").unwrap();
+        let rim = registry.light_group("rim").unwrap();
+
+        assert_ne!(key, rim);
+        assert_eq!(registry.light_group("key"), Some(key));
+        assert!(!key.shares_any(rim));
+    }
+
+    #[test]
+    fn running_out_of_light_groups() {
+        let mut registry = Registry::new();
+        for i in 0..32 {
+            assert!(registry.light_group(&format!("group-{}", i)).is_some());
+        }
+
+        assert_eq!(registry.light_group("one-too-many"), None);
+        assert!(registry.light_group("group-0").is_some());
+    }
+
+    #[test]
+    fn sharing_definitions_between_objects() {
+        let mut registry = Registry::new();
+        registry.define_material("white-material", white_material());
+        registry.define_transform("raised", vec![Transform::translation(0.0, 1.0, 0.0)]);
+
+        let spheres = (0..3)
+            .map(|_| {
+                Sphere::new()
+                    .with_material(registry.material("white-material").unwrap())
+                    .with_transform(registry.transform("raised").unwrap())
+            })
+            .collect::<Vec<_>>();
+
+        for s in &spheres {
+            assert_eq!(s.material(), &white_material());
+            assert_eq!(s.transform(), &Transform::translation(0.0, 1.0, 0.0));
+        }
+    }
+}
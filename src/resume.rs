@@ -0,0 +1,268 @@
+use std::{
+    error::Error,
+    fmt::{self, Display},
+    io::{self, Read, Write},
+    str::FromStr,
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Arc,
+    },
+};
+
+use crate::{
+    canvas::{checked_pixels, Canvas},
+    color::Color,
+};
+
+const MAGIC: &str = "rtc-partial";
+
+/// Asks a running render to stop. Clones share the flag, so one can be
+/// handed to another thread or a signal handler while the render checks
+/// its own copy after every row.
+#[derive(Clone, Debug, Default)]
+pub struct CancelToken(Arc<AtomicBool>);
+
+impl CancelToken {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn cancel(&self) {
+        self.0.store(true, Ordering::Relaxed);
+    }
+
+    pub fn is_cancelled(&self) -> bool {
+        self.0.load(Ordering::Relaxed)
+    }
+}
+
+/// A render stopped part way: the rows finished so far, before exposure,
+/// and the rows still to do.
+#[derive(Clone, Debug, PartialEq)]
+pub struct PartialRender {
+    pub image: Canvas,
+    pub remaining_rows: Vec<usize>,
+}
+
+impl PartialRender {
+    /// Saves the render in a plain text format that keeps full precision,
+    /// so resuming gives exactly the image an uninterrupted render would.
+    pub fn save(&self, mut writer: impl Write) -> io::Result<()> {
+        writeln!(writer, "{}", MAGIC)?;
+        writeln!(writer, "{} {}", self.image.width, self.image.height)?;
+        writeln!(writer, "{}", self.remaining_rows.len())?;
+        for row in &self.remaining_rows {
+            writeln!(writer, "{}", row)?;
+        }
+        for y in 0..self.image.height {
+            for x in 0..self.image.width {
+                let pixel = self.image.pixel_at(x, y);
+                writeln!(writer, "{} {} {}", pixel.red(), pixel.green(), pixel.blue())?;
+            }
+        }
+        Ok(())
+    }
+
+    pub fn load(mut reader: impl Read) -> Result<Self, LoadError> {
+        let mut text = String::new();
+        reader.read_to_string(&mut text)?;
+        let mut tokens = text.split_whitespace();
+
+        match tokens.next() {
+            Some(MAGIC) => (),
+            _ => return Err(LoadError::NotAPartialRender),
+        }
+        // Everything is checked against what's actually in the file before
+        // any of it is allocated, so a corrupt header can't ask for more.
+        let tokens = tokens.collect::<Vec<_>>();
+        let mut tokens = tokens.into_iter();
+        let width = parse(tokens.next())?;
+        let height = parse(tokens.next())?;
+        let count = parse(tokens.next())?;
+        if count > tokens.len() {
+            return Err(LoadError::UnexpectedEnd);
+        }
+        let remaining_rows = (0..count)
+            .map(|_| parse(tokens.next()))
+            .collect::<Result<Vec<usize>, _>>()?;
+        if let Some(&row) = remaining_rows.iter().find(|&&row| row >= height) {
+            return Err(LoadError::RowOutOfRange(row));
+        }
+        let pixels = checked_pixels(width, height).ok_or(LoadError::TooLarge(width, height))?;
+        if tokens.len() < pixels * 3 {
+            return Err(LoadError::UnexpectedEnd);
+        }
+
+        let mut channel = || parse::<f32>(tokens.next());
+        let mut image = Canvas::new(width, height);
+        for y in 0..height {
+            for x in 0..width {
+                image.write_pixel(x, y, Color::new(channel()?, channel()?, channel()?));
+            }
+        }
+
+        Ok(Self {
+            image,
+            remaining_rows,
+        })
+    }
+}
+
+/// Why `Camera::resume` finished without an image.
+#[derive(Clone, Debug, PartialEq)]
+pub enum ResumeError {
+    /// Cancelled again, with the rows rendered so far.
+    Cancelled(PartialRender),
+    /// The render was started at a different size from the camera's image,
+    /// and is handed back untouched.
+    WrongSize {
+        camera: (usize, usize),
+        partial: PartialRender,
+    },
+}
+
+impl Display for ResumeError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ResumeError::Cancelled(partial) => write!(
+                f,
+                "cancelled with {} rows still to render",
+                partial.remaining_rows.len()
+            ),
+            ResumeError::WrongSize { camera, partial } => write!(
+                f,
+                "a {}x{} render can't be resumed by a {}x{} camera",
+                partial.image.width, partial.image.height, camera.0, camera.1
+            ),
+        }
+    }
+}
+
+impl Error for ResumeError {}
+
+fn parse<T: FromStr>(token: Option<&str>) -> Result<T, LoadError> {
+    let token = token.ok_or(LoadError::UnexpectedEnd)?;
+    token
+        .parse()
+        .map_err(|_| LoadError::InvalidNumber(token.to_string()))
+}
+
+#[derive(Debug)]
+pub enum LoadError {
+    Io(io::Error),
+    NotAPartialRender,
+    InvalidNumber(String),
+    RowOutOfRange(usize),
+    TooLarge(usize, usize),
+    UnexpectedEnd,
+}
+
+impl Display for LoadError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            LoadError::Io(e) => write!(f, "couldn't read the saved render: {}", e),
+            LoadError::NotAPartialRender => write!(f, "not a saved partial render"),
+            LoadError::InvalidNumber(token) => write!(f, "{:?} is not a valid number", token),
+            LoadError::RowOutOfRange(row) => write!(f, "row {} is outside the image", row),
+            LoadError::TooLarge(width, height) => {
+                write!(f, "a {} by {} render is too large", width, height)
+            }
+            LoadError::UnexpectedEnd => write!(f, "the saved render is cut short"),
+        }
+    }
+}
+
+impl Error for LoadError {}
+
+impl From<io::Error> for LoadError {
+    fn from(e: io::Error) -> Self {
+        LoadError::Io(e)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn partial() -> PartialRender {
+        let mut image = Canvas::new(2, 3);
+        image.write_pixel(0, 0, Color::new(1.5, 0.25, 0.123_456_79));
+        image.write_pixel(1, 0, Color::new(0.0, -0.5, 1.0));
+        PartialRender {
+            image,
+            remaining_rows: vec![1, 2],
+        }
+    }
+
+    #[test]
+    fn cancelling_a_token_cancels_its_clones() {
+        let token = CancelToken::new();
+        let other = token.clone();
+
+        assert!(!other.is_cancelled());
+        token.cancel();
+
+        assert!(other.is_cancelled());
+    }
+
+    #[test]
+    fn a_saved_partial_render_loads_back_unchanged() {
+        let mut saved = vec![];
+        partial().save(&mut saved).unwrap();
+
+        let loaded = PartialRender::load(saved.as_slice()).unwrap();
+
+        assert_eq!(loaded, partial());
+        assert_eq!(loaded.image.pixel_at(0, 0).blue(), 0.123_456_79);
+    }
+
+    #[test]
+    fn loading_something_else() {
+        let result = PartialRender::load("P3\n2 3\n255\n".as_bytes());
+
+        assert!(matches!(result, Err(LoadError::NotAPartialRender)));
+    }
+
+    #[test]
+    fn loading_a_truncated_partial_render() {
+        let mut saved = vec![];
+        partial().save(&mut saved).unwrap();
+        saved.truncate(saved.len() - 10);
+
+        let result = PartialRender::load(saved.as_slice());
+
+        assert!(matches!(result, Err(LoadError::UnexpectedEnd)));
+    }
+
+    #[test]
+    fn loading_a_partial_render_with_a_corrupt_size() {
+        let overflowing = format!("rtc-partial\n{} 2\n0\n", usize::MAX);
+        let huge = "rtc-partial\n100000 100000\n0\n0 0 0\n";
+        let short = "rtc-partial\n4000 4000\n0\n0 0 0\n";
+        let rows = format!("rtc-partial\n2 3\n{}\n0\n", usize::MAX);
+
+        assert!(matches!(
+            PartialRender::load(overflowing.as_bytes()),
+            Err(LoadError::TooLarge(_, 2))
+        ));
+        assert!(matches!(
+            PartialRender::load(huge.as_bytes()),
+            Err(LoadError::TooLarge(100000, 100000))
+        ));
+        assert!(matches!(
+            PartialRender::load(short.as_bytes()),
+            Err(LoadError::UnexpectedEnd)
+        ));
+        assert!(matches!(
+            PartialRender::load(rows.as_bytes()),
+            Err(LoadError::UnexpectedEnd)
+        ));
+    }
+
+    #[test]
+    fn loading_a_partial_render_with_a_row_outside_the_image() {
+        let result = PartialRender::load("rtc-partial\n2 3\n1\n3\n".as_bytes());
+
+        assert!(matches!(result, Err(LoadError::RowOutOfRange(3))));
+    }
+}
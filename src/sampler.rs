@@ -0,0 +1,771 @@
+use std::{cell::Cell, fmt::Debug};
+
+/// A source of numbers in [0, 1) for stochastic rendering. Samplers are
+/// deterministic, so a render can be repeated exactly from the same seed,
+/// and tests can feed in canned values.
+pub trait Sampler: Debug {
+    fn next_f32(&self) -> f32;
+
+    /// A value in [`min`, `max`).
+    fn next_in(&self, min: f32, max: f32) -> f32 {
+        min + (max - min) * self.next_f32()
+    }
+
+    /// A point in the unit square, for sampling surfaces and directions.
+    /// Samplers that spread points out in two dimensions override it.
+    fn next_2d(&self) -> (f32, f32) {
+        let u = self.next_f32();
+        (u, self.next_f32())
+    }
+
+    /// Starts over on the stream picked out by `seed`, as each pixel does
+    /// with its `pixel_seed`. Canned samplers just start from the top.
+    fn reseed(&self, seed: u64);
+
+    /// Moves on to sample `index` of the current pixel, with its dimensions
+    /// starting over from the first. Samplers that spread each dimension
+    /// across a pixel's samples need it; the rest carry on as they were.
+    fn start_sample(&self, _index: u32) {}
+}
+
+/// Which sampler to draw numbers from, as a render setting.
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum SamplerKind {
+    /// Independent random numbers from `Xorshift`.
+    #[default]
+    Random,
+    /// `Stratified`, with this many cells a side.
+    Stratified(usize),
+    /// `BlueNoise`, with this many points.
+    BlueNoise(usize),
+    Halton,
+}
+
+impl SamplerKind {
+    pub fn sampler(self, seed: u64) -> Box<dyn Sampler> {
+        match self {
+            Self::Random => Box::new(Xorshift::new(seed)),
+            Self::Stratified(n) => Box::new(Stratified::new(n, seed)),
+            Self::BlueNoise(count) => Box::new(BlueNoise::new(count, seed)),
+            Self::Halton => {
+                let halton = Halton::new();
+                halton.reseed(seed);
+                Box::new(halton)
+            }
+        }
+    }
+}
+
+/// The seed for one pass over the pixel at `x`, `y`, scrambled from the
+/// render's `seed`. Renderers start every pixel on its own seed, so the
+/// numbers a pixel draws don't depend on which pixels were rendered before
+/// it, and the image comes out the same whatever order, tiles or threads
+/// it's rendered in.
+pub fn pixel_seed(seed: u64, x: usize, y: usize, pass: u32) -> u64 {
+    [x as u64, y as u64, pass as u64]
+        .iter()
+        .fold(mix(seed), |h, &v| mix_in(h, v))
+}
+
+/// SplitMix64's finalizer, which scrambles every bit of `z` into every
+/// other.
+fn mix(mut z: u64) -> u64 {
+    z = (z ^ (z >> 30)).wrapping_mul(0xbf58_476d_1ce4_e5b9);
+    z = (z ^ (z >> 27)).wrapping_mul(0x94d0_49bb_1331_11eb);
+    z ^ (z >> 31)
+}
+
+/// Folds `value` into the hash `h`.
+fn mix_in(h: u64, value: u64) -> u64 {
+    mix(h ^ value.wrapping_add(0x9e37_79b9_7f4a_7c15))
+}
+
+/// Where `index` lands in a shuffle of 0 up to `len` picked out by `key`:
+/// Kensler's hashed permutation, so each key shuffles without storing it.
+fn permute(mut index: u32, len: u32, key: u32) -> u32 {
+    let mut w = len - 1;
+    w |= w >> 1;
+    w |= w >> 2;
+    w |= w >> 4;
+    w |= w >> 8;
+    w |= w >> 16;
+    loop {
+        index ^= key;
+        index = index.wrapping_mul(0xe170_893d);
+        index ^= key >> 16;
+        index ^= (index & w) >> 4;
+        index ^= key >> 8;
+        index = index.wrapping_mul(0x0929_eb3f);
+        index ^= key >> 23;
+        index ^= (index & w) >> 1;
+        index = index.wrapping_mul(1 | key >> 27);
+        index = index.wrapping_mul(0x6935_fa69);
+        index ^= (index & w) >> 11;
+        index = index.wrapping_mul(0x74dc_b303);
+        index ^= (index & w) >> 2;
+        index = index.wrapping_mul(0x9e50_1cc3);
+        index ^= (index & w) >> 2;
+        index = index.wrapping_mul(0xc860_a3df);
+        index &= w;
+        index ^= index >> 5;
+        if index < len {
+            return (index + key) % len;
+        }
+    }
+}
+
+/// Marsaglia's xorshift generator: fast, seedable and good enough for
+/// picking sample points.
+#[derive(Clone, Debug)]
+pub struct Xorshift {
+    seed: Cell<u64>,
+    state: Cell<u64>,
+}
+
+impl Xorshift {
+    pub fn new(seed: u64) -> Self {
+        Self {
+            seed: Cell::new(seed),
+            state: Cell::new(seed.max(1)),
+        }
+    }
+}
+
+impl Sampler for Xorshift {
+    fn next_f32(&self) -> f32 {
+        let mut x = self.state.get();
+        x ^= x << 13;
+        x ^= x >> 7;
+        x ^= x << 17;
+        self.state.set(x);
+        (x >> 40) as f32 / (1u64 << 24) as f32
+    }
+
+    fn reseed(&self, seed: u64) {
+        self.seed.set(seed);
+        self.state.set(seed.max(1));
+    }
+
+    /// Each sample draws from a stream of its own, hashed from the seed.
+    fn start_sample(&self, index: u32) {
+        self.state
+            .set(mix_in(mix(self.seed.get()), index as u64).max(1));
+    }
+}
+
+/// The Halton sequence: the radical inverses of 0, 1, 2, ... in base 2,
+/// paired with base 3 for points. Every run of it, from the start, covers
+/// the unit square evenly without any randomness, so samples can keep
+/// being added until an image looks good enough.
+#[derive(Clone, Debug, Default)]
+pub struct Halton {
+    start: Cell<u32>,
+    index: Cell<u32>,
+}
+
+impl Halton {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    fn advance(&self) -> u32 {
+        let index = self.index.get();
+        self.index.set(index.wrapping_add(1));
+        index
+    }
+}
+
+impl Sampler for Halton {
+    fn next_f32(&self) -> f32 {
+        radical_inverse(self.advance(), 2)
+    }
+
+    fn next_2d(&self) -> (f32, f32) {
+        let index = self.advance();
+        (radical_inverse(index, 2), radical_inverse(index, 3))
+    }
+
+    /// Jumps to a point along the sequence picked by `seed`.
+    fn reseed(&self, seed: u64) {
+        let start = (seed >> 32) as u32 ^ seed as u32;
+        self.start.set(start);
+        self.index.set(start);
+    }
+
+    /// Each sample starts on its own point, counting on from the seed's.
+    fn start_sample(&self, index: u32) {
+        self.index.set(self.start.get().wrapping_add(index));
+    }
+}
+
+/// `index` written in `base` and mirrored about the point: 6 is 110 in
+/// binary, giving 0.011, or 0.375.
+pub fn radical_inverse(mut index: u32, base: u32) -> f32 {
+    let mut inverse = 0.0;
+    let mut digit_value = 1.0 / base as f32;
+    while index > 0 {
+        inverse += digit_value * (index % base) as f32;
+        index /= base;
+        digit_value /= base as f32;
+    }
+    inverse
+}
+
+/// Jittered sampling: the unit square is split into `n` by `n` cells and
+/// each run of `n * n` samples puts one at a random spot in every cell, so
+/// even a handful of samples can't all clump together. Single values are
+/// stratified the same way over `n * n` slices of [0, 1). Every dimension
+/// of a sample visits the cells in its own shuffled order, so the strata
+/// one dimension lands in say nothing about another's.
+#[derive(Clone, Debug)]
+pub struct Stratified {
+    n: usize,
+    jitter: Xorshift,
+    key: Cell<u64>,
+    sample: Cell<u32>,
+    dimension: Cell<u64>,
+}
+
+impl Stratified {
+    pub fn new(n: usize, seed: u64) -> Self {
+        Self {
+            n: n.max(1),
+            jitter: Xorshift::new(seed),
+            key: Cell::new(seed),
+            sample: Cell::new(0),
+            dimension: Cell::new(0),
+        }
+    }
+
+    fn cells(&self) -> usize {
+        self.n * self.n
+    }
+
+    /// The cell the current sample takes in its next dimension.
+    fn next_cell(&self) -> usize {
+        let dimension = self.dimension.get();
+        self.dimension.set(dimension + 1);
+        let cells = self.cells() as u32;
+        let key = mix_in(mix(self.key.get()), dimension) as u32;
+        permute(self.sample.get() % cells, cells, key) as usize
+    }
+}
+
+impl Sampler for Stratified {
+    fn next_f32(&self) -> f32 {
+        let cell = self.next_cell();
+        (cell as f32 + self.jitter.next_f32()) / self.cells() as f32
+    }
+
+    fn next_2d(&self) -> (f32, f32) {
+        let cell = self.next_cell();
+        let (i, j) = (cell % self.n, cell / self.n);
+        let n = self.n as f32;
+        (
+            (i as f32 + self.jitter.next_f32()) / n,
+            (j as f32 + self.jitter.next_f32()) / n,
+        )
+    }
+
+    /// Shuffles the cells afresh for `seed`, as well as the jitter, so the
+    /// first values every pixel draws aren't all from the same corner.
+    fn reseed(&self, seed: u64) {
+        self.jitter.reseed(seed);
+        self.key.set(seed);
+        self.sample.set(0);
+        self.dimension.set(0);
+    }
+
+    fn start_sample(&self, index: u32) {
+        self.jitter.start_sample(index);
+        self.sample.set(index);
+        self.dimension.set(0);
+    }
+}
+
+/// Blue noise: points in the unit square kept well apart from one another,
+/// found with Mitchell's best-candidate algorithm. Each new point is the
+/// one of a growing batch of random candidates farthest from those already
+/// placed, measured around the wrapped edges so tiles of it join up. Sample
+/// `i` takes the point `i` places along from where each dimension starts,
+/// and every dimension starts somewhere of its own and shifts the points
+/// around the wrapped square by its own amount, so the dimensions are each
+/// as well spread as the points but unrelated to one another. Single values
+/// are the x of a dimension's point.
+#[derive(Clone, Debug)]
+pub struct BlueNoise {
+    points: Vec<(f32, f32)>,
+    key: Cell<u64>,
+    sample: Cell<u32>,
+    dimension: Cell<u64>,
+}
+
+impl BlueNoise {
+    const CANDIDATES: usize = 8;
+
+    pub fn new(count: usize, seed: u64) -> Self {
+        let count = count.max(1);
+        let rng = Xorshift::new(seed);
+        let mut placed = PointGrid::new(count);
+        let mut points: Vec<(f32, f32)> = Vec::with_capacity(count);
+        for _ in 0..count {
+            let candidates = Self::CANDIDATES * points.len() + 1;
+            let best = (0..candidates)
+                .map(|_| (rng.next_f32(), rng.next_f32()))
+                .map(|candidate| (candidate, placed.nearest_distance_squared(candidate)))
+                .fold(None, |best: Option<((f32, f32), f32)>, next| match best {
+                    Some(b) if b.1 >= next.1 => Some(b),
+                    _ => Some(next),
+                })
+                .map(|(candidate, _)| candidate)
+                .unwrap();
+            placed.insert(best);
+            points.push(best);
+        }
+
+        Self {
+            points,
+            key: Cell::new(seed),
+            sample: Cell::new(0),
+            dimension: Cell::new(0),
+        }
+    }
+
+    pub fn points(&self) -> &[(f32, f32)] {
+        &self.points
+    }
+}
+
+impl Sampler for BlueNoise {
+    fn next_f32(&self) -> f32 {
+        self.next_2d().0
+    }
+
+    fn next_2d(&self) -> (f32, f32) {
+        let dimension = self.dimension.get();
+        self.dimension.set(dimension + 1);
+        let h = mix_in(mix(self.key.get()), dimension);
+        let len = self.points.len() as u64;
+        let i = (self.sample.get() as u64 + h % len) % len;
+        let shift = |bits: u64| (bits >> 40) as f32 / (1u64 << 24) as f32;
+        let (u, v) = self.points[i as usize];
+        ((u + shift(h)).fract(), (v + shift(mix(h))).fract())
+    }
+
+    /// Moves every dimension to a start and shift picked by `seed`,
+    /// keeping the same points.
+    fn reseed(&self, seed: u64) {
+        self.key.set(seed);
+        self.start_sample(0);
+    }
+
+    fn start_sample(&self, index: u32) {
+        self.sample.set(index);
+        self.dimension.set(0);
+    }
+}
+
+/// Points filed by which cell of a grid over the unit square they're in,
+/// about one per cell once full, so the nearest to any spot is found by
+/// looking only at the cells around it.
+struct PointGrid {
+    size: usize,
+    cells: Vec<Vec<(f32, f32)>>,
+}
+
+impl PointGrid {
+    fn new(count: usize) -> Self {
+        let size = (count as f32).sqrt().ceil().max(1.0) as usize;
+        Self {
+            size,
+            cells: vec![vec![]; size * size],
+        }
+    }
+
+    fn cell_of(&self, p: (f32, f32)) -> (usize, usize) {
+        let index = |v: f32| ((v * self.size as f32) as usize).min(self.size - 1);
+        (index(p.0), index(p.1))
+    }
+
+    fn insert(&mut self, p: (f32, f32)) {
+        let (x, y) = self.cell_of(p);
+        self.cells[x + y * self.size].push(p);
+    }
+
+    /// The squared distance from `p` to the nearest point, around the
+    /// wrapped edges, searching rings of cells outwards until no nearer
+    /// point could be further out. Infinite with no points at all.
+    fn nearest_distance_squared(&self, p: (f32, f32)) -> f32 {
+        let (cx, cy) = self.cell_of(p);
+        let size = self.size as isize;
+        let cell = 1.0 / self.size as f32;
+        let mut nearest = f32::INFINITY;
+        for ring in 0..=size / 2 {
+            for dy in -ring..=ring {
+                for dx in -ring..=ring {
+                    if dx.abs() != ring && dy.abs() != ring {
+                        continue;
+                    }
+                    let x = (cx as isize + dx).rem_euclid(size) as usize;
+                    let y = (cy as isize + dy).rem_euclid(size) as usize;
+                    for &q in &self.cells[x + y * self.size] {
+                        nearest = nearest.min(toroidal_distance_squared(p, q));
+                    }
+                }
+            }
+            let beyond = ring as f32 * cell;
+            if nearest <= beyond * beyond {
+                break;
+            }
+        }
+        nearest
+    }
+}
+
+fn toroidal_distance_squared(a: (f32, f32), b: (f32, f32)) -> f32 {
+    let wrap = |d: f32| d.abs().min(1.0 - d.abs());
+    wrap(a.0 - b.0).powi(2) + wrap(a.1 - b.1).powi(2)
+}
+
+/// Hands out a fixed list of values, starting over once it runs out. The
+/// list can't be empty.
+#[derive(Clone, Debug)]
+pub struct Sequence {
+    values: Vec<f32>,
+    next: Cell<usize>,
+}
+
+impl Sequence {
+    pub fn new(values: Vec<f32>) -> Self {
+        assert!(!values.is_empty(), "a sequence needs at least one value");
+        Self {
+            values,
+            next: Cell::new(0),
+        }
+    }
+}
+
+impl Sampler for Sequence {
+    fn next_f32(&self) -> f32 {
+        let i = self.next.get();
+        self.next.set((i + 1) % self.values.len());
+        self.values[i]
+    }
+
+    fn reseed(&self, _seed: u64) {
+        self.next.set(0);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::float_eq;
+
+    use super::*;
+
+    #[test]
+    fn random_numbers_are_in_the_unit_interval() {
+        let rng = Xorshift::new(42);
+
+        for _ in 0..1000 {
+            let u = rng.next_f32();
+
+            assert!((0.0..1.0).contains(&u));
+        }
+    }
+
+    #[test]
+    fn the_same_seed_gives_the_same_numbers() {
+        let a = Xorshift::new(7);
+        let b = Xorshift::new(7);
+
+        for _ in 0..10 {
+            assert!(float_eq(a.next_f32(), b.next_f32()));
+        }
+    }
+
+    #[test]
+    fn reseeding_starts_a_fresh_stream() {
+        let rng = Xorshift::new(7);
+        rng.next_f32();
+
+        rng.reseed(3);
+
+        assert_eq!(rng.next_f32(), Xorshift::new(3).next_f32());
+    }
+
+    #[test]
+    fn each_sample_draws_from_a_stream_of_its_own() {
+        let rng = Xorshift::new(7);
+
+        rng.start_sample(1);
+        let first = rng.next_f32();
+        rng.start_sample(2);
+        let second = rng.next_f32();
+        rng.start_sample(1);
+
+        assert_eq!(rng.next_f32(), first);
+        assert_ne!(first, second);
+    }
+
+    #[test]
+    fn every_pixel_and_pass_gets_its_own_seed() {
+        let seeds = [
+            pixel_seed(1, 0, 0, 0),
+            pixel_seed(1, 1, 0, 0),
+            pixel_seed(1, 0, 1, 0),
+            pixel_seed(1, 0, 0, 1),
+            pixel_seed(2, 0, 0, 0),
+        ];
+
+        for (i, a) in seeds.iter().enumerate() {
+            assert!(seeds[i + 1..].iter().all(|b| a != b));
+        }
+        assert_eq!(pixel_seed(1, 4, 5, 6), pixel_seed(1, 4, 5, 6));
+    }
+
+    #[test]
+    fn a_sequence_returns_a_cycle_of_numbers() {
+        let gen = Sequence::new(vec![0.1, 0.5, 1.0]);
+
+        assert!(float_eq(gen.next_f32(), 0.1));
+        assert!(float_eq(gen.next_f32(), 0.5));
+        assert!(float_eq(gen.next_f32(), 1.0));
+        assert!(float_eq(gen.next_f32(), 0.1));
+    }
+
+    #[test]
+    #[should_panic(expected = "a sequence needs at least one value")]
+    fn a_sequence_cannot_be_empty() {
+        Sequence::new(vec![]);
+    }
+
+    #[test]
+    fn points_come_in_pairs_by_default() {
+        let gen = Sequence::new(vec![0.1, 0.5, 0.7]);
+
+        assert_eq!(gen.next_2d(), (0.1, 0.5));
+        assert_eq!(gen.next_2d(), (0.7, 0.1));
+    }
+
+    #[test]
+    fn stratified_points_fill_every_cell_once_per_run() {
+        let n = 4;
+        let sampler = Stratified::new(n, 11);
+        let mut cells = vec![0; n * n];
+
+        for sample in 0..n * n * 3 {
+            sampler.start_sample(sample as u32);
+            let (u, v) = sampler.next_2d();
+            assert!((0.0..1.0).contains(&u) && (0.0..1.0).contains(&v));
+            cells[(v * n as f32) as usize * n + (u * n as f32) as usize] += 1;
+        }
+
+        assert!(cells.iter().all(|&count| count == 3));
+    }
+
+    #[test]
+    fn stratified_values_fill_every_slice_once_per_run() {
+        let sampler = Stratified::new(3, 5);
+        let mut slices = [0; 9];
+
+        for sample in 0..9 {
+            sampler.start_sample(sample);
+            slices[(sampler.next_f32() * 9.0) as usize] += 1;
+        }
+
+        assert!(slices.iter().all(|&count| count == 1));
+    }
+
+    #[test]
+    fn each_dimension_of_a_stratified_sampler_has_its_own_strata() {
+        let sampler = Stratified::new(4, 5);
+        let (mut first, mut second) = (vec![], vec![]);
+
+        for sample in 0..16 {
+            sampler.start_sample(sample);
+            first.push((sampler.next_f32() * 16.0) as usize);
+            second.push((sampler.next_f32() * 16.0) as usize);
+        }
+
+        let mut sorted = second.clone();
+        sorted.sort();
+        assert_eq!(sorted, (0..16).collect::<Vec<_>>());
+        assert!(first
+            .iter()
+            .zip(&second)
+            .any(|(&a, &b)| b != a && b != (a + 1) % 16));
+    }
+
+    #[test]
+    fn reseeding_a_stratified_sampler_moves_its_first_stratum() {
+        let n = 4;
+        let sampler = Stratified::new(n, 1);
+        let mut first_cells = vec![0; n * n];
+
+        for pixel in 0..64 {
+            sampler.reseed(pixel_seed(1, pixel, 0, 0));
+            let (u, v) = sampler.next_2d();
+            first_cells[(v * n as f32) as usize * n + (u * n as f32) as usize] += 1;
+        }
+
+        assert!(first_cells.iter().filter(|&&count| count > 0).count() > n);
+    }
+
+    #[test]
+    fn a_one_by_one_stratified_sampler_is_plain_random() {
+        let sampler = Stratified::new(0, 7);
+        let rng = Xorshift::new(7);
+
+        assert!(float_eq(sampler.next_f32(), rng.next_f32()));
+    }
+
+    macro_rules! radical_inverse_tests {
+        ($($name:ident: $value:expr,)*) => {
+            $(
+                #[test]
+                fn $name() {
+                    let (index, base, expected) = $value;
+
+                    assert!(float_eq(radical_inverse(index, base), expected));
+                }
+            )*
+        }
+    }
+
+    radical_inverse_tests! {
+        the_radical_inverse_of_zero_is_zero: (0, 2, 0.0),
+        the_radical_inverse_of_one_in_base_two: (1, 2, 0.5),
+        the_radical_inverse_of_six_in_base_two: (6, 2, 0.375),
+        the_radical_inverse_of_five_in_base_three: (5, 3, 2.0 / 3.0 + 1.0 / 9.0),
+    }
+
+    #[test]
+    fn halton_points_pair_bases_two_and_three() {
+        let halton = Halton::new();
+
+        assert_eq!(halton.next_2d(), (0.0, 0.0));
+        assert_eq!(halton.next_2d(), (0.5, 1.0 / 3.0));
+        assert_eq!(halton.next_2d(), (0.25, 2.0 / 3.0));
+        assert!(float_eq(halton.next_f32(), 0.75));
+    }
+
+    #[test]
+    fn every_run_of_halton_points_covers_the_square_evenly() {
+        let halton = Halton::new();
+        let mut cells = [0; 6];
+
+        for _ in 0..6 {
+            let (u, v) = halton.next_2d();
+            cells[(v * 3.0) as usize * 2 + (u * 2.0) as usize] += 1;
+        }
+
+        assert!(cells.iter().all(|&count| count == 1));
+    }
+
+    fn closest_pair(points: &[(f32, f32)]) -> f32 {
+        let mut closest = f32::INFINITY;
+        for (i, &a) in points.iter().enumerate() {
+            for &b in &points[i + 1..] {
+                closest = closest.min(toroidal_distance_squared(a, b).sqrt());
+            }
+        }
+        closest
+    }
+
+    #[test]
+    fn blue_noise_points_stay_apart() {
+        let count = 32;
+        let blue = BlueNoise::new(count, 3);
+        let rng = Xorshift::new(3);
+        let random = (0..count)
+            .map(|_| (rng.next_f32(), rng.next_f32()))
+            .collect::<Vec<_>>();
+
+        assert_eq!(blue.points().len(), count);
+        assert!(closest_pair(blue.points()) > 2.0 * closest_pair(&random));
+    }
+
+    #[test]
+    fn the_grid_finds_the_nearest_point_around_the_edges() {
+        let rng = Xorshift::new(5);
+        let mut grid = PointGrid::new(50);
+        let mut points = vec![];
+        for _ in 0..50 {
+            let p = (rng.next_f32(), rng.next_f32());
+            grid.insert(p);
+            points.push(p);
+        }
+
+        for _ in 0..100 {
+            let p = (rng.next_f32(), rng.next_f32());
+            let nearest = points
+                .iter()
+                .map(|&q| toroidal_distance_squared(p, q))
+                .fold(f32::INFINITY, f32::min);
+
+            assert_eq!(grid.nearest_distance_squared(p), nearest);
+        }
+        assert_eq!(
+            PointGrid::new(4).nearest_distance_squared((0.5, 0.5)),
+            f32::INFINITY
+        );
+    }
+
+    #[test]
+    fn every_dimension_of_blue_noise_is_as_spread_out_as_its_points() {
+        let count = 32;
+        let blue = BlueNoise::new(count, 9);
+        let (mut first, mut second) = (vec![], vec![]);
+
+        for sample in 0..count as u32 {
+            blue.start_sample(sample);
+            first.push(blue.next_2d());
+            second.push(blue.next_2d());
+        }
+
+        let spread = closest_pair(blue.points());
+        assert!((closest_pair(&first) - spread).abs() < 1e-4);
+        assert!((closest_pair(&second) - spread).abs() < 1e-4);
+        assert_ne!(first, second);
+    }
+
+    #[test]
+    fn single_blue_noise_values_are_unrelated_to_points() {
+        let blue = BlueNoise::new(64, 9);
+        let mut cells = [false; 16];
+
+        for sample in 0..64 {
+            blue.start_sample(sample);
+            let (u, _) = blue.next_2d();
+            let w = blue.next_f32();
+            cells[(w * 4.0) as usize * 4 + (u * 4.0) as usize] = true;
+        }
+
+        assert!(cells.iter().filter(|&&hit| hit).count() > 8);
+    }
+
+    #[test]
+    fn each_kind_of_sampler_starts_on_its_seed() {
+        for kind in [
+            SamplerKind::Random,
+            SamplerKind::Stratified(4),
+            SamplerKind::BlueNoise(16),
+            SamplerKind::Halton,
+        ] {
+            let (a, b) = (kind.sampler(3), kind.sampler(3));
+
+            assert_eq!(a.next_2d(), b.next_2d());
+            assert_ne!(kind.sampler(3).next_2d(), kind.sampler(4).next_2d());
+        }
+    }
+
+    #[test]
+    fn sampling_a_range() {
+        let gen = Sequence::new(vec![0.0, 0.5]);
+
+        assert!(float_eq(gen.next_in(-2.0, 2.0), -2.0));
+        assert!(float_eq(gen.next_in(-2.0, 2.0), 0.0));
+    }
+}
@@ -0,0 +1,540 @@
+use std::sync::Arc;
+
+use serde::{ser::Error, Deserialize, Deserializer, Serialize, Serializer};
+
+use crate::{
+    color::Color,
+    materials::Material,
+    patterns::{
+        checkered::Checkered,
+        gradient::Gradient,
+        grid::Grid,
+        noise::Noise,
+        ring::Ring,
+        striped::Striped,
+        uv::{CubeMap, TextureMap},
+        Pattern,
+    },
+    shapes::{
+        cone::Cone,
+        cube::Cube,
+        cylinder::Cylinder,
+        deformed::{Deformation, DeformedShape},
+        group::Group,
+        instance::Instance,
+        plane::Plane,
+        quad::Quad,
+        sphere::Sphere,
+        triangle::Triangle,
+        Shape, ShapeBuilder, Tags, Visibility,
+    },
+    transformations::Transform,
+    tuple::{Point, Vector},
+};
+
+/// Saves trait objects, and loads them boxed, as whichever concrete type
+/// they hold, tagged with its name, e.g. `{"Sphere": {...}}` in JSON.
+/// Loaded objects are boxed straight away, so the biggest kind doesn't set
+/// the size of every other.
+macro_rules! serialize_by_type {
+    ($object:ty, $borrowed:ident, $owned:ident, $($kind:ident),*) => {
+        #[derive(Serialize)]
+        enum $borrowed<'a> {
+            $($kind(&'a $kind)),*
+        }
+
+        #[derive(Deserialize)]
+        enum $owned {
+            $($kind(Box<$kind>)),*
+        }
+
+        impl Serialize for $object {
+            fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+                let any = self.as_any();
+                $(
+                    if let Some(object) = any.downcast_ref::<$kind>() {
+                        return $borrowed::$kind(object).serialize(serializer);
+                    }
+                )*
+                Err(S::Error::custom(format!("can't save {:?}", self)))
+            }
+        }
+
+        impl<'de> Deserialize<'de> for Box<$object> {
+            fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+                Ok(match $owned::deserialize(deserializer)? {
+                    $($owned::$kind(object) => object),*
+                })
+            }
+        }
+    };
+}
+
+serialize_by_type!(
+    dyn Shape,
+    BorrowedShape,
+    OwnedShape,
+    Sphere,
+    Plane,
+    Cube,
+    Cylinder,
+    Cone,
+    Triangle,
+    Quad,
+    Group,
+    Instance,
+    DeformedShape
+);
+
+serialize_by_type!(
+    dyn Pattern,
+    BorrowedPattern,
+    OwnedPattern,
+    Checkered,
+    CubeMap,
+    Gradient,
+    Grid,
+    Noise,
+    Ring,
+    Striped,
+    TextureMap
+);
+
+/// A saved triangle. Only its corners are stored; the edges and normal
+/// are worked out again when it's loaded.
+#[derive(Deserialize)]
+pub struct TriangleFields {
+    p1: Point,
+    p2: Point,
+    p3: Point,
+    #[serde(default)]
+    material: Material,
+    #[serde(default)]
+    transform: Transform,
+    #[serde(default)]
+    visibility: Visibility,
+    #[serde(default)]
+    colors: Option<[Color; 3]>,
+    #[serde(default)]
+    normals: Option<[Vector; 3]>,
+    #[serde(default)]
+    tags: Tags,
+}
+
+impl From<TriangleFields> for Triangle {
+    fn from(fields: TriangleFields) -> Self {
+        let mut triangle = Triangle::new(fields.p1, fields.p2, fields.p3)
+            .with_material(fields.material)
+            .with_transform(fields.transform)
+            .with_visibility(fields.visibility);
+        triangle.colors = fields.colors;
+        triangle.normals = fields.normals;
+        *triangle.tags_mut() = fields.tags;
+        triangle
+    }
+}
+
+/// A saved quad, whose normal is worked out again when it's loaded.
+#[derive(Deserialize)]
+pub struct QuadFields {
+    origin: Point,
+    uvec: Vector,
+    vvec: Vector,
+    #[serde(default)]
+    material: Material,
+    #[serde(default)]
+    transform: Transform,
+    #[serde(default)]
+    visibility: Visibility,
+    #[serde(default)]
+    tags: Tags,
+}
+
+impl From<QuadFields> for Quad {
+    fn from(fields: QuadFields) -> Self {
+        let mut quad = Quad::new(fields.origin, fields.uvec, fields.vvec)
+            .with_material(fields.material)
+            .with_transform(fields.transform)
+            .with_visibility(fields.visibility);
+        *quad.tags_mut() = fields.tags;
+        quad
+    }
+}
+
+/// A saved group. Its children are added back one by one, so each knows
+/// its parent again.
+#[derive(Default, Deserialize)]
+#[serde(default)]
+pub struct GroupFields {
+    visibility: Visibility,
+    transform: Transform,
+    material: Material,
+    tags: Tags,
+    objects: Vec<Box<dyn Shape>>,
+}
+
+impl From<GroupFields> for Group {
+    fn from(fields: GroupFields) -> Self {
+        let mut group = Group::new()
+            .with_material(fields.material)
+            .with_transform(fields.transform)
+            .with_visibility(fields.visibility);
+        *group.tags_mut() = fields.tags;
+        for child in fields.objects {
+            group.add_child(child);
+        }
+        group
+    }
+}
+
+/// Saves the shape an instance shares as the shape itself.
+pub fn serialize_shared<S: Serializer>(
+    shape: &Arc<dyn Shape>,
+    serializer: S,
+) -> Result<S::Ok, S::Error> {
+    shape.as_ref().serialize(serializer)
+}
+
+/// A saved instance. Each is loaded with a shape of its own, so instances
+/// that shared one before saving no longer share it after loading.
+#[derive(Deserialize)]
+pub struct InstanceFields {
+    shape: Box<dyn Shape>,
+    #[serde(default)]
+    material: Option<Material>,
+    #[serde(default)]
+    transform: Transform,
+    #[serde(default)]
+    visibility: Visibility,
+    #[serde(default)]
+    tags: Tags,
+}
+
+impl From<InstanceFields> for Instance {
+    fn from(fields: InstanceFields) -> Self {
+        let mut instance = Instance::new(Arc::from(fields.shape))
+            .with_transform(fields.transform)
+            .with_visibility(fields.visibility);
+        if let Some(material) = fields.material {
+            instance = instance.with_material(material);
+        }
+        *instance.tags_mut() = fields.tags;
+        instance
+    }
+}
+
+/// A saved deformed shape, whose bounds are worked out again when it's
+/// loaded.
+#[derive(Deserialize)]
+pub struct DeformedShapeFields {
+    shape: Box<dyn Shape>,
+    deformation: Deformation,
+    steps: usize,
+    #[serde(default)]
+    transform: Transform,
+    #[serde(default)]
+    visibility: Visibility,
+    #[serde(default)]
+    tags: Tags,
+}
+
+impl From<DeformedShapeFields> for DeformedShape {
+    fn from(fields: DeformedShapeFields) -> Self {
+        let mut shape = DeformedShape::new(fields.shape, fields.deformation)
+            .steps(fields.steps)
+            .with_transform(fields.transform)
+            .with_visibility(fields.visibility);
+        *shape.tags_mut() = fields.tags;
+        shape
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{
+        camera::{Camera, Exposure},
+        color::{self, Color},
+        lights::PointLight,
+        patterns::uv::UvChecker,
+        ray::Ray,
+        registry::Registry,
+        shapes::TestShape,
+        snapshot::ObjectSnapshot,
+        world::World,
+    };
+
+    use super::*;
+
+    fn round_trip<T: Serialize + for<'de> Deserialize<'de>>(value: &T) -> T {
+        serde_json::from_str(&serde_json::to_string(value).unwrap()).unwrap()
+    }
+
+    #[test]
+    fn points_colors_and_transforms_are_saved_as_arrays() {
+        assert_eq!(
+            serde_json::to_string(&Point::new(1.0, 2.0, 3.0)).unwrap(),
+            "[1.0,2.0,3.0]"
+        );
+        assert_eq!(
+            serde_json::to_string(&Color::new(0.5, 0.25, 1.0)).unwrap(),
+            "[0.5,0.25,1.0]"
+        );
+        assert_eq!(
+            serde_json::to_string(&Transform::translation(1.0, 2.0, 3.0)).unwrap(),
+            "[[1.0,0.0,0.0,1.0],[0.0,1.0,0.0,2.0],[0.0,0.0,1.0,3.0],[0.0,0.0,0.0,1.0]]"
+        );
+    }
+
+    #[test]
+    fn saving_and_loading_a_material_with_a_pattern() {
+        let m = Material::default()
+            .reflective(0.5)
+            .pattern(Box::new(Checkered::new(color::WHITE, color::BLACK)));
+
+        assert_eq!(round_trip(&m), m);
+    }
+
+    #[test]
+    fn saving_and_loading_a_cube_map() {
+        let checkers = UvChecker::default();
+        let m = Material::default().pattern(Box::new(CubeMap::new(
+            checkers, checkers, checkers, checkers, checkers, checkers,
+        )));
+
+        assert_eq!(round_trip(&m), m);
+    }
+
+    #[test]
+    fn missing_material_fields_take_their_defaults() {
+        let m: Material = serde_json::from_str(r#"{"diffuse": 0.5}"#).unwrap();
+
+        assert_eq!(m, Material::default().diffuse(0.5));
+    }
+
+    #[test]
+    fn saving_and_loading_a_registry() {
+        let mut registry = Registry::presets();
+        registry.define_transform("raised", vec![Transform::translation(0.0, 1.0, 0.0)]);
+        registry.light_group("key");
+
+        assert_eq!(round_trip(&registry), registry);
+        let loaded: Registry = serde_json::from_str(
+            r#"{"materials": {"copper": {"color": [0.95, 0.64, 0.54], "reflective": 0.3}}}"#,
+        )
+        .unwrap();
+        assert_eq!(
+            loaded.material("copper"),
+            Some(
+                Material::default()
+                    .color(Color::new(0.95, 0.64, 0.54))
+                    .reflective(0.3)
+            )
+        );
+    }
+
+    #[test]
+    fn saving_and_loading_a_world_snapshot() {
+        let snapshot = World::default().snapshot();
+
+        assert_eq!(round_trip(&snapshot), snapshot);
+    }
+
+    #[test]
+    fn saving_and_loading_a_light() {
+        let light = PointLight::new(Point::new(-10.0, 10.0, -10.0), color::WHITE);
+
+        assert_eq!(round_trip(&light), light);
+    }
+
+    #[test]
+    fn saving_and_loading_a_camera() {
+        let c = Camera::new(160, 120, 1.0)
+            .transform(Transform::translation(0.0, 1.0, -5.0))
+            .exposure(Exposure::Auto(0.18));
+        let saved = serde_json::to_string(&c).unwrap();
+
+        let loaded: Camera = serde_json::from_str(&saved).unwrap();
+
+        assert_eq!(serde_json::to_string(&loaded).unwrap(), saved);
+    }
+
+    #[test]
+    fn saving_and_loading_shapes_of_every_kind() {
+        let mut g = Group::new().with_transform(Transform::translation(0.0, 1.0, 0.0));
+        g.add_child(Box::new(Cylinder::default().with_caps(0.0, 2.0).bevel(0.1)));
+        g.add_child(Box::new(Triangle::new(
+            Point::new(0.0, 1.0, 0.0),
+            Point::new(-1.0, 0.0, 0.0),
+            Point::new(1.0, 0.0, 0.0),
+        )));
+        let shapes: Vec<Box<dyn Shape>> = vec![
+            Box::new(Sphere::glass()),
+            Box::new(Plane::default()),
+            Box::new(Cube::default()),
+            Box::new(Cone::default().with_caps(-1.0, 0.0)),
+            Box::new(Quad::default()),
+            Box::new(g),
+        ];
+
+        let loaded = round_trip(&shapes);
+
+        assert_eq!(loaded.len(), shapes.len());
+        for (a, b) in shapes.iter().zip(loaded.iter()) {
+            assert_eq!(
+                ObjectSnapshot::from_shape(a.as_ref()),
+                ObjectSnapshot::from_shape(b.as_ref())
+            );
+        }
+        assert_eq!(loaded[3].bounds(), shapes[3].bounds());
+        assert_eq!(
+            loaded[5].children()[0].bounds(),
+            shapes[5].children()[0].bounds()
+        );
+    }
+
+    #[test]
+    fn saving_and_loading_an_instance() {
+        let shared: Arc<dyn Shape> = Arc::new(
+            Sphere::default()
+                .with_transform(Transform::scaling(2.0, 2.0, 2.0))
+                .with_material(Material::default().diffuse(0.3)),
+        );
+        let red = Instance::new(shared.clone())
+            .with_transform(Transform::translation(5.0, 0.0, 0.0))
+            .with_material(Material::default().color(Color::new(1.0, 0.0, 0.0)))
+            .with_tag("copy", "red");
+        let shapes: Vec<Box<dyn Shape>> = vec![Box::new(Instance::new(shared)), Box::new(red)];
+
+        let loaded = round_trip(&shapes);
+
+        for (a, b) in shapes.iter().zip(loaded.iter()) {
+            assert_eq!(
+                ObjectSnapshot::from_shape(a.as_ref()),
+                ObjectSnapshot::from_shape(b.as_ref())
+            );
+            assert_eq!(a.material(), b.material());
+            assert_eq!(a.bounds(), b.bounds());
+        }
+        assert_eq!(loaded[1].tag("copy"), Some("red"));
+    }
+
+    #[test]
+    fn saving_and_loading_a_deformed_shape() {
+        let s: Box<dyn Shape> = Box::new(
+            DeformedShape::new(
+                Box::new(Cube::default().with_material(Material::default().diffuse(0.3))),
+                Deformation::Twist(1.0),
+            )
+            .steps(12)
+            .with_transform(Transform::translation(0.0, 1.0, 0.0))
+            .with_tag("role", "column"),
+        );
+        let r = Ray::new(Point::new(0.2, 1.5, -5.0), Vector::new(0.0, 0.0, 1.0));
+
+        let loaded = round_trip(&s);
+
+        assert_eq!(
+            ObjectSnapshot::from_shape(loaded.as_ref()),
+            ObjectSnapshot::from_shape(s.as_ref())
+        );
+        assert_eq!(loaded.bounds(), s.bounds());
+        assert_eq!(loaded.material().diffuse, 0.3);
+        assert_eq!(loaded.tag("role"), Some("column"));
+        let (xs, expected) = (loaded.intersect(r), s.intersect(r));
+        assert_eq!(xs.len(), expected.len());
+        assert_eq!(xs[0].t, expected[0].t);
+    }
+
+    #[test]
+    fn saving_and_loading_a_shape_keeps_its_tags() {
+        let s: Box<dyn Shape> = Box::new(Sphere::default().with_tag("role", "hero"));
+
+        assert_eq!(round_trip(&s).tag("role"), Some("hero"));
+    }
+
+    #[test]
+    fn saving_and_loading_triangles_quads_and_groups_keeps_their_tags() {
+        let triangle = Triangle::new(
+            Point::new(0.0, 1.0, 0.0),
+            Point::new(-1.0, 0.0, 0.0),
+            Point::new(1.0, 0.0, 0.0),
+        )
+        .with_tag("role", "roof");
+        let quad = Quad::new(
+            Point::new(0.0, 0.0, 0.0),
+            Vector::new(1.0, 0.0, 0.0),
+            Vector::new(0.0, 1.0, 0.0),
+        )
+        .with_tag("role", "door");
+        let mut group = Group::new().with_tag("role", "house");
+        group.add_child(Box::new(triangle));
+        group.add_child(Box::new(quad));
+        let g: Box<dyn Shape> = Box::new(group);
+
+        let loaded = round_trip(&g);
+
+        assert_eq!(loaded.tag("role"), Some("house"));
+        assert_eq!(loaded.children()[0].tag("role"), Some("roof"));
+        assert_eq!(loaded.children()[1].tag("role"), Some("door"));
+    }
+
+    #[test]
+    fn loaded_shapes_get_new_ids() {
+        let s: Box<dyn Shape> = Box::new(Sphere::default());
+
+        assert_ne!(round_trip(&s).id(), s.id());
+    }
+
+    #[test]
+    fn loaded_children_know_their_group() {
+        let mut g = Group::new();
+        g.add_child(Box::new(Sphere::default()));
+        let g: Box<dyn Shape> = Box::new(g);
+
+        let loaded = round_trip(&g);
+
+        assert_eq!(loaded.children()[0].parent(), Some(loaded.id()));
+    }
+
+    #[test]
+    fn loading_a_triangle_works_out_its_normal() {
+        let t: Box<dyn Shape> = serde_json::from_str(
+            r#"{"Triangle": {"p1": [0, 1, 0], "p2": [-1, 0, 0], "p3": [1, 0, 0]}}"#,
+        )
+        .unwrap();
+
+        assert_eq!(
+            t.local_normal_at(Point::new(0.0, 0.5, 0.0), None),
+            Vector::new(0.0, 0.0, -1.0)
+        );
+    }
+
+    #[test]
+    fn saving_and_loading_a_triangle_keeps_its_vertex_colors() {
+        let t = Triangle::new(
+            Point::new(0.0, 1.0, 0.0),
+            Point::new(-1.0, 0.0, 0.0),
+            Point::new(1.0, 0.0, 0.0),
+        )
+        .with_vertex_colors([
+            Color::new(1.0, 0.0, 0.0),
+            Color::new(0.0, 1.0, 0.0),
+            Color::new(0.0, 0.0, 1.0),
+        ]);
+        let t: Box<dyn Shape> = Box::new(t);
+
+        let loaded = round_trip(&t);
+
+        assert_eq!(
+            loaded.as_any().downcast_ref::<Triangle>().unwrap().colors,
+            t.as_any().downcast_ref::<Triangle>().unwrap().colors
+        );
+    }
+
+    #[test]
+    fn shapes_without_a_saved_form_cant_be_saved() {
+        let s: Box<dyn Shape> = Box::new(TestShape::default());
+
+        assert!(serde_json::to_string(&s).is_err());
+    }
+}
@@ -0,0 +1,118 @@
+use crate::{float_eq, ray::Ray, tuple::Vector, EPSILON};
+
+/// Whether a ray crossing a cap's plane at `t` lands within `radius` of the
+/// y axis. The tolerance keeps rays through the rim from slipping out on
+/// rounding error.
+pub fn within_cap(ray: Ray, t: f32, radius: f32) -> bool {
+    let x = ray.origin.x() + t * ray.direction.x();
+    let z = ray.origin.z() + t * ray.direction.z();
+
+    x.powi(2) + z.powi(2) <= (radius.abs() + EPSILON).powi(2)
+}
+
+/// The `t` values at which a ray passes through the caps at `minimum` and
+/// `maximum`, where `radius` gives the cap's radius at a height.
+pub fn cap_hits(ray: Ray, minimum: f32, maximum: f32, radius: impl Fn(f32) -> f32) -> Vec<f32> {
+    if float_eq(ray.direction.y(), 0.0) {
+        return vec![];
+    }
+
+    [minimum, maximum]
+        .iter()
+        .map(|&y| ((y - ray.origin.y()) / ray.direction.y(), radius(y)))
+        .filter(|&(t, r)| within_cap(ray, t, r))
+        .map(|(t, _)| t)
+        .collect()
+}
+
+/// Bends the normal `own` of a point `distance` from where a cap meets the
+/// side towards `other`, the normal on the far side of the seam. Right at
+/// the seam both surfaces give the same halfway normal, and `width` away
+/// they are back to their own, so the edge lights smoothly.
+pub fn smooth_seam(own: Vector, other: Vector, distance: f32, width: f32) -> Vector {
+    if distance >= width {
+        return own;
+    }
+
+    let blend = 0.5 * (1.0 - distance.max(0.0) / width);
+    own.normalize() * (1.0 - blend) + other.normalize() * blend
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::tuple::Point;
+
+    use super::*;
+
+    #[test]
+    fn a_ray_through_the_rim_is_within_the_cap() {
+        let r = Ray::new(
+            Point::new(0.0, 4.0, -2.0),
+            Vector::new(0.0, -1.0, 1.0).normalize(),
+        );
+        let t = (2.0 - r.origin.y()) / r.direction.y();
+
+        assert!(within_cap(r, t, 1.0));
+    }
+
+    #[test]
+    fn a_ray_just_outside_the_rim_misses_the_cap() {
+        let r = Ray::new(Point::new(1.01, 3.0, 0.0), Vector::new(0.0, -1.0, 0.0));
+
+        assert!(!within_cap(r, 1.0, 1.0));
+    }
+
+    #[test]
+    fn a_negative_radius_is_measured_from_the_axis() {
+        let r = Ray::new(Point::new(0.4, 3.0, 0.0), Vector::new(0.0, -1.0, 0.0));
+
+        assert!(within_cap(r, 3.5, -0.5));
+    }
+
+    #[test]
+    fn a_ray_parallel_to_the_caps_never_crosses_them() {
+        let r = Ray::new(Point::new(0.0, 1.5, -5.0), Vector::new(0.0, 0.0, 1.0));
+
+        assert!(cap_hits(r, 1.0, 2.0, |_| 1.0).is_empty());
+    }
+
+    #[test]
+    fn a_seam_with_no_width_is_left_sharp() {
+        let side = Vector::new(2.0, 0.0, 0.0);
+        let cap = Vector::new(0.0, 1.0, 0.0);
+
+        assert_eq!(smooth_seam(side, cap, 0.0, 0.0), side);
+    }
+
+    #[test]
+    fn both_sides_of_a_smoothed_seam_share_a_normal() {
+        let side = Vector::new(1.0, 0.0, 0.0);
+        let cap = Vector::new(0.0, 1.0, 0.0);
+
+        assert_eq!(
+            smooth_seam(side, cap, 0.0, 0.1),
+            smooth_seam(cap, side, 0.0, 0.1)
+        );
+        assert_eq!(smooth_seam(side, cap, 0.0, 0.1), Vector::new(0.5, 0.5, 0.0));
+    }
+
+    #[test]
+    fn a_smoothed_seam_fades_out_across_its_width() {
+        let side = Vector::new(1.0, 0.0, 0.0);
+        let cap = Vector::new(0.0, 1.0, 0.0);
+
+        assert_eq!(
+            smooth_seam(side, cap, 0.05, 0.1),
+            Vector::new(0.75, 0.25, 0.0)
+        );
+        assert_eq!(smooth_seam(side, cap, 0.1, 0.1), side);
+        assert_eq!(smooth_seam(side, cap, 0.5, 0.1), side);
+    }
+
+    #[test]
+    fn a_ray_along_the_axis_crosses_both_caps() {
+        let r = Ray::new(Point::new(0.0, 3.0, 0.0), Vector::new(0.0, -1.0, 0.0));
+
+        assert_eq!(cap_hits(r, 1.0, 2.0, |_| 1.0), vec![2.0, 1.0]);
+    }
+}
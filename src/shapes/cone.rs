@@ -1,25 +1,46 @@
+use std::any::Any;
+
 use crate::{
+    bounds::BoundingBox,
     float_eq,
     intersection::Intersection,
     materials::Material,
     ray::Ray,
-    shapes::{Shape, ShapeBuilder},
+    shapes::{caps, mesh::Mesh, Shape, ShapeBuilder, Tags, Visibility},
     transformations::Transform,
-    tuple::Tuple,
+    tuple::{Point, Vector},
     EPSILON,
 };
-use std::f32::{MAX, MIN};
 use uuid::Uuid;
 
+/// One half of the double cone, either side of the apex.
+#[derive(Clone, Copy, Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum Nappe {
+    Upper,
+    Lower,
+}
+
 #[derive(Clone, Debug, PartialEq)]
+#[cfg_attr(
+    feature = "serde",
+    derive(serde::Serialize, serde::Deserialize),
+    serde(default)
+)]
 pub struct Cone {
+    #[cfg_attr(feature = "serde", serde(skip))]
     id: Uuid,
+    #[cfg_attr(feature = "serde", serde(skip))]
     parent: Option<Uuid>,
+    visibility: Visibility,
+    tags: Tags,
     material: Material,
     transform: Transform,
     minimum: f32,
     maximum: f32,
     closed: bool,
+    nappe: Option<Nappe>,
+    bevel: f32,
 }
 
 impl Cone {
@@ -32,20 +53,44 @@ impl Cone {
         }
     }
 
-    fn intersect_caps<'a>(&'a self, ray: Ray, xs: &[Intersection<'a>]) -> Vec<Intersection<'a>> {
-        let mut result = xs.to_vec();
-        if !self.closed || float_eq(ray.direction.y(), 0.0) {
-            return result;
+    /// Keeps only one nappe, so the cone's extent on the other side of the
+    /// apex is ignored whatever the caps say.
+    pub fn single_nappe(self, nappe: Nappe) -> Self {
+        Self {
+            nappe: Some(nappe),
+            ..self
         }
+    }
 
-        let t = (self.minimum - ray.origin.y()) / ray.direction.y();
-        if check_cap(ray, t, self.minimum) {
-            result.push(Intersection::new(t, self));
+    /// Smooths the normal within `width` of where the caps meet the side,
+    /// softening the hard edge. `width` should be well under the radius.
+    pub fn bevel(self, width: f32) -> Self {
+        Self {
+            bevel: width,
+            ..self
         }
+    }
 
-        let t = (self.maximum - ray.origin.y()) / ray.direction.y();
-        if check_cap(ray, t, self.maximum) {
-            result.push(Intersection::new(t, self));
+    fn lower(&self) -> f32 {
+        match self.nappe {
+            Some(Nappe::Upper) => self.minimum.max(0.0),
+            _ => self.minimum,
+        }
+    }
+
+    fn upper(&self) -> f32 {
+        match self.nappe {
+            Some(Nappe::Lower) => self.maximum.min(0.0),
+            _ => self.maximum,
+        }
+    }
+
+    fn intersect_caps<'a>(&'a self, ray: Ray, xs: &[Intersection<'a>]) -> Vec<Intersection<'a>> {
+        let mut result = xs.to_vec();
+        if self.closed {
+            for t in caps::cap_hits(ray, self.lower(), self.upper(), |y| y) {
+                result.push(Intersection::new(t, self));
+            }
         }
 
         result
@@ -57,11 +102,15 @@ impl Default for Cone {
         Self {
             id: Uuid::new_v4(),
             parent: None,
-            minimum: MIN,
-            maximum: MAX,
+            visibility: Visibility::default(),
+            tags: Tags::new(),
+            minimum: f32::NEG_INFINITY,
+            maximum: f32::INFINITY,
             transform: Transform::default(),
             material: Material::default(),
             closed: false,
+            nappe: None,
+            bevel: 0.0,
         }
     }
 }
@@ -81,6 +130,10 @@ impl Shape for Cone {
         self.id
     }
 
+    fn as_any(&self) -> &dyn Any {
+        self
+    }
+
     fn transform(&self) -> &Transform {
         &self.transform
     }
@@ -109,6 +162,36 @@ impl Shape for Cone {
         self.parent = Some(parent);
     }
 
+    fn clear_parent(&mut self) {
+        self.parent = None;
+    }
+
+    fn visibility(&self) -> Visibility {
+        self.visibility
+    }
+
+    fn set_visibility(&mut self, visibility: Visibility) {
+        self.visibility = visibility;
+    }
+
+    fn tags(&self) -> &Tags {
+        &self.tags
+    }
+
+    fn tags_mut(&mut self) -> &mut Tags {
+        &mut self.tags
+    }
+
+    fn parameters(&self) -> Vec<(&'static str, String)> {
+        vec![
+            ("minimum", format!("{:?}", self.minimum)),
+            ("maximum", format!("{:?}", self.maximum)),
+            ("closed", format!("{:?}", self.closed)),
+            ("nappe", format!("{:?}", self.nappe)),
+            ("bevel", format!("{:?}", self.bevel)),
+        ]
+    }
+
     fn local_intersect(&self, ray: Ray) -> Vec<Intersection> {
         let a = ray.direction.x().powi(2) - ray.direction.y().powi(2) + ray.direction.z().powi(2);
 
@@ -120,14 +203,24 @@ impl Shape for Cone {
         if float_eq(a, 0.0) && float_eq(b, 0.0) {
             return self.intersect_caps(ray, &[]);
         } else if float_eq(a, 0.0) {
-            return self.intersect_caps(ray, &[Intersection::new(-c / (2.0 * b), self)]);
+            let t = -c / (2.0 * b);
+            let y = ray.origin.y() + t * ray.direction.y();
+            let xs = if self.lower() < y && y < self.upper() {
+                vec![Intersection::new(t, self)]
+            } else {
+                vec![]
+            };
+            return self.intersect_caps(ray, &xs);
         }
 
-        let disc = b.powi(2) - 4.0 * a * c + EPSILON;
+        // Rays grazing the surface, or passing through the apex, can come out
+        // with a slightly negative discriminant from rounding alone.
+        let disc = b.powi(2) - 4.0 * a * c;
 
-        if disc < 0.0 {
-            vec![]
+        if disc < -EPSILON {
+            self.intersect_caps(ray, &[])
         } else {
+            let disc = disc.max(0.0);
             let mut t = (
                 (-b - disc.sqrt()) / (2.0 * a),
                 (-b + disc.sqrt()) / (2.0 * a),
@@ -138,12 +231,12 @@ impl Shape for Cone {
             let mut xs: Vec<Intersection> = vec![];
 
             let y0 = ray.origin.y() + t.0 * ray.direction.y();
-            if self.minimum < y0 && y0 < self.maximum {
+            if self.lower() < y0 && y0 < self.upper() {
                 xs.push(Intersection::new(t.0, self));
             }
 
             let y1 = ray.origin.y() + t.1 * ray.direction.y();
-            if self.minimum < y1 && y1 < self.maximum {
+            if self.lower() < y1 && y1 < self.upper() {
                 xs.push(Intersection::new(t.1, self));
             }
 
@@ -151,30 +244,75 @@ impl Shape for Cone {
         }
     }
 
-    fn local_normal_at(&self, point: Tuple) -> Tuple {
-        match point.x().powi(2) + point.z().powi(2) {
-            dist if dist < 1.0 && point.y() >= self.maximum - EPSILON => {
-                Tuple::vector(0.0, 1.0, 0.0)
+    fn local_normal_at(&self, point: Point, _hit: Option<&Intersection>) -> Vector {
+        let up = Vector::new(0.0, 1.0, 0.0);
+        let down = Vector::new(0.0, -1.0, 0.0);
+        let dist = point.x().powi(2) + point.z().powi(2);
+        let side = {
+            let mut y = dist.sqrt();
+            if point.y() > 0.0 {
+                y = -y;
             }
-            dist if dist < 1.0 && point.y() <= self.minimum + EPSILON => {
-                Tuple::vector(0.0, -1.0, 0.0)
-            }
-            dist => {
-                let mut y = dist.sqrt();
-                if point.y() > 0.0 {
-                    y = -y;
-                }
-                Tuple::vector(point.x(), y, point.z())
+            Vector::new(point.x(), y, point.z())
+        };
+        let (lower, upper) = (self.lower(), self.upper());
+
+        if dist < upper.powi(2) && point.y() >= upper - EPSILON {
+            caps::smooth_seam(up, side, upper.abs() - dist.sqrt(), self.bevel)
+        } else if dist < lower.powi(2) && point.y() <= lower + EPSILON {
+            caps::smooth_seam(down, side, lower.abs() - dist.sqrt(), self.bevel)
+        } else if self.closed {
+            // A cap of no size at the apex leaves no seam to smooth.
+            let to_upper = if upper.abs() > EPSILON {
+                upper - point.y()
+            } else {
+                f32::INFINITY
+            };
+            let to_lower = if lower.abs() > EPSILON {
+                point.y() - lower
+            } else {
+                f32::INFINITY
+            };
+            if to_upper < to_lower {
+                caps::smooth_seam(side, up, to_upper, self.bevel)
+            } else {
+                caps::smooth_seam(side, down, to_lower, self.bevel)
             }
+        } else {
+            side
         }
     }
-}
 
-fn check_cap(ray: Ray, t: f32, y: f32) -> bool {
-    let x = ray.origin.x() + t * ray.direction.x();
-    let z = ray.origin.z() + t * ray.direction.z();
+    fn bounds(&self) -> BoundingBox {
+        let radius = self.lower().abs().max(self.upper().abs());
+        BoundingBox::new(
+            Point::new(-radius, self.lower(), -radius),
+            Point::new(radius, self.upper(), radius),
+        )
+    }
+
+    fn tessellate(&self, resolution: usize) -> Option<Mesh> {
+        let (lower, upper) = (self.lower(), self.upper());
+        if !lower.is_finite() || !upper.is_finite() {
+            return None;
+        }
+
+        let mut profile = vec![];
+        if self.closed {
+            profile.push((0.0, lower));
+        }
+        profile.push((lower.abs(), lower));
+        if lower < 0.0 && upper > 0.0 {
+            profile.push((0.0, 0.0));
+        }
+        profile.push((upper.abs(), upper));
+        if self.closed {
+            profile.push((0.0, upper));
+        }
+        profile.dedup();
 
-    x.powi(2) + z.powi(2) <= y.powi(2)
+        Some(Mesh::revolve(&profile, resolution.max(3)))
+    }
 }
 
 #[cfg(test)]
@@ -203,16 +341,16 @@ mod tests {
     }
 
     intersecting_a_cone_with_a_ray! {
-        intersecting_a_cone_with_a_ray_1: (Tuple::point(0.0, 0.0, -5.0), Tuple::vector(0.0, 0.0, 1.0), 4.995, 5.005),
-        intersecting_a_cone_with_a_ray_2: (Tuple::point(0.0, 0.0, -5.0), Tuple::vector(1.0, 1.0, 1.0), 8.645543, 8.674966),
-        intersecting_a_cone_with_a_ray_3: (Tuple::point(1.0, 1.0, -5.0), Tuple::vector(-0.5, -1.0, 1.0), 4.550057, 49.44995),
+        intersecting_a_cone_with_a_ray_1: (Point::new(0.0, 0.0, -5.0), Vector::new(0.0, 0.0, 1.0), 5.0, 5.0),
+        intersecting_a_cone_with_a_ray_2: (Point::new(0.0, 0.0, -5.0), Vector::new(1.0, 1.0, 1.0), 8.66025, 8.66025),
+        intersecting_a_cone_with_a_ray_3: (Point::new(1.0, 1.0, -5.0), Vector::new(-0.5, -1.0, 1.0), 4.55006, 49.44994),
     }
 
     #[test]
     fn intersecting_a_cone_with_a_ray_parallel_to_one_of_its_halves() {
         let shape = Cone::default();
-        let direction = Tuple::vector(0.0, 1.0, 1.0);
-        let r = Ray::new(Tuple::point(0.0, 0.0, -1.0), direction.normalize());
+        let direction = Vector::new(0.0, 1.0, 1.0);
+        let r = Ray::new(Point::new(0.0, 0.0, -1.0), direction.normalize());
 
         let xs = shape.local_intersect(r);
 
@@ -239,9 +377,9 @@ mod tests {
     }
 
     intersecting_a_cones_end_caps! {
-        intersecting_a_cones_end_caps_1: (Tuple::point(0.0, 0.0, -5.0), Tuple::vector(0.0, 1.0, 0.0), 0),
-        intersecting_a_cones_end_caps_2: (Tuple::point(0.0, 0.0, -0.25), Tuple::vector(0.0, 1.0, 1.0), 2),
-        intersecting_a_cones_end_caps_3: (Tuple::point(0.0, 0.0, -0.25), Tuple::vector(0.0, 1.0, 0.0), 4),
+        intersecting_a_cones_end_caps_1: (Point::new(0.0, 0.0, -5.0), Vector::new(0.0, 1.0, 0.0), 0),
+        intersecting_a_cones_end_caps_2: (Point::new(0.0, 0.0, -0.25), Vector::new(0.0, 1.0, 1.0), 2),
+        intersecting_a_cones_end_caps_3: (Point::new(0.0, 0.0, -0.25), Vector::new(0.0, 1.0, 0.0), 4),
     }
 
     macro_rules! computing_the_normal_vector_on_a_cone {
@@ -251,7 +389,7 @@ mod tests {
             fn $name() {
                 let (point, normal) = $value;
                 let shape = Cone::default();
-                let n = shape.local_normal_at(point);
+                let n = shape.local_normal_at(point, None);
 
                 assert_eq!(n, normal);
             }
@@ -260,8 +398,163 @@ mod tests {
     }
 
     computing_the_normal_vector_on_a_cone! {
-        computing_the_normal_vector_on_a_cone_1: (Tuple::point(0.0, 0.0, 0.0), Tuple::vector(0.0, 0.0, 0.0)),
-        computing_the_normal_vector_on_a_cone_2: (Tuple::point(1.0, 1.0, 1.0), Tuple::vector(1.0, -SQRT_2, 1.0)),
-        computing_the_normal_vector_on_a_cone_3: (Tuple::point(-1.0, -1.0, 0.0), Tuple::vector(-1.0, 1.0, 0.0)),
+        computing_the_normal_vector_on_a_cone_1: (Point::new(0.0, 0.0, 0.0), Vector::new(0.0, 0.0, 0.0)),
+        computing_the_normal_vector_on_a_cone_2: (Point::new(1.0, 1.0, 1.0), Vector::new(1.0, -SQRT_2, 1.0)),
+        computing_the_normal_vector_on_a_cone_3: (Point::new(-1.0, -1.0, 0.0), Vector::new(-1.0, 1.0, 0.0)),
+    }
+
+    #[test]
+    fn the_default_cone_has_both_nappes() {
+        let shape = Cone::default();
+        let r = Ray::new(Point::new(0.0, -2.0, -5.0), Vector::new(0.0, 0.0, 1.0));
+
+        let xs = shape.local_intersect(r);
+
+        assert_eq!(xs.len(), 2);
+    }
+
+    macro_rules! intersecting_a_single_nappe {
+        ($($name:ident: $value:expr,)*) => {
+        $(
+            #[test]
+            fn $name() {
+                let (nappe, origin, count) = $value;
+                let shape = Cone::default().single_nappe(nappe);
+                let r = Ray::new(origin, Vector::new(0.0, 0.0, 1.0));
+
+                let xs = shape.local_intersect(r);
+
+                assert_eq!(xs.len(), count);
+            }
+        )*
+        }
+    }
+
+    intersecting_a_single_nappe! {
+        intersecting_a_single_nappe_1: (Nappe::Upper, Point::new(0.0, 2.0, -5.0), 2),
+        intersecting_a_single_nappe_2: (Nappe::Upper, Point::new(0.0, -2.0, -5.0), 0),
+        intersecting_a_single_nappe_3: (Nappe::Lower, Point::new(0.0, 2.0, -5.0), 0),
+        intersecting_a_single_nappe_4: (Nappe::Lower, Point::new(0.0, -2.0, -5.0), 2),
+    }
+
+    #[test]
+    fn a_single_nappe_ignores_caps_beyond_the_apex() {
+        let shape = Cone::default()
+            .with_caps(-2.0, 1.0)
+            .single_nappe(Nappe::Upper);
+        let r = Ray::new(Point::new(0.0, -1.5, -5.0), Vector::new(0.0, 0.0, 1.0));
+        let up = Ray::new(Point::new(0.5, -5.0, 0.0), Vector::new(0.0, 1.0, 0.0));
+
+        assert!(shape.local_intersect(r).is_empty());
+        assert_eq!(shape.local_intersect(up).len(), 2);
+    }
+
+    #[test]
+    fn the_normal_on_a_capped_single_nappe() {
+        let shape = Cone::default()
+            .with_caps(-2.0, 1.0)
+            .single_nappe(Nappe::Upper);
+
+        assert_eq!(
+            shape.local_normal_at(Point::new(0.5, 1.0, 0.0), None),
+            Vector::new(0.0, 1.0, 0.0)
+        );
+        assert_eq!(
+            shape.local_normal_at(Point::new(0.5, 0.5, 0.0), None),
+            Vector::new(0.5, -0.5, 0.0)
+        );
+    }
+
+    #[test]
+    fn an_unbounded_cone_has_a_bounding_box() {
+        let shape = Cone::default();
+
+        let b = shape.bounds();
+
+        assert!(!b.is_finite());
+        assert_eq!(b.min.x(), f32::NEG_INFINITY);
+        assert_eq!(b.max.y(), f32::INFINITY);
+    }
+
+    #[test]
+    fn a_bounded_cone_has_a_bounding_box() {
+        let shape = Cone::default().with_caps(-5.0, 3.0);
+
+        let b = shape.bounds();
+
+        assert_eq!(b.min, Point::new(-5.0, -5.0, -5.0));
+        assert_eq!(b.max, Point::new(5.0, 3.0, 5.0));
+    }
+
+    #[test]
+    fn a_single_nappe_is_bounded_at_the_apex() {
+        let shape = Cone::default()
+            .with_caps(-5.0, 3.0)
+            .single_nappe(Nappe::Upper);
+
+        let b = shape.bounds();
+
+        assert_eq!(b.min, Point::new(-3.0, 0.0, -3.0));
+        assert_eq!(b.max, Point::new(3.0, 3.0, 3.0));
+    }
+
+    #[test]
+    fn the_normal_near_a_beveled_cone_seam() {
+        let shape = Cone::default().with_caps(-2.0, 1.0).bevel(0.2);
+        let side = Vector::new(1.0, -1.0, 0.0).normalize();
+        let up = Vector::new(0.0, 1.0, 0.0);
+
+        let on_side = shape
+            .local_normal_at(Point::new(1.0, 1.0, 0.0), None)
+            .normalize();
+        let on_cap = shape
+            .local_normal_at(Point::new(0.99999, 1.0, 0.0), None)
+            .normalize();
+
+        assert_eq!(on_side, (side + up).normalize());
+        assert!((on_cap - on_side).magnitude() < 0.001);
+        assert_eq!(
+            shape.local_normal_at(Point::new(0.5, 0.5, 0.0), None),
+            Vector::new(0.5, -0.5, 0.0)
+        );
+    }
+
+    #[test]
+    fn a_bevel_ignores_a_cap_at_the_apex() {
+        let shape = Cone::default().with_caps(0.0, 1.0).bevel(0.2);
+
+        assert_eq!(
+            shape.local_normal_at(Point::new(0.1, 0.1, 0.0), None),
+            Vector::new(0.1, -0.1, 0.0)
+        );
+    }
+
+    #[test]
+    fn an_infinite_cone_cannot_be_tessellated() {
+        assert!(Cone::default().tessellate(16).is_none());
+        assert!(Cone::default()
+            .single_nappe(Nappe::Upper)
+            .tessellate(16)
+            .is_none());
+    }
+
+    #[test]
+    fn tessellating_a_capped_double_cone() {
+        let mesh = Cone::default().with_caps(-1.0, 1.0).tessellate(64).unwrap();
+
+        assert!(mesh.is_closed());
+        assert!((mesh.signed_volume() - 2.0 * std::f32::consts::PI / 3.0).abs() < 0.01);
+    }
+
+    #[test]
+    fn tessellating_a_single_capped_nappe() {
+        let mesh = Cone::default()
+            .with_caps(-1.0, 1.0)
+            .single_nappe(Nappe::Upper)
+            .tessellate(64)
+            .unwrap();
+
+        assert!(mesh.is_closed());
+        assert!((mesh.signed_volume() - std::f32::consts::PI / 3.0).abs() < 0.01);
     }
 }
@@ -1,21 +1,34 @@
+use std::any::Any;
+
 use uuid::Uuid;
 
 use crate::{
+    bounds::BoundingBox,
     float_eq,
     intersection::Intersection,
     materials::Material,
+    packet::RayPacket,
     ray::Ray,
-    shapes::{Shape, ShapeBuilder},
+    shapes::{mesh::Mesh, Shape, ShapeBuilder, Tags, Visibility},
     transformations::{Transform, IDENTITY},
-    tuple::Tuple,
+    tuple::{Point, Vector},
     EPSILON,
 };
 use std::{cmp::Ordering::Equal, f32::MAX};
 
 #[derive(Clone, Debug, PartialEq)]
+#[cfg_attr(
+    feature = "serde",
+    derive(serde::Serialize, serde::Deserialize),
+    serde(default)
+)]
 pub struct Cube {
+    #[cfg_attr(feature = "serde", serde(skip))]
     id: Uuid,
+    #[cfg_attr(feature = "serde", serde(skip))]
     parent: Option<Uuid>,
+    visibility: Visibility,
+    tags: Tags,
     material: Material,
     transform: Transform,
 }
@@ -25,6 +38,8 @@ impl Default for Cube {
         Self {
             id: Uuid::new_v4(),
             parent: None,
+            visibility: Visibility::default(),
+            tags: Tags::new(),
             material: Material::default(),
             transform: IDENTITY,
         }
@@ -77,6 +92,10 @@ impl Shape for Cube {
         self.id
     }
 
+    fn as_any(&self) -> &dyn Any {
+        self
+    }
+
     fn transform(&self) -> &Transform {
         &self.transform
     }
@@ -105,6 +124,26 @@ impl Shape for Cube {
         self.parent = Some(parent);
     }
 
+    fn clear_parent(&mut self) {
+        self.parent = None;
+    }
+
+    fn visibility(&self) -> Visibility {
+        self.visibility
+    }
+
+    fn set_visibility(&mut self, visibility: Visibility) {
+        self.visibility = visibility;
+    }
+
+    fn tags(&self) -> &Tags {
+        &self.tags
+    }
+
+    fn tags_mut(&mut self) -> &mut Tags {
+        &mut self.tags
+    }
+
     fn local_intersect(&self, ray: Ray) -> Vec<Intersection> {
         let (xtmin, xtmax) = check_axis(ray.origin.x(), ray.direction.x());
         let (ytmin, ytmax) = check_axis(ray.origin.y(), ray.direction.y());
@@ -120,7 +159,17 @@ impl Shape for Cube {
         vec![Intersection::new(tmin, self), Intersection::new(tmax, self)]
     }
 
-    fn local_normal_at(&self, point: Tuple) -> Tuple {
+    fn local_intersect_packet(&self, packet: &RayPacket) -> [Vec<Intersection>; 4] {
+        let mut hits: [Vec<Intersection>; 4] = Default::default();
+        for (lane, ts) in packet.cube_hits().iter().enumerate() {
+            if let Some((tmin, tmax)) = *ts {
+                hits[lane] = vec![Intersection::new(tmin, self), Intersection::new(tmax, self)];
+            }
+        }
+        hits
+    }
+
+    fn local_normal_at(&self, point: Point, _hit: Option<&Intersection>) -> Vector {
         let abs_x = point.x().abs();
         let abs_y = point.y().abs();
         let abs_z = point.z().abs();
@@ -128,12 +177,58 @@ impl Shape for Cube {
         let maxc = max(&[abs_x, abs_y, abs_z]);
 
         match maxc {
-            _ if float_eq(maxc, abs_x) => Tuple::vector(point.x(), 0.0, 0.0),
-            _ if float_eq(maxc, abs_y) => Tuple::vector(0.0, point.y(), 0.0),
-            _ if float_eq(maxc, abs_z) => Tuple::vector(0.0, 0.0, point.z()),
+            _ if float_eq(maxc, abs_x) => Vector::new(point.x(), 0.0, 0.0),
+            _ if float_eq(maxc, abs_y) => Vector::new(0.0, point.y(), 0.0),
+            _ if float_eq(maxc, abs_z) => Vector::new(0.0, 0.0, point.z()),
             _ => panic!(),
         }
     }
+
+    fn bounds(&self) -> BoundingBox {
+        BoundingBox::new(Point::new(-1.0, -1.0, -1.0), Point::new(1.0, 1.0, 1.0))
+    }
+
+    fn local_surface_point(&self, u: f32, v: f32) -> Option<Point> {
+        let face = (u * 6.0).floor().min(5.0);
+        let a = 2.0 * (u * 6.0 - face) - 1.0;
+        let b = 2.0 * v - 1.0;
+        let point = match face as u32 {
+            0 => Point::new(1.0, a, b),
+            1 => Point::new(-1.0, a, b),
+            2 => Point::new(a, 1.0, b),
+            3 => Point::new(a, -1.0, b),
+            4 => Point::new(a, b, 1.0),
+            _ => Point::new(a, b, -1.0),
+        };
+        Some(point)
+    }
+
+    fn local_surface_area(&self) -> f32 {
+        24.0
+    }
+
+    fn tessellate(&self, _resolution: usize) -> Option<Mesh> {
+        let vertices = (0..8)
+            .map(|i| {
+                let coordinate = |bit| if i & bit == 0 { -1.0 } else { 1.0 };
+                Point::new(coordinate(1), coordinate(2), coordinate(4))
+            })
+            .collect();
+        let quads = [
+            [1, 5, 7, 3],
+            [0, 2, 6, 4],
+            [2, 3, 7, 6],
+            [0, 4, 5, 1],
+            [4, 6, 7, 5],
+            [0, 1, 3, 2],
+        ];
+        let faces = quads
+            .iter()
+            .flat_map(|&[a, b, c, d]| vec![[a, b, c], [a, c, d]])
+            .collect();
+
+        Some(Mesh::new(vertices, faces))
+    }
 }
 
 #[cfg(test)]
@@ -174,13 +269,13 @@ mod tests {
     }
 
     a_ray_intersects_a_cube! {
-        a_ray_intersects_a_cube_plus_x: (Tuple::point(5.0, 0.5, 0.0), Tuple::vector(-1.0, 0.0, 0.0), 4.0, 6.0),
-        a_ray_intersects_a_cube_minus_x: (Tuple::point(-5.0, 0.5, 0.0), Tuple::vector(1.0, 0.0, 0.0), 4.0, 6.0),
-        a_ray_intersects_a_cube_plus_y: (Tuple::point(0.5, 5.0, 0.0), Tuple::vector(0.0, -1.0, 0.0), 4.0, 6.0),
-        a_ray_intersects_a_cube_minus_y: (Tuple::point(0.5, -5.0, 0.0), Tuple::vector(0.0, 1.0, 0.0), 4.0, 6.0),
-        a_ray_intersects_a_cube_plus_z: (Tuple::point(0.5, 0.0, 5.0), Tuple::vector(0.0, 0.0, -1.0), 4.0, 6.0),
-        a_ray_intersects_a_cube_minus_z: (Tuple::point(0.5, 0.0, -5.0), Tuple::vector(0.0, 0.0, 1.0), 4.0, 6.0),
-        a_ray_intersects_a_cube_inside: (Tuple::point(0.0, 0.5, 0.0), Tuple::vector(0.0, 0.0, 1.0), -1.0, 1.0),
+        a_ray_intersects_a_cube_plus_x: (Point::new(5.0, 0.5, 0.0), Vector::new(-1.0, 0.0, 0.0), 4.0, 6.0),
+        a_ray_intersects_a_cube_minus_x: (Point::new(-5.0, 0.5, 0.0), Vector::new(1.0, 0.0, 0.0), 4.0, 6.0),
+        a_ray_intersects_a_cube_plus_y: (Point::new(0.5, 5.0, 0.0), Vector::new(0.0, -1.0, 0.0), 4.0, 6.0),
+        a_ray_intersects_a_cube_minus_y: (Point::new(0.5, -5.0, 0.0), Vector::new(0.0, 1.0, 0.0), 4.0, 6.0),
+        a_ray_intersects_a_cube_plus_z: (Point::new(0.5, 0.0, 5.0), Vector::new(0.0, 0.0, -1.0), 4.0, 6.0),
+        a_ray_intersects_a_cube_minus_z: (Point::new(0.5, 0.0, -5.0), Vector::new(0.0, 0.0, 1.0), 4.0, 6.0),
+        a_ray_intersects_a_cube_inside: (Point::new(0.0, 0.5, 0.0), Vector::new(0.0, 0.0, 1.0), -1.0, 1.0),
     }
 
     macro_rules! a_ray_misses_a_cube {
@@ -200,12 +295,12 @@ mod tests {
     }
 
     a_ray_misses_a_cube! {
-        a_ray_misses_a_cube_1: (Tuple::point(-2.0, 0.0, 0.0), Tuple::vector(0.2673, 0.5345, 0.8018)),
-        a_ray_misses_a_cube_2: (Tuple::point(0.0, -2.0, 0.0), Tuple::vector(0.8018, 0.2673, 0.5345)),
-        a_ray_misses_a_cube_3: (Tuple::point(0.0, 0.0, -2.0), Tuple::vector(0.5345, 0.8018, 0.2673)),
-        a_ray_misses_a_cube_4: (Tuple::point(2.0, 0.0, 2.0), Tuple::vector(0.0, 0.0, -1.0)),
-        a_ray_misses_a_cube_5: (Tuple::point(0.0, 2.0, 2.0), Tuple::vector(0.0, -1.0, 0.0)),
-        a_ray_misses_a_cube_6: (Tuple::point(2.0, 2.0, 0.0), Tuple::vector(-1.0, 0.0, 0.0)),
+        a_ray_misses_a_cube_1: (Point::new(-2.0, 0.0, 0.0), Vector::new(0.2673, 0.5345, 0.8018)),
+        a_ray_misses_a_cube_2: (Point::new(0.0, -2.0, 0.0), Vector::new(0.8018, 0.2673, 0.5345)),
+        a_ray_misses_a_cube_3: (Point::new(0.0, 0.0, -2.0), Vector::new(0.5345, 0.8018, 0.2673)),
+        a_ray_misses_a_cube_4: (Point::new(2.0, 0.0, 2.0), Vector::new(0.0, 0.0, -1.0)),
+        a_ray_misses_a_cube_5: (Point::new(0.0, 2.0, 2.0), Vector::new(0.0, -1.0, 0.0)),
+        a_ray_misses_a_cube_6: (Point::new(2.0, 2.0, 0.0), Vector::new(-1.0, 0.0, 0.0)),
     }
 
     macro_rules! the_normal_on_the_surface_of_a_cube {
@@ -216,7 +311,7 @@ mod tests {
                 let (point, expected_normal) = $value;
                 let c = Cube::default();
 
-                let actual_normal = c.local_normal_at(point);
+                let actual_normal = c.local_normal_at(point, None);
 
                 assert_eq!(actual_normal, expected_normal);
             }
@@ -225,13 +320,57 @@ mod tests {
     }
 
     the_normal_on_the_surface_of_a_cube! {
-        the_normal_on_the_surface_of_a_cube_1: (Tuple::point(1.0, 0.5, -0.8), Tuple::vector(1.0, 0.0, 0.0)),
-        the_normal_on_the_surface_of_a_cube_2: (Tuple::point(-1.0, -0.2, 0.9), Tuple::vector(-1.0, 0.0, 0.0)),
-        the_normal_on_the_surface_of_a_cube_3: (Tuple::point(-0.4, 1.0, -0.1), Tuple::vector(0.0, 1.0, 0.0)),
-        the_normal_on_the_surface_of_a_cube_4: (Tuple::point(0.3, -1.0, -0.7), Tuple::vector(0.0, -1.0, 0.0)),
-        the_normal_on_the_surface_of_a_cube_5: (Tuple::point(-0.6, 0.3, 1.0), Tuple::vector(0.0, 0.0, 1.0)),
-        the_normal_on_the_surface_of_a_cube_6: (Tuple::point(0.4, 0.4, -1.0), Tuple::vector(0.0, 0.0, -1.0)),
-        the_normal_on_the_surface_of_a_cube_7: (Tuple::point(1.0, 1.0, 1.0), Tuple::vector(1.0, 0.0, 0.0)),
-        the_normal_on_the_surface_of_a_cube_8: (Tuple::point(-1.0, -1.0, -1.0), Tuple::vector(-1.0, 0.0, 0.0)),
+        the_normal_on_the_surface_of_a_cube_1: (Point::new(1.0, 0.5, -0.8), Vector::new(1.0, 0.0, 0.0)),
+        the_normal_on_the_surface_of_a_cube_2: (Point::new(-1.0, -0.2, 0.9), Vector::new(-1.0, 0.0, 0.0)),
+        the_normal_on_the_surface_of_a_cube_3: (Point::new(-0.4, 1.0, -0.1), Vector::new(0.0, 1.0, 0.0)),
+        the_normal_on_the_surface_of_a_cube_4: (Point::new(0.3, -1.0, -0.7), Vector::new(0.0, -1.0, 0.0)),
+        the_normal_on_the_surface_of_a_cube_5: (Point::new(-0.6, 0.3, 1.0), Vector::new(0.0, 0.0, 1.0)),
+        the_normal_on_the_surface_of_a_cube_6: (Point::new(0.4, 0.4, -1.0), Vector::new(0.0, 0.0, -1.0)),
+        the_normal_on_the_surface_of_a_cube_7: (Point::new(1.0, 1.0, 1.0), Vector::new(1.0, 0.0, 0.0)),
+        the_normal_on_the_surface_of_a_cube_8: (Point::new(-1.0, -1.0, -1.0), Vector::new(-1.0, 0.0, 0.0)),
+    }
+
+    #[test]
+    fn surface_samples_cover_every_face_of_the_cube() {
+        let c = Cube::default();
+
+        let normals = (0..6)
+            .map(|face| {
+                let sample = c.sample_surface((face as f32 + 0.5) / 6.0, 0.5).unwrap();
+                assert!(float_eq(sample.area, 24.0));
+                sample.normal
+            })
+            .collect::<Vec<_>>();
+
+        assert_eq!(
+            normals,
+            vec![
+                Vector::new(1.0, 0.0, 0.0),
+                Vector::new(-1.0, 0.0, 0.0),
+                Vector::new(0.0, 1.0, 0.0),
+                Vector::new(0.0, -1.0, 0.0),
+                Vector::new(0.0, 0.0, 1.0),
+                Vector::new(0.0, 0.0, -1.0),
+            ]
+        );
+    }
+
+    #[test]
+    fn a_cube_has_a_bounding_box() {
+        let c = Cube::default();
+
+        let b = c.bounds();
+
+        assert_eq!(b.min, Point::new(-1.0, -1.0, -1.0));
+        assert_eq!(b.max, Point::new(1.0, 1.0, 1.0));
+    }
+
+    #[test]
+    fn tessellating_a_cube() {
+        let mesh = Cube::default().tessellate(16).unwrap();
+
+        assert_eq!(mesh.faces.len(), 12);
+        assert!(mesh.is_closed());
+        assert!(float_eq(mesh.signed_volume(), 8.0));
     }
 }
@@ -1,25 +1,37 @@
+use std::any::Any;
+
 use crate::{
+    bounds::BoundingBox,
     float_eq,
     intersection::Intersection,
     materials::Material,
     ray::Ray,
-    shapes::{Shape, ShapeBuilder},
+    shapes::{caps, mesh::Mesh, Shape, ShapeBuilder, Tags, Visibility},
     transformations::Transform,
-    tuple::Tuple,
+    tuple::{Point, Vector},
     EPSILON,
 };
-use std::f32::{MAX, MIN};
 use uuid::Uuid;
 
 #[derive(Clone, Debug, PartialEq)]
+#[cfg_attr(
+    feature = "serde",
+    derive(serde::Serialize, serde::Deserialize),
+    serde(default)
+)]
 pub struct Cylinder {
+    #[cfg_attr(feature = "serde", serde(skip))]
     id: Uuid,
+    #[cfg_attr(feature = "serde", serde(skip))]
     parent: Option<Uuid>,
+    visibility: Visibility,
+    tags: Tags,
     material: Material,
     transform: Transform,
     minimum: f32,
     maximum: f32,
     closed: bool,
+    bevel: f32,
 }
 
 impl Cylinder {
@@ -32,20 +44,31 @@ impl Cylinder {
         }
     }
 
-    fn intersect_caps<'a>(&'a self, ray: Ray, xs: &[Intersection<'a>]) -> Vec<Intersection<'a>> {
-        let mut result = xs.to_vec();
-        if !self.closed || float_eq(ray.direction.y(), 0.0) {
-            return result;
+    /// Cuts the cylinder off at `bottom` and `top`, leaving its ends open.
+    pub fn truncated(self, bottom: f32, top: f32) -> Self {
+        Self {
+            closed: false,
+            minimum: bottom,
+            maximum: top,
+            ..self
         }
+    }
 
-        let t = (self.minimum - ray.origin.y()) / ray.direction.y();
-        if check_cap(ray, t) {
-            result.push(Intersection::new(t, self));
+    /// Smooths the normal within `width` of where the caps meet the side,
+    /// softening the hard edge. `width` should be well under the radius.
+    pub fn bevel(self, width: f32) -> Self {
+        Self {
+            bevel: width,
+            ..self
         }
+    }
 
-        let t = (self.maximum - ray.origin.y()) / ray.direction.y();
-        if check_cap(ray, t) {
-            result.push(Intersection::new(t, self));
+    fn intersect_caps<'a>(&'a self, ray: Ray, xs: &[Intersection<'a>]) -> Vec<Intersection<'a>> {
+        let mut result = xs.to_vec();
+        if self.closed {
+            for t in caps::cap_hits(ray, self.minimum, self.maximum, |_| 1.0) {
+                result.push(Intersection::new(t, self));
+            }
         }
 
         result
@@ -57,11 +80,14 @@ impl Default for Cylinder {
         Self {
             id: Uuid::new_v4(),
             parent: None,
-            minimum: MIN,
-            maximum: MAX,
+            visibility: Visibility::default(),
+            tags: Tags::new(),
+            minimum: f32::NEG_INFINITY,
+            maximum: f32::INFINITY,
             transform: Transform::default(),
             material: Material::default(),
             closed: false,
+            bevel: 0.0,
         }
     }
 }
@@ -81,6 +107,10 @@ impl Shape for Cylinder {
         self.id
     }
 
+    fn as_any(&self) -> &dyn Any {
+        self
+    }
+
     fn transform(&self) -> &Transform {
         &self.transform
     }
@@ -109,6 +139,35 @@ impl Shape for Cylinder {
         self.parent = Some(parent);
     }
 
+    fn clear_parent(&mut self) {
+        self.parent = None;
+    }
+
+    fn visibility(&self) -> Visibility {
+        self.visibility
+    }
+
+    fn set_visibility(&mut self, visibility: Visibility) {
+        self.visibility = visibility;
+    }
+
+    fn tags(&self) -> &Tags {
+        &self.tags
+    }
+
+    fn tags_mut(&mut self) -> &mut Tags {
+        &mut self.tags
+    }
+
+    fn parameters(&self) -> Vec<(&'static str, String)> {
+        vec![
+            ("minimum", format!("{:?}", self.minimum)),
+            ("maximum", format!("{:?}", self.maximum)),
+            ("closed", format!("{:?}", self.closed)),
+            ("bevel", format!("{:?}", self.bevel)),
+        ]
+    }
+
     fn local_intersect(&self, ray: Ray) -> Vec<Intersection> {
         let a = ray.direction.x().powi(2) + ray.direction.z().powi(2);
         if float_eq(a, 0.0) {
@@ -147,24 +206,53 @@ impl Shape for Cylinder {
         }
     }
 
-    fn local_normal_at(&self, point: Tuple) -> Tuple {
+    fn local_normal_at(&self, point: Point, _hit: Option<&Intersection>) -> Vector {
+        let up = Vector::new(0.0, 1.0, 0.0);
+        let down = Vector::new(0.0, -1.0, 0.0);
+        let side = Vector::new(point.x(), 0.0, point.z());
         match point.x().powi(2) + point.z().powi(2) {
             dist if dist < 1.0 && point.y() >= self.maximum - EPSILON => {
-                Tuple::vector(0.0, 1.0, 0.0)
+                caps::smooth_seam(up, side, 1.0 - dist.sqrt(), self.bevel)
             }
             dist if dist < 1.0 && point.y() <= self.minimum + EPSILON => {
-                Tuple::vector(0.0, -1.0, 0.0)
+                caps::smooth_seam(down, side, 1.0 - dist.sqrt(), self.bevel)
             }
-            _ => Tuple::vector(point.x(), 0.0, point.z()),
+            _ if self.closed => {
+                let (cap, distance) = if self.maximum - point.y() < point.y() - self.minimum {
+                    (up, self.maximum - point.y())
+                } else {
+                    (down, point.y() - self.minimum)
+                };
+                caps::smooth_seam(side, cap, distance, self.bevel)
+            }
+            _ => side,
         }
     }
-}
 
-fn check_cap(ray: Ray, t: f32) -> bool {
-    let x = ray.origin.x() + t * ray.direction.x();
-    let z = ray.origin.z() + t * ray.direction.z();
+    fn bounds(&self) -> BoundingBox {
+        BoundingBox::new(
+            Point::new(-1.0, self.minimum, -1.0),
+            Point::new(1.0, self.maximum, 1.0),
+        )
+    }
+
+    fn tessellate(&self, resolution: usize) -> Option<Mesh> {
+        if !self.minimum.is_finite() || !self.maximum.is_finite() {
+            return None;
+        }
 
-    x.powi(2) + z.powi(2) <= 1.0 + EPSILON
+        let profile = if self.closed {
+            vec![
+                (0.0, self.minimum),
+                (1.0, self.minimum),
+                (1.0, self.maximum),
+                (0.0, self.maximum),
+            ]
+        } else {
+            vec![(1.0, self.minimum), (1.0, self.maximum)]
+        };
+        Some(Mesh::revolve(&profile, resolution.max(3)))
+    }
 }
 
 #[cfg(test)]
@@ -189,9 +277,9 @@ mod tests {
     }
 
     a_ray_misses_a_cylinder! {
-        a_ray_misses_a_cylinder_1: (Tuple::point(1.0, 0.0, 0.0), Tuple::vector(0.0, 1.0, 0.0)),
-        a_ray_misses_a_cylinder_2: (Tuple::point(0.0, 0.0, 0.0), Tuple::vector(0.0, 1.0, 0.0)),
-        a_ray_misses_a_cylinder_3: (Tuple::point(0.0, 0.0, -5.0), Tuple::vector(1.0, 1.0, 1.0)),
+        a_ray_misses_a_cylinder_1: (Point::new(1.0, 0.0, 0.0), Vector::new(0.0, 1.0, 0.0)),
+        a_ray_misses_a_cylinder_2: (Point::new(0.0, 0.0, 0.0), Vector::new(0.0, 1.0, 0.0)),
+        a_ray_misses_a_cylinder_3: (Point::new(0.0, 0.0, -5.0), Vector::new(1.0, 1.0, 1.0)),
     }
 
     macro_rules! a_ray_strikes_a_cylinder {
@@ -215,9 +303,9 @@ mod tests {
     }
 
     a_ray_strikes_a_cylinder! {
-        a_ray_strikes_a_cylinder_1: (Tuple::point(1.0, 0.0, -5.0), Tuple::vector(0.0, 0.0, 1.0), 5.0, 5.0),
-        a_ray_strikes_a_cylinder_2: (Tuple::point(0.0, 0.0, -5.0), Tuple::vector(0.0, 0.0, 1.0), 4.0, 6.0),
-        a_ray_strikes_a_cylinder_3: (Tuple::point(0.5, 0.0, -5.0), Tuple::vector(0.1, 1.0, 1.0), 6.808006, 7.0886984),
+        a_ray_strikes_a_cylinder_1: (Point::new(1.0, 0.0, -5.0), Vector::new(0.0, 0.0, 1.0), 5.0, 5.0),
+        a_ray_strikes_a_cylinder_2: (Point::new(0.0, 0.0, -5.0), Vector::new(0.0, 0.0, 1.0), 4.0, 6.0),
+        a_ray_strikes_a_cylinder_3: (Point::new(0.5, 0.0, -5.0), Vector::new(0.1, 1.0, 1.0), 6.808006, 7.0886984),
     }
 
     macro_rules! normal_vector_on_a_cylinder {
@@ -227,7 +315,7 @@ mod tests {
             fn $name() {
                 let (point, normal) = $value;
                 let cyl = Cylinder::default();
-                let n = cyl.local_normal_at(point);
+                let n = cyl.local_normal_at(point, None);
 
                 assert_eq!(n, normal);
             }
@@ -236,18 +324,18 @@ mod tests {
     }
 
     normal_vector_on_a_cylinder! {
-        normal_vector_on_a_cylinder_1: (Tuple::point(1.0, 0.0, 0.0), Tuple::vector(1.0, 0.0, 0.0)),
-        normal_vector_on_a_cylinder_2: (Tuple::point(0.0, 5.0, -1.0), Tuple::vector(0.0, 0.0, -1.0)),
-        normal_vector_on_a_cylinder_3: (Tuple::point(0.0, -2.0, 1.0), Tuple::vector(0.0, 0.0, 1.0)),
-        normal_vector_on_a_cylinder_4: (Tuple::point(-1.0, 1.0, 0.0), Tuple::vector(-1.0, 0.0, 0.0)),
+        normal_vector_on_a_cylinder_1: (Point::new(1.0, 0.0, 0.0), Vector::new(1.0, 0.0, 0.0)),
+        normal_vector_on_a_cylinder_2: (Point::new(0.0, 5.0, -1.0), Vector::new(0.0, 0.0, -1.0)),
+        normal_vector_on_a_cylinder_3: (Point::new(0.0, -2.0, 1.0), Vector::new(0.0, 0.0, 1.0)),
+        normal_vector_on_a_cylinder_4: (Point::new(-1.0, 1.0, 0.0), Vector::new(-1.0, 0.0, 0.0)),
     }
 
     #[test]
     fn the_default_minimum_and_maximum_for_a_cylinder() {
         let cyl = Cylinder::default();
 
-        assert!(float_eq(cyl.minimum, MIN));
-        assert!(float_eq(cyl.maximum, MAX));
+        assert_eq!(cyl.minimum, f32::NEG_INFINITY);
+        assert_eq!(cyl.maximum, f32::INFINITY);
     }
 
     macro_rules! intersecting_a_contstrained_cylinder {
@@ -273,12 +361,12 @@ mod tests {
     }
 
     intersecting_a_contstrained_cylinder! {
-        intersecting_a_contstrained_cylinder_1: (Tuple::point(0.0, 1.5, 0.0), Tuple::vector(0.1, 1.0, 0.0), 0),
-        intersecting_a_contstrained_cylinder_2: (Tuple::point(0.0, 3.0, -5.0), Tuple::vector(0.0, 0.0, 1.0), 0),
-        intersecting_a_contstrained_cylinder_3: (Tuple::point(0.0, 0.0, -5.0), Tuple::vector(0.0, 0.0, 1.0), 0),
-        intersecting_a_contstrained_cylinder_4: (Tuple::point(0.0, 2.0, -5.0), Tuple::vector(0.0, 0.0, 1.0), 0),
-        intersecting_a_contstrained_cylinder_5: (Tuple::point(0.0, 1.0, -5.0), Tuple::vector(0.0, 0.0, 1.0), 0),
-        intersecting_a_contstrained_cylinder_6: (Tuple::point(0.0, 1.5, -2.0), Tuple::vector(0.0, 0.0, 1.0), 2),
+        intersecting_a_contstrained_cylinder_1: (Point::new(0.0, 1.5, 0.0), Vector::new(0.1, 1.0, 0.0), 0),
+        intersecting_a_contstrained_cylinder_2: (Point::new(0.0, 3.0, -5.0), Vector::new(0.0, 0.0, 1.0), 0),
+        intersecting_a_contstrained_cylinder_3: (Point::new(0.0, 0.0, -5.0), Vector::new(0.0, 0.0, 1.0), 0),
+        intersecting_a_contstrained_cylinder_4: (Point::new(0.0, 2.0, -5.0), Vector::new(0.0, 0.0, 1.0), 0),
+        intersecting_a_contstrained_cylinder_5: (Point::new(0.0, 1.0, -5.0), Vector::new(0.0, 0.0, 1.0), 0),
+        intersecting_a_contstrained_cylinder_6: (Point::new(0.0, 1.5, -2.0), Vector::new(0.0, 0.0, 1.0), 2),
     }
 
     #[test]
@@ -311,13 +399,12 @@ mod tests {
         }
     }
 
-    // TODO: not sure why 3 and 5 don't pass
     intersecting_the_caps_of_a_closed_cylinder! {
-        intersecting_the_caps_of_a_closed_cylinder_1: (Tuple::point(0.0, 3.0, 0.0), Tuple::vector(0.0, -1.0, 0.0), 2),
-        intersecting_the_caps_of_a_closed_cylinder_2: (Tuple::point(0.0, 3.0, -2.0), Tuple::vector(0.0, -1.0, 2.0), 2),
-        intersecting_the_caps_of_a_closed_cylinder_3: (Tuple::point(0.0, 4.0, -2.0), Tuple::vector(0.0, -1.0, 1.0), 2),
-        intersecting_the_caps_of_a_closed_cylinder_4: (Tuple::point(0.0, 0.0, -2.0), Tuple::vector(0.0, 1.0, 2.0), 2),
-        intersecting_the_caps_of_a_closed_cylinder_5: (Tuple::point(0.0, -1.0, -2.0), Tuple::vector(0.0, 1.0, 1.0), 2),
+        intersecting_the_caps_of_a_closed_cylinder_1: (Point::new(0.0, 3.0, 0.0), Vector::new(0.0, -1.0, 0.0), 2),
+        intersecting_the_caps_of_a_closed_cylinder_2: (Point::new(0.0, 3.0, -2.0), Vector::new(0.0, -1.0, 2.0), 2),
+        intersecting_the_caps_of_a_closed_cylinder_3: (Point::new(0.0, 4.0, -2.0), Vector::new(0.0, -1.0, 1.0), 2),
+        intersecting_the_caps_of_a_closed_cylinder_4: (Point::new(0.0, 0.0, -2.0), Vector::new(0.0, 1.0, 2.0), 2),
+        intersecting_the_caps_of_a_closed_cylinder_5: (Point::new(0.0, -1.0, -2.0), Vector::new(0.0, 1.0, 1.0), 2),
     }
 
     macro_rules! the_normal_vector_on_a_cylinders_end_caps {
@@ -333,7 +420,7 @@ mod tests {
                     ..Cylinder::default()
                 };
 
-                let n = cyl.local_normal_at(point);
+                let n = cyl.local_normal_at(point, None);
 
                 assert_eq!(n, normal);
             }
@@ -342,11 +429,91 @@ mod tests {
     }
 
     the_normal_vector_on_a_cylinders_end_caps! {
-        the_normal_vector_on_a_cylinders_end_caps_1: (Tuple::point(0.0, 1.0, 0.0), Tuple::vector(0.0, -1.0, 0.0)),
-        the_normal_vector_on_a_cylinders_end_caps_2: (Tuple::point(0.5, 1.0, 0.0), Tuple::vector(0.0, -1.0, 0.0)),
-        the_normal_vector_on_a_cylinders_end_caps_3: (Tuple::point(0.0, 1.0, 0.5), Tuple::vector(0.0, -1.0, 0.0)),
-        the_normal_vector_on_a_cylinders_end_caps_4: (Tuple::point(0.0, 2.0, 0.0), Tuple::vector(0.0, 1.0, 0.0)),
-        the_normal_vector_on_a_cylinders_end_caps_5: (Tuple::point(0.5, 2.0, 0.0), Tuple::vector(0.0, 1.0, 0.0)),
-        the_normal_vector_on_a_cylinders_end_caps_6: (Tuple::point(0.0, 2.0, 0.5), Tuple::vector(0.0, 1.0, 0.0)),
+        the_normal_vector_on_a_cylinders_end_caps_1: (Point::new(0.0, 1.0, 0.0), Vector::new(0.0, -1.0, 0.0)),
+        the_normal_vector_on_a_cylinders_end_caps_2: (Point::new(0.5, 1.0, 0.0), Vector::new(0.0, -1.0, 0.0)),
+        the_normal_vector_on_a_cylinders_end_caps_3: (Point::new(0.0, 1.0, 0.5), Vector::new(0.0, -1.0, 0.0)),
+        the_normal_vector_on_a_cylinders_end_caps_4: (Point::new(0.0, 2.0, 0.0), Vector::new(0.0, 1.0, 0.0)),
+        the_normal_vector_on_a_cylinders_end_caps_5: (Point::new(0.5, 2.0, 0.0), Vector::new(0.0, 1.0, 0.0)),
+        the_normal_vector_on_a_cylinders_end_caps_6: (Point::new(0.0, 2.0, 0.5), Vector::new(0.0, 1.0, 0.0)),
+    }
+
+    macro_rules! the_normal_vector_near_a_beveled_seam {
+        ($($name:ident: $value:expr,)*) => {
+        $(
+            #[test]
+            fn $name() {
+                let (point, normal) = $value;
+                let cyl = Cylinder::default().with_caps(1.0, 2.0).bevel(0.2);
+
+                assert_eq!(cyl.local_normal_at(point, None).normalize(), normal);
+            }
+        )*
+        };
+    }
+
+    the_normal_vector_near_a_beveled_seam! {
+        the_normal_vector_near_a_beveled_seam_on_the_side: (Point::new(1.0, 2.0, 0.0), Vector::new(1.0, 1.0, 0.0).normalize()),
+        the_normal_vector_near_a_beveled_seam_on_the_cap: (Point::new(0.99999, 2.0, 0.0), Vector::new(1.0, 1.0, 0.0).normalize()),
+        the_normal_vector_near_a_beveled_seam_below: (Point::new(0.0, 1.1, -1.0), Vector::new(0.0, -0.25, -0.75).normalize()),
+        the_normal_vector_beyond_a_beveled_seam_on_the_side: (Point::new(-1.0, 1.5, 0.0), Vector::new(-1.0, 0.0, 0.0)),
+        the_normal_vector_beyond_a_beveled_seam_on_the_cap: (Point::new(0.5, 2.0, 0.0), Vector::new(0.0, 1.0, 0.0)),
+    }
+
+    #[test]
+    fn an_open_cylinder_has_no_seam_to_bevel() {
+        let cyl = Cylinder::default().truncated(1.0, 2.0).bevel(0.2);
+
+        assert_eq!(
+            cyl.local_normal_at(Point::new(1.0, 1.95, 0.0), None),
+            Vector::new(1.0, 0.0, 0.0)
+        );
+    }
+
+    #[test]
+    fn an_unbounded_cylinder_has_a_bounding_box() {
+        let cyl = Cylinder::default();
+
+        let b = cyl.bounds();
+
+        assert!(float_eq(b.min.x(), -1.0));
+        assert_eq!(b.min.y(), f32::NEG_INFINITY);
+        assert!(float_eq(b.max.z(), 1.0));
+        assert_eq!(b.max.y(), f32::INFINITY);
+    }
+
+    #[test]
+    fn a_bounded_cylinder_has_a_bounding_box() {
+        let cyl = Cylinder::default().with_caps(-5.0, 3.0);
+
+        let b = cyl.bounds();
+
+        assert_eq!(b.min, Point::new(-1.0, -5.0, -1.0));
+        assert_eq!(b.max, Point::new(1.0, 3.0, 1.0));
+    }
+
+    #[test]
+    fn an_infinite_cylinder_cannot_be_tessellated() {
+        assert!(Cylinder::default().tessellate(16).is_none());
+    }
+
+    #[test]
+    fn tessellating_a_capped_cylinder() {
+        let mesh = Cylinder::default()
+            .with_caps(1.0, 2.0)
+            .tessellate(64)
+            .unwrap();
+
+        assert!(mesh.is_closed());
+        assert!((mesh.signed_volume() - std::f32::consts::PI).abs() < 0.01);
+    }
+
+    #[test]
+    fn tessellating_a_truncated_open_cylinder() {
+        let c = Cylinder::default().truncated(1.0, 2.0);
+
+        let mesh = c.tessellate(16).unwrap();
+
+        assert_eq!(mesh.vertices.len(), 32);
+        assert!(!mesh.is_closed());
     }
 }
@@ -0,0 +1,517 @@
+use std::any::Any;
+
+use uuid::Uuid;
+
+use crate::{
+    bounds::BoundingBox,
+    color::Color,
+    intersection::Intersection,
+    materials::Material,
+    ray::Ray,
+    shapes::{Shape, ShapeBuilder, Tags, Visibility},
+    transformations::{Transform, IDENTITY},
+    tuple::{Point, Vector},
+};
+
+const NEWTON_ITERATIONS: usize = 16;
+const NEWTON_TOLERANCE: f32 = 1e-5;
+const REFINEMENTS: usize = 3;
+
+/// A smooth, invertible bending of space, taking a point on the plain
+/// shape to where it ends up.
+#[derive(Clone, Copy, Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum Deformation {
+    /// Turns each slice of the shape around the y axis, by this many
+    /// radians for each unit of height.
+    Twist(f32),
+    /// Widens x and z by this much for each unit of height, narrowing
+    /// below y = 0. The shape must stay where the width is positive.
+    Taper(f32),
+    /// Curls the y axis round towards +x into an arc of this curvature,
+    /// one over the arc's radius.
+    Bend(f32),
+}
+
+impl Deformation {
+    pub fn apply(&self, point: Point) -> Point {
+        let (x, y, z) = (point.x(), point.y(), point.z());
+        match *self {
+            Deformation::Twist(rate) => Transform::rotation_y(rate * y) * point,
+            Deformation::Taper(rate) => {
+                let scale = 1.0 + rate * y;
+                Point::new(x * scale, y, z * scale)
+            }
+            Deformation::Bend(0.0) => point,
+            Deformation::Bend(curvature) => {
+                let radius = 1.0 / curvature - x;
+                let angle = curvature * y;
+                Point::new(
+                    1.0 / curvature - radius * angle.cos(),
+                    radius * angle.sin(),
+                    z,
+                )
+            }
+        }
+    }
+
+    /// The point that `apply` takes to `point`, found by Newton's method
+    /// starting from `point` itself.
+    pub fn invert(&self, point: Point) -> Point {
+        let mut guess = point;
+        for _ in 0..NEWTON_ITERATIONS {
+            let error = self.apply(guess) - point;
+            if error.magnitude() < NEWTON_TOLERANCE {
+                break;
+            }
+            guess = guess - self.jacobian(guess).inverse() * error;
+        }
+        guess
+    }
+
+    /// How `apply` stretches space around `point`, estimated by central
+    /// differences.
+    pub fn jacobian(&self, point: Point) -> Transform {
+        const H: f32 = 1e-3;
+        let column = |step: Vector| {
+            (self.apply(point + step * H) - self.apply(point - step * H)) / (2.0 * H)
+        };
+        let (dx, dy, dz) = (
+            column(Vector::new(1.0, 0.0, 0.0)),
+            column(Vector::new(0.0, 1.0, 0.0)),
+            column(Vector::new(0.0, 0.0, 1.0)),
+        );
+
+        Transform::from([
+            [dx.x(), dy.x(), dz.x(), 0.0],
+            [dx.y(), dy.y(), dz.y(), 0.0],
+            [dx.z(), dy.z(), dz.z(), 0.0],
+            [0.0, 0.0, 0.0, 1.0],
+        ])
+    }
+}
+
+/// A shape twisted, tapered or bent by a `Deformation`. Rays curve once
+/// they're taken back into the plain shape's space, so each is followed in
+/// `steps` straight pieces, and any hit is then narrowed down on ever
+/// shorter pieces around it. Normals are carried through the deformation's
+/// Jacobian. Only finite shapes can be deformed; unbounded ones like planes
+/// are never hit.
+#[derive(Debug)]
+#[cfg_attr(
+    feature = "serde",
+    derive(serde::Serialize, serde::Deserialize),
+    serde(from = "crate::serialization::DeformedShapeFields")
+)]
+pub struct DeformedShape {
+    #[cfg_attr(feature = "serde", serde(skip))]
+    id: Uuid,
+    #[cfg_attr(feature = "serde", serde(skip))]
+    parent: Option<Uuid>,
+    visibility: Visibility,
+    tags: Tags,
+    transform: Transform,
+    pub shape: Box<dyn Shape>,
+    pub deformation: Deformation,
+    pub steps: usize,
+    #[cfg_attr(feature = "serde", serde(skip_serializing))]
+    bounds: BoundingBox,
+}
+
+impl DeformedShape {
+    pub fn new(shape: Box<dyn Shape>, deformation: Deformation) -> Self {
+        let bounds = deformed_bounds(shape.parent_space_bounds(), deformation);
+        Self {
+            id: Uuid::new_v4(),
+            parent: None,
+            visibility: Visibility::default(),
+            tags: Tags::new(),
+            transform: IDENTITY,
+            shape,
+            deformation,
+            steps: 32,
+            bounds,
+        }
+    }
+
+    /// More steps follow strongly deformed rays more closely.
+    pub fn steps(self, steps: usize) -> Self {
+        Self {
+            steps: steps.max(1),
+            ..self
+        }
+    }
+
+    /// Hits on the plain shape along the straight piece between where
+    /// `ray` is at `t0` and `t1`, taken back into the plain shape's space,
+    /// with `t` along `ray`.
+    fn hits_between(&self, ray: Ray, t0: f32, t1: f32) -> Vec<Intersection> {
+        let start = self.deformation.invert(ray.position(t0));
+        let end = self.deformation.invert(ray.position(t1));
+        let piece = Ray::new(start, end - start).kind(ray.kind);
+
+        self.shape
+            .intersect(piece)
+            .into_iter()
+            .filter(|i| (0.0..1.0).contains(&i.t))
+            .map(|i| Intersection::new(t0 + i.t * (t1 - t0), self).inner(i))
+            .collect()
+    }
+
+    /// Narrows `hit` down on shorter and shorter pieces of `ray` around
+    /// it, each a quarter the length of the last.
+    fn refine<'a>(&'a self, ray: Ray, mut hit: Intersection<'a>, step: f32) -> Intersection<'a> {
+        let mut half = step / 2.0;
+        for _ in 0..REFINEMENTS {
+            half /= 4.0;
+            let nearest = self
+                .hits_between(ray, hit.t - half, hit.t + half)
+                .into_iter()
+                .min_by(|a, b| {
+                    (a.t - hit.t)
+                        .abs()
+                        .partial_cmp(&(b.t - hit.t).abs())
+                        .unwrap()
+                });
+            match nearest {
+                Some(closer) => hit = closer,
+                None => break,
+            }
+        }
+        hit
+    }
+}
+
+/// The box around `bounds` once deformed, found from a lattice of points
+/// through it and padded a little for the curves between them.
+fn deformed_bounds(bounds: BoundingBox, deformation: Deformation) -> BoundingBox {
+    if bounds.is_empty() || !bounds.is_finite() {
+        return bounds;
+    }
+
+    const N: usize = 8;
+    let mut deformed = BoundingBox::default();
+    for i in 0..=N {
+        for j in 0..=N {
+            for k in 0..=N {
+                let at = |min: f32, max: f32, n: usize| min + (max - min) * n as f32 / N as f32;
+                let point = Point::new(
+                    at(bounds.min.x(), bounds.max.x(), i),
+                    at(bounds.min.y(), bounds.max.y(), j),
+                    at(bounds.min.z(), bounds.max.z(), k),
+                );
+                deformed = deformed.add_point(deformation.apply(point));
+            }
+        }
+    }
+
+    let pad = (deformed.max - deformed.min) * 0.05;
+    BoundingBox::new(deformed.min - pad, deformed.max + pad)
+}
+
+impl ShapeBuilder for DeformedShape {
+    fn with_transform(self, transform: Transform) -> Self {
+        Self { transform, ..self }
+    }
+
+    fn with_material(mut self, material: Material) -> Self {
+        self.shape.set_material(material);
+        self
+    }
+}
+
+impl Shape for DeformedShape {
+    fn id(&self) -> Uuid {
+        self.id
+    }
+
+    fn as_any(&self) -> &dyn Any {
+        self
+    }
+
+    fn transform(&self) -> &Transform {
+        &self.transform
+    }
+
+    fn set_transform(&mut self, transform: Transform) {
+        self.transform = transform;
+    }
+
+    fn material(&self) -> &Material {
+        self.shape.material()
+    }
+
+    fn material_mut(&mut self) -> &mut Material {
+        self.shape.material_mut()
+    }
+
+    fn set_material(&mut self, material: Material) {
+        self.shape.set_material(material);
+    }
+
+    fn parent(&self) -> Option<Uuid> {
+        self.parent
+    }
+
+    fn set_parent(&mut self, parent: Uuid) {
+        self.parent = Some(parent);
+    }
+
+    fn clear_parent(&mut self) {
+        self.parent = None;
+    }
+
+    fn visibility(&self) -> Visibility {
+        self.visibility
+    }
+
+    fn set_visibility(&mut self, visibility: Visibility) {
+        self.visibility = visibility;
+    }
+
+    fn tags(&self) -> &Tags {
+        &self.tags
+    }
+
+    fn tags_mut(&mut self) -> &mut Tags {
+        &mut self.tags
+    }
+
+    fn parameters(&self) -> Vec<(&'static str, String)> {
+        vec![
+            ("shape", self.shape.type_name().to_string()),
+            ("deformation", format!("{:?}", self.deformation)),
+            ("steps", format!("{:?}", self.steps)),
+        ]
+    }
+
+    fn local_intersect(&self, ray: Ray) -> Vec<Intersection> {
+        if !self.bounds.is_finite() {
+            return vec![];
+        }
+        // Hits behind the ray's origin count too, so refraction can tell
+        // which shapes the hit is inside.
+        let (t_min, t_max) = match self.bounds.ray_range(ray) {
+            Some((t_min, t_max)) => (t_min.max(ray.t_min), t_max.min(ray.t_max)),
+            None => return vec![],
+        };
+        if t_min > t_max {
+            return vec![];
+        }
+
+        let step = (t_max - t_min) / self.steps as f32;
+        (0..self.steps)
+            .flat_map(|i| {
+                let t0 = t_min + step * i as f32;
+                self.hits_between(ray, t0, t0 + step)
+            })
+            .map(|hit| self.refine(ray, hit, step))
+            .collect()
+    }
+
+    /// The plain shape's normal, at the point the deformation took to
+    /// `point`, carried through the deformation there.
+    fn local_normal_at(&self, point: Point, hit: Option<&Intersection>) -> Vector {
+        let plain_point = self.deformation.invert(point);
+        let normal = match hit.and_then(Intersection::inner_hit) {
+            Some(inner) => inner.object.normal_at(plain_point, Some(&inner)),
+            None => self.shape.normal_at(plain_point, None),
+        };
+
+        (self.deformation.jacobian(plain_point).inverse().transpose() * normal).normalize()
+    }
+
+    fn surface_color(&self, hit: &Intersection) -> Option<Color> {
+        hit.inner_hit()
+            .and_then(|inner| inner.object.surface_color(&inner))
+    }
+
+    fn bounds(&self) -> BoundingBox {
+        self.bounds
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::f32::consts::PI;
+
+    use crate::{
+        float_eq,
+        shapes::{cube::Cube, group::Group, sphere::Sphere},
+    };
+
+    use super::*;
+
+    macro_rules! round_trip_tests {
+        ($($name:ident: $value:expr,)*) => {
+            $(
+                #[test]
+                fn $name() {
+                    let deformation = $value;
+                    let p = Point::new(0.3, 0.7, -0.4);
+
+                    let deformed = deformation.apply(p);
+
+                    assert_ne!(deformed, p);
+                    assert_eq!(deformation.invert(deformed), p);
+                }
+            )*
+        }
+    }
+
+    round_trip_tests! {
+        inverting_a_twist: Deformation::Twist(PI / 2.0),
+        inverting_a_taper: Deformation::Taper(0.5),
+        inverting_a_bend: Deformation::Bend(0.8),
+    }
+
+    #[test]
+    fn a_twist_turns_each_slice_by_its_height() {
+        let twist = Deformation::Twist(PI / 2.0);
+
+        assert_eq!(
+            twist.apply(Point::new(1.0, 0.0, 0.0)),
+            Point::new(1.0, 0.0, 0.0)
+        );
+        assert_eq!(
+            twist.apply(Point::new(1.0, 1.0, 0.0)),
+            Point::new(0.0, 1.0, -1.0)
+        );
+    }
+
+    #[test]
+    fn a_bend_curls_the_y_axis_into_an_arc() {
+        let bend = Deformation::Bend(1.0);
+
+        assert_eq!(
+            bend.apply(Point::new(0.0, PI / 2.0, 0.0)),
+            Point::new(1.0, 1.0, 0.0)
+        );
+        assert_eq!(
+            Deformation::Bend(0.0).apply(Point::new(0.0, 2.0, 0.0)),
+            Point::new(0.0, 2.0, 0.0)
+        );
+    }
+
+    #[test]
+    fn the_jacobian_of_a_taper() {
+        let j = Deformation::Taper(0.5).jacobian(Point::new(2.0, 1.0, 0.0));
+
+        assert_eq!(
+            j,
+            Transform::from([
+                [1.5, 1.0, 0.0, 0.0],
+                [0.0, 1.0, 0.0, 0.0],
+                [0.0, 0.0, 1.5, 0.0],
+                [0.0, 0.0, 0.0, 1.0],
+            ])
+        );
+    }
+
+    #[test]
+    fn a_tapered_cube_is_wider_at_the_top() {
+        let shape = DeformedShape::new(Box::new(Cube::default()), Deformation::Taper(0.5));
+        let at = |y: f32| {
+            let xs = shape.intersect(Ray::new(
+                Point::new(-5.0, y, 0.0),
+                Vector::new(1.0, 0.0, 0.0),
+            ));
+            xs[0].t
+        };
+
+        assert!(float_eq(at(0.0), 4.0));
+        assert!(float_eq(at(0.8), 3.6));
+        assert!(float_eq(at(-0.8), 4.4));
+    }
+
+    #[test]
+    fn a_ray_from_inside_a_deformed_shape_finds_the_hit_behind_it() {
+        let shape = DeformedShape::new(Box::new(Cube::default()), Deformation::Taper(0.5));
+        let r = Ray::new(Point::new(0.0, 0.0, 0.0), Vector::new(1.0, 0.0, 0.0));
+
+        let xs = shape.intersect(r);
+
+        assert_eq!(xs.len(), 2);
+        assert!(float_eq(xs[0].t, -1.0));
+        assert!(float_eq(xs[1].t, 1.0));
+    }
+
+    #[test]
+    fn the_normal_on_a_deformed_group_follows_the_groups_transform() {
+        let group = || {
+            let mut g = Group::new().with_transform(
+                Transform::rotation_x(PI / 3.0) * Transform::scaling(1.0, 2.0, 1.0),
+            );
+            g.add_child(Box::new(
+                Sphere::default().with_transform(Transform::translation(0.0, 0.5, 0.0)),
+            ));
+            g
+        };
+        let shape = DeformedShape::new(Box::new(group()), Deformation::Twist(0.0));
+        let plain = group();
+        let r = Ray::new(Point::new(0.1, 0.3, -5.0), Vector::new(0.0, 0.0, 1.0));
+
+        let xs = shape.intersect(r);
+        let expected = plain.intersect(r);
+
+        assert!(float_eq(xs[0].t, expected[0].t));
+        assert_eq!(
+            xs[0].prepare_computations(r, &xs).normalv,
+            expected[0].prepare_computations(r, &expected).normalv
+        );
+    }
+
+    #[test]
+    fn a_ray_missing_a_deformed_shape() {
+        let shape = DeformedShape::new(Box::new(Sphere::default()), Deformation::Twist(1.0));
+        let r = Ray::new(Point::new(0.0, 3.0, -5.0), Vector::new(0.0, 0.0, 1.0));
+
+        assert!(shape.intersect(r).is_empty());
+    }
+
+    #[test]
+    fn a_twisted_cube_is_hit_on_its_turned_faces() {
+        let shape = DeformedShape::new(Box::new(Cube::default()), Deformation::Twist(PI / 3.0));
+        let r = Ray::new(Point::new(0.0, 0.5, -5.0), Vector::new(0.0, 0.0, 1.0));
+
+        let xs = shape.intersect(r);
+
+        assert_eq!(xs.len(), 2);
+        assert!(float_eq(xs[0].t, 5.0 - 2.0 / 3.0_f32.sqrt()));
+        assert_eq!(xs[0].object.id(), shape.id());
+        assert_eq!(
+            shape.normal_at(r.position(xs[0].t), Some(&xs[0])),
+            Vector::new(-0.5, -PI / (3.0 * 3.0_f32.sqrt()), -(3.0_f32.sqrt()) / 2.0).normalize()
+        );
+    }
+
+    #[test]
+    fn the_normal_on_a_tapered_cube_tilts_with_its_sides() {
+        let shape = DeformedShape::new(Box::new(Cube::default()), Deformation::Taper(0.5));
+        let r = Ray::new(Point::new(-5.0, 0.0, 0.0), Vector::new(1.0, 0.0, 0.0));
+        let xs = shape.intersect(r);
+
+        let n = shape.normal_at(r.position(xs[0].t), Some(&xs[0]));
+
+        assert_eq!(n, Vector::new(-1.0, -0.5, 0.0).normalize());
+    }
+
+    #[test]
+    fn a_deformed_shape_is_bounded_around_its_deformed_form() {
+        let shape = DeformedShape::new(Box::new(Cube::default()), Deformation::Taper(0.5));
+        let bounds = shape.bounds();
+
+        assert!(bounds.contains_point(Point::new(1.5, 1.0, 1.5)));
+        assert!(bounds.contains_point(Point::new(-0.5, -1.0, -0.5)));
+        assert!(!bounds.contains_point(Point::new(1.8, 0.0, 0.0)));
+    }
+
+    #[test]
+    fn a_deformed_shape_takes_its_material_from_the_shape_it_wraps() {
+        let shape = DeformedShape::new(Box::new(Sphere::default()), Deformation::Bend(0.5))
+            .with_material(Material::default().diffuse(0.3));
+
+        assert_eq!(shape.material(), shape.shape.material());
+        assert_eq!(shape.material().diffuse, 0.3);
+    }
+}
@@ -1,31 +1,113 @@
+use std::{any::Any, sync::OnceLock};
+
 use uuid::Uuid;
 
 use crate::{
-    intersection::Intersection,
+    bounds::BoundingBox,
+    bvh::Bvh,
+    intersection::{Intersection, Intersections},
     materials::Material,
     ray::Ray,
-    shapes::Shape,
+    shapes::{Shape, ShapeBuilder, Tags, Visibility},
     transformations::{Transform, IDENTITY},
-    tuple::Tuple,
+    tuple::{Point, Vector},
 };
 
 #[derive(Debug, PartialEq)]
+#[cfg_attr(
+    feature = "serde",
+    derive(serde::Serialize, serde::Deserialize),
+    serde(from = "crate::serialization::GroupFields")
+)]
 pub struct Group {
+    #[cfg_attr(feature = "serde", serde(skip))]
     id: Uuid,
+    #[cfg_attr(feature = "serde", serde(skip))]
     parent: Option<Uuid>,
+    visibility: Visibility,
+    tags: Tags,
     pub transform: Transform,
     pub material: Material,
+    /// Changing these in place once the group has been intersected leaves
+    /// its index out of date; go through `add_child` or `children_mut`.
     pub objects: Vec<Box<dyn Shape>>,
+    /// A hierarchy over the children's bounds, built the first time a ray
+    /// is traced through the group, so it only tests the children the ray
+    /// might hit.
+    #[cfg_attr(feature = "serde", serde(skip))]
+    index: OnceLock<Bvh>,
 }
 
 impl Group {
-    fn new() -> Self {
+    pub fn new() -> Self {
         Self::default()
     }
 
-    fn add_child(&mut self, mut child: Box<dyn Shape>) {
+    pub fn add_child(&mut self, mut child: Box<dyn Shape>) {
         child.set_parent(self.id);
-        self.objects.push(child)
+        self.objects.push(child);
+        self.index = OnceLock::new();
+    }
+
+    fn index(&self) -> &Bvh {
+        self.index.get_or_init(|| {
+            let bounds = self
+                .objects
+                .iter()
+                .map(|child| child.parent_space_bounds())
+                .collect::<Vec<_>>();
+            Bvh::build(&bounds)
+        })
+    }
+
+    /// Every leaf shape in the hierarchy, in the group's parent space, with
+    /// inherited materials, group visibility and group tags folded in, so
+    /// they render the same without any groups around them. A leaf's own
+    /// tags win over its groups'.
+    pub fn flatten(mut self) -> Vec<Box<dyn Shape>> {
+        self.bake_transforms();
+
+        let mut leaves = vec![];
+        collect_leaves(
+            self.objects,
+            &self.material,
+            self.visibility,
+            &self.tags,
+            &mut leaves,
+        );
+        leaves
+    }
+}
+
+fn collect_leaves(
+    children: Vec<Box<dyn Shape>>,
+    material: &Material,
+    visibility: Visibility,
+    tags: &Tags,
+    leaves: &mut Vec<Box<dyn Shape>>,
+) {
+    for mut child in children {
+        let material = if child.inherits_material() {
+            material.clone()
+        } else {
+            child.material().clone()
+        };
+        let visibility = child.visibility().combine(visibility);
+        let mut tags = tags.clone();
+        tags.extend(child.tags().clone());
+
+        let grandchildren = child.take_children();
+        if child.as_any().is::<Group>() && grandchildren.is_empty() {
+            continue;
+        } else if grandchildren.is_empty() {
+            child.set_material(material);
+            child.set_visibility(visibility);
+            *child.tags_mut() = tags;
+            child.clear_parent();
+            leaves.push(child);
+        } else {
+            collect_leaves(grandchildren, &material, visibility, &tags, leaves);
+        }
     }
 }
 
@@ -34,18 +116,35 @@ impl Default for Group {
         Self {
             id: Uuid::new_v4(),
             parent: None,
+            visibility: Visibility::default(),
+            tags: Tags::new(),
             transform: IDENTITY,
             material: Material::default(),
             objects: vec![],
+            index: OnceLock::new(),
         }
     }
 }
 
+impl ShapeBuilder for Group {
+    fn with_transform(self, transform: Transform) -> Self {
+        Self { transform, ..self }
+    }
+
+    fn with_material(self, material: Material) -> Self {
+        Self { material, ..self }
+    }
+}
+
 impl Shape for Group {
     fn id(&self) -> uuid::Uuid {
         self.id
     }
 
+    fn as_any(&self) -> &dyn Any {
+        self
+    }
+
     fn transform(&self) -> &Transform {
         &self.transform
     }
@@ -74,28 +173,83 @@ impl Shape for Group {
         self.parent = Some(parent);
     }
 
-    fn local_intersect(&self, ray: Ray) -> Vec<Intersection> {
-        let mut result = vec![];
+    fn clear_parent(&mut self) {
+        self.parent = None;
+    }
+
+    fn visibility(&self) -> Visibility {
+        self.visibility
+    }
+
+    fn set_visibility(&mut self, visibility: Visibility) {
+        self.visibility = visibility;
+    }
+
+    fn tags(&self) -> &Tags {
+        &self.tags
+    }
+
+    fn tags_mut(&mut self) -> &mut Tags {
+        &mut self.tags
+    }
+
+    fn children(&self) -> &[Box<dyn Shape>] {
+        &self.objects
+    }
 
-        for object in &self.objects {
-            let intersections = object.intersect(ray);
-            for intersection in intersections {
-                result.push(intersection);
-            }
+    fn children_mut(&mut self) -> &mut [Box<dyn Shape>] {
+        self.index = OnceLock::new();
+        &mut self.objects
+    }
+
+    fn take_children(&mut self) -> Vec<Box<dyn Shape>> {
+        self.index = OnceLock::new();
+        std::mem::take(&mut self.objects)
+    }
+
+    fn bake_transforms(&mut self) {
+        self.index = OnceLock::new();
+        for child in self.objects.iter_mut() {
+            child.set_transform(self.transform * *child.transform());
+            child.bake_transforms();
         }
+        self.transform = IDENTITY;
+    }
 
-        result.sort_by(|a, b| a.partial_cmp(b).unwrap());
-        result
+    fn local_intersect(&self, ray: Ray) -> Vec<Intersection> {
+        self.index()
+            .candidates(ray)
+            .into_iter()
+            .fold(Intersections::new(), |result, i| {
+                result.merge(self.objects[i].intersect(ray).into())
+            })
+            .into_vec()
+            .into_iter()
+            .map(|i| i.within(&self.transform))
+            .collect()
     }
 
-    fn local_normal_at(&self, _point: Tuple) -> Tuple {
+    fn local_normal_at(&self, _point: Point, _hit: Option<&Intersection>) -> Vector {
         panic!("Don't call me bro!")
     }
+
+    fn bounds(&self) -> BoundingBox {
+        self.objects
+            .iter()
+            .fold(BoundingBox::default(), |bounds, child| {
+                bounds.merge(child.parent_space_bounds())
+            })
+    }
 }
 
 #[cfg(test)]
 mod tests {
-    use crate::shapes::{sphere::Sphere, ShapeBuilder, TestShape};
+    use std::f32::consts::PI;
+
+    use crate::{
+        float_eq,
+        shapes::{cylinder::Cylinder, sphere::Sphere, TestShape},
+    };
 
     use super::*;
 
@@ -174,4 +328,232 @@ mod tests {
         let xs = g.intersect(r);
         assert_eq!(xs.len(), 2);
     }
+
+    #[test]
+    fn a_group_only_tests_the_children_a_ray_can_reach() {
+        let mut g = Group::new();
+        for i in 0..10 {
+            g.add_child(Box::new(
+                Sphere::new().with_transform(Transform::translation(i as f32 * 3.0, 0.0, 0.0)),
+            ));
+        }
+        let r = Ray::default()
+            .origin(6.0, 0.0, -5.0)
+            .direction(0.0, 0.0, 1.0);
+
+        let (xs, stats) = crate::stats::collect(|| g.intersect(r));
+
+        assert_eq!(xs.len(), 2);
+        assert_eq!(stats.intersection_tests["Sphere"], 1);
+    }
+
+    #[test]
+    fn a_child_added_after_intersecting_is_still_found() {
+        let mut g = Group::new();
+        g.add_child(Box::new(Sphere::new()));
+        let r = Ray::default()
+            .origin(5.0, 0.0, -5.0)
+            .direction(0.0, 0.0, 1.0);
+        assert!(g.intersect(r).is_empty());
+
+        g.add_child(Box::new(
+            Sphere::new().with_transform(Transform::translation(5.0, 0.0, 0.0)),
+        ));
+
+        assert_eq!(g.intersect(r).len(), 2);
+    }
+
+    #[test]
+    fn converting_a_point_from_world_to_object_space() {
+        let mut g2 = Group::new().with_transform(Transform::scaling(2.0, 2.0, 2.0));
+        g2.add_child(Box::new(
+            Sphere::new().with_transform(Transform::translation(5.0, 0.0, 0.0)),
+        ));
+        let mut g1 = Group::new().with_transform(Transform::rotation_y(PI / 2.0));
+        g1.add_child(Box::new(g2));
+        let r = Ray::default()
+            .origin(-5.0, 0.0, -10.0)
+            .direction(1.0, 0.0, 0.0);
+
+        let xs = g1.intersect(r);
+        let p = xs[0]
+            .object
+            .world_to_object(r.position(xs[0].t), &xs[0].groups);
+
+        assert!(float_eq(xs[0].t, 3.0));
+        assert_eq!(p, Point::new(0.0, 0.0, -1.0));
+    }
+
+    #[test]
+    fn finding_the_normal_on_a_child_object() {
+        let s = Sphere::new().with_transform(Transform::translation(5.0, 0.0, 0.0));
+        let hit = Intersection::new(1.0, &s)
+            .within(&Transform::scaling(1.0, 2.0, 3.0))
+            .within(&Transform::rotation_y(PI / 2.0));
+
+        let n = s.normal_at(Point::new(1.7321, 1.1547, -5.5774), Some(&hit));
+
+        assert_eq!(n, Vector::new(0.2857, 0.4286, -0.8571));
+        assert_ne!(s.normal_at(Point::new(1.7321, 1.1547, -5.5774), None), n);
+    }
+
+    #[test]
+    fn a_group_has_a_bounding_box_that_contains_its_children() {
+        let s = Sphere::default().with_transform(
+            Transform::translation(2.0, 5.0, -3.0) * Transform::scaling(2.0, 2.0, 2.0),
+        );
+        let c = Cylinder::default().with_caps(-2.0, 2.0).with_transform(
+            Transform::translation(-4.0, -1.0, 4.0) * Transform::scaling(0.5, 1.0, 0.5),
+        );
+        let mut g = Group::new();
+        g.add_child(Box::new(s));
+        g.add_child(Box::new(c));
+
+        let b = g.bounds();
+
+        assert_eq!(b.min, Point::new(-4.5, -3.0, -5.0));
+        assert_eq!(b.max, Point::new(4.0, 7.0, 4.5));
+    }
+
+    #[test]
+    fn an_empty_group_has_an_empty_bounding_box() {
+        assert!(Group::new().bounds().is_empty());
+    }
+
+    #[test]
+    fn a_grouped_shape_with_the_default_material_inherits() {
+        let mut g = Group::new();
+        g.add_child(Box::new(Sphere::new()));
+        g.add_child(Box::new(
+            Sphere::new().with_material(Material::default().ambient(1.0)),
+        ));
+
+        assert!(g.children()[0].inherits_material());
+        assert!(!g.children()[1].inherits_material());
+        assert!(!Sphere::new().inherits_material());
+    }
+
+    fn nested_groups() -> Group {
+        let s1 = Sphere::new().with_transform(Transform::translation(5.0, 0.0, 0.0));
+        let s2 = Sphere::new()
+            .with_transform(Transform::translation(0.0, 0.0, -3.0))
+            .with_material(Material::default().ambient(1.0));
+        let mut inner = Group::new().with_transform(Transform::rotation_y(PI / 2.0));
+        inner.add_child(Box::new(s1));
+        let mut outer = Group::new()
+            .with_transform(Transform::scaling(2.0, 2.0, 2.0))
+            .with_material(Material::default().diffuse(0.3));
+        outer.add_child(Box::new(inner));
+        outer.add_child(Box::new(s2));
+        outer
+    }
+
+    fn hit_distances(xs: &[Intersection]) -> Vec<f32> {
+        xs.iter().map(|i| i.t).collect()
+    }
+
+    #[test]
+    fn baking_transforms_leaves_the_group_with_the_identity() {
+        let mut g = nested_groups();
+
+        g.bake_transforms();
+
+        assert_eq!(g.transform, IDENTITY);
+        assert_eq!(g.objects[0].transform(), &IDENTITY);
+        assert_eq!(
+            g.objects[0].children()[0].transform(),
+            &(Transform::scaling(2.0, 2.0, 2.0)
+                * Transform::rotation_y(PI / 2.0)
+                * Transform::translation(5.0, 0.0, 0.0))
+        );
+        assert_eq!(
+            g.objects[1].transform(),
+            &(Transform::scaling(2.0, 2.0, 2.0) * Transform::translation(0.0, 0.0, -3.0))
+        );
+    }
+
+    #[test]
+    fn baking_transforms_does_not_change_what_a_ray_hits() {
+        let r = Ray::new(Point::new(0.0, 0.0, -20.0), Vector::new(0.0, 0.0, 1.0));
+        let g = nested_groups();
+        let mut baked = nested_groups();
+        baked.bake_transforms();
+
+        assert_eq!(
+            hit_distances(&baked.intersect(r)),
+            hit_distances(&g.intersect(r))
+        );
+        assert_eq!(hit_distances(&g.intersect(r)).len(), 4);
+    }
+
+    #[test]
+    fn flattening_a_group_lists_its_leaves() {
+        let r = Ray::new(Point::new(0.0, 0.0, -20.0), Vector::new(0.0, 0.0, 1.0));
+        let expected = hit_distances(&nested_groups().intersect(r));
+
+        let leaves = nested_groups().flatten();
+        let xs = leaves
+            .iter()
+            .flat_map(|leaf| leaf.intersect(r))
+            .collect::<Intersections>();
+
+        assert_eq!(leaves.len(), 2);
+        assert!(leaves.iter().all(|leaf| leaf.parent().is_none()));
+        assert_eq!(hit_distances(&xs), expected);
+    }
+
+    #[test]
+    fn flattening_leaves_out_empty_groups() {
+        let mut g = nested_groups();
+        g.add_child(Box::new(Group::new()));
+
+        assert_eq!(g.flatten().len(), 2);
+    }
+
+    #[test]
+    fn flattening_bakes_in_inherited_materials() {
+        let leaves = nested_groups().flatten();
+
+        assert!(float_eq(leaves[0].material().diffuse, 0.3));
+        assert!(float_eq(leaves[1].material().ambient, 1.0));
+        assert!(float_eq(leaves[1].material().diffuse, 0.9));
+    }
+
+    #[test]
+    fn flattening_bakes_in_group_visibility() {
+        let mut g = nested_groups();
+        g.set_visibility(Visibility::default().visible_to_shadows(false));
+        g.objects[1].set_visibility(Visibility::default().visible_to_camera(false));
+
+        let leaves = g.flatten();
+
+        assert_eq!(
+            leaves[0].visibility(),
+            Visibility::default().visible_to_shadows(false)
+        );
+        assert_eq!(
+            leaves[1].visibility(),
+            Visibility::default()
+                .visible_to_shadows(false)
+                .visible_to_camera(false)
+        );
+    }
+
+    #[test]
+    fn flattening_bakes_in_group_tags() {
+        let mut g = nested_groups().with_tag("set", "outer");
+        g.objects[0]
+            .tags_mut()
+            .insert("part".to_string(), "inner".to_string());
+        g.objects[1]
+            .tags_mut()
+            .insert("set".to_string(), "own".to_string());
+
+        let leaves = g.flatten();
+
+        assert_eq!(leaves[0].tag("set"), Some("outer"));
+        assert_eq!(leaves[0].tag("part"), Some("inner"));
+        assert_eq!(leaves[1].tag("set"), Some("own"));
+        assert_eq!(leaves[1].tag("part"), None);
+    }
 }
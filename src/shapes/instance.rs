@@ -0,0 +1,364 @@
+use std::{any::Any, sync::Arc};
+
+use uuid::Uuid;
+
+use crate::{
+    bounds::BoundingBox,
+    color::Color,
+    intersection::Intersection,
+    materials::Material,
+    ray::Ray,
+    shapes::{Shape, ShapeBuilder, Tags, Visibility},
+    transformations::{Transform, IDENTITY},
+    tuple::{Point, Vector},
+};
+
+/// Another placement of a shape that's shared rather than copied, so a
+/// large mesh can appear many times while its triangles are held once,
+/// even by renders on other threads.
+/// The instance has its own id, transform and visibility, and may replace
+/// the shared shape's material; everything else comes from the original.
+#[derive(Debug)]
+#[cfg_attr(
+    feature = "serde",
+    derive(serde::Serialize, serde::Deserialize),
+    serde(from = "crate::serialization::InstanceFields")
+)]
+pub struct Instance {
+    #[cfg_attr(feature = "serde", serde(skip))]
+    id: Uuid,
+    #[cfg_attr(feature = "serde", serde(skip))]
+    parent: Option<Uuid>,
+    visibility: Visibility,
+    tags: Tags,
+    transform: Transform,
+    #[cfg_attr(feature = "serde", serde(skip_serializing_if = "Option::is_none"))]
+    material: Option<Material>,
+    #[cfg_attr(
+        feature = "serde",
+        serde(serialize_with = "crate::serialization::serialize_shared")
+    )]
+    pub shape: Arc<dyn Shape>,
+}
+
+impl Instance {
+    pub fn new(shape: Arc<dyn Shape>) -> Self {
+        Self {
+            id: Uuid::new_v4(),
+            parent: None,
+            visibility: Visibility::default(),
+            tags: Tags::new(),
+            transform: IDENTITY,
+            material: None,
+            shape,
+        }
+    }
+}
+
+impl ShapeBuilder for Instance {
+    fn with_transform(self, transform: Transform) -> Self {
+        Self { transform, ..self }
+    }
+
+    fn with_material(self, material: Material) -> Self {
+        Self {
+            material: Some(material),
+            ..self
+        }
+    }
+}
+
+impl Shape for Instance {
+    fn id(&self) -> Uuid {
+        self.id
+    }
+
+    fn as_any(&self) -> &dyn Any {
+        self
+    }
+
+    fn transform(&self) -> &Transform {
+        &self.transform
+    }
+
+    fn set_transform(&mut self, transform: Transform) {
+        self.transform = transform;
+    }
+
+    fn material(&self) -> &Material {
+        self.material
+            .as_ref()
+            .unwrap_or_else(|| self.shape.material())
+    }
+
+    /// Starts the instance's own material from the shared one, leaving the
+    /// other instances as they were.
+    fn material_mut(&mut self) -> &mut Material {
+        let shape = &self.shape;
+        self.material
+            .get_or_insert_with(|| shape.material().clone())
+    }
+
+    fn set_material(&mut self, material: Material) {
+        self.material = Some(material);
+    }
+
+    fn parent(&self) -> Option<Uuid> {
+        self.parent
+    }
+
+    fn set_parent(&mut self, parent: Uuid) {
+        self.parent = Some(parent);
+    }
+
+    fn clear_parent(&mut self) {
+        self.parent = None;
+    }
+
+    fn visibility(&self) -> Visibility {
+        self.visibility
+    }
+
+    fn set_visibility(&mut self, visibility: Visibility) {
+        self.visibility = visibility;
+    }
+
+    fn tags(&self) -> &Tags {
+        &self.tags
+    }
+
+    fn tags_mut(&mut self) -> &mut Tags {
+        &mut self.tags
+    }
+
+    /// The shared shape's intersections, claimed by the instance so it's
+    /// shaded with the instance's transform and material. The part of the
+    /// shared shape that was hit, and the groups it was found in, go along
+    /// as `inner` for its normal.
+    fn parameters(&self) -> Vec<(&'static str, String)> {
+        vec![("shape", self.shape.type_name().to_string())]
+    }
+
+    fn local_intersect(&self, ray: Ray) -> Vec<Intersection> {
+        self.shape
+            .intersect(ray)
+            .into_iter()
+            .map(|i| Intersection::new(i.t, self).inner(i))
+            .collect()
+    }
+
+    fn local_normal_at(&self, point: Point, hit: Option<&Intersection>) -> Vector {
+        match hit.and_then(Intersection::inner_hit) {
+            Some(inner) => inner.object.normal_at(point, Some(&inner)),
+            None => self.shape.normal_at(point, None),
+        }
+    }
+
+    fn surface_color(&self, hit: &Intersection) -> Option<Color> {
+        hit.inner_hit()
+            .and_then(|inner| inner.object.surface_color(&inner))
+    }
+
+    /// The material of the part of the shared shape that was hit, as it
+    /// inherits inside the shared shape, unless the instance replaces it.
+    fn material_at<'a>(&'a self, hit: &Intersection<'a>) -> Option<&'a Material> {
+        if self.material.is_some() {
+            return None;
+        }
+        let material = shared_material(self.shape.as_ref(), hit.inner?);
+        Some(material).filter(|&m| *m != Material::default())
+    }
+
+    fn bounds(&self) -> BoundingBox {
+        self.shape.parent_space_bounds()
+    }
+}
+
+/// The material `part` of `shared` is shaded with, taken from the nearest
+/// group up the chain inside `shared` when `part` inherits.
+fn shared_material<'a>(shared: &'a dyn Shape, part: &'a dyn Shape) -> &'a Material {
+    match part
+        .parent()
+        .filter(|_| part.inherits_material())
+        .and_then(|id| find(shared, id))
+    {
+        Some(parent) => shared_material(shared, parent),
+        None => part.material(),
+    }
+}
+
+fn find(shape: &dyn Shape, id: Uuid) -> Option<&dyn Shape> {
+    if shape.id() == id {
+        return Some(shape);
+    }
+    shape
+        .children()
+        .iter()
+        .find_map(|child| find(child.as_ref(), id))
+}
+
+#[cfg(test)]
+mod tests {
+    use std::f32::consts::PI;
+
+    use crate::shapes::{group::Group, sphere::Sphere, triangle::Triangle};
+
+    use super::*;
+
+    fn shared_sphere() -> Arc<dyn Shape> {
+        Arc::new(Sphere::default().with_material(Material::default().diffuse(0.3)))
+    }
+
+    #[test]
+    fn instances_share_one_shape() {
+        let sphere = shared_sphere();
+
+        let a = Instance::new(sphere.clone());
+        let b = Instance::new(sphere.clone());
+
+        assert_eq!(Arc::strong_count(&sphere), 3);
+        assert_ne!(a.id(), b.id());
+        assert_eq!(a.shape.id(), b.shape.id());
+    }
+
+    #[test]
+    fn intersecting_a_moved_instance() {
+        let instance =
+            Instance::new(shared_sphere()).with_transform(Transform::translation(5.0, 0.0, 0.0));
+        let r = Ray::new(Point::new(5.0, 0.0, -5.0), Vector::new(0.0, 0.0, 1.0));
+
+        let xs = instance.intersect(r);
+
+        assert_eq!(xs.len(), 2);
+        assert_eq!(xs[0].t, 4.0);
+        assert_eq!(xs[0].object.id(), instance.id());
+        assert_eq!(xs[0].inner.unwrap().id(), instance.shape.id());
+    }
+
+    #[test]
+    fn the_normal_on_a_moved_instance() {
+        let instance =
+            Instance::new(shared_sphere()).with_transform(Transform::translation(0.0, 0.0, 5.0));
+        let r = Ray::new(Point::new(0.0, 0.0, 0.0), Vector::new(0.0, 0.0, 1.0));
+        let xs = instance.intersect(r);
+
+        let comps = xs[0].prepare_computations(r, &xs);
+
+        assert_eq!(comps.point, Point::new(0.0, 0.0, 4.0));
+        assert_eq!(comps.normalv, Vector::new(0.0, 0.0, -1.0));
+    }
+
+    #[test]
+    fn the_normal_comes_from_the_part_of_a_shared_group_that_was_hit() {
+        let mut mesh = Group::new();
+        mesh.add_child(Box::new(Triangle::new(
+            Point::new(0.0, 1.0, 0.0),
+            Point::new(-1.0, 0.0, 0.0),
+            Point::new(1.0, 0.0, 0.0),
+        )));
+        let instance =
+            Instance::new(Arc::new(mesh)).with_transform(Transform::translation(0.0, 0.0, 2.0));
+        let r = Ray::new(Point::new(0.0, 0.5, -2.0), Vector::new(0.0, 0.0, 1.0));
+        let xs = instance.intersect(r);
+
+        let comps = xs[0].prepare_computations(r, &xs);
+
+        assert_eq!(xs[0].t, 4.0);
+        assert_eq!(comps.normalv, Vector::new(0.0, 0.0, -1.0));
+    }
+
+    #[test]
+    fn the_normal_on_an_instance_of_a_transformed_group() {
+        let group = || {
+            let mut g = Group::new().with_transform(
+                Transform::rotation_x(PI / 3.0) * Transform::scaling(1.0, 2.0, 1.0),
+            );
+            g.add_child(Box::new(
+                Sphere::default().with_transform(Transform::translation(0.0, 0.5, 0.0)),
+            ));
+            g
+        };
+        let instance =
+            Instance::new(Arc::new(group())).with_transform(Transform::translation(0.0, 0.0, 5.0));
+        let mut direct = Group::new().with_transform(Transform::translation(0.0, 0.0, 5.0));
+        direct.add_child(Box::new(group()));
+        let r = Ray::new(Point::new(0.1, 0.3, 0.0), Vector::new(0.0, 0.0, 1.0));
+
+        let xs = instance.intersect(r);
+        let expected = direct.intersect(r);
+
+        assert_eq!(xs[0].t, expected[0].t);
+        assert_eq!(
+            xs[0].prepare_computations(r, &xs).normalv,
+            expected[0].prepare_computations(r, &expected).normalv
+        );
+    }
+
+    #[test]
+    fn an_instance_uses_the_shared_material_until_given_its_own() {
+        let sphere = shared_sphere();
+        let plain = Instance::new(sphere.clone());
+        let mut red = Instance::new(sphere.clone());
+
+        red.material_mut().color = Color::new(1.0, 0.0, 0.0);
+
+        assert_eq!(plain.material(), sphere.material());
+        assert_eq!(
+            red.material(),
+            &Material::default()
+                .diffuse(0.3)
+                .color(Color::new(1.0, 0.0, 0.0))
+        );
+        assert_eq!(sphere.material().color, Material::default().color);
+    }
+
+    #[test]
+    fn each_part_of_an_instanced_group_keeps_its_own_material() {
+        let red = Material::default().color(Color::new(1.0, 0.0, 0.0));
+        let blue = Material::default().color(Color::new(0.0, 0.0, 1.0));
+        let mut group = Group::new();
+        group.add_child(Box::new(
+            Sphere::default()
+                .with_transform(Transform::translation(-2.0, 0.0, 0.0))
+                .with_material(red.clone()),
+        ));
+        group.add_child(Box::new(
+            Sphere::default()
+                .with_transform(Transform::translation(2.0, 0.0, 0.0))
+                .with_material(blue.clone()),
+        ));
+        let instance = Instance::new(Arc::new(group));
+        let material_hit_at = |x: f32| {
+            let r = Ray::new(Point::new(x, 0.0, -5.0), Vector::new(0.0, 0.0, 1.0));
+            let xs = instance.intersect(r);
+            xs[0].prepare_computations(r, &xs).material.clone()
+        };
+
+        assert_eq!(material_hit_at(-2.0), red);
+        assert_eq!(material_hit_at(2.0), blue);
+    }
+
+    #[test]
+    fn parts_of_an_instanced_group_inherit_the_groups_material() {
+        let green = Material::default().color(Color::new(0.0, 1.0, 0.0));
+        let mut group = Group::new().with_material(green.clone());
+        group.add_child(Box::new(Sphere::default()));
+        let instance = Instance::new(Arc::new(group));
+        let r = Ray::new(Point::new(0.0, 0.0, -5.0), Vector::new(0.0, 0.0, 1.0));
+        let xs = instance.intersect(r);
+
+        assert_eq!(xs[0].prepare_computations(r, &xs).material, &green);
+    }
+
+    #[test]
+    fn the_bounds_of_an_instance_include_the_shared_shapes_transform() {
+        let sphere: Arc<dyn Shape> =
+            Arc::new(Sphere::default().with_transform(Transform::translation(1.0, 0.0, 0.0)));
+        let instance = Instance::new(sphere).with_transform(Transform::scaling(2.0, 2.0, 2.0));
+
+        assert_eq!(
+            instance.parent_space_bounds(),
+            BoundingBox::new(Point::new(0.0, -2.0, -2.0), Point::new(4.0, 2.0, 2.0))
+        );
+    }
+}
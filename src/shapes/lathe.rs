@@ -0,0 +1,167 @@
+use std::f32::consts::PI;
+
+use crate::{
+    materials::Material,
+    shapes::{group::Group, mesh::Mesh},
+    tuple::{Point, Vector},
+};
+
+/// A surface of revolution, such as a vase or goblet, turned from a
+/// profile of `(radius, y)` points running upward. It's built as smooth
+/// shaded triangles, optionally with flat caps closing off the ends.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Lathe {
+    pub profile: Vec<(f32, f32)>,
+    pub segments: usize,
+    pub capped: bool,
+    pub material: Material,
+}
+
+impl Lathe {
+    pub fn new(profile: Vec<(f32, f32)>) -> Self {
+        Self {
+            profile,
+            segments: 32,
+            capped: false,
+            material: Material::default(),
+        }
+    }
+
+    pub fn segments(self, segments: usize) -> Self {
+        Self { segments, ..self }
+    }
+
+    pub fn capped(self, capped: bool) -> Self {
+        Self { capped, ..self }
+    }
+
+    pub fn material(self, material: Material) -> Self {
+        Self { material, ..self }
+    }
+
+    /// The turned surface with its normals smoothed around the axis. Caps
+    /// get vertices of their own so the rim stays sharp.
+    pub fn to_mesh(&self) -> Mesh {
+        let mut mesh = Mesh::revolve(&self.profile, self.segments)
+            .smooth_normals()
+            .material(self.material.clone());
+
+        if self.capped {
+            if let Some(&(radius, y)) = self.profile.first() {
+                self.add_cap(&mut mesh, radius, y, false);
+            }
+            if let Some(&(radius, y)) = self.profile.last() {
+                self.add_cap(&mut mesh, radius, y, true);
+            }
+        }
+        mesh
+    }
+
+    pub fn to_group(&self) -> Group {
+        self.to_mesh().to_group()
+    }
+
+    /// A flat disc closing the end at height `y`, facing up or down.
+    fn add_cap(&self, mesh: &mut Mesh, radius: f32, y: f32, up: bool) {
+        if radius == 0.0 {
+            return;
+        }
+
+        let normal = Vector::new(0.0, if up { 1.0 } else { -1.0 }, 0.0);
+        let center = mesh.vertices.len();
+        mesh.vertices.push(Point::new(0.0, y, 0.0));
+        for i in 0..self.segments {
+            let theta = 2.0 * PI * i as f32 / self.segments as f32;
+            mesh.vertices
+                .push(Point::new(radius * theta.cos(), y, radius * theta.sin()));
+        }
+        mesh.normals.resize(mesh.vertices.len(), normal);
+
+        for i in 0..self.segments {
+            let a = center + 1 + i;
+            let b = center + 1 + (i + 1) % self.segments;
+            mesh.faces
+                .push(if up { [center, a, b] } else { [center, b, a] });
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{
+        float_eq,
+        ray::Ray,
+        shapes::{Shape, ShapeBuilder},
+        transformations::Transform,
+    };
+
+    use super::*;
+
+    fn tube() -> Lathe {
+        Lathe::new(vec![(1.0, 0.0), (1.0, 2.0)]).segments(8)
+    }
+
+    #[test]
+    fn an_uncapped_lathe_is_just_the_turned_surface() {
+        let mesh = tube().to_mesh();
+
+        assert_eq!(mesh.vertices.len(), 16);
+        assert_eq!(mesh.faces.len(), 16);
+        assert_eq!(mesh.normals.len(), 16);
+    }
+
+    #[test]
+    fn the_walls_of_a_lathe_have_smooth_outward_normals() {
+        let mesh = tube().to_mesh();
+
+        for (point, normal) in mesh.vertices.iter().zip(&mesh.normals) {
+            let outward = Vector::new(point.x(), 0.0, point.z()).normalize();
+            assert_eq!(*normal, outward);
+        }
+    }
+
+    #[test]
+    fn caps_close_both_ends_facing_away() {
+        let mesh = tube().capped(true).to_mesh();
+
+        assert_eq!(mesh.vertices.len(), 16 + 2 * 9);
+        assert_eq!(mesh.faces.len(), 16 + 2 * 8);
+        for triangle in mesh.triangles().iter().skip(16).take(8) {
+            assert_eq!(triangle.normal, Vector::new(0.0, -1.0, 0.0));
+        }
+        for triangle in mesh.triangles().iter().skip(24) {
+            assert_eq!(triangle.normal, Vector::new(0.0, 1.0, 0.0));
+        }
+    }
+
+    #[test]
+    fn an_end_on_the_axis_needs_no_cap() {
+        let cone = Lathe::new(vec![(1.0, 0.0), (0.0, 1.0)])
+            .segments(6)
+            .capped(true);
+
+        let mesh = cone.to_mesh();
+
+        assert_eq!(mesh.vertices.len(), 7 + 7);
+        assert_eq!(mesh.faces.len(), 6 + 6);
+    }
+
+    #[test]
+    fn a_ray_through_a_capped_lathe() {
+        let vase = tube()
+            .capped(true)
+            .material(Material::default().ambient(0.5))
+            .to_group()
+            .with_transform(Transform::translation(0.0, -1.0, 0.0));
+        let down = Ray::new(Point::new(0.3, 5.0, 0.1), Vector::new(0.0, -1.0, 0.0));
+        let across = Ray::new(Point::new(-5.0, 0.0, 0.2), Vector::new(1.0, 0.0, 0.0));
+
+        let xs = vase.intersect(down);
+        let ys = vase.intersect(across);
+
+        assert_eq!(xs.len(), 2);
+        assert!(float_eq(xs[0].t, 4.0));
+        assert!(float_eq(ys[0].t, 4.08284));
+        assert!(float_eq(vase.children()[0].material().ambient, 0.5));
+    }
+}
@@ -0,0 +1,660 @@
+use std::{collections::HashMap, f32::consts::PI};
+
+use crate::{
+    color::Color,
+    materials::Material,
+    shapes::{group::Group, triangle::Triangle, ShapeBuilder},
+    tuple::{Point, Vector},
+};
+
+/// An indexed triangle mesh. Faces follow the same winding as `Triangle`, so
+/// `(p3 - p1) x (p2 - p1)` points out of the surface.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct Mesh {
+    pub vertices: Vec<Point>,
+    pub faces: Vec<[usize; 3]>,
+    pub material: Material,
+    /// A color for each vertex, or none at all.
+    pub colors: Vec<Color>,
+    /// A normal for each vertex, or none for flat shading.
+    pub normals: Vec<Vector>,
+}
+
+impl Mesh {
+    pub fn new(vertices: Vec<Point>, faces: Vec<[usize; 3]>) -> Self {
+        Self {
+            vertices,
+            faces,
+            ..Self::default()
+        }
+    }
+
+    pub fn material(self, material: Material) -> Self {
+        Self { material, ..self }
+    }
+
+    pub fn colors(self, colors: Vec<Color>) -> Self {
+        Self { colors, ..self }
+    }
+
+    pub fn normals(self, normals: Vec<Vector>) -> Self {
+        Self { normals, ..self }
+    }
+
+    /// Gives each vertex the average normal of the faces around it, so the
+    /// triangles shade as one smooth surface. Each face counts by its angle
+    /// at the vertex, which keeps the result from leaning toward whichever
+    /// way the faces happen to be split.
+    pub fn smooth_normals(self) -> Self {
+        let mut normals = vec![Vector::new(0.0, 0.0, 0.0); self.vertices.len()];
+        for &[a, b, c] in &self.faces {
+            let p1 = self.vertices[a];
+            let normal = (self.vertices[c] - p1).cross(self.vertices[b] - p1);
+            if normal.magnitude() == 0.0 {
+                continue;
+            }
+            let normal = normal.normalize();
+            for &(i, j, k) in &[(a, b, c), (b, c, a), (c, a, b)] {
+                let to_j = (self.vertices[j] - self.vertices[i]).normalize();
+                let to_k = (self.vertices[k] - self.vertices[i]).normalize();
+                let angle = to_j.dot(to_k).clamp(-1.0, 1.0).acos();
+                normals[i] = normals[i] + normal * angle;
+            }
+        }
+        let normals = normals
+            .into_iter()
+            .map(|n| {
+                if n.magnitude() > 0.0 {
+                    n.normalize()
+                } else {
+                    n
+                }
+            })
+            .collect();
+
+        Self { normals, ..self }
+    }
+
+    /// Gives each vertex the sum of the normals of the faces around it,
+    /// each counting by its area, so small sliver triangles barely sway
+    /// the result.
+    pub fn area_weighted_normals(self) -> Self {
+        let mut normals = vec![Vector::new(0.0, 0.0, 0.0); self.vertices.len()];
+        for &[a, b, c] in &self.faces {
+            let p1 = self.vertices[a];
+            let normal = (self.vertices[c] - p1).cross(self.vertices[b] - p1);
+            for &i in &[a, b, c] {
+                normals[i] = normals[i] + normal;
+            }
+        }
+        let normals = normals
+            .into_iter()
+            .map(|n| {
+                if n.magnitude() > 0.0 {
+                    n.normalize()
+                } else {
+                    n
+                }
+            })
+            .collect();
+
+        Self { normals, ..self }
+    }
+
+    /// Merges vertices less than `epsilon` apart, so faces that only
+    /// touched become joined, and drops any face that collapses. Vertices
+    /// with different normals are left apart, keeping hard edges hard. The
+    /// first of each merged bunch keeps its place, color and normal.
+    pub fn weld(self, epsilon: f32) -> Self {
+        let cell = |p: Point| {
+            (
+                (p.x() / epsilon).floor() as i64,
+                (p.y() / epsilon).floor() as i64,
+                (p.z() / epsilon).floor() as i64,
+            )
+        };
+        let mut grid = HashMap::<(i64, i64, i64), Vec<usize>>::new();
+        let mut kept: Vec<usize> = vec![];
+        let mut remap = vec![];
+        for (i, &p) in self.vertices.iter().enumerate() {
+            let (x, y, z) = cell(p);
+            let nearby = (-1..=1)
+                .flat_map(|dx| (-1..=1).flat_map(move |dy| (-1..=1).map(move |dz| (dx, dy, dz))))
+                .filter_map(|(dx, dy, dz)| grid.get(&(x + dx, y + dy, z + dz)))
+                .flatten()
+                .find(|&&welded| {
+                    (self.vertices[kept[welded]] - p).magnitude() < epsilon
+                        && self.normals.get(kept[welded]) == self.normals.get(i)
+                })
+                .copied();
+            remap.push(match nearby {
+                Some(welded) => welded,
+                None => {
+                    kept.push(i);
+                    grid.entry((x, y, z)).or_default().push(kept.len() - 1);
+                    kept.len() - 1
+                }
+            });
+        }
+
+        let faces = self
+            .faces
+            .iter()
+            .map(|face| face.map(|i| remap[i]))
+            .filter(|&[a, b, c]| a != b && b != c && c != a)
+            .collect();
+
+        Self {
+            vertices: pick(&self.vertices, &kept, self.vertices.len()),
+            colors: pick(&self.colors, &kept, self.vertices.len()),
+            normals: pick(&self.normals, &kept, self.vertices.len()),
+            faces,
+            ..self
+        }
+    }
+
+    /// Smooths the mesh by Loop subdivision, splitting every triangle into
+    /// four `levels` times and easing the vertices toward the limit surface,
+    /// then recomputes smooth normals. Open edges are kept as curves of
+    /// their own rather than shrinking inward. Vertex colors are blended
+    /// along with the points.
+    pub fn subdivide(self, levels: usize) -> Self {
+        (0..levels)
+            .fold(self, |mesh, _| mesh.subdivide_once())
+            .smooth_normals()
+    }
+
+    fn subdivide_once(self) -> Self {
+        let mut opposite = HashMap::<(usize, usize), Vec<usize>>::new();
+        let mut neighbors = vec![vec![]; self.vertices.len()];
+        for &[a, b, c] in &self.faces {
+            for &(from, to, across) in &[(a, b, c), (b, c, a), (c, a, b)] {
+                let edge = (from.min(to), from.max(to));
+                let entry = opposite.entry(edge).or_default();
+                if entry.is_empty() {
+                    neighbors[from].push(to);
+                    neighbors[to].push(from);
+                }
+                entry.push(across);
+            }
+        }
+
+        let is_open = |a: usize, b: usize| opposite[&(a.min(b), a.max(b))].len() != 2;
+        let mut masks = (0..self.vertices.len())
+            .map(|v| {
+                let around = &neighbors[v];
+                let open = around
+                    .iter()
+                    .filter(|&&n| is_open(v, n))
+                    .copied()
+                    .collect::<Vec<_>>();
+                match open[..] {
+                    [] if !around.is_empty() => {
+                        let n = around.len();
+                        let beta = if n == 3 {
+                            3.0 / 16.0
+                        } else {
+                            3.0 / (8.0 * n as f32)
+                        };
+                        let mut mask = vec![(v, 1.0 - n as f32 * beta)];
+                        mask.extend(around.iter().map(|&n| (n, beta)));
+                        mask
+                    }
+                    [a, b] => vec![(v, 0.75), (a, 0.125), (b, 0.125)],
+                    _ => vec![(v, 1.0)],
+                }
+            })
+            .collect::<Vec<_>>();
+
+        let mut edge_vertices = HashMap::new();
+        let mut edge_vertex = |a: usize, b: usize| {
+            let edge = (a.min(b), a.max(b));
+            *edge_vertices.entry(edge).or_insert_with(|| {
+                masks.push(match opposite[&edge][..] {
+                    [c, d] => vec![(a, 0.375), (b, 0.375), (c, 0.125), (d, 0.125)],
+                    _ => vec![(a, 0.5), (b, 0.5)],
+                });
+                masks.len() - 1
+            })
+        };
+        let faces = self
+            .faces
+            .iter()
+            .flat_map(|&[a, b, c]| {
+                let (ab, bc, ca) = (edge_vertex(a, b), edge_vertex(b, c), edge_vertex(c, a));
+                vec![[a, ab, ca], [ab, b, bc], [ca, bc, c], [ab, bc, ca]]
+            })
+            .collect();
+
+        let vertices = masks
+            .iter()
+            .map(|mask| {
+                mask.iter()
+                    .fold(Vector::new(0.0, 0.0, 0.0), |sum, &(i, weight)| {
+                        sum + self.vertices[i].to_vector() * weight
+                    })
+                    .to_point()
+            })
+            .collect();
+        let colors = if self.colors.len() == self.vertices.len() {
+            masks
+                .iter()
+                .map(|mask| {
+                    mask.iter().fold(Color::default(), |sum, &(i, weight)| {
+                        sum + self.colors[i] * weight
+                    })
+                })
+                .collect()
+        } else {
+            vec![]
+        };
+
+        Self {
+            vertices,
+            faces,
+            colors,
+            normals: vec![],
+            ..self
+        }
+    }
+
+    /// A surface of revolution, sweeping the `(radius, y)` points of
+    /// `profile` around the y axis in `segments` steps. The profile should
+    /// run upward for the faces to point out; points on the axis become a
+    /// single vertex.
+    pub fn revolve(profile: &[(f32, f32)], segments: usize) -> Self {
+        let mut vertices = vec![];
+        let rings = profile
+            .iter()
+            .map(|&(radius, y)| {
+                let count = if radius == 0.0 { 1 } else { segments };
+                (0..count)
+                    .map(|i| {
+                        let theta = 2.0 * PI * i as f32 / segments as f32;
+                        vertices.push(Point::new(radius * theta.cos(), y, radius * theta.sin()));
+                        vertices.len() - 1
+                    })
+                    .collect::<Vec<_>>()
+            })
+            .collect::<Vec<_>>();
+
+        let mut faces = vec![];
+        for pair in rings.windows(2) {
+            let (lower, upper) = (&pair[0], &pair[1]);
+            for i in 0..segments {
+                let a = lower[i % lower.len()];
+                let b = lower[(i + 1) % lower.len()];
+                let c = upper[(i + 1) % upper.len()];
+                let d = upper[i % upper.len()];
+                for &face in &[[a, b, c], [a, c, d]] {
+                    if face[0] != face[1] && face[1] != face[2] && face[2] != face[0] {
+                        faces.push(face);
+                    }
+                }
+            }
+        }
+
+        Self::new(vertices, faces)
+    }
+
+    /// True when every edge is shared by exactly two faces.
+    pub fn is_closed(&self) -> bool {
+        let mut edges = HashMap::new();
+        for &[a, b, c] in &self.faces {
+            for &(from, to) in &[(a, b), (b, c), (c, a)] {
+                *edges.entry((from.min(to), from.max(to))).or_insert(0) += 1;
+            }
+        }
+        !edges.is_empty() && edges.values().all(|&count| count == 2)
+    }
+
+    /// Volume enclosed by a closed mesh, negative when its faces point inward.
+    pub fn signed_volume(&self) -> f32 {
+        self.faces
+            .iter()
+            .map(|&[a, b, c]| {
+                let p1 = self.vertices[a].to_vector();
+                let p2 = self.vertices[b].to_vector();
+                let p3 = self.vertices[c].to_vector();
+                p1.dot(p3.cross(p2))
+            })
+            .sum::<f32>()
+            / 6.0
+    }
+
+    pub fn flip_winding(&mut self) {
+        for face in &mut self.faces {
+            face.swap(1, 2);
+        }
+    }
+
+    /// Flips every face of a closed mesh whose normals mostly point inward.
+    /// Returns whether the winding was changed; open meshes are left alone
+    /// since their signed volume means nothing.
+    pub fn fix_winding(&mut self) -> bool {
+        if !self.is_closed() || self.signed_volume() >= 0.0 {
+            return false;
+        }
+
+        self.flip_winding();
+        true
+    }
+
+    pub fn triangles(&self) -> Vec<Triangle> {
+        self.faces
+            .iter()
+            .map(|&[a, b, c]| {
+                let mut triangle =
+                    Triangle::new(self.vertices[a], self.vertices[b], self.vertices[c])
+                        .with_material(self.material.clone());
+                if self.colors.len() == self.vertices.len() {
+                    triangle = triangle.with_vertex_colors([
+                        self.colors[a],
+                        self.colors[b],
+                        self.colors[c],
+                    ]);
+                }
+                if self.normals.len() == self.vertices.len() {
+                    triangle = triangle.with_vertex_normals([
+                        self.normals[a],
+                        self.normals[b],
+                        self.normals[c],
+                    ]);
+                }
+                triangle
+            })
+            .collect()
+    }
+
+    pub fn to_group(&self) -> Group {
+        let mut group = Group::new();
+        for triangle in self.triangles() {
+            group.add_child(Box::new(triangle));
+        }
+        group
+    }
+}
+
+/// The `kept` entries of a per-vertex list, or nothing if the list doesn't
+/// have one for each of the `count` vertices.
+fn pick<T: Copy>(values: &[T], kept: &[usize], count: usize) -> Vec<T> {
+    if values.len() == count {
+        kept.iter().map(|&i| values[i]).collect()
+    } else {
+        vec![]
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{color, float_eq, shapes::Shape, test::sqrt_n_over_n};
+
+    use super::*;
+
+    fn tetrahedron() -> Mesh {
+        Mesh::new(
+            vec![
+                Point::new(0.0, 0.0, 0.0),
+                Point::new(1.0, 0.0, 0.0),
+                Point::new(0.0, 1.0, 0.0),
+                Point::new(0.0, 0.0, 1.0),
+            ],
+            vec![[0, 1, 2], [0, 3, 1], [0, 2, 3], [1, 3, 2]],
+        )
+    }
+
+    fn points_outward(mesh: &Mesh) -> bool {
+        let centroid = mesh
+            .vertices
+            .iter()
+            .fold(Vector::new(0.0, 0.0, 0.0), |sum, &v| sum + v.to_vector())
+            / mesh.vertices.len() as f32;
+
+        mesh.triangles().iter().all(|t| {
+            let face_center = (t.p1.to_vector() + t.p2.to_vector() + t.p3.to_vector()) / 3.0;
+            t.normal.dot(face_center - centroid) > 0.0
+        })
+    }
+
+    #[test]
+    fn revolving_a_closed_profile_makes_a_closed_mesh() {
+        let mesh = Mesh::revolve(&[(0.0, 0.0), (1.0, 0.0), (1.0, 1.0), (0.0, 1.0)], 4);
+
+        assert_eq!(mesh.vertices.len(), 10);
+        assert_eq!(mesh.faces.len(), 16);
+        assert!(mesh.is_closed());
+        assert!(points_outward(&mesh));
+    }
+
+    #[test]
+    fn revolving_an_open_profile_makes_a_tube() {
+        let mesh = Mesh::revolve(&[(1.0, 0.0), (1.0, 1.0)], 8);
+
+        assert_eq!(mesh.faces.len(), 16);
+        assert!(!mesh.is_closed());
+    }
+
+    #[test]
+    fn a_tetrahedron_is_closed() {
+        assert!(tetrahedron().is_closed());
+    }
+
+    #[test]
+    fn a_mesh_missing_a_face_is_open() {
+        let mut mesh = tetrahedron();
+        mesh.faces.pop();
+
+        assert!(!mesh.is_closed());
+    }
+
+    #[test]
+    fn an_outward_facing_mesh_has_positive_volume() {
+        let mesh = tetrahedron();
+
+        assert!(points_outward(&mesh));
+        assert!(float_eq(mesh.signed_volume(), 1.0 / 6.0));
+    }
+
+    #[test]
+    fn an_inside_out_mesh_has_negative_volume() {
+        let mut mesh = tetrahedron();
+        mesh.flip_winding();
+
+        assert!(!points_outward(&mesh));
+        assert!(float_eq(mesh.signed_volume(), -1.0 / 6.0));
+    }
+
+    #[test]
+    fn signed_volume_does_not_depend_on_position() {
+        let mut mesh = tetrahedron();
+        for v in &mut mesh.vertices {
+            *v = *v + Vector::new(5.0, -3.0, 2.0);
+        }
+
+        assert!(float_eq(mesh.signed_volume(), 1.0 / 6.0));
+    }
+
+    #[test]
+    fn fixing_the_winding_of_an_inside_out_mesh() {
+        let mut mesh = tetrahedron();
+        mesh.flip_winding();
+
+        assert!(mesh.fix_winding());
+        assert!(points_outward(&mesh));
+    }
+
+    #[test]
+    fn fixing_the_winding_of_a_correct_mesh_does_nothing() {
+        let mut mesh = tetrahedron();
+
+        assert!(!mesh.fix_winding());
+        assert_eq!(mesh, tetrahedron());
+    }
+
+    #[test]
+    fn fixing_the_winding_leaves_open_meshes_alone() {
+        let mut mesh = tetrahedron();
+        mesh.faces.pop();
+        mesh.flip_winding();
+        let before = mesh.clone();
+
+        assert!(!mesh.fix_winding());
+        assert_eq!(mesh, before);
+    }
+
+    #[test]
+    fn converting_a_mesh_to_a_group_of_triangles() {
+        let mesh = tetrahedron().material(Material::default().ambient(1.0));
+
+        let g = mesh.to_group();
+
+        assert_eq!(g.objects.len(), 4);
+        for child in &g.objects {
+            assert_eq!(child.parent(), Some(g.id()));
+            assert!(float_eq(child.material().ambient, 1.0));
+        }
+    }
+
+    #[test]
+    fn vertex_colors_are_carried_over_to_the_triangles() {
+        let red = Color::new(1.0, 0.0, 0.0);
+        let green = Color::new(0.0, 1.0, 0.0);
+        let blue = Color::new(0.0, 0.0, 1.0);
+        let white = Color::new(1.0, 1.0, 1.0);
+        let mesh = tetrahedron().colors(vec![red, green, blue, white]);
+
+        let triangles = mesh.triangles();
+
+        assert_eq!(triangles[0].colors, Some([red, green, blue]));
+        assert!(tetrahedron().triangles()[0].colors.is_none());
+    }
+
+    #[test]
+    fn smoothing_averages_the_normals_around_each_vertex() {
+        let mesh = Mesh::new(
+            vec![
+                Point::new(0.0, 0.0, 0.0),
+                Point::new(1.0, 0.0, 0.0),
+                Point::new(0.0, 0.0, 1.0),
+                Point::new(-1.0, 1.0, 0.0),
+            ],
+            vec![[0, 1, 2], [0, 2, 3]],
+        )
+        .smooth_normals();
+
+        assert_eq!(mesh.normals[1], Vector::new(0.0, 1.0, 0.0));
+        assert_eq!(
+            mesh.normals[3],
+            Vector::new(sqrt_n_over_n(2), sqrt_n_over_n(2), 0.0)
+        );
+        assert_eq!(mesh.normals[0], Vector::new(0.38268, 0.92388, 0.0));
+        assert_eq!(
+            mesh.triangles()[1].normals,
+            Some([mesh.normals[0], mesh.normals[2], mesh.normals[3]])
+        );
+    }
+
+    #[test]
+    fn subdividing_splits_each_face_into_four() {
+        let mesh = tetrahedron().subdivide(2);
+
+        assert_eq!(mesh.faces.len(), 64);
+        assert_eq!(mesh.vertices.len(), 34);
+        assert_eq!(mesh.normals.len(), mesh.vertices.len());
+        assert!(mesh.is_closed());
+        assert!(points_outward(&mesh));
+    }
+
+    #[test]
+    fn subdividing_moves_vertices_toward_a_smooth_surface() {
+        let mesh = tetrahedron().subdivide(1);
+
+        assert_eq!(mesh.vertices[0], Point::new(0.1875, 0.1875, 0.1875));
+        assert_eq!(mesh.vertices[4], Point::new(0.375, 0.125, 0.125));
+        assert!(mesh.signed_volume() < tetrahedron().signed_volume());
+    }
+
+    #[test]
+    fn subdividing_keeps_open_edges_on_their_curve() {
+        let square = Mesh::new(
+            vec![
+                Point::new(0.0, 0.0, 0.0),
+                Point::new(1.0, 0.0, 0.0),
+                Point::new(1.0, 0.0, 1.0),
+                Point::new(0.0, 0.0, 1.0),
+            ],
+            vec![[0, 1, 2], [0, 2, 3]],
+        );
+
+        let mesh = square.subdivide(1);
+
+        assert_eq!(mesh.vertices[1], Point::new(0.875, 0.0, 0.125));
+        assert_eq!(mesh.vertices[4], Point::new(0.5, 0.0, 0.0));
+        assert!(mesh.vertices.iter().all(|v| v.y() == 0.0));
+        assert!(mesh
+            .normals
+            .iter()
+            .all(|&n| n == Vector::new(0.0, 1.0, 0.0)));
+    }
+
+    #[test]
+    fn subdividing_blends_vertex_colors() {
+        let red = Color::new(1.0, 0.0, 0.0);
+        let blue = Color::new(0.0, 0.0, 1.0);
+        let mesh = tetrahedron()
+            .colors(vec![red, red, blue, blue])
+            .subdivide(1);
+
+        assert_eq!(mesh.colors.len(), mesh.vertices.len());
+        assert_eq!(mesh.colors[4], Color::new(0.75, 0.0, 0.25));
+    }
+
+    #[test]
+    fn welding_joins_faces_that_only_touch() {
+        let mesh = Mesh::new(
+            vec![
+                Point::new(0.0, 0.0, 0.0),
+                Point::new(1.0, 0.0, 0.0),
+                Point::new(1.0, 0.0, 1.0),
+                Point::new(0.00001, 0.0, 0.0),
+                Point::new(1.0, 0.0, 1.00001),
+                Point::new(0.0, 0.0, 1.0),
+            ],
+            vec![[0, 1, 2], [3, 4, 5], [0, 3, 5]],
+        )
+        .colors(vec![color::WHITE; 6]);
+
+        let welded = mesh.weld(0.001);
+
+        assert_eq!(welded.vertices.len(), 4);
+        assert_eq!(welded.vertices[3], Point::new(0.0, 0.0, 1.0));
+        assert_eq!(welded.faces, vec![[0, 1, 2], [0, 2, 3]]);
+        assert_eq!(welded.colors.len(), 4);
+        assert!(welded.normals.is_empty());
+    }
+
+    #[test]
+    fn welding_leaves_distant_vertices_apart() {
+        let welded = tetrahedron().weld(0.001);
+
+        assert_eq!(welded, tetrahedron());
+    }
+
+    #[test]
+    fn area_weighted_normals_favor_larger_faces() {
+        let mesh = Mesh::new(
+            vec![
+                Point::new(0.0, 0.0, 0.0),
+                Point::new(0.0, 0.0, 1.0),
+                Point::new(4.0, 0.0, 0.0),
+                Point::new(0.0, 1.0, 0.0),
+            ],
+            vec![[0, 2, 1], [0, 1, 3]],
+        )
+        .area_weighted_normals();
+
+        assert_eq!(mesh.normals[2], Vector::new(0.0, 1.0, 0.0));
+        assert_eq!(mesh.normals[3], Vector::new(1.0, 0.0, 0.0));
+        assert_eq!(mesh.normals[0], Vector::new(1.0, 4.0, 0.0).normalize());
+    }
+}
@@ -1,25 +1,134 @@
+pub mod caps;
 pub mod cone;
 pub mod cube;
 pub mod cylinder;
+pub mod deformed;
 pub mod group;
+pub mod instance;
+pub mod lathe;
+pub mod mesh;
 pub mod plane;
+pub mod quad;
 pub mod sphere;
+pub mod terrain;
+pub mod triangle;
 
-use std::fmt::Debug;
+use std::{any::Any, collections::BTreeMap, fmt::Debug};
 use uuid::Uuid;
 
 use crate::{
-    intersection::Intersection, materials::Material, ray::Ray, transformations::Transform,
-    tuple::Tuple,
+    bounds::BoundingBox,
+    color::Color,
+    intersection::Intersection,
+    materials::Material,
+    packet::RayPacket,
+    ray::{Ray, RayKind},
+    shapes::mesh::Mesh,
+    stats,
+    transformations::{Transform, IDENTITY},
+    tuple::{Point, Vector},
 };
 
 pub trait ShapeBuilder {
     fn with_material(self, material: Material) -> Self;
     fn with_transform(self, transform: Transform) -> Self;
+
+    fn with_visibility(mut self, visibility: Visibility) -> Self
+    where
+        Self: Shape + Sized,
+    {
+        self.set_visibility(visibility);
+        self
+    }
+
+    fn with_tag(mut self, key: &str, value: &str) -> Self
+    where
+        Self: Shape + Sized,
+    {
+        self.tags_mut().insert(key.to_string(), value.to_string());
+        self
+    }
+}
+
+/// Names given to a shape by whoever built the scene, such as
+/// `"role" => "hero"`, for finding it again without knowing its id.
+pub type Tags = BTreeMap<String, String>;
+
+/// Which kinds of ray can see a shape. Hiding a shape from the camera but
+/// not from shadows makes an invisible shadow caster; the reverse makes a
+/// card that doesn't block the light.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+#[cfg_attr(
+    feature = "serde",
+    derive(serde::Serialize, serde::Deserialize),
+    serde(default)
+)]
+pub struct Visibility {
+    pub visible_to_camera: bool,
+    pub visible_in_reflections: bool,
+    pub visible_to_shadows: bool,
 }
 
-pub trait Shape: 'static + Debug {
+impl Visibility {
+    pub fn visible_to_camera(self, visible_to_camera: bool) -> Self {
+        Self {
+            visible_to_camera,
+            ..self
+        }
+    }
+
+    pub fn visible_in_reflections(self, visible_in_reflections: bool) -> Self {
+        Self {
+            visible_in_reflections,
+            ..self
+        }
+    }
+
+    pub fn visible_to_shadows(self, visible_to_shadows: bool) -> Self {
+        Self {
+            visible_to_shadows,
+            ..self
+        }
+    }
+
+    /// Visible only where both `self` and `other` are, as for a shape
+    /// inside a group.
+    pub fn combine(self, other: Visibility) -> Self {
+        Self {
+            visible_to_camera: self.visible_to_camera && other.visible_to_camera,
+            visible_in_reflections: self.visible_in_reflections && other.visible_in_reflections,
+            visible_to_shadows: self.visible_to_shadows && other.visible_to_shadows,
+        }
+    }
+
+    /// Refracted and diffusely bounced rays count as reflections.
+    pub fn is_visible_to(&self, kind: RayKind) -> bool {
+        match kind {
+            RayKind::Primary => self.visible_to_camera,
+            RayKind::Shadow => self.visible_to_shadows,
+            RayKind::Reflection | RayKind::Refraction | RayKind::Diffuse => {
+                self.visible_in_reflections
+            }
+        }
+    }
+}
+
+impl Default for Visibility {
+    fn default() -> Self {
+        Self {
+            visible_to_camera: true,
+            visible_in_reflections: true,
+            visible_to_shadows: true,
+        }
+    }
+}
+
+/// Something that can be placed in a world and hit by rays. Shapes can be
+/// sent and shared between threads, so one can be behind an `Arc` that
+/// many `Instance`s point to.
+pub trait Shape: 'static + Debug + Send + Sync {
     fn id(&self) -> Uuid;
+    fn as_any(&self) -> &dyn Any;
 
     fn shape_eq(&self, other: &dyn Shape) -> bool {
         self.id() == other.id()
@@ -34,21 +143,197 @@ pub trait Shape: 'static + Debug {
 
     fn parent(&self) -> Option<Uuid>;
     fn set_parent(&mut self, parent: Uuid);
+    fn clear_parent(&mut self);
+
+    fn visibility(&self) -> Visibility;
+    fn set_visibility(&mut self, visibility: Visibility);
+
+    fn tags(&self) -> &Tags;
+    fn tags_mut(&mut self) -> &mut Tags;
+
+    /// The value of the shape's own tag `key`. Tags on the groups it's in
+    /// aren't included; `World::tag_of` looks through those too.
+    fn tag(&self, key: &str) -> Option<&str> {
+        self.tags().get(key).map(String::as_str)
+    }
+
+    /// Shapes nested directly inside this one.
+    fn children(&self) -> &[Box<dyn Shape>] {
+        &[]
+    }
+
+    fn children_mut(&mut self) -> &mut [Box<dyn Shape>] {
+        &mut []
+    }
+
+    /// Removes and returns the shapes nested directly inside this one.
+    fn take_children(&mut self) -> Vec<Box<dyn Shape>> {
+        vec![]
+    }
+
+    /// Pushes this shape's transform down into anything nested inside it,
+    /// leaving it with the identity. Shapes without children keep theirs.
+    fn bake_transforms(&mut self) {}
+
+    /// Whether the shape takes its material from its parent group. By
+    /// default any grouped shape left with the default material does, so a
+    /// whole group can be recolored with one assignment.
+    fn inherits_material(&self) -> bool {
+        self.parent().is_some() && *self.material() == Material::default()
+    }
+
+    /// The shape's type without its module path, e.g. `Sphere`.
+    fn type_name(&self) -> &'static str {
+        let name = std::any::type_name::<Self>();
+        name.rsplit("::").next().unwrap_or(name)
+    }
+
+    /// The settings that make the shape what it is besides its transform
+    /// and material, by name, as snapshots describe them. Most shapes have
+    /// none.
+    fn parameters(&self) -> Vec<(&'static str, String)> {
+        vec![]
+    }
 
     fn local_intersect(&self, ray: Ray) -> Vec<Intersection>;
-    fn local_normal_at(&self, point: Tuple) -> Tuple;
+    /// The object space normal at `point`. `hit` is the intersection that
+    /// found the point, when there is one, for shapes whose normal depends
+    /// on more than where the point is.
+    fn local_normal_at(&self, point: Point, hit: Option<&Intersection>) -> Vector;
+
+    /// A color the surface itself carries where `hit` landed, used in place
+    /// of the material's color and pattern. Most shapes have none.
+    fn surface_color(&self, _hit: &Intersection) -> Option<Color> {
+        None
+    }
+
+    /// The material of the part of the shape `hit` landed on, for shapes
+    /// wrapping geometry whose parts have materials of their own. `None`
+    /// means the shape's own material, as usual.
+    fn material_at<'a>(&'a self, _hit: &Intersection<'a>) -> Option<&'a Material> {
+        None
+    }
+
+    /// The shape's extent in object space.
+    fn bounds(&self) -> BoundingBox;
+
+    /// The shape's extent in its parent's space.
+    fn parent_space_bounds(&self) -> BoundingBox {
+        self.bounds().transform(*self.transform())
+    }
+
+    /// Maps `u` and `v` in [0, 1) to a point spread evenly over the
+    /// untransformed surface. Shapes without one can't be sampled as emitters.
+    fn local_surface_point(&self, _u: f32, _v: f32) -> Option<Point> {
+        None
+    }
+
+    fn local_surface_area(&self) -> f32 {
+        0.0
+    }
+
+    /// An object space triangle mesh approximating the surface, with curved
+    /// shapes split into roughly `resolution` steps around. Unbounded shapes
+    /// and groups have none.
+    fn tessellate(&self, _resolution: usize) -> Option<Mesh> {
+        None
+    }
+
+    /// A world space point on the surface, its normal, and the total surface
+    /// area as measured around that point.
+    fn sample_surface(&self, u: f32, v: f32) -> Option<SurfaceSample> {
+        let local_point = self.local_surface_point(u, v)?;
+        let local_sample = SurfaceSample {
+            point: local_point,
+            normal: self.local_normal_at(local_point, None),
+            area: self.local_surface_area(),
+        };
+
+        Some(local_sample.transformed(self.transform()))
+    }
 
     fn intersect(&self, ray: Ray) -> Vec<Intersection> {
+        if !self.visibility().is_visible_to(ray.kind) {
+            return vec![];
+        }
+
+        stats::record_intersection_test(self.type_name());
         let local_ray = ray.transform(self.transform().inverse());
-        self.local_intersect(local_ray)
+        let mut hits = self.local_intersect(local_ray);
+        hits.retain(|i| ray.in_range(i.t));
+        hits
+    }
+
+    /// `local_intersect` for four rays at once. Shapes with a vectorized
+    /// test override it; the rest take the rays one at a time.
+    fn local_intersect_packet(&self, packet: &RayPacket) -> [Vec<Intersection>; 4] {
+        let [a, b, c, d] = packet.rays;
+        [
+            self.local_intersect(a),
+            self.local_intersect(b),
+            self.local_intersect(c),
+            self.local_intersect(d),
+        ]
     }
 
-    fn normal_at(&self, x: f32, y: f32, z: f32) -> Tuple {
-        let world_point = Tuple::point(x, y, z);
-        let local_point = self.transform().inverse() * world_point;
-        let local_normal = self.local_normal_at(local_point);
-        let world_normal = self.transform().inverse().transpose() * local_normal;
-        world_normal.normalize()
+    fn intersect_packet(&self, packet: &RayPacket) -> [Vec<Intersection>; 4] {
+        let mut hits = self.local_intersect_packet(&packet.transform(self.transform().inverse()));
+        for (lane, hit) in hits.iter_mut().enumerate() {
+            let ray = &packet.rays[lane];
+            if self.visibility().is_visible_to(ray.kind) {
+                stats::record_intersection_test(self.type_name());
+                hit.retain(|i| ray.in_range(i.t));
+            } else {
+                hit.clear();
+            }
+        }
+        hits
+    }
+
+    /// The normal at `world_point`. When `hit` is on this shape, it's
+    /// carried through the groups the hit found the shape nested in.
+    fn normal_at(&self, world_point: Point, hit: Option<&Intersection>) -> Vector {
+        let groups = match hit {
+            Some(h) if h.object.id() == self.id() => h.groups,
+            _ => IDENTITY,
+        };
+        let local_point = self.world_to_object(world_point, &groups);
+        let local_normal = self.local_normal_at(local_point, hit);
+        self.normal_to_world(local_normal, &groups)
+    }
+
+    /// `world_point` in object space, passing in through `groups`, the
+    /// combined transforms of the groups the shape is nested in.
+    fn world_to_object(&self, world_point: Point, groups: &Transform) -> Point {
+        self.transform().inverse() * (groups.inverse() * world_point)
+    }
+
+    /// An object space normal turned back out through the shape's transform
+    /// and then `groups`.
+    fn normal_to_world(&self, local_normal: Vector, groups: &Transform) -> Vector {
+        let normal = self.transform().inverse().transpose() * local_normal;
+        (groups.inverse().transpose() * normal).normalize()
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct SurfaceSample {
+    pub point: Point,
+    pub normal: Vector,
+    pub area: f32,
+}
+
+impl SurfaceSample {
+    /// The sample moved by `transform`, with the area scaled to match.
+    pub fn transformed(self, transform: &Transform) -> Self {
+        let normal = transform.inverse().transpose() * self.normal;
+        let scale = transform.mat().determinant().abs() * normal.magnitude();
+
+        Self {
+            point: *transform * self.point,
+            normal: normal.normalize(),
+            area: self.area * scale,
+        }
     }
 }
 
@@ -62,6 +347,7 @@ impl PartialEq for dyn Shape {
 #[derive(Debug, Default)]
 pub struct TestShape {
     pub parent: Option<Uuid>,
+    pub tags: Tags,
 }
 
 #[cfg(test)]
@@ -77,6 +363,10 @@ impl Shape for TestShape {
         todo!()
     }
 
+    fn as_any(&self) -> &dyn Any {
+        self
+    }
+
     fn transform(&self) -> &Transform {
         todo!()
     }
@@ -105,18 +395,42 @@ impl Shape for TestShape {
         self.parent = Some(parent);
     }
 
+    fn clear_parent(&mut self) {
+        self.parent = None;
+    }
+
+    fn visibility(&self) -> Visibility {
+        Visibility::default()
+    }
+
+    fn set_visibility(&mut self, _visibility: Visibility) {
+        todo!()
+    }
+
+    fn tags(&self) -> &Tags {
+        &self.tags
+    }
+
+    fn tags_mut(&mut self) -> &mut Tags {
+        &mut self.tags
+    }
+
     fn local_intersect(&self, _ray: Ray) -> Vec<Intersection> {
         todo!()
     }
 
-    fn local_normal_at(&self, _point: Tuple) -> Tuple {
+    fn local_normal_at(&self, _point: Point, _hit: Option<&Intersection>) -> Vector {
         todo!()
     }
+
+    fn bounds(&self) -> BoundingBox {
+        BoundingBox::new(Point::new(-1.0, -1.0, -1.0), Point::new(1.0, 1.0, 1.0))
+    }
 }
 
 #[cfg(test)]
 mod tests {
-    use super::*;
+    use super::{group::Group, sphere::Sphere, *};
 
     #[test]
     fn a_shape_has_a_parent_attribute() {
@@ -124,4 +438,63 @@ mod tests {
 
         assert_eq!(s.parent(), None);
     }
+
+    #[test]
+    fn shapes_are_visible_to_every_kind_of_ray_by_default() {
+        let v = Visibility::default();
+
+        for &kind in &[
+            RayKind::Primary,
+            RayKind::Shadow,
+            RayKind::Reflection,
+            RayKind::Refraction,
+            RayKind::Diffuse,
+        ] {
+            assert!(v.is_visible_to(kind));
+        }
+    }
+
+    #[test]
+    fn secondary_rays_follow_the_reflection_flag() {
+        let v = Visibility::default().visible_in_reflections(false);
+
+        assert!(v.is_visible_to(RayKind::Primary));
+        assert!(v.is_visible_to(RayKind::Shadow));
+        assert!(!v.is_visible_to(RayKind::Reflection));
+        assert!(!v.is_visible_to(RayKind::Refraction));
+        assert!(!v.is_visible_to(RayKind::Diffuse));
+    }
+
+    #[test]
+    fn a_shape_only_finds_hits_within_the_rays_span() {
+        let s = Sphere::default().with_transform(Transform::scaling(2.0, 2.0, 2.0));
+        let r = Ray::new(Point::new(0.0, 0.0, -5.0), Vector::new(0.0, 0.0, 1.0));
+
+        let ts = |r: Ray| s.intersect(r).iter().map(|i| i.t).collect::<Vec<_>>();
+
+        assert_eq!(ts(r), vec![3.0, 7.0]);
+        assert_eq!(ts(r.t_min(4.0)), vec![7.0]);
+        assert_eq!(ts(r.t_max(3.0)), vec![3.0]);
+        assert!(ts(r.t_min(4.0).t_max(6.0)).is_empty());
+    }
+
+    #[test]
+    fn a_shape_hidden_from_a_kind_of_ray_is_not_intersected_by_it() {
+        let s = Sphere::default().with_visibility(Visibility::default().visible_to_camera(false));
+        let r = Ray::new(Point::new(0.0, 0.0, -5.0), Vector::new(0.0, 0.0, 1.0));
+
+        assert!(s.intersect(r).is_empty());
+        assert_eq!(s.intersect(r.kind(RayKind::Shadow)).len(), 2);
+    }
+
+    #[test]
+    fn hiding_a_group_hides_its_children() {
+        let mut g = Group::new();
+        g.add_child(Box::new(Sphere::default()));
+        g.set_visibility(Visibility::default().visible_to_shadows(false));
+        let r = Ray::new(Point::new(0.0, 0.0, -5.0), Vector::new(0.0, 0.0, 1.0));
+
+        assert_eq!(g.intersect(r).len(), 2);
+        assert!(g.intersect(r.kind(RayKind::Shadow)).is_empty());
+    }
 }
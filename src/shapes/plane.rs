@@ -1,19 +1,32 @@
+use std::any::Any;
+
 use uuid::Uuid;
 
 use crate::{
+    bounds::BoundingBox,
     intersection::Intersection,
     materials::Material,
+    packet::RayPacket,
     ray::Ray,
-    shapes::{Shape, ShapeBuilder},
+    shapes::{Shape, ShapeBuilder, Tags, Visibility},
     transformations::{Transform, IDENTITY},
-    tuple::Tuple,
+    tuple::{Point, Vector},
     EPSILON,
 };
 
 #[derive(Clone, Debug, PartialEq)]
+#[cfg_attr(
+    feature = "serde",
+    derive(serde::Serialize, serde::Deserialize),
+    serde(default)
+)]
 pub struct Plane {
+    #[cfg_attr(feature = "serde", serde(skip))]
     id: Uuid,
+    #[cfg_attr(feature = "serde", serde(skip))]
     parent: Option<Uuid>,
+    visibility: Visibility,
+    tags: Tags,
     material: Material,
     transform: Transform,
 }
@@ -23,6 +36,8 @@ impl Default for Plane {
         Self {
             id: Uuid::new_v4(),
             parent: None,
+            visibility: Visibility::default(),
+            tags: Tags::new(),
             material: Material::default(),
             transform: IDENTITY,
         }
@@ -44,6 +59,10 @@ impl Shape for Plane {
         self.id
     }
 
+    fn as_any(&self) -> &dyn Any {
+        self
+    }
+
     fn transform(&self) -> &Transform {
         &self.transform
     }
@@ -72,6 +91,26 @@ impl Shape for Plane {
         self.parent = Some(parent);
     }
 
+    fn clear_parent(&mut self) {
+        self.parent = None;
+    }
+
+    fn visibility(&self) -> Visibility {
+        self.visibility
+    }
+
+    fn set_visibility(&mut self, visibility: Visibility) {
+        self.visibility = visibility;
+    }
+
+    fn tags(&self) -> &Tags {
+        &self.tags
+    }
+
+    fn tags_mut(&mut self) -> &mut Tags {
+        &mut self.tags
+    }
+
     fn local_intersect(&self, ray: Ray) -> Vec<Intersection> {
         if ray.direction.y().abs() < EPSILON {
             vec![]
@@ -81,8 +120,25 @@ impl Shape for Plane {
         }
     }
 
-    fn local_normal_at(&self, _point: Tuple) -> Tuple {
-        Tuple::vector(0.0, 1.0, 0.0)
+    fn local_intersect_packet(&self, packet: &RayPacket) -> [Vec<Intersection>; 4] {
+        let mut hits: [Vec<Intersection>; 4] = Default::default();
+        for (lane, t) in packet.plane_hits().iter().enumerate() {
+            if let Some(t) = *t {
+                hits[lane] = vec![Intersection::new(t, self)];
+            }
+        }
+        hits
+    }
+
+    fn local_normal_at(&self, _point: Point, _hit: Option<&Intersection>) -> Vector {
+        Vector::new(0.0, 1.0, 0.0)
+    }
+
+    fn bounds(&self) -> BoundingBox {
+        BoundingBox::new(
+            Point::new(f32::NEG_INFINITY, 0.0, f32::NEG_INFINITY),
+            Point::new(f32::INFINITY, 0.0, f32::INFINITY),
+        )
     }
 }
 
@@ -97,13 +153,13 @@ mod tests {
         let p = Plane::default();
 
         // Note: the book uses `local_normal_at` but I didn't
-        let n1 = p.normal_at(0.0, 0.0, 0.0);
-        let n2 = p.normal_at(10.0, 0.0, -10.0);
-        let n3 = p.normal_at(-5.0, 0.0, 150.0);
+        let n1 = p.normal_at(Point::new(0.0, 0.0, 0.0), None);
+        let n2 = p.normal_at(Point::new(10.0, 0.0, -10.0), None);
+        let n3 = p.normal_at(Point::new(-5.0, 0.0, 150.0), None);
 
-        assert_eq!(n1, Tuple::vector(0.0, 1.0, 0.0));
-        assert_eq!(n2, Tuple::vector(0.0, 1.0, 0.0));
-        assert_eq!(n3, Tuple::vector(0.0, 1.0, 0.0));
+        assert_eq!(n1, Vector::new(0.0, 1.0, 0.0));
+        assert_eq!(n2, Vector::new(0.0, 1.0, 0.0));
+        assert_eq!(n3, Vector::new(0.0, 1.0, 0.0));
     }
 
     #[test]
@@ -155,4 +211,18 @@ mod tests {
         assert_eq!(xs.len(), 1);
         assert!(float_eq(xs[0].t, 1.0));
     }
+
+    #[test]
+    fn a_plane_has_a_bounding_box() {
+        let p = Plane::default();
+
+        let b = p.bounds();
+
+        assert_eq!(b.min.x(), f32::NEG_INFINITY);
+        assert!(float_eq(b.min.y(), 0.0));
+        assert_eq!(b.min.z(), f32::NEG_INFINITY);
+        assert_eq!(b.max.x(), f32::INFINITY);
+        assert!(float_eq(b.max.y(), 0.0));
+        assert_eq!(b.max.z(), f32::INFINITY);
+    }
 }
@@ -0,0 +1,362 @@
+use std::any::Any;
+
+use uuid::Uuid;
+
+use crate::{
+    bounds::BoundingBox,
+    intersection::Intersection,
+    materials::Material,
+    patterns::uv::UvMapping,
+    ray::Ray,
+    shapes::{mesh::Mesh, Shape, ShapeBuilder, Tags, Visibility},
+    transformations::{Transform, IDENTITY},
+    tuple::{Point, Vector},
+    EPSILON,
+};
+
+/// A flat parallelogram with one corner at `origin` and sides along
+/// `uvec` and `vvec`. Points on it have texture coordinates running from
+/// (0, 0) at `origin` to (1, 1) at the far corner. It faces the side
+/// `vvec` turns towards `uvec`, so a quad along x and z faces up.
+#[derive(Clone, Debug, PartialEq)]
+#[cfg_attr(
+    feature = "serde",
+    derive(serde::Serialize, serde::Deserialize),
+    serde(from = "crate::serialization::QuadFields")
+)]
+pub struct Quad {
+    #[cfg_attr(feature = "serde", serde(skip))]
+    id: Uuid,
+    #[cfg_attr(feature = "serde", serde(skip))]
+    parent: Option<Uuid>,
+    visibility: Visibility,
+    tags: Tags,
+    material: Material,
+    transform: Transform,
+    pub origin: Point,
+    pub uvec: Vector,
+    pub vvec: Vector,
+    #[cfg_attr(feature = "serde", serde(skip_serializing))]
+    pub normal: Vector,
+}
+
+impl Quad {
+    pub fn new(origin: Point, uvec: Vector, vvec: Vector) -> Self {
+        Self {
+            id: Uuid::new_v4(),
+            parent: None,
+            visibility: Visibility::default(),
+            tags: Tags::new(),
+            material: Material::default(),
+            transform: IDENTITY,
+            origin,
+            uvec,
+            vvec,
+            normal: vvec.cross(uvec).normalize(),
+        }
+    }
+
+    /// The quad's own texture coordinates, for texturing it with a
+    /// `TextureMap`.
+    pub fn uv_mapping(&self) -> UvMapping {
+        UvMapping::Parallelogram {
+            origin: self.origin,
+            uvec: self.uvec,
+            vvec: self.vvec,
+        }
+    }
+
+    pub fn uv_at(&self, point: Point) -> (f32, f32) {
+        self.uv_mapping().map(point)
+    }
+}
+
+impl Default for Quad {
+    /// The square from -1 to 1 on x and z, facing up.
+    fn default() -> Self {
+        Self::new(
+            Point::new(-1.0, 0.0, -1.0),
+            Vector::new(2.0, 0.0, 0.0),
+            Vector::new(0.0, 0.0, 2.0),
+        )
+    }
+}
+
+impl ShapeBuilder for Quad {
+    fn with_transform(self, transform: Transform) -> Self {
+        Self { transform, ..self }
+    }
+
+    fn with_material(self, material: Material) -> Self {
+        Self { material, ..self }
+    }
+}
+
+impl Shape for Quad {
+    fn id(&self) -> Uuid {
+        self.id
+    }
+
+    fn as_any(&self) -> &dyn Any {
+        self
+    }
+
+    fn transform(&self) -> &Transform {
+        &self.transform
+    }
+
+    fn set_transform(&mut self, transform: Transform) {
+        self.transform = transform;
+    }
+
+    fn material(&self) -> &Material {
+        &self.material
+    }
+
+    fn material_mut(&mut self) -> &mut Material {
+        &mut self.material
+    }
+
+    fn set_material(&mut self, material: Material) {
+        self.material = material;
+    }
+
+    fn parent(&self) -> Option<Uuid> {
+        self.parent
+    }
+
+    fn set_parent(&mut self, parent: Uuid) {
+        self.parent = Some(parent);
+    }
+
+    fn clear_parent(&mut self) {
+        self.parent = None;
+    }
+
+    fn visibility(&self) -> Visibility {
+        self.visibility
+    }
+
+    fn set_visibility(&mut self, visibility: Visibility) {
+        self.visibility = visibility;
+    }
+
+    fn tags(&self) -> &Tags {
+        &self.tags
+    }
+
+    fn tags_mut(&mut self) -> &mut Tags {
+        &mut self.tags
+    }
+
+    fn parameters(&self) -> Vec<(&'static str, String)> {
+        vec![
+            ("origin", format!("{:?}", self.origin)),
+            ("uvec", format!("{:?}", self.uvec)),
+            ("vvec", format!("{:?}", self.vvec)),
+        ]
+    }
+
+    fn local_intersect(&self, ray: Ray) -> Vec<Intersection> {
+        let denominator = ray.direction.dot(self.normal);
+        if denominator.abs() < EPSILON {
+            return vec![];
+        }
+
+        let t = (self.origin - ray.origin).dot(self.normal) / denominator;
+        let (u, v) = self.uv_at(ray.position(t));
+        if !(0.0..=1.0).contains(&u) || !(0.0..=1.0).contains(&v) {
+            return vec![];
+        }
+
+        vec![Intersection::new(t, self)]
+    }
+
+    fn local_normal_at(&self, _point: Point, _hit: Option<&Intersection>) -> Vector {
+        self.normal
+    }
+
+    fn bounds(&self) -> BoundingBox {
+        BoundingBox::default()
+            .add_point(self.origin)
+            .add_point(self.origin + self.uvec)
+            .add_point(self.origin + self.vvec)
+            .add_point(self.origin + self.uvec + self.vvec)
+    }
+
+    fn local_surface_point(&self, u: f32, v: f32) -> Option<Point> {
+        Some(self.origin + self.uvec * u + self.vvec * v)
+    }
+
+    fn local_surface_area(&self) -> f32 {
+        self.uvec.cross(self.vvec).magnitude()
+    }
+
+    fn tessellate(&self, _resolution: usize) -> Option<Mesh> {
+        Some(Mesh::new(
+            vec![
+                self.origin,
+                self.origin + self.uvec,
+                self.origin + self.uvec + self.vvec,
+                self.origin + self.vvec,
+            ],
+            vec![[0, 1, 2], [0, 2, 3]],
+        ))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{
+        color::{self, Color},
+        float_eq,
+        lights::PointLight,
+        patterns::{
+            uv::{TextureMap, UvChecker},
+            Pattern,
+        },
+        world::World,
+    };
+
+    use super::*;
+
+    fn wall() -> Quad {
+        Quad::new(
+            Point::new(1.0, 2.0, 0.0),
+            Vector::new(4.0, 0.0, 0.0),
+            Vector::new(0.0, 2.0, 0.0),
+        )
+    }
+
+    #[test]
+    fn constructing_a_quad() {
+        let q = wall();
+
+        assert_eq!(q.origin, Point::new(1.0, 2.0, 0.0));
+        assert_eq!(q.normal, Vector::new(0.0, 0.0, -1.0));
+        assert_eq!(Quad::default().normal, Vector::new(0.0, 1.0, 0.0));
+    }
+
+    #[test]
+    fn the_normal_of_a_quad_is_the_same_everywhere() {
+        let q = wall();
+
+        assert_eq!(q.local_normal_at(Point::new(2.0, 3.0, 0.0), None), q.normal);
+        assert_eq!(q.local_normal_at(Point::new(4.0, 2.5, 0.0), None), q.normal);
+    }
+
+    macro_rules! intersect_tests {
+        ($($name:ident: $value:expr,)*) => {
+            $(
+                #[test]
+                fn $name() {
+                    let (origin, direction, expected): (Point, Vector, Vec<f32>) = $value;
+                    let r = Ray::new(origin, direction);
+
+                    let ts = wall().local_intersect(r).iter().map(|i| i.t).collect::<Vec<_>>();
+
+                    assert_eq!(ts, expected);
+                }
+            )*
+        }
+    }
+
+    intersect_tests! {
+        a_ray_strikes_a_quad: (Point::new(2.0, 3.0, -5.0), Vector::new(0.0, 0.0, 1.0), vec![5.0]),
+        a_ray_strikes_a_quad_from_behind: (Point::new(2.0, 3.0, 2.0), Vector::new(0.0, 0.0, -1.0), vec![2.0]),
+        a_ray_strikes_a_quad_corner: (Point::new(5.0, 4.0, -1.0), Vector::new(0.0, 0.0, 1.0), vec![1.0]),
+        a_ray_misses_a_quad_to_the_side: (Point::new(5.5, 3.0, -5.0), Vector::new(0.0, 0.0, 1.0), vec![]),
+        a_ray_misses_a_quad_above: (Point::new(2.0, 4.5, -5.0), Vector::new(0.0, 0.0, 1.0), vec![]),
+        a_ray_parallel_to_a_quad: (Point::new(2.0, 3.0, 0.0), Vector::new(1.0, 0.0, 0.0), vec![]),
+    }
+
+    macro_rules! uv_tests {
+        ($($name:ident: $value:expr,)*) => {
+            $(
+                #[test]
+                fn $name() {
+                    let (point, (u, v)) = $value;
+
+                    let (actual_u, actual_v) = wall().uv_at(point);
+
+                    assert!(float_eq(actual_u, u));
+                    assert!(float_eq(actual_v, v));
+                }
+            )*
+        }
+    }
+
+    uv_tests! {
+        uv_at_the_origin_of_a_quad: (Point::new(1.0, 2.0, 0.0), (0.0, 0.0)),
+        uv_at_the_far_corner_of_a_quad: (Point::new(5.0, 4.0, 0.0), (1.0, 1.0)),
+        uv_in_the_middle_of_a_quad: (Point::new(3.0, 3.0, 0.0), (0.5, 0.5)),
+        uv_along_a_quad_edge: (Point::new(2.0, 2.0, 0.0), (0.25, 0.0)),
+    }
+
+    #[test]
+    fn texturing_a_quad_with_its_own_coordinates() {
+        let q = wall();
+        let pattern = TextureMap::new(
+            UvChecker::new(2.0, 2.0, color::BLACK, color::WHITE),
+            q.uv_mapping(),
+        );
+
+        assert_eq!(pattern.pattern_at(Point::new(1.5, 2.5, 0.0)), color::BLACK);
+        assert_eq!(pattern.pattern_at(Point::new(3.5, 2.5, 0.0)), color::WHITE);
+        assert_eq!(pattern.pattern_at(Point::new(3.5, 3.5, 0.0)), color::BLACK);
+    }
+
+    #[test]
+    fn a_quad_has_a_bounding_box() {
+        let b = wall().bounds();
+
+        assert_eq!(b.min, Point::new(1.0, 2.0, 0.0));
+        assert_eq!(b.max, Point::new(5.0, 4.0, 0.0));
+    }
+
+    #[test]
+    fn surface_samples_cover_the_whole_quad() {
+        let q = wall().with_transform(Transform::scaling(2.0, 1.0, 1.0));
+
+        let first = q.sample_surface(0.0, 0.0).unwrap();
+        let last = q.sample_surface(1.0, 1.0).unwrap();
+
+        assert_eq!(first.point, Point::new(2.0, 2.0, 0.0));
+        assert_eq!(last.point, Point::new(10.0, 4.0, 0.0));
+        assert_eq!(first.normal, Vector::new(0.0, 0.0, -1.0));
+        assert!(float_eq(first.area, 16.0));
+    }
+
+    #[test]
+    fn tessellating_a_quad_gives_two_triangles_facing_its_way() {
+        let q = wall();
+
+        let mesh = q.tessellate(16).unwrap();
+
+        assert_eq!(mesh.faces.len(), 2);
+        for face in &mesh.faces {
+            let [a, b, c] = [
+                mesh.vertices[face[0]],
+                mesh.vertices[face[1]],
+                mesh.vertices[face[2]],
+            ];
+            assert_eq!((c - a).cross(b - a).normalize(), q.normal);
+        }
+    }
+
+    #[test]
+    fn a_glowing_quad_lights_the_floor_like_an_area_light() {
+        let panel = Quad::default()
+            .with_transform(
+                Transform::translation(0.0, 2.0, 0.0) * Transform::rotation_x(std::f32::consts::PI),
+            )
+            .with_material(Material::default().emissive(Color::new(4.0, 4.0, 4.0)));
+        let w = World::new(PointLight::default()).object(Box::new(panel));
+        let floor = Quad::default().with_transform(Transform::scaling(10.0, 1.0, 10.0));
+        let r = Ray::new(Point::new(0.0, 1.0, 0.0), Vector::new(0.0, -1.0, 0.0));
+        let i = Intersection::new(1.0, &floor);
+        let comps = i.prepare_computations(r, &[i]);
+
+        assert!(w.emitted_lighting(comps).red() > 0.0);
+    }
+}
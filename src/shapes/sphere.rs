@@ -1,18 +1,31 @@
+use std::{any::Any, f32::consts::PI};
+
 use uuid::Uuid;
 
 use crate::{
+    bounds::BoundingBox,
     intersection::Intersection,
     materials::Material,
+    packet::RayPacket,
     ray::Ray,
-    shapes::{Shape, ShapeBuilder},
+    shapes::{mesh::Mesh, Shape, ShapeBuilder, Tags, Visibility},
     transformations::{Transform, IDENTITY},
-    tuple::Tuple,
+    tuple::{Point, Vector},
 };
 
 #[derive(Clone, Debug, PartialEq)]
+#[cfg_attr(
+    feature = "serde",
+    derive(serde::Serialize, serde::Deserialize),
+    serde(default)
+)]
 pub struct Sphere {
+    #[cfg_attr(feature = "serde", serde(skip))]
     id: Uuid,
+    #[cfg_attr(feature = "serde", serde(skip))]
     parent: Option<Uuid>,
+    visibility: Visibility,
+    tags: Tags,
     transform: Transform,
     material: Material,
 }
@@ -32,6 +45,8 @@ impl Default for Sphere {
         Self {
             id: Uuid::new_v4(),
             parent: None,
+            visibility: Visibility::default(),
+            tags: Tags::new(),
             transform: IDENTITY,
             material: Material::default(),
         }
@@ -53,6 +68,10 @@ impl Shape for Sphere {
         self.id
     }
 
+    fn as_any(&self) -> &dyn Any {
+        self
+    }
+
     fn transform(&self) -> &Transform {
         &self.transform
     }
@@ -81,6 +100,26 @@ impl Shape for Sphere {
         self.parent = Some(parent);
     }
 
+    fn clear_parent(&mut self) {
+        self.parent = None;
+    }
+
+    fn visibility(&self) -> Visibility {
+        self.visibility
+    }
+
+    fn set_visibility(&mut self, visibility: Visibility) {
+        self.visibility = visibility;
+    }
+
+    fn tags(&self) -> &Tags {
+        &self.tags
+    }
+
+    fn tags_mut(&mut self) -> &mut Tags {
+        &mut self.tags
+    }
+
     fn local_intersect(&self, ray: Ray) -> Vec<Intersection> {
         let discriminant = discriminant(ray);
 
@@ -97,8 +136,50 @@ impl Shape for Sphere {
         }
     }
 
-    fn local_normal_at(&self, point: Tuple) -> Tuple {
-        point - Tuple::point(0.0, 0.0, 0.0)
+    fn local_intersect_packet(&self, packet: &RayPacket) -> [Vec<Intersection>; 4] {
+        let mut hits: [Vec<Intersection>; 4] = Default::default();
+        for (lane, ts) in packet.sphere_hits().iter().enumerate() {
+            if let Some((t1, t2)) = *ts {
+                hits[lane] = vec![Intersection::new(t1, self), Intersection::new(t2, self)];
+            }
+        }
+        hits
+    }
+
+    fn local_normal_at(&self, point: Point, _hit: Option<&Intersection>) -> Vector {
+        point - Point::new(0.0, 0.0, 0.0)
+    }
+
+    fn bounds(&self) -> BoundingBox {
+        BoundingBox::new(Point::new(-1.0, -1.0, -1.0), Point::new(1.0, 1.0, 1.0))
+    }
+
+    fn local_surface_point(&self, u: f32, v: f32) -> Option<Point> {
+        let y = 1.0 - 2.0 * u;
+        let radius = (1.0 - y * y).max(0.0).sqrt();
+        let phi = 2.0 * PI * v;
+        Some(Point::new(radius * phi.cos(), y, radius * phi.sin()))
+    }
+
+    fn local_surface_area(&self) -> f32 {
+        4.0 * PI
+    }
+
+    fn tessellate(&self, resolution: usize) -> Option<Mesh> {
+        let stacks = (resolution / 2).max(2);
+        let profile = (0..=stacks)
+            .map(|i| {
+                let phi = PI * i as f32 / stacks as f32;
+                let radius = if i == 0 || i == stacks {
+                    0.0
+                } else {
+                    phi.sin()
+                };
+                (radius, -phi.cos())
+            })
+            .collect::<Vec<_>>();
+
+        Some(Mesh::revolve(&profile, resolution.max(3)))
     }
 }
 
@@ -107,12 +188,12 @@ fn a(ray: Ray) -> f32 {
 }
 
 fn b(ray: Ray) -> f32 {
-    let sphere_to_ray = ray.origin - Tuple::point(0.0, 0.0, 0.0);
+    let sphere_to_ray = ray.origin - Point::new(0.0, 0.0, 0.0);
     2.0 * ray.direction.dot(sphere_to_ray)
 }
 
 fn c(ray: Ray) -> f32 {
-    let sphere_to_ray = ray.origin - Tuple::point(0.0, 0.0, 0.0);
+    let sphere_to_ray = ray.origin - Point::new(0.0, 0.0, 0.0);
     sphere_to_ray.dot(sphere_to_ray) - 1.0
 }
 
@@ -127,7 +208,7 @@ mod tests {
         test::*,
         transformations::{self, IDENTITY},
     };
-    use std::f32::consts::{FRAC_1_SQRT_2, PI};
+    use std::f32::consts::FRAC_1_SQRT_2;
 
     use super::*;
 
@@ -151,7 +232,7 @@ mod tests {
 
     #[test]
     fn ray_intersects_sphere_at_two_points() {
-        let r = Ray::new(Tuple::point(0.0, 0.0, -5.0), Tuple::vector(0.0, 0.0, 1.0));
+        let r = Ray::new(Point::new(0.0, 0.0, -5.0), Vector::new(0.0, 0.0, 1.0));
         let s = Sphere::default();
 
         let xs = s.intersect(r);
@@ -163,7 +244,7 @@ mod tests {
 
     #[test]
     fn ray_intersects_sphere_at_a_tangent() {
-        let r = Ray::new(Tuple::point(0.0, 1.0, -5.0), Tuple::vector(0.0, 0.0, 1.0));
+        let r = Ray::new(Point::new(0.0, 1.0, -5.0), Vector::new(0.0, 0.0, 1.0));
         let s = Sphere::default();
 
         let xs = s.intersect(r);
@@ -175,7 +256,7 @@ mod tests {
 
     #[test]
     fn ray_misses_sphere() {
-        let r = Ray::new(Tuple::point(0.0, 2.0, -5.0), Tuple::vector(0.0, 0.0, 1.0));
+        let r = Ray::new(Point::new(0.0, 2.0, -5.0), Vector::new(0.0, 0.0, 1.0));
         let s = Sphere::default();
 
         let xs = s.intersect(r);
@@ -184,7 +265,7 @@ mod tests {
 
     #[test]
     fn ray_originating_inside_sphere() {
-        let r = Ray::new(Tuple::point(0.0, 0.0, 0.0), Tuple::vector(0.0, 0.0, 1.0));
+        let r = Ray::new(Point::new(0.0, 0.0, 0.0), Vector::new(0.0, 0.0, 1.0));
         let s = Sphere::default();
 
         let xs = s.intersect(r);
@@ -196,7 +277,7 @@ mod tests {
 
     #[test]
     fn sphere_behind_ray() {
-        let r = Ray::new(Tuple::point(0.0, 0.0, 5.0), Tuple::vector(0.0, 0.0, 1.0));
+        let r = Ray::new(Point::new(0.0, 0.0, 5.0), Vector::new(0.0, 0.0, 1.0));
         let s = Sphere::default();
 
         let xs = s.intersect(r);
@@ -223,7 +304,7 @@ mod tests {
 
     #[test]
     fn intersecting_a_scaled_sphere_with_a_ray() {
-        let r = Ray::new(Tuple::point(0.0, 0.0, -5.0), Tuple::vector(0.0, 0.0, 1.0));
+        let r = Ray::new(Point::new(0.0, 0.0, -5.0), Vector::new(0.0, 0.0, 1.0));
 
         let s = Sphere::default().with_transform(Transform::scaling(2.0, 2.0, 2.0));
         let xs = s.intersect(r);
@@ -237,38 +318,41 @@ mod tests {
     fn normal_at_a_point_on_x_axis() {
         let s = Sphere::default();
 
-        let n = s.normal_at(1.0, 0.0, 0.0);
+        let n = s.normal_at(Point::new(1.0, 0.0, 0.0), None);
 
-        assert_eq!(n, Tuple::vector(1.0, 0.0, 0.0));
+        assert_eq!(n, Vector::new(1.0, 0.0, 0.0));
     }
 
     #[test]
     fn normal_at_a_point_on_y_axis() {
         let s = Sphere::default();
 
-        let n = s.normal_at(0.0, 1.0, 0.0);
+        let n = s.normal_at(Point::new(0.0, 1.0, 0.0), None);
 
-        assert_eq!(n, Tuple::vector(0.0, 1.0, 0.0));
+        assert_eq!(n, Vector::new(0.0, 1.0, 0.0));
     }
 
     #[test]
     fn normal_at_a_point_on_z_axis() {
         let s = Sphere::default();
 
-        let n = s.normal_at(0.0, 0.0, 1.0);
+        let n = s.normal_at(Point::new(0.0, 0.0, 1.0), None);
 
-        assert_eq!(n, Tuple::vector(0.0, 0.0, 1.0));
+        assert_eq!(n, Vector::new(0.0, 0.0, 1.0));
     }
 
     #[test]
     fn normal_at_a_nonaxial_point() {
         let s = Sphere::default();
 
-        let n = s.normal_at(sqrt_n_over_n(3), sqrt_n_over_n(3), sqrt_n_over_n(3));
+        let n = s.normal_at(
+            Point::new(sqrt_n_over_n(3), sqrt_n_over_n(3), sqrt_n_over_n(3)),
+            None,
+        );
 
         assert_eq!(
             n,
-            Tuple::vector(sqrt_n_over_n(3), sqrt_n_over_n(3), sqrt_n_over_n(3))
+            Vector::new(sqrt_n_over_n(3), sqrt_n_over_n(3), sqrt_n_over_n(3))
         );
     }
 
@@ -276,7 +360,10 @@ mod tests {
     fn normal_is_normalized() {
         let s = Sphere::default();
 
-        let n = s.normal_at(sqrt_n_over_n(3), sqrt_n_over_n(3), sqrt_n_over_n(3));
+        let n = s.normal_at(
+            Point::new(sqrt_n_over_n(3), sqrt_n_over_n(3), sqrt_n_over_n(3)),
+            None,
+        );
 
         assert_eq!(n, n.normalize());
     }
@@ -284,9 +371,9 @@ mod tests {
     #[test]
     fn normal_on_a_translated_sphere() {
         let s = Sphere::default().with_transform(Transform::translation(0.0, 1.0, 0.0));
-        let n = s.normal_at(0.0, 1.0 + FRAC_1_SQRT_2, -FRAC_1_SQRT_2);
+        let n = s.normal_at(Point::new(0.0, 1.0 + FRAC_1_SQRT_2, -FRAC_1_SQRT_2), None);
 
-        assert_eq!(n, Tuple::vector(0.0, FRAC_1_SQRT_2, -FRAC_1_SQRT_2));
+        assert_eq!(n, Vector::new(0.0, FRAC_1_SQRT_2, -FRAC_1_SQRT_2));
     }
 
     #[test]
@@ -294,9 +381,9 @@ mod tests {
         let m = Transform::scaling(1.0, 0.5, 1.0) * Transform::rotation_z(PI / 5.0);
         let s = Sphere::default().with_transform(m);
 
-        let n = s.normal_at(0.0, sqrt_n_over_n(2), -sqrt_n_over_n(2));
+        let n = s.normal_at(Point::new(0.0, sqrt_n_over_n(2), -sqrt_n_over_n(2)), None);
 
-        assert_eq!(n, Tuple::vector(0.0, 0.97014, -0.24254));
+        assert_eq!(n, Vector::new(0.0, 0.97014, -0.24254));
     }
 
     #[test]
@@ -307,4 +394,64 @@ mod tests {
         assert!(float_eq(s.material.transparency, 1.0));
         assert!(float_eq(s.material.refractive_index, 1.5));
     }
+
+    #[test]
+    fn surface_samples_lie_on_the_sphere() {
+        let s = Sphere::default();
+
+        for &(u, v) in &[(0.0, 0.0), (0.25, 0.5), (0.5, 0.1), (0.99, 0.7)] {
+            let sample = s.sample_surface(u, v).unwrap();
+
+            assert!(float_eq(sample.point.to_vector().magnitude(), 1.0));
+            assert_eq!(sample.normal, sample.point.to_vector());
+            assert!(float_eq(sample.area, 4.0 * PI));
+        }
+    }
+
+    #[test]
+    fn sampling_a_transformed_sphere() {
+        let s = Sphere::default().with_transform(
+            Transform::translation(0.0, 5.0, 0.0) * Transform::scaling(2.0, 2.0, 2.0),
+        );
+
+        let sample = s.sample_surface(0.0, 0.0).unwrap();
+
+        assert_eq!(sample.point, Point::new(0.0, 7.0, 0.0));
+        assert_eq!(sample.normal, Vector::new(0.0, 1.0, 0.0));
+        assert!(float_eq(sample.area, 16.0 * PI));
+    }
+
+    #[test]
+    fn a_sphere_has_a_bounding_box() {
+        let s = Sphere::default();
+
+        let b = s.bounds();
+
+        assert_eq!(b.min, Point::new(-1.0, -1.0, -1.0));
+        assert_eq!(b.max, Point::new(1.0, 1.0, 1.0));
+    }
+
+    #[test]
+    fn querying_a_shapes_bounding_box_in_its_parents_space() {
+        let s = Sphere::default().with_transform(
+            Transform::translation(1.0, -3.0, 5.0) * Transform::scaling(0.5, 2.0, 4.0),
+        );
+
+        let b = s.parent_space_bounds();
+
+        assert_eq!(b.min, Point::new(0.5, -5.0, 1.0));
+        assert_eq!(b.max, Point::new(1.5, -1.0, 9.0));
+    }
+
+    #[test]
+    fn tessellating_a_sphere() {
+        let mesh = Sphere::default().tessellate(64).unwrap();
+
+        assert!(mesh.is_closed());
+        assert!((mesh.signed_volume() - 4.0 / 3.0 * PI).abs() < 0.05);
+        assert!(mesh
+            .vertices
+            .iter()
+            .all(|v| float_eq(v.to_vector().magnitude(), 1.0)));
+    }
 }
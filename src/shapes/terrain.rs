@@ -0,0 +1,127 @@
+use crate::{
+    canvas::Canvas,
+    shapes::{group::Group, mesh::Mesh},
+    tuple::{Point, Vector},
+};
+
+/// A grid of vertices, one per pixel of `image`, raised by the pixel's
+/// brightness. The terrain is centred on the origin, `scale.x` wide and
+/// `scale.z` deep, with white `scale.y` high. Seen from above it reads like
+/// a map, with the top of the image toward +z.
+pub fn heightmap_mesh(image: &Canvas, scale: Vector) -> Mesh {
+    let (width, height) = (image.width, image.height);
+    if width < 2 || height < 2 {
+        return Mesh::default();
+    }
+
+    let mut vertices = vec![];
+    for row in 0..height {
+        for col in 0..width {
+            vertices.push(Point::new(
+                (col as f32 / (width - 1) as f32 - 0.5) * scale.x(),
+                image.pixel_at(col, row).luminance() * scale.y(),
+                (0.5 - row as f32 / (height - 1) as f32) * scale.z(),
+            ));
+        }
+    }
+
+    let mut faces = vec![];
+    for row in 0..height - 1 {
+        for col in 0..width - 1 {
+            let top_left = row * width + col;
+            let top_right = top_left + 1;
+            let bottom_left = top_left + width;
+            let bottom_right = bottom_left + 1;
+            faces.push([bottom_left, bottom_right, top_left]);
+            faces.push([bottom_right, top_right, top_left]);
+        }
+    }
+
+    Mesh::new(vertices, faces).smooth_normals()
+}
+
+/// The terrain from `heightmap_mesh` as a group of smooth triangles.
+pub fn from_heightmap(image: &Canvas, scale: Vector) -> Group {
+    heightmap_mesh(image, scale).to_group()
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{
+        color::{self, Color},
+        float_eq,
+        ray::Ray,
+        shapes::Shape,
+    };
+
+    use super::*;
+
+    /// A ridge running along z, high in the middle column.
+    fn ridge() -> Canvas {
+        Canvas::from_ppm(
+            "P3 3 2 255
+             0 0 0  255 255 255  0 0 0
+             0 0 0  255 255 255  0 0 0"
+                .as_bytes(),
+        )
+        .unwrap()
+    }
+
+    #[test]
+    fn a_heightmap_has_a_vertex_per_pixel_and_two_triangles_per_cell() {
+        let mesh = heightmap_mesh(&ridge(), Vector::new(4.0, 1.0, 2.0));
+
+        assert_eq!(mesh.vertices.len(), 6);
+        assert_eq!(mesh.faces.len(), 4);
+        assert_eq!(mesh.vertices[0], Point::new(-2.0, 0.0, 1.0));
+        assert_eq!(mesh.vertices[4], Point::new(0.0, 1.0, -1.0));
+        assert_eq!(mesh.vertices[5], Point::new(2.0, 0.0, -1.0));
+    }
+
+    #[test]
+    fn a_flat_heightmap_faces_up() {
+        let mut image = Canvas::new(3, 3);
+        for y in 0..3 {
+            for x in 0..3 {
+                image.write_pixel(x, y, Color::new(0.5, 0.5, 0.5));
+            }
+        }
+
+        let mesh = heightmap_mesh(&image, Vector::new(1.0, 2.0, 1.0));
+
+        for triangle in mesh.triangles() {
+            assert_eq!(triangle.normal, Vector::new(0.0, 1.0, 0.0));
+        }
+        assert!(mesh.vertices.iter().all(|p| float_eq(p.y(), 1.0)));
+    }
+
+    #[test]
+    fn normals_on_a_ridge_lean_away_from_the_crest() {
+        let mesh = heightmap_mesh(&ridge(), Vector::new(4.0, 1.0, 2.0));
+
+        assert!(mesh.normals.iter().all(|n| n.y() > 0.0));
+        assert!(mesh.normals[0].x() < 0.0 && mesh.normals[3].x() < 0.0);
+        assert!(mesh.normals[2].x() > 0.0 && mesh.normals[5].x() > 0.0);
+        assert_eq!(mesh.triangles()[0].normals.unwrap()[0], mesh.normals[3]);
+    }
+
+    #[test]
+    fn a_ray_from_above_lands_on_the_terrain() {
+        let terrain = from_heightmap(&ridge(), Vector::new(4.0, 1.0, 2.0));
+        let r = Ray::new(Point::new(0.0, 5.0, 0.0), Vector::new(0.0, -1.0, 0.0));
+
+        let xs = terrain.intersect(r);
+
+        assert!(float_eq(xs[0].t, 4.0));
+    }
+
+    #[test]
+    fn an_image_too_small_to_make_a_grid() {
+        let mut image = Canvas::new(1, 4);
+        image.write_pixel(0, 0, color::WHITE);
+
+        assert!(from_heightmap(&image, Vector::new(1.0, 1.0, 1.0))
+            .children()
+            .is_empty());
+    }
+}
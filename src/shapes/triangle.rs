@@ -0,0 +1,563 @@
+use std::any::Any;
+
+use uuid::Uuid;
+
+use crate::{
+    bounds::BoundingBox,
+    color::Color,
+    intersection::Intersection,
+    materials::Material,
+    ray::Ray,
+    shapes::{mesh::Mesh, Shape, ShapeBuilder, Tags, Visibility},
+    transformations::{Transform, IDENTITY},
+    tuple::{Point, Vector},
+    EPSILON,
+};
+
+#[derive(Clone, Debug, PartialEq)]
+#[cfg_attr(
+    feature = "serde",
+    derive(serde::Serialize, serde::Deserialize),
+    serde(from = "crate::serialization::TriangleFields")
+)]
+pub struct Triangle {
+    #[cfg_attr(feature = "serde", serde(skip))]
+    id: Uuid,
+    #[cfg_attr(feature = "serde", serde(skip))]
+    parent: Option<Uuid>,
+    visibility: Visibility,
+    tags: Tags,
+    material: Material,
+    transform: Transform,
+    pub p1: Point,
+    pub p2: Point,
+    pub p3: Point,
+    #[cfg_attr(feature = "serde", serde(skip_serializing))]
+    pub e1: Vector,
+    #[cfg_attr(feature = "serde", serde(skip_serializing))]
+    pub e2: Vector,
+    #[cfg_attr(feature = "serde", serde(skip_serializing))]
+    pub normal: Vector,
+    /// Colors at `p1`, `p2` and `p3`, blended across the face in place of
+    /// the material's color.
+    #[cfg_attr(feature = "serde", serde(skip_serializing_if = "Option::is_none"))]
+    pub colors: Option<[Color; 3]>,
+    /// Normals at `p1`, `p2` and `p3`, blended across the face so a mesh
+    /// of flat triangles shades as a smooth surface.
+    #[cfg_attr(feature = "serde", serde(skip_serializing_if = "Option::is_none"))]
+    pub normals: Option<[Vector; 3]>,
+}
+
+impl Triangle {
+    pub fn new(p1: Point, p2: Point, p3: Point) -> Self {
+        let e1 = p2 - p1;
+        let e2 = p3 - p1;
+        Self {
+            id: Uuid::new_v4(),
+            parent: None,
+            visibility: Visibility::default(),
+            tags: Tags::new(),
+            material: Material::default(),
+            transform: IDENTITY,
+            p1,
+            p2,
+            p3,
+            e1,
+            e2,
+            normal: e2.cross(e1).normalize(),
+            colors: None,
+            normals: None,
+        }
+    }
+
+    pub fn with_vertex_colors(self, colors: [Color; 3]) -> Self {
+        Self {
+            colors: Some(colors),
+            ..self
+        }
+    }
+
+    pub fn with_vertex_normals(self, normals: [Vector; 3]) -> Self {
+        Self {
+            normals: Some(normals),
+            ..self
+        }
+    }
+
+    /// Möller and Trumbore's test, as in the book, giving `t`, `u` and
+    /// `v`. It's quick, but rounding can let a ray through an edge shared
+    /// by two triangles slip between them both.
+    fn moller_trumbore(&self, ray: Ray) -> Option<(f32, f32, f32)> {
+        let dir_cross_e2 = ray.direction.cross(self.e2);
+        let det = self.e1.dot(dir_cross_e2);
+        if det.abs() < EPSILON {
+            return None;
+        }
+
+        let f = 1.0 / det;
+        let p1_to_origin = ray.origin - self.p1;
+        let u = f * p1_to_origin.dot(dir_cross_e2);
+        if !(0.0..=1.0).contains(&u) {
+            return None;
+        }
+
+        let origin_cross_e1 = p1_to_origin.cross(self.e1);
+        let v = f * ray.direction.dot(origin_cross_e1);
+        if v < 0.0 || u + v > 1.0 {
+            return None;
+        }
+
+        Some((f * self.e2.dot(origin_cross_e1), u, v))
+    }
+
+    /// Woop, Benthin and Wald's watertight test, giving `t`, `u` and `v`.
+    /// The triangle is moved and sheared so the ray runs down the z axis
+    /// from the origin, where a shared edge is tested the same way from
+    /// either side, so a ray through it always hits at least one of the
+    /// triangles meeting there.
+    fn watertight(&self, ray: Ray) -> Option<(f32, f32, f32)> {
+        let d = ray.direction;
+        let kz = (0..3)
+            .max_by(|&a, &b| d[a].abs().partial_cmp(&d[b].abs()).unwrap())
+            .unwrap();
+        let (mut kx, mut ky) = ((kz + 1) % 3, (kz + 2) % 3);
+        if d[kz] < 0.0 {
+            std::mem::swap(&mut kx, &mut ky);
+        }
+        let (sx, sy, sz) = (d[kx] / d[kz], d[ky] / d[kz], 1.0 / d[kz]);
+
+        let (a, b, c) = (
+            self.p1 - ray.origin,
+            self.p2 - ray.origin,
+            self.p3 - ray.origin,
+        );
+        let (ax, ay) = (a[kx] - sx * a[kz], a[ky] - sy * a[kz]);
+        let (bx, by) = (b[kx] - sx * b[kz], b[ky] - sy * b[kz]);
+        let (cx, cy) = (c[kx] - sx * c[kz], c[ky] - sy * c[kz]);
+
+        let edge = |px: f32, py: f32, qx: f32, qy: f32| {
+            let e = px * qy - py * qx;
+            if e == 0.0 {
+                // Too close to call in single precision.
+                (px as f64 * qy as f64 - py as f64 * qx as f64) as f32
+            } else {
+                e
+            }
+        };
+        let (u, v, w) = (
+            edge(cx, cy, bx, by),
+            edge(ax, ay, cx, cy),
+            edge(bx, by, ax, ay),
+        );
+        if (u < 0.0 || v < 0.0 || w < 0.0) && (u > 0.0 || v > 0.0 || w > 0.0) {
+            return None;
+        }
+        let det = u + v + w;
+        if det == 0.0 {
+            return None;
+        }
+
+        let t = (u * a[kz] + v * b[kz] + w * c[kz]) * sz;
+        Some((t / det, v / det, w / det))
+    }
+}
+
+impl ShapeBuilder for Triangle {
+    fn with_transform(self, transform: Transform) -> Self {
+        Self { transform, ..self }
+    }
+
+    fn with_material(self, material: Material) -> Self {
+        Self { material, ..self }
+    }
+}
+
+impl Shape for Triangle {
+    fn id(&self) -> Uuid {
+        self.id
+    }
+
+    fn as_any(&self) -> &dyn Any {
+        self
+    }
+
+    fn transform(&self) -> &Transform {
+        &self.transform
+    }
+
+    fn set_transform(&mut self, transform: Transform) {
+        self.transform = transform;
+    }
+
+    fn material(&self) -> &Material {
+        &self.material
+    }
+
+    fn material_mut(&mut self) -> &mut Material {
+        &mut self.material
+    }
+
+    fn set_material(&mut self, material: Material) {
+        self.material = material;
+    }
+
+    fn parent(&self) -> Option<Uuid> {
+        self.parent
+    }
+
+    fn set_parent(&mut self, parent: Uuid) {
+        self.parent = Some(parent);
+    }
+
+    fn clear_parent(&mut self) {
+        self.parent = None;
+    }
+
+    fn visibility(&self) -> Visibility {
+        self.visibility
+    }
+
+    fn set_visibility(&mut self, visibility: Visibility) {
+        self.visibility = visibility;
+    }
+
+    fn tags(&self) -> &Tags {
+        &self.tags
+    }
+
+    fn tags_mut(&mut self) -> &mut Tags {
+        &mut self.tags
+    }
+
+    /// With the `watertight` feature, by the watertight test; otherwise
+    /// by the book's.
+    fn parameters(&self) -> Vec<(&'static str, String)> {
+        vec![
+            ("p1", format!("{:?}", self.p1)),
+            ("p2", format!("{:?}", self.p2)),
+            ("p3", format!("{:?}", self.p3)),
+            ("colors", format!("{:?}", self.colors)),
+            ("normals", format!("{:?}", self.normals)),
+        ]
+    }
+
+    fn local_intersect(&self, ray: Ray) -> Vec<Intersection> {
+        let hit = if cfg!(feature = "watertight") {
+            self.watertight(ray)
+        } else {
+            self.moller_trumbore(ray)
+        };
+        match hit {
+            Some((t, u, v)) => vec![Intersection::new(t, self).uv(u, v)],
+            None => vec![],
+        }
+    }
+
+    /// The face normal, or the vertex normals blended at the hit's `u`
+    /// and `v` when the triangle has them.
+    fn local_normal_at(&self, _point: Point, hit: Option<&Intersection>) -> Vector {
+        match (self.normals, hit) {
+            (Some([n1, n2, n3]), Some(hit)) => n2 * hit.u + n3 * hit.v + n1 * (1.0 - hit.u - hit.v),
+            _ => self.normal,
+        }
+    }
+
+    fn surface_color(&self, hit: &Intersection) -> Option<Color> {
+        let [c1, c2, c3] = self.colors?;
+        Some(c2 * hit.u + c3 * hit.v + c1 * (1.0 - hit.u - hit.v))
+    }
+
+    fn bounds(&self) -> BoundingBox {
+        BoundingBox::default()
+            .add_point(self.p1)
+            .add_point(self.p2)
+            .add_point(self.p3)
+    }
+
+    fn local_surface_point(&self, u: f32, v: f32) -> Option<Point> {
+        let su = u.sqrt();
+        Some(self.p1 + self.e1 * (su * (1.0 - v)) + self.e2 * (su * v))
+    }
+
+    fn local_surface_area(&self) -> f32 {
+        self.e1.cross(self.e2).magnitude() / 2.0
+    }
+
+    fn tessellate(&self, _resolution: usize) -> Option<Mesh> {
+        Some(Mesh::new(vec![self.p1, self.p2, self.p3], vec![[0, 1, 2]]))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::float_eq;
+
+    use super::*;
+
+    fn book_triangle() -> Triangle {
+        Triangle::new(
+            Point::new(0.0, 1.0, 0.0),
+            Point::new(-1.0, 0.0, 0.0),
+            Point::new(1.0, 0.0, 0.0),
+        )
+    }
+
+    #[test]
+    fn constructing_a_triangle() {
+        let p1 = Point::new(0.0, 1.0, 0.0);
+        let p2 = Point::new(-1.0, 0.0, 0.0);
+        let p3 = Point::new(1.0, 0.0, 0.0);
+
+        let t = Triangle::new(p1, p2, p3);
+
+        assert_eq!(t.p1, p1);
+        assert_eq!(t.p2, p2);
+        assert_eq!(t.p3, p3);
+        assert_eq!(t.e1, Vector::new(-1.0, -1.0, 0.0));
+        assert_eq!(t.e2, Vector::new(1.0, -1.0, 0.0));
+        assert_eq!(t.normal, Vector::new(0.0, 0.0, -1.0));
+    }
+
+    #[test]
+    fn finding_the_normal_on_a_triangle() {
+        let t = book_triangle();
+
+        let n1 = t.local_normal_at(Point::new(0.0, 0.5, 0.0), None);
+        let n2 = t.local_normal_at(Point::new(-0.5, 0.75, 0.0), None);
+        let n3 = t.local_normal_at(Point::new(0.5, 0.25, 0.0), None);
+
+        assert_eq!(n1, t.normal);
+        assert_eq!(n2, t.normal);
+        assert_eq!(n3, t.normal);
+    }
+
+    #[test]
+    fn intersecting_a_ray_parallel_to_the_triangle() {
+        let t = book_triangle();
+        let r = Ray::default()
+            .origin(0.0, -1.0, -2.0)
+            .direction(0.0, 1.0, 0.0);
+
+        let xs = t.local_intersect(r);
+
+        assert!(xs.is_empty());
+    }
+
+    #[test]
+    fn a_ray_misses_the_p1_p3_edge() {
+        let t = book_triangle();
+        let r = Ray::default()
+            .origin(1.0, 1.0, -2.0)
+            .direction(0.0, 0.0, 1.0);
+
+        let xs = t.local_intersect(r);
+
+        assert!(xs.is_empty());
+    }
+
+    #[test]
+    fn a_ray_misses_the_p1_p2_edge() {
+        let t = book_triangle();
+        let r = Ray::default()
+            .origin(-1.0, 1.0, -2.0)
+            .direction(0.0, 0.0, 1.0);
+
+        let xs = t.local_intersect(r);
+
+        assert!(xs.is_empty());
+    }
+
+    #[test]
+    fn a_ray_misses_the_p2_p3_edge() {
+        let t = book_triangle();
+        let r = Ray::default()
+            .origin(0.0, -1.0, -2.0)
+            .direction(0.0, 0.0, 1.0);
+
+        let xs = t.local_intersect(r);
+
+        assert!(xs.is_empty());
+    }
+
+    #[test]
+    fn a_ray_strikes_a_triangle() {
+        let t = book_triangle();
+        let r = Ray::default()
+            .origin(0.0, 0.5, -2.0)
+            .direction(0.0, 0.0, 1.0);
+
+        let xs = t.local_intersect(r);
+
+        assert_eq!(xs.len(), 1);
+        assert!(float_eq(xs[0].t, 2.0));
+    }
+
+    #[test]
+    fn an_intersection_with_a_triangle_stores_u_and_v() {
+        let t = book_triangle();
+        let r = Ray::default()
+            .origin(-0.2, 0.3, -2.0)
+            .direction(0.0, 0.0, 1.0);
+
+        let xs = t.local_intersect(r);
+
+        assert!(float_eq(xs[0].u, 0.45));
+        assert!(float_eq(xs[0].v, 0.25));
+    }
+
+    #[test]
+    fn the_watertight_test_agrees_with_the_books() {
+        let t = book_triangle();
+        let rays = [
+            Ray::new(Point::new(0.0, 0.5, -2.0), Vector::new(0.0, 0.0, 1.0)),
+            Ray::new(Point::new(-0.2, 0.3, -2.0), Vector::new(0.1, 0.05, 1.0)),
+            Ray::new(Point::new(0.3, 0.1, 2.0), Vector::new(0.0, 0.0, -1.0)),
+            Ray::new(Point::new(1.0, 1.0, -2.0), Vector::new(0.0, 0.0, 1.0)),
+            Ray::new(Point::new(0.0, -1.0, -2.0), Vector::new(0.0, 0.0, 1.0)),
+            Ray::new(Point::new(0.0, -1.0, -2.0), Vector::new(0.0, 1.0, 0.0)),
+        ];
+
+        for &r in rays.iter() {
+            match (t.watertight(r), t.moller_trumbore(r)) {
+                (Some((t1, u1, v1)), Some((t2, u2, v2))) => {
+                    assert!(float_eq(t1, t2) && float_eq(u1, u2) && float_eq(v1, v2))
+                }
+                (None, None) => {}
+                (a, b) => panic!("{:?} and {:?} disagree about {:?}", a, b, r),
+            }
+        }
+    }
+
+    #[test]
+    fn rays_through_shared_edges_hit_watertight_triangles() {
+        let ring = (0..7)
+            .map(|i| {
+                let angle = i as f32 * 2.0 * std::f32::consts::PI / 7.0;
+                Point::new(angle.cos(), angle.sin(), 0.1 * angle.sin())
+            })
+            .collect::<Vec<_>>();
+        let center = Point::new(0.013, -0.021, 0.0);
+        let fan = (0..7)
+            .map(|i| Triangle::new(center, ring[i], ring[(i + 1) % 7]))
+            .collect::<Vec<_>>();
+        let targets = ring
+            .iter()
+            .flat_map(|&p| (1..4).map(move |k| center + (p - center) * (k as f32 * 0.2371)))
+            .chain(std::iter::once(center));
+
+        for target in targets {
+            for i in 0..5 {
+                let origin = Point::new(i as f32 * 0.731 - 1.3, 0.917 - i as f32 * 0.43, -3.0);
+                let r = Ray::new(origin, target - origin);
+
+                assert!(fan.iter().any(|t| t.watertight(r).is_some()));
+            }
+        }
+    }
+
+    #[test]
+    fn a_plain_triangle_has_no_surface_color() {
+        let t = book_triangle();
+        let r = Ray::default()
+            .origin(0.0, 0.5, -2.0)
+            .direction(0.0, 0.0, 1.0);
+
+        let xs = t.local_intersect(r);
+
+        assert_eq!(t.surface_color(&xs[0]), None);
+    }
+
+    #[test]
+    fn vertex_colors_are_blended_across_the_triangle() {
+        let t = book_triangle().with_vertex_colors([
+            Color::new(1.0, 0.0, 0.0),
+            Color::new(0.0, 1.0, 0.0),
+            Color::new(0.0, 0.0, 1.0),
+        ]);
+        let r = Ray::default()
+            .origin(-0.2, 0.3, -2.0)
+            .direction(0.0, 0.0, 1.0);
+
+        let xs = t.local_intersect(r);
+
+        assert_eq!(t.surface_color(&xs[0]), Some(Color::new(0.3, 0.45, 0.25)));
+    }
+
+    fn smooth_triangle() -> Triangle {
+        book_triangle().with_vertex_normals([
+            Vector::new(0.0, 1.0, 0.0),
+            Vector::new(-1.0, 0.0, 0.0),
+            Vector::new(1.0, 0.0, 0.0),
+        ])
+    }
+
+    #[test]
+    fn a_smooth_triangle_uses_u_and_v_to_interpolate_the_normal() {
+        let t = smooth_triangle();
+        let i = Intersection::new(1.0, &t).uv(0.45, 0.25);
+
+        let n = t.normal_at(Point::new(0.0, 0.0, 0.0), Some(&i));
+
+        assert_eq!(n, Vector::new(-0.5547, 0.83205, 0.0));
+    }
+
+    #[test]
+    fn preparing_the_normal_on_a_smooth_triangle() {
+        let t = smooth_triangle();
+        let r = Ray::default()
+            .origin(-0.2, 0.3, -2.0)
+            .direction(0.0, 0.0, 1.0);
+        let xs = t.intersect(r);
+
+        let comps = xs[0].prepare_computations(r, &xs);
+
+        assert_eq!(comps.normalv, Vector::new(-0.5547, 0.83205, 0.0));
+    }
+
+    #[test]
+    fn a_smooth_triangle_without_a_hit_uses_its_face_normal() {
+        let t = smooth_triangle();
+
+        assert_eq!(t.local_normal_at(Point::new(0.0, 0.5, 0.0), None), t.normal);
+    }
+
+    #[test]
+    fn surface_samples_lie_inside_the_triangle() {
+        let t = book_triangle();
+
+        for &(u, v) in &[(0.0, 0.0), (1.0, 0.0), (1.0, 1.0), (0.5, 0.5), (0.3, 0.9)] {
+            let sample = t.sample_surface(u, v).unwrap();
+            let r = Ray::new(
+                sample.point + Vector::new(0.0, 0.0, -1.0),
+                Vector::new(0.0, 0.0, 1.0),
+            );
+
+            assert_eq!(t.local_intersect(r).len(), 1);
+            assert!(float_eq(sample.area, 1.0));
+        }
+    }
+
+    #[test]
+    fn a_triangle_has_a_bounding_box() {
+        let t = Triangle::new(
+            Point::new(-3.0, 7.0, 2.0),
+            Point::new(6.0, 2.0, -4.0),
+            Point::new(2.0, -1.0, -1.0),
+        );
+
+        let b = t.bounds();
+
+        assert_eq!(b.min, Point::new(-3.0, -1.0, -4.0));
+        assert_eq!(b.max, Point::new(6.0, 7.0, 2.0));
+    }
+
+    #[test]
+    fn tessellating_a_triangle_gives_it_back() {
+        let t = book_triangle();
+
+        let mesh = t.tessellate(16).unwrap();
+
+        assert_eq!(mesh.vertices, vec![t.p1, t.p2, t.p3]);
+        assert_eq!(mesh.faces, vec![[0, 1, 2]]);
+    }
+}
@@ -0,0 +1,389 @@
+use std::fmt::Debug;
+
+use crate::{
+    color::Color,
+    lights::{LightGroups, PointLight},
+    materials::{Channel, Material, Shading},
+    shapes::Shape,
+    spectrum::SpectralCurve,
+    transformations::Transform,
+    tuple::Point,
+    world::World,
+};
+
+/// A plain description of a world: what each object is, where it sits and
+/// what it's made of, with ids and caches left out. Two worlds built the
+/// same way give equal snapshots, so tests can compare whole scenes and
+/// `diff` says exactly where they part.
+#[derive(Clone, Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct WorldSnapshot {
+    pub light: LightSnapshot,
+    pub objects: Vec<ObjectSnapshot>,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct LightSnapshot {
+    pub position: Point,
+    pub intensity: Color,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct ObjectSnapshot {
+    pub kind: String,
+    /// The shape's own settings, such as a cylinder's extent, each written
+    /// out as its debug description.
+    pub parameters: Vec<(String, String)>,
+    pub transform: Transform,
+    pub material: MaterialSnapshot,
+    pub children: Vec<ObjectSnapshot>,
+}
+
+/// The material's settings, with any patterns written out as their debug
+/// descriptions.
+#[derive(Clone, Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct MaterialSnapshot {
+    pub color: Color,
+    pub shading: Shading,
+    pub ambient: f32,
+    pub diffuse: f32,
+    pub reflective: f32,
+    pub specular: f32,
+    pub shininess: f32,
+    pub transparency: f32,
+    pub refractive_index: f32,
+    pub abbe_number: Option<f32>,
+    pub flip_normal: bool,
+    pub two_sided: bool,
+    pub absorption: Color,
+    pub spectrum: Option<SpectralCurve>,
+    pub translucency: f32,
+    pub scatter_distance: f32,
+    pub pattern: Option<String>,
+    pub emissive: Color,
+    pub light_groups: LightGroups,
+    pub channel_patterns: Vec<(Channel, String)>,
+}
+
+impl WorldSnapshot {
+    pub fn from_world(world: &World) -> Self {
+        Self {
+            light: LightSnapshot::from_light(&world.light()),
+            objects: world
+                .objects()
+                .iter()
+                .map(|o| ObjectSnapshot::from_shape(o.as_ref()))
+                .collect(),
+        }
+    }
+
+    /// Every place `other` differs from this snapshot, one line each, as
+    /// the path to the field followed by this value and then the other's.
+    /// Empty when the two match.
+    pub fn diff(&self, other: &WorldSnapshot) -> Vec<String> {
+        let mut differences = Differences::default();
+        differences.light(&self.light, &other.light);
+        differences.objects("objects", &self.objects, &other.objects);
+        differences.lines
+    }
+}
+
+impl LightSnapshot {
+    pub fn from_light(light: &PointLight) -> Self {
+        Self {
+            position: light.position,
+            intensity: light.intensity,
+        }
+    }
+}
+
+impl ObjectSnapshot {
+    pub fn from_shape(shape: &dyn Shape) -> Self {
+        Self {
+            kind: shape.type_name().to_string(),
+            parameters: shape
+                .parameters()
+                .into_iter()
+                .map(|(name, value)| (name.to_string(), value))
+                .collect(),
+            transform: *shape.transform(),
+            material: MaterialSnapshot::from_material(shape.material()),
+            children: shape
+                .children()
+                .iter()
+                .map(|c| ObjectSnapshot::from_shape(c.as_ref()))
+                .collect(),
+        }
+    }
+}
+
+impl MaterialSnapshot {
+    pub fn from_material(material: &Material) -> Self {
+        Self {
+            color: material.color,
+            shading: material.shading,
+            ambient: material.ambient,
+            diffuse: material.diffuse,
+            reflective: material.reflective,
+            specular: material.specular,
+            shininess: material.shininess,
+            transparency: material.transparency,
+            refractive_index: material.refractive_index,
+            abbe_number: material.abbe_number,
+            flip_normal: material.flip_normal,
+            two_sided: material.two_sided,
+            absorption: material.absorption,
+            spectrum: material.spectrum.clone(),
+            translucency: material.translucency,
+            scatter_distance: material.scatter_distance,
+            pattern: material.pattern.as_ref().map(|p| format!("{:?}", p)),
+            emissive: material.emissive,
+            light_groups: material.light_groups,
+            channel_patterns: material
+                .channel_patterns
+                .iter()
+                .map(|(channel, p)| (*channel, format!("{:?}", p)))
+                .collect(),
+        }
+    }
+}
+
+#[derive(Default)]
+struct Differences {
+    lines: Vec<String>,
+}
+
+/// Compares exactly, as `==` on the snapshots does, so two snapshots are
+/// equal just when their diff is empty.
+impl Differences {
+    fn field<T: PartialEq + Debug>(&mut self, path: &str, ours: &T, theirs: &T) {
+        if ours != theirs {
+            self.lines
+                .push(format!("{}: {:?} != {:?}", path, ours, theirs));
+        }
+    }
+
+    fn light(&mut self, ours: &LightSnapshot, theirs: &LightSnapshot) {
+        self.field("light.position", &ours.position, &theirs.position);
+        self.field("light.intensity", &ours.intensity, &theirs.intensity);
+    }
+
+    fn objects(&mut self, path: &str, ours: &[ObjectSnapshot], theirs: &[ObjectSnapshot]) {
+        if ours.len() != theirs.len() {
+            self.lines.push(format!(
+                "{}: {} objects != {} objects",
+                path,
+                ours.len(),
+                theirs.len()
+            ));
+        }
+        for (i, (a, b)) in ours.iter().zip(theirs).enumerate() {
+            self.object(&format!("{}[{}]", path, i), a, b);
+        }
+    }
+
+    fn object(&mut self, path: &str, ours: &ObjectSnapshot, theirs: &ObjectSnapshot) {
+        self.field(&format!("{}.kind", path), &ours.kind, &theirs.kind);
+        self.parameters(
+            &format!("{}.parameters", path),
+            &ours.parameters,
+            &theirs.parameters,
+        );
+        self.field(
+            &format!("{}.transform", path),
+            &ours.transform,
+            &theirs.transform,
+        );
+        self.material(
+            &format!("{}.material", path),
+            &ours.material,
+            &theirs.material,
+        );
+        self.objects(
+            &format!("{}.children", path),
+            &ours.children,
+            &theirs.children,
+        );
+    }
+
+    fn parameters(&mut self, path: &str, ours: &[(String, String)], theirs: &[(String, String)]) {
+        let names = |parameters: &[(String, String)]| {
+            parameters
+                .iter()
+                .map(|(name, _)| name.clone())
+                .collect::<Vec<_>>()
+        };
+        if names(ours) != names(theirs) {
+            self.field(path, &names(ours), &names(theirs));
+            return;
+        }
+        for ((name, a), (_, b)) in ours.iter().zip(theirs) {
+            if a != b {
+                self.lines
+                    .push(format!("{}.{}: {} != {}", path, name, a, b));
+            }
+        }
+    }
+
+    fn material(&mut self, path: &str, ours: &MaterialSnapshot, theirs: &MaterialSnapshot) {
+        let at = |field: &str| format!("{}.{}", path, field);
+        self.field(&at("color"), &ours.color, &theirs.color);
+        self.field(&at("shading"), &ours.shading, &theirs.shading);
+        self.field(&at("ambient"), &ours.ambient, &theirs.ambient);
+        self.field(&at("diffuse"), &ours.diffuse, &theirs.diffuse);
+        self.field(&at("reflective"), &ours.reflective, &theirs.reflective);
+        self.field(&at("specular"), &ours.specular, &theirs.specular);
+        self.field(&at("shininess"), &ours.shininess, &theirs.shininess);
+        self.field(
+            &at("transparency"),
+            &ours.transparency,
+            &theirs.transparency,
+        );
+        self.field(
+            &at("refractive_index"),
+            &ours.refractive_index,
+            &theirs.refractive_index,
+        );
+        self.field(&at("abbe_number"), &ours.abbe_number, &theirs.abbe_number);
+        self.field(&at("flip_normal"), &ours.flip_normal, &theirs.flip_normal);
+        self.field(&at("two_sided"), &ours.two_sided, &theirs.two_sided);
+        self.field(&at("absorption"), &ours.absorption, &theirs.absorption);
+        self.field(&at("spectrum"), &ours.spectrum, &theirs.spectrum);
+        self.field(
+            &at("translucency"),
+            &ours.translucency,
+            &theirs.translucency,
+        );
+        self.field(
+            &at("scatter_distance"),
+            &ours.scatter_distance,
+            &theirs.scatter_distance,
+        );
+        self.field(&at("pattern"), &ours.pattern, &theirs.pattern);
+        self.field(&at("emissive"), &ours.emissive, &theirs.emissive);
+        self.field(
+            &at("light_groups"),
+            &ours.light_groups,
+            &theirs.light_groups,
+        );
+        self.field(
+            &at("channel_patterns"),
+            &ours.channel_patterns,
+            &theirs.channel_patterns,
+        );
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{
+        color, float_eq,
+        shapes::{cylinder::Cylinder, group::Group, sphere::Sphere, ShapeBuilder},
+    };
+
+    use super::*;
+
+    #[test]
+    fn snapshotting_the_default_world() {
+        let snapshot = World::default().snapshot();
+
+        assert_eq!(snapshot.light.position, Point::new(-10.0, 10.0, -10.0));
+        assert_eq!(snapshot.light.intensity, color::WHITE);
+        assert_eq!(snapshot.objects.len(), 2);
+        assert_eq!(snapshot.objects[0].kind, "Sphere");
+        assert!(float_eq(snapshot.objects[0].material.diffuse, 0.7));
+        assert_eq!(
+            snapshot.objects[1].transform,
+            Transform::scaling(0.5, 0.5, 0.5)
+        );
+    }
+
+    #[test]
+    fn worlds_built_the_same_way_have_equal_snapshots() {
+        let a = World::default().snapshot();
+        let b = World::default().snapshot();
+
+        assert_eq!(a, b);
+        assert!(a.diff(&b).is_empty());
+    }
+
+    #[test]
+    fn a_snapshot_describes_the_children_of_groups() {
+        let mut g = Group::new().with_transform(Transform::translation(0.0, 1.0, 0.0));
+        g.add_child(Box::new(Sphere::default()));
+        let w = World::new(PointLight::default()).object(Box::new(g));
+
+        let snapshot = w.snapshot();
+
+        assert_eq!(snapshot.objects[0].kind, "Group");
+        assert_eq!(snapshot.objects[0].children.len(), 1);
+        assert_eq!(snapshot.objects[0].children[0].kind, "Sphere");
+    }
+
+    #[test]
+    fn diffing_names_each_field_that_changed() {
+        let a = World::default().snapshot();
+        let mut b = a.clone();
+        b.light.intensity = Color::new(0.5, 0.5, 0.5);
+        b.objects[0].material.diffuse = 0.9;
+
+        assert_eq!(
+            a.diff(&b),
+            vec![
+                format!(
+                    "light.intensity: {:?} != {:?}",
+                    color::WHITE,
+                    Color::new(0.5, 0.5, 0.5)
+                ),
+                "objects[0].material.diffuse: 0.7 != 0.9".to_string(),
+            ]
+        );
+    }
+
+    #[test]
+    fn diffing_names_each_shape_parameter_that_changed() {
+        let a = World::new(PointLight::default())
+            .object(Box::new(Cylinder::default().truncated(0.0, 1.0)))
+            .snapshot();
+        let b = World::new(PointLight::default())
+            .object(Box::new(Cylinder::default().truncated(0.0, 2.0)))
+            .snapshot();
+
+        assert_eq!(
+            a.diff(&b),
+            vec!["objects[0].parameters.maximum: 1.0 != 2.0"]
+        );
+    }
+
+    #[test]
+    fn diffing_compares_every_material_field_exactly() {
+        let a = World::default().snapshot();
+        let mut b = a.clone();
+        b.objects[0].material.ambient += 1e-6;
+        b.objects[1].material.abbe_number = Some(20.0);
+
+        assert_ne!(a, b);
+        assert_eq!(
+            a.diff(&b),
+            vec![
+                format!(
+                    "objects[0].material.ambient: {:?} != {:?}",
+                    a.objects[0].material.ambient, b.objects[0].material.ambient
+                ),
+                "objects[1].material.abbe_number: None != Some(20.0)".to_string(),
+            ]
+        );
+    }
+
+    #[test]
+    fn diffing_reports_missing_objects() {
+        let a = World::default().snapshot();
+        let mut b = a.clone();
+        b.objects.pop();
+
+        assert_eq!(a.diff(&b), vec!["objects: 2 objects != 1 objects"]);
+    }
+}
@@ -0,0 +1,343 @@
+//! Light as a handful of wavelength samples instead of red, green and blue,
+//! so that surfaces and lights can be multiplied wavelength by wavelength
+//! and glass can bend each wavelength by its own amount.
+
+use std::ops::{Add, Mul, Sub};
+
+use crate::{
+    color::{self, Color},
+    lights::PointLight,
+    materials::Material,
+    shapes::Shape,
+    tuple::{Point, Vector},
+};
+
+/// The visible range the samples of a `Spectrum` are spread over, in
+/// nanometres.
+pub const MIN_WAVELENGTH: f32 = 380.0;
+pub const MAX_WAVELENGTH: f32 = 720.0;
+
+/// Where the smooth bumps that red, green and blue turn into peak, and how
+/// wide they are, in nanometres.
+const PEAKS: [f32; 3] = [610.0, 545.0, 455.0];
+const WIDTH: f32 = 40.0;
+
+/// What the shading path carries along a ray: plain RGB, or a spectrum.
+pub trait Radiance:
+    Copy
+    + Default
+    + Add<Output = Self>
+    + Sub<Output = Self>
+    + Mul<Output = Self>
+    + Mul<f32, Output = Self>
+{
+    /// How many wavelengths are carried.
+    const SAMPLES: usize;
+
+    /// The wavelength sample `i` stands for, in nanometres.
+    fn wavelength(i: usize) -> f32;
+
+    fn from_rgb(color: Color) -> Self;
+
+    fn to_rgb(self) -> Color;
+
+    /// `curve` taken at each sample's wavelength.
+    fn from_curve(curve: &SpectralCurve) -> Self;
+
+    /// Just sample `i`, with every other one dark.
+    fn only(self, i: usize) -> Self;
+}
+
+/// Red, green and blue stand for the C, d and F lines, the wavelengths an
+/// Abbe number is measured at, so glass bends them as `Material` says.
+impl Radiance for Color {
+    const SAMPLES: usize = 3;
+
+    fn wavelength(i: usize) -> f32 {
+        [656.3, 587.6, 486.1][i]
+    }
+
+    fn from_rgb(color: Color) -> Self {
+        color
+    }
+
+    fn to_rgb(self) -> Color {
+        self
+    }
+
+    fn from_curve(curve: &SpectralCurve) -> Self {
+        Color::new(
+            curve.at(Self::wavelength(0)),
+            curve.at(Self::wavelength(1)),
+            curve.at(Self::wavelength(2)),
+        )
+    }
+
+    fn only(self, i: usize) -> Self {
+        let mut channels: [f32; 3] = self.into();
+        for (j, channel) in channels.iter_mut().enumerate() {
+            if j != i {
+                *channel = 0.0;
+            }
+        }
+        channels.into()
+    }
+}
+
+/// `N` samples at the middles of equal slices of the visible range.
+/// Colors become spectra as a blend of three smooth bumps that add up to
+/// one everywhere, so white is flat, and spectra become colors again by
+/// the closest fit of those bumps, so a color survives the round trip.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Spectrum<const N: usize>(pub [f32; N]);
+
+impl<const N: usize> Spectrum<N> {
+    pub fn flat(value: f32) -> Self {
+        Self([value; N])
+    }
+}
+
+impl<const N: usize> Default for Spectrum<N> {
+    fn default() -> Self {
+        Self::flat(0.0)
+    }
+}
+
+impl<const N: usize> Add for Spectrum<N> {
+    type Output = Self;
+
+    fn add(mut self, rhs: Self) -> Self::Output {
+        for (a, b) in self.0.iter_mut().zip(rhs.0.iter()) {
+            *a += b;
+        }
+        self
+    }
+}
+
+impl<const N: usize> Sub for Spectrum<N> {
+    type Output = Self;
+
+    fn sub(mut self, rhs: Self) -> Self::Output {
+        for (a, b) in self.0.iter_mut().zip(rhs.0.iter()) {
+            *a -= b;
+        }
+        self
+    }
+}
+
+impl<const N: usize> Mul for Spectrum<N> {
+    type Output = Self;
+
+    fn mul(mut self, rhs: Self) -> Self::Output {
+        for (a, b) in self.0.iter_mut().zip(rhs.0.iter()) {
+            *a *= b;
+        }
+        self
+    }
+}
+
+impl<const N: usize> Mul<f32> for Spectrum<N> {
+    type Output = Self;
+
+    fn mul(mut self, rhs: f32) -> Self::Output {
+        for a in self.0.iter_mut() {
+            *a *= rhs;
+        }
+        self
+    }
+}
+
+impl<const N: usize> Radiance for Spectrum<N> {
+    const SAMPLES: usize = N;
+
+    fn wavelength(i: usize) -> f32 {
+        MIN_WAVELENGTH + (i as f32 + 0.5) * (MAX_WAVELENGTH - MIN_WAVELENGTH) / N as f32
+    }
+
+    fn from_rgb(color: Color) -> Self {
+        let mut samples = [0.0; N];
+        for (i, sample) in samples.iter_mut().enumerate() {
+            *sample = basis(Self::wavelength(i)).dot(rgb_vector(color));
+        }
+        Self(samples)
+    }
+
+    fn to_rgb(self) -> Color {
+        // Least squares: solve (B^T B) c = B^T s for the color c, where
+        // B's rows are the bumps at each sample.
+        let (mut rows, mut projected) =
+            ([Vector::new(0.0, 0.0, 0.0); 3], Vector::new(0.0, 0.0, 0.0));
+        for (i, &sample) in self.0.iter().enumerate() {
+            let b = basis(Self::wavelength(i));
+            rows[0] = rows[0] + b * b.x();
+            rows[1] = rows[1] + b * b.y();
+            rows[2] = rows[2] + b * b.z();
+            projected = projected + b * sample;
+        }
+        let determinant = rows[0].dot(rows[1].cross(rows[2]));
+        if determinant.abs() < f32::EPSILON {
+            return color::BLACK;
+        }
+        let c = (rows[1].cross(rows[2]) * projected.x()
+            + rows[2].cross(rows[0]) * projected.y()
+            + rows[0].cross(rows[1]) * projected.z())
+            * (1.0 / determinant);
+        Color::new(c.x(), c.y(), c.z())
+    }
+
+    fn from_curve(curve: &SpectralCurve) -> Self {
+        let mut samples = [0.0; N];
+        for (i, sample) in samples.iter_mut().enumerate() {
+            *sample = curve.at(Self::wavelength(i));
+        }
+        Self(samples)
+    }
+
+    fn only(self, i: usize) -> Self {
+        let mut only = Self::default();
+        only.0[i] = self.0[i];
+        only
+    }
+}
+
+/// Which spectral curves shading takes in place of the RGB colors they
+/// stand for. The default takes none, as RGB renders do.
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct Spectra<'a> {
+    /// Whether materials with a `spectrum` reflect light by it.
+    pub materials: bool,
+    /// The point light's emission by wavelength, in place of its intensity.
+    pub light: Option<&'a SpectralCurve>,
+}
+
+impl Spectra<'_> {
+    /// How much of the light `material` reflects at `point` on `object`.
+    pub fn surface<R: Radiance>(&self, material: &Material, object: &dyn Shape, point: Point) -> R {
+        match &material.spectrum {
+            Some(curve) if self.materials => R::from_curve(curve),
+            _ => R::from_rgb(material.color_at(object, point)),
+        }
+    }
+
+    /// The light's intensity.
+    pub fn light<R: Radiance>(&self, light: &PointLight) -> R {
+        match self.light {
+            Some(curve) => R::from_curve(curve),
+            None => R::from_rgb(light.intensity),
+        }
+    }
+}
+
+/// How much red, green and blue each contribute at `wavelength`.
+fn basis(wavelength: f32) -> Vector {
+    let [r, g, b] = PEAKS.map(|peak| (-((wavelength - peak) / WIDTH).powi(2) / 2.0).exp());
+    let sum = r + g + b;
+    Vector::new(r / sum, g / sum, b / sum)
+}
+
+fn rgb_vector(color: Color) -> Vector {
+    Vector::new(color.red(), color.green(), color.blue())
+}
+
+/// A spectrum given by its values at a few wavelengths in nanometres,
+/// joined by straight lines and flat beyond the first and last, for
+/// materials and lights no RGB color describes.
+#[derive(Clone, Debug, Default, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct SpectralCurve {
+    points: Vec<(f32, f32)>,
+}
+
+impl SpectralCurve {
+    pub fn new(mut points: Vec<(f32, f32)>) -> Self {
+        points.sort_by(|a, b| a.0.partial_cmp(&b.0).unwrap());
+        Self { points }
+    }
+
+    pub fn at(&self, wavelength: f32) -> f32 {
+        let after = self.points.iter().position(|&(w, _)| w >= wavelength);
+        match after {
+            None => self.points.last().map_or(0.0, |&(_, v)| v),
+            Some(0) => self.points[0].1,
+            Some(i) => {
+                let ((w0, v0), (w1, v1)) = (self.points[i - 1], self.points[i]);
+                v0 + (v1 - v0) * (wavelength - w0) / (w1 - w0)
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    type Sixteen = Spectrum<16>;
+
+    #[test]
+    fn samples_spread_evenly_over_the_visible_range() {
+        assert_eq!(Spectrum::<4>::wavelength(0), 422.5);
+        assert_eq!(Spectrum::<4>::wavelength(3), 677.5);
+    }
+
+    #[test]
+    fn white_is_a_flat_spectrum() {
+        let white = Sixteen::from_rgb(color::WHITE);
+
+        for &sample in white.0.iter() {
+            assert!((sample - 1.0).abs() < 1e-5);
+        }
+    }
+
+    #[test]
+    fn colors_survive_the_round_trip() {
+        let colors = [
+            Color::new(1.0, 0.0, 0.0),
+            Color::new(0.2, 0.7, 0.4),
+            Color::new(0.0, 0.0, 0.9),
+            color::WHITE,
+        ];
+
+        for &c in colors.iter() {
+            assert_eq!(Sixteen::from_rgb(c).to_rgb(), c);
+            assert_eq!(Spectrum::<3>::from_rgb(c).to_rgb(), c);
+        }
+    }
+
+    #[test]
+    fn red_is_long_wavelengths_and_blue_short_ones() {
+        let red = Sixteen::from_rgb(Color::new(1.0, 0.0, 0.0));
+        let blue = Sixteen::from_rgb(Color::new(0.0, 0.0, 1.0));
+
+        assert!(red.0[15] > 0.9 && red.0[0] < 0.1);
+        assert!(blue.0[0] > 0.9 && blue.0[15] < 0.1);
+    }
+
+    #[test]
+    fn spectra_multiply_wavelength_by_wavelength() {
+        let red = Sixteen::from_rgb(Color::new(1.0, 0.0, 0.0));
+        let blue = Sixteen::from_rgb(Color::new(0.0, 0.0, 1.0));
+
+        let product = (red * blue).to_rgb();
+
+        assert!(product.red() < 0.05 && product.blue() < 0.05);
+    }
+
+    #[test]
+    fn only_keeps_a_single_sample() {
+        let s = Sixteen::flat(0.5).only(3);
+
+        assert_eq!(s.0.iter().sum::<f32>(), 0.5);
+        assert_eq!(s.0[3], 0.5);
+        assert_eq!(Color::new(0.1, 0.2, 0.3).only(1), Color::new(0.0, 0.2, 0.0));
+    }
+
+    #[test]
+    fn a_curve_is_joined_by_straight_lines() {
+        let curve = SpectralCurve::new(vec![(600.0, 1.0), (400.0, 0.0)]);
+
+        assert_eq!(curve.at(300.0), 0.0);
+        assert_eq!(curve.at(450.0), 0.25);
+        assert_eq!(curve.at(700.0), 1.0);
+        assert_eq!(SpectralCurve::default().at(500.0), 0.0);
+    }
+}
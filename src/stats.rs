@@ -0,0 +1,201 @@
+use std::{
+    cell::RefCell,
+    collections::BTreeMap,
+    fmt::{self, Display},
+    time::{Duration, Instant},
+};
+
+use crate::ray::RayKind;
+
+/// Counts gathered while rendering inside `collect`. Outside of it the
+/// recording functions do nothing, so uninstrumented renders pay only for a
+/// thread-local lookup.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct RenderStats {
+    pub primary_rays: u64,
+    pub shadow_rays: u64,
+    pub reflection_rays: u64,
+    pub refraction_rays: u64,
+    pub diffuse_rays: u64,
+    pub intersection_tests: BTreeMap<&'static str, u64>,
+    /// Spatial index nodes rays stepped through, and the objects the index
+    /// then handed on to be tested, whichever kind of index it was.
+    pub index_nodes_visited: u64,
+    pub index_candidates: u64,
+    pub phases: Vec<(&'static str, Duration)>,
+}
+
+impl RenderStats {
+    pub fn total_rays(&self) -> u64 {
+        self.primary_rays
+            + self.shadow_rays
+            + self.reflection_rays
+            + self.refraction_rays
+            + self.diffuse_rays
+    }
+
+    pub fn total_intersection_tests(&self) -> u64 {
+        self.intersection_tests.values().sum()
+    }
+
+    pub fn phase(&self, name: &str) -> Option<Duration> {
+        self.phases
+            .iter()
+            .filter(|(phase, _)| *phase == name)
+            .map(|(_, duration)| *duration)
+            .reduce(|a, b| a + b)
+    }
+}
+
+impl Display for RenderStats {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(f, "rays: {}", self.total_rays())?;
+        writeln!(f, "  primary:    {}", self.primary_rays)?;
+        writeln!(f, "  shadow:     {}", self.shadow_rays)?;
+        writeln!(f, "  reflection: {}", self.reflection_rays)?;
+        writeln!(f, "  refraction: {}", self.refraction_rays)?;
+        writeln!(f, "  diffuse:    {}", self.diffuse_rays)?;
+        writeln!(f, "intersection tests: {}", self.total_intersection_tests())?;
+        for (shape, count) in &self.intersection_tests {
+            writeln!(f, "  {}: {}", shape, count)?;
+        }
+        writeln!(
+            f,
+            "index: {} nodes visited, {} candidates",
+            self.index_nodes_visited, self.index_candidates
+        )?;
+        writeln!(f, "phases:")?;
+        for (name, duration) in &self.phases {
+            writeln!(f, "  {}: {:.3}s", name, duration.as_secs_f64())?;
+        }
+        Ok(())
+    }
+}
+
+thread_local! {
+    static ACTIVE: RefCell<Option<RenderStats>> = const { RefCell::new(None) };
+}
+
+fn with_active(record: impl FnOnce(&mut RenderStats)) {
+    ACTIVE.with(|active| {
+        if let Some(stats) = active.borrow_mut().as_mut() {
+            record(stats);
+        }
+    });
+}
+
+/// Runs `f`, returning its result along with everything it recorded.
+/// Collections nest: an inner one doesn't leak into the outer one.
+pub fn collect<T>(f: impl FnOnce() -> T) -> (T, RenderStats) {
+    let outer = ACTIVE.with(|active| active.replace(Some(RenderStats::default())));
+    let result = f();
+    let stats = ACTIVE.with(|active| active.replace(outer));
+    (result, stats.unwrap_or_default())
+}
+
+pub fn record_ray(kind: RayKind) {
+    with_active(|stats| match kind {
+        RayKind::Primary => stats.primary_rays += 1,
+        RayKind::Shadow => stats.shadow_rays += 1,
+        RayKind::Reflection => stats.reflection_rays += 1,
+        RayKind::Refraction => stats.refraction_rays += 1,
+        RayKind::Diffuse => stats.diffuse_rays += 1,
+    });
+}
+
+pub fn record_intersection_test(shape: &'static str) {
+    with_active(|stats| *stats.intersection_tests.entry(shape).or_insert(0) += 1);
+}
+
+pub fn record_index_traversal(nodes: u64, candidates: u64) {
+    with_active(|stats| {
+        stats.index_nodes_visited += nodes;
+        stats.index_candidates += candidates;
+    });
+}
+
+/// Runs `f` and records its wall-clock time under `name`.
+pub fn time_phase<T>(name: &'static str, f: impl FnOnce() -> T) -> T {
+    let start = Instant::now();
+    let result = f();
+    let elapsed = start.elapsed();
+    with_active(|stats| stats.phases.push((name, elapsed)));
+    result
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn nothing_is_recorded_outside_a_collection() {
+        record_ray(RayKind::Primary);
+
+        let (_, stats) = collect(|| ());
+
+        assert_eq!(stats, RenderStats::default());
+    }
+
+    #[test]
+    fn counting_rays_by_kind() {
+        let (_, stats) = collect(|| {
+            record_ray(RayKind::Primary);
+            record_ray(RayKind::Shadow);
+            record_ray(RayKind::Shadow);
+            record_ray(RayKind::Refraction);
+        });
+
+        assert_eq!(stats.primary_rays, 1);
+        assert_eq!(stats.shadow_rays, 2);
+        assert_eq!(stats.refraction_rays, 1);
+        assert_eq!(stats.total_rays(), 4);
+    }
+
+    #[test]
+    fn counting_intersection_tests_by_shape() {
+        let (_, stats) = collect(|| {
+            record_intersection_test("Sphere");
+            record_intersection_test("Plane");
+            record_intersection_test("Sphere");
+        });
+
+        assert_eq!(stats.intersection_tests["Sphere"], 2);
+        assert_eq!(stats.intersection_tests["Plane"], 1);
+        assert_eq!(stats.total_intersection_tests(), 3);
+    }
+
+    #[test]
+    fn nested_collections_are_kept_apart() {
+        let (inner, outer) = collect(|| {
+            record_ray(RayKind::Primary);
+            let (_, inner) = collect(|| record_ray(RayKind::Shadow));
+            record_ray(RayKind::Primary);
+            inner
+        });
+
+        assert_eq!(inner.shadow_rays, 1);
+        assert_eq!(inner.primary_rays, 0);
+        assert_eq!(outer.primary_rays, 2);
+        assert_eq!(outer.shadow_rays, 0);
+    }
+
+    #[test]
+    fn counting_index_traversals() {
+        let (_, stats) = collect(|| {
+            record_index_traversal(5, 2);
+            record_index_traversal(3, 0);
+        });
+
+        assert_eq!(stats.index_nodes_visited, 8);
+        assert_eq!(stats.index_candidates, 2);
+    }
+
+    #[test]
+    fn timing_a_phase() {
+        let (result, stats) = collect(|| time_phase("work", || 42));
+
+        assert_eq!(result, 42);
+        assert!(stats.phase("work").is_some());
+        assert!(stats.phase("other").is_none());
+    }
+}
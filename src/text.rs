@@ -0,0 +1,324 @@
+use crate::{
+    materials::Material,
+    shapes::{group::Group, mesh::Mesh, ShapeBuilder},
+    transformations::{Transform, IDENTITY},
+    tuple::{Point, Vector},
+};
+
+/// Glyph rows from top to bottom, `#` for a filled cell.
+pub(crate) type Glyph = [&'static str; GLYPH_ROWS];
+
+pub(crate) const GLYPH_ROWS: usize = 7;
+pub(crate) const GLYPH_COLUMNS: usize = 5;
+
+/// A line of text built from blocky extruded letters, for titles and
+/// labels. It reads along +x on the xy plane, standing on y = 0 with its
+/// face toward -z, so a default camera sees it the right way round.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Text {
+    pub text: String,
+    /// The height of a capital letter.
+    pub height: f32,
+    /// How far the letters are extruded back along +z.
+    pub depth: f32,
+    pub material: Material,
+}
+
+impl Text {
+    pub fn new(text: &str) -> Self {
+        Self {
+            text: text.to_string(),
+            height: 1.0,
+            depth: 0.2,
+            material: Material::default(),
+        }
+    }
+
+    pub fn height(self, height: f32) -> Self {
+        Self { height, ..self }
+    }
+
+    pub fn depth(self, depth: f32) -> Self {
+        Self { depth, ..self }
+    }
+
+    pub fn material(self, material: Material) -> Self {
+        Self { material, ..self }
+    }
+
+    /// The size of one cell of the glyph grid.
+    fn cell(&self) -> f32 {
+        self.height / GLYPH_ROWS as f32
+    }
+
+    /// How far each character moves the next one along, a cell of space
+    /// included.
+    pub fn advance(&self) -> f32 {
+        (GLYPH_COLUMNS + 1) as f32 * self.cell()
+    }
+
+    pub fn line_height(&self) -> f32 {
+        (GLYPH_ROWS + 2) as f32 * self.cell()
+    }
+
+    pub fn to_group(&self) -> Group {
+        self.to_group_with(|_, _| IDENTITY)
+    }
+
+    /// A group holding a group of triangles for each character, with
+    /// `transform` given the character's index and value to pose it before
+    /// it's moved into place, e.g. to tilt or bounce single letters. The
+    /// transform works about the character's bottom left corner. Newlines
+    /// start a new line below; other whitespace only takes up room. Letters
+    /// inherit the text's material.
+    pub fn to_group_with(&self, transform: impl Fn(usize, char) -> Transform) -> Group {
+        let mut group = Group::new().with_material(self.material.clone());
+        let (mut column, mut line) = (0, 0);
+        for (i, c) in self.text.chars().enumerate() {
+            if c == '\n' {
+                column = 0;
+                line += 1;
+                continue;
+            }
+            if !c.is_whitespace() {
+                let position = Transform::translation(
+                    column as f32 * self.advance(),
+                    -(line as f32) * self.line_height(),
+                    0.0,
+                );
+                let letter = self
+                    .glyph_mesh(glyph(c))
+                    .to_group()
+                    .with_transform(position * transform(i, c));
+                group.add_child(Box::new(letter));
+            }
+            column += 1;
+        }
+        group
+    }
+
+    /// Each filled cell becomes a block, with walls only where it borders
+    /// an empty one.
+    fn glyph_mesh(&self, glyph: Glyph) -> Mesh {
+        let s = self.cell();
+        let d = self.depth;
+        let filled = |col: isize, row: isize| {
+            (0..GLYPH_COLUMNS as isize).contains(&col)
+                && (0..GLYPH_ROWS as isize).contains(&row)
+                && glyph[row as usize].as_bytes()[col as usize] == b'#'
+        };
+
+        let mut mesh = Mesh::default();
+        for row in 0..GLYPH_ROWS as isize {
+            for col in 0..GLYPH_COLUMNS as isize {
+                if !filled(col, row) {
+                    continue;
+                }
+                let x = col as f32 * s;
+                let y = (GLYPH_ROWS as isize - 1 - row) as f32 * s;
+                let across = Vector::new(s, 0.0, 0.0);
+                let up = Vector::new(0.0, s, 0.0);
+                let back = Vector::new(0.0, 0.0, d);
+
+                add_quad(&mut mesh, Point::new(x, y, 0.0), across, up);
+                add_quad(&mut mesh, Point::new(x, y, d), up, across);
+                if !filled(col - 1, row) {
+                    add_quad(&mut mesh, Point::new(x, y, 0.0), up, back);
+                }
+                if !filled(col + 1, row) {
+                    add_quad(&mut mesh, Point::new(x + s, y, 0.0), back, up);
+                }
+                if !filled(col, row + 1) {
+                    add_quad(&mut mesh, Point::new(x, y, 0.0), back, across);
+                }
+                if !filled(col, row - 1) {
+                    add_quad(&mut mesh, Point::new(x, y + s, 0.0), across, back);
+                }
+            }
+        }
+        mesh
+    }
+}
+
+/// Two triangles spanning `u` and `v` from `corner`, facing along `v x u`
+/// to match the winding of `Triangle`.
+fn add_quad(mesh: &mut Mesh, corner: Point, u: Vector, v: Vector) {
+    let first = mesh.vertices.len();
+    mesh.vertices
+        .extend_from_slice(&[corner, corner + u, corner + u + v, corner + v]);
+    mesh.faces.push([first, first + 1, first + 2]);
+    mesh.faces.push([first, first + 2, first + 3]);
+}
+
+/// The built-in font: capitals, digits and a little punctuation. Lower
+/// case letters are drawn as capitals, and anything else as `?`.
+#[rustfmt::skip]
+pub(crate) fn glyph(c: char) -> Glyph {
+    match c.to_ascii_uppercase() {
+        'A' => [".###.", "#...#", "#...#", "#####", "#...#", "#...#", "#...#"],
+        'B' => ["####.", "#...#", "#...#", "####.", "#...#", "#...#", "####."],
+        'C' => [".###.", "#...#", "#....", "#....", "#....", "#...#", ".###."],
+        'D' => ["####.", "#...#", "#...#", "#...#", "#...#", "#...#", "####."],
+        'E' => ["#####", "#....", "#....", "####.", "#....", "#....", "#####"],
+        'F' => ["#####", "#....", "#....", "####.", "#....", "#....", "#...."],
+        'G' => [".###.", "#...#", "#....", "#.###", "#...#", "#...#", ".####"],
+        'H' => ["#...#", "#...#", "#...#", "#####", "#...#", "#...#", "#...#"],
+        'I' => [".###.", "..#..", "..#..", "..#..", "..#..", "..#..", ".###."],
+        'J' => ["..###", "...#.", "...#.", "...#.", "...#.", "#..#.", ".##.."],
+        'K' => ["#...#", "#..#.", "#.#..", "##...", "#.#..", "#..#.", "#...#"],
+        'L' => ["#....", "#....", "#....", "#....", "#....", "#....", "#####"],
+        'M' => ["#...#", "##.##", "#.#.#", "#.#.#", "#...#", "#...#", "#...#"],
+        'N' => ["#...#", "#...#", "##..#", "#.#.#", "#..##", "#...#", "#...#"],
+        'O' => [".###.", "#...#", "#...#", "#...#", "#...#", "#...#", ".###."],
+        'P' => ["####.", "#...#", "#...#", "####.", "#....", "#....", "#...."],
+        'Q' => [".###.", "#...#", "#...#", "#...#", "#.#.#", "#..#.", ".##.#"],
+        'R' => ["####.", "#...#", "#...#", "####.", "#.#..", "#..#.", "#...#"],
+        'S' => [".####", "#....", "#....", ".###.", "....#", "....#", "####."],
+        'T' => ["#####", "..#..", "..#..", "..#..", "..#..", "..#..", "..#.."],
+        'U' => ["#...#", "#...#", "#...#", "#...#", "#...#", "#...#", ".###."],
+        'V' => ["#...#", "#...#", "#...#", "#...#", "#...#", ".#.#.", "..#.."],
+        'W' => ["#...#", "#...#", "#...#", "#.#.#", "#.#.#", "#.#.#", ".#.#."],
+        'X' => ["#...#", "#...#", ".#.#.", "..#..", ".#.#.", "#...#", "#...#"],
+        'Y' => ["#...#", "#...#", ".#.#.", "..#..", "..#..", "..#..", "..#.."],
+        'Z' => ["#####", "....#", "...#.", "..#..", ".#...", "#....", "#####"],
+        '0' => [".###.", "#...#", "#..##", "#.#.#", "##..#", "#...#", ".###."],
+        '1' => ["..#..", ".##..", "..#..", "..#..", "..#..", "..#..", ".###."],
+        '2' => [".###.", "#...#", "....#", "...#.", "..#..", ".#...", "#####"],
+        '3' => ["#####", "...#.", "..#..", "...#.", "....#", "#...#", ".###."],
+        '4' => ["...#.", "..##.", ".#.#.", "#..#.", "#####", "...#.", "...#."],
+        '5' => ["#####", "#....", "####.", "....#", "....#", "#...#", ".###."],
+        '6' => ["..##.", ".#...", "#....", "####.", "#...#", "#...#", ".###."],
+        '7' => ["#####", "....#", "...#.", "..#..", ".#...", ".#...", ".#..."],
+        '8' => [".###.", "#...#", "#...#", ".###.", "#...#", "#...#", ".###."],
+        '9' => [".###.", "#...#", "#...#", ".####", "....#", "...#.", ".##.."],
+        '.' => [".....", ".....", ".....", ".....", ".....", ".##..", ".##.."],
+        ',' => [".....", ".....", ".....", ".....", ".##..", "..#..", ".#..."],
+        ':' => [".....", ".##..", ".##..", ".....", ".##..", ".##..", "....."],
+        '!' => ["..#..", "..#..", "..#..", "..#..", "..#..", ".....", "..#.."],
+        '-' => [".....", ".....", ".....", "#####", ".....", ".....", "....."],
+        '+' => [".....", "..#..", "..#..", "#####", "..#..", "..#..", "....."],
+        '\'' => ["..#..", "..#..", ".#...", ".....", ".....", ".....", "....."],
+        _ => [".###.", "#...#", "....#", "...#.", "..#..", ".....", "..#.."],
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::f32::consts::PI;
+
+    use crate::{bounds::BoundingBox, float_eq, ray::Ray, shapes::Shape};
+
+    use super::*;
+
+    fn origin_of(shape: &dyn Shape) -> Point {
+        *shape.transform() * Point::new(0.0, 0.0, 0.0)
+    }
+
+    #[test]
+    fn every_glyph_fills_the_grid() {
+        for c in (b' '..=b'~').map(char::from) {
+            for row in &glyph(c) {
+                assert_eq!(row.len(), GLYPH_COLUMNS);
+            }
+        }
+    }
+
+    #[test]
+    fn a_bar_is_extruded_into_a_single_box() {
+        let text = Text::new("-").height(7.0).depth(2.0);
+
+        let mesh = text.glyph_mesh(glyph('-'));
+
+        assert_eq!(mesh.faces.len(), 22 * 2);
+        let bounds = mesh
+            .vertices
+            .iter()
+            .fold(BoundingBox::default(), |b, &p| b.add_point(p));
+        assert_eq!(bounds.min, Point::new(0.0, 3.0, 0.0));
+        assert_eq!(bounds.max, Point::new(5.0, 4.0, 2.0));
+    }
+
+    #[test]
+    fn every_face_of_a_glyph_points_outward() {
+        let text = Text::new("o");
+        let mesh = text.glyph_mesh(glyph('o'));
+
+        assert!(float_eq(
+            mesh.signed_volume(),
+            16.0 * text.cell().powi(2) * text.depth
+        ));
+    }
+
+    #[test]
+    fn characters_are_laid_out_along_x_and_lines_down_y() {
+        let text = Text::new("A B\nC");
+
+        let g = text.to_group();
+
+        assert_eq!(g.children().len(), 3);
+        assert_eq!(
+            origin_of(g.children()[0].as_ref()),
+            Point::new(0.0, 0.0, 0.0)
+        );
+        assert_eq!(
+            origin_of(g.children()[1].as_ref()),
+            Point::new(2.0 * text.advance(), 0.0, 0.0)
+        );
+        assert_eq!(
+            origin_of(g.children()[2].as_ref()),
+            Point::new(0.0, -text.line_height(), 0.0)
+        );
+    }
+
+    #[test]
+    fn lower_case_is_drawn_as_capitals_and_unknown_characters_as_a_question_mark() {
+        assert_eq!(glyph('q'), glyph('Q'));
+        assert_eq!(glyph('@'), glyph('?'));
+    }
+
+    #[test]
+    fn posing_single_characters() {
+        let text = Text::new("HI");
+
+        let g = text.to_group_with(|i, c| {
+            if i == 1 && c == 'I' {
+                Transform::translation(0.0, 1.0, 0.0) * Transform::rotation_z(PI)
+            } else {
+                IDENTITY
+            }
+        });
+
+        assert_eq!(
+            origin_of(g.children()[0].as_ref()),
+            Point::new(0.0, 0.0, 0.0)
+        );
+        assert_eq!(
+            origin_of(g.children()[1].as_ref()),
+            Point::new(text.advance(), 1.0, 0.0)
+        );
+    }
+
+    #[test]
+    fn letters_inherit_the_texts_material() {
+        let g = Text::new("T")
+            .material(Material::default().ambient(1.0))
+            .to_group();
+
+        assert!(float_eq(g.material().ambient, 1.0));
+        assert!(g.children()[0].children()[0].inherits_material());
+    }
+
+    #[test]
+    fn a_ray_hits_the_face_of_a_letter() {
+        let g = Text::new("T").height(7.0).to_group();
+        let r = Ray::new(Point::new(2.5, 6.5, -5.0), Vector::new(0.0, 0.0, 1.0));
+
+        let xs = g.intersect(r);
+
+        assert!(float_eq(xs[0].t, 5.0));
+        assert_eq!(
+            xs[0].object.normal_at(r.position(xs[0].t), Some(&xs[0])),
+            Vector::new(0.0, 0.0, -1.0)
+        );
+    }
+}
@@ -0,0 +1,173 @@
+use std::f32::consts::PI;
+
+use crate::{
+    sampler::{Sampler, Xorshift},
+    shapes::{group::Group, Shape},
+    transformations::Transform,
+};
+
+/// Where `scatter` puts things: a rectangle of the xz plane from `min` to
+/// `max`, with no two placements closer than `spacing`.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct ScatterSettings {
+    pub min: (f32, f32),
+    pub max: (f32, f32),
+    pub count: usize,
+    pub spacing: f32,
+    /// The smallest and largest uniform scale given to each copy.
+    pub scale: (f32, f32),
+    pub seed: u64,
+}
+
+impl Default for ScatterSettings {
+    fn default() -> Self {
+        Self {
+            min: (-5.0, -5.0),
+            max: (5.0, 5.0),
+            count: 20,
+            spacing: 1.0,
+            scale: (1.0, 1.0),
+            seed: 1,
+        }
+    }
+}
+
+impl ScatterSettings {
+    /// Random tries allowed per copy before giving up on a crowded region.
+    const ATTEMPTS: usize = 30;
+}
+
+/// A group of up to `settings.count` shapes from `shape`, dropped at random
+/// over the region by dart throwing, so the result is a Poisson disk
+/// distribution. Each copy is turned about y and scaled at random on top of
+/// its own transform. A region too small to fit them all gets as many as
+/// would go.
+pub fn scatter(settings: ScatterSettings, mut shape: impl FnMut() -> Box<dyn Shape>) -> Group {
+    let rng = Xorshift::new(settings.seed);
+    let mut placed: Vec<(f32, f32)> = vec![];
+    let mut group = Group::new();
+
+    for _ in 0..settings.count * ScatterSettings::ATTEMPTS {
+        if placed.len() == settings.count {
+            break;
+        }
+        let x = rng.next_in(settings.min.0, settings.max.0);
+        let z = rng.next_in(settings.min.1, settings.max.1);
+        if placed
+            .iter()
+            .any(|&(px, pz)| (px - x).hypot(pz - z) < settings.spacing)
+        {
+            continue;
+        }
+        placed.push((x, z));
+
+        let angle = rng.next_in(0.0, 2.0 * PI);
+        let scale = rng.next_in(settings.scale.0, settings.scale.1);
+        let mut copy = shape();
+        copy.set_transform(
+            Transform::translation(x, 0.0, z)
+                * Transform::rotation_y(angle)
+                * Transform::scaling(scale, scale, scale)
+                * *copy.transform(),
+        );
+        group.add_child(copy);
+    }
+    group
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{
+        float_eq,
+        shapes::{sphere::Sphere, ShapeBuilder},
+        tuple::Point,
+    };
+
+    use super::*;
+
+    fn positions(group: &Group) -> Vec<Point> {
+        group
+            .children()
+            .iter()
+            .map(|c| *c.transform() * Point::new(0.0, 0.0, 0.0))
+            .collect()
+    }
+
+    fn unit_sphere() -> Box<dyn Shape> {
+        Box::new(Sphere::default())
+    }
+
+    #[test]
+    fn scattering_places_the_requested_number_of_shapes() {
+        let field = scatter(ScatterSettings::default(), unit_sphere);
+
+        assert_eq!(field.children().len(), 20);
+    }
+
+    #[test]
+    fn scattered_shapes_stay_in_the_region_and_apart() {
+        let settings = ScatterSettings {
+            min: (0.0, 2.0),
+            max: (4.0, 3.0),
+            count: 5,
+            spacing: 0.5,
+            ..ScatterSettings::default()
+        };
+
+        let points = positions(&scatter(settings, unit_sphere));
+
+        assert_eq!(points.len(), 5);
+        for (i, a) in points.iter().enumerate() {
+            assert!((0.0..4.0).contains(&a.x()) && (2.0..3.0).contains(&a.z()));
+            for b in &points[i + 1..] {
+                assert!((*a - *b).magnitude() >= 0.5);
+            }
+        }
+    }
+
+    #[test]
+    fn a_crowded_region_gets_as_many_as_fit() {
+        let settings = ScatterSettings {
+            min: (0.0, 0.0),
+            max: (1.0, 1.0),
+            count: 50,
+            spacing: 2.0,
+            ..ScatterSettings::default()
+        };
+
+        assert_eq!(scatter(settings, unit_sphere).children().len(), 1);
+    }
+
+    #[test]
+    fn the_same_seed_scatters_the_same_way() {
+        let a = positions(&scatter(ScatterSettings::default(), unit_sphere));
+        let b = positions(&scatter(ScatterSettings::default(), unit_sphere));
+        let c = positions(&scatter(
+            ScatterSettings {
+                seed: 2,
+                ..ScatterSettings::default()
+            },
+            unit_sphere,
+        ));
+
+        assert_eq!(a, b);
+        assert_ne!(a, c);
+    }
+
+    #[test]
+    fn scattering_keeps_each_shapes_own_transform_and_scales_it() {
+        let settings = ScatterSettings {
+            count: 1,
+            scale: (2.0, 2.0),
+            ..ScatterSettings::default()
+        };
+
+        let field = scatter(settings, || {
+            Box::new(Sphere::default().with_transform(Transform::translation(0.0, 1.0, 0.0)))
+        });
+
+        let bounds = field.children()[0].parent_space_bounds();
+        assert!(float_eq(bounds.min.y(), 0.0));
+        assert!(float_eq(bounds.max.y(), 4.0));
+    }
+}
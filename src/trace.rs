@@ -0,0 +1,392 @@
+use std::fmt::{self, Display};
+
+use uuid::Uuid;
+
+use crate::{
+    color::{self, Color},
+    intersection::{Computations, Intersection},
+    ray::{DepthBudget, Ray},
+    spectrum::Radiance,
+    tuple::{Point, Vector},
+    world::World,
+};
+
+/// Everything that happened to one ray under `World::color_at`: what it
+/// crossed, how its hit was lit, and the reflected and refracted rays it
+/// spawned, each traced in turn. Printing it gives an indented report,
+/// for working out why a single pixel comes out wrong.
+#[derive(Clone, Debug, PartialEq)]
+pub struct RayTrace {
+    pub ray: Ray,
+    /// Every intersection along the ray, nearest first, including any
+    /// behind its origin.
+    pub intersections: Vec<TracedIntersection>,
+    pub hit: Option<HitTrace>,
+    /// The ray's final color, as `World::color_at` gives it.
+    pub color: Color,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct TracedIntersection {
+    pub t: f32,
+    pub object: Uuid,
+    pub kind: &'static str,
+}
+
+impl From<&Intersection<'_>> for TracedIntersection {
+    fn from(i: &Intersection) -> Self {
+        Self {
+            t: i.t,
+            object: i.object.id(),
+            kind: i.object.type_name(),
+        }
+    }
+}
+
+/// How the hit was shaded. `surface` is everything but reflection and
+/// refraction, which come in `reflected` and `refracted` before any
+/// Fresnel weighting.
+#[derive(Clone, Debug, PartialEq)]
+pub struct HitTrace {
+    pub intersection: TracedIntersection,
+    pub point: Point,
+    pub normal: Vector,
+    pub over_point: Point,
+    pub shadow: ShadowTest,
+    pub surface: Color,
+    pub reflected: Color,
+    pub refracted: Color,
+    pub reflection: Option<Box<RayTrace>>,
+    /// One refracted ray, or one per channel where a dispersive surface
+    /// splits the light.
+    pub refractions: Vec<RayTrace>,
+}
+
+impl HitTrace {
+    fn new(world: &World, hit: &Intersection, comps: Computations, remaining: DepthBudget) -> Self {
+        let surface = world.shade_hit(comps, DepthBudget::new(0, 0));
+
+        let reflection = world
+            .reflection_budget(&comps, remaining)
+            .map(|(remaining, weight)| {
+                let trace = RayTrace::new(world, comps.reflected_ray(), remaining);
+                (Box::new(trace), weight)
+            });
+        let reflected = match &reflection {
+            Some((trace, weight)) => trace.color * *weight,
+            None => color::BLACK,
+        };
+
+        let split = comps.splits_refraction(Color::SAMPLES);
+        let (refractions, refracted) = match world.refraction_budget(&comps, remaining) {
+            Some((remaining, weight)) => {
+                let channels = if split {
+                    (0..Color::SAMPLES).map(Some).collect()
+                } else {
+                    vec![comps.channel]
+                };
+                let refractions = channels
+                    .into_iter()
+                    .filter_map(|channel| {
+                        comps.refracted_ray(channel, channel.map(Color::wavelength))
+                    })
+                    .map(|ray| RayTrace::new(world, ray, remaining))
+                    .collect::<Vec<_>>();
+                let refracted = refractions.iter().fold(color::BLACK, |sum, trace| {
+                    match trace.ray.channel.filter(|_| split) {
+                        Some(i) => sum + trace.color.only(i),
+                        None => sum + trace.color,
+                    }
+                });
+                (refractions, refracted * weight)
+            }
+            None => (vec![], color::BLACK),
+        };
+
+        Self {
+            intersection: TracedIntersection::from(hit),
+            point: comps.point,
+            normal: comps.normalv,
+            over_point: comps.over_point,
+            shadow: ShadowTest::new(world, &comps),
+            surface,
+            reflected,
+            refracted,
+            reflection: reflection.map(|(trace, _)| trace),
+            refractions,
+        }
+    }
+}
+
+/// The shadow ray from a hit to the light.
+#[derive(Clone, Debug, PartialEq)]
+pub struct ShadowTest {
+    pub light_position: Point,
+    /// Objects between the hit and the light.
+    pub blockers: Vec<TracedIntersection>,
+    /// The light that gets through, per channel.
+    pub light_reaching: Color,
+}
+
+impl RayTrace {
+    pub fn new(world: &World, ray: Ray, remaining: impl Into<DepthBudget>) -> Self {
+        let remaining = remaining.into();
+        let xs = world.intersect(ray);
+        let intersections = xs.iter().map(TracedIntersection::from).collect::<Vec<_>>();
+        let (hit, color) = match xs.hit() {
+            Some(hit) => {
+                let comps = world.prepare_computations(hit, ray, &xs);
+                let trace = HitTrace::new(world, hit, comps, remaining);
+                let radiance = comps.with_bounces(
+                    &world.shading_material(&comps),
+                    trace.surface,
+                    trace.reflected,
+                    trace.refracted,
+                );
+                let color = world.arriving_from_hit(radiance, ray, &comps, hit.t);
+                (Some(trace), color)
+            }
+            None => (None, world.arriving_from_background(ray)),
+        };
+
+        Self {
+            ray,
+            intersections,
+            hit,
+            color,
+        }
+    }
+
+    fn write(&self, f: &mut fmt::Formatter<'_>, indent: usize) -> fmt::Result {
+        let pad = "  ".repeat(indent);
+        writeln!(
+            f,
+            "{}{:?} ray from {} toward {}: {}",
+            pad,
+            self.ray.kind,
+            point(self.ray.origin),
+            point(self.ray.direction),
+            rgb(self.color)
+        )?;
+        for i in &self.intersections {
+            writeln!(f, "{}  crosses {} {} at t = {}", pad, i.kind, i.object, i.t)?;
+        }
+        let hit = match &self.hit {
+            Some(hit) => hit,
+            None => return writeln!(f, "{}  misses everything", pad),
+        };
+        writeln!(
+            f,
+            "{}  hits {} at {} with normal {}",
+            pad,
+            hit.intersection.kind,
+            point(hit.point),
+            point(hit.normal)
+        )?;
+        if hit.shadow.blockers.is_empty() {
+            writeln!(f, "{}  sees the light", pad)?;
+        }
+        for blocker in &hit.shadow.blockers {
+            writeln!(
+                f,
+                "{}  shadowed by {} {} at t = {}",
+                pad, blocker.kind, blocker.object, blocker.t
+            )?;
+        }
+        writeln!(
+            f,
+            "{}  surface {}, reflected {}, refracted {}",
+            pad,
+            rgb(hit.surface),
+            rgb(hit.reflected),
+            rgb(hit.refracted)
+        )?;
+        if let Some(reflection) = &hit.reflection {
+            reflection.write(f, indent + 1)?;
+        }
+        for refraction in &hit.refractions {
+            refraction.write(f, indent + 1)?;
+        }
+        Ok(())
+    }
+}
+
+impl Display for RayTrace {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        self.write(f, 0)
+    }
+}
+
+impl ShadowTest {
+    fn new(world: &World, comps: &Computations) -> Self {
+        let (_, casters) = world.shadow_casters_of(comps);
+
+        Self {
+            light_position: world.light().position,
+            blockers: casters.iter().map(TracedIntersection::from).collect(),
+            light_reaching: world.light_reaching(comps),
+        }
+    }
+}
+
+fn point(t: impl Into<[f32; 3]>) -> String {
+    let [x, y, z] = t.into();
+    format!("({:.3}, {:.3}, {:.3})", x, y, z)
+}
+
+fn rgb(c: Color) -> String {
+    if c == color::BLACK {
+        "black".to_string()
+    } else {
+        format!("({:.3}, {:.3}, {:.3})", c.red(), c.green(), c.blue())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{
+        lights::PointLight,
+        materials::Material,
+        shapes::{plane::Plane, sphere::Sphere, ShapeBuilder},
+        transformations::Transform,
+    };
+
+    use super::*;
+
+    #[test]
+    fn tracing_a_ray_that_misses() {
+        let w = World::default();
+        let r = Ray::new(Point::new(0.0, 0.0, -5.0), Vector::new(0.0, 1.0, 0.0));
+
+        let trace = w.debug_ray(r, 5);
+
+        assert!(trace.intersections.is_empty());
+        assert_eq!(trace.hit, None);
+        assert_eq!(trace.color, color::BLACK);
+    }
+
+    #[test]
+    fn tracing_a_ray_into_the_default_world() {
+        let w = World::default();
+        let r = Ray::new(Point::new(0.0, 0.0, -5.0), Vector::new(0.0, 0.0, 1.0));
+
+        let trace = w.debug_ray(r, 5);
+
+        let ts = trace.intersections.iter().map(|i| i.t).collect::<Vec<_>>();
+        assert_eq!(ts, vec![4.0, 4.5, 5.5, 6.0]);
+        let hit = trace.hit.unwrap();
+        assert_eq!(hit.intersection.object, w.objects()[0].id());
+        assert_eq!(hit.intersection.kind, "Sphere");
+        assert_eq!(hit.normal, Vector::new(0.0, 0.0, -1.0));
+        assert!(hit.shadow.blockers.is_empty());
+        assert_eq!(hit.surface, trace.color);
+        assert_eq!(trace.color, w.color_at(r, 5));
+    }
+
+    #[test]
+    fn tracing_a_shadowed_hit_names_the_blocker() {
+        let w = World::default();
+        let r = Ray::new(Point::new(0.0, 0.0, 5.0), Vector::new(0.0, 0.0, -1.0));
+
+        let hit = w.debug_ray(r, 5).hit.unwrap();
+
+        assert_eq!(hit.shadow.blockers[0].object, w.objects()[0].id());
+        assert_eq!(hit.shadow.light_reaching, color::BLACK);
+    }
+
+    #[test]
+    fn tracing_follows_reflections_until_the_budget_runs_out() {
+        let w = World::new(PointLight::new(Point::new(0.0, 0.0, 0.0), color::WHITE))
+            .object(Box::new(
+                Plane::default()
+                    .with_material(Material::default().reflective(0.5))
+                    .with_transform(Transform::translation(0.0, -1.0, 0.0)),
+            ))
+            .object(Box::new(
+                Plane::default()
+                    .with_material(Material::default().reflective(0.5))
+                    .with_transform(Transform::translation(0.0, 1.0, 0.0)),
+            ));
+        let r = Ray::new(
+            Point::new(0.0, 0.0, 0.0),
+            Vector::new(0.0, 1.0, 1.0).normalize(),
+        );
+
+        let trace = w.debug_ray(r, 2);
+
+        let first = trace.hit.unwrap();
+        let second = first.reflection.unwrap().hit.unwrap();
+        let third = second.reflection.unwrap().hit.unwrap();
+        assert!(third.reflection.is_none());
+        assert_eq!(third.reflected, color::BLACK);
+        assert!(first.refractions.is_empty());
+    }
+
+    #[test]
+    fn tracing_stops_where_the_world_stops_following_faint_rays() {
+        let w = World::new(PointLight::new(Point::new(0.0, 5.0, 0.0), color::WHITE))
+            .min_contribution(0.6)
+            .object(Box::new(
+                Plane::default().with_material(Material::default().reflective(0.5)),
+            ));
+        let r = Ray::new(
+            Point::new(0.0, 1.0, -1.0),
+            Vector::new(0.0, -1.0, 1.0).normalize(),
+        );
+
+        let trace = w.debug_ray(r, 5);
+
+        let hit = trace.hit.unwrap();
+        assert!(hit.reflection.is_none());
+        assert_eq!(hit.reflected, color::BLACK);
+        assert_eq!(trace.color, w.color_at(r, 5));
+    }
+
+    #[test]
+    fn tracing_splits_light_where_a_dispersive_surface_does() {
+        let flint = Material::default()
+            .transparency(1.0)
+            .refractive_index(1.5)
+            .abbe_number(20.0);
+        let w = World::new(PointLight::new(Point::new(0.0, 10.0, -10.0), color::WHITE))
+            .object(Box::new(Sphere::default().with_material(flint)));
+        let r = Ray::new(Point::new(0.0, 0.5, -5.0), Vector::new(0.0, 0.0, 1.0));
+
+        let trace = w.debug_ray(r, 5);
+
+        let refractions = trace.hit.unwrap().refractions;
+        let channels = refractions
+            .iter()
+            .map(|t| t.ray.channel)
+            .collect::<Vec<_>>();
+        assert_eq!(channels, vec![Some(0), Some(1), Some(2)]);
+        assert_eq!(trace.color, w.color_at(r, 5));
+    }
+
+    #[test]
+    fn a_translucent_hit_isnt_shadowed_by_its_own_shape() {
+        let w = World::new(PointLight::new(Point::new(0.0, 0.0, 10.0), color::WHITE)).object(
+            Box::new(Sphere::default().with_material(Material::default().translucency(0.5))),
+        );
+        let r = Ray::new(Point::new(0.0, 0.0, -5.0), Vector::new(0.0, 0.0, 1.0));
+
+        let hit = w.debug_ray(r, 5).hit.unwrap();
+
+        assert!(hit.shadow.blockers.is_empty());
+        assert_eq!(hit.shadow.light_reaching, color::WHITE);
+    }
+
+    #[test]
+    fn printing_a_trace() {
+        let w = World::default();
+        let r = Ray::new(Point::new(0.0, 0.0, -5.0), Vector::new(0.0, 0.0, 1.0));
+
+        let report = w.debug_ray(r, 5).to_string();
+
+        assert!(report
+            .starts_with("Primary ray from (0.000, 0.000, -5.000) toward (0.000, 0.000, 1.000)"));
+        assert!(report.contains("hits Sphere at (0.000, 0.000, -1.000)"));
+        assert!(report.contains("sees the light"));
+        assert!(report.contains("reflected black, refracted black"));
+    }
+}
@@ -1,10 +1,18 @@
-use std::ops::Mul;
+use std::{f32::consts::PI, ops::Mul};
 
 use bevy::math::{Mat4, Vec3, Vec4};
 
-use crate::{float_eq, tuple::Tuple};
+use crate::{
+    float_eq,
+    tuple::{Point, Vector},
+};
 
 #[derive(Clone, Copy, Debug, Default)]
+#[cfg_attr(
+    feature = "serde",
+    derive(serde::Serialize, serde::Deserialize),
+    serde(from = "[[f32; 4]; 4]", into = "[[f32; 4]; 4]")
+)]
 pub struct Transform(Mat4);
 
 pub const IDENTITY: Transform = Transform(Mat4::IDENTITY);
@@ -51,7 +59,13 @@ impl Transform {
         Self(self.0.transpose())
     }
 
-    pub fn view_transform(from: Tuple, to: Tuple, up: Tuple) -> Self {
+    /// How much the transform scales lengths on average: the cube root of
+    /// how much it scales volumes.
+    pub fn scale_factor(&self) -> f32 {
+        self.0.determinant().abs().cbrt()
+    }
+
+    pub fn view_transform(from: Point, to: Point, up: Vector) -> Self {
         let forward = (to - from).normalize();
         let left = forward.cross(up.normalize());
         let true_up = left.cross(forward);
@@ -65,6 +79,69 @@ impl Transform {
     }
 }
 
+#[derive(Clone, Copy, Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum UpAxis {
+    Y,
+    Z,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum Handedness {
+    Left,
+    Right,
+}
+
+/// The coordinate system a scene or asset was authored in. The renderer
+/// itself works in the book's left-handed, Y-up space.
+#[derive(Clone, Copy, Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct AxisConvention {
+    pub up: UpAxis,
+    pub handedness: Handedness,
+}
+
+impl AxisConvention {
+    pub fn new(up: UpAxis, handedness: Handedness) -> Self {
+        Self { up, handedness }
+    }
+
+    /// Right-handed and Z-up, as exported by Blender.
+    pub fn blender() -> Self {
+        Self::new(UpAxis::Z, Handedness::Right)
+    }
+
+    pub fn to_native(self) -> Transform {
+        let mirror = match (self.handedness, self.up) {
+            (Handedness::Left, _) => IDENTITY,
+            (Handedness::Right, UpAxis::Y) => Transform::scaling(1.0, 1.0, -1.0),
+            (Handedness::Right, UpAxis::Z) => Transform::scaling(1.0, -1.0, 1.0),
+        };
+        let up = match self.up {
+            UpAxis::Y => IDENTITY,
+            UpAxis::Z => Transform::rotation_x(-PI / 2.0),
+        };
+
+        up * mirror
+    }
+
+    pub fn to_source(self) -> Transform {
+        self.to_native().inverse()
+    }
+
+    pub fn view_transform(self, from: Point, to: Point, up: Vector) -> Transform {
+        let to_native = self.to_native();
+        Transform::view_transform(to_native * from, to_native * to, to_native * up)
+    }
+}
+
+impl Default for AxisConvention {
+    fn default() -> Self {
+        Self::new(UpAxis::Y, Handedness::Left)
+    }
+}
+
 impl Mul for Transform {
     type Output = Self;
 
@@ -73,12 +150,23 @@ impl Mul for Transform {
     }
 }
 
-impl Mul<Tuple> for Transform {
-    type Output = Tuple;
+impl Mul<Point> for Transform {
+    type Output = Point;
 
-    fn mul(self, rhs: Tuple) -> Self::Output {
+    fn mul(self, rhs: Point) -> Self::Output {
         let vec = self.0 * rhs.vec();
-        Tuple::new(vec.x, vec.y, vec.z, rhs.vec().w)
+        Point::new(vec.x, vec.y, vec.z)
+    }
+}
+
+/// Vectors are unmoved by translation, and stay vectors even under
+/// matrices like the inverse transpose that would give them a w.
+impl Mul<Vector> for Transform {
+    type Output = Vector;
+
+    fn mul(self, rhs: Vector) -> Self::Output {
+        let vec = self.0 * rhs.vec();
+        Vector::new(vec.x, vec.y, vec.z)
     }
 }
 
@@ -95,6 +183,19 @@ impl PartialEq for Transform {
     }
 }
 
+/// A transform's matrix row by row, as the book writes them.
+impl From<[[f32; 4]; 4]> for Transform {
+    fn from(rows: [[f32; 4]; 4]) -> Self {
+        Transform(Mat4::from_cols_array_2d(&rows).transpose())
+    }
+}
+
+impl From<Transform> for [[f32; 4]; 4] {
+    fn from(transform: Transform) -> Self {
+        transform.0.transpose().to_cols_array_2d()
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use std::f32::consts::PI;
@@ -104,24 +205,24 @@ mod tests {
     #[test]
     fn multiplying_by_a_translation_matrix() {
         let transform = Transform::translation(5.0, -3.0, 2.0);
-        let p = Tuple::point(-3.0, 4.0, 5.0);
+        let p = Point::new(-3.0, 4.0, 5.0);
 
-        assert_eq!(transform * p, Tuple::point(2.0, 1.0, 7.0));
+        assert_eq!(transform * p, Point::new(2.0, 1.0, 7.0));
     }
 
     #[test]
     fn multiplying_by_the_inverse_of_a_translation_matrix() {
         let transform = Transform::translation(5.0, -3.0, 2.0);
         let inv = transform.inverse();
-        let p = Tuple::point(-3.0, 4.0, 5.0);
+        let p = Point::new(-3.0, 4.0, 5.0);
 
-        assert_eq!(inv * p, Tuple::point(-8.0, 7.0, 3.0));
+        assert_eq!(inv * p, Point::new(-8.0, 7.0, 3.0));
     }
 
     #[test]
     fn translation_does_not_affect_vectors() {
         let transform = Transform::translation(5.0, -3.0, 2.0);
-        let v = Tuple::vector(-3.0, 4.0, 5.0);
+        let v = Vector::new(-3.0, 4.0, 5.0);
 
         assert_eq!(transform * v, v);
     }
@@ -129,144 +230,144 @@ mod tests {
     #[test]
     fn scaling_matrix_applied_to_point() {
         let transform = Transform::scaling(2.0, 3.0, 4.0);
-        let p = Tuple::point(-4.0, 6.0, 8.0);
+        let p = Point::new(-4.0, 6.0, 8.0);
 
-        assert_eq!(transform * p, Tuple::point(-8.0, 18.0, 32.0));
+        assert_eq!(transform * p, Point::new(-8.0, 18.0, 32.0));
     }
 
     #[test]
     fn scaling_matrix_applied_to_vector() {
         let transform = Transform::scaling(2.0, 3.0, 4.0);
-        let v = Tuple::vector(-4.0, 6.0, 8.0);
+        let v = Vector::new(-4.0, 6.0, 8.0);
 
-        assert_eq!(transform * v, Tuple::vector(-8.0, 18.0, 32.0));
+        assert_eq!(transform * v, Vector::new(-8.0, 18.0, 32.0));
     }
 
     #[test]
     fn multiplying_by_the_inverse_of_a_scaling_matrix() {
         let transform = Transform::scaling(2.0, 3.0, 4.0);
         let inv = transform.inverse();
-        let v = Tuple::vector(-4.0, 6.0, 8.0);
+        let v = Vector::new(-4.0, 6.0, 8.0);
 
-        assert_eq!(inv * v, Tuple::vector(-2.0, 2.0, 2.0));
+        assert_eq!(inv * v, Vector::new(-2.0, 2.0, 2.0));
     }
 
     #[test]
     fn reflection_is_scaling_by_negative_value() {
         let transform = Transform::scaling(-1.0, 1.0, 1.0);
-        let p = Tuple::point(2.0, 3.0, 4.0);
+        let p = Point::new(2.0, 3.0, 4.0);
 
-        assert_eq!(transform * p, Tuple::point(-2.0, 3.0, 4.0));
+        assert_eq!(transform * p, Point::new(-2.0, 3.0, 4.0));
     }
 
     #[test]
     fn rotating_a_point_around_the_x_axis() {
-        let p = Tuple::point(0.0, 1.0, 0.0);
+        let p = Point::new(0.0, 1.0, 0.0);
         let half_quarter = Transform::rotation_x(PI / 4.0);
         let full_quarter = Transform::rotation_x(PI / 2.0);
 
         let sqrt2over2 = 2_f32.sqrt() / 2.0;
-        assert_eq!(half_quarter * p, Tuple::point(0.0, sqrt2over2, sqrt2over2));
-        assert_eq!(full_quarter * p, Tuple::point(0.0, 0.0, 1.0));
+        assert_eq!(half_quarter * p, Point::new(0.0, sqrt2over2, sqrt2over2));
+        assert_eq!(full_quarter * p, Point::new(0.0, 0.0, 1.0));
     }
 
     #[test]
     fn rotating_a_point_around_the_y_axis() {
-        let p = Tuple::point(0.0, 0.0, 1.0);
+        let p = Point::new(0.0, 0.0, 1.0);
         let half_quarter = Transform::rotation_y(PI / 4.0);
         let full_quarter = Transform::rotation_y(PI / 2.0);
 
         let sqrt2over2 = 2_f32.sqrt() / 2.0;
-        assert_eq!(half_quarter * p, Tuple::point(sqrt2over2, 0.0, sqrt2over2));
-        assert_eq!(full_quarter * p, Tuple::point(1.0, 0.0, 0.0));
+        assert_eq!(half_quarter * p, Point::new(sqrt2over2, 0.0, sqrt2over2));
+        assert_eq!(full_quarter * p, Point::new(1.0, 0.0, 0.0));
     }
 
     #[test]
     fn rotating_a_point_around_the_z_axis() {
-        let p = Tuple::point(0.0, 1.0, 0.0);
+        let p = Point::new(0.0, 1.0, 0.0);
         let half_quarter = Transform::rotation_z(PI / 4.0);
         let full_quarter = Transform::rotation_z(PI / 2.0);
 
         let sqrt2over2 = 2_f32.sqrt() / 2.0;
-        assert_eq!(half_quarter * p, Tuple::point(-sqrt2over2, sqrt2over2, 0.0));
-        assert_eq!(full_quarter * p, Tuple::point(-1.0, 0.0, 0.0));
+        assert_eq!(half_quarter * p, Point::new(-sqrt2over2, sqrt2over2, 0.0));
+        assert_eq!(full_quarter * p, Point::new(-1.0, 0.0, 0.0));
     }
 
     #[test]
     fn shearing_x_in_proportion_to_y() {
         let transform = Transform::shearing(1.0, 0.0, 0.0, 0.0, 0.0, 0.0);
-        let p = Tuple::point(2.0, 3.0, 4.0);
+        let p = Point::new(2.0, 3.0, 4.0);
 
-        assert_eq!(transform * p, Tuple::point(5.0, 3.0, 4.0));
+        assert_eq!(transform * p, Point::new(5.0, 3.0, 4.0));
     }
 
     #[test]
     fn shearing_x_in_proportion_to_z() {
         let transform = Transform::shearing(0.0, 1.0, 0.0, 0.0, 0.0, 0.0);
-        let p = Tuple::point(2.0, 3.0, 4.0);
+        let p = Point::new(2.0, 3.0, 4.0);
 
-        assert_eq!(transform * p, Tuple::point(6.0, 3.0, 4.0));
+        assert_eq!(transform * p, Point::new(6.0, 3.0, 4.0));
     }
 
     #[test]
     fn shearing_y_in_proportion_to_x() {
         let transform = Transform::shearing(0.0, 0.0, 1.0, 0.0, 0.0, 0.0);
-        let p = Tuple::point(2.0, 3.0, 4.0);
+        let p = Point::new(2.0, 3.0, 4.0);
 
-        assert_eq!(transform * p, Tuple::point(2.0, 5.0, 4.0));
+        assert_eq!(transform * p, Point::new(2.0, 5.0, 4.0));
     }
 
     #[test]
     fn shearing_y_in_proportion_to_z() {
         let transform = Transform::shearing(0.0, 0.0, 0.0, 1.0, 0.0, 0.0);
-        let p = Tuple::point(2.0, 3.0, 4.0);
+        let p = Point::new(2.0, 3.0, 4.0);
 
-        assert_eq!(transform * p, Tuple::point(2.0, 7.0, 4.0));
+        assert_eq!(transform * p, Point::new(2.0, 7.0, 4.0));
     }
 
     #[test]
     fn shearing_z_in_proportion_to_x() {
         let transform = Transform::shearing(0.0, 0.0, 0.0, 0.0, 1.0, 0.0);
-        let p = Tuple::point(2.0, 3.0, 4.0);
+        let p = Point::new(2.0, 3.0, 4.0);
 
-        assert_eq!(transform * p, Tuple::point(2.0, 3.0, 6.0));
+        assert_eq!(transform * p, Point::new(2.0, 3.0, 6.0));
     }
 
     #[test]
     fn shearing_z_in_proportion_to_y() {
         let transform = Transform::shearing(0.0, 0.0, 0.0, 0.0, 0.0, 1.0);
-        let p = Tuple::point(2.0, 3.0, 4.0);
+        let p = Point::new(2.0, 3.0, 4.0);
 
-        assert_eq!(transform * p, Tuple::point(2.0, 3.0, 7.0));
+        assert_eq!(transform * p, Point::new(2.0, 3.0, 7.0));
     }
 
     #[test]
     fn individual_transformations_are_applied_in_sequence() {
-        let p = Tuple::point(1.0, 0.0, 1.0);
+        let p = Point::new(1.0, 0.0, 1.0);
         let a = Transform::rotation_x(PI / 2.0);
         let b = Transform::scaling(5.0, 5.0, 5.0);
         let c = Transform::translation(10.0, 5.0, 7.0);
 
         let p2 = a * p;
-        assert_eq!(p2, Tuple::point(1.0, -1.0, 0.0));
+        assert_eq!(p2, Point::new(1.0, -1.0, 0.0));
 
         let p3 = b * p2;
-        assert_eq!(p3, Tuple::point(5.0, -5.0, 0.0));
+        assert_eq!(p3, Point::new(5.0, -5.0, 0.0));
 
         let p4 = c * p3;
-        assert_eq!(p4, Tuple::point(15.0, 0.0, 7.0));
+        assert_eq!(p4, Point::new(15.0, 0.0, 7.0));
     }
 
     #[test]
     fn chained_transformations_must_be_applied_in_reverse_order() {
-        let point = Tuple::point(1.0, 0.0, 1.0);
+        let point = Point::new(1.0, 0.0, 1.0);
         let a = Transform::rotation_x(PI / 2.0);
         let b = Transform::scaling(5.0, 5.0, 5.0);
         let c = Transform::translation(10.0, 5.0, 7.0);
 
         let transform = c * b * a;
 
-        assert_eq!(transform * point, Tuple::point(15.0, 0.0, 7.0));
+        assert_eq!(transform * point, Point::new(15.0, 0.0, 7.0));
     }
 
     // TODO
@@ -293,9 +394,9 @@ mod tests {
 
     #[test]
     fn transformation_matrix_for_the_default_orientation() {
-        let from = Tuple::point(0.0, 0.0, 0.0);
-        let to = Tuple::point(0.0, 0.0, -1.0);
-        let up = Tuple::vector(0.0, 1.0, 0.0);
+        let from = Point::new(0.0, 0.0, 0.0);
+        let to = Point::new(0.0, 0.0, -1.0);
+        let up = Vector::new(0.0, 1.0, 0.0);
 
         let t = Transform::view_transform(from, to, up);
 
@@ -304,9 +405,9 @@ mod tests {
 
     #[test]
     fn view_transformation_matrix_looking_in_positive_z_direction() {
-        let from = Tuple::point(0.0, 0.0, 0.0);
-        let to = Tuple::point(0.0, 0.0, 1.0);
-        let up = Tuple::vector(0.0, 1.0, 0.0);
+        let from = Point::new(0.0, 0.0, 0.0);
+        let to = Point::new(0.0, 0.0, 1.0);
+        let up = Vector::new(0.0, 1.0, 0.0);
 
         let t = Transform::view_transform(from, to, up);
 
@@ -315,9 +416,9 @@ mod tests {
 
     #[test]
     fn view_transformation_moves_the_world() {
-        let from = Tuple::point(0.0, 0.0, 8.0);
-        let to = Tuple::point(0.0, 0.0, 0.0);
-        let up = Tuple::vector(0.0, 1.0, 0.0);
+        let from = Point::new(0.0, 0.0, 8.0);
+        let to = Point::new(0.0, 0.0, 0.0);
+        let up = Vector::new(0.0, 1.0, 0.0);
 
         let t = Transform::view_transform(from, to, up);
 
@@ -326,9 +427,9 @@ mod tests {
 
     #[test]
     fn an_arbitrary_view_transformation() {
-        let from = Tuple::point(1.0, 3.0, 2.0);
-        let to = Tuple::point(4.0, -2.0, 8.0);
-        let up = Tuple::vector(1.0, 1.0, 0.0);
+        let from = Point::new(1.0, 3.0, 2.0);
+        let to = Point::new(4.0, -2.0, 8.0);
+        let up = Vector::new(1.0, 1.0, 0.0);
 
         let t = Transform::view_transform(from, to, up);
 
@@ -342,4 +443,76 @@ mod tests {
             ))
         )
     }
+
+    #[test]
+    fn the_default_axis_convention_is_native() {
+        let c = AxisConvention::default();
+
+        assert_eq!(c.up, UpAxis::Y);
+        assert_eq!(c.handedness, Handedness::Left);
+        assert_eq!(c.to_native(), IDENTITY);
+    }
+
+    #[test]
+    fn converting_from_z_up_right_handed_swaps_y_and_z() {
+        let c = AxisConvention::blender();
+
+        assert_eq!(c.to_native() * Point::new(1.0, 2.0, 3.0), Point::new(1.0, 3.0, 2.0));
+        assert_eq!(
+            c.to_native() * Vector::new(0.0, 0.0, 1.0),
+            Vector::new(0.0, 1.0, 0.0)
+        );
+    }
+
+    #[test]
+    fn converting_from_z_up_left_handed_is_a_rotation() {
+        let c = AxisConvention::new(UpAxis::Z, Handedness::Left);
+
+        assert_eq!(c.to_native() * Point::new(1.0, 2.0, 3.0), Point::new(1.0, 3.0, -2.0));
+    }
+
+    #[test]
+    fn converting_from_y_up_right_handed_mirrors_z() {
+        let c = AxisConvention::new(UpAxis::Y, Handedness::Right);
+
+        assert_eq!(c.to_native() * Point::new(1.0, 2.0, 3.0), Point::new(1.0, 2.0, -3.0));
+    }
+
+    #[test]
+    fn to_source_undoes_to_native() {
+        let c = AxisConvention::blender();
+        let p = Point::new(1.0, 2.0, 3.0);
+
+        assert_eq!(c.to_source() * (c.to_native() * p), p);
+    }
+
+    #[test]
+    fn the_scale_factor_of_a_transform() {
+        let t = Transform::translation(3.0, 4.0, 5.0)
+            * Transform::rotation_x(0.7)
+            * Transform::scaling(2.0, 4.0, -8.0);
+
+        assert!(float_eq(t.scale_factor(), 4.0));
+        assert!(float_eq(t.inverse().scale_factor(), 0.25));
+    }
+
+    #[test]
+    fn a_view_transform_in_a_z_up_scene() {
+        let c = AxisConvention::blender();
+
+        let t = c.view_transform(
+            Point::new(0.0, -8.0, 0.0),
+            Point::new(0.0, 0.0, 0.0),
+            Vector::new(0.0, 0.0, 1.0),
+        );
+
+        assert_eq!(
+            t,
+            Transform::view_transform(
+                Point::new(0.0, 0.0, -8.0),
+                Point::new(0.0, 0.0, 0.0),
+                Vector::new(0.0, 1.0, 0.0)
+            )
+        );
+    }
 }
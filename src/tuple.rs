@@ -1,64 +1,139 @@
 #![allow(dead_code)]
 
-use std::ops::{Add, Div, Mul, Neg, Sub};
+use std::ops::{Add, Div, Index, Mul, Neg, Sub};
 
 use bevy::math::{Vec3A, Vec4};
 
 use crate::float_eq;
 
+/// A position in space, stored with a w of 1 so translations move it.
+/// Points can't be added together or scaled; subtracting one from another
+/// gives the `Vector` between them.
 #[derive(Clone, Copy, Debug, Default)]
-pub struct Tuple(Vec4);
-
-impl Tuple {
-    pub fn new(x: f32, y: f32, z: f32, w: f32) -> Self {
-        Self(Vec4::new(x, y, z, w))
-    }
-
-    pub fn point(x: f32, y: f32, z: f32) -> Self {
-        Self::new(x, y, z, 1.0)
-    }
-
-    pub fn vector(x: f32, y: f32, z: f32) -> Self {
-        Self::new(x, y, z, 0.0)
-    }
-
-    pub fn is_point(self) -> bool {
-        self.0.w > 0.0
-    }
+#[cfg_attr(
+    feature = "serde",
+    derive(serde::Serialize, serde::Deserialize),
+    serde(from = "[f32; 3]", into = "[f32; 3]")
+)]
+pub struct Point(Vec4);
+
+/// A direction and length, stored with a w of 0 so translations leave it
+/// alone.
+#[derive(Clone, Copy, Debug, Default)]
+#[cfg_attr(
+    feature = "serde",
+    derive(serde::Serialize, serde::Deserialize),
+    serde(from = "[f32; 3]", into = "[f32; 3]")
+)]
+pub struct Vector(Vec4);
+
+/// What points and vectors have in common: their components, and the
+/// componentwise operations that make sense for either.
+macro_rules! components {
+    ($tuple:ident, $w:expr) => {
+        impl $tuple {
+            pub fn new(x: f32, y: f32, z: f32) -> Self {
+                Self(Vec4::new(x, y, z, $w))
+            }
+
+            pub fn x(self) -> f32 {
+                self.0.x
+            }
+
+            pub fn y(self) -> f32 {
+                self.0.y
+            }
+
+            pub fn z(self) -> f32 {
+                self.0.z
+            }
+
+            pub fn vec(self) -> Vec4 {
+                self.0
+            }
+
+            /// The smaller of each pair of components.
+            pub fn min(self, other: Self) -> Self {
+                Self(self.0.min(other.0))
+            }
+
+            /// The larger of each pair of components.
+            pub fn max(self, other: Self) -> Self {
+                Self(self.0.max(other.0))
+            }
+
+            pub fn abs(self) -> Self {
+                Self(self.0.abs())
+            }
+
+            /// Each component limited to the range given by `min` and `max`.
+            pub fn clamp(self, min: Self, max: Self) -> Self {
+                self.max(min).min(max)
+            }
+
+            /// The value `t` of the way from `self` to `other`.
+            pub fn lerp(self, other: Self, t: f32) -> Self {
+                Self(self.0.lerp(other.0, t))
+            }
+        }
+
+        impl Index<usize> for $tuple {
+            type Output = f32;
+
+            fn index(&self, index: usize) -> &Self::Output {
+                match index {
+                    0 => &self.0.x,
+                    1 => &self.0.y,
+                    2 => &self.0.z,
+                    3 => &self.0.w,
+                    _ => panic!("tuple index {} out of range", index),
+                }
+            }
+        }
+
+        impl From<[f32; 3]> for $tuple {
+            fn from([x, y, z]: [f32; 3]) -> Self {
+                Self::new(x, y, z)
+            }
+        }
+
+        impl From<$tuple> for [f32; 3] {
+            fn from(tuple: $tuple) -> Self {
+                [tuple.x(), tuple.y(), tuple.z()]
+            }
+        }
+
+        impl PartialEq for $tuple {
+            fn eq(&self, other: &Self) -> bool {
+                float_eq(self.0.x, other.0.x)
+                    && float_eq(self.0.y, other.0.y)
+                    && float_eq(self.0.z, other.0.z)
+            }
+        }
+    };
+}
 
-    pub fn is_vector(self) -> bool {
-        self.0.w == 0.0
-    }
+components!(Point, 1.0);
+components!(Vector, 0.0);
 
-    pub fn to_point(self) -> Self {
-        let mut vector = self.0;
-        vector.w = 1.0;
-        Self(vector)
+impl Point {
+    /// The vector from the origin to this point.
+    pub fn to_vector(self) -> Vector {
+        Vector::new(self.x(), self.y(), self.z())
     }
+}
 
-    pub fn to_vector(self) -> Self {
-        let mut vector = self.0;
-        vector.w = 0.0;
-        Self(vector)
+impl Vector {
+    /// The point this vector reaches from the origin.
+    pub fn to_point(self) -> Point {
+        Point::new(self.x(), self.y(), self.z())
     }
 
     pub fn magnitude(self) -> f32 {
         self.0.length()
     }
 
-    pub fn x(self) -> f32 {
-        self.0.x
-    }
-
-    pub fn y(self) -> f32 {
-        self.0.y
-    }
-
-    pub fn z(self) -> f32 {
-        self.0.z
-    }
-
-    pub fn reflect(self, normal: Tuple) -> Self {
+    pub fn reflect(self, normal: Vector) -> Self {
         self - normal * 2.0 * self.dot(normal)
     }
 
@@ -71,63 +146,81 @@ impl Tuple {
     }
 
     pub fn cross(self, other: Self) -> Self {
-        let vec1 = Vec3A::from(self.0);
-        let vec2 = Vec3A::from(other.0);
-        let cross = vec1.cross(vec2);
-        Self(Vec4::new(cross.x, cross.y, cross.z, self.0.w))
+        let cross = Vec3A::from(self.0).cross(Vec3A::from(other.0));
+        Self::new(cross.x, cross.y, cross.z)
     }
+}
+
+impl Add<Vector> for Point {
+    type Output = Point;
 
-    pub fn vec(self) -> Vec4 {
-        self.0
+    fn add(self, rhs: Vector) -> Self::Output {
+        Point(self.0 + rhs.0)
     }
 }
 
-impl PartialEq for Tuple {
-    fn eq(&self, other: &Self) -> bool {
-        float_eq(self.0.x, other.0.x)
-            && float_eq(self.0.y, other.0.y)
-            && float_eq(self.0.z, other.0.z)
-            && float_eq(self.0.w, other.0.w)
+impl Sub<Vector> for Point {
+    type Output = Point;
+
+    fn sub(self, rhs: Vector) -> Self::Output {
+        Point(self.0 - rhs.0)
     }
 }
 
-impl Add for Tuple {
+impl Sub for Point {
+    type Output = Vector;
+
+    fn sub(self, rhs: Self) -> Self::Output {
+        Vector(self.0 - rhs.0)
+    }
+}
+
+impl Add for Vector {
     type Output = Self;
 
     fn add(self, rhs: Self) -> Self::Output {
-        Tuple(self.0 + rhs.0)
+        Vector(self.0 + rhs.0)
     }
 }
 
-impl Sub for Tuple {
+impl Sub for Vector {
     type Output = Self;
 
     fn sub(self, rhs: Self) -> Self::Output {
-        Tuple(self.0 - rhs.0)
+        Vector(self.0 - rhs.0)
     }
 }
 
-impl Neg for Tuple {
+impl Neg for Vector {
     type Output = Self;
 
     fn neg(self) -> Self::Output {
-        Tuple(-self.0)
+        Vector(-self.0)
     }
 }
 
-impl Mul<f32> for Tuple {
+impl Mul<f32> for Vector {
     type Output = Self;
 
     fn mul(self, rhs: f32) -> Self::Output {
-        Tuple(self.0 * rhs)
+        Vector(self.0 * rhs)
+    }
+}
+
+/// Multiplies component by component.
+impl Mul for Vector {
+    type Output = Self;
+
+    fn mul(self, rhs: Self) -> Self::Output {
+        Vector(self.0 * rhs.0)
     }
 }
 
-impl Div<f32> for Tuple {
+impl Div<f32> for Vector {
     type Output = Self;
 
     fn div(self, rhs: f32) -> Self::Output {
-        Tuple(self.0 / rhs)
+        Vector(self.0 / rhs)
     }
 }
 
@@ -138,160 +231,142 @@ mod tests {
     use crate::test::*;
 
     #[test]
-    fn tuple_with_w_1_is_a_point() {
-        let a = Tuple::new(4.3, -4.2, 3.1, 1.0);
+    fn a_point_has_w_1() {
+        let p = Point::new(4.3, -4.2, 3.1);
 
-        float_eq(a.0.x, 4.3);
-        float_eq(a.0.y, -4.2);
-        float_eq(a.0.z, 3.1);
-        float_eq(a.0.w, 1.0);
-        assert!(a.is_point());
-        assert!(!a.is_vector());
+        assert!(float_eq(p.x(), 4.3));
+        assert!(float_eq(p.y(), -4.2));
+        assert!(float_eq(p.z(), 3.1));
+        assert!(float_eq(p[3], 1.0));
     }
 
     #[test]
-    fn tuple_with_w_0_is_a_point() {
-        let a = Tuple::new(4.3, -4.2, 3.1, 0.0);
+    fn a_vector_has_w_0() {
+        let v = Vector::new(4.3, -4.2, 3.1);
 
-        float_eq(a.0.x, 4.3);
-        float_eq(a.0.y, -4.2);
-        float_eq(a.0.z, 3.1);
-        float_eq(a.0.w, 0.0);
-        assert!(!a.is_point());
-        assert!(a.is_vector());
+        assert!(float_eq(v.x(), 4.3));
+        assert!(float_eq(v.y(), -4.2));
+        assert!(float_eq(v.z(), 3.1));
+        assert!(float_eq(v[3], 0.0));
     }
 
     #[test]
-    fn point_creates_tuples_with_w_1() {
-        let p = Tuple::point(4.0, -4.0, 3.0);
+    fn adding_a_vector_to_a_point() {
+        let p = Point::new(3.0, -2.0, 5.0);
+        let v = Vector::new(-2.0, 3.0, 1.0);
 
-        assert_eq!(p, Tuple::new(4.0, -4.0, 3.0, 1.0));
-    }
-
-    #[test]
-    fn vector_creates_tuples_with_w_1() {
-        let p = Tuple::vector(4.0, -4.0, 3.0);
-
-        assert_eq!(p, Tuple::new(4.0, -4.0, 3.0, 0.0));
-    }
-
-    #[test]
-    fn adding_two_tuples() {
-        let a1 = Tuple::new(3.0, -2.0, 5.0, 1.0);
-        let a2 = Tuple::new(-2.0, 3.0, 1.0, 0.0);
-
-        assert_eq!(a1 + a2, Tuple::new(1.0, 1.0, 6.0, 1.0));
+        assert_eq!(p + v, Point::new(1.0, 1.0, 6.0));
     }
 
     #[test]
     fn subtracting_two_points() {
-        let p1 = Tuple::point(3.0, 2.0, 1.0);
-        let p2 = Tuple::point(5.0, 6.0, 7.0);
+        let p1 = Point::new(3.0, 2.0, 1.0);
+        let p2 = Point::new(5.0, 6.0, 7.0);
 
-        assert_eq!(p1 - p2, Tuple::vector(-2.0, -4.0, -6.0));
+        assert_eq!(p1 - p2, Vector::new(-2.0, -4.0, -6.0));
     }
 
     #[test]
     fn subtracting_a_vector_from_a_point() {
-        let p = Tuple::point(3.0, 2.0, 1.0);
-        let v = Tuple::vector(5.0, 6.0, 7.0);
+        let p = Point::new(3.0, 2.0, 1.0);
+        let v = Vector::new(5.0, 6.0, 7.0);
 
-        assert_eq!(p - v, Tuple::point(-2.0, -4.0, -6.0));
+        assert_eq!(p - v, Point::new(-2.0, -4.0, -6.0));
     }
 
     #[test]
     fn subtracting_two_vectors() {
-        let v1 = Tuple::vector(3.0, 2.0, 1.0);
-        let v2 = Tuple::vector(5.0, 6.0, 7.0);
+        let v1 = Vector::new(3.0, 2.0, 1.0);
+        let v2 = Vector::new(5.0, 6.0, 7.0);
 
-        assert_eq!(v1 - v2, Tuple::vector(-2.0, -4.0, -6.0));
+        assert_eq!(v1 - v2, Vector::new(-2.0, -4.0, -6.0));
     }
 
     #[test]
     fn subtracting_a_vector_from_the_zero_vector() {
-        let zero = Tuple::vector(0.0, 0.0, 0.0);
-        let v = Tuple::vector(1.0, -2.0, 3.0);
+        let zero = Vector::new(0.0, 0.0, 0.0);
+        let v = Vector::new(1.0, -2.0, 3.0);
 
-        assert_eq!(zero - v, Tuple::vector(-1.0, 2.0, -3.0));
+        assert_eq!(zero - v, Vector::new(-1.0, 2.0, -3.0));
     }
 
     #[test]
-    fn negating_a_tuple() {
-        let a = Tuple::new(1.0, -2.0, 3.0, -4.0);
+    fn negating_a_vector() {
+        let v = Vector::new(1.0, -2.0, 3.0);
 
-        assert_eq!(-a, Tuple::new(-1.0, 2.0, -3.0, 4.0));
+        assert_eq!(-v, Vector::new(-1.0, 2.0, -3.0));
     }
 
     #[test]
-    fn multiplying_a_tuple_by_a_scalar() {
-        let a = Tuple::new(1.0, -2.0, 3.0, -4.0);
+    fn multiplying_a_vector_by_a_scalar() {
+        let v = Vector::new(1.0, -2.0, 3.0);
 
-        assert_eq!(a * 3.5, Tuple::new(3.5, -7.0, 10.5, -14.0));
+        assert_eq!(v * 3.5, Vector::new(3.5, -7.0, 10.5));
     }
 
     #[test]
-    fn multiplying_a_tuple_by_a_fraction() {
-        let a = Tuple::new(1.0, -2.0, 3.0, -4.0);
+    fn multiplying_a_vector_by_a_fraction() {
+        let v = Vector::new(1.0, -2.0, 3.0);
 
-        assert_eq!(a * 0.5, Tuple::new(0.5, -1.0, 1.5, -2.0));
+        assert_eq!(v * 0.5, Vector::new(0.5, -1.0, 1.5));
     }
 
     #[test]
-    fn dividing_a_tuple_by_a_scalar() {
-        let a = Tuple::new(1.0, -2.0, 3.0, -4.0);
+    fn dividing_a_vector_by_a_scalar() {
+        let v = Vector::new(1.0, -2.0, 3.0);
 
-        assert_eq!(a / 2.0, Tuple::new(0.5, -1.0, 1.5, -2.0));
+        assert_eq!(v / 2.0, Vector::new(0.5, -1.0, 1.5));
     }
 
     #[test]
     fn computing_the_magnitude_of_vector_1_0_0() {
-        let v = Tuple::vector(1.0, 0.0, 0.0);
+        let v = Vector::new(1.0, 0.0, 0.0);
 
-        float_eq(v.magnitude(), 1.0);
+        assert!(float_eq(v.magnitude(), 1.0));
     }
 
     #[test]
     fn computing_the_magnitude_of_vector_0_1_0() {
-        let v = Tuple::vector(0.0, 1.0, 0.0);
+        let v = Vector::new(0.0, 1.0, 0.0);
 
-        float_eq(v.magnitude(), 1.0);
+        assert!(float_eq(v.magnitude(), 1.0));
     }
 
     #[test]
     fn computing_the_magnitude_of_vector_0_0_1() {
-        let v = Tuple::vector(0.0, 0.0, 1.0);
+        let v = Vector::new(0.0, 0.0, 1.0);
 
-        float_eq(v.magnitude(), 1.0);
+        assert!(float_eq(v.magnitude(), 1.0));
     }
 
     #[test]
     fn computing_the_magnitude_of_vector_1_2_3() {
-        let v = Tuple::vector(1.0, 2.0, 3.0);
+        let v = Vector::new(1.0, 2.0, 3.0);
 
-        float_eq(v.magnitude(), 14_f32.sqrt());
+        assert!(float_eq(v.magnitude(), 14_f32.sqrt()));
     }
 
     #[test]
     fn computing_the_magnitude_of_vector_neg1_neg2_neg3() {
-        let v = Tuple::vector(-1.0, -2.0, -3.0);
+        let v = Vector::new(-1.0, -2.0, -3.0);
 
-        float_eq(v.magnitude(), 14_f32.sqrt());
+        assert!(float_eq(v.magnitude(), 14_f32.sqrt()));
     }
 
     #[test]
     fn normalizing_vector_4_0_0_gives_1_0_0() {
-        let v = Tuple::vector(4.0, 0.0, 0.0);
+        let v = Vector::new(4.0, 0.0, 0.0);
 
-        assert_eq!(v.normalize(), Tuple::vector(1.0, 0.0, 0.0));
+        assert_eq!(v.normalize(), Vector::new(1.0, 0.0, 0.0));
     }
 
     #[test]
     fn normalizing_vector_1_2_3() {
-        let v = Tuple::vector(1.0, 2.0, 3.0);
+        let v = Vector::new(1.0, 2.0, 3.0);
 
         assert_eq!(
             v.normalize(),
-            Tuple::vector(
+            Vector::new(
                 1.0 / 14_f32.sqrt(),
                 2.0 / 14_f32.sqrt(),
                 3.0 / 14_f32.sqrt()
@@ -301,47 +376,116 @@ mod tests {
 
     #[test]
     fn the_magnitude_of_a_normalized_vector() {
-        let v = Tuple::vector(1.0, 2.0, 3.0);
+        let v = Vector::new(1.0, 2.0, 3.0);
 
         let norm = v.normalize();
 
-        float_eq(norm.magnitude(), 1.0);
+        assert!(float_eq(norm.magnitude(), 1.0));
     }
 
     #[test]
-    fn the_dot_product_of_two_tuples() {
-        let a = Tuple::vector(1.0, 2.0, 3.0);
-        let b = Tuple::vector(2.0, 3.0, 4.0);
+    fn the_dot_product_of_two_vectors() {
+        let a = Vector::new(1.0, 2.0, 3.0);
+        let b = Vector::new(2.0, 3.0, 4.0);
 
-        float_eq(a.dot(b), 20.0);
+        assert!(float_eq(a.dot(b), 20.0));
     }
 
     #[test]
     fn the_cross_product_of_two_vectors() {
-        let a = Tuple::vector(1.0, 2.0, 3.0);
-        let b = Tuple::vector(2.0, 3.0, 4.0);
+        let a = Vector::new(1.0, 2.0, 3.0);
+        let b = Vector::new(2.0, 3.0, 4.0);
 
-        assert_eq!(a.cross(b), Tuple::vector(-1.0, 2.0, -1.0));
-        assert_eq!(b.cross(a), Tuple::vector(1.0, -2.0, 1.0));
+        assert_eq!(a.cross(b), Vector::new(-1.0, 2.0, -1.0));
+        assert_eq!(b.cross(a), Vector::new(1.0, -2.0, 1.0));
     }
 
     #[test]
     fn reflecting_a_vector_approacing_at_45_deg() {
-        let v = Tuple::vector(1.0, -1.0, 0.0);
-        let n = Tuple::vector(0.0, 1.0, 0.0);
+        let v = Vector::new(1.0, -1.0, 0.0);
+        let n = Vector::new(0.0, 1.0, 0.0);
 
         let r = v.reflect(n);
 
-        assert_eq!(r, Tuple::vector(1.0, 1.0, 0.0));
+        assert_eq!(r, Vector::new(1.0, 1.0, 0.0));
     }
 
     #[test]
     fn reflecting_a_vector_off_a_slanted_surface() {
-        let v = Tuple::vector(0.0, -1.0, 0.0);
-        let n = Tuple::vector(sqrt_n_over_n(2), sqrt_n_over_n(2), 0.0);
+        let v = Vector::new(0.0, -1.0, 0.0);
+        let n = Vector::new(sqrt_n_over_n(2), sqrt_n_over_n(2), 0.0);
 
         let r = v.reflect(n);
 
-        assert_eq!(r, Tuple::vector(1.0, 0.0, 0.0));
+        assert_eq!(r, Vector::new(1.0, 0.0, 0.0));
+    }
+
+    #[test]
+    fn converting_between_points_and_vectors() {
+        let p = Point::new(1.0, 2.0, 3.0);
+
+        assert_eq!(p.to_vector(), Vector::new(1.0, 2.0, 3.0));
+        assert_eq!(p.to_vector().to_point(), p);
+        assert!(float_eq(p.to_vector()[3], 0.0));
+    }
+
+    #[test]
+    fn componentwise_minimum_and_maximum() {
+        let a = Point::new(1.0, -2.0, 3.0);
+        let b = Point::new(-1.0, 2.0, 5.0);
+
+        assert_eq!(a.min(b), Point::new(-1.0, -2.0, 3.0));
+        assert_eq!(a.max(b), Point::new(1.0, 2.0, 5.0));
+    }
+
+    #[test]
+    fn componentwise_absolute_value() {
+        let v = Vector::new(-1.0, 2.0, -3.0);
+
+        assert_eq!(v.abs(), Vector::new(1.0, 2.0, 3.0));
+    }
+
+    #[test]
+    fn clamping_a_point() {
+        let p = Point::new(-2.0, 0.5, 7.0);
+
+        let clamped = p.clamp(Point::new(-1.0, -1.0, -1.0), Point::new(1.0, 1.0, 1.0));
+
+        assert_eq!(clamped, Point::new(-1.0, 0.5, 1.0));
+    }
+
+    #[test]
+    fn interpolating_between_points() {
+        let a = Point::new(0.0, 2.0, -4.0);
+        let b = Point::new(10.0, 4.0, 4.0);
+
+        assert_eq!(a.lerp(b, 0.0), a);
+        assert_eq!(a.lerp(b, 0.25), Point::new(2.5, 2.5, -2.0));
+        assert_eq!(a.lerp(b, 1.0), b);
+        assert!(float_eq(a.lerp(b, 0.25)[3], 1.0));
+    }
+
+    #[test]
+    fn multiplying_vectors_component_by_component() {
+        let a = Vector::new(1.0, 2.0, 3.0);
+        let b = Vector::new(-2.0, 0.5, 4.0);
+
+        assert_eq!(a * b, Vector::new(-2.0, 1.0, 12.0));
+    }
+
+    #[test]
+    fn indexing_a_point() {
+        let p = Point::new(4.0, -4.0, 3.0);
+
+        assert!(float_eq(p[0], 4.0));
+        assert!(float_eq(p[1], -4.0));
+        assert!(float_eq(p[2], 3.0));
+        assert!(float_eq(p[3], 1.0));
+    }
+
+    #[test]
+    #[should_panic]
+    fn indexing_a_point_out_of_range() {
+        let _ = Point::new(4.0, -4.0, 3.0)[4];
     }
 }
@@ -0,0 +1,335 @@
+use std::{
+    collections::HashMap,
+    fmt::{self, Display},
+};
+
+use uuid::Uuid;
+
+use crate::{
+    color,
+    ray::{Ray, RayKind},
+    shapes::Shape,
+    tuple::{Point, Vector},
+    world::World,
+    EPSILON,
+};
+
+/// Materials whose ambient, diffuse and specular add up to more than this
+/// can reflect several times the light that falls on them.
+pub const BRIGHTNESS_LIMIT: f32 = 3.0;
+
+/// Something about a scene that's probably a mistake, most often one that
+/// leaves the render black or missing objects.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum SceneWarning {
+    NoObjects,
+    DarkLight,
+    /// The light sits inside a closed object, so nothing outside it is lit.
+    LightInside {
+        object: Uuid,
+    },
+    /// A transform that can't be inverted, such as a scale of zero, so rays
+    /// can't be taken into the object's space.
+    SingularTransform {
+        object: Uuid,
+    },
+    OverbrightMaterial {
+        object: Uuid,
+        total: f32,
+    },
+    EmptyGroup {
+        object: Uuid,
+    },
+    /// An object lying wholly beneath an upward facing plane.
+    BelowFloor {
+        object: Uuid,
+        floor: Uuid,
+    },
+}
+
+impl Display for SceneWarning {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            SceneWarning::NoObjects => write!(f, "the world has no objects"),
+            SceneWarning::DarkLight => write!(f, "the light has no intensity"),
+            SceneWarning::LightInside { object } => {
+                write!(f, "the light is inside object {}", object)
+            }
+            SceneWarning::SingularTransform { object } => {
+                write!(
+                    f,
+                    "object {} has a transform that can't be inverted",
+                    object
+                )
+            }
+            SceneWarning::OverbrightMaterial { object, total } => write!(
+                f,
+                "object {} has ambient, diffuse and specular adding up to {}",
+                object, total
+            ),
+            SceneWarning::EmptyGroup { object } => write!(f, "group {} is empty", object),
+            SceneWarning::BelowFloor { object, floor } => {
+                write!(f, "object {} is entirely below floor {}", object, floor)
+            }
+        }
+    }
+}
+
+/// Everything in `world` that looks like a mistake, scene-wide warnings
+/// first and then each object's in the order they were added.
+pub fn validate(world: &World) -> Vec<SceneWarning> {
+    let mut warnings = vec![];
+    if world.objects().is_empty() {
+        warnings.push(SceneWarning::NoObjects);
+    }
+    if world.light().intensity == color::BLACK {
+        warnings.push(SceneWarning::DarkLight);
+    }
+    warnings.extend(
+        enclosing_objects(world, world.light().position)
+            .into_iter()
+            .map(|object| SceneWarning::LightInside { object }),
+    );
+    for object in world.objects() {
+        check_object(object.as_ref(), &mut warnings);
+    }
+    check_floors(world, &mut warnings);
+    warnings
+}
+
+/// The closed objects around `point` that would stop a shadow ray. A ray
+/// from a point inside one crosses its surface an odd number of times going
+/// each way.
+fn enclosing_objects(world: &World, point: Point) -> Vec<Uuid> {
+    let ray = Ray::new(point, Vector::new(0.267, 0.802, 0.535)).kind(RayKind::Shadow);
+    let mut crossings: HashMap<Uuid, (usize, usize)> = HashMap::new();
+    let mut order = vec![];
+    for i in world.intersect(ray).iter() {
+        let (ahead, behind) = crossings.entry(i.object.id()).or_insert_with(|| {
+            order.push(i.object.id());
+            (0, 0)
+        });
+        if i.t > 0.0 {
+            *ahead += 1;
+        } else {
+            *behind += 1;
+        }
+    }
+
+    order
+        .into_iter()
+        .filter(|id| {
+            let (ahead, behind) = crossings[id];
+            ahead % 2 == 1 && behind % 2 == 1
+        })
+        .collect()
+}
+
+fn check_object(object: &dyn Shape, warnings: &mut Vec<SceneWarning>) {
+    let id = object.id();
+    let determinant = object.transform().mat().determinant();
+    if determinant == 0.0 || !determinant.is_finite() {
+        warnings.push(SceneWarning::SingularTransform { object: id });
+    }
+
+    let material = object.material();
+    let total = material.ambient + material.diffuse + material.specular;
+    if !object.inherits_material() && total > BRIGHTNESS_LIMIT {
+        warnings.push(SceneWarning::OverbrightMaterial { object: id, total });
+    }
+
+    if object.type_name() == "Group" && object.children().is_empty() {
+        warnings.push(SceneWarning::EmptyGroup { object: id });
+    }
+
+    for child in object.children() {
+        check_object(child.as_ref(), warnings);
+    }
+}
+
+/// Warns about top level objects hidden beneath any top level plane that
+/// faces straight up.
+fn check_floors(world: &World, warnings: &mut Vec<SceneWarning>) {
+    let floors = world
+        .objects()
+        .iter()
+        .filter(|o| o.type_name() == "Plane")
+        .filter(|o| o.normal_at(Point::new(0.0, 0.0, 0.0), None).y() > 1.0 - EPSILON)
+        .map(|o| (o.id(), (*o.transform() * Point::new(0.0, 0.0, 0.0)).y()))
+        .collect::<Vec<_>>();
+
+    for object in world.objects() {
+        let bounds = object.parent_space_bounds();
+        if bounds.is_empty() || !bounds.is_finite() {
+            continue;
+        }
+        if let Some(&(floor, _)) = floors
+            .iter()
+            .find(|&&(floor, height)| floor != object.id() && bounds.max.y() < height - EPSILON)
+        {
+            warnings.push(SceneWarning::BelowFloor {
+                object: object.id(),
+                floor,
+            });
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::f32::consts::PI;
+
+    use crate::{
+        lights::PointLight,
+        materials::Material,
+        shapes::{group::Group, plane::Plane, sphere::Sphere, triangle::Triangle, ShapeBuilder},
+        transformations::Transform,
+    };
+
+    use super::*;
+
+    fn lit_world() -> World {
+        World::new(PointLight::new(
+            Point::new(-10.0, 10.0, -10.0),
+            color::WHITE,
+        ))
+    }
+
+    #[test]
+    fn the_default_world_has_no_warnings() {
+        assert!(World::default().validate().is_empty());
+    }
+
+    #[test]
+    fn an_empty_world_with_a_dark_light() {
+        let w = World::new(PointLight::default());
+
+        assert_eq!(
+            w.validate(),
+            vec![SceneWarning::NoObjects, SceneWarning::DarkLight]
+        );
+    }
+
+    #[test]
+    fn a_light_inside_a_sphere() {
+        let w =
+            World::default().light_source(PointLight::new(Point::new(0.0, 0.0, 0.0), color::WHITE));
+
+        assert_eq!(
+            w.validate(),
+            vec![
+                SceneWarning::LightInside {
+                    object: w.objects()[0].id()
+                },
+                SceneWarning::LightInside {
+                    object: w.objects()[1].id()
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn a_light_in_front_of_a_single_triangle_isnt_inside_it() {
+        let w = lit_world().object(Box::new(
+            Triangle::new(
+                Point::new(0.0, 0.0, 0.0),
+                Point::new(0.0, 100.0, 0.0),
+                Point::new(0.0, 0.0, 100.0),
+            )
+            .with_transform(Transform::translation(-5.0, 0.0, -15.0)),
+        ));
+
+        assert!(w.validate().is_empty());
+    }
+
+    #[test]
+    fn a_zero_scale_makes_a_singular_transform() {
+        let mut g = Group::new();
+        g.add_child(Box::new(
+            Sphere::default().with_transform(Transform::scaling(1.0, 0.0, 1.0)),
+        ));
+        let w = lit_world().object(Box::new(g));
+
+        assert_eq!(
+            w.validate(),
+            vec![SceneWarning::SingularTransform {
+                object: w.objects()[0].children()[0].id()
+            }]
+        );
+    }
+
+    #[test]
+    fn an_overbright_material() {
+        let w = lit_world()
+            .object(Box::new(Sphere::default().with_material(
+                Material::default().ambient(1.0).diffuse(1.5).specular(1.0),
+            )));
+
+        assert_eq!(
+            w.validate(),
+            vec![SceneWarning::OverbrightMaterial {
+                object: w.objects()[0].id(),
+                total: 3.5
+            }]
+        );
+    }
+
+    #[test]
+    fn an_empty_group() {
+        let w = lit_world().object(Box::new(Group::new()));
+
+        assert_eq!(
+            w.validate(),
+            vec![SceneWarning::EmptyGroup {
+                object: w.objects()[0].id()
+            }]
+        );
+    }
+
+    #[test]
+    fn an_object_below_the_floor() {
+        let w = lit_world()
+            .object(Box::new(Plane::default()))
+            .object(Box::new(
+                Sphere::default().with_transform(Transform::translation(0.0, -2.0, 0.0)),
+            ))
+            .object(Box::new(
+                Sphere::default().with_transform(Transform::translation(0.0, -0.5, 0.0)),
+            ));
+
+        assert_eq!(
+            w.validate(),
+            vec![SceneWarning::BelowFloor {
+                object: w.objects()[1].id(),
+                floor: w.objects()[0].id()
+            }]
+        );
+    }
+
+    #[test]
+    fn a_wall_isnt_a_floor() {
+        let w = lit_world()
+            .object(Box::new(
+                Plane::default().with_transform(Transform::rotation_x(PI / 2.0)),
+            ))
+            .object(Box::new(
+                Sphere::default().with_transform(Transform::translation(0.0, -2.0, 0.0)),
+            ));
+
+        assert!(w.validate().is_empty());
+    }
+
+    #[test]
+    fn warnings_read_as_sentences() {
+        let id = Uuid::nil();
+
+        assert_eq!(
+            SceneWarning::EmptyGroup { object: id }.to_string(),
+            format!("group {} is empty", id)
+        );
+        assert_eq!(
+            SceneWarning::DarkLight.to_string(),
+            "the light has no intensity"
+        );
+    }
+}
@@ -0,0 +1,167 @@
+use crate::{color::Color, ray::Ray, shapes::Shape, spectrum::Radiance};
+
+/// Haze filling the whole world. Light travelling a distance `d` through it
+/// keeps `exp(-density * d)` of its radiance and makes up the rest with
+/// the fog's color, so distant objects fade into it.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Fog {
+    pub density: f32,
+    pub color: Color,
+}
+
+impl Fog {
+    pub fn new(density: f32, color: Color) -> Self {
+        Self { density, color }
+    }
+
+    /// `radiance` as seen from `distance` away through the fog. Fog with no
+    /// density changes nothing, even over the infinite distance to the
+    /// background.
+    pub fn apply<R: Radiance>(&self, radiance: R, distance: f32) -> R {
+        if self.density <= 0.0 {
+            return radiance;
+        }
+        blend(radiance, self.color, self.density * distance)
+    }
+}
+
+/// A region of fog bounded by a closed shape, like a cloud or a beam of
+/// light through dust. The boundary itself is never drawn.
+#[derive(Debug)]
+pub struct Volume {
+    pub boundary: Box<dyn Shape>,
+    pub density: f32,
+    pub color: Color,
+}
+
+impl Volume {
+    pub fn new(boundary: Box<dyn Shape>, density: f32, color: Color) -> Self {
+        Self {
+            boundary,
+            density,
+            color,
+        }
+    }
+
+    /// How far `ray` travels inside the volume before reaching `distance`.
+    pub fn depth_along(&self, ray: Ray, distance: f32) -> f32 {
+        let mut xs = self.boundary.intersect(ray);
+        xs.sort();
+        xs.chunks(2)
+            .filter(|pair| pair.len() == 2)
+            .map(|pair| {
+                let enter = pair[0].t.max(0.0);
+                let exit = pair[1].t.min(distance);
+                (exit - enter).max(0.0)
+            })
+            .sum()
+    }
+
+    /// `radiance` arriving from `distance` along `ray`, after passing
+    /// through whatever part of the volume lies in between.
+    pub fn apply<R: Radiance>(&self, radiance: R, ray: Ray, distance: f32) -> R {
+        if self.density <= 0.0 {
+            return radiance;
+        }
+        blend(
+            radiance,
+            self.color,
+            self.density * self.depth_along(ray, distance),
+        )
+    }
+}
+
+fn blend<R: Radiance>(radiance: R, scattered: Color, optical_depth: f32) -> R {
+    if optical_depth.is_infinite() {
+        return R::from_rgb(scattered);
+    }
+
+    let transmittance = (-optical_depth).exp();
+    radiance * transmittance + R::from_rgb(scattered) * (1.0 - transmittance)
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{
+        color, float_eq,
+        shapes::{sphere::Sphere, ShapeBuilder},
+        transformations::Transform,
+        tuple::{Point, Vector},
+    };
+
+    use super::*;
+
+    fn grey() -> Color {
+        Color::new(0.5, 0.5, 0.5)
+    }
+
+    #[test]
+    fn fog_over_no_distance_changes_nothing() {
+        let fog = Fog::new(0.5, grey());
+
+        assert_eq!(fog.apply(color::WHITE, 0.0), color::WHITE);
+    }
+
+    #[test]
+    fn fog_fades_radiance_into_its_color() {
+        let fog = Fog::new(0.5, grey());
+
+        let c = fog.apply(color::WHITE, 2.0);
+
+        let kept = (-1.0_f32).exp();
+        assert_eq!(c, color::WHITE * kept + grey() * (1.0 - kept));
+    }
+
+    #[test]
+    fn nothing_shows_through_infinite_fog() {
+        let fog = Fog::new(0.01, grey());
+
+        assert_eq!(fog.apply(color::WHITE, f32::INFINITY), grey());
+    }
+
+    #[test]
+    fn fog_with_no_density_leaves_the_background_alone() {
+        let fog = Fog::new(0.0, grey());
+
+        assert_eq!(fog.apply(color::WHITE, f32::INFINITY), color::WHITE);
+    }
+
+    fn cloud() -> Volume {
+        Volume::new(
+            Box::new(Sphere::default().with_transform(Transform::scaling(2.0, 2.0, 2.0))),
+            1.0,
+            grey(),
+        )
+    }
+
+    macro_rules! depth_tests {
+        ($($name:ident: $value:expr,)*) => {
+            $(
+                #[test]
+                fn $name() {
+                    let (origin, distance, expected) = $value;
+                    let r = Ray::new(origin, Vector::new(0.0, 0.0, 1.0));
+
+                    assert!(float_eq(cloud().depth_along(r, distance), expected));
+                }
+            )*
+        }
+    }
+
+    depth_tests! {
+        a_ray_through_a_volume: (Point::new(0.0, 0.0, -5.0), f32::INFINITY, 4.0),
+        a_ray_stopped_inside_a_volume: (Point::new(0.0, 0.0, -5.0), 4.0, 1.0),
+        a_ray_starting_inside_a_volume: (Point::new(0.0, 0.0, 1.0), f32::INFINITY, 1.0),
+        a_ray_stopped_before_a_volume: (Point::new(0.0, 0.0, -5.0), 2.0, 0.0),
+        a_ray_missing_a_volume: (Point::new(3.0, 0.0, -5.0), f32::INFINITY, 0.0),
+    }
+
+    #[test]
+    fn a_volume_blends_in_its_color_by_depth() {
+        let r = Ray::new(Point::new(0.0, 0.0, -5.0), Vector::new(0.0, 0.0, 1.0));
+
+        let c = cloud().apply(color::BLACK, r, 4.0);
+
+        assert_eq!(c, grey() * (1.0 - (-1.0_f32).exp()));
+    }
+}
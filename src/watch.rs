@@ -0,0 +1,279 @@
+use std::{
+    fs,
+    path::{Path, PathBuf},
+    thread,
+    time::{Duration, Instant, SystemTime},
+};
+
+use crate::{
+    camera::Camera, canvas::Canvas, integrator::Integrator, resume::CancelToken, world::World,
+};
+
+/// Notices when a file changes by polling its modification time. A change
+/// is only reported once the file has stayed put for the debounce period,
+/// so an editor writing a scene in several steps triggers one re-render.
+#[derive(Clone, Debug)]
+pub struct FileWatcher {
+    path: PathBuf,
+    debounce: Duration,
+    seen: Option<SystemTime>,
+    pending: Option<(Option<SystemTime>, Instant)>,
+}
+
+impl FileWatcher {
+    pub fn new(path: impl Into<PathBuf>, debounce: Duration) -> Self {
+        let path = path.into();
+        Self {
+            seen: modified(&path),
+            path,
+            debounce,
+            pending: None,
+        }
+    }
+
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    /// Whether the file has changed and settled since the last change was
+    /// reported. Deleting the file counts as a change.
+    pub fn poll(&mut self) -> bool {
+        self.poll_at(Instant::now())
+    }
+
+    /// Blocks until `poll` reports a change, checking every `interval`.
+    pub fn wait(&mut self, interval: Duration) {
+        while !self.poll() {
+            thread::sleep(interval);
+        }
+    }
+
+    fn poll_at(&mut self, now: Instant) -> bool {
+        let current = modified(&self.path);
+        if current == self.seen {
+            self.pending = None;
+            return false;
+        }
+
+        match self.pending {
+            Some((pending, since)) if pending == current => {
+                if now.duration_since(since) < self.debounce {
+                    return false;
+                }
+                self.seen = current;
+                self.pending = None;
+                true
+            }
+            _ => {
+                self.pending = Some((current, now));
+                false
+            }
+        }
+    }
+}
+
+/// Renders the scene `load` reads from the watched file, then renders it
+/// again each time the file changes, handing every finished image to
+/// `show` until it returns `false`. A change part way through a render
+/// cancels it and starts over on the new scene. Where `load` can't read
+/// the file, say because it was saved half-written, nothing is rendered
+/// until the next change.
+pub fn render_on_change(
+    mut watcher: FileWatcher,
+    interval: Duration,
+    integrator: &dyn Integrator,
+    mut load: impl FnMut(&Path) -> Option<(World, Camera)>,
+    mut show: impl FnMut(Canvas) -> bool,
+) {
+    loop {
+        let mut changed = false;
+        if let Some((world, camera)) = load(watcher.path()) {
+            let token = CancelToken::new();
+            let finished = CancelToken::new();
+            let render = thread::scope(|scope| {
+                let watching = scope.spawn(|| {
+                    while !finished.is_cancelled() {
+                        if watcher.poll() {
+                            token.cancel();
+                            return true;
+                        }
+                        thread::sleep(interval);
+                    }
+                    false
+                });
+                let render = camera.render_cancellable(&world, integrator, &token);
+                finished.cancel();
+                changed = watching.join().unwrap();
+                render
+            });
+            if let Ok(canvas) = render {
+                if !show(canvas) {
+                    return;
+                }
+            }
+        }
+        if !changed {
+            watcher.wait(interval);
+        }
+    }
+}
+
+fn modified(path: &Path) -> Option<SystemTime> {
+    fs::metadata(path).and_then(|m| m.modified()).ok()
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{
+        fs::File,
+        sync::atomic::{AtomicBool, Ordering},
+    };
+
+    use uuid::Uuid;
+
+    use crate::{color::Color, integrator::Whitted, ray::Ray};
+
+    use super::*;
+
+    const DEBOUNCE: Duration = Duration::from_millis(100);
+
+    fn scene_file() -> PathBuf {
+        let path = std::env::temp_dir().join(format!("scene-{}.yaml", Uuid::new_v4()));
+        fs::write(&path, "- add: camera").unwrap();
+        path
+    }
+
+    fn touch(path: &Path, seconds: u64) {
+        File::options()
+            .write(true)
+            .open(path)
+            .unwrap()
+            .set_modified(SystemTime::UNIX_EPOCH + Duration::from_secs(seconds))
+            .unwrap();
+    }
+
+    #[test]
+    fn an_untouched_file_has_not_changed() {
+        let path = scene_file();
+        let mut watcher = FileWatcher::new(&path, DEBOUNCE);
+
+        assert!(!watcher.poll());
+
+        fs::remove_file(path).unwrap();
+    }
+
+    #[test]
+    fn a_change_is_reported_once_it_settles() {
+        let path = scene_file();
+        let mut watcher = FileWatcher::new(&path, DEBOUNCE);
+        let start = Instant::now();
+
+        touch(&path, 1_000);
+
+        assert!(!watcher.poll_at(start));
+        assert!(!watcher.poll_at(start + DEBOUNCE / 2));
+        assert!(watcher.poll_at(start + DEBOUNCE));
+        assert!(!watcher.poll_at(start + DEBOUNCE * 2));
+
+        fs::remove_file(path).unwrap();
+    }
+
+    #[test]
+    fn further_changes_restart_the_debounce() {
+        let path = scene_file();
+        let mut watcher = FileWatcher::new(&path, DEBOUNCE);
+        let start = Instant::now();
+
+        touch(&path, 1_000);
+        assert!(!watcher.poll_at(start));
+        touch(&path, 2_000);
+        assert!(!watcher.poll_at(start + DEBOUNCE));
+
+        assert!(watcher.poll_at(start + DEBOUNCE * 2));
+
+        fs::remove_file(path).unwrap();
+    }
+
+    #[test]
+    fn deleting_the_file_is_a_change() {
+        let path = scene_file();
+        let mut watcher = FileWatcher::new(&path, DEBOUNCE);
+        let start = Instant::now();
+
+        fs::remove_file(&path).unwrap();
+
+        assert!(!watcher.poll_at(start));
+        assert!(watcher.poll_at(start + DEBOUNCE));
+    }
+
+    #[test]
+    fn saving_the_scene_renders_it_again() {
+        let path = scene_file();
+        let integrator = Whitted::new(1);
+        let mut loads = 0;
+        let mut shown = 0;
+
+        render_on_change(
+            FileWatcher::new(&path, Duration::from_millis(10)),
+            Duration::from_millis(1),
+            &integrator,
+            |_| {
+                loads += 1;
+                Some((World::default(), Camera::new(4, 4, 1.0)))
+            },
+            |_| {
+                shown += 1;
+                touch(&path, 1_000);
+                shown < 2
+            },
+        );
+
+        assert_eq!((loads, shown), (2, 2));
+        fs::remove_file(path).unwrap();
+    }
+
+    /// Saves the scene file the first time it's asked for a color, then
+    /// takes long enough over every pixel for the watcher to notice.
+    struct SavesMidRender {
+        path: PathBuf,
+        saved: AtomicBool,
+    }
+
+    impl Integrator for SavesMidRender {
+        fn color_at(&self, _world: &World, _ray: Ray) -> Color {
+            if !self.saved.swap(true, Ordering::Relaxed) {
+                touch(&self.path, 1_000);
+            }
+            thread::sleep(Duration::from_millis(1));
+            Color::new(0.0, 0.0, 0.0)
+        }
+    }
+
+    #[test]
+    fn a_change_mid_render_starts_it_over() {
+        let path = scene_file();
+        let integrator = SavesMidRender {
+            path: path.clone(),
+            saved: AtomicBool::new(false),
+        };
+        let mut loads = 0;
+        let mut shown = 0;
+
+        render_on_change(
+            FileWatcher::new(&path, Duration::from_millis(10)),
+            Duration::from_millis(1),
+            &integrator,
+            |_| {
+                loads += 1;
+                Some((World::default(), Camera::new(10, 10, 1.0)))
+            },
+            |_| {
+                shown += 1;
+                false
+            },
+        );
+
+        assert_eq!((loads, shown), (2, 1));
+        fs::remove_file(path).unwrap();
+    }
+}
@@ -1,19 +1,81 @@
+use std::{borrow::Cow, collections::HashMap, f32::consts::PI, sync::OnceLock};
+
+use uuid::Uuid;
+
 use crate::{
+    animation::Animation,
+    bvh::Bvh,
+    clipping::ClipPlane,
     color::{self, Color},
-    intersection::{Computations, Intersection},
-    lights::PointLight,
+    environment::Environment,
+    intersection::{Computations, Intersection, Intersections},
+    kdtree::KdTree,
+    lights::{PointLight, Portal},
     materials::Material,
-    ray::Ray,
-    shapes::{sphere::Sphere, Shape, ShapeBuilder},
-    transformations::Transform,
-    tuple::Tuple,
+    photon_map::{self, Photon, PhotonMap, PhotonSettings},
+    ray::{DepthBudget, Ray, RayKind, Roulette},
+    shapes::{group::Group, sphere::Sphere, Shape, ShapeBuilder},
+    snapshot::WorldSnapshot,
+    spectrum::{Radiance, Spectra},
+    stats,
+    trace::RayTrace,
+    transformations::{Transform, IDENTITY},
+    tuple::{Point, Vector},
+    validation::{self, SceneWarning},
+    volume::{Fog, Volume},
     EPSILON,
 };
 
+/// The kinds of spatial index a world can build. Which is faster depends
+/// on the scene; the index counts in `RenderStats` help tell.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum IndexKind {
+    Bvh,
+    KdTree,
+}
+
+#[derive(Debug)]
+enum SpatialIndex {
+    Bvh(Bvh),
+    KdTree(KdTree),
+}
+
+impl SpatialIndex {
+    fn candidates(&self, ray: Ray) -> Vec<usize> {
+        match self {
+            SpatialIndex::Bvh(bvh) => bvh.candidates(ray),
+            SpatialIndex::KdTree(tree) => tree.candidates(ray),
+        }
+    }
+}
+
 #[derive(Debug)]
 pub struct World {
     light_source: PointLight,
     objects: Vec<Box<dyn Shape>>,
+    photon_map: Option<PhotonMap>,
+    emitter_samples: usize,
+    environment: Box<dyn Environment>,
+    portals: Vec<Portal>,
+    index_kind: Option<IndexKind>,
+    /// The index over the objects as they stand, built the first time a
+    /// ray needs it after they last changed.
+    index: OnceLock<SpatialIndex>,
+    /// Where every emissive shape sits, as for `group_paths`, with the
+    /// transforms of the groups it's nested in, found the first time a
+    /// surface is lit by them after the objects last changed.
+    emitters: OnceLock<Vec<(Vec<usize>, Transform)>>,
+    clip_planes: Vec<ClipPlane>,
+    colored_shadows: bool,
+    min_contribution: f32,
+    roulette: Option<Roulette>,
+    fog: Option<Fog>,
+    volumes: Vec<Volume>,
+    animations: Vec<Animation>,
+    /// Where each group sits, as the index of the child taken at each level
+    /// down to it, so a shape's groups are found without searching the
+    /// scene every time a hit is shaded.
+    group_paths: HashMap<Uuid, Vec<usize>>,
 }
 
 impl World {
@@ -21,6 +83,21 @@ impl World {
         World {
             light_source: light,
             objects: vec![],
+            photon_map: None,
+            emitter_samples: 16,
+            environment: Box::new(color::BLACK),
+            portals: vec![],
+            index_kind: None,
+            index: OnceLock::new(),
+            emitters: OnceLock::new(),
+            clip_planes: vec![],
+            colored_shadows: false,
+            min_contribution: 0.0,
+            roulette: None,
+            fog: None,
+            volumes: vec![],
+            animations: vec![],
+            group_paths: HashMap::new(),
         }
     }
 
@@ -31,575 +108,2944 @@ impl World {
         }
     }
 
-    pub fn object(self, object: Box<dyn Shape>) -> Self {
-        let mut objects = self.objects;
-        objects.push(object);
+    pub fn emitter_samples(self, emitter_samples: usize) -> Self {
+        Self {
+            emitter_samples,
+            ..self
+        }
+    }
 
-        Self { objects, ..self }
+    /// Radiance arriving along rays that escape the scene.
+    /// A plain color seen wherever rays escape the scene.
+    pub fn background(self, background: Color) -> Self {
+        self.environment(background)
     }
 
-    pub fn color_at(&self, ray: Ray, remaining: u32) -> Color {
-        let intersections = self.intersect(ray);
-        if let Some(hit) = Intersection::hit(&intersections) {
-            let comps = hit.prepare_computations(ray, &intersections);
-            self.shade_hit(comps, remaining)
-        } else {
-            color::BLACK
+    /// What rays that escape the scene see, whether straight from the
+    /// camera or after bouncing off something.
+    pub fn environment(self, environment: impl Environment + 'static) -> Self {
+        Self {
+            environment: Box::new(environment),
+            ..self
         }
     }
 
-    pub fn intersect(&self, ray: Ray) -> Vec<Intersection> {
-        let mut vec = self
-            .objects
-            .iter()
-            .flat_map(|o| o.intersect(ray))
-            .collect::<Vec<Intersection>>();
+    pub fn portal(self, portal: Portal) -> Self {
+        let mut portals = self.portals;
+        portals.push(portal);
 
-        vec.sort_by(|a, b| a.t.partial_cmp(&b.t).unwrap());
-        vec
+        Self { portals, ..self }
     }
 
-    pub fn is_shadowed(&self, point: Tuple) -> bool {
-        let v = self.light_source.position - point;
-        let distance = v.magnitude();
-        let direction = v.normalize();
+    /// Cuts away everything on the far side of `plane`, shadows included.
+    pub fn clip_plane(self, plane: ClipPlane) -> Self {
+        let mut clip_planes = self.clip_planes;
+        clip_planes.push(plane);
 
-        let r = Ray::new(point, direction);
-        let intersections = self.intersect(r);
-
-        if let Some(h) = Intersection::hit(&intersections) {
-            h.t < distance
-        } else {
-            false
+        Self {
+            clip_planes,
+            ..self
         }
     }
 
-    pub fn shade_hit(&self, comps: Computations, remaining: u32) -> Color {
-        // TODO: try multiple light sources.  It will slow things down though
-        let shadowed = self.is_shadowed(comps.over_point);
+    /// Lets light through transparent objects onto whatever is behind them,
+    /// tinted by their color. By default, as in the book, anything in the
+    /// way casts a full shadow.
+    pub fn colored_shadows(self, colored_shadows: bool) -> Self {
+        Self {
+            colored_shadows,
+            ..self
+        }
+    }
 
-        let material = comps.object.material();
-        let surface = material.lighting(
-            comps.object,
-            self.light_source,
-            comps.over_point,
-            comps.eyev,
-            comps.normalv,
-            shadowed,
-        );
+    /// Stops following reflected and refracted rays once what they bring
+    /// back would be scaled below this, e.g. deep inside a stack of tinted
+    /// glass, however much depth budget is left. Zero, the default, follows
+    /// them as far as the budget allows.
+    pub fn min_contribution(self, min_contribution: f32) -> Self {
+        Self {
+            min_contribution,
+            ..self
+        }
+    }
 
-        let reflected = self.reflected_color(comps, remaining);
-        let refracted = self.refracted_color(comps, remaining);
+    /// Plays Russian roulette with faint reflected and refracted rays,
+    /// which trims render time without darkening the image on average,
+    /// though it adds some noise.
+    pub fn roulette(self, roulette: Roulette) -> Self {
+        Self {
+            roulette: Some(roulette),
+            ..self
+        }
+    }
 
-        if material.reflective > 0.0 && material.transparency > 0.0 {
-            let reflectance = comps.schlick();
-            surface + reflected * reflectance + refracted * (1.0 - reflectance)
-        } else {
-            surface + reflected + refracted
+    pub fn fog(self, fog: Fog) -> Self {
+        Self {
+            fog: Some(fog),
+            ..self
         }
     }
 
-    pub fn reflected_color(&self, comps: Computations, remaining: u32) -> Color {
-        if comps.object.material().reflective < EPSILON || remaining == 0 {
-            color::BLACK
-        } else {
-            let reflect_ray = Ray::new(comps.over_point, comps.reflectv);
-            let color = self.color_at(reflect_ray, remaining - 1);
+    pub fn volume(self, volume: Volume) -> Self {
+        let mut volumes = self.volumes;
+        volumes.push(volume);
 
-            color * comps.object.material().reflective
-        }
+        Self { volumes, ..self }
     }
 
-    pub fn refracted_color(&self, comps: Computations, remaining: u32) -> Color {
-        if comps.object.material().transparency <= EPSILON || remaining == 0 {
-            color::BLACK
-        } else {
-            let n_ratio = comps.n1 / comps.n2;
-            let cos_i = comps.eyev.dot(comps.normalv);
-            let sin2_t = n_ratio.powi(2) * (1.0 - cos_i.powi(2));
+    pub fn animation(self, animation: Animation) -> Self {
+        let mut animations = self.animations;
+        animations.push(animation);
 
-            if sin2_t > 1.0 {
-                color::BLACK
+        Self { animations, ..self }
+    }
+
+    /// Adds everything in `other` to this world, e.g. to furnish a room
+    /// from scenes built on their own. Other's light is handed back, as a
+    /// world only has one.
+    pub fn merge(self, other: World) -> (Self, PointLight) {
+        self.merge_with_transform(other, IDENTITY)
+    }
+
+    /// Like `merge`, with `transform` placing the whole of `other`. It's
+    /// pushed into other's top level objects, portals and volumes, and
+    /// animations of those objects are moved along with them. This world
+    /// keeps its own light, background, fog and clip planes, since there's
+    /// only one of each to go round, and other's light is handed back moved
+    /// to where `transform` puts it.
+    pub fn merge_with_transform(
+        mut self,
+        other: World,
+        transform: Transform,
+    ) -> (Self, PointLight) {
+        let mut light = other.light_source;
+        if light.parent.is_none() {
+            light.transform = transform * light.transform;
+        }
+        let top_level = other.objects.iter().map(|o| o.id()).collect::<Vec<_>>();
+        for mut object in other.objects {
+            object.set_transform(transform * *object.transform());
+            self.objects.push(object);
+        }
+        self.portals
+            .extend(other.portals.into_iter().map(|portal| Portal {
+                corner: transform * portal.corner,
+                uvec: transform * portal.uvec,
+                vvec: transform * portal.vvec,
+            }));
+        for mut volume in other.volumes {
+            volume
+                .boundary
+                .set_transform(transform * *volume.boundary.transform());
+            self.volumes.push(volume);
+        }
+        for animation in other.animations {
+            if top_level.contains(&animation.target) {
+                let target = animation.target;
+                self.animations.push(Animation::new(target, move |t| {
+                    transform * animation.transform_at(t)
+                }));
             } else {
-                let cos_t = (1.0 - sin2_t).sqrt();
-                let direction = comps.normalv * (n_ratio * cos_i - cos_t) - comps.eyev * n_ratio;
-                let refract_ray = Ray::new(comps.under_point, direction);
-                self.color_at(refract_ray, remaining - 1) * comps.object.material().transparency
+                self.animations.push(animation);
             }
         }
+
+        self.refresh_group_paths();
+        self.objects_changed();
+        (self, light)
     }
-}
 
-impl Default for World {
-    fn default() -> Self {
-        let sphere1 = Sphere::default().with_material(
-            Material::default()
-                .color(Color::new(0.8, 1.0, 0.6))
-                .diffuse(0.7)
-                .specular(0.2),
-        );
-        let sphere2 = Sphere::default().with_transform(Transform::scaling(0.5, 0.5, 0.5));
-        Self {
-            light_source: PointLight::new(
-                Tuple::point(-10.0, 10.0, -10.0),
-                Color::new(1.0, 1.0, 1.0),
-            ),
-            objects: vec![Box::new(sphere1), Box::new(sphere2)],
+    /// The world as it stands at time `t`, with every animated object moved
+    /// to where its animation puts it. The animations carry over, so each
+    /// frame can be posed from the last.
+    pub fn at_time(mut self, t: f32) -> Self {
+        for animation in &self.animations {
+            if let Some(object) = find_object_mut(&mut self.objects, animation.target) {
+                object.set_transform(animation.transform_at(t));
+            }
         }
+        self.objects_changed();
+        self
     }
-}
 
-#[cfg(test)]
-mod tests {
-    use std::f32::consts::SQRT_2;
+    pub fn object(self, object: Box<dyn Shape>) -> Self {
+        let mut objects = self.objects;
+        objects.push(object);
 
-    use crate::{
-        color, float_eq,
-        patterns::TestPattern,
-        shapes::{plane::Plane, ShapeBuilder},
-        test::sqrt_n_over_n,
-    };
+        let mut world = Self { objects, ..self };
+        let last = world.objects.len() - 1;
+        find_groups(
+            world.objects[last].as_ref(),
+            &mut vec![last],
+            &mut world.group_paths,
+        );
+        world.objects_changed();
+        world
+    }
 
-    use super::*;
+    pub fn portals(&self) -> &[Portal] {
+        &self.portals
+    }
 
-    #[test]
-    fn creating_a_world() {
-        let w = World::new(PointLight::default());
+    pub fn objects(&self) -> &[Box<dyn Shape>] {
+        &self.objects
+    }
 
-        assert!(w.objects.is_empty());
+    /// A description of the scene that can be compared with another's.
+    pub fn snapshot(&self) -> WorldSnapshot {
+        WorldSnapshot::from_world(self)
     }
 
-    #[test]
-    fn default_world() {
-        let light = PointLight::new(Tuple::point(-10.0, 10.0, -10.0), Color::new(1.0, 1.0, 1.0));
-        let s1 = Sphere::default().with_material(
-            Material::default()
-                .color(Color::new(0.8, 1.0, 0.6))
-                .diffuse(0.7)
-                .specular(0.2),
-        );
-        let s2 = Sphere::default().with_transform(Transform::scaling(0.5, 0.5, 0.5));
+    /// Anything in the scene that looks like a mistake, such as a light
+    /// shut inside an object or a transform that scales to nothing.
+    pub fn validate(&self) -> Vec<SceneWarning> {
+        validation::validate(self)
+    }
 
-        let w = World::default();
+    /// Changes the object at `index` in place, keeping the spatial index (if
+    /// any) in step with where it now sits.
+    pub fn update_object(&mut self, index: usize, update: impl FnOnce(&mut dyn Shape)) {
+        update(self.objects[index].as_mut());
+        self.refresh_group_paths();
+        self.objects_changed();
+    }
 
-        assert_eq!(w.light_source, light);
-        assert_eq!(w.objects[0].material(), s1.material());
-        assert_eq!(w.objects[1].material(), s2.material());
-        assert_eq!(w.objects[0].transform(), s1.transform());
-        assert_eq!(w.objects[1].transform(), s2.transform());
+    /// Builds a bounding volume hierarchy over the objects, so `intersect`
+    /// only tests the ones a ray might hit. Adding or changing objects
+    /// through the world afterwards only marks it out of date, and it's
+    /// built again once, when the next ray is traced.
+    pub fn build_index(&mut self) {
+        self.build_index_of(IndexKind::Bvh);
     }
 
-    #[test]
-    fn intersect_a_world_with_a_ray() {
-        let w = World::default();
-        let r = Ray::new(Tuple::point(0.0, 0.0, -5.0), Tuple::vector(0.0, 0.0, 1.0));
+    /// `build_index`, but with the chosen kind of index.
+    pub fn build_index_of(&mut self, kind: IndexKind) {
+        self.index_kind = Some(kind);
+        self.objects_changed();
+        self.spatial_index();
+    }
 
-        let xs = w.intersect(r);
+    /// The spatial index, built now if the objects changed since it last
+    /// was.
+    fn spatial_index(&self) -> Option<&SpatialIndex> {
+        let kind = self.index_kind?;
+        Some(self.index.get_or_init(|| {
+            stats::time_phase("index", || {
+                let bounds = self
+                    .objects
+                    .iter()
+                    .map(|o| o.parent_space_bounds())
+                    .collect::<Vec<_>>();
+                match kind {
+                    IndexKind::Bvh => SpatialIndex::Bvh(Bvh::build(&bounds)),
+                    IndexKind::KdTree => SpatialIndex::KdTree(KdTree::build(&bounds)),
+                }
+            })
+        }))
+    }
 
-        assert_eq!(xs.len(), 4);
-        assert!(float_eq(xs[0].t, 4.0));
-        assert!(float_eq(xs[1].t, 4.5));
-        assert!(float_eq(xs[2].t, 5.5));
-        assert!(float_eq(xs[3].t, 6.0));
+    /// Puts anything random in the world onto the stream for one pass over
+    /// a pixel, as renderers do before tracing it.
+    pub fn start_pixel(&self, x: usize, y: usize, pass: u32) {
+        if let Some(roulette) = &self.roulette {
+            roulette.start_pixel(x, y, pass);
+        }
     }
 
-    #[test]
-    fn shading_an_intersection() {
-        let w = World::default();
-        let r = Ray::new(Tuple::point(0.0, 0.0, -5.0), Tuple::vector(0.0, 0.0, 1.0));
-        let shape = w.objects[0].as_ref();
-        let i = Intersection::new(4.0, shape);
+    /// The shape with the given id, searching inside groups.
+    pub fn find_object(&self, id: Uuid) -> Option<&dyn Shape> {
+        fn find(objects: &[Box<dyn Shape>], id: Uuid) -> Option<&dyn Shape> {
+            objects.iter().find_map(|o| {
+                if o.id() == id {
+                    Some(o.as_ref())
+                } else {
+                    find(o.children(), id)
+                }
+            })
+        }
 
-        let comps = i.prepare_computations(r, &[i]);
-        let c = w.shade_hit(comps, 3);
+        find(&self.objects, id)
+    }
 
-        assert_eq!(c, Color::new(0.38066, 0.47583, 0.2855));
+    /// Every shape, groups included, that has its own tag `key` set to
+    /// `value`.
+    pub fn find_tagged(&self, key: &str, value: &str) -> Vec<&dyn Shape> {
+        fn find<'a>(
+            objects: &'a [Box<dyn Shape>],
+            key: &str,
+            value: &str,
+            found: &mut Vec<&'a dyn Shape>,
+        ) {
+            for o in objects {
+                if o.tag(key) == Some(value) {
+                    found.push(o.as_ref());
+                }
+                find(o.children(), key, value, found);
+            }
+        }
+
+        let mut found = vec![];
+        find(&self.objects, key, value, &mut found);
+        found
     }
 
-    #[test]
-    fn shading_an_intersection_from_the_inside() {
-        let w = World {
-            light_source: PointLight::new(Tuple::point(0.0, 0.25, 0.0), Color::new(1.0, 1.0, 1.0)),
-            ..World::default()
-        };
-        let r = Ray::new(Tuple::point(0.0, 0.0, 0.0), Tuple::vector(0.0, 0.0, 1.0));
-        let shape = w.objects[1].as_ref();
-        let i = Intersection::new(0.5, shape);
+    /// The shape's tag `key`, or failing that the nearest group's up the
+    /// chain that has one.
+    pub fn tag_of<'a>(&'a self, object: &'a dyn Shape, key: &str) -> Option<&'a str> {
+        object.tag(key).or_else(|| {
+            object
+                .parent()
+                .and_then(|id| self.group(id))
+                .and_then(|parent| self.tag_of(parent, key))
+        })
+    }
 
-        let comps = i.prepare_computations(r, &[i]);
-        let c = w.shade_hit(comps, 3);
+    /// The light in world space, moved by its own transform and those of the
+    /// groups it's parented to.
+    pub fn light(&self) -> PointLight {
+        let mut position = self.light_source.transformed_position();
+        let mut parent = self.light_source.parent;
+        while let Some(group) = parent.and_then(|id| self.group(id)) {
+            position = *group.transform() * position;
+            parent = group.parent();
+        }
 
-        assert_eq!(c, Color::new(0.90498, 0.90498, 0.90498));
+        PointLight {
+            position,
+            transform: Transform::default(),
+            parent: None,
+            ..self.light_source
+        }
     }
 
-    #[test]
-    fn color_when_ray_misses() {
-        let w = World::default();
-        let r = Ray::new(Tuple::point(0.0, 0.0, -5.0), Tuple::vector(0.0, 1.0, 0.0));
+    /// How much of the light reaches `comps`: none at all if the surface
+    /// is outside the light's groups, otherwise whatever gets past the
+    /// shadow casters.
+    pub fn light_reaching(&self, comps: &Computations) -> Color {
+        if !self.light_source.illuminates(comps.material) {
+            return color::BLACK;
+        }
+        let (ray, casters) = self.shadow_casters_of(comps);
+        self.shadow_of(ray, &casters)
+    }
 
-        let c = w.color_at(r, 3);
+    /// The shadow ray from `comps` to the light and the shapes along it
+    /// that shadow the hit.
+    pub fn shadow_casters_of(&self, comps: &Computations) -> (Ray, Vec<Intersection<'_>>) {
+        // Light wrapping round a translucent shape past its terminator
+        // reaches it through the shape itself, which mustn't shadow it.
+        let id = comps.object.id();
+        let through = (comps.material.translucency > 0.0).then_some(id);
+        self.shadow_casters(comps.over_point, Some(id), through)
+    }
 
-        assert_eq!(c, color::BLACK);
+    /// The material a shape is shaded with. Shapes that inherit take it from
+    /// the nearest group up the chain that doesn't inherit in turn.
+    pub fn material_of<'a>(&'a self, object: &'a dyn Shape) -> &'a Material {
+        match object
+            .parent()
+            .filter(|_| object.inherits_material())
+            .and_then(|id| self.group(id))
+        {
+            Some(parent) => self.material_of(parent),
+            None => object.material(),
+        }
     }
 
-    #[test]
-    fn color_when_a_ray_hits() {
-        let w = World::default();
-        let r = Ray::new(Tuple::point(0.0, 0.0, -5.0), Tuple::vector(0.0, 0.0, 1.0));
+    /// `hit.prepare_computations`, going by the materials shapes inherit
+    /// from their groups.
+    pub fn prepare_computations<'a>(
+        &'a self,
+        hit: &Intersection<'a>,
+        ray: Ray,
+        intersections: &[Intersection<'a>],
+    ) -> Computations<'a> {
+        hit.prepare_computations_with(ray, intersections, |object| self.material_of(object))
+    }
 
-        let c = w.color_at(r, 3);
+    /// The material a hit is shaded with: the object's, with its patterns
+    /// following any groups the object is nested in and averaged over the
+    /// ray's footprint, unless the surface carries its own color there,
+    /// which then replaces the material's color and pattern.
+    pub fn shading_material<'a>(&'a self, comps: &Computations<'a>) -> Cow<'a, Material> {
+        let material = comps.material.at_hit(&comps.groups, comps.footprint);
+        match comps.surface_color {
+            Some(color) => Cow::Owned(Material {
+                color,
+                pattern: None,
+                ..material.into_owned()
+            }),
+            None => material,
+        }
+    }
 
-        assert_eq!(c, Color::new(0.38066, 0.47583, 0.2855));
+    pub fn is_indexed(&self) -> bool {
+        self.index_kind.is_some()
     }
 
-    #[test]
-    fn color_with_an_intersection_behind_ray() {
-        let outer = Sphere::default().with_material(
-            Material::default()
-                .color(Color::new(0.8, 1.0, 0.6))
-                .diffuse(0.7)
-                .specular(0.2)
-                .ambient(1.0),
-        );
-        let inner = Sphere::default()
-            .with_transform(Transform::scaling(0.5, 0.5, 0.5))
-            .with_material(Material::default().ambient(1.0));
-        let w = World::new(PointLight::new(
-            Tuple::point(-10.0, 10.0, -10.0),
-            Color::new(1.0, 1.0, 1.0),
-        ))
-        .object(Box::new(outer))
-        .object(Box::new(inner.clone()));
+    /// The kind of spatial index built, if any.
+    pub fn index_kind(&self) -> Option<IndexKind> {
+        self.index_kind
+    }
 
-        let r = Ray::new(Tuple::point(0.0, 0.0, 0.75), Tuple::vector(0.0, 0.0, -1.0));
+    /// Marks the index and the emitters found out of date after the
+    /// objects changed.
+    fn objects_changed(&mut self) {
+        self.index = OnceLock::new();
+        self.emitters = OnceLock::new();
+    }
 
-        let c = w.color_at(r, 3);
+    /// Finds every group again, after objects may have been moved about.
+    fn refresh_group_paths(&mut self) {
+        self.group_paths.clear();
+        for (i, object) in self.objects.iter().enumerate() {
+            find_groups(object.as_ref(), &mut vec![i], &mut self.group_paths);
+        }
+    }
 
-        assert_eq!(c, inner.material().color);
+    /// The group with the given id, if it's in the world.
+    fn group(&self, id: Uuid) -> Option<&dyn Shape> {
+        self.shape_at(self.group_paths.get(&id)?)
+            .filter(|group| group.id() == id)
     }
 
-    #[test]
-    fn no_shadow_when_nothing_is_collinear_with_point_and_light() {
-        let w = World::default();
-        let p = Tuple::point(0.0, 10.0, 0.0);
+    /// The shape found by taking the child at each index of `path` in turn.
+    fn shape_at(&self, path: &[usize]) -> Option<&dyn Shape> {
+        let (first, rest) = path.split_first()?;
+        rest.iter()
+            .try_fold(self.objects.get(*first)?.as_ref(), |group, &i| {
+                group.children().get(i).map(AsRef::as_ref)
+            })
+    }
 
-        assert!(!w.is_shadowed(p));
+    /// Every emissive shape, however deeply it's nested in groups, with the
+    /// transforms of those groups.
+    pub fn emitters(&self) -> impl Iterator<Item = (&dyn Shape, &Transform)> {
+        self.emitters
+            .get_or_init(|| {
+                let mut emitters = vec![];
+                for (i, object) in self.objects.iter().enumerate() {
+                    self.find_emitters(object.as_ref(), IDENTITY, &mut vec![i], &mut emitters);
+                }
+                emitters
+            })
+            .iter()
+            .filter_map(move |(path, groups)| Some((self.shape_at(path)?, groups)))
     }
 
-    #[test]
-    fn shadow_when_object_between_point_and_light() {
-        let w = World::default();
-        let p = Tuple::point(10.0, -10.0, 10.0);
+    fn find_emitters(
+        &self,
+        shape: &dyn Shape,
+        groups: Transform,
+        path: &mut Vec<usize>,
+        emitters: &mut Vec<(Vec<usize>, Transform)>,
+    ) {
+        let children = shape.children();
+        if children.is_empty() {
+            if self.material_of(shape).is_emissive() {
+                emitters.push((path.clone(), groups));
+            }
+            return;
+        }
+        let groups = groups * *shape.transform();
+        for (i, child) in children.iter().enumerate() {
+            path.push(i);
+            self.find_emitters(child.as_ref(), groups, path, emitters);
+            path.pop();
+        }
+    }
 
-        assert!(w.is_shadowed(p));
+    /// The color seen along `ray`, following at most `remaining` bounces.
+    pub fn color_at(&self, ray: Ray, remaining: impl Into<DepthBudget>) -> Color {
+        self.radiance_at(ray, remaining, Spectra::default())
     }
 
-    #[test]
-    fn no_shadow_when_object_behind_light() {
-        let w = World::default();
-        let p = Tuple::point(-20.0, 20.0, -20.0);
+    /// `color_at` with the light carried as `R`, such as a `Spectrum`, and
+    /// the curves in `spectra` taken in place of the colors they stand for.
+    pub fn radiance_at<R: Radiance>(
+        &self,
+        ray: Ray,
+        remaining: impl Into<DepthBudget>,
+        spectra: Spectra,
+    ) -> R {
+        let intersections = self.intersect(ray);
+        match intersections.hit() {
+            Some(hit) => {
+                let comps = self.prepare_computations(hit, ray, &intersections);
+                let radiance = self.shade_hit_as(comps, remaining, spectra);
+                self.arriving_from_hit(radiance, ray, &comps, hit.t)
+            }
+            None => self.arriving_from_background(ray),
+        }
+    }
 
-        assert!(!w.is_shadowed(p));
+    /// What's left of `radiance` leaving the hit `comps`, at `t` along
+    /// `ray`, by the time it gets back to the ray's origin.
+    pub fn arriving_from_hit<R: Radiance>(
+        &self,
+        radiance: R,
+        ray: Ray,
+        comps: &Computations,
+        t: f32,
+    ) -> R {
+        let distance = t * ray.direction.magnitude();
+        let radiance = radiance * R::from_rgb(self.transmittance(comps, distance));
+        self.through_the_air(radiance, ray, t)
     }
 
-    #[test]
-    fn no_shadow_when_object_behind_point() {
-        let w = World::default();
-        let p = Tuple::point(-2.0, 2.0, -2.0);
+    /// The background seen along `ray`, through whatever is in the air.
+    pub fn arriving_from_background<R: Radiance>(&self, ray: Ray) -> R {
+        let radiance = R::from_rgb(self.background_in(ray.direction));
+        self.through_the_air(radiance, ray, f32::INFINITY)
+    }
 
-        assert!(!w.is_shadowed(p));
+    /// Radiance from `distance` along `ray` after any volumes and fog in
+    /// between have dimmed it and scattered in their own color. Shadow
+    /// rays ignore both.
+    pub fn through_the_air<R: Radiance>(&self, color: R, ray: Ray, distance: f32) -> R {
+        let color = self
+            .volumes
+            .iter()
+            .fold(color, |color, volume| volume.apply(color, ray, distance));
+
+        match self.fog {
+            Some(fog) => fog.apply(color, distance),
+            None => color,
+        }
     }
 
-    #[test]
-    fn shade_hit_is_given_an_intersection_in_shadow() {
-        let w = World::new(
-            PointLight::default()
-                .position(0.0, 0.0, -10.0)
-                .intensity(1.0, 1.0, 1.0),
-        )
-        .object(Box::new(Sphere::default()))
-        .object(Box::new(
-            Sphere::default().with_transform(Transform::translation(0.0, 0.0, 10.0)),
-        ));
-        let r = Ray::default()
-            .origin(0.0, 0.0, 5.0)
-            .direction(0.0, 0.0, 1.0);
-        let i = Intersection::new(4.0, w.objects[1].as_ref());
+    /// How much of each channel survives crossing `distance` of whatever
+    /// the ray passed through to reach `comps`, by the Beer-Lambert law.
+    pub fn transmittance(&self, comps: &Computations, distance: f32) -> Color {
+        match comps.medium() {
+            Some(medium) => {
+                let absorption = self.material_of(medium).absorption;
+                Color::new(
+                    (-absorption.red() * distance).exp(),
+                    (-absorption.green() * distance).exp(),
+                    (-absorption.blue() * distance).exp(),
+                )
+            }
+            None => color::WHITE,
+        }
+    }
 
-        let comps = i.prepare_computations(r, &[i]);
-        let c = w.shade_hit(comps, 3);
+    /// Everything `color_at` does with `ray`, step by step.
+    pub fn debug_ray(&self, ray: Ray, remaining: impl Into<DepthBudget>) -> RayTrace {
+        RayTrace::new(self, ray, remaining)
+    }
 
-        assert_eq!(c, Color::new(0.1, 0.1, 0.1));
+    /// The environment seen looking along `direction`.
+    pub fn background_in(&self, direction: Vector) -> Color {
+        self.environment.color_in(direction)
     }
 
-    #[test]
-    fn the_reflected_color_for_a_nonreflective_material() {
-        let sphere1 = Sphere::default().with_material(
-            Material::default()
-                .color(Color::new(0.8, 1.0, 0.6))
-                .diffuse(0.7)
-                .specular(0.2),
-        );
-        let sphere2 = Sphere::default()
+    pub fn escapes_through_portal(&self, ray: Ray) -> bool {
+        self.portals.iter().any(|p| p.intersects(ray))
+    }
+
+    pub fn intersect(&self, ray: Ray) -> Intersections<'_> {
+        self.intersect_with(ray, |_| true)
+    }
+
+    /// `intersect`, keeping only hits on shapes that pass `filter`. Hits
+    /// cut away by a clip plane are dropped here as well, so every kind of
+    /// ray filters what it hits in the one place.
+    pub fn intersect_with(
+        &self,
+        ray: Ray,
+        filter: impl Fn(&dyn Shape) -> bool,
+    ) -> Intersections<'_> {
+        let ray = self.clip_planes.iter().fold(ray, |ray, plane| {
+            let (t_min, t_max) = plane.unclipped_range(ray);
+            ray.t_min(ray.t_min.max(t_min)).t_max(ray.t_max.min(t_max))
+        });
+        let keep = |i: &Intersection| filter(i.object) && !ray.is_self_hit(i.object.id(), i.t);
+
+        match self.spatial_index() {
+            Some(index) => index
+                .candidates(ray)
+                .into_iter()
+                .flat_map(|i| self.objects[i].intersect(ray))
+                .filter(|i| keep(i))
+                .collect(),
+            None => self
+                .objects
+                .iter()
+                .flat_map(|o| o.intersect(ray))
+                .filter(|i| keep(i))
+                .collect(),
+        }
+    }
+
+    pub fn is_shadowed(&self, point: Point) -> bool {
+        self.shadow_at(point) == color::BLACK
+    }
+
+    /// How much of each channel of the light reaches `point`. With colored
+    /// shadows, every transparent surface crossed on the way filters it by
+    /// its color and transparency; any opaque surface blocks it outright.
+    /// Only surfaces the light shines on cast its shadows, so light groups
+    /// decide shadows as well as lighting.
+    pub fn shadow_at(&self, point: Point) -> Color {
+        let (ray, casters) = self.shadow_casters(point, None, None);
+        self.shadow_of(ray, &casters)
+    }
+
+    /// The shadow ray from `point` to the light and the shapes along it
+    /// that can shadow the point: the ones the light shines on, except the
+    /// shape `leaving` where the ray sets off and the shape `through`.
+    fn shadow_casters(
+        &self,
+        point: Point,
+        leaving: Option<Uuid>,
+        through: Option<Uuid>,
+    ) -> (Ray, Vec<Intersection<'_>>) {
+        let light = self.light();
+        let v = light.position - point;
+        let distance = v.magnitude();
+        let direction = v.normalize();
+
+        let r = Ray::new(point, direction)
+            .kind(RayKind::Shadow)
+            .leaving(leaving)
+            .t_min(0.0)
+            .t_max(distance);
+        stats::record_ray(RayKind::Shadow);
+        let casters = self
+            .intersect_with(r, |o| light.illuminates(self.material_of(o)))
+            .into_iter()
+            .filter(|i| Some(i.object.id()) != through)
+            .collect();
+        (r, casters)
+    }
+
+    /// How much of each channel gets past `casters` along the shadow ray
+    /// `ray`.
+    fn shadow_of(&self, ray: Ray, casters: &[Intersection]) -> Color {
+        let mut shadow = color::WHITE;
+        for i in casters {
+            let material = self.material_of(i.object).at_hit(&i.groups, 0.0);
+            let point = ray.position(i.t);
+            let transparency = material.transparency_at(i.object, point);
+            if !self.colored_shadows || transparency == 0.0 {
+                return color::BLACK;
+            }
+            shadow = shadow * material.color_at(i.object, point) * transparency;
+        }
+        shadow
+    }
+
+    pub fn shade_hit(&self, comps: Computations, remaining: impl Into<DepthBudget>) -> Color {
+        self.shade_hit_as(comps, remaining, Spectra::default())
+    }
+
+    /// `shade_hit` with the light carried as `R`, as for `radiance_at`.
+    pub fn shade_hit_as<R: Radiance>(
+        &self,
+        comps: Computations,
+        remaining: impl Into<DepthBudget>,
+        spectra: Spectra,
+    ) -> R {
+        let remaining = remaining.into();
+        // TODO: try multiple light sources.  It will slow things down though
+        let shadow = self.light_reaching(&comps);
+
+        let material = self.shading_material(&comps);
+        let light = self.light();
+        let color = spectra.surface::<R>(&material, comps.object, comps.over_point);
+        let surface = material
+            .lighting_breakdown_as(
+                comps.object,
+                light,
+                comps.over_point,
+                comps.eyev,
+                comps.normalv,
+                shadow,
+                color,
+                spectra.light(&light),
+            )
+            .total();
+
+        let albedo = color * material.diffuse_at(comps.object, comps.over_point);
+        let surface = surface
+            + R::from_rgb(material.emissive)
+            + R::from_rgb(self.emitter_irradiance(comps) + self.caustic_irradiance(comps)) * albedo
+            + self.subsurface_as(comps, spectra);
+
+        let reflected = self.reflected_as::<R>(comps, remaining, spectra);
+        let refracted = self.refracted_as::<R>(comps, remaining, spectra);
+
+        comps.with_bounces(&material, surface, reflected, refracted)
+    }
+
+    /// Light from behind a translucent object glowing through it. It fades
+    /// with how far a ray carrying on into the surface travels before
+    /// coming out again, so thin parts glow more than thick ones.
+    pub fn subsurface_color(&self, comps: Computations) -> Color {
+        self.subsurface_as(comps, Spectra::default())
+    }
+
+    fn subsurface_as<R: Radiance>(&self, comps: Computations, spectra: Spectra) -> R {
+        let material = self.shading_material(&comps);
+        if material.translucency <= 0.0 || !self.light_source.illuminates(&material) {
+            return R::default();
+        }
+
+        let inward = Ray::new(comps.under_point, -comps.eyev);
+        let id = comps.object.id();
+        let exit = self
+            .intersect_with(inward, |o| o.id() == id)
+            .iter()
+            .find(|i| i.t > 0.0)
+            .map(|i| i.t);
+        let thickness = match exit {
+            Some(t) => t,
+            None => return R::default(),
+        };
+        let light = self.light();
+        let lightv = (light.position - comps.point).normalize();
+        let facing = lightv.dot(-comps.eyev).max(0.0);
+
+        spectra.surface::<R>(&material, comps.object, comps.point)
+            * spectra.light::<R>(&light)
+            * (material.translucency * facing * (-thickness / material.scatter_distance).exp())
+    }
+
+    /// Phong lighting as if nothing cast shadows, plus the surface's own
+    /// glow. Cheap enough for previews.
+    pub fn unshadowed_lighting(&self, comps: Computations) -> Color {
+        let material = self.shading_material(&comps);
+        let light = self.light();
+        let shadow = if light.illuminates(&material) {
+            color::WHITE
+        } else {
+            color::BLACK
+        };
+        material.lighting(
+            comps.object,
+            light,
+            comps.over_point,
+            comps.eyev,
+            comps.normalv,
+            shadow,
+        ) + material.emissive
+    }
+
+    /// Diffuse and specular light arriving straight from the light source,
+    /// without the ambient approximation.
+    pub fn direct_lighting(&self, comps: Computations) -> Color {
+        let shadow = self.light_reaching(&comps);
+        let breakdown = self.shading_material(&comps).lighting_breakdown(
+            comps.object,
+            self.light(),
+            comps.over_point,
+            comps.eyev,
+            comps.normalv,
+            shadow,
+        );
+
+        breakdown.diffuse + breakdown.specular
+    }
+
+    /// Diffuse light from every emissive shape, each one sampled at the same
+    /// evenly spread set of `emitter_samples` points on its surface.
+    pub fn emitted_lighting(&self, comps: Computations) -> Color {
+        self.emitter_irradiance(comps) * self.diffuse_albedo(comps)
+    }
+
+    /// The light from every emissive shape falling on the surface, before
+    /// the surface scatters it, sampled as for `emitted_lighting`.
+    fn emitter_irradiance(&self, comps: Computations) -> Color {
+        lattice_average(self.emitter_samples, |u, v| {
+            self.sample_emitter_irradiance(comps, u, v)
+        })
+    }
+
+    /// Diffuse light from the background reaching the surface through the
+    /// portals, sampled like `emitted_lighting`.
+    pub fn portal_lighting(&self, comps: Computations) -> Color {
+        lattice_average(self.emitter_samples, |u, v| {
+            self.sample_portals(comps, u, v)
+        })
+    }
+
+    /// One sample of the background seen through every portal, taken at the
+    /// point `u`, `v` maps to on each opening.
+    pub fn sample_portals(&self, comps: Computations, u: f32, v: f32) -> Color {
+        self.portals.iter().fold(color::BLACK, |sum, portal| {
+            let v = portal.point_on(u, v) - comps.over_point;
+            let distance = v.magnitude();
+            let lightv = v / distance;
+            let cos_surface = lightv.dot(comps.normalv);
+            let cos_portal = lightv.dot(portal.normal()).abs();
+            if cos_surface <= 0.0 {
+                return sum;
+            }
+
+            stats::record_ray(RayKind::Shadow);
+            if self
+                .intersect(
+                    Ray::new(comps.over_point, lightv)
+                        .kind(RayKind::Shadow)
+                        .leaving(Some(comps.object.id())),
+                )
+                .hit()
+                .is_some()
+            {
+                return sum;
+            }
+
+            sum + self.background_in(lightv)
+                * (cos_surface * cos_portal * portal.area() / (PI * distance.powi(2)))
+        }) * self.diffuse_albedo(comps)
+    }
+
+    /// One sample of the diffuse light from every emissive shape, taken at
+    /// the point `u`, `v` maps to on each of their surfaces.
+    pub fn sample_emitters(&self, comps: Computations, u: f32, v: f32) -> Color {
+        self.sample_emitter_irradiance(comps, u, v) * self.diffuse_albedo(comps)
+    }
+
+    fn sample_emitter_irradiance(&self, comps: Computations, u: f32, v: f32) -> Color {
+        self.emitters()
+            .filter(|(o, _)| o.id() != comps.object.id())
+            .filter_map(|(o, groups)| {
+                let sample = o.sample_surface(u, v)?.transformed(groups);
+                Some((self.material_of(o).emissive, sample))
+            })
+            .fold(color::BLACK, |sum, (emissive, sample)| {
+                let v = sample.point - comps.over_point;
+                let distance = v.magnitude();
+                let lightv = v / distance;
+                let cos_surface = lightv.dot(comps.normalv);
+                let cos_emitter = -lightv.dot(sample.normal);
+                if cos_surface <= 0.0 || cos_emitter <= 0.0 {
+                    return sum;
+                }
+
+                stats::record_ray(RayKind::Shadow);
+                let shadow_ray = Ray::new(comps.over_point, lightv)
+                    .kind(RayKind::Shadow)
+                    .leaving(Some(comps.object.id()))
+                    .t_max(distance - EPSILON);
+                if self.intersect(shadow_ray).hit().is_some() {
+                    sum
+                } else {
+                    sum + emissive
+                        * (cos_surface * cos_emitter * sample.area / (PI * distance.powi(2)))
+                }
+            })
+    }
+
+    /// The share of each channel of the light arriving at `comps` that the
+    /// surface scatters diffusely.
+    fn diffuse_albedo(&self, comps: Computations) -> Color {
+        let material = self.shading_material(&comps);
+        material.color_at(comps.object, comps.over_point)
+            * material.diffuse_at(comps.object, comps.over_point)
+    }
+
+    pub fn caustic_color(&self, comps: Computations) -> Color {
+        self.caustic_irradiance(comps) * self.diffuse_albedo(comps)
+    }
+
+    /// The light the photon map gathers at the surface, before the surface
+    /// scatters it.
+    fn caustic_irradiance(&self, comps: Computations) -> Color {
+        match &self.photon_map {
+            Some(map) => map.irradiance_at(comps.point),
+            None => color::BLACK,
+        }
+    }
+
+    /// Caustics pre-pass: photons are shot from the light, followed through
+    /// reflective and transparent surfaces, and stored where they land on a
+    /// diffuse one. Direct lighting is left to `Material::lighting`, so
+    /// photons that never meet a specular surface are dropped.
+    pub fn build_photon_map(&mut self, settings: PhotonSettings) {
+        let map = stats::time_phase("photon_map", || {
+            let light = self.light();
+            let mut photons = vec![];
+            for i in 0..settings.photons {
+                let direction = photon_map::emission_direction(i, settings.photons);
+                let ray = Ray::new(light.position, direction);
+                if let Some(photon) = self.trace_photon(ray, settings) {
+                    photons.push(photon);
+                }
+            }
+            PhotonMap::new(photons, settings.radius)
+        });
+
+        self.photon_map = Some(map);
+    }
+
+    fn trace_photon(&self, mut ray: Ray, settings: PhotonSettings) -> Option<Photon> {
+        // Point lights here don't fall off with distance, so each photon is
+        // scaled by the squared distance to the first surface it reaches to
+        // keep the flux arriving there consistent with `Material::lighting`.
+        let mut power = self.light().intensity * (4.0 * PI / settings.photons as f32);
+        let mut specular = false;
+
+        for bounce in 0..=settings.max_bounces {
+            let intersections = self.intersect(ray);
+            let hit = intersections.hit()?;
+            let comps = self.prepare_computations(hit, ray, &intersections);
+            let material = self.shading_material(&comps);
+
+            if bounce == 0 {
+                power = power * hit.t.powi(2);
+            }
+
+            let transparency = material.transparency_at(comps.object, comps.over_point);
+            let reflective = material.reflective_at(comps.object, comps.over_point);
+            if transparency > EPSILON {
+                power = power * transparency;
+                ray = match comps.refracted_direction() {
+                    Some(direction) => Ray::new(comps.under_point, direction),
+                    None => Ray::new(comps.over_point, comps.reflectv),
+                }
+                .bias(comps.bias)
+                .leaving(Some(comps.object.id()));
+            } else if reflective > EPSILON {
+                power = power * reflective;
+                ray = Ray::new(comps.over_point, comps.reflectv)
+                    .bias(comps.bias)
+                    .leaving(Some(comps.object.id()));
+            } else if specular {
+                return Some(Photon::new(comps.point, power));
+            } else {
+                return None;
+            }
+
+            specular = true;
+        }
+
+        None
+    }
+
+    pub fn reflected_color(&self, comps: Computations, remaining: impl Into<DepthBudget>) -> Color {
+        self.reflected_as(comps, remaining, Spectra::default())
+    }
+
+    fn reflected_as<R: Radiance>(
+        &self,
+        comps: Computations,
+        remaining: impl Into<DepthBudget>,
+        spectra: Spectra,
+    ) -> R {
+        let (remaining, weight) = match self.reflection_budget(&comps, remaining.into()) {
+            Some(budget) => budget,
+            None => return R::default(),
+        };
+
+        stats::record_ray(RayKind::Reflection);
+        let radiance: R = self.radiance_at(comps.reflected_ray(), remaining, spectra);
+
+        radiance * weight
+    }
+
+    /// The budget a reflected ray from `comps` carries on with and what
+    /// its color is scaled by, or `None` if it isn't followed.
+    pub fn reflection_budget(
+        &self,
+        comps: &Computations,
+        remaining: DepthBudget,
+    ) -> Option<(DepthBudget, f32)> {
+        let reflective = self
+            .shading_material(comps)
+            .reflective_at(comps.object, comps.over_point);
+        if reflective < EPSILON || !remaining.allows(RayKind::Reflection) {
+            return None;
+        }
+        let weight = reflective * self.survival_weight(remaining.contribution * reflective)?;
+        Some((
+            remaining.spend(RayKind::Reflection).attenuate(weight),
+            weight,
+        ))
+    }
+
+    /// Light arriving through a transparent surface. Where the colors bend
+    /// differently, the ray splits into one per channel, and each of those
+    /// carries only its own channel from then on, so a ray is split at
+    /// most once however many dispersive surfaces it passes through.
+    pub fn refracted_color(&self, comps: Computations, remaining: impl Into<DepthBudget>) -> Color {
+        self.refracted_as(comps, remaining, Spectra::default())
+    }
+
+    /// `refracted_color` with the light carried as `R`, which splits into
+    /// one ray for each of its wavelength samples.
+    fn refracted_as<R: Radiance>(
+        &self,
+        comps: Computations,
+        remaining: impl Into<DepthBudget>,
+        spectra: Spectra,
+    ) -> R {
+        let (remaining, weight) = match self.refraction_budget(&comps, remaining.into()) {
+            Some(budget) => budget,
+            None => return R::default(),
+        };
+
+        let refract = |channel: Option<usize>| match comps
+            .refracted_ray(channel, channel.map(R::wavelength))
+        {
+            Some(refract_ray) => {
+                stats::record_ray(RayKind::Refraction);
+                self.radiance_at(refract_ray, remaining, spectra)
+            }
+            None => R::default(),
+        };
+
+        let radiance = if comps.splits_refraction(R::SAMPLES) {
+            (0..R::SAMPLES).fold(R::default(), |sum, i| sum + refract(Some(i)).only(i))
+        } else {
+            refract(comps.channel)
+        };
+        radiance * weight
+    }
+
+    /// The budget refracted rays from `comps` carry on with and what their
+    /// colors are scaled by, or `None` if they aren't followed.
+    pub fn refraction_budget(
+        &self,
+        comps: &Computations,
+        remaining: DepthBudget,
+    ) -> Option<(DepthBudget, f32)> {
+        let transparency = self
+            .shading_material(comps)
+            .transparency_at(comps.object, comps.over_point);
+        if transparency <= EPSILON || !remaining.allows(RayKind::Refraction) {
+            return None;
+        }
+        let weight = transparency * self.survival_weight(remaining.contribution * transparency)?;
+        Some((
+            remaining.spend(RayKind::Refraction).attenuate(weight),
+            weight,
+        ))
+    }
+
+    /// What to scale a reflected or refracted ray contributing
+    /// `contribution` by, or `None` if it isn't worth following.
+    fn survival_weight(&self, contribution: f32) -> Option<f32> {
+        if contribution < self.min_contribution {
+            return None;
+        }
+        match &self.roulette {
+            Some(roulette) => roulette.survive(contribution),
+            None => Some(1.0),
+        }
+    }
+}
+
+/// Adds the path down to `object`, if it's a group, and to every group
+/// inside it, to `paths`.
+fn find_groups(object: &dyn Shape, path: &mut Vec<usize>, paths: &mut HashMap<Uuid, Vec<usize>>) {
+    if object.as_any().is::<Group>() || !object.children().is_empty() {
+        paths.insert(object.id(), path.clone());
+    }
+    for (i, child) in object.children().iter().enumerate() {
+        path.push(i);
+        find_groups(child.as_ref(), path, paths);
+        path.pop();
+    }
+}
+
+fn find_object_mut(objects: &mut [Box<dyn Shape>], id: Uuid) -> Option<&mut dyn Shape> {
+    for object in objects.iter_mut() {
+        if object.id() == id {
+            return Some(object.as_mut());
+        }
+        if let Some(found) = find_object_mut(object.children_mut(), id) {
+            return Some(found);
+        }
+    }
+    None
+}
+
+/// Averages `sample` over `samples` points spread evenly over the unit
+/// square: stratified along `u`, golden ratio steps along `v`.
+fn lattice_average(samples: usize, sample: impl Fn(f32, f32) -> Color) -> Color {
+    let golden_ratio = (1.0 + 5_f32.sqrt()) / 2.0;
+
+    (0..samples).fold(color::BLACK, |sum, i| {
+        let u = (i as f32 + 0.5) / samples as f32;
+        let v = (i as f32 * golden_ratio).fract();
+        sum + sample(u, v)
+    }) * (1.0 / samples.max(1) as f32)
+}
+
+impl Default for World {
+    fn default() -> Self {
+        let sphere1 = Sphere::default().with_material(
+            Material::default()
+                .color(Color::new(0.8, 1.0, 0.6))
+                .diffuse(0.7)
+                .specular(0.2),
+        );
+        let sphere2 = Sphere::default().with_transform(Transform::scaling(0.5, 0.5, 0.5));
+        Self {
+            light_source: PointLight::new(
+                Point::new(-10.0, 10.0, -10.0),
+                Color::new(1.0, 1.0, 1.0),
+            ),
+            objects: vec![Box::new(sphere1), Box::new(sphere2)],
+            photon_map: None,
+            emitter_samples: 16,
+            environment: Box::new(color::BLACK),
+            portals: vec![],
+            index_kind: None,
+            index: OnceLock::new(),
+            emitters: OnceLock::new(),
+            clip_planes: vec![],
+            colored_shadows: false,
+            min_contribution: 0.0,
+            roulette: None,
+            fog: None,
+            volumes: vec![],
+            animations: vec![],
+            group_paths: HashMap::new(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::f32::consts::SQRT_2;
+
+    use crate::{
+        color, float_eq,
+        intersection::Intersection,
+        lights::LightGroups,
+        materials::Channel,
+        patterns::{checkered::Checkered, PatternBuilder, TestPattern},
+        shapes::{cube::Cube, plane::Plane, triangle::Triangle, ShapeBuilder, Visibility},
+        test::sqrt_n_over_n,
+    };
+
+    use super::*;
+
+    #[test]
+    fn creating_a_world() {
+        let w = World::new(PointLight::default());
+
+        assert!(w.objects.is_empty());
+    }
+
+    #[test]
+    fn default_world() {
+        let light = PointLight::new(Point::new(-10.0, 10.0, -10.0), Color::new(1.0, 1.0, 1.0));
+        let s1 = Sphere::default().with_material(
+            Material::default()
+                .color(Color::new(0.8, 1.0, 0.6))
+                .diffuse(0.7)
+                .specular(0.2),
+        );
+        let s2 = Sphere::default().with_transform(Transform::scaling(0.5, 0.5, 0.5));
+
+        let w = World::default();
+
+        assert_eq!(w.light_source, light);
+        assert_eq!(w.objects[0].material(), s1.material());
+        assert_eq!(w.objects[1].material(), s2.material());
+        assert_eq!(w.objects[0].transform(), s1.transform());
+        assert_eq!(w.objects[1].transform(), s2.transform());
+    }
+
+    #[test]
+    fn intersect_a_world_with_a_ray() {
+        let w = World::default();
+        let r = Ray::new(Point::new(0.0, 0.0, -5.0), Vector::new(0.0, 0.0, 1.0));
+
+        let xs = w.intersect(r);
+
+        assert_eq!(xs.len(), 4);
+        assert!(float_eq(xs[0].t, 4.0));
+        assert!(float_eq(xs[1].t, 4.5));
+        assert!(float_eq(xs[2].t, 5.5));
+        assert!(float_eq(xs[3].t, 6.0));
+    }
+
+    #[test]
+    fn a_ray_skips_the_shape_it_leaves_right_where_it_leaves() {
+        let w = World::default();
+        let s = w.objects()[0].id();
+        let r = Ray::new(Point::new(0.0, 0.0, -1.0), Vector::new(0.0, 0.0, 1.0));
+
+        let from_nowhere = w.intersect(r);
+        let leaving = w.intersect(r.leaving(Some(s)));
+
+        assert!(float_eq(from_nowhere.hit().unwrap().t, 0.0));
+        assert!(float_eq(leaving.hit().unwrap().t, 0.5));
+        assert_eq!(leaving.len(), from_nowhere.len() - 1);
+        assert!(leaving
+            .iter()
+            .any(|i| i.object.id() == s && float_eq(i.t, 2.0)));
+    }
+
+    #[test]
+    fn intersecting_a_world_with_a_filter() {
+        let w = World::default();
+        let outer = w.objects()[0].id();
+        let r = Ray::new(Point::new(0.0, 0.0, -5.0), Vector::new(0.0, 0.0, 1.0));
+
+        let xs = w.intersect_with(r, |o| o.id() != outer);
+
+        assert_eq!(xs.len(), 2);
+        assert!(float_eq(xs[0].t, 4.5));
+        assert!(float_eq(xs[1].t, 5.5));
+    }
+
+    #[test]
+    fn shading_an_intersection() {
+        let w = World::default();
+        let r = Ray::new(Point::new(0.0, 0.0, -5.0), Vector::new(0.0, 0.0, 1.0));
+        let shape = w.objects[0].as_ref();
+        let i = Intersection::new(4.0, shape);
+
+        let comps = i.prepare_computations(r, &[i]);
+        let c = w.shade_hit(comps, 3);
+
+        assert_eq!(c, Color::new(0.38066, 0.47583, 0.2855));
+    }
+
+    #[test]
+    fn shading_an_intersection_from_the_inside() {
+        let w = World {
+            light_source: PointLight::new(Point::new(0.0, 0.25, 0.0), Color::new(1.0, 1.0, 1.0)),
+            ..World::default()
+        };
+        let r = Ray::new(Point::new(0.0, 0.0, 0.0), Vector::new(0.0, 0.0, 1.0));
+        let shape = w.objects[1].as_ref();
+        let i = Intersection::new(0.5, shape);
+
+        let comps = i.prepare_computations(r, &[i]);
+        let c = w.shade_hit(comps, 3);
+
+        assert_eq!(c, Color::new(0.90498, 0.90498, 0.90498));
+    }
+
+    #[test]
+    fn color_when_ray_misses() {
+        let w = World::default();
+        let r = Ray::new(Point::new(0.0, 0.0, -5.0), Vector::new(0.0, 1.0, 0.0));
+
+        let c = w.color_at(r, 3);
+
+        assert_eq!(c, color::BLACK);
+    }
+
+    #[test]
+    fn color_when_a_ray_hits() {
+        let w = World::default();
+        let r = Ray::new(Point::new(0.0, 0.0, -5.0), Vector::new(0.0, 0.0, 1.0));
+
+        let c = w.color_at(r, 3);
+
+        assert_eq!(c, Color::new(0.38066, 0.47583, 0.2855));
+    }
+
+    #[test]
+    fn color_with_an_intersection_behind_ray() {
+        let outer = Sphere::default().with_material(
+            Material::default()
+                .color(Color::new(0.8, 1.0, 0.6))
+                .diffuse(0.7)
+                .specular(0.2)
+                .ambient(1.0),
+        );
+        let inner = Sphere::default()
+            .with_transform(Transform::scaling(0.5, 0.5, 0.5))
+            .with_material(Material::default().ambient(1.0));
+        let w = World::new(PointLight::new(
+            Point::new(-10.0, 10.0, -10.0),
+            Color::new(1.0, 1.0, 1.0),
+        ))
+        .object(Box::new(outer))
+        .object(Box::new(inner.clone()));
+
+        let r = Ray::new(Point::new(0.0, 0.0, 0.75), Vector::new(0.0, 0.0, -1.0));
+
+        let c = w.color_at(r, 3);
+
+        assert_eq!(c, inner.material().color);
+    }
+
+    #[test]
+    fn a_transformed_light_is_lit_from_where_it_ends_up() {
+        let light = PointLight::new(Point::new(0.0, 0.0, 0.0), color::WHITE)
+            .transform(Transform::translation(-10.0, 10.0, -10.0));
+        let w = World::default().light_source(light);
+
+        assert_eq!(w.light().position, Point::new(-10.0, 10.0, -10.0));
+        assert!(w.is_shadowed(Point::new(10.0, -10.0, 10.0)));
+    }
+
+    #[test]
+    fn a_light_parented_to_nested_groups_follows_them() {
+        let mut inner = Group::new().with_transform(Transform::scaling(2.0, 2.0, 2.0));
+        inner.add_child(Box::new(Sphere::default()));
+        let inner_id = inner.id();
+        let mut outer = Group::new().with_transform(Transform::translation(0.0, 5.0, 0.0));
+        outer.add_child(Box::new(inner));
+        let light = PointLight::new(Point::new(1.0, 0.0, 0.0), color::WHITE)
+            .transform(Transform::translation(0.0, 1.0, 0.0))
+            .parent(inner_id);
+        let mut w = World::new(light).object(Box::new(outer));
+
+        assert_eq!(w.light().position, Point::new(2.0, 7.0, 0.0));
+
+        w.update_object(0, |group| {
+            group.set_transform(Transform::translation(0.0, -5.0, 0.0))
+        });
+
+        assert_eq!(w.light().position, Point::new(2.0, -3.0, 0.0));
+        assert!(w.is_shadowed(Point::new(0.0, -8.0, 0.0)));
+    }
+
+    #[test]
+    fn a_light_parented_to_an_empty_group() {
+        let pivot = Group::new().with_transform(Transform::translation(0.0, 5.0, 0.0));
+        let light = PointLight::new(Point::new(1.0, 0.0, 0.0), color::WHITE).parent(pivot.id());
+        let w = World::default().light_source(light).object(Box::new(pivot));
+
+        assert_eq!(w.light().position, Point::new(1.0, 5.0, 0.0));
+    }
+
+    /// The shaded color and direct lighting where a ray from the front
+    /// hits the first sphere.
+    fn light_on_first_sphere(w: &World) -> (Color, Color) {
+        let r = Ray::new(Point::new(0.0, 0.0, -5.0), Vector::new(0.0, 0.0, 1.0));
+        let i = Intersection::new(4.0, w.objects[0].as_ref());
+        let comps = i.prepare_computations(r, &[i]);
+
+        (w.shade_hit(comps, 5), w.direct_lighting(comps))
+    }
+
+    #[test]
+    fn a_light_illuminates_objects_in_its_groups() {
+        let key = LightGroups::group(0).unwrap();
+        let mut w = World::default();
+        w.light_source = w.light_source.groups(key);
+        w.update_object(0, |s| {
+            *s.material_mut() = s.material().clone().light_groups(key)
+        });
+
+        assert_eq!(
+            light_on_first_sphere(&w).0,
+            Color::new(0.38066, 0.47583, 0.2855)
+        );
+    }
+
+    #[test]
+    fn a_light_leaves_objects_outside_its_groups_in_the_dark() {
+        let key = LightGroups::group(0).unwrap();
+        let fill = LightGroups::group(1).unwrap();
+        let mut w = World::default();
+        w.light_source = w.light_source.groups(key);
+        w.update_object(0, |s| {
+            *s.material_mut() = s.material().clone().light_groups(fill)
+        });
+        let (shaded, direct) = light_on_first_sphere(&w);
+
+        assert_eq!(shaded, Color::new(0.08, 0.1, 0.06));
+        assert_eq!(direct, color::BLACK);
+        assert_eq!(w.light().groups, key);
+    }
+
+    #[test]
+    fn only_objects_in_the_lights_groups_cast_its_shadows() {
+        let key = LightGroups::group(0).unwrap();
+        let fill = LightGroups::group(1).unwrap();
+        let mut w = World::default();
+        w.light_source = w.light_source.groups(key);
+        let p = Point::new(10.0, -10.0, 10.0);
+        assert!(w.is_shadowed(p));
+
+        for i in 0..2 {
+            w.update_object(i, |s| {
+                *s.material_mut() = s.material().clone().light_groups(fill)
+            });
+        }
+
+        assert!(!w.is_shadowed(p));
+    }
+
+    #[test]
+    fn no_shadow_when_nothing_is_collinear_with_point_and_light() {
+        let w = World::default();
+        let p = Point::new(0.0, 10.0, 0.0);
+
+        assert!(!w.is_shadowed(p));
+    }
+
+    #[test]
+    fn shadow_when_object_between_point_and_light() {
+        let w = World::default();
+        let p = Point::new(10.0, -10.0, 10.0);
+
+        assert!(w.is_shadowed(p));
+    }
+
+    #[test]
+    fn no_shadow_when_object_behind_light() {
+        let w = World::default();
+        let p = Point::new(-20.0, 20.0, -20.0);
+
+        assert!(!w.is_shadowed(p));
+    }
+
+    #[test]
+    fn no_shadow_when_object_behind_point() {
+        let w = World::default();
+        let p = Point::new(-2.0, 2.0, -2.0);
+
+        assert!(!w.is_shadowed(p));
+    }
+
+    fn glass_between_light_and_floor(glass: Material) -> World {
+        let light = PointLight::new(Point::new(0.0, 10.0, 0.0), color::WHITE);
+        World::new(light)
+            .object(Box::new(
+                Sphere::default()
+                    .with_transform(Transform::translation(0.0, 5.0, 0.0))
+                    .with_material(glass),
+            ))
+            .colored_shadows(true)
+    }
+
+    #[test]
+    fn transparent_objects_cast_full_shadows_by_default() {
+        let w = glass_between_light_and_floor(Material::default().transparency(1.0))
+            .colored_shadows(false);
+
+        assert_eq!(w.shadow_at(Point::new(0.0, 0.0, 0.0)), color::BLACK);
+    }
+
+    #[test]
+    fn opaque_objects_cast_full_colored_shadows() {
+        let w = glass_between_light_and_floor(Material::default());
+
+        assert_eq!(w.shadow_at(Point::new(0.0, 0.0, 0.0)), color::BLACK);
+        assert!(w.is_shadowed(Point::new(0.0, 0.0, 0.0)));
+    }
+
+    #[test]
+    fn red_glass_casts_a_red_shadow() {
+        let red_glass = Material::default()
+            .color(Color::new(1.0, 0.0, 0.0))
+            .transparency(0.5);
+        let w = glass_between_light_and_floor(red_glass);
+
+        let shadow = w.shadow_at(Point::new(0.0, 0.0, 0.0));
+
+        assert_eq!(shadow, Color::new(0.25, 0.0, 0.0));
+        assert!(!w.is_shadowed(Point::new(0.0, 0.0, 0.0)));
+        assert_eq!(w.shadow_at(Point::new(3.0, 0.0, 0.0)), color::WHITE);
+    }
+
+    #[test]
+    fn shade_hit_tints_light_passing_through_glass() {
+        let red_glass = Material::default()
+            .color(Color::new(1.0, 0.0, 0.0))
+            .transparency(1.0);
+        let w = glass_between_light_and_floor(red_glass).object(Box::new(Plane::default()));
+        let r = Ray::new(
+            Point::new(0.0, 1.0, -1.0),
+            Vector::new(0.0, -1.0, 1.0).normalize(),
+        );
+        let xs = w.intersect(r);
+        let comps = xs.hit().unwrap().prepare_computations(r, &xs);
+
+        let c = w.shade_hit(comps, 0);
+
+        assert!(c.red() > 0.5);
+        assert_eq!((c.green(), c.blue()), (0.1, 0.1));
+    }
+
+    fn prism_world(glass: Material) -> World {
+        World::new(PointLight::new(Point::new(0.0, 10.0, -10.0), color::WHITE))
+            .object(Box::new(Sphere::default().with_material(glass)))
+            .background(color::WHITE)
+    }
+
+    fn oblique_ray() -> Ray {
+        Ray::new(Point::new(0.0, 0.7, -5.0), Vector::new(0.0, 0.0, 1.0))
+    }
+
+    #[test]
+    fn a_dispersive_surface_splits_the_refracted_ray_by_channel() {
+        let flint = Material::default()
+            .transparency(1.0)
+            .refractive_index(1.5)
+            .abbe_number(20.0);
+        let w = prism_world(flint);
+        let r = oblique_ray();
+        let xs = w.intersect(r);
+        let comps = xs[0].prepare_computations(r, &xs);
+
+        let (_, stats) = stats::collect(|| w.refracted_color(comps, 1));
+
+        assert!(comps.is_dispersive());
+        assert_ne!(
+            comps.refracted_direction_at(Some(656.3)),
+            comps.refracted_direction_at(Some(486.1))
+        );
+        assert_eq!(stats.refraction_rays, 3);
+    }
+
+    #[test]
+    fn a_single_sided_surface_is_unlit_from_behind() {
+        let world = |two_sided: bool| {
+            World::new(PointLight::new(Point::new(0.0, -10.0, 0.0), color::WHITE)).object(Box::new(
+                Plane::default().with_material(Material::default().two_sided(two_sided)),
+            ))
+        };
+        let r = Ray::new(
+            Point::new(0.0, -1.0, -1.0),
+            Vector::new(0.0, 1.0, 1.0).normalize(),
+        );
+
+        let front = world(true).color_at(r, 0);
+        let back = world(false).color_at(r, 0);
+
+        assert!(front.red() > 0.5);
+        assert_eq!(back, Color::new(0.1, 0.1, 0.1));
+    }
+
+    fn absorbing_glass() -> World {
+        let glass = Material::default()
+            .ambient(0.0)
+            .diffuse(0.0)
+            .specular(0.0)
+            .transparency(1.0)
+            .absorption(Color::new(1.0, 0.0, 0.5));
+        prism_world(glass)
+    }
+
+    #[test]
+    fn glass_absorbs_light_exponentially_with_the_distance_inside() {
+        let w = absorbing_glass();
+        let r = Ray::new(Point::new(0.0, 0.0, -5.0), Vector::new(0.0, 0.0, 1.0));
+
+        let c = w.color_at(r, 5);
+
+        assert_eq!(c, Color::new((-2.0_f32).exp(), 1.0, (-1.0_f32).exp()));
+    }
+
+    #[test]
+    fn thick_glass_is_tinted_more_deeply_than_thin() {
+        let w = absorbing_glass();
+        let thick = Ray::new(Point::new(0.0, 0.0, -5.0), Vector::new(0.0, 0.0, 1.0));
+        let thin = Ray::new(Point::new(0.0, 0.8, -5.0), Vector::new(0.0, 0.0, 1.0));
+
+        assert!(w.color_at(thick, 5).red() < w.color_at(thin, 5).red());
+        assert!((w.color_at(thin, 5).red() - (-1.2_f32).exp()).abs() < 0.001);
+    }
+
+    #[test]
+    fn refracting_at_a_wavelength_matches_the_channel_at_that_line() {
+        let flint = Material::default()
+            .transparency(1.0)
+            .refractive_index(1.5)
+            .abbe_number(20.0);
+        let w = prism_world(flint);
+        let r = oblique_ray();
+        let xs = w.intersect(r);
+        let comps = xs[0].prepare_computations(r, &xs);
+
+        assert_eq!(
+            comps.refracted_direction_at(Some(587.6)),
+            comps.refracted_direction_at(None)
+        );
+        assert_eq!(
+            comps.refracted_direction_at(Some(656.3)),
+            xs[0]
+                .prepare_computations(r.channel(Some(0)), &xs)
+                .refracted_direction()
+        );
+        assert_ne!(
+            comps.refracted_direction_at(Some(400.0)),
+            comps.refracted_direction_at(Some(700.0))
+        );
+    }
+
+    #[test]
+    fn a_split_ray_is_not_split_again() {
+        let flint = Material::default()
+            .transparency(1.0)
+            .refractive_index(1.5)
+            .abbe_number(20.0);
+        let w = prism_world(flint);
+        let r = oblique_ray().channel(Some(2));
+        let xs = w.intersect(r);
+        let comps = xs[0].prepare_computations(r, &xs);
+
+        let (_, stats) = stats::collect(|| w.color_at(r, 2));
+
+        assert_eq!(comps.channel, Some(2));
+        assert_eq!(
+            comps.refracted_direction(),
+            comps.refracted_direction_at(Some(486.1))
+        );
+        assert_eq!(stats.refraction_rays, 2);
+    }
+
+    #[test]
+    fn glass_without_dispersion_refracts_one_ray() {
+        let glass = Material::default().transparency(1.0).refractive_index(1.5);
+        let w = prism_world(glass);
+        let r = oblique_ray();
+        let xs = w.intersect(r);
+        let comps = xs[0].prepare_computations(r, &xs);
+
+        let (_, stats) = stats::collect(|| w.refracted_color(comps, 1));
+
+        assert!(!comps.is_dispersive());
+        assert_eq!(stats.refraction_rays, 1);
+    }
+
+    #[test]
+    fn fog_hides_the_background() {
+        let grey = Color::new(0.5, 0.5, 0.5);
+        let w = World::default().fog(Fog::new(0.1, grey));
+        let r = Ray::new(Point::new(0.0, 0.0, -5.0), Vector::new(0.0, 1.0, 0.0));
+
+        assert_eq!(w.color_at(r, 5), grey);
+    }
+
+    #[test]
+    fn fog_fades_a_surface_with_distance() {
+        let grey = Color::new(0.5, 0.5, 0.5);
+        let r = Ray::new(Point::new(0.0, 0.0, -5.0), Vector::new(0.0, 0.0, 1.0));
+        let clear = World::default().color_at(r, 5);
+
+        let foggy = World::default().fog(Fog::new(0.1, grey)).color_at(r, 5);
+
+        assert_eq!(foggy, Fog::new(0.1, grey).apply(clear, 4.0));
+    }
+
+    #[test]
+    fn a_volume_between_the_camera_and_a_surface() {
+        let white = Color::new(1.0, 1.0, 1.0);
+        let mist = Volume::new(
+            Box::new(Cube::default().with_transform(Transform::translation(0.0, 0.0, -3.0))),
+            0.5,
+            white,
+        );
+        let r = Ray::new(Point::new(0.0, 0.0, -5.0), Vector::new(0.0, 0.0, 1.0));
+        let clear = World::default().color_at(r, 5);
+
+        let misty = World::default().volume(mist).color_at(r, 5);
+
+        let kept = (-1.0_f32).exp();
+        assert_eq!(misty, clear * kept + white * (1.0 - kept));
+    }
+
+    fn backlit_slab(depth: f32, material: Material) -> (World, Ray) {
+        let slab = Cube::default()
+            .with_transform(Transform::scaling(1.0, 1.0, depth))
+            .with_material(material);
+        let light = PointLight::new(Point::new(0.0, 0.0, 10.0), color::WHITE);
+        let w = World::new(light).object(Box::new(slab));
+        let r = Ray::new(Point::new(0.0, 0.0, -5.0), Vector::new(0.0, 0.0, 1.0));
+        (w, r)
+    }
+
+    fn subsurface(depth: f32, material: Material) -> Color {
+        let (w, r) = backlit_slab(depth, material);
+        let xs = w.intersect(r);
+        w.subsurface_color(xs[0].prepare_computations(r, &xs))
+    }
+
+    #[test]
+    fn opaque_surfaces_let_no_light_through() {
+        assert_eq!(subsurface(0.1, Material::default()), color::BLACK);
+    }
+
+    #[test]
+    fn light_shows_through_a_thin_translucent_object() {
+        let wax = Material::default().translucency(0.8).scatter_distance(0.5);
+
+        let thin = subsurface(0.1, wax.clone());
+        let thick = subsurface(1.0, wax);
+
+        assert!((thin.red() - 0.8 * (-0.4_f32).exp()).abs() < 0.001);
+        assert!(thick.red() < thin.red());
+    }
+
+    #[test]
+    fn the_thickness_of_a_translucent_shape_follows_its_groups() {
+        let wax = Material::default().translucency(0.8).scatter_distance(1.0);
+        let mut group = Group::new().with_transform(Transform::scaling(2.0, 2.0, 2.0));
+        group.add_child(Box::new(Sphere::default().with_material(wax)));
+        let light = PointLight::new(Point::new(0.0, 0.0, 10.0), color::WHITE);
+        let w = World::new(light).object(Box::new(group));
+        let r = Ray::new(Point::new(0.0, 0.0, -5.0), Vector::new(0.0, 0.0, 1.0));
+        let xs = w.intersect(r);
+
+        let c = w.subsurface_color(w.prepare_computations(&xs[0], r, &xs));
+
+        assert!((c.red() - 0.8 * (-4.0_f32).exp()).abs() < 0.001);
+    }
+
+    #[test]
+    fn light_wraps_past_the_terminator_of_a_translucent_shape() {
+        let wax = |translucency| {
+            Material::default()
+                .ambient(0.0)
+                .specular(0.0)
+                .translucency(translucency)
+                .scatter_distance(0.01)
+        };
+        let lit = |material| {
+            let light = PointLight::new(Point::new(10.0, 0.0, 0.0), color::WHITE);
+            let w = World::new(light).object(Box::new(Sphere::default().with_material(material)));
+            w.color_at(
+                Ray::new(Point::new(0.0, 0.0, -5.0), Vector::new(0.0, 0.0, 1.0)),
+                0,
+            )
+        };
+
+        assert_eq!(lit(wax(0.0)), color::BLACK);
+        assert!(lit(wax(0.8)).red() > 0.3);
+    }
+
+    #[test]
+    fn a_lit_translucent_slab_shades_brighter_than_an_opaque_one() {
+        let (opaque, r) = backlit_slab(0.1, Material::default());
+        let (wax, _) = backlit_slab(0.1, Material::default().translucency(0.8));
+
+        assert!(wax.color_at(r, 0).red() > opaque.color_at(r, 0).red());
+    }
+
+    #[test]
+    fn shade_hit_is_given_an_intersection_in_shadow() {
+        let w = World::new(
+            PointLight::default()
+                .position(0.0, 0.0, -10.0)
+                .intensity(1.0, 1.0, 1.0),
+        )
+        .object(Box::new(Sphere::default()))
+        .object(Box::new(
+            Sphere::default().with_transform(Transform::translation(0.0, 0.0, 10.0)),
+        ));
+        let r = Ray::default()
+            .origin(0.0, 0.0, 5.0)
+            .direction(0.0, 0.0, 1.0);
+        let i = Intersection::new(4.0, w.objects[1].as_ref());
+
+        let comps = i.prepare_computations(r, &[i]);
+        let c = w.shade_hit(comps, 3);
+
+        assert_eq!(c, Color::new(0.1, 0.1, 0.1));
+    }
+
+    #[test]
+    fn the_reflected_color_for_a_nonreflective_material() {
+        let sphere1 = Sphere::default().with_material(
+            Material::default()
+                .color(Color::new(0.8, 1.0, 0.6))
+                .diffuse(0.7)
+                .specular(0.2),
+        );
+        let sphere2 = Sphere::default()
             .with_transform(Transform::scaling(0.5, 0.5, 0.5))
             .with_material(Material::default().ambient(1.0));
         let w = World::new(PointLight::new(
-            Tuple::point(-10.0, 10.0, -10.0),
+            Point::new(-10.0, 10.0, -10.0),
+            Color::new(1.0, 1.0, 1.0),
+        ))
+        .object(Box::new(sphere1))
+        .object(Box::new(sphere2.clone()));
+        let r = Ray::default()
+            .origin(0.0, 0.0, 0.0)
+            .direction(0.0, 0.0, 1.0);
+        let i = Intersection::new(1.0, &sphere2);
+
+        let comps = i.prepare_computations(r, &[i]);
+        let color = w.reflected_color(comps, 3);
+
+        assert_eq!(color, color::BLACK);
+    }
+
+    #[test]
+    fn the_reflected_color_for_a_reflective_material() {
+        let w = World::default().object(Box::new(
+            Plane::default()
+                .with_material(Material::default().reflective(0.5))
+                .with_transform(Transform::translation(0.0, -1.0, 0.0)),
+        ));
+        let shape = w.objects[2].as_ref();
+        let r = Ray::default().origin(0.0, 0.0, -3.0).direction(
+            0.0,
+            -sqrt_n_over_n(2),
+            sqrt_n_over_n(2),
+        );
+        let i = Intersection::new(SQRT_2, shape);
+
+        let comps = i.prepare_computations(r, &[i]);
+        let color = w.reflected_color(comps, 10);
+
+        assert_eq!(color, Color::new(0.19032, 0.2379, 0.14274));
+    }
+
+    fn reflected_color_off_a_checkered_floor(shift: f32) -> Color {
+        let reflective_squares = Checkered::new(color::WHITE, color::BLACK)
+            .with_transform(Transform::translation(shift, 0.0, 0.0));
+        let w = World::default().object(Box::new(
+            Plane::default()
+                .with_material(
+                    Material::default()
+                        .channel_pattern(Channel::Reflective, Box::new(reflective_squares)),
+                )
+                .with_transform(Transform::translation(0.0, -1.0, 0.0)),
+        ));
+        let shape = w.objects[2].as_ref();
+        let r = Ray::default().origin(0.0, 0.0, -3.0).direction(
+            0.0,
+            -sqrt_n_over_n(2),
+            sqrt_n_over_n(2),
+        );
+        let i = Intersection::new(SQRT_2, shape);
+
+        let comps = i.prepare_computations(r, &[i]);
+        w.reflected_color(comps, 10)
+    }
+
+    #[test]
+    fn only_the_white_squares_of_a_checkered_floor_reflect() {
+        assert_eq!(
+            reflected_color_off_a_checkered_floor(0.0),
+            Color::new(0.38064, 0.4758, 0.28548)
+        );
+        assert_eq!(reflected_color_off_a_checkered_floor(1.0), color::BLACK);
+    }
+
+    #[test]
+    fn shade_hit_with_a_reflective_material() {
+        let mut w = World::default();
+        let shape = Plane::default()
+            .with_material(Material::default().reflective(0.5))
+            .with_transform(Transform::translation(0.0, -1.0, 0.0));
+        w.objects.push(Box::new(shape.clone()));
+        let r = Ray::default()
+            .origin(0.0, 0.0, -3.0)
+            .direction(0.0, -SQRT_2 / 2.0, SQRT_2 / 2.0);
+        let i = Intersection::new(SQRT_2, &shape);
+
+        let comps = i.prepare_computations(r, &[i]);
+        let color = w.shade_hit(comps, 5);
+
+        assert_eq!(color, Color::new(0.87677, 0.92436, 0.82918));
+    }
+
+    #[test]
+    fn color_at_with_mutually_reflective_surfaces() {
+        let lower = Plane::default()
+            .with_material(Material::default().reflective(1.0))
+            .with_transform(Transform::translation(0.0, -1.0, 0.0));
+        let upper = Plane::default()
+            .with_material(Material::default().reflective(1.0))
+            .with_transform(Transform::translation(0.0, 1.0, 0.0));
+        let w = World::new(PointLight::new(Point::new(0.0, 0.0, 0.0), color::WHITE))
+            .object(Box::new(lower))
+            .object(Box::new(upper));
+        let r = Ray::default()
+            .origin(0.0, 0.0, 0.0)
+            .direction(0.0, 1.0, 0.0);
+
+        let _ = w.color_at(r, 3);
+    }
+
+    // TODO: maybe revisit infinite recursion, so far seems to be fine maybe because of compiler optimizations?
+
+    #[test]
+    fn the_refracted_color_with_an_opaque_surface() {
+        let w = World::default();
+        let shape = w.objects[0].as_ref();
+        let r = Ray::default()
+            .origin(0.0, 0.0, -5.0)
+            .direction(0.0, 0.0, 1.0);
+        let xs = vec![Intersection::new(4.0, shape), Intersection::new(6.0, shape)];
+
+        let comps = xs[0].prepare_computations(r, &xs);
+        let c = w.refracted_color(comps, 5);
+
+        assert_eq!(c, color::BLACK);
+    }
+
+    #[test]
+    fn the_refracted_color_at_the_maximum_recursive_depth() {
+        let w = World::new(PointLight::new(
+            Point::new(-10.0, 10.0, -10.0),
+            Color::new(1.0, 1.0, 1.0),
+        ))
+        .object(Box::new(
+            Sphere::default().with_material(
+                Material::default()
+                    .color(Color::new(0.8, 1.0, 0.6))
+                    .diffuse(0.7)
+                    .specular(0.2)
+                    .transparency(1.0)
+                    .refractive_index(1.5),
+            ),
+        ))
+        .object(Box::new(
+            Sphere::default().with_transform(Transform::scaling(0.5, 0.5, 0.5)),
+        ));
+        let r = Ray::default()
+            .origin(0.0, 0.0, -5.0)
+            .direction(0.0, 0.0, 1.0);
+        let shape = w.objects[0].as_ref();
+        let xs = vec![Intersection::new(4.0, shape), Intersection::new(6.0, shape)];
+
+        let comps = xs[0].prepare_computations(r, &xs);
+        let c = w.refracted_color(comps, 0);
+
+        assert_eq!(c, color::BLACK);
+    }
+
+    #[test]
+    fn the_refracted_color_under_total_internal_reflection() {
+        let w = World::new(PointLight::new(
+            Point::new(-10.0, 10.0, -10.0),
+            Color::new(1.0, 1.0, 1.0),
+        ))
+        .object(Box::new(
+            Sphere::default().with_material(
+                Material::default()
+                    .color(Color::new(0.8, 1.0, 0.6))
+                    .diffuse(0.7)
+                    .specular(0.2)
+                    .transparency(1.0)
+                    .refractive_index(1.5),
+            ),
+        ))
+        .object(Box::new(
+            Sphere::default().with_transform(Transform::scaling(0.5, 0.5, 0.5)),
+        ));
+        let r = Ray::default()
+            .origin(0.0, 0.0, sqrt_n_over_n(2))
+            .direction(0.0, 1.0, 0.0);
+        let shape = w.objects[0].as_ref();
+        let xs = vec![
+            Intersection::new(-sqrt_n_over_n(2), shape),
+            Intersection::new(sqrt_n_over_n(2), shape),
+        ];
+
+        let comps = xs[1].prepare_computations(r, &xs);
+        let c = w.refracted_color(comps, 5);
+
+        assert_eq!(c, color::BLACK);
+    }
+
+    #[test]
+    fn the_refracted_color_with_a_refracted_ray() {
+        let w = World::new(PointLight::new(
+            Point::new(-10.0, 10.0, -10.0),
+            Color::new(1.0, 1.0, 1.0),
+        ))
+        .object(Box::new(
+            Sphere::default().with_material(
+                Material::default()
+                    .color(Color::new(0.8, 1.0, 0.6))
+                    .diffuse(0.7)
+                    .specular(0.2)
+                    .ambient(1.0)
+                    .pattern(Box::new(TestPattern::default())),
+            ),
+        ))
+        .object(Box::new(Sphere::default().with_material(
+            Material::default().transparency(1.0).refractive_index(1.5),
+        )));
+        let r = Ray::default()
+            .origin(0.0, 0.0, 0.1)
+            .direction(0.0, 1.0, 0.0);
+        let a = w.objects[0].as_ref();
+        let b = w.objects[1].as_ref();
+        let xs = vec![
+            Intersection::new(-0.9899, a),
+            Intersection::new(-0.4899, b),
+            Intersection::new(0.4899, b),
+            Intersection::new(0.9899, a),
+        ];
+
+        let comps = xs[2].prepare_computations(r, &xs);
+        let color = w.refracted_color(comps, 5);
+
+        assert_eq!(color, Color::new(0.0, 0.99888, 0.04725));
+    }
+
+    #[test]
+    fn the_reflected_color_when_only_refraction_budget_is_left() {
+        let w = World::default().object(Box::new(
+            Plane::default()
+                .with_material(Material::default().reflective(0.5))
+                .with_transform(Transform::translation(0.0, -1.0, 0.0)),
+        ));
+        let shape = w.objects[2].as_ref();
+        let r = Ray::default().origin(0.0, 0.0, -3.0).direction(
+            0.0,
+            -sqrt_n_over_n(2),
+            sqrt_n_over_n(2),
+        );
+        let i = Intersection::new(SQRT_2, shape);
+
+        let comps = i.prepare_computations(r, &[i]);
+
+        assert_eq!(
+            w.reflected_color(comps, DepthBudget::new(0, 10)),
+            color::BLACK
+        );
+        assert_eq!(
+            w.reflected_color(comps, DepthBudget::new(10, 0)),
+            Color::new(0.19032, 0.2379, 0.14274)
+        );
+    }
+
+    #[test]
+    fn the_refracted_color_when_only_reflection_budget_is_left() {
+        let w = glass_sphere_over_a_floor();
+        let r = Ray::new(Point::new(0.0, 0.0, -5.0), Vector::new(0.0, 0.0, 1.0));
+        let xs = w.intersect(r);
+        let comps = xs.hit().unwrap().prepare_computations(r, &xs);
+
+        assert_eq!(
+            w.refracted_color(comps, DepthBudget::new(10, 0)),
+            color::BLACK
+        );
+        assert_ne!(
+            w.refracted_color(comps, DepthBudget::new(0, 10)),
+            color::BLACK
+        );
+    }
+
+    #[test]
+    fn mirrors_reflect_each_other_only_as_often_as_the_budget_allows() {
+        let mirror = |y: f32| {
+            Plane::default()
+                .with_material(Material::default().reflective(1.0))
+                .with_transform(Transform::translation(0.0, y, 0.0))
+        };
+        let w = World::new(PointLight::new(
+            Point::new(0.0, 0.0, 0.0),
+            Color::new(1.0, 1.0, 1.0),
+        ))
+        .object(Box::new(mirror(-1.0)))
+        .object(Box::new(mirror(1.0)));
+        let r = Ray::new(Point::new(0.0, 0.0, 0.0), Vector::new(0.0, 1.0, 0.0));
+
+        let (_, stats) = stats::collect(|| w.color_at(r, DepthBudget::new(4, 1)));
+
+        assert_eq!(stats.reflection_rays, 4);
+    }
+
+    #[test]
+    fn reflections_stop_once_they_contribute_too_little() {
+        let mirror = |y: f32| {
+            Plane::default()
+                .with_material(Material::default().reflective(0.5))
+                .with_transform(Transform::translation(0.0, y, 0.0))
+        };
+        let w = World::new(PointLight::new(
+            Point::new(0.0, 0.0, 0.0),
+            Color::new(1.0, 1.0, 1.0),
+        ))
+        .object(Box::new(mirror(-1.0)))
+        .object(Box::new(mirror(1.0)));
+        let r = Ray::new(Point::new(0.0, 0.0, 0.0), Vector::new(0.0, 1.0, 0.0));
+
+        let (_, all) = stats::collect(|| w.color_at(r, 10));
+        let w = w.min_contribution(0.2);
+        let (_, cut) = stats::collect(|| w.color_at(r, 10));
+
+        assert_eq!(all.reflection_rays, 10);
+        assert_eq!(cut.reflection_rays, 2);
+    }
+
+    #[test]
+    fn russian_roulette_casts_fewer_rays_but_keeps_the_average() {
+        let mirror = |y: f32| {
+            Plane::default()
+                .with_material(Material::default().reflective(0.5).ambient(1.0))
+                .with_transform(Transform::translation(0.0, y, 0.0))
+        };
+        let w = World::new(PointLight::new(
+            Point::new(0.0, 0.0, 0.0),
             Color::new(1.0, 1.0, 1.0),
         ))
-        .object(Box::new(sphere1))
-        .object(Box::new(sphere2.clone()));
+        .object(Box::new(mirror(-1.0)))
+        .object(Box::new(mirror(1.0)));
+        let r = Ray::new(Point::new(0.0, 0.0, 0.0), Vector::new(0.0, 1.0, 0.0));
+        let exact = w.color_at(r, 10);
+
+        let w = w.roulette(Roulette::new(0.5, 7));
+        let runs = 2000;
+        let (sum, stats) =
+            stats::collect(|| (0..runs).fold(color::BLACK, |sum, _| sum + w.color_at(r, 10)));
+        let average = sum * (1.0 / runs as f32);
+
+        assert!(stats.reflection_rays < runs * 4);
+        assert!((average.red() - exact.red()).abs() < 0.05 * exact.red());
+    }
+
+    #[test]
+    fn shade_hit_with_a_transparent_material() {
+        let w = World::default()
+            .object(Box::new(
+                Plane::default()
+                    .with_transform(Transform::translation(0.0, -1.0, 0.0))
+                    .with_material(Material::default().transparency(0.5).refractive_index(1.5)),
+            ))
+            .object(Box::new(
+                Sphere::default()
+                    .with_material(
+                        Material::default()
+                            .color(Color::new(1.0, 0.0, 0.0))
+                            .ambient(0.5),
+                    )
+                    .with_transform(Transform::translation(0.0, -3.5, -0.5)),
+            ));
+        let r = Ray::default().origin(0.0, 0.0, -3.0).direction(
+            0.0,
+            -sqrt_n_over_n(2),
+            sqrt_n_over_n(2),
+        );
+        let floor = w.objects[2].as_ref();
+        let xs = vec![Intersection::new(SQRT_2, floor)];
+
+        let comps = xs[0].prepare_computations(r, &xs);
+        let color = w.shade_hit(comps, 5);
+
+        assert_eq!(color, Color::new(0.93642, 0.686432, 0.68642))
+    }
+
+    #[test]
+    fn shade_hit_with_a_reflective_transparent_material() {
+        let w = World::default()
+            .object(Box::new(
+                Plane::default()
+                    .with_transform(Transform::translation(0.0, -1.0, 0.0))
+                    .with_material(
+                        Material::default()
+                            .reflective(0.5)
+                            .transparency(0.5)
+                            .refractive_index(1.5),
+                    ),
+            ))
+            .object(Box::new(
+                Sphere::default()
+                    .with_material(
+                        Material::default()
+                            .color(Color::new(1.0, 0.0, 0.0))
+                            .ambient(0.5),
+                    )
+                    .with_transform(Transform::translation(0.0, -3.5, -0.5)),
+            ));
+        let floor = w.objects[2].as_ref();
+        let xs = vec![Intersection::new(SQRT_2, floor)];
+        let r = Ray::default().origin(0.0, 0.0, -3.0).direction(
+            0.0,
+            -sqrt_n_over_n(2),
+            sqrt_n_over_n(2),
+        );
+
+        let comps = xs[0].prepare_computations(r, &xs);
+        let color = w.shade_hit(comps, 5);
+
+        assert_eq!(color, Color::new(0.93391, 0.69643, 0.69243));
+    }
+
+    fn glass_sphere_over_a_floor() -> World {
+        World::new(PointLight::new(Point::new(0.0, 10.0, 0.0), color::WHITE))
+            .object(Box::new(Sphere::glass()))
+            .object(Box::new(
+                Plane::default().with_transform(Transform::translation(0.0, -2.0, 0.0)),
+            ))
+    }
+
+    fn glowing_sphere_over_a_floor() -> World {
+        World::new(PointLight::new(Point::new(0.0, 10.0, 0.0), color::BLACK))
+            .emitter_samples(256)
+            .object(Box::new(
+                Sphere::default()
+                    .with_transform(Transform::translation(0.0, 3.0, 0.0))
+                    .with_material(Material::default().emissive(color::WHITE)),
+            ))
+            .object(Box::new(
+                Plane::default().with_material(Material::default().diffuse(1.0).ambient(0.0)),
+            ))
+    }
+
+    #[test]
+    fn an_emissive_surface_glows_without_any_light() {
+        let w = glowing_sphere_over_a_floor();
         let r = Ray::default()
-            .origin(0.0, 0.0, 0.0)
+            .origin(0.0, 3.0, -5.0)
             .direction(0.0, 0.0, 1.0);
-        let i = Intersection::new(1.0, &sphere2);
 
+        let c = w.color_at(r, 5);
+
+        assert!(c.red() >= 1.0);
+    }
+
+    #[test]
+    fn an_emissive_sphere_lights_the_floor_below_it() {
+        let w = glowing_sphere_over_a_floor();
+        let r = Ray::default().origin(0.0, 1.0, -1.0).direction(
+            0.0,
+            -sqrt_n_over_n(2),
+            sqrt_n_over_n(2),
+        );
+        let xs = w.intersect(r);
+        let comps = xs.hit().unwrap().prepare_computations(r, &xs);
+
+        let c = w.emitted_lighting(comps);
+
+        // A uniformly glowing sphere of radius 1 seen from distance 3.
+        assert!((c.red() - 1.0 / 9.0).abs() < 0.005);
+        assert_eq!(w.shade_hit(comps, 5), c);
+    }
+
+    #[test]
+    fn an_emitter_in_a_group_lights_the_floor_like_one_outside() {
+        let mut lamp = Group::new()
+            .with_transform(Transform::translation(0.0, 2.0, 0.0))
+            .with_material(Material::default().emissive(color::WHITE));
+        lamp.add_child(Box::new(
+            Sphere::default().with_transform(Transform::translation(0.0, 1.0, 0.0)),
+        ));
+        let grouped = World::new(PointLight::new(Point::new(0.0, 10.0, 0.0), color::BLACK))
+            .emitter_samples(256)
+            .object(Box::new(lamp))
+            .object(Box::new(
+                Plane::default().with_material(Material::default().diffuse(1.0).ambient(0.0)),
+            ));
+        let r = Ray::default().origin(0.0, 1.0, -1.0).direction(
+            0.0,
+            -sqrt_n_over_n(2),
+            sqrt_n_over_n(2),
+        );
+        let lit = |w: &World| {
+            let xs = w.intersect(r);
+            w.emitted_lighting(w.prepare_computations(xs.hit().unwrap(), r, &xs))
+        };
+
+        assert_eq!(grouped.emitters().count(), 1);
+        assert_eq!(lit(&grouped), lit(&glowing_sphere_over_a_floor()));
+    }
+
+    #[test]
+    fn an_emitter_does_not_light_surfaces_it_is_hidden_from() {
+        let w = glowing_sphere_over_a_floor().object(Box::new(
+            Plane::default().with_transform(Transform::translation(0.0, 1.0, 0.0)),
+        ));
+        let r = Ray::default().origin(0.0, 0.5, -1.0).direction(
+            0.0,
+            -sqrt_n_over_n(2),
+            sqrt_n_over_n(2),
+        );
+        let xs = w.intersect(r);
+        let comps = xs.hit().unwrap().prepare_computations(r, &xs);
+
+        assert_eq!(w.emitted_lighting(comps), color::BLACK);
+    }
+
+    fn room_with_a_skylight() -> World {
+        let roof = |x| {
+            Cube::default().with_transform(
+                Transform::translation(x, 3.0, 0.0) * Transform::scaling(5.0, 0.5, 20.0),
+            )
+        };
+        World::new(PointLight::new(Point::new(0.0, 1.0, 0.0), color::BLACK))
+            .background(color::WHITE)
+            .emitter_samples(256)
+            .object(Box::new(Plane::default().with_material(
+                Material::default().diffuse(1.0).ambient(0.0).specular(0.0),
+            )))
+            .object(Box::new(roof(-5.5)))
+            .object(Box::new(roof(5.5)))
+            .portal(Portal::new(
+                Point::new(-0.5, 3.5, -20.0),
+                Vector::new(1.0, 0.0, 0.0),
+                Vector::new(0.0, 0.0, 40.0),
+            ))
+    }
+
+    fn floor_portal_lighting(w: &World, x: f32) -> Color {
+        let r =
+            Ray::default()
+                .origin(x, 1.0, -1.0)
+                .direction(0.0, -sqrt_n_over_n(2), sqrt_n_over_n(2));
+        let xs = w.intersect(r);
+        let comps = xs.hit().unwrap().prepare_computations(r, &xs);
+        w.portal_lighting(comps)
+    }
+
+    #[test]
+    fn unshadowed_lighting_ignores_occluders() {
+        let w = World::new(PointLight::new(Point::new(0.0, 0.0, -10.0), color::WHITE))
+            .object(Box::new(Sphere::default()))
+            .object(Box::new(
+                Sphere::default().with_transform(Transform::translation(0.0, 0.0, 10.0)),
+            ));
+        let r = Ray::new(Point::new(0.0, 0.0, 5.0), Vector::new(0.0, 0.0, 1.0));
+        let i = Intersection::new(4.0, w.objects[1].as_ref());
         let comps = i.prepare_computations(r, &[i]);
-        let color = w.reflected_color(comps, 3);
 
-        assert_eq!(color, color::BLACK);
+        assert_eq!(w.unshadowed_lighting(comps), Color::new(1.9, 1.9, 1.9));
+        assert_eq!(w.shade_hit(comps, 0), Color::new(0.1, 0.1, 0.1));
+    }
+
+    #[test]
+    fn merging_worlds_adds_the_others_objects() {
+        let table = World::new(PointLight::default()).object(Box::new(Cube::default()));
+        let cube = table.objects[0].id();
+
+        let (w, light) = World::default().merge(table);
+
+        assert_eq!(w.objects.len(), 3);
+        assert_eq!(w.objects[2].id(), cube);
+        assert_eq!(w.light(), World::default().light());
+        assert_eq!(light, PointLight::default());
+    }
+
+    #[test]
+    fn merging_with_a_transform_moves_everything_in_the_other_world() {
+        let mut inner = Group::new().with_transform(Transform::translation(0.0, 1.0, 0.0));
+        inner.add_child(Box::new(Sphere::default()));
+        let inner: Box<dyn Shape> = Box::new(inner);
+        let (group, child) = (inner.id(), inner.children()[0].id());
+        let room = World::new(PointLight::default().position(0.0, 5.0, 0.0))
+            .object(inner)
+            .portal(Portal::new(
+                Point::new(0.0, 0.0, 0.0),
+                Vector::new(1.0, 0.0, 0.0),
+                Vector::new(0.0, 1.0, 0.0),
+            ))
+            .volume(Volume::new(Box::new(Sphere::default()), 1.0, color::WHITE))
+            .animation(Animation::new(group, |t| {
+                Transform::translation(0.0, t, 0.0)
+            }))
+            .animation(Animation::new(child, |t| {
+                Transform::translation(t, 0.0, 0.0)
+            }));
+        let mut w = World::new(PointLight::default());
+        w.build_index();
+
+        let (w, light) = w.merge_with_transform(room, Transform::translation(10.0, 0.0, 0.0));
+
+        assert_eq!(
+            *w.objects[0].transform(),
+            Transform::translation(10.0, 1.0, 0.0)
+        );
+        assert_eq!(w.portals[0].corner, Point::new(10.0, 0.0, 0.0));
+        assert_eq!(w.portals[0].uvec, Vector::new(1.0, 0.0, 0.0));
+        assert_eq!(
+            *w.volumes[0].boundary.transform(),
+            Transform::translation(10.0, 0.0, 0.0)
+        );
+        assert_eq!(
+            w.animations[0].transform_at(2.0),
+            Transform::translation(10.0, 2.0, 0.0)
+        );
+        assert_eq!(
+            w.animations[1].transform_at(2.0),
+            Transform::translation(2.0, 0.0, 0.0)
+        );
+        let r = Ray::new(Point::new(10.0, 1.0, -5.0), Vector::new(0.0, 0.0, 1.0));
+        assert!(float_eq(w.intersect(r).hit().unwrap().t, 4.0));
+        assert_eq!(light.transformed_position(), Point::new(10.0, 5.0, 0.0));
+    }
+
+    #[test]
+    fn vertex_colors_replace_the_material_color_when_shading() {
+        let triangle = Triangle::new(
+            Point::new(0.0, 1.0, 0.0),
+            Point::new(-1.0, 0.0, 0.0),
+            Point::new(1.0, 0.0, 0.0),
+        )
+        .with_vertex_colors([color::WHITE, color::WHITE, Color::new(1.0, 0.0, 0.0)])
+        .with_material(
+            Material::default()
+                .pattern(Box::new(Checkered::new(color::BLACK, color::BLACK)))
+                .ambient(1.0)
+                .diffuse(0.0)
+                .specular(0.0),
+        );
+        let w = World::new(PointLight::new(Point::new(0.0, 0.0, -10.0), color::WHITE))
+            .object(Box::new(triangle));
+        let r = Ray::new(Point::new(0.5, 0.25, -5.0), Vector::new(0.0, 0.0, 1.0));
+        let xs = w.intersect(r);
+        let comps = xs[0].prepare_computations(r, &xs);
+
+        assert_eq!(w.shade_hit(comps, 0), Color::new(1.0, 0.375, 0.375));
+    }
+
+    #[test]
+    fn the_color_when_a_ray_misses_is_the_background() {
+        let w = World::default().background(Color::new(0.2, 0.4, 0.8));
+        let r = Ray::default()
+            .origin(0.0, 0.0, -5.0)
+            .direction(0.0, 1.0, 0.0);
+
+        assert_eq!(w.color_at(r, 5), Color::new(0.2, 0.4, 0.8));
+    }
+
+    #[derive(Debug)]
+    struct UpAndDown;
+
+    impl Environment for UpAndDown {
+        fn color_in(&self, direction: Vector) -> Color {
+            if direction.y() > 0.0 {
+                color::WHITE
+            } else {
+                Color::new(0.2, 0.2, 0.2)
+            }
+        }
+    }
+
+    #[test]
+    fn a_ray_that_misses_sees_the_environment_in_its_direction() {
+        let w = World::default().environment(UpAndDown);
+        let up = Ray::new(Point::new(0.0, 0.0, -5.0), Vector::new(0.0, 1.0, 0.0));
+        let down = Ray::new(Point::new(0.0, 0.0, -5.0), Vector::new(0.0, -1.0, 0.0));
+
+        assert_eq!(w.color_at(up, 5), color::WHITE);
+        assert_eq!(w.color_at(down, 5), Color::new(0.2, 0.2, 0.2));
+    }
+
+    #[test]
+    fn a_mirror_reflects_the_environment() {
+        let w = World::new(PointLight::default())
+            .environment(UpAndDown)
+            .object(Box::new(
+                Plane::default().with_material(
+                    Material::default()
+                        .reflective(1.0)
+                        .ambient(0.0)
+                        .diffuse(0.0)
+                        .specular(0.0),
+                ),
+            ));
+        let r = Ray::new(
+            Point::new(0.0, 1.0, -1.0),
+            Vector::new(0.0, -1.0, 1.0).normalize(),
+        );
+
+        assert_eq!(w.color_at(r, 5), color::WHITE);
+    }
+
+    #[test]
+    fn the_sky_lights_the_floor_below_a_portal() {
+        let w = room_with_a_skylight();
+
+        assert!(floor_portal_lighting(&w, 0.0).red() > 0.0);
+    }
+
+    #[test]
+    fn a_portal_does_not_light_surfaces_that_cannot_see_through_it() {
+        let w = room_with_a_skylight();
+
+        assert_eq!(floor_portal_lighting(&w, 9.0), color::BLACK);
+    }
+
+    #[test]
+    fn rays_through_a_portal_escape() {
+        let w = room_with_a_skylight();
+
+        assert!(w.escapes_through_portal(Ray::new(
+            Point::new(0.0, 0.0, 0.0),
+            Vector::new(0.0, 1.0, 0.0)
+        )));
+        assert!(!w.escapes_through_portal(Ray::new(
+            Point::new(5.0, 0.0, 0.0),
+            Vector::new(0.0, 1.0, 0.0)
+        )));
+    }
+
+    #[test]
+    fn there_are_no_caustics_without_a_photon_map() {
+        let w = glass_sphere_over_a_floor();
+        let r = Ray::default().origin(0.0, -1.0, -3.0).direction(
+            0.0,
+            -sqrt_n_over_n(2),
+            sqrt_n_over_n(2),
+        );
+        let xs = w.intersect(r);
+        let comps = xs.hit().unwrap().prepare_computations(r, &xs);
+
+        assert_eq!(w.caustic_color(comps), color::BLACK);
+    }
+
+    #[test]
+    fn photons_focused_by_a_glass_sphere_light_the_floor_below_it() {
+        let mut w = glass_sphere_over_a_floor();
+        w.build_photon_map(PhotonSettings {
+            photons: 50_000,
+            radius: 0.25,
+            max_bounces: 5,
+        });
+        let map = w.photon_map.as_ref().unwrap();
+
+        let below = map.irradiance_at(Point::new(0.0, -2.0, 0.0));
+        let aside = map.irradiance_at(Point::new(4.0, -2.0, 0.0));
+
+        assert!(!map.is_empty());
+        assert!(below.red() > 1.0);
+        assert_eq!(aside, color::BLACK);
+    }
+
+    #[test]
+    fn a_photon_that_never_meets_a_specular_surface_is_not_stored() {
+        let w = World::new(PointLight::new(Point::new(0.0, 10.0, 0.0), color::WHITE)).object(
+            Box::new(Plane::default().with_transform(Transform::translation(0.0, -2.0, 0.0))),
+        );
+        let r = Ray::default()
+            .origin(0.0, 10.0, 0.0)
+            .direction(0.0, -1.0, 0.0);
+
+        assert_eq!(w.trace_photon(r, PhotonSettings::default()), None);
+    }
+
+    fn spheres_in_a_row() -> World {
+        (0..10).fold(World::default(), |w, i| {
+            w.object(Box::new(Sphere::default().with_transform(
+                Transform::translation(i as f32 * 3.0, 0.0, 10.0),
+            )))
+        })
+    }
+
+    #[test]
+    fn an_indexed_world_finds_the_same_intersections() {
+        let mut w = spheres_in_a_row();
+        let rays = [
+            Ray::new(Point::new(0.0, 0.0, -5.0), Vector::new(0.0, 0.0, 1.0)),
+            Ray::new(Point::new(9.0, 0.0, -5.0), Vector::new(0.0, 0.0, 1.0)),
+            Ray::new(Point::new(-5.0, 0.0, 10.0), Vector::new(1.0, 0.0, 0.0)),
+            Ray::new(Point::new(1.5, 5.0, 0.0), Vector::new(0.0, -1.0, 0.0)),
+        ];
+        let linear = rays
+            .iter()
+            .map(|&r| w.intersect(r).iter().map(|i| i.t).collect::<Vec<_>>())
+            .collect::<Vec<_>>();
+
+        w.build_index();
+        let indexed = rays
+            .iter()
+            .map(|&r| w.intersect(r).iter().map(|i| i.t).collect::<Vec<_>>())
+            .collect::<Vec<_>>();
+
+        assert!(w.is_indexed());
+        assert_eq!(indexed, linear);
+    }
+
+    #[test]
+    fn an_indexed_world_tests_fewer_objects() {
+        let mut w = spheres_in_a_row();
+        let r = Ray::new(Point::new(9.0, 0.0, -5.0), Vector::new(0.0, 0.0, 1.0));
+
+        let (_, linear) = stats::collect(|| w.intersect(r));
+        w.build_index();
+        let (_, indexed) = stats::collect(|| w.intersect(r));
+
+        assert_eq!(linear.total_intersection_tests(), 12);
+        assert_eq!(indexed.total_intersection_tests(), 1);
+    }
+
+    #[test]
+    fn a_kd_tree_finds_the_same_intersections_as_a_bvh() {
+        let mut w = spheres_in_a_row().object(Box::new(Plane::default()));
+        let rays = [
+            Ray::new(Point::new(9.0, 0.0, -5.0), Vector::new(0.0, 0.0, 1.0)),
+            Ray::new(Point::new(-5.0, 0.0, 10.0), Vector::new(1.0, 0.0, 0.0)),
+            Ray::new(Point::new(1.5, 5.0, 0.0), Vector::new(0.2, -1.0, 0.3)),
+        ];
+        let hits = |w: &World| {
+            rays.iter()
+                .map(|&r| w.intersect(r).iter().map(|i| i.t).collect::<Vec<_>>())
+                .collect::<Vec<_>>()
+        };
+
+        w.build_index();
+        let (bvh_hits, bvh) = stats::collect(|| hits(&w));
+        w.build_index_of(IndexKind::KdTree);
+        let (kd_hits, kd) = stats::collect(|| hits(&w));
+
+        assert_eq!(w.index_kind(), Some(IndexKind::KdTree));
+        assert_eq!(kd_hits, bvh_hits);
+        assert_eq!(kd.index_candidates, bvh.index_candidates);
+        assert!(kd.index_nodes_visited > 0 && bvh.index_nodes_visited > 0);
+    }
+
+    #[test]
+    fn the_index_is_rebuilt_as_the_same_kind() {
+        let mut w = World::default();
+        w.build_index_of(IndexKind::KdTree);
+        let w = w.object(Box::new(
+            Sphere::default().with_transform(Transform::translation(10.0, 0.0, 0.0)),
+        ));
+        let r = Ray::new(Point::new(10.0, 0.0, -5.0), Vector::new(0.0, 0.0, 1.0));
+
+        assert_eq!(w.index_kind(), Some(IndexKind::KdTree));
+        assert_eq!(w.intersect(r).len(), 2);
+    }
+
+    #[test]
+    fn an_indexed_world_still_tests_infinite_objects() {
+        let mut w = World::default().object(Box::new(
+            Plane::default().with_transform(Transform::translation(0.0, -1.0, 0.0)),
+        ));
+        w.build_index();
+        let r = Ray::new(Point::new(10.0, 5.0, 0.0), Vector::new(0.0, -1.0, 0.0));
+
+        let xs = w.intersect(r);
+
+        assert_eq!(xs.len(), 1);
+        assert!(float_eq(xs[0].t, 6.0));
+    }
+
+    #[test]
+    fn the_index_is_rebuilt_when_an_object_is_added() {
+        let mut w = World::default();
+        w.build_index();
+        let w = w.object(Box::new(
+            Sphere::default().with_transform(Transform::translation(10.0, 0.0, 0.0)),
+        ));
+        let r = Ray::new(Point::new(10.0, 0.0, -5.0), Vector::new(0.0, 0.0, 1.0));
+
+        assert_eq!(w.intersect(r).len(), 2);
     }
 
     #[test]
-    fn the_reflected_color_for_a_reflective_material() {
-        let w = World::default().object(Box::new(
-            Plane::default()
-                .with_material(Material::default().reflective(0.5))
-                .with_transform(Transform::translation(0.0, -1.0, 0.0)),
-        ));
-        let shape = w.objects[2].as_ref();
-        let r = Ray::default().origin(0.0, 0.0, -3.0).direction(
-            0.0,
-            -sqrt_n_over_n(2),
-            sqrt_n_over_n(2),
-        );
-        let i = Intersection::new(SQRT_2, shape);
+    fn adding_objects_leaves_the_index_to_be_built_by_the_next_ray() {
+        let mut w = World::default();
+        w.build_index();
+        let w = (1..4).fold(w, |w, i| {
+            w.object(Box::new(Sphere::default().with_transform(
+                Transform::translation(i as f32 * 3.0, 0.0, 0.0),
+            )))
+        });
+
+        assert!(w.index.get().is_none());
+        let r = Ray::new(Point::new(9.0, 0.0, -5.0), Vector::new(0.0, 0.0, 1.0));
+        assert_eq!(w.intersect(r).len(), 2);
+        assert!(w.index.get().is_some());
+    }
 
-        let comps = i.prepare_computations(r, &[i]);
-        let color = w.reflected_color(comps, 10);
+    #[test]
+    fn the_index_is_rebuilt_when_an_object_moves() {
+        let mut w = World::default();
+        w.build_index();
+        w.update_object(0, |o| {
+            o.set_transform(Transform::translation(10.0, 0.0, 0.0))
+        });
+        let r = Ray::new(Point::new(10.0, 0.0, -5.0), Vector::new(0.0, 0.0, 1.0));
 
-        assert_eq!(color, Color::new(0.19032, 0.2379, 0.14274));
+        let xs = w.intersect(r);
+
+        assert_eq!(xs.len(), 2);
+        assert_eq!(xs[0].object.id(), w.objects[0].id());
     }
 
     #[test]
-    fn shade_hit_with_a_reflective_material() {
+    fn shadows_are_found_through_the_index() {
         let mut w = World::default();
-        let shape = Plane::default()
-            .with_material(Material::default().reflective(0.5))
-            .with_transform(Transform::translation(0.0, -1.0, 0.0));
-        w.objects.push(Box::new(shape.clone()));
-        let r = Ray::default()
-            .origin(0.0, 0.0, -3.0)
-            .direction(0.0, -SQRT_2 / 2.0, SQRT_2 / 2.0);
-        let i = Intersection::new(SQRT_2, &shape);
+        w.build_index();
 
-        let comps = i.prepare_computations(r, &[i]);
-        let color = w.shade_hit(comps, 5);
+        assert!(w.is_shadowed(Point::new(10.0, -10.0, 10.0)));
+        assert!(!w.is_shadowed(Point::new(-20.0, 20.0, -20.0)));
+    }
 
-        assert_eq!(color, Color::new(0.87677, 0.92436, 0.82918));
+    fn red_group_around(child: Sphere) -> (World, Uuid) {
+        let child_id = child.id();
+        let mut g =
+            Group::new().with_material(Material::default().color(Color::new(1.0, 0.0, 0.0)));
+        g.add_child(Box::new(child));
+
+        let light = PointLight::new(Point::new(0.0, 0.0, -10.0), Color::new(1.0, 1.0, 1.0));
+        (World::new(light).object(Box::new(g)), child_id)
     }
 
     #[test]
-    fn color_at_with_mutually_reflective_surfaces() {
-        let lower = Plane::default()
-            .with_material(Material::default().reflective(1.0))
-            .with_transform(Transform::translation(0.0, -1.0, 0.0));
-        let upper = Plane::default()
-            .with_material(Material::default().reflective(1.0))
-            .with_transform(Transform::translation(0.0, 1.0, 0.0));
-        let w = World::new(PointLight::new(Tuple::point(0.0, 0.0, 0.0), color::WHITE))
-            .object(Box::new(lower))
-            .object(Box::new(upper));
-        let r = Ray::default()
-            .origin(0.0, 0.0, 0.0)
-            .direction(0.0, 1.0, 0.0);
+    fn finding_a_shape_inside_a_group() {
+        let (w, child_id) = red_group_around(Sphere::new());
 
-        let _ = w.color_at(r, 3);
+        assert_eq!(w.find_object(child_id).map(|o| o.id()), Some(child_id));
+        assert!(w.find_object(Uuid::new_v4()).is_none());
     }
 
-    // TODO: maybe revisit infinite recursion, so far seems to be fine maybe because of compiler optimizations?
+    #[test]
+    fn finding_shapes_by_tag() {
+        let (w, child_id) = red_group_around(Sphere::new().with_tag("role", "hero"));
+        let w = w.object(Box::new(Sphere::new().with_tag("role", "extra")));
+
+        let heroes = w.find_tagged("role", "hero");
+
+        assert_eq!(heroes.len(), 1);
+        assert_eq!(heroes[0].id(), child_id);
+        assert!(w.find_tagged("role", "villain").is_empty());
+    }
 
     #[test]
-    fn the_refracted_color_with_an_opaque_surface() {
-        let w = World::default();
-        let shape = w.objects[0].as_ref();
-        let r = Ray::default()
-            .origin(0.0, 0.0, -5.0)
-            .direction(0.0, 0.0, 1.0);
-        let xs = vec![Intersection::new(4.0, shape), Intersection::new(6.0, shape)];
+    fn a_shape_inherits_tags_from_its_groups() {
+        let s = Sphere::new();
+        let s_id = s.id();
+        let mut g = Group::new()
+            .with_tag("layer", "background")
+            .with_tag("role", "set");
+        g.add_child(Box::new(s.with_tag("role", "tree")));
+        let w = World::new(PointLight::new(Point::new(0.0, 0.0, -10.0), color::WHITE))
+            .object(Box::new(g));
+        let s = w.find_object(s_id).unwrap();
+
+        assert_eq!(w.tag_of(s, "role"), Some("tree"));
+        assert_eq!(w.tag_of(s, "layer"), Some("background"));
+        assert_eq!(w.tag_of(s, "lod"), None);
+    }
 
-        let comps = xs[0].prepare_computations(r, &xs);
-        let c = w.refracted_color(comps, 5);
+    #[test]
+    fn a_shape_with_the_default_material_inherits_its_groups() {
+        let (w, child_id) = red_group_around(Sphere::new());
+        let child = w.find_object(child_id).unwrap();
 
-        assert_eq!(c, color::BLACK);
+        assert_eq!(w.material_of(child).color, Color::new(1.0, 0.0, 0.0));
     }
 
     #[test]
-    fn the_refracted_color_at_the_maximum_recursive_depth() {
-        let w = World::new(PointLight::new(
-            Tuple::point(-10.0, 10.0, -10.0),
-            Color::new(1.0, 1.0, 1.0),
-        ))
-        .object(Box::new(
-            Sphere::default().with_material(
-                Material::default()
-                    .color(Color::new(0.8, 1.0, 0.6))
-                    .diffuse(0.7)
-                    .specular(0.2)
-                    .transparency(1.0)
-                    .refractive_index(1.5),
-            ),
-        ))
-        .object(Box::new(
-            Sphere::default().with_transform(Transform::scaling(0.5, 0.5, 0.5)),
-        ));
-        let r = Ray::default()
-            .origin(0.0, 0.0, -5.0)
-            .direction(0.0, 0.0, 1.0);
-        let shape = w.objects[0].as_ref();
-        let xs = vec![Intersection::new(4.0, shape), Intersection::new(6.0, shape)];
+    fn a_shape_keeps_a_material_of_its_own() {
+        let (w, child_id) = red_group_around(
+            Sphere::new().with_material(Material::default().color(Color::new(0.0, 0.0, 1.0))),
+        );
+        let child = w.find_object(child_id).unwrap();
 
-        let comps = xs[0].prepare_computations(r, &xs);
-        let c = w.refracted_color(comps, 0);
+        assert_eq!(w.material_of(child).color, Color::new(0.0, 0.0, 1.0));
+    }
 
-        assert_eq!(c, color::BLACK);
+    #[test]
+    fn materials_are_inherited_through_nested_groups() {
+        let s = Sphere::new();
+        let s_id = s.id();
+        let mut inner = Group::new();
+        inner.add_child(Box::new(s));
+        let mut outer =
+            Group::new().with_material(Material::default().color(Color::new(1.0, 0.0, 0.0)));
+        outer.add_child(Box::new(inner));
+        let w = World::default().object(Box::new(outer));
+
+        let s = w.find_object(s_id).unwrap();
+
+        assert_eq!(w.material_of(s).color, Color::new(1.0, 0.0, 0.0));
     }
 
     #[test]
-    fn the_refracted_color_under_total_internal_reflection() {
-        let w = World::new(PointLight::new(
-            Tuple::point(-10.0, 10.0, -10.0),
-            Color::new(1.0, 1.0, 1.0),
-        ))
-        .object(Box::new(
-            Sphere::default().with_material(
-                Material::default()
-                    .color(Color::new(0.8, 1.0, 0.6))
-                    .diffuse(0.7)
-                    .specular(0.2)
-                    .transparency(1.0)
-                    .refractive_index(1.5),
-            ),
-        ))
-        .object(Box::new(
-            Sphere::default().with_transform(Transform::scaling(0.5, 0.5, 0.5)),
-        ));
-        let r = Ray::default()
-            .origin(0.0, 0.0, sqrt_n_over_n(2))
-            .direction(0.0, 1.0, 0.0);
-        let shape = w.objects[0].as_ref();
-        let xs = vec![
-            Intersection::new(-sqrt_n_over_n(2), shape),
-            Intersection::new(sqrt_n_over_n(2), shape),
-        ];
+    fn shading_a_shape_that_inherits_its_groups_material() {
+        let (w, _) =
+            red_group_around(Sphere::new().with_transform(Transform::translation(0.0, 0.0, 5.0)));
+        let r = Ray::new(Point::new(0.0, 0.0, -5.0), Vector::new(0.0, 0.0, 1.0));
 
-        let comps = xs[1].prepare_computations(r, &xs);
-        let c = w.refracted_color(comps, 5);
+        let red = Sphere::new()
+            .with_transform(Transform::translation(0.0, 0.0, 5.0))
+            .with_material(Material::default().color(Color::new(1.0, 0.0, 0.0)));
+        let expected = World::new(w.light_source).object(Box::new(red));
 
-        assert_eq!(c, color::BLACK);
+        assert_eq!(w.color_at(r, 5), expected.color_at(r, 5));
     }
 
     #[test]
-    fn the_refracted_color_with_a_refracted_ray() {
-        let w = World::new(PointLight::new(
-            Tuple::point(-10.0, 10.0, -10.0),
-            Color::new(1.0, 1.0, 1.0),
-        ))
-        .object(Box::new(
-            Sphere::default().with_material(
-                Material::default()
-                    .color(Color::new(0.8, 1.0, 0.6))
-                    .diffuse(0.7)
-                    .specular(0.2)
-                    .ambient(1.0)
-                    .pattern(Box::new(TestPattern::default())),
-            ),
+    fn a_shape_takes_its_normals_facing_from_its_groups_material() {
+        let mut group =
+            Group::new().with_material(Material::default().flip_normal(true).two_sided(false));
+        group.add_child(Box::new(Sphere::new()));
+        let w = World::default().object(Box::new(group));
+        let r = Ray::new(Point::new(0.0, 0.0, -5.0), Vector::new(0.0, 0.0, 1.0));
+        let xs = w.objects[2].intersect(r);
+
+        let comps = w.prepare_computations(&xs[0], r, &xs);
+
+        assert_eq!(comps.normalv, Vector::new(0.0, 0.0, 1.0));
+        assert_eq!(
+            xs[0].prepare_computations(r, &xs).normalv,
+            Vector::new(0.0, 0.0, -1.0)
+        );
+    }
+
+    #[test]
+    fn a_pattern_is_averaged_over_the_footprint_of_a_ray() {
+        let floor = Plane::default()
+            .with_transform(Transform::scaling(0.5, 0.5, 0.5))
+            .with_material(
+                Material::default().pattern(Box::new(Checkered::new(color::WHITE, color::BLACK))),
+            );
+        let light = PointLight::new(Point::new(0.0, 10.0, 0.0), color::WHITE);
+        let w = World::new(light).object(Box::new(floor));
+        let r = Ray::new(Point::new(0.25, 1.0, 0.25), Vector::new(0.0, -1.0, 0.0));
+        let wide = r.spread(4.0);
+
+        let xs = w.intersect(r);
+        let sharp = xs.hit().unwrap().prepare_computations(r, &xs);
+        let ys = w.intersect(wide);
+        let blurred = ys.hit().unwrap().prepare_computations(wide, &ys);
+
+        assert_eq!(
+            w.shading_material(&sharp)
+                .color_at(sharp.object, sharp.point),
+            color::WHITE
+        );
+        assert!(float_eq(blurred.footprint, 4.0));
+        assert_eq!(
+            w.shading_material(&blurred)
+                .color_at(blurred.object, blurred.point),
+            Color::new(0.5, 0.5, 0.5)
+        );
+    }
+
+    #[test]
+    fn a_pattern_stays_on_a_shape_inside_a_moved_group() {
+        let placement = Transform::translation(1.0, 0.0, 5.0) * Transform::scaling(2.0, 2.0, 2.0);
+        let child = Transform::translation(0.25, 0.0, 0.0);
+        let material = Material::default()
+            .pattern(Box::new(TestPattern::default()))
+            .ambient(1.0);
+        let mut g = Group::new().with_transform(placement);
+        g.add_child(Box::new(
+            Sphere::new()
+                .with_transform(child)
+                .with_material(material.clone()),
+        ));
+        let light = PointLight::new(Point::new(-10.0, 10.0, -10.0), color::WHITE);
+        let w = World::new(light).object(Box::new(g));
+        let r = Ray::new(Point::new(1.5, 0.5, -5.0), Vector::new(0.0, 0.0, 1.0));
+
+        let ungrouped = Sphere::new()
+            .with_transform(placement * child)
+            .with_material(material);
+        let expected = World::new(light).object(Box::new(ungrouped));
+
+        assert_eq!(w.color_at(r, 5), expected.color_at(r, 5));
+    }
+
+    fn cut_away_world() -> World {
+        World::default().clip_plane(ClipPlane::new(
+            Point::new(0.0, 0.0, 0.0),
+            Vector::new(0.0, 0.0, -1.0),
         ))
-        .object(Box::new(Sphere::default().with_material(
-            Material::default().transparency(1.0).refractive_index(1.5),
-        )));
-        let r = Ray::default()
-            .origin(0.0, 0.0, 0.1)
-            .direction(0.0, 1.0, 0.0);
-        let a = w.objects[0].as_ref();
-        let b = w.objects[1].as_ref();
-        let xs = vec![
-            Intersection::new(-0.9899, a),
-            Intersection::new(-0.4899, b),
-            Intersection::new(0.4899, b),
-            Intersection::new(0.9899, a),
-        ];
+    }
 
-        let comps = xs[2].prepare_computations(r, &xs);
-        let color = w.refracted_color(comps, 5);
+    #[test]
+    fn a_clip_plane_discards_intersections_on_its_far_side() {
+        let w = cut_away_world();
+        let r = Ray::new(Point::new(0.0, 0.0, -5.0), Vector::new(0.0, 0.0, 1.0));
 
-        assert_eq!(color, Color::new(0.0, 0.99888, 0.04725));
+        let xs = w.intersect(r);
+
+        assert_eq!(xs.len(), 2);
+        assert!(float_eq(xs[0].t, 5.5));
+        assert!(float_eq(xs[1].t, 6.0));
     }
 
     #[test]
-    fn shade_hit_with_a_transparent_material() {
-        let w = World::default()
-            .object(Box::new(
-                Plane::default()
-                    .with_transform(Transform::translation(0.0, -1.0, 0.0))
-                    .with_material(Material::default().transparency(0.5).refractive_index(1.5)),
-            ))
-            .object(Box::new(
-                Sphere::default()
-                    .with_material(
-                        Material::default()
-                            .color(Color::new(1.0, 0.0, 0.0))
-                            .ambient(0.5),
-                    )
-                    .with_transform(Transform::translation(0.0, -3.5, -0.5)),
-            ));
-        let r = Ray::default().origin(0.0, 0.0, -3.0).direction(
-            0.0,
-            -sqrt_n_over_n(2),
-            sqrt_n_over_n(2),
-        );
-        let floor = w.objects[2].as_ref();
-        let xs = vec![Intersection::new(SQRT_2, floor)];
+    fn a_ray_behind_a_clip_plane_is_unaffected() {
+        let w = cut_away_world();
+        let r = Ray::new(Point::new(0.0, 5.0, 0.5), Vector::new(0.0, -1.0, 0.0));
 
-        let comps = xs[0].prepare_computations(r, &xs);
-        let color = w.shade_hit(comps, 5);
+        let xs = w.intersect(r);
 
-        assert_eq!(color, Color::new(0.93642, 0.686432, 0.68642))
+        assert_eq!(xs.len(), 4);
     }
 
     #[test]
-    fn shade_hit_with_a_reflective_transparent_material() {
-        let w = World::default()
-            .object(Box::new(
-                Plane::default()
-                    .with_transform(Transform::translation(0.0, -1.0, 0.0))
-                    .with_material(
-                        Material::default()
-                            .reflective(0.5)
-                            .transparency(0.5)
-                            .refractive_index(1.5),
-                    ),
-            ))
-            .object(Box::new(
-                Sphere::default()
-                    .with_material(
-                        Material::default()
-                            .color(Color::new(1.0, 0.0, 0.0))
-                            .ambient(0.5),
-                    )
-                    .with_transform(Transform::translation(0.0, -3.5, -0.5)),
-            ));
-        let floor = w.objects[2].as_ref();
-        let xs = vec![Intersection::new(SQRT_2, floor)];
-        let r = Ray::default().origin(0.0, 0.0, -3.0).direction(
-            0.0,
-            -sqrt_n_over_n(2),
-            sqrt_n_over_n(2),
+    fn a_cut_away_shows_the_inside_of_a_sphere() {
+        let s = Sphere::default().with_material(
+            Material::default()
+                .color(Color::new(1.0, 0.0, 0.0))
+                .ambient(1.0)
+                .diffuse(0.0)
+                .specular(0.0),
         );
+        let light = PointLight::new(Point::new(0.0, 0.0, -10.0), color::WHITE);
+        let w = World::new(light)
+            .object(Box::new(s))
+            .clip_plane(ClipPlane::new(
+                Point::new(0.0, 0.0, 0.0),
+                Vector::new(0.0, 0.0, -1.0),
+            ));
+        let r = Ray::new(Point::new(0.0, 0.0, -5.0), Vector::new(0.0, 0.0, 1.0));
 
-        let comps = xs[0].prepare_computations(r, &xs);
-        let color = w.shade_hit(comps, 5);
+        let xs = w.intersect(r);
+        let comps = xs.hit().unwrap().prepare_computations(r, &xs);
 
-        assert_eq!(color, Color::new(0.93391, 0.69643, 0.69243));
+        assert!(float_eq(xs.hit().unwrap().t, 6.0));
+        assert_eq!(comps.normalv, Vector::new(0.0, 0.0, -1.0));
+        assert_eq!(w.color_at(r, 5), Color::new(1.0, 0.0, 0.0));
+    }
+
+    #[test]
+    fn clipped_surfaces_cast_no_shadows() {
+        let w = World::default().clip_plane(ClipPlane::new(
+            Point::new(0.0, 0.0, -2.0),
+            Vector::new(0.0, 0.0, 1.0),
+        ));
+
+        assert!(!w.is_shadowed(Point::new(10.0, -10.0, 10.0)));
+    }
+
+    #[test]
+    fn a_shape_hidden_from_the_camera_still_casts_a_shadow() {
+        let caster = Sphere::default()
+            .with_transform(Transform::translation(0.0, 2.0, 0.0))
+            .with_visibility(Visibility::default().visible_to_camera(false));
+        let floor = Plane::default();
+        let light = PointLight::new(Point::new(0.0, 10.0, 0.0), color::WHITE);
+        let w = World::new(light)
+            .object(Box::new(caster))
+            .object(Box::new(floor));
+        let r = Ray::new(Point::new(0.0, 5.0, 0.0), Vector::new(0.0, -1.0, 0.0));
+
+        let xs = w.intersect(r);
+
+        assert_eq!(xs.len(), 1);
+        assert!(w.is_shadowed(Point::new(0.0, 0.0, 0.0)));
+    }
+
+    #[test]
+    fn a_shape_hidden_from_shadows_does_not_block_the_light() {
+        let card = Cube::default()
+            .with_transform(Transform::translation(0.0, 5.0, 0.0))
+            .with_visibility(Visibility::default().visible_to_shadows(false));
+        let light = PointLight::new(Point::new(0.0, 10.0, 0.0), color::WHITE);
+        let w = World::new(light).object(Box::new(card));
+        let r = Ray::new(Point::new(0.0, 0.0, 0.0), Vector::new(0.0, 1.0, 0.0));
+
+        assert_eq!(w.intersect(r).len(), 2);
+        assert!(!w.is_shadowed(Point::new(0.0, 0.0, 0.0)));
+    }
+
+    #[test]
+    fn a_shape_hidden_from_reflections_is_not_reflected() {
+        let reflection = |visibility: Visibility| {
+            let sphere = Sphere::default()
+                .with_transform(Transform::translation(0.0, 0.0, -3.0))
+                .with_material(Material::default().ambient(1.0))
+                .with_visibility(visibility);
+            let mirror = Plane::default()
+                .with_transform(Transform::rotation_x(PI / 2.0))
+                .with_material(Material::default().reflective(1.0));
+            let light = PointLight::new(Point::new(0.0, 5.0, -1.5), color::WHITE);
+            let w = World::new(light)
+                .object(Box::new(sphere))
+                .object(Box::new(mirror));
+            let r = Ray::new(Point::new(0.0, 0.0, -1.5), Vector::new(0.0, 0.0, 1.0));
+            let xs = w.intersect(r);
+            let comps = xs.hit().unwrap().prepare_computations(r, &xs);
+
+            w.reflected_color(comps, 5)
+        };
+
+        assert_ne!(reflection(Visibility::default()), color::BLACK);
+        assert_eq!(
+            reflection(Visibility::default().visible_in_reflections(false)),
+            color::BLACK
+        );
     }
 }